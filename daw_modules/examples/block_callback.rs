@@ -0,0 +1,75 @@
+// examples/block_callback.rs
+//
+// Drives the Engine offline (no audio device) and shows both ways to observe blocks:
+//   1. a realtime hook (runs inside render, must not allocate or block)
+//   2. the relay channel (copied out, read from another thread)
+//
+//   cargo run --example block_callback -- path/to/song.wav
+
+use daw_modules::engine::hooks::{self, BlockInfo};
+use daw_modules::engine::Engine;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+const SAMPLE_RATE: u32 = 44_100;
+const CHANNELS: usize = 2;
+const BLOCK_FRAMES: usize = 512;
+
+fn main() -> anyhow::Result<()> {
+    let mut engine = Engine::new(SAMPLE_RATE, CHANNELS);
+    if let Some(path) = std::env::args().nth(1) {
+        engine.add_track(path)?;
+    }
+    engine.play();
+
+    let mut out = vec![0.0f32; BLOCK_FRAMES * CHANNELS];
+    let live_in = vec![0.0f32; BLOCK_FRAMES * CHANNELS];
+
+    // --- 1. Realtime hook: only touches an atomic ("drive an LED") ---
+    let loudest = Arc::new(AtomicU32::new(0.0f32.to_bits()));
+    let loudest_cb = loudest.clone();
+    engine.set_block_callback(Box::new(move |info: BlockInfo<'_>| {
+        let block_max = info
+            .track_peaks
+            .iter()
+            .map(|p| p.peak_l.max(p.peak_r))
+            .fold(0.0f32, f32::max);
+        loudest_cb.store(block_max.to_bits(), Ordering::Relaxed);
+    }));
+
+    for _ in 0..100 {
+        engine.render(&mut out, &live_in);
+    }
+    println!("realtime hook: last block peak = {:.3}", f32::from_bits(loudest.load(Ordering::Relaxed)));
+
+    // --- 2. Relay: snapshots over a bounded channel ("send meters over IPC") ---
+    let (callback, rx) = hooks::relay_channel(256);
+    engine.set_block_callback(callback);
+
+    let consumer = std::thread::spawn(move || {
+        let mut blocks = 0;
+        while let Ok(snapshot) = rx.recv() {
+            blocks += 1;
+            if blocks % 20 == 0 {
+                println!(
+                    "relay: t={:.3}s frames={} playing={} tracks={:?}",
+                    snapshot.start.as_secs_f64(),
+                    snapshot.frames,
+                    snapshot.playing,
+                    snapshot.track_peaks()
+                );
+            }
+        }
+        blocks
+    });
+
+    for _ in 0..100 {
+        engine.render(&mut out, &live_in);
+    }
+
+    // Dropping the callback closes the channel and ends the consumer
+    engine.clear_block_callback();
+    let received = consumer.join().expect("consumer panicked");
+    println!("relay: received {} blocks", received);
+    Ok(())
+}
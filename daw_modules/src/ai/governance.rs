@@ -116,6 +116,7 @@ pub fn translate_action(action: AiAction) -> Result<Box<dyn Command>, Governance
                 attack_ms: safe_attack,
                 release_ms: safe_release,
                 makeup_gain_db: safe_makeup,
+                ..Default::default()
             };
 
             Ok(Box::new(UpdateCompressor {
@@ -206,7 +207,7 @@ pub fn translate_action(action: AiAction) -> Result<Box<dyn Command>, Governance
         
         // 🛠️ DEBUG LOG 6: THE REJECTION WALL (Update your catch-all)
         unmapped_action => {
-            eprintln!("❌ [GOVERNANCE REJECTED] Command not mapped to DSP: {:?}", unmapped_action);
+            rt_error!("❌ [GOVERNANCE REJECTED] Command not mapped to DSP: {:?}", unmapped_action);
             Err(GovernanceError::InvalidParameter(format!("Command translation not yet mapped: {:?}", unmapped_action)))
         }
     }
@@ -1,4 +1,5 @@
 // src/audio.rs
+#![deny(clippy::print_stdout, clippy::print_stderr)]
 
 use cpal::traits::{DeviceTrait, HostTrait};
 use cpal::{Device, SampleFormat, Stream, StreamConfig, SizedSample};
@@ -27,7 +28,7 @@ pub fn setup_output_device() -> Result<OutputConfig, anyhow::Error> {
     let output_channels = config.channels as usize;
     let output_sample_rate = config.sample_rate.0;
 
-    println!(
+    rt_info!(
         "🔊 Output device: channels: {}, sample_rate: {:?}",
         output_channels, config.sample_rate
     );
@@ -47,7 +48,7 @@ pub fn build_stream<T, C>(
     config: StreamConfig,
     is_playing: Arc<AtomicBool>,
     volume: Arc<AtomicU32>,
-    current_time_samples: Arc<AtomicU64>,
+    current_time_frames: Arc<AtomicU64>,
     mut consumer: C,
     err_fn: fn(cpal::StreamError),
 ) -> Result<Stream, anyhow::Error>
@@ -55,23 +56,22 @@ where
     T: cpal::Sample + cpal::FromSample<f32> + SizedSample,
     C: Consumer<Item = f32> + Send + 'static,
 {
+    let channels = config.channels.max(1) as usize;
     device
         .build_output_stream(
             &config,
             move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                crate::rt_log::mark_audio_thread();
                 let vol_float = f32::from_bits(volume.load(Ordering::Relaxed));
                 let playing = is_playing.load(Ordering::Relaxed);
 
                 for out in data.iter_mut() {
-                    let s = if playing {
-                        let s = consumer.try_pop().unwrap_or(0.0);
-                        current_time_samples.fetch_add(1, Ordering::Relaxed);
-                        s
-                    } else {
-                        0.0
-                    };
+                    let s = if playing { consumer.try_pop().unwrap_or(0.0) } else { 0.0 };
                     *out = T::from_sample(s * vol_float);
                 }
+                if playing {
+                    current_time_frames.fetch_add((data.len() / channels) as u64, Ordering::Relaxed);
+                }
             },
             err_fn,
             None,
@@ -1,8 +1,11 @@
 // src/audio_runtime.rs
 
+// Reachable from the CPAL callback: log through rt_log, never std streams.
+#![deny(clippy::print_stdout, clippy::print_stderr)]
+
 use std::sync::{Arc, Mutex};
-use std::sync::mpsc::{self, SyncSender};
-use std::sync::atomic::{Ordering};
+use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::time::Duration;
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
@@ -10,19 +13,31 @@ use cpal::Stream;
 
 use crate::audio::setup_output_device;
 use crate::engine::Engine;
+use crate::engine::handle::{EngineHandle, REPLY_TIMEOUT};
 use crate::session::{Session, commands::*}; 
-use crate::engine::time::GridLine;
+use crate::engine::time::{Frames, GridLine, Seconds, SnapResolution};
 use crate::ai::ai_schema::{AiAction, EqFilterType as SchemaEqFilterType};
 use crate::effects::equalizer::EqParams; // <--- Import this
 use crate::effects::compressor::CompressorParams;
 use crate::effects::reverb::ReverbParams;
 use crate::analyzer::AnalysisProfile;
+use crate::session::serialization::{AudioPrefs, ProjectManifest, PROCESSING_BLOCK_SIZES};
+use crate::session::history::HistoryEntry;
+use crate::engine::output_routing::{BusRoute, OutputBus, RoutingError};
+use crate::session::bounce::{BounceOptions, BounceResult, RealtimeBounce};
+use crate::session::multitrack::{MultitrackCapture, MultitrackResult, TrackFeed};
+use crate::session::stats::{ProjectStats, StatsInput};
+use crate::engine::mute_regions::{self, MuteRegion};
+use crate::session::recovery::{RecoveryReport, RecoverySnapshot, SnapshotSource};
 
 
 // --- ADDED: The Lock-Free AI / UI Command Queue ---
+// Fire-and-forget edits, queued to the engine in the audio callback (see `engine::handle`).
 pub enum EngineCommand {
     Play,
+    PlayWithCountIn,
     Pause,
+    Stop,
     TogglePlay,
     Seek(Duration),
     SetMasterGain(f32),
@@ -39,10 +54,77 @@ pub enum EngineCommand {
     SetEffectParam(usize, String, String, f32),
     SetMonitor(crate::recorder::monitor::Monitor), // <--- NEW
     ClearMonitor, // <--- NEW
+    SetMonitorBlend(f32, f32), // (input level, playback level) for the cue mix only
+    SetCueOnSpeakers(bool),
+    SetSoloPolicy(crate::engine::SoloPolicy),
+    SetMonitorMuted(bool),
+    SetRecording(bool),
+}
+
+impl EngineCommand {
+    fn apply(self, eng: &mut Engine) {
+        match self {
+            EngineCommand::SetMonitor(m) => eng.input_monitor = Some(m),
+            EngineCommand::ClearMonitor => eng.input_monitor = None,
+            EngineCommand::SetMonitorBlend(input, playback) => eng.cue.set_levels(input, playback),
+            EngineCommand::SetCueOnSpeakers(on) => eng.cue_on_speakers = on,
+            EngineCommand::Play => eng.play_with_preroll(),
+            EngineCommand::PlayWithCountIn => eng.play_with_count_in(),
+            EngineCommand::Pause => eng.pause(),
+            EngineCommand::Stop => eng.stop(),
+            EngineCommand::TogglePlay => {
+                if eng.transport.playing { eng.pause(); } else { eng.play_with_preroll(); }
+            }
+            EngineCommand::Seek(pos) => eng.seek(pos),
+            EngineCommand::SetMasterGain(g) => eng.master_gain = g,
+            EngineCommand::SetBpm(bpm) => eng.set_bpm(bpm),
+            EngineCommand::SetTimeSignature(num, den) => {
+                eng.transport.tempo.signature.numerator = num;
+                eng.transport.tempo.signature.denominator = den;
+            }
+            EngineCommand::ToggleMute(idx) => {
+                if let Some(t) = eng.tracks_mut().get_mut(idx) { t.muted = !t.muted; }
+            }
+            // --- NEW HANDLERS ---
+            EngineCommand::SetTrackMute(idx, state) => {
+                if let Some(t) = eng.tracks_mut().get_mut(idx) { t.muted = state; }
+            }
+            EngineCommand::UpdateEq(track_idx, band_idx, params) => {
+                if let Some(t) = eng.tracks_mut().get_mut(track_idx) {
+                    t.track_eq.update_band(band_idx, params);
+                }
+            }
+            EngineCommand::SetTrackGain(idx, gain) => {
+                if let Some(t) = eng.tracks_mut().get_mut(idx) { t.gain = gain; }
+            }
+            EngineCommand::SetTrackPan(idx, pan) => {
+                if let Some(t) = eng.tracks_mut().get_mut(idx) { t.pan = pan; }
+            }
+            EngineCommand::UpdateCompressor(idx, params) => {
+                if let Some(t) = eng.tracks_mut().get_mut(idx) {
+                    t.track_compressor.set_params(params);
+                }
+            }
+            EngineCommand::SetEffectParam(idx, effect, param, value) => {
+                if let Some(t) = eng.tracks_mut().get_mut(idx) {
+                    match effect.as_str() {
+                        "reverb" => t.track_reverb.set_param(&param, value),
+                        // "compressor" => t.track_compressor.set_param(&param, value), // Future proofing
+                        _ => {}
+                    }
+                }
+            }
+            EngineCommand::ToggleSolo(idx) => eng.toggle_solo(idx),
+            EngineCommand::ClearSolo => eng.clear_solo(),
+            EngineCommand::SetSoloPolicy(policy) => eng.solo_policy = policy,
+            EngineCommand::SetMonitorMuted(muted) => eng.monitor_muted = muted,
+            EngineCommand::SetRecording(recording) => eng.set_recording(recording),
+        }
+    }
 }
 
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, Clone)]
 pub struct MeterSnapshot {
     pub track_id: u32,
     pub peak_l: f32,
@@ -53,19 +135,107 @@ pub struct MeterSnapshot {
     pub rms_r: f32,
 }
 
+/// Outcome of restoring a project's audio setup: what it asked for, what the
+/// hardware gave us, and a readable line for every preference that couldn't be honored.
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct AppliedAudioPrefs {
+    pub requested: AudioPrefs,
+    pub actual: AudioPrefs,
+    pub mismatches: Vec<String>,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputRoutingSnapshot {
+    pub device: Option<String>,
+    pub device_channels: usize,
+    pub routes: Vec<BusRoute>,
+}
+
+/// A running loopback recording: the master writer, plus where the take goes on the timeline.
+struct LoopbackTake {
+    bounce: RealtimeBounce,
+    start_time: f64,
+    channels: usize,
+}
+
 /// Owns Engine + CPAL stream and exposes a simple control API.
 pub struct AudioRuntime {
-    engine: Arc<Mutex<Engine>>,
-    master_gain: Arc<Mutex<f32>>,
+    engine: EngineHandle, // the engine itself lives in the stream's callback
+    master_gain: Arc<AtomicU32>, // f32 bits; the callback reads it without locking
+    panic_request: Arc<AtomicBool>, // set by `panic`, taken by the callback before it renders
     session: Mutex<Session>,
     stream: Option<Stream>, // Changed to Option to allow hot-swapping
-    command_tx: Mutex<SyncSender<EngineCommand>>, // Wrapped in Mutex to allow channel recreation
     pub target_output_device: Option<String>,
+    requested_sample_rate: Option<u32>,
+    requested_buffer_size: Option<u32>,
+    requested_block_size: Option<u32>, // internal quantum; None renders whatever the device asks for
+    active_prefs: AudioPrefs, // what the running stream actually uses
     // --- ADDED: A safe map of Track ID -> Lock-Free Atomics ---
     pub meter_registry: Arc<Mutex<std::collections::HashMap<u32, std::sync::Arc<crate::engine::metering::TrackMeters>>>>,
     pub master_meter: Arc<crate::engine::metering::TrackMeters>, // <--- CHANGED TYPE
+    pub limiter_meter: Arc<crate::effects::limiter::GainReductionMeter>,
+    pub loudness: Arc<crate::engine::loudness::LoudnessReadout>, // master LUFS, lock-free
+    pub correlation: Arc<crate::engine::correlation::CorrelationReadout>, // master phase, lock-free
+    pub clip_detect: Arc<crate::engine::clip_detect::ClipReadout>, // pre-limiter overs, lock-free
+    pub transport_clock: Arc<crate::engine::clock::TransportClock>, // lock-free playhead timing
     pub recorder: Arc<Mutex<Option<crate::recorder::Recorder>>>, // <--- NEW
-    pub decode_cache: Arc<Mutex<std::collections::HashMap<String, (Arc<Vec<f32>>, u32, usize)>>>,
+    bounce: Mutex<Option<RealtimeBounce>>,
+    multitrack: Mutex<Option<MultitrackCapture>>,
+    loopback: Mutex<Option<LoopbackTake>>,
+    stats_cache: Mutex<Option<(u64, ProjectStats)>>, // keyed by the engine's content revision
+    exporting: Mutex<std::collections::HashSet<String>>, // offline exports in flight, by path
+    checkpoint: Mutex<Option<RecoverySnapshot>>, // last good state, for `rebuild` when the engine is stuck
+    callback_beats: Arc<AtomicU64>, // bumped on every audio callback, rendered or not
+    dropouts: Arc<AtomicU64>, // callbacks that played silence because the engine was away
+    last_beat: Mutex<(u64, std::time::Instant)>, // last beat count seen by `engine_health`, and when
+}
+
+/// How long `rebuild` waits for the old engine or a stream to shut down.
+const REBUILD_TIMEOUT: Duration = REPLY_TIMEOUT;
+
+// One engine block into `out` (device channels, interleaved), with the live input mixed in.
+// The block is as long as `out`: the device callback, or the fixed processing quantum.
+fn render_block(
+    eng: &mut Engine,
+    scratch: &mut Vec<f32>,
+    live: &mut Vec<f32>,
+    out: &mut [f32],
+    device_channels: usize,
+) {
+    let frames = out.len() / device_channels.max(1);
+    if scratch.len() != frames * 2 {
+        scratch.resize(frames * 2, 0.0);
+        live.resize(frames * 2, 0.0);
+    }
+
+    live.fill(0.0);
+    if let Some(mon) = eng.input_monitor.as_mut() {
+        mon.process_into(live, 2);
+    }
+    eng.cue_active = eng.input_monitor.as_ref().is_some_and(|m| m.is_enabled());
+
+    eng.render(scratch, live);
+
+    // Map the rendered buses onto the device channels
+    let master: &[f32] = scratch;
+    let eng_ref = &*eng;
+    eng_ref.output_routing.interleave(out, |bus| match bus {
+        OutputBus::Master => Some(master),
+        other => eng_ref.bus_buffer(other),
+    });
+}
+
+/// Result of `AudioRuntime::engine_health`.
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum EngineHealth {
+    Ok,
+    /// An edit or a render panicked; the engine refuses edits until it is rebuilt.
+    Poisoned,
+    /// The stream is open but the callback hasn't rendered for longer than allowed, or it
+    /// never handed the engine back.
+    Stalled,
 }
 
 pub struct TrackSnapshot {
@@ -73,6 +243,11 @@ pub struct TrackSnapshot {
     pub pan: f32,
     pub muted: bool,
     pub solo: bool,
+    pub audible: bool, // resolved through the engine's SoloPolicy
+    pub armed: bool,
+    pub record_safe: bool,
+    pub solo_safe: bool,
+    pub listen: crate::engine::track::ListenMode,
 }
 
 pub struct FrontendClipInfo {
@@ -81,6 +256,11 @@ pub struct FrontendClipInfo {
     pub duration: f64,
     pub offset: f64,
     pub clip_number: usize,
+    pub notes: String,
+    pub mute_regions: Vec<MuteRegion>,
+    pub stretch: f64, // timeline length / source length; offset is in source seconds
+    pub varispeed: bool, // the stretch changes pitch too
+    pub crossfade: Option<f64>, // seconds of crossfade into the clip before it (None = no overlap)
 }
 
 pub struct FrontendTrackInfo {
@@ -91,10 +271,21 @@ pub struct FrontendTrackInfo {
     pub pan: f32,
     pub muted: bool,
     pub solo: bool,
+    pub armed: bool,
+    pub record_safe: bool,
+    pub solo_safe: bool,
+    pub notes: String,
+    pub listen: crate::engine::track::ListenMode,
+    pub fx_bypass: bool,
+    pub kind: Option<crate::engine::track::TrackKind>,
+    pub kind_manual: bool,
     pub clips: Vec<FrontendClipInfo>,
     pub compressor: Option<CompressorParams>,
     pub eq: Option<Vec<EqParams>>,
     pub reverb: Option<ReverbParams>,
+    pub sends: Vec<crate::engine::mixer::AuxSend>,
+    pub group: Option<u32>,
+    pub frozen: bool,
     pub volume_automation: Vec<crate::engine::automation::AutomationNode<f32>>,
 }
 
@@ -102,6 +293,19 @@ pub struct EngineSnapshot {
     pub tracks: Vec<TrackSnapshot>,
 }
 
+/// A track or clip carrying a note, for the "to-do" sidebar.
+#[derive(serde::Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    pub track_id: u32,
+    pub track_name: String,
+    /// None for a note on the track itself.
+    pub clip_index: Option<usize>,
+    pub clip_number: Option<usize>,
+    pub start_time: Option<f64>,
+    pub notes: String,
+}
+
 // --- NEW: Struct for AI Context ---
 #[derive(serde::Serialize)]
 pub struct TrackAnalysisPayload {
@@ -109,10 +313,41 @@ pub struct TrackAnalysisPayload {
     pub analysis: Option<AnalysisProfile>,
 }
 
+/// The locked session. History entries recorded while it was held reach the listener once
+/// the lock is released.
+struct SessionGuard<'a>(Option<std::sync::MutexGuard<'a, Session>>);
+
+impl std::ops::Deref for SessionGuard<'_> {
+    type Target = Session;
+    fn deref(&self) -> &Session {
+        self.0.as_ref().expect("session guard used after drop")
+    }
+}
+
+impl std::ops::DerefMut for SessionGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Session {
+        self.0.as_mut().expect("session guard used after drop")
+    }
+}
+
+impl Drop for SessionGuard<'_> {
+    fn drop(&mut self) {
+        let Some(mut session) = self.0.take() else { return };
+        let unsent = session.history.take_unsent();
+        drop(session);
+        if let Some((listener, entries)) = unsent {
+            for entry in &entries {
+                listener(entry);
+            }
+        }
+    }
+}
+
 impl AudioRuntime {
     /// Create engine + output stream. Optionally add one initial track.
     pub fn new(initial_track: Option<String>) -> anyhow::Result<Self> {
-        let master_gain = Arc::new(Mutex::new(1.0_f32));
+        crate::rt_log::init(); // Start the log drain before any realtime thread can log
+        let master_gain = Arc::new(AtomicU32::new(1.0_f32.to_bits()));
         let mut engine = Engine::new(44100, 2); 
 
         if let Some(path) = initial_track {
@@ -120,58 +355,88 @@ impl AudioRuntime {
             engine.play();
         }
 
-        let engine = Arc::new(Mutex::new(engine));
         let session = Mutex::new(Session::new());
-        let (command_tx, command_rx) = mpsc::sync_channel::<EngineCommand>(1024);
         
         let recorder = Arc::new(Mutex::new(None::<crate::recorder::Recorder>));
-        let master_meter = engine.lock().unwrap().master_meter.clone(); 
+        let master_meter = engine.master_meter.clone(); 
+        let limiter_meter = engine.limiter_meter.clone();
+        let loudness = engine.loudness.clone();
+        let correlation = engine.correlation.clone();
+        let clip_detect = engine.clip_detect.clone();
+        let transport_clock = engine.clock.clone();
         let meter_registry = Arc::new(Mutex::new(std::collections::HashMap::new()));
-        let decode_cache = Arc::new(Mutex::new(std::collections::HashMap::new())); // <--- INIT CACHE
 
         let mut runtime = Self {
-            engine,
+            engine: EngineHandle::new(engine),
             master_gain,
+            panic_request: Arc::new(AtomicBool::new(false)),
             session,
             stream: None,
-            command_tx: Mutex::new(command_tx), 
             target_output_device: None,
+            requested_sample_rate: None,
+            requested_buffer_size: None,
+            requested_block_size: None,
+            active_prefs: AudioPrefs::default(),
             meter_registry,
             master_meter,
+            limiter_meter,
+            loudness,
+            correlation,
+            clip_detect,
+            transport_clock,
             recorder,
-            decode_cache,
+            bounce: Mutex::new(None),
+            multitrack: Mutex::new(None),
+            loopback: Mutex::new(None),
+            stats_cache: Mutex::new(None),
+            exporting: Mutex::new(std::collections::HashSet::new()),
+            checkpoint: Mutex::new(None),
+            callback_beats: Arc::new(AtomicU64::new(0)),
+            dropouts: Arc::new(AtomicU64::new(0)),
+            last_beat: Mutex::new((0, std::time::Instant::now())),
         };
 
-        if let Err(e) = runtime.build_and_start_stream(command_rx) {
-            eprintln!("⚠️ Warning: Failed to hook default audio device on startup: {}", e);
+        if let Err(e) = runtime.build_and_start_stream() {
+            rt_warn!("⚠️ Warning: Failed to hook default audio device on startup: {}", e);
         }
 
         Ok(runtime)
     }
 
     pub fn set_output_device(&mut self, device_name: String) -> anyhow::Result<()> {
-        println!("🔄 Switching Audio Output to: {}", device_name);
+        rt_info!("🔄 Switching Audio Output to: {}", device_name);
         self.target_output_device = Some(device_name);
         self.reload_device() // Re-use the reload logic to safely swap the stream
     }
 
     pub fn reload_device(&mut self) -> anyhow::Result<()> {
-        println!("🔄 Reloading Audio Device...");
-        self.stream = None; 
-        let (new_tx, new_rx) = mpsc::sync_channel::<EngineCommand>(1024);
-        
-        if let Ok(mut tx_guard) = self.command_tx.lock() {
-            *tx_guard = new_tx;
-        }
-
-        self.build_and_start_stream(new_rx)?;
-        println!("✅ Audio Device Successfully Reloaded.");
+        rt_info!("🔄 Reloading Audio Device...");
+        self.close_stream();
+        self.build_and_start_stream()?;
+        rt_info!("✅ Audio Device Successfully Reloaded.");
         Ok(())
     }
 
-    fn build_and_start_stream(&mut self, command_rx: mpsc::Receiver<EngineCommand>) -> anyhow::Result<()> {
+    // Take the engine out of the callback, then drop the stream. A wedged callback can
+    // block the drop; don't hang the caller on it.
+    fn close_stream(&mut self) {
+        let Some(stream) = self.stream.take() else { return };
+        if !self.engine.detach(REBUILD_TIMEOUT) {
+            rt_warn!("⚠️ The audio callback did not hand the engine back; it needs a rebuild");
+        }
+        let (done_tx, done_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            drop(stream);
+            let _ = done_tx.send(());
+        });
+        if done_rx.recv_timeout(REBUILD_TIMEOUT).is_err() {
+            rt_warn!("⚠️ Old audio stream did not shut down in time; abandoned");
+        }
+    }
+
+    fn build_and_start_stream(&mut self) -> anyhow::Result<()> {
         // --- NEW DEVICE SELECTION LOGIC ---
-        let (device, config, sample_rate, device_channels) = if let Some(ref name) = self.target_output_device {
+        let (device, mut config, mut sample_rate, mut device_channels): (cpal::Device, cpal::StreamConfig, u32, usize) = if let Some(ref name) = self.target_output_device {
             let host = cpal::default_host();
             let dev = host.output_devices()?
                 .find(|d| d.name().unwrap_or_default() == *name)
@@ -190,120 +455,106 @@ impl AudioRuntime {
         };
         // -----------------------------------
 
-        if let Ok(mut eng) = self.engine.lock() {
-            eng.sample_rate = sample_rate;
+        // Project overrides, only where the hardware supports them
+        if let Some(rate) = self.requested_sample_rate {
+            if supports_sample_rate(&device, config.channels, rate) {
+                config.sample_rate = cpal::SampleRate(rate);
+                sample_rate = rate;
+            }
+        }
+        // Open every output the interface has so buses can be routed past the first pair
+        if let Some(max) = max_output_channels(&device, sample_rate) {
+            if max as usize > device_channels {
+                config.channels = max;
+                device_channels = max as usize;
+            }
+        }
+        config.buffer_size = match self.requested_buffer_size {
+            Some(frames) if supports_buffer_size(&device, frames) => cpal::BufferSize::Fixed(frames),
+            _ => cpal::BufferSize::Default,
+        };
+        let active_prefs = AudioPrefs {
+            sample_rate: Some(sample_rate),
+            buffer_size: match config.buffer_size {
+                cpal::BufferSize::Fixed(frames) => Some(frames),
+                cpal::BufferSize::Default => None,
+            },
+            output_device: device.name().ok(),
+            block_size: self.requested_block_size.filter(|b| PROCESSING_BLOCK_SIZES.contains(b)),
+        };
+        let quantum = active_prefs.block_size.map(|b| b as usize);
+
+        // The engine is parked (no stream runs yet), so this happens right here
+        let dropped = self.engine.with(move |eng| {
+            eng.set_sample_rate(sample_rate);
+            eng.set_output_channels(device_channels)
+        })?;
+        for route in dropped {
+            rt_warn!("⚠️ Output route {:?} doesn't fit a {}-channel device; dropped", route.bus, device_channels);
         }
 
-        println!("🔊 AudioRuntime: Device running at {} Hz with {} channels", sample_rate, device_channels);
+        rt_info!("🔊 AudioRuntime: Device running at {} Hz with {} channels", sample_rate, device_channels);
 
         // NOTE: `let device = ...` and `let config = ...` were removed from here 
         // because we extracted them directly in the if/else block above.
 
-        let engine_cb = self.engine.clone();
+        let mut slot = self.engine.open_slot();
         let gain_cb = self.master_gain.clone();
+        let panic_cb = self.panic_request.clone();
+        let beats_cb = self.callback_beats.clone();
+        let dropouts_cb = self.dropouts.clone();
+        let clock_cb = self.transport_clock.clone();
+        let latency_rate = sample_rate as u64;
 
         let mut scratch_buffer: Vec<f32> = Vec::with_capacity(1024);
         let mut live_scratch: Vec<f32> = Vec::with_capacity(1024);
-        let mut active_monitor: Option<crate::recorder::monitor::Monitor> = None; 
+        // With a fixed quantum the engine renders whole blocks into `fifo` (device channels,
+        // interleaved) and callbacks drain it; `fifo_pos` is the first frame not yet sent
+        let mut fifo: Vec<f32> = vec![0.0; quantum.unwrap_or(0) * device_channels];
+        let mut fifo_pos = fifo.len();
 
-        let err_fn = |err| eprintln!("AudioRuntime stream error: {}", err);
+        let err_fn = |err| rt_error!("AudioRuntime stream error: {}", err);
 
         let stream = device.build_output_stream(
             &config,
-            move |data: &mut [f32], _| {
-                if let Ok(mut eng) = engine_cb.lock() {
-                    while let Ok(cmd) = command_rx.try_recv() {
-                        match cmd {
-                            EngineCommand::SetMonitor(m) => active_monitor = Some(m),
-                            EngineCommand::ClearMonitor => active_monitor = None,
-                            EngineCommand::Play => eng.play(),
-                            EngineCommand::Pause => eng.pause(),
-                            EngineCommand::TogglePlay => {
-                                if eng.transport.playing { eng.pause(); } else { eng.play(); }
-                            }
-                            EngineCommand::Seek(pos) => eng.seek(pos),
-                            EngineCommand::SetMasterGain(g) => eng.master_gain = g,
-                            EngineCommand::SetBpm(bpm) => eng.set_bpm(bpm),
-                            EngineCommand::SetTimeSignature(num, den) => {
-                                eng.transport.tempo.signature.numerator = num;
-                                eng.transport.tempo.signature.denominator = den;
-                            }
-                            EngineCommand::ToggleMute(idx) => {
-                                if let Some(t) = eng.tracks_mut().get_mut(idx) { t.muted = !t.muted; }
-                            }
-                            // --- NEW HANDLERS ---
-                            EngineCommand::SetTrackMute(idx, state) => {
-                                if let Some(t) = eng.tracks_mut().get_mut(idx) { t.muted = state; }
-                            }
-                            EngineCommand::UpdateEq(track_idx, band_idx, params) => {
-                                if let Some(t) = eng.tracks_mut().get_mut(track_idx) {
-                                    t.track_eq.update_band(band_idx, params);
-                                }
-                            }
-                            EngineCommand::SetTrackGain(idx, gain) => {
-                                if let Some(t) = eng.tracks_mut().get_mut(idx) { t.gain = gain; }
-                            }
-                            EngineCommand::SetTrackPan(idx, pan) => {
-                                if let Some(t) = eng.tracks_mut().get_mut(idx) { t.pan = pan; }
-                            }
-                            EngineCommand::UpdateCompressor(idx, params) => {
-                                if let Some(t) = eng.tracks_mut().get_mut(idx) {
-                                    t.track_compressor.set_params(params);
-                                }
-                            }
-                            EngineCommand::SetEffectParam(idx, effect, param, value) => {
-                                if let Some(t) = eng.tracks_mut().get_mut(idx) {
-                                    match effect.as_str() {
-                                        "reverb" => t.track_reverb.set_param(&param, value),
-                                        // "compressor" => t.track_compressor.set_param(&param, value), // Future proofing
-                                        _ => {}
-                                    }
-                                }
-                            }
-                            EngineCommand::ToggleSolo(idx) => {
-                                let target_id = eng.tracks().get(idx).map(|t| t.id);
-                                if let Some(tid) = target_id {
-                                    for t in eng.tracks_mut() {
-                                        if t.id == tid { t.solo = !t.solo; }
-                                    }
-                                }
-                            }
-                            EngineCommand::ClearSolo => {
-                                for t in eng.tracks_mut() { t.solo = false; }
-                            }
-                        }
-                    }
+            move |data: &mut [f32], info: &cpal::OutputCallbackInfo| {
+                crate::rt_log::mark_audio_thread();
+                beats_cb.fetch_add(1, Ordering::Relaxed);
+                let stamp = info.timestamp();
+                if let Some(latency) = stamp.playback.duration_since(&stamp.callback) {
+                    // Frames already rendered into the FIFO reach the speakers first
+                    let queued = ((fifo.len() - fifo_pos) / device_channels.max(1)) as u64;
+                    clock_cb.set_output_latency(latency.as_nanos() as u64 * latency_rate / 1_000_000_000 + queued);
+                }
 
-                    if let Ok(g) = gain_cb.try_lock() {
-                        eng.master_gain = *g;
-                    }
-                    
-                    let frames = data.len() / device_channels as usize;
-                    if scratch_buffer.len() != frames * 2 {
-                        scratch_buffer.resize(frames * 2, 0.0);
-                        live_scratch.resize(frames * 2, 0.0);
-                    }
-                    
-                    live_scratch.fill(0.0);
-                    if let Some(mon) = active_monitor.as_mut() {
-                        mon.process_into(&mut live_scratch, 2);
+                let rendered = slot.process(|eng| {
+                    eng.master_gain = f32::from_bits(gain_cb.load(Ordering::Relaxed));
+                    if panic_cb.swap(false, Ordering::AcqRel) {
+                        eng.panic();
                     }
 
-                    eng.render(&mut scratch_buffer, &live_scratch);
-                
-                    let mut scratch_idx = 0;
-                    for frame in data.chunks_mut(device_channels as usize) {
-                        if frame.len() >= 2 {
-                            frame[0] = scratch_buffer[scratch_idx];    
-                            frame[1] = scratch_buffer[scratch_idx + 1]; 
-                        }
-                        for sample in frame.iter_mut().skip(2) {
-                            *sample = 0.0;
+                    match quantum {
+                        None => render_block(eng, &mut scratch_buffer, &mut live_scratch, data, device_channels),
+                        Some(_) => {
+                            let mut sent = 0;
+                            while sent < data.len() {
+                                if fifo_pos == fifo.len() {
+                                    render_block(eng, &mut scratch_buffer, &mut live_scratch, &mut fifo, device_channels);
+                                    fifo_pos = 0;
+                                }
+                                let n = (data.len() - sent).min(fifo.len() - fifo_pos);
+                                data[sent..sent + n].copy_from_slice(&fifo[fifo_pos..fifo_pos + n]);
+                                sent += n;
+                                fifo_pos += n;
+                            }
                         }
-                        scratch_idx += 2;
                     }
-                } else {
+                });
+                if !rendered {
+                    // The engine is away (detached for a long edit, or stopped by a panic)
+                    dropouts_cb.fetch_add(1, Ordering::Relaxed);
                     data.fill(0.0);
+                    fifo_pos = fifo.len(); // start the next quantum fresh rather than replay stale audio
                 }
             },
             err_fn,
@@ -311,207 +562,993 @@ impl AudioRuntime {
         )?;
 
         stream.play()?;
+        self.engine.attach();
         self.stream = Some(stream);
+        self.active_prefs = active_prefs;
         Ok(())
     }
 
+    // --- RECOVERY ---
+
+    /// Poisoned engine, or no audio callback for longer than `stall` while a stream is open.
+    /// Poll it regularly: a stall is measured from the last call that saw the callback move.
+    pub fn engine_health(&self, stall: Duration) -> EngineHealth {
+        if self.engine.is_poisoned() {
+            return EngineHealth::Poisoned;
+        }
+        if self.engine.is_lost() {
+            return EngineHealth::Stalled;
+        }
+        let beats = self.callback_beats.load(Ordering::Relaxed);
+        let Ok(mut last) = self.last_beat.lock() else { return EngineHealth::Ok };
+        if beats != last.0 || self.stream.is_none() {
+            *last = (beats, std::time::Instant::now());
+            return EngineHealth::Ok;
+        }
+        if last.1.elapsed() > stall { EngineHealth::Stalled } else { EngineHealth::Ok }
+    }
+
+    /// Remember the current state for `rebuild`. Skipped when the engine doesn't answer.
+    pub fn checkpoint(&self) {
+        let master_gain = self.master_gain();
+        let Ok(snapshot) = self.engine.with(move |eng| RecoverySnapshot::capture(eng, master_gain)) else { return };
+        if let Ok(mut checkpoint) = self.checkpoint.lock() {
+            *checkpoint = Some(snapshot);
+        }
+    }
+
+    /// Throw away the engine and the stream and start over with the same project, without
+    /// restarting the app. The state comes from the old engine when it can still be read
+    /// (even after a panic poisoned it), otherwise from the last `checkpoint`. The new
+    /// engine goes into the same handle, and the meters and transport clock are carried
+    /// over, so handles held elsewhere stay valid. Transport ends up paused at the old
+    /// position; running bounces are finalized and an active input monitor is dropped (a
+    /// take in progress keeps recording).
+    pub fn rebuild(&mut self) -> Result<RecoveryReport, String> {
+        rt_warn!("🚑 Rebuilding the audio engine");
+        let (snapshot, source) = self.recovery_snapshot();
+
+        self.close_stream();
+
+        // The writers' taps lived in the old engine
+        for e in self.stop_captures() {
+            rt_warn!("⚠️ Capture stopped by the rebuild: {}", e);
+        }
+        // A command that panicked may have poisoned more than the engine
+        self.session.clear_poison();
+        self.recorder.clear_poison();
+        self.bounce.clear_poison();
+        self.multitrack.clear_poison();
+        self.stats_cache.clear_poison();
+        self.exporting.clear_poison();
+        self.checkpoint.clear_poison();
+        self.meter_registry.clear_poison();
+        self.last_beat.clear_poison();
+        if let Ok(mut last) = self.last_beat.lock() {
+            *last = (self.callback_beats.load(Ordering::Relaxed), std::time::Instant::now());
+        }
+
+        // Fresh engine publishing into the meters and clock the UI already holds
+        let mut engine = Engine::new(44100, 2);
+        engine.master_meter = self.master_meter.clone();
+        engine.limiter_meter = self.limiter_meter.clone();
+        engine.loudness = self.loudness.clone();
+        engine.correlation = self.correlation.clone();
+        engine.clip_detect = self.clip_detect.clone();
+        engine.clock = self.transport_clock.clone();
+        engine.clock.bump_generation();
+        if let Some(old) = self.engine.replace(engine) {
+            // Its clips' decoder threads end as it drops
+            std::thread::spawn(move || drop(old));
+        }
+
+        let stream_running = match self.build_and_start_stream() {
+            Ok(()) => true,
+            Err(e) => {
+                rt_error!("❌ Audio stream could not be reopened after the rebuild: {}", e);
+                false
+            }
+        };
+
+        // Tracks are rebuilt at the rate the new stream runs at
+        let tracks = snapshot.as_ref().map(RecoverySnapshot::track_count).unwrap_or(0);
+        let position = snapshot.as_ref().map(|s| s.position).unwrap_or_default();
+        if let Some(snapshot) = snapshot {
+            let master_gain = self
+                .engine
+                .with_detached(|eng| snapshot.clone().restore(eng))
+                .map_err(|e| e.to_string())?;
+            self.master_gain.store(master_gain.to_bits(), Ordering::Relaxed);
+            if let Ok(mut checkpoint) = self.checkpoint.lock() {
+                *checkpoint = Some(snapshot);
+            }
+        }
+        if let Ok(mut cache) = self.stats_cache.lock() {
+            *cache = None;
+        }
+        self.sync_meter_registry();
+
+        rt_info!("✅ Audio engine rebuilt from {:?} state ({} tracks)", source, tracks);
+        self.log_event("Recover Engine", format!("{:?}, {} tracks", source, tracks), Vec::new());
+        Ok(RecoveryReport { source, tracks, position_secs: position.as_secs_f64(), stream_running })
+    }
+
+    // State to rebuild from: the live engine if it can be reached in time, else the checkpoint
+    fn recovery_snapshot(&self) -> (Option<RecoverySnapshot>, SnapshotSource) {
+        let master_gain = self.master_gain();
+        if self.engine.is_poisoned() {
+            // Half-applied state may itself panic on the way out
+            if let Some(snapshot) = self.engine.salvage(|eng| RecoverySnapshot::capture(eng, master_gain)) {
+                return (Some(snapshot), SnapshotSource::Poisoned);
+            }
+        } else if let Ok(snapshot) = self.engine.with_detached(|eng| RecoverySnapshot::capture(eng, master_gain)) {
+            return (Some(snapshot), SnapshotSource::Live);
+        }
+
+        let checkpoint = self.checkpoint.lock().ok().and_then(|c| c.clone());
+        match checkpoint {
+            Some(mut snapshot) => {
+                // The clock is lock-free, so it still has the latest playhead
+                let sync = self.transport_clock.snapshot();
+                snapshot.position = Duration::from_secs_f64(sync.position_frames as f64 / sync.sample_rate.max(1) as f64);
+                (Some(snapshot), SnapshotSource::Checkpoint)
+            }
+            None => (None, SnapshotSource::Empty),
+        }
+    }
+
+    // Finalize a running realtime bounce, multitrack capture or loopback take; returns their errors
+    fn stop_captures(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let bounce = self.bounce.get_mut().unwrap_or_else(std::sync::PoisonError::into_inner).take();
+        if let Some(Err(e)) = bounce.map(RealtimeBounce::stop) {
+            errors.push(e.to_string());
+        }
+        let multitrack = self.multitrack.get_mut().unwrap_or_else(std::sync::PoisonError::into_inner).take();
+        if let Some(Err(e)) = multitrack.map(MultitrackCapture::stop) {
+            errors.push(e.to_string());
+        }
+        let loopback = self.loopback.get_mut().unwrap_or_else(std::sync::PoisonError::into_inner).take();
+        if let Some(Err(e)) = loopback.map(|l| l.bounce.stop()) {
+            errors.push(e.to_string());
+        }
+        errors
+    }
+
+    // --- OUTPUT ROUTING ---
+
+    pub fn set_bus_output_channels(&self, bus: OutputBus, left: usize, right: usize) -> Result<(), RoutingError> {
+        self.engine.with(move |eng| eng.set_bus_output_channels(bus, left, right)).unwrap_or(Ok(()))
+    }
+
+    pub fn clear_bus_output(&self, bus: OutputBus) {
+        let _ = self.engine.post(move |eng| eng.clear_bus_output(bus));
+    }
+
+    /// Apply saved routes; returns the ones the current device can't take.
+    pub fn restore_output_routing(&self, routes: &[BusRoute]) -> Vec<RoutingError> {
+        let routes = routes.to_vec();
+        self.engine.with(move |eng| eng.restore_output_routing(&routes)).unwrap_or_default()
+    }
+
+    /// Current routing and the channel count it is validated against.
+    pub fn output_routing(&self) -> OutputRoutingSnapshot {
+        let device = self.active_prefs.output_device.clone();
+        self.engine
+            .with(move |eng| OutputRoutingSnapshot {
+                device,
+                device_channels: eng.output_routing.device_channels(),
+                routes: eng.output_routing.routes().to_vec(),
+            })
+            .unwrap_or(OutputRoutingSnapshot { device: None, device_channels: 0, routes: Vec::new() })
+    }
+
+    /// Audio settings of the running stream (what a save captures).
+    pub fn audio_prefs(&self) -> AudioPrefs {
+        self.active_prefs.clone()
+    }
+
+    /// Try to run with a project's audio settings, falling back to the system default
+    /// output when the combination can't be opened.
+    pub fn apply_audio_prefs(&mut self, prefs: &AudioPrefs) -> AppliedAudioPrefs {
+        let mut mismatches = Vec::new();
+
+        // Only retarget if the device is actually connected, otherwise keep the current one
+        if let Some(name) = &prefs.output_device {
+            let connected = cpal::default_host()
+                .output_devices()
+                .map(|mut devs| devs.any(|d| d.name().map(|n| n == *name).unwrap_or(false)))
+                .unwrap_or(false);
+            if connected {
+                self.target_output_device = Some(name.clone());
+            }
+        }
+        self.requested_sample_rate = prefs.sample_rate;
+        self.requested_buffer_size = prefs.buffer_size;
+        self.requested_block_size = prefs.block_size;
+
+        if let Err(e) = self.reload_device() {
+            rt_warn!("⚠️ Project audio settings rejected ({}), falling back to default output", e);
+            self.target_output_device = None;
+            self.requested_sample_rate = None;
+            self.requested_buffer_size = None;
+            self.requested_block_size = None;
+            if let Err(e) = self.reload_device() {
+                mismatches.push(format!("No audio output could be opened: {}", e));
+            }
+        }
+
+        let actual = self.audio_prefs();
+        if let Some(name) = &prefs.output_device {
+            if actual.output_device.as_ref() != Some(name) {
+                mismatches.push(format!(
+                    "Output device \"{}\" is not available, using \"{}\"",
+                    name,
+                    actual.output_device.clone().unwrap_or_default()
+                ));
+            }
+        }
+        if let Some(rate) = prefs.sample_rate {
+            if actual.sample_rate != Some(rate) {
+                mismatches.push(format!(
+                    "Sample rate {} Hz is not supported, running at {} Hz",
+                    rate,
+                    actual.sample_rate.unwrap_or(0)
+                ));
+            }
+        }
+        if let Some(frames) = prefs.buffer_size {
+            if actual.buffer_size != Some(frames) {
+                mismatches.push(format!("Buffer size of {} frames is not supported, using the device default", frames));
+            }
+        }
+        if let Some(frames) = prefs.block_size {
+            if actual.block_size != Some(frames) {
+                mismatches.push(format!(
+                    "Processing block of {} frames is not supported (use one of {:?}), following the device",
+                    frames, PROCESSING_BLOCK_SIZES
+                ));
+            }
+        }
+
+        AppliedAudioPrefs { requested: prefs.clone(), actual, mismatches }
+    }
+
+    /// Forget the rate, buffer and block overrides an earlier project applied, for a
+    /// project that saved none (loaded or new). The output device stays as chosen.
+    pub fn clear_audio_prefs(&mut self) -> anyhow::Result<()> {
+        let overridden = self.requested_sample_rate.is_some()
+            || self.requested_buffer_size.is_some()
+            || self.requested_block_size.is_some();
+        self.requested_sample_rate = None;
+        self.requested_buffer_size = None;
+        self.requested_block_size = None;
+        if overridden { self.reload_device() } else { Ok(()) }
+    }
+
     // --- UNDO / REDO ---
 
     pub fn undo(&self) {
-        if let Ok(mut session) = self.session.lock() {
+        if let Ok(mut session) = self.lock_session() {
             if let Ok(success) = session.undo(&self.engine) {
                 if success { 
-                    println!("Using Undo"); 
+                    rt_info!("Using Undo"); 
                     // 🚀 FIX: Force renumbering on all tracks after undoing structural changes
-                    if let Ok(mut eng) = self.engine.lock() {
+                    let _ = self.engine.with(|eng| {
                         for track in eng.tracks_mut().iter_mut() {
                             track.renumber_clips();
                         }
-                    }
+                    });
+                    // A track delete may have been undone or redone
+                    self.sync_meter_registry();
                     // Re-sync decoders
                     let pos = self.position();
                     self.seek(pos);
                 }
-                else { println!("Nothing to Undo"); }
+                else { rt_info!("Nothing to Undo"); }
             }
         }
     }
 
     pub fn redo(&self) {
-        if let Ok(mut session) = self.session.lock() {
+        if let Ok(mut session) = self.lock_session() {
             if let Ok(success) = session.redo(&self.engine) {
                 if success { 
-                    println!("Using Redo"); 
+                    rt_info!("Using Redo"); 
                     // 🚀 FIX: Force renumbering on all tracks after redoing structural changes
-                    if let Ok(mut eng) = self.engine.lock() {
+                    let _ = self.engine.with(|eng| {
                         for track in eng.tracks_mut().iter_mut() {
                             track.renumber_clips();
                         }
-                    }
+                    });
+                    // A track delete may have been undone or redone
+                    self.sync_meter_registry();
                     // Re-sync decoders
                     let pos = self.position();
                     self.seek(pos);
                 }
-                else { println!("Nothing to Redo"); }
+                else { rt_info!("Nothing to Redo"); }
             }
         }
     }
 
     // --- TRANSPORT ---
 
+    // Queue a fire-and-forget edit; like a full queue, an unreachable engine drops it
+    fn send(&self, cmd: EngineCommand) {
+        let _ = self.engine.post(move |eng| cmd.apply(eng));
+    }
+
     pub fn play(&self) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::Play);
+        self.send(EngineCommand::Play);
+    }
+
+    /// Play after the metronome's count-in (plain play when none is configured).
+    pub fn play_with_count_in(&self) {
+        self.send(EngineCommand::PlayWithCountIn);
     }
 
     pub fn pause(&self) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::Pause);
+        self.send(EngineCommand::Pause);
     }
 
-    pub fn toggle_play(&self) {
-       let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::TogglePlay);
+    /// Pause and send the playhead back (see `Engine::stop`); `pause` leaves it where it is.
+    pub fn stop(&self) {
+        self.send(EngineCommand::Stop);
     }
 
-    pub fn is_playing(&self) -> bool {
-        if let Ok(eng) = self.engine.lock() {
-            eng.transport.playing
-        } else {
-            false
-        }
+    /// Start playback this far before the playhead (see `Engine::play_with_preroll`).
+    pub fn set_preroll(&self, preroll: crate::engine::PreRoll) {
+        let _ = self.engine.post(move |eng| eng.preroll = preroll);
     }
 
-    pub fn seek(&self, pos: Duration) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::Seek(pos));
+    pub fn preroll(&self) -> crate::engine::PreRoll {
+        self.engine.with(move |eng| eng.preroll).unwrap_or_default()
     }
 
-    pub fn position(&self) -> Duration {
-        if let Ok(eng) = self.engine.lock() {
-            eng.transport.position
-        } else {
-            Duration::ZERO
-        }
+    pub fn set_return_to_start_on_stop(&self, to_start: bool) {
+        let _ = self.engine.post(move |eng| eng.return_to_start_on_stop = to_start);
     }
 
-    pub fn sample_rate(&self) -> u32 {
-        if let Ok(eng) = self.engine.lock() {
-            eng.sample_rate
-        } else {
-            44100
-        }
+    pub fn return_to_start_on_stop(&self) -> bool {
+        self.engine.with(move |eng| eng.return_to_start_on_stop).unwrap_or(false)
     }
 
-    pub fn add_track(&self, path: String) -> anyhow::Result<()> {
-        if let Ok(mut eng) = self.engine.lock() {
-            let _ = eng.add_track(path)?;
-        }
-        Ok(())
+    pub fn set_end_action(&self, action: crate::engine::EndAction) {
+        let _ = self.engine.post(move |eng| eng.end_action = action);
     }
 
-    pub fn delete_track(&self, index: usize) -> anyhow::Result<()> {
-        if let Ok(mut eng) = self.engine.lock() {
-            eng.remove_track(index)?;
-        }
-        Ok(())
+    pub fn end_action(&self) -> crate::engine::EndAction {
+        self.engine.with(move |eng| eng.end_action).unwrap_or_default()
     }
 
-    // --- ADD THIS NEW METHOD ---
-    pub fn create_empty_track(&self) -> anyhow::Result<()> {
-        if let Ok(mut eng) = self.engine.lock() {
-            let id = eng.add_empty_track(); 
-            
-            // FIX: If the engine is currently playing/recording, 
-            // force the new track to wake up and play.
-            if eng.transport.playing {
-                if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id == id) {
-                    track.set_state(crate::engine::track::TrackState::Playing);
-                }
+    /// Play one calibration click (see `recorder::calibration::measure_round_trip`) and
+    /// return when it was rendered. Fails when the output stream isn't running.
+    pub fn send_latency_ping(&self) -> anyhow::Result<std::time::Instant> {
+        self.engine.with(|eng| eng.request_latency_ping())?;
+        let deadline = std::time::Instant::now() + Duration::from_millis(500);
+        loop {
+            std::thread::sleep(Duration::from_millis(1));
+            if let Some(sent) = self.engine.with(|eng| eng.take_latency_ping())? {
+                return Ok(sent);
+            }
+            if std::time::Instant::now() > deadline {
+                anyhow::bail!("The output stream isn't running");
             }
         }
-        Ok(())
     }
 
-    pub fn add_clip(&self, track_index: usize, path: String, start_time: f64) -> anyhow::Result<()> {
-        println!("➡️ Backend: Attempting to add clip to Track Index {}", track_index); // <--- DEBUG LOG
-        
-        if let Ok(mut eng) = self.engine.lock() {
-            match eng.add_clip(track_index, path.clone(), start_time) {
-                Ok(_) => println!("✅ Backend: Successfully added clip: {}", path),
-                Err(e) => {
-                    println!("❌ Backend: Failed to add clip! Error: {}", e);
-                    return Err(e); // Pass error up
-                }
-            }
+    /// Tell the engine a take is being recorded (drives the recording-only click).
+    pub fn set_recording(&self, recording: bool) {
+        self.send(EngineCommand::SetRecording(recording));
+    }
+
+    /// Emergency stop: ramps all outputs to silence (~50 ms) in the audio callback, then
+    /// pauses, flushes the decoders and mutes the monitor until `set_monitor_muted(false)`.
+    /// A flag rather than a command, so a full queue or a detached engine can't lose it:
+    /// the callback picks it up on the next block it renders.
+    pub fn panic(&self) {
+        self.panic_request.store(true, Ordering::Release);
+        if self.stream.is_none() && self.panic_request.swap(false, Ordering::AcqRel) {
+            // No callback to pick it up
+            let _ = self.engine.with(|eng| eng.panic());
         }
-        Ok(())
     }
 
-    pub fn move_clip(&self, track_index: usize, clip_index: usize, new_start: f64) -> anyhow::Result<()> {
-        let (track_id, old_start) = {
-             let eng = self.engine.lock().unwrap();
-             let track = eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?;
-             let clip = track.clips.get(clip_index).ok_or(anyhow::anyhow!("Clip not found"))?;
-             (track.id, clip.start_time)
-        };
+    pub fn set_monitor_muted(&self, muted: bool) {
+        self.send(EngineCommand::SetMonitorMuted(muted));
+    }
 
-        let cmd = Box::new(MoveClip {
-            track_id,
-            clip_index,
-            old_start,
-            new_start: Duration::from_secs_f64(new_start),
-        });
+    pub fn is_monitor_muted(&self) -> bool {
+        self.engine.state().monitor_muted
+    }
 
-        if let Ok(mut session) = self.session.lock() {
-            session.apply(&self.engine, cmd)?;
-        }
+    pub fn toggle_play(&self) {
+       self.send(EngineCommand::TogglePlay);
+    }
 
-        if let Ok(mut eng) = self.engine.lock() {
-            if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id == track_id) {
-                track.renumber_clips();
-            }
-        }
-        
-        // 🚀 FIX: Force the decoders to flush buffers and re-align with the new position
-        let pos = self.position();
-        self.seek(pos);
-        
-        Ok(())
+    pub fn is_playing(&self) -> bool {
+        self.engine.state().playing
     }
 
-    pub fn split_clip(&self, track_index: usize, time: f64) -> anyhow::Result<()> {
-        let track_id = {
-             let eng = self.engine.lock().unwrap();
-             eng.tracks().get(track_index).map(|t| t.id)
-        }.ok_or(anyhow::anyhow!("Track not found"))?;
-    
-        let cmd = Box::new(SplitClip {
-            track_id,
-            split_time: std::time::Duration::from_secs_f64(time),
-        });
-    
-        if let Ok(mut session) = self.session.lock() {
-            session.apply(&self.engine, cmd)?;
-        }
+    pub fn seek(&self, pos: Duration) {
+        self.send(EngineCommand::Seek(pos));
+    }
 
-        // 🚀 FIX: Force renumbering and trigger our new debug logs
-        if let Ok(mut eng) = self.engine.lock() {
-            // Match the exact inner u32 ID to guarantee we find the track
-            if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id.0 == track_id.0) {
-                println!("🔍 DEBUG: Split successful. Calling renumber_clips on Track {}...", track.id.0);
-                track.renumber_clips();
-            }
-        }
+    // --- RECORD ARM ---
 
-        // Re-sync decoders
-        let pos = self.position();
-        self.seek(pos);
+    pub fn set_track_armed(&self, track_index: usize, armed: bool) -> Result<Vec<u32>, crate::engine::ArmError> {
+        self.engine
+            .with(move |eng| eng.set_track_armed(track_index, armed).map(|ids| ids.into_iter().map(|id| id.0).collect()))
+            .map_err(|e| crate::engine::ArmError::EngineUnavailable { reason: e.to_string() })?
+    }
 
-        Ok(())
+    pub fn set_record_safe(&self, track_index: usize, safe: bool) -> Result<(), crate::engine::ArmError> {
+        self.engine
+            .with(move |eng| eng.set_record_safe(track_index, safe))
+            .map_err(|e| crate::engine::ArmError::EngineUnavailable { reason: e.to_string() })?
     }
 
-    // --- GLOBAL SETTINGS ---
+    pub fn set_arm_exclusive(&self, exclusive: bool) {
+        let _ = self.engine.post(move |eng| eng.arm_exclusive = exclusive);
+    }
 
-    // --- GLOBAL SETTINGS ---
+    pub fn arm_exclusive(&self) -> bool {
+        self.engine.with(move |eng| eng.arm_exclusive).unwrap_or(false)
+    }
 
-    // --- GLOBAL SETTINGS ---
+    /// Hardware inputs (0-based) a take on this track captures; empty records all of them.
+    pub fn set_track_record_inputs(&self, track_index: usize, inputs: Vec<usize>) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            let track = eng.tracks_mut().get_mut(track_index).ok_or("Track not found")?;
+            track.record_inputs = inputs;
+            Ok(())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn track_record_inputs(&self, track_index: usize) -> Result<Vec<usize>, String> {
+        self.engine.with(move |eng| -> Result<Vec<usize>, String> {
+            let track = eng.tracks().get(track_index).ok_or("Track not found")?;
+            Ok(track.record_inputs.clone())
+        }).map_err(|e| e.to_string())?
+    }
+
+    /// Input device a take on this track records from; `None` is the default input.
+    pub fn set_track_record_device(&self, track_index: usize, device: Option<String>) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            let track = eng.tracks_mut().get_mut(track_index).ok_or("Track not found")?;
+            track.record_device = device;
+            Ok(())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn track_record_device(&self, track_index: usize) -> Result<Option<String>, String> {
+        self.engine.with(move |eng| -> Result<Option<String>, String> {
+            let track = eng.tracks().get(track_index).ok_or("Track not found")?;
+            Ok(track.record_device.clone())
+        }).map_err(|e| e.to_string())?
+    }
+
+    /// (id, device, inputs) of every armed track, in track order. Record-safe tracks can't be armed.
+    pub fn armed_record_inputs(&self) -> Vec<(u32, Option<String>, Vec<usize>)> {
+        self.engine
+            .with(|eng| {
+                eng.tracks()
+                    .iter()
+                    .filter(|t| t.armed)
+                    .map(|t| (t.id.0, t.record_device.clone(), t.record_inputs.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Drops targets aimed at record-safe tracks, whatever the frontend sent. Targets without
+    /// a track (new-track takes) are kept; an error when every requested track is record-safe.
+    pub fn recordable_targets(&self, targets: Vec<crate::recorder::RecordTarget>) -> Result<Vec<crate::recorder::RecordTarget>, String> {
+        let safe: Vec<u32> = self
+            .engine
+            .with(|eng| eng.tracks().iter().filter(|t| t.record_safe).map(|t| t.id.0).collect())
+            .map_err(|e| e.to_string())?;
+        let (kept, skipped): (Vec<_>, Vec<_>) =
+            targets.into_iter().partition(|t| t.track_id.is_none_or(|id| !safe.contains(&id)));
+        for t in &skipped {
+            rt_warn!("Skipping record-safe track {:?}", t.track_id);
+        }
+        if kept.is_empty() && !skipped.is_empty() {
+            return Err("All requested tracks are record-safe".to_string());
+        }
+        Ok(kept)
+    }
+
+    // --- MARKERS (undoable) ---
+
+    pub fn markers(&self) -> Vec<crate::engine::markers::MarkerInfo> {
+        self.engine.with(move |eng| eng.markers.iter().map(Into::into).collect()).unwrap_or_default()
+    }
+
+    /// Returns the new marker's id. An empty name becomes "Marker N".
+    pub fn add_marker(&self, time_secs: f64, name: String, color: Option<String>) -> anyhow::Result<u32> {
+        let time = marker_time(time_secs)?;
+        let marker = self.engine.with(move |eng| {
+            let name = if name.trim().is_empty() { format!("Marker {}", eng.markers.len() + 1) } else { name };
+            let color = color.unwrap_or_else(|| crate::engine::markers::DEFAULT_MARKER_COLOR.to_string());
+            crate::engine::markers::Marker { id: eng.markers.reserve_id(), time, name, color }
+        })?;
+        let id = marker.id;
+        self.apply_marker_command(Box::new(AddMarker { marker }))?;
+        Ok(id)
+    }
+
+    pub fn move_marker(&self, id: u32, time_secs: f64) -> anyhow::Result<()> {
+        let time = marker_time(time_secs)?;
+        self.edit_marker(id, "Move Marker", |m| m.time = time)
+    }
+
+    pub fn rename_marker(&self, id: u32, name: String) -> anyhow::Result<()> {
+        if name.trim().is_empty() {
+            return Err(anyhow::anyhow!("Marker name can't be empty"));
+        }
+        self.edit_marker(id, "Rename Marker", |m| m.name = name)
+    }
+
+    pub fn set_marker_color(&self, id: u32, color: String) -> anyhow::Result<()> {
+        self.edit_marker(id, "Recolor Marker", |m| m.color = color)
+    }
+
+    pub fn delete_marker(&self, id: u32) -> anyhow::Result<()> {
+        let marker = self.marker(id)?;
+        self.apply_marker_command(Box::new(DeleteMarker { marker }))
+    }
+
+    /// Jump the playhead to a marker; returns where it went.
+    pub fn seek_to_marker(&self, id: u32) -> Option<Duration> {
+        self.seek_to_target(move |eng| eng.markers.get(id).map(|m| m.time))
+    }
+
+    fn marker(&self, id: u32) -> anyhow::Result<crate::engine::markers::Marker> {
+        self.engine.with(move |eng| -> anyhow::Result<crate::engine::markers::Marker> {
+            eng.markers.get(id).cloned().ok_or(anyhow::anyhow!("Marker not found"))
+        })?
+    }
+
+    fn edit_marker(
+        &self,
+        id: u32,
+        label: &'static str,
+        change: impl FnOnce(&mut crate::engine::markers::Marker),
+    ) -> anyhow::Result<()> {
+        let old = self.marker(id)?;
+        let mut new = old.clone();
+        change(&mut new);
+        if new == old {
+            return Ok(());
+        }
+        self.apply_marker_command(Box::new(EditMarker { old, new, label }))
+    }
+
+    fn apply_marker_command(&self, cmd: Box<dyn Command>) -> anyhow::Result<()> {
+        let mut session = self.lock_session().map_err(|_| anyhow::anyhow!("Lock error"))?;
+        session.apply(&self.engine, cmd)
+    }
+
+    // --- NAVIGATION: returns where the playhead went so the UI can flash it ---
+
+    pub fn seek_to_next_marker(&self) -> Option<Duration> {
+        self.seek_to_target(|eng| eng.next_marker())
+    }
+
+    pub fn seek_to_previous_marker(&self) -> Option<Duration> {
+        self.seek_to_target(|eng| eng.previous_marker())
+    }
+
+    pub fn seek_to_next_edit_point(&self) -> Option<Duration> {
+        self.seek_to_target(|eng| eng.next_edit_point())
+    }
+
+    pub fn seek_to_previous_edit_point(&self) -> Option<Duration> {
+        self.seek_to_target(|eng| eng.previous_edit_point())
+    }
+
+    fn seek_to_target(&self, find: impl FnOnce(&mut Engine) -> Option<Duration> + Send + 'static) -> Option<Duration> {
+        let target = self.engine.with(find).ok()??;
+        self.seek(target);
+        Some(target)
+    }
+
+    /// Latest (frames rendered, host time) pair for UI playhead extrapolation. Lock-free.
+    pub fn time_sync(&self) -> crate::engine::clock::TimeSync {
+        self.transport_clock.snapshot()
+    }
+
+    pub fn position(&self) -> Duration {
+        self.engine.state().position
+    }
+
+    /// Largest insert latency across tracks (frames); every other track is delayed to match.
+    pub fn project_latency_frames(&self) -> usize {
+        self.engine.state().latency_frames
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        match self.engine.state().sample_rate {
+            0 => 44100,
+            sr => sr,
+        }
+    }
+
+    /// `path` may be an https URL: the file streams in and is cached (see `decoder::remote`).
+    pub fn add_track(&self, path: String) -> anyhow::Result<()> {
+        Self::prefetch_remote(&path)?;
+        let clip = self.open_clip(path.clone(), 0.0)?;
+        let (id, analysis) = self.engine.with(move |eng| -> anyhow::Result<_> {
+            let id = eng.add_empty_track();
+            let index = eng.tracks().len() - 1;
+            let analysis = eng.add_prepared_clip(index, clip)?;
+            Ok((id, analysis))
+        })??;
+        crate::engine::track::Track::analyze_in_background(analysis, path.clone());
+        self.sync_meter_registry();
+        self.log_event("Import Audio", path, vec![id.0]);
+        Ok(())
+    }
+
+    /// Opens `path` as a clip at `start_time` seconds for the current stream format. The file
+    /// is probed here so the engine only has to insert it.
+    fn open_clip(&self, path: String, start_time: f64) -> anyhow::Result<crate::engine::track::Clip> {
+        let state = self.engine.state();
+        crate::engine::track::Clip::new(path, Duration::from_secs_f64(start_time), self.sample_rate(), state.channels.max(1))
+    }
+
+    /// Inserts a clip from `open_clip` and starts its track's analysis.
+    fn insert_clip(&self, track_index: usize, clip: crate::engine::track::Clip) -> anyhow::Result<()> {
+        let path = clip.path.clone();
+        let analysis = self.engine.with(move |eng| eng.add_prepared_clip(track_index, clip))??;
+        crate::engine::track::Track::analyze_in_background(analysis, path);
+        Ok(())
+    }
+
+    /// Start a remote clip's download and probe it before the engine is locked: the header
+    /// (or, for formats that don't state their length, the whole file) arrives here, and
+    /// the clip's own probe is then served from the decode cache.
+    fn prefetch_remote(path: &str) -> anyhow::Result<()> {
+        if crate::decoder::remote::is_remote(path) {
+            crate::bpm::cache::shared().probe(path)?;
+        }
+        Ok(())
+    }
+
+    /// Undoable. Refused for an armed track while recording.
+    pub fn delete_track(&self, index: usize) -> anyhow::Result<()> {
+        let recording = self.recorder.lock().map(|r| r.is_some()).unwrap_or(false);
+        let cmd = self.engine.with(move |eng| -> anyhow::Result<_> {
+            let track = eng.tracks().get(index).ok_or(anyhow::anyhow!("Track not found"))?;
+            if recording && track.armed {
+                return Err(anyhow::anyhow!("Stop recording before deleting an armed track"));
+            }
+            Ok(Box::new(DeleteTrack { track_id: track.id, index, state: crate::session::capture_track(track) }))
+        })??;
+        let track_id = cmd.track_id;
+
+        if let Ok(mut session) = self.lock_session() {
+            session.apply(&self.engine, cmd)?;
+        }
+        if let Ok(mut reg) = self.meter_registry.lock() {
+            reg.remove(&track_id.0);
+        }
+        Ok(())
+    }
+
+    /// Undoable. `order` lists every track id once, top to bottom.
+    pub fn reorder_tracks(&self, order: &[u32]) -> anyhow::Result<()> {
+        let old_order = self.engine.with(|eng| eng.track_order())?;
+        let new_order: Vec<crate::engine::TrackId> = order.iter().map(|&id| crate::engine::TrackId(id)).collect();
+        if new_order == old_order {
+            return Ok(());
+        }
+        if let Ok(mut session) = self.lock_session() {
+            session.apply(&self.engine, Box::new(ReorderTracks { old_order, new_order }))?;
+        }
+        Ok(())
+    }
+
+    // --- ADD THIS NEW METHOD ---
+    pub fn create_empty_track(&self) -> anyhow::Result<()> {
+        self.engine.with(|eng| {
+            let id = eng.add_empty_track(); 
+            
+            // FIX: If the engine is currently playing/recording, 
+            // force the new track to wake up and play.
+            if eng.transport.playing {
+                if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id == id) {
+                    track.set_state(crate::engine::track::TrackState::Playing);
+                }
+            }
+        })?;
+        self.sync_meter_registry();
+        Ok(())
+    }
+
+    /// New track holding a finished recording at `start_time`; the clip lands as an undoable
+    /// "Record Take". Returns the track id.
+    pub fn add_record_take_track(&self, path: String, start_time: f64, offset: f64) -> anyhow::Result<u32> {
+        let (id, index) = self.engine.with(|eng| -> anyhow::Result<_> {
+            let id = eng.add_empty_track();
+            let index = eng.tracks().iter().position(|t| t.id == id).ok_or_else(|| anyhow::anyhow!("Track not found"))?;
+            let playing = eng.transport.playing;
+            if let Some(track) = eng.tracks_mut().get_mut(index) {
+                track.name = format!("Track-{}", id.0);
+                if playing {
+                    track.set_state(crate::engine::track::TrackState::Playing);
+                }
+            }
+            Ok((id, index))
+        })??;
+        self.sync_meter_registry();
+        if let Err(e) = self.commit_record_take(index, path, start_time, offset) {
+            // Don't leave an empty track behind for a take that couldn't be placed
+            if self.engine.with(move |eng| eng.remove_track(id)).is_ok() {
+                self.sync_meter_registry();
+            }
+            return Err(e);
+        }
+        Ok(id.0)
+    }
+
+    /// New named track holding `clips` as (path, start in seconds). Returns its id.
+    pub fn add_track_with_clips(&self, name: String, clips: &[(String, f64)]) -> anyhow::Result<u32> {
+        let opened = clips
+            .iter()
+            .map(|(path, start)| self.open_clip(path.clone(), *start))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let paths: Vec<String> = clips.iter().map(|(path, _)| path.clone()).collect();
+        let track_name = name.clone();
+        let (id, analysis) = self.engine.with(move |eng| -> anyhow::Result<_> {
+            let id = eng.add_empty_track();
+            let index = eng.tracks().iter().position(|t| t.id == id).ok_or_else(|| anyhow::anyhow!("Track not found"))?;
+            let mut analysis = None;
+            for clip in opened {
+                analysis = Some(eng.add_prepared_clip(index, clip)?);
+            }
+            let playing = eng.transport.playing;
+            if let Some(track) = eng.tracks_mut().get_mut(index) {
+                track.name = track_name;
+                if playing {
+                    track.set_state(crate::engine::track::TrackState::Playing);
+                }
+            }
+            Ok((id, analysis))
+        })??;
+        if let (Some(analysis), Some(path)) = (analysis, paths.into_iter().last()) {
+            crate::engine::track::Track::analyze_in_background(analysis, path);
+        }
+        self.sync_meter_registry();
+        self.log_event("Import Audio", format!("{} ({} clips)", name, clips.len()), vec![id.0]);
+        Ok(id.0)
+    }
+
+    pub fn add_clip(&self, track_index: usize, path: String, start_time: f64) -> anyhow::Result<()> {
+        rt_debug!("➡️ Backend: Attempting to add clip to Track Index {}", track_index); // <--- DEBUG LOG
+        Self::prefetch_remote(&path)?;
+        
+        let added = self
+            .open_clip(path.clone(), start_time)
+            .and_then(|clip| self.insert_clip(track_index, clip));
+        match added {
+            Ok(_) => {
+                rt_info!("✅ Backend: Successfully added clip: {}", path);
+                let ids = self
+                    .engine
+                    .with(move |eng| eng.tracks().get(track_index).map(|t| vec![t.id.0]).unwrap_or_default())
+                    .unwrap_or_default();
+                self.log_event("Import Audio", format!("{} @ {:.3}s", path, start_time), ids);
+                Ok(())
+            }
+            Err(e) => {
+                rt_warn!("❌ Backend: Failed to add clip! Error: {}", e);
+                Err(e) // Pass error up
+            }
+        }
+    }
+
+    /// Undoable. `new_start` is snapped to the grid (see `set_snap_resolution`); returns where
+    /// the clip landed.
+    pub fn move_clip(&self, track_index: usize, clip_index: usize, new_start: f64) -> anyhow::Result<f64> {
+        let (track_id, old_start, new_start) = self.engine.with(move |eng| -> anyhow::Result<_> {
+             eng.ensure_not_frozen(track_index)?;
+             let track = eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?;
+             let clip = track.clips.get(clip_index).ok_or(anyhow::anyhow!("Clip not found"))?;
+             Ok((track.id, clip.start(), eng.snap_time(new_start)))
+        })??;
+
+        let cmd = Box::new(MoveClip {
+            track_id,
+            clip_index,
+            old_start,
+            new_start: Duration::from_secs_f64(new_start),
+        });
+
+        if let Ok(mut session) = self.lock_session() {
+            session.apply(&self.engine, cmd)?;
+        }
+
+        let _ = self.engine.post(move |eng| {
+            if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id == track_id) {
+                track.renumber_clips();
+            }
+        });
+        
+        // 🚀 FIX: Force the decoders to flush buffers and re-align with the new position
+        let pos = self.position();
+        self.seek(pos);
+        
+        Ok(new_start)
+    }
+
+    /// Move a clip's left edge (undoable). Errors with `ClipEditError` when it would get too short.
+    pub fn trim_clip_start(&self, track_index: usize, clip_index: usize, new_start: f64) -> anyhow::Result<()> {
+        self.edit_clip_bounds(track_index, clip_index, "Trim Clip Start", move |clip| clip.with_start(Duration::from_secs_f64(new_start.max(0.0))))
+    }
+
+    /// Move a clip's right edge (undoable).
+    pub fn trim_clip_end(&self, track_index: usize, clip_index: usize, new_end: f64) -> anyhow::Result<()> {
+        self.edit_clip_bounds(track_index, clip_index, "Trim Clip End", move |clip| clip.with_end(Duration::from_secs_f64(new_end.max(0.0))))
+    }
+
+    /// Time stretch a clip (undoable); it keeps its start and grows or shrinks from there.
+    pub fn set_clip_stretch(&self, track_index: usize, clip_index: usize, ratio: f64) -> anyhow::Result<()> {
+        self.edit_clip_bounds(track_index, clip_index, "Time Stretch", move |clip| clip.with_stretch(ratio))
+    }
+
+    /// Play a clip's stretch as varispeed, pitch following speed (undoable). With a stretch
+    /// of 1.0 nothing is heard until the clip is stretched.
+    pub fn set_clip_varispeed(&self, track_index: usize, clip_index: usize, on: bool) -> anyhow::Result<()> {
+        self.edit_clip_bounds(track_index, clip_index, "Varispeed", move |clip| {
+            Ok(crate::engine::track::ClipBounds { varispeed: on, ..clip.bounds() })
+        })
+    }
+
+    /// Stretch a clip recorded at `clip_bpm` so its beats land on the project grid. Returns the ratio.
+    pub fn conform_clip_to_tempo(&self, track_index: usize, clip_index: usize, clip_bpm: f32) -> anyhow::Result<f64> {
+        let project_bpm = self.bpm();
+        if !clip_bpm.is_finite() || clip_bpm <= 0.0 || project_bpm <= 0.0 {
+            return Err(anyhow::anyhow!("Invalid tempo: clip {} BPM, project {} BPM", clip_bpm, project_bpm));
+        }
+        let ratio = clip_bpm as f64 / project_bpm as f64;
+        self.set_clip_stretch(track_index, clip_index, ratio)?;
+        Ok(ratio)
+    }
+
+    fn edit_clip_bounds(
+        &self,
+        track_index: usize,
+        clip_index: usize,
+        label: &'static str,
+        trim: impl FnOnce(&crate::engine::track::Clip) -> Result<crate::engine::track::ClipBounds, crate::engine::track::ClipEditError>
+            + Send
+            + 'static,
+    ) -> anyhow::Result<()> {
+        let cmd = self.engine.with(move |eng| -> anyhow::Result<_> {
+            eng.ensure_not_frozen(track_index)?;
+            let track = eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?;
+            let clip = track.clips.get(clip_index).ok_or(anyhow::anyhow!("Clip not found"))?;
+            let new = trim(clip)?;
+            if new == clip.bounds() {
+                return Ok(None);
+            }
+            Ok(Some(Box::new(TrimClip {
+                track_id: track.id,
+                path: clip.path.clone(),
+                old: clip.bounds(),
+                new,
+                rate: clip.frame_rate(),
+                label,
+            })))
+        })??;
+        let Some(cmd) = cmd else { return Ok(()) };
+
+        if let Ok(mut session) = self.lock_session() {
+            session.apply(&self.engine, cmd)?;
+        }
+        Ok(())
+    }
+
+    pub fn split_clip(&self, track_index: usize, time: f64) -> anyhow::Result<()> {
+        let track_id = self.engine.with(move |eng| -> anyhow::Result<_> {
+             eng.ensure_not_frozen(track_index)?;
+             Ok(eng.tracks().get(track_index).map(|t| t.id))
+        })??.ok_or(anyhow::anyhow!("Track not found"))?;
+    
+        let cmd = Box::new(SplitClip {
+            track_id,
+            split_time: std::time::Duration::from_secs_f64(time),
+        });
+    
+        if let Ok(mut session) = self.lock_session() {
+            session.apply(&self.engine, cmd)?;
+        }
+
+        // 🚀 FIX: Force renumbering and trigger our new debug logs
+        let _ = self.engine.post(move |eng| {
+            // Match the exact inner u32 ID to guarantee we find the track
+            if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id.0 == track_id.0) {
+                rt_debug!("🔍 DEBUG: Split successful. Calling renumber_clips on Track {}...", track.id.0);
+                track.renumber_clips();
+            }
+        });
+
+        // Re-sync decoders
+        let pos = self.position();
+        self.seek(pos);
+
+        Ok(())
+    }
+
+    // --- GLOBAL SETTINGS ---
+
+    // --- GLOBAL SETTINGS ---
+
+    // --- GLOBAL SETTINGS ---
+
+    /// Master loudness (EBU R128): momentary, short-term, integrated and true peak.
+    pub fn loudness(&self) -> crate::engine::loudness::LoudnessReading {
+        self.loudness.reading()
+    }
+
+    /// Start integrated loudness and true peak over (e.g. before playing a song through).
+    pub fn reset_loudness(&self) {
+        self.loudness.request_reset();
+    }
+
+    /// Put a spectrum analyzer on a track (post-fader) or, with `None`, on the master.
+    pub fn attach_spectrum(&self, track_index: Option<usize>) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            let target = Self::spectrum_target(eng, track_index)?;
+            eng.attach_spectrum(target).map_err(|e| e.to_string())?;
+            Ok(())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn detach_spectrum(&self, track_index: Option<usize>) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            let target = Self::spectrum_target(eng, track_index)?;
+            eng.detach_spectrum(target);
+            Ok(())
+        }).map_err(|e| e.to_string())?
+    }
+
+    /// Latest spectrum of a track or the master; `None` until an analyzer is attached.
+    pub fn spectrum(&self, track_index: Option<usize>) -> Result<Option<crate::engine::spectrum::SpectrumFrame>, String> {
+        let readout = self.engine.with(move |eng| -> Result<_, String> {
+            let target = Self::spectrum_target(eng, track_index)?;
+            Ok(eng.spectrum_readout(target))
+        }).map_err(|e| e.to_string())??;
+        Ok(readout.map(|r| r.snapshot()))
+    }
+
+    fn spectrum_target(eng: &Engine, track_index: Option<usize>) -> Result<crate::engine::spectrum::SpectrumTarget, String> {
+        use crate::engine::spectrum::SpectrumTarget;
+        match track_index {
+            None => Ok(SpectrumTarget::Master),
+            Some(index) => {
+                let track = eng.tracks().get(index).ok_or("Track not found")?;
+                Ok(SpectrumTarget::Track(track.id))
+            }
+        }
+    }
+
+    /// Master stereo correlation (-1..+1) and recent L/R pairs for a goniometer.
+    pub fn correlation(&self) -> crate::engine::correlation::CorrelationReading {
+        self.correlation.reading()
+    }
+
+    /// The last over on the master (pre-limiter), if the indicator is lit.
+    pub fn clip_status(&self) -> Option<crate::engine::clip_detect::ClipEvent> {
+        self.clip_detect.latest()
+    }
+
+    pub fn clear_clip_indicator(&self) {
+        self.clip_detect.clear();
+    }
 
     pub fn get_master_meter(&self) -> (f32, f32, f32, f32) {
         // FIX: Pull hold_l/hold_r (decayed) instead of peak_l/peak_r (instant)
@@ -523,129 +1560,492 @@ impl AudioRuntime {
     }
 
     pub fn set_master_gain(&self, gain: f32) {
-        if let Ok(mut g) = self.master_gain.lock() {
-            *g = gain.clamp(0.0, 2.0);
+        self.master_gain.store(gain.clamp(0.0, 2.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Key change on the whole mix; while active it adds its latency to the project's.
+    pub fn set_master_pitch_shift(&self, params: crate::effects::pitch_shift::PitchShiftParams) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            eng.set_master_pitch_shift(params);
+            Ok(())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn master_pitch_shift(&self) -> Result<crate::effects::pitch_shift::PitchShiftParams, String> {
+        self.engine.with(move |eng| -> Result<crate::effects::pitch_shift::PitchShiftParams, String> {
+            Ok(eng.master_pitch_shift())
+        }).map_err(|e| e.to_string())?
+    }
+
+    /// Master saturation stage (curve, drive, trim, oversampling, true bypass).
+    pub fn set_master_soft_clip(&self, params: crate::effects::soft_clip::SoftClipParams) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            eng.set_master_soft_clip(params);
+            Ok(())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn set_master_limiter(&self, params: crate::effects::limiter::LimiterParams) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            eng.set_master_limiter(params);
+            Ok(())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn master_limiter(&self) -> Result<crate::effects::limiter::LimiterParams, String> {
+        self.engine.with(move |eng| -> Result<crate::effects::limiter::LimiterParams, String> {
+            Ok(eng.master_limiter())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn master_soft_clip(&self) -> Result<crate::effects::soft_clip::SoftClipParams, String> {
+        self.engine.with(move |eng| -> Result<crate::effects::soft_clip::SoftClipParams, String> {
+            Ok(eng.master_soft_clip())
+        }).map_err(|e| e.to_string())?
+    }
+
+    // --- GROUPS ---
+
+    pub fn groups(&self) -> Result<Vec<crate::engine::mixer::GroupInfo>, String> {
+        self.engine.with(move |eng| -> Result<Vec<crate::engine::mixer::GroupInfo>, String> {
+            Ok(eng.groups().iter().map(|g| g.info()).collect())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn add_group(&self, name: String) -> Result<u32, String> {
+        self.engine.with(move |eng| -> Result<u32, String> {
+            eng.add_group(name).map_err(|e| e.to_string())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn remove_group(&self, group_id: u32) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            eng.remove_group(group_id).map_err(|e| e.to_string())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn rename_group(&self, group_id: u32, name: String) -> Result<(), String> {
+        self.update_group(group_id, move |group| group.name = name)
+    }
+
+    pub fn set_group_gain(&self, group_id: u32, gain: f32) -> Result<(), String> {
+        self.update_group(group_id, move |group| group.gain = gain.clamp(0.0, 2.0))
+    }
+
+    pub fn set_group_muted(&self, group_id: u32, muted: bool) -> Result<(), String> {
+        self.update_group(group_id, move |group| group.muted = muted)
+    }
+
+    pub fn set_group_solo(&self, group_id: u32, solo: bool) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            eng.set_group_solo(group_id, solo).map_err(|e| e.to_string())
+        }).map_err(|e| e.to_string())?
+    }
+
+    fn update_group(&self, group_id: u32, edit: impl FnOnce(&mut crate::engine::mixer::GroupBus) + Send + 'static) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            let group = eng.group_mut(group_id).ok_or_else(|| format!("Group {} not found", group_id))?;
+            edit(group);
+            Ok(())
+        }).map_err(|e| e.to_string())?
+    }
+
+    /// Put a track in a group (`None` takes it out).
+    pub fn set_track_group(&self, track_index: usize, group_id: Option<u32>) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            eng.set_track_group(track_index, group_id).map_err(|e| e.to_string())
+        }).map_err(|e| e.to_string())?
+    }
+
+    // --- FREEZE ---
+
+    /// Render the track through its inserts to a temp file and play that instead. The render
+    /// runs on this thread from a copy of the track, so playback carries on.
+    pub fn freeze_track(&self, track_index: usize) -> Result<(), String> {
+        let (track_id, state, sample_rate) = self.engine.with(move |eng| -> Result<_, String> {
+            let track = eng.tracks().get(track_index).ok_or("Track not found")?;
+            if track.is_frozen() {
+                return Err("Track is already frozen".into());
+            }
+            let state = crate::session::capture_manifest(eng, 1.0, None).tracks.swap_remove(track_index);
+            Ok((track.id, state, eng.sample_rate))
+        }).map_err(|e| e.to_string())??;
+
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let path = std::env::temp_dir().join(format!("haven_freeze_{}_{}.wav", track_id.0, millis));
+        crate::session::freeze::render_frozen_track(&state, sample_rate, &path).map_err(|e| e.to_string())?;
+
+        // The track may have moved (or gone) while rendering
+        let channels = self.engine.state().channels.max(1);
+        let result = crate::engine::track::Clip::new(path.to_string_lossy().into_owned(), Duration::ZERO, sample_rate, channels)
+            .map_err(|e| e.to_string())
+            .and_then(|rendered| {
+                self.engine
+                    .with(move |eng| match eng.tracks().iter().position(|t| t.id == track_id) {
+                        Some(index) => eng.freeze_track(index, rendered).map_err(|e| e.to_string()),
+                        None => Err("Track was removed while freezing".to_string()),
+                    })
+                    .map_err(|e| e.to_string())?
+            });
+        if result.is_err() {
+            let _ = std::fs::remove_file(&path);
         }
+        result
     }
 
-    pub fn master_gain(&self) -> f32 {
-        if let Ok(g) = self.master_gain.lock() {
-            *g
-        } else {
-            1.0
+    /// Back to the clips and live inserts; the rendered file is deleted.
+    pub fn unfreeze_track(&self, track_index: usize) -> Result<(), String> {
+        let path = self
+            .engine
+            .with(move |eng| eng.unfreeze_track(track_index).map_err(|e| e.to_string()))
+            .map_err(|e| e.to_string())??;
+        let Some(path) = path else { return Ok(()) };
+        if let Err(e) = std::fs::remove_file(&path) {
+            rt_warn!("⚠️ Could not delete freeze file {}: {}", path, e);
         }
+        Ok(())
+    }
+
+    // --- AUX BUSES ---
+
+    pub fn aux_buses(&self) -> Result<Vec<crate::engine::mixer::AuxBusInfo>, String> {
+        self.engine.with(move |eng| -> Result<Vec<crate::engine::mixer::AuxBusInfo>, String> {
+            Ok(eng.aux_buses().iter().map(|b| b.info()).collect())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn add_aux_bus(&self, name: String) -> Result<u32, String> {
+        self.engine.with(move |eng| -> Result<u32, String> {
+            eng.add_aux_bus(name).map_err(|e| e.to_string())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn remove_aux_bus(&self, bus_id: u32) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            eng.remove_aux_bus(bus_id).map_err(|e| e.to_string())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn rename_aux_bus(&self, bus_id: u32, name: String) -> Result<(), String> {
+        self.update_aux_bus(bus_id, move |bus| bus.name = name)
+    }
+
+    pub fn set_aux_bus_gain(&self, bus_id: u32, gain: f32) -> Result<(), String> {
+        self.update_aux_bus(bus_id, move |bus| bus.gain = gain.clamp(0.0, 2.0))
+    }
+
+    pub fn set_aux_bus_muted(&self, bus_id: u32, muted: bool) -> Result<(), String> {
+        self.update_aux_bus(bus_id, move |bus| bus.muted = muted)
+    }
+
+    /// Replace the bus's effect chain (inserts that keep their kind keep their tails).
+    pub fn set_aux_bus_effects(&self, bus_id: u32, chain: Vec<crate::engine::mixer::BusEffectParams>) -> Result<(), String> {
+        self.update_aux_bus(bus_id, move |bus| bus.set_effects(&chain))
+    }
+
+    // --- TRACK INSERTS ---
+
+    pub fn track_inserts(&self, track_index: usize) -> Result<Vec<crate::effects::chain::InsertState>, String> {
+        self.engine.with(move |eng| -> Result<Vec<crate::effects::chain::InsertState>, String> {
+            let track = eng.tracks().get(track_index).ok_or("Track not found")?;
+            Ok(track.inserts.states())
+        }).map_err(|e| e.to_string())?
+    }
+
+    /// Add an effect to a track's insert chain at `index` (the end when `None`). Returns its id.
+    pub fn add_track_insert(&self, track_index: usize, params: crate::effects::chain::InsertParams, index: Option<usize>) -> Result<u32, String> {
+        self.update_inserts(track_index, move |chain| chain.add(&params, index))
+    }
+
+    pub fn remove_track_insert(&self, track_index: usize, insert_id: u32) -> Result<(), String> {
+        self.update_inserts(track_index, move |chain| chain.remove(insert_id).map(|_| ()))
+    }
+
+    pub fn move_track_insert(&self, track_index: usize, insert_id: u32, index: usize) -> Result<(), String> {
+        self.update_inserts(track_index, move |chain| chain.move_insert(insert_id, index))
+    }
+
+    pub fn set_track_insert_bypass(&self, track_index: usize, insert_id: u32, bypass: bool) -> Result<(), String> {
+        self.update_inserts(track_index, move |chain| chain.set_bypass(insert_id, bypass))
+    }
+
+    /// Dry/wet blend (0..1) of an insert; EQ inserts refuse it.
+    pub fn set_track_insert_mix(&self, track_index: usize, insert_id: u32, mix: f32) -> Result<(), String> {
+        self.update_inserts(track_index, move |chain| chain.set_mix(insert_id, mix))
+    }
+
+    /// While the track writes automation (Write/Latch, transport rolling) the moved
+    /// parameters are also recorded into the insert's lanes.
+    pub fn set_track_insert_params(&self, track_index: usize, insert_id: u32, params: crate::effects::chain::InsertParams) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            let time = eng.automation_write_time();
+            let track = eng.tracks_mut().get_mut(track_index).ok_or("Track not found")?;
+            let result = match time.filter(|_| track.automation_mode.is_writing()) {
+                Some(time) => track.inserts.write_params(insert_id, &params, time),
+                None => track.inserts.set_params(insert_id, &params),
+            };
+            result.map_err(|e| e.to_string())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn set_track_automation_mode(&self, track_index: usize, mode: crate::engine::automation::AutomationMode) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            eng.set_automation_mode(track_index, mode).map_err(|e| e.to_string())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn track_automation_mode(&self, track_index: usize) -> Result<crate::engine::automation::AutomationMode, String> {
+        self.engine.with(move |eng| -> Result<crate::engine::automation::AutomationMode, String> {
+            let track = eng.tracks().get(track_index).ok_or("Track not found")?;
+            Ok(track.automation_mode)
+        }).map_err(|e| e.to_string())?
+    }
+
+    /// Drop an insert's lane for `param`, or every lane of the insert when `None`.
+    pub fn clear_track_insert_automation(&self, track_index: usize, insert_id: u32, param: Option<String>) -> Result<(), String> {
+        self.update_inserts(track_index, move |chain| chain.clear_automation(insert_id, param.as_deref()))
+    }
+
+    fn update_inserts<T: Send + 'static>(
+        &self,
+        track_index: usize,
+        edit: impl FnOnce(&mut crate::effects::chain::EffectChain) -> anyhow::Result<T> + Send + 'static,
+    ) -> Result<T, String> {
+        self.engine.with(move |eng| -> Result<T, String> {
+            let track = eng.tracks_mut().get_mut(track_index).ok_or("Track not found")?;
+            edit(&mut track.inserts).map_err(|e| e.to_string())
+        }).map_err(|e| e.to_string())?
+    }
+
+    fn update_aux_bus(&self, bus_id: u32, edit: impl FnOnce(&mut crate::engine::mixer::AuxBus) + Send + 'static) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            let bus = eng.aux_bus_mut(bus_id).ok_or_else(|| format!("Aux bus {} not found", bus_id))?;
+            edit(bus);
+            Ok(())
+        }).map_err(|e| e.to_string())?
+    }
+
+    /// Post-fader send level (linear, 0..2) from a track into a bus.
+    pub fn set_track_send(&self, track_index: usize, bus_id: u32, level: f32) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            eng.set_track_send(track_index, bus_id, level).map_err(|e| e.to_string())
+        }).map_err(|e| e.to_string())?
+    }
+
+    /// Returns the config as applied (ranges clamped).
+    pub fn set_metronome_config(
+        &self,
+        config: crate::engine::metronome::MetronomeConfig,
+    ) -> Result<crate::engine::metronome::MetronomeConfig, String> {
+        self.engine.with(move |eng| -> Result<crate::engine::metronome::MetronomeConfig, String> {
+            Ok(eng.set_metronome_config(config))
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn metronome_config(&self) -> Result<crate::engine::metronome::MetronomeConfig, String> {
+        self.engine.with(move |eng| -> Result<crate::engine::metronome::MetronomeConfig, String> {
+            Ok(eng.metronome_config())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn master_gain(&self) -> f32 {
+        f32::from_bits(self.master_gain.load(Ordering::Relaxed))
+    }
+
+    /// Audio callbacks that played silence because the engine was away from the callback.
+    /// Climbs while a long edit (e.g. loading a project) has it detached.
+    pub fn dropouts(&self) -> u64 {
+        self.dropouts.load(Ordering::Relaxed)
     }
 
     pub fn set_bpm(&self, bpm: f32) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::SetBpm(bpm));
+        self.send(EngineCommand::SetBpm(bpm));
+    }
+
+    pub fn playback_rate(&self) -> f64 {
+        self.engine.with(move |eng| eng.playback_rate()).unwrap_or(1.0)
+    }
+
+    /// Master varispeed: speed and pitch together, like a tape machine (0.25x to 2x).
+    /// Export renders at 1x. Refused while recording, so takes stay in time with the project.
+    pub fn set_playback_rate(&self, rate: f64) -> Result<f64, String> {
+        self.engine.with(move |eng| -> Result<f64, String> {
+            if eng.recording && rate != eng.playback_rate() {
+                return Err("Cannot change the playback rate while recording".into());
+            }
+            let applied = eng.set_playback_rate(rate);
+            rt_info!("⏩ Playback rate: {:.2}x", applied);
+            Ok(applied)
+        }).map_err(|e| e.to_string())?
     }
 
     // 3. ADD THIS PUBLIC METHOD
     pub fn set_time_signature(&self, numerator: u32, denominator: u32) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::SetTimeSignature(numerator, denominator));
+        self.send(EngineCommand::SetTimeSignature(numerator, denominator));
+    }
+
+    /// Switch meter from the start of `bar` (1-indexed); bar 1 sets the initial meter.
+    pub fn set_meter_change(&self, bar: u32, numerator: u32, denominator: u32) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            eng.transport.tempo.set_meter_change(bar, crate::engine::time::TimeSignature { numerator, denominator })
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn remove_meter_change(&self, bar: u32) -> Result<bool, String> {
+        self.engine.with(move |eng| -> Result<bool, String> {
+            Ok(eng.transport.tempo.remove_meter_change(bar))
+        }).map_err(|e| e.to_string())?
+    }
+
+    /// The initial meter (bar 1) followed by every change.
+    pub fn meter_changes(&self) -> Result<Vec<crate::engine::time::MeterChange>, String> {
+        self.engine.with(move |eng| -> Result<Vec<crate::engine::time::MeterChange>, String> {
+            let tempo = &eng.transport.tempo;
+            let initial = crate::engine::time::MeterChange { bar: 1, signature: tempo.signature };
+            Ok(std::iter::once(initial).chain(tempo.meter_changes().iter().copied()).collect())
+        }).map_err(|e| e.to_string())?
     }
 
     pub fn bpm(&self) -> f32 {
-        if let Ok(eng) = self.engine.lock() {
-            eng.transport.tempo.bpm as f32
-        } else {
-            120.0
+        match self.engine.state().bpm {
+            bpm if bpm > 0.0 => bpm as f32,
+            _ => 120.0,
         }
     }
 
+    pub fn snap_resolution(&self) -> SnapResolution {
+        self.engine.with(move |eng| eng.snap_resolution()).unwrap_or_default()
+    }
+
+    /// Grid that `move_clip` snaps to (`Off` places clips exactly where asked).
+    pub fn set_snap_resolution(&self, resolution: SnapResolution) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            eng.set_snap_resolution(resolution);
+            Ok(())
+        }).map_err(|e| e.to_string())?
+    }
+
     pub fn get_grid_lines(&self, start: Duration, end: Duration, resolution: u32) -> Vec<GridLine> {
-        if let Ok(eng) = self.engine.lock() {
-            eng.transport.tempo.get_grid_lines(start, end, resolution)
-        } else {
-            Vec::new()
-        }
+        self.engine
+            .with(move |eng| eng.transport.tempo.get_grid_lines(start, end, resolution))
+            .unwrap_or_default()
     }
 
     // --- TRACK CONTROLS ---
 
     pub fn toggle_mute(&self, track_index: usize) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::ToggleMute(track_index));
+        self.send(EngineCommand::ToggleMute(track_index));
     }
 
     // Non-destructive solo logic
     pub fn toggle_solo(&self, track_index: usize) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::ToggleSolo(track_index));
+        self.send(EngineCommand::ToggleSolo(track_index));
     }
 
-    pub fn solo_track(&self, track_index: usize) {
-        self.toggle_solo(track_index);
+    pub fn clear_solo(&self) {
+        self.send(EngineCommand::ClearSolo);
     }
 
-    pub fn clear_solo(&self) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::ClearSolo);
+    /// Additive or exclusive solo (soloing clears the other solos).
+    pub fn set_solo_mode(&self, mode: crate::engine::SoloMode) {
+        let _ = self.engine.post(move |eng| eng.solo_mode = mode);
+    }
+
+    pub fn solo_mode(&self) -> crate::engine::SoloMode {
+        self.engine.with(move |eng| eng.solo_mode).unwrap_or_default()
+    }
+
+    /// A solo-safe track keeps playing while other tracks are soloed (reverb returns, references).
+    pub fn set_solo_safe(&self, track_index: usize, safe: bool) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            let track = eng.tracks_mut().get_mut(track_index).ok_or("Track not found")?;
+            track.solo_safe = safe;
+            Ok(())
+        }).map_err(|e| e.to_string())?
+    }
+
+    pub fn set_solo_policy(&self, policy: crate::engine::SoloPolicy) {
+        self.send(EngineCommand::SetSoloPolicy(policy));
+    }
+
+    pub fn solo_policy(&self) -> crate::engine::SoloPolicy {
+        self.engine.with(move |eng| eng.solo_policy).unwrap_or_default()
     }
 
     // Absolute Gain Setter (for Sliders)
     pub fn set_track_gain(&self, track_index: usize, gain: f32) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::SetTrackGain(track_index, gain.clamp(0.0, 2.0)));
+        self.send(EngineCommand::SetTrackGain(track_index, gain.clamp(0.0, 2.0)));
     }
 
     // Absolute Pan Setter
     pub fn set_track_pan(&self, track_index: usize, pan: f32) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::SetTrackPan(track_index, pan.clamp(-1.0, 1.0)));
+        self.send(EngineCommand::SetTrackPan(track_index, pan.clamp(-1.0, 1.0)));
     }
 
     pub fn set_monitor(&self, monitor: crate::recorder::monitor::Monitor) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::SetMonitor(monitor));
+        self.send(EngineCommand::SetMonitor(monitor));
+    }
+    /// Install a per-block hook on the engine (runs on the audio thread, see `engine::hooks`).
+    pub fn set_block_callback(&self, callback: crate::engine::hooks::BlockCallback) {
+        let _ = self.engine.post(move |eng| eng.set_block_callback(callback));
+    }
+
+    pub fn clear_block_callback(&self) {
+        let _ = self.engine.post(move |eng| eng.clear_block_callback());
+    }
+
+    /// Balance live input against playback in what the performer hears.
+    /// Never affects recordings, faders or exports.
+    pub fn set_monitor_blend(&self, input_level: f32, playback_level: f32) {
+        self.send(EngineCommand::SetMonitorBlend(input_level, playback_level));
+    }
+
+    /// Play the cue mix (blend, listen) on the speakers when the cue has no outputs of its
+    /// own. Off: the speakers play the master, with monitored input summed in at unity.
+    pub fn set_cue_on_speakers(&self, on: bool) {
+        self.send(EngineCommand::SetCueOnSpeakers(on));
     }
+
     pub fn clear_monitor(&self) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::ClearMonitor);
+        self.send(EngineCommand::ClearMonitor);
     }
 
     // Relative Adjusters (Kept for Keyboard Shortcuts in daw_controller)
     pub fn adjust_track_gain(&self, track_index: usize, delta: f32) {
-        let (track_id, old_gain) = {
-            let eng = self.engine.lock().unwrap();
-            if let Some(t) = eng.tracks().get(track_index) {
-                (t.id, t.gain)
-            } else { return; }
+        let Ok(Some((track_id, old_gain))) = self.engine.with(move |eng| eng.tracks().get(track_index).map(|t| (t.id, t.gain))) else {
+            return;
         };
         let new_gain = (old_gain + delta).clamp(0.0, 2.0);
         let cmd = Box::new(SetTrackGain { track_id, old_gain, new_gain });
-        if let Ok(mut session) = self.session.lock() {
+        if let Ok(mut session) = self.lock_session() {
             let _ = session.apply(&self.engine, cmd);
         }
     }
 
     pub fn adjust_track_pan(&self, track_index: usize, delta: f32) {
-        let (track_id, old_pan) = {
-            let eng = self.engine.lock().unwrap();
-            if let Some(t) = eng.tracks().get(track_index) {
-                (t.id, t.pan)
-            } else { return; }
+        let Ok(Some((track_id, old_pan))) = self.engine.with(move |eng| eng.tracks().get(track_index).map(|t| (t.id, t.pan))) else {
+            return;
         };
         let new_pan = (old_pan + delta).clamp(-1.0, 1.0);
         let cmd = Box::new(SetTrackPan { track_id, old_pan, new_pan });
-        if let Ok(mut session) = self.session.lock() {
+        if let Ok(mut session) = self.lock_session() {
             let _ = session.apply(&self.engine, cmd);
         }
     }
 
     pub fn merge_clip_with_next(&self, track_index: usize, clip_index: usize) -> anyhow::Result<()> {
-        let (track_id, original_duration, right_clip_data) = {
-            let eng = self.engine.lock().unwrap();
+        let (track_id, original_duration, right_clip_data) = self.engine.with(move |eng| -> anyhow::Result<_> {
+            eng.ensure_not_frozen(track_index)?;
             let track = eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?;
             
             let left = track.clips.get(clip_index).ok_or(anyhow::anyhow!("Left clip not found"))?;
             let right = track.clips.get(clip_index + 1).ok_or(anyhow::anyhow!("Right clip not found"))?;
             
-            let right_data = DeletedClipData {
-                path: right.path.clone(),
-                start_time: right.start_time,
-                offset: right.offset,
-                duration: right.duration,
-                source_duration: right.source_duration,
-                source_sr: right.source_sr,
-                source_ch: right.source_ch,
-            };
-            
-            (track.id, left.duration, right_data)
-        };
+            Ok((track.id, left.duration, DeletedClipData::of(right)))
+        })??;
 
         let cmd = Box::new(crate::session::commands::MergeClip {
             track_id,
@@ -654,58 +2054,298 @@ impl AudioRuntime {
             right_clip_data,
         });
 
-        if let Ok(mut session) = self.session.lock() {
+        if let Ok(mut session) = self.lock_session() {
             session.apply(&self.engine, cmd)?;
         }
 
-        if let Ok(mut eng) = self.engine.lock() {
+        let _ = self.engine.post(move |eng| {
             if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id == track_id) {
                 track.renumber_clips();
             }
+        });
+
+        // 🚀 FIX: Re-sync decoders
+        let pos = self.position();
+        self.seek(pos);
+
+        Ok(())
+    }
+
+    pub fn delete_clip(&self, track_index: usize, clip_index: usize) -> anyhow::Result<()> {
+        let (track_id, clip_data) = self.engine.with(move |eng| -> anyhow::Result<_> {
+            eng.ensure_not_frozen(track_index)?;
+            let track = eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?;
+            let clip = track.clips.get(clip_index).ok_or(anyhow::anyhow!("Clip not found"))?;
+            
+            Ok((track.id, DeletedClipData::of(clip)))
+        })??;
+    
+        let cmd = Box::new(DeleteClip {
+            track_id,
+            clip_index,
+            clip_data,
+        });
+        
+        if let Ok(mut session) = self.lock_session() {
+            session.apply(&self.engine, cmd)?;
+        }
+
+        let _ = self.engine.post(move |eng| {
+            if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id == track_id) {
+                track.renumber_clips();
+            }
+        });
+
+        // 🚀 FIX: Re-sync decoders
+        let pos = self.position();
+        self.seek(pos);
+
+        Ok(())
+    }
+
+    // --- GAPS ---
+
+    /// Silent holes in the arrangement as (start, end) seconds, for the timeline overlay.
+    pub fn find_gaps(&self, min_gap: Duration, fades_are_gaps: bool) -> Vec<(f64, f64)> {
+        self.engine
+            .with(move |eng| {
+                eng.find_gaps_with(min_gap, fades_are_gaps)
+                    .into_iter()
+                    .map(|(start, end)| (start.as_secs_f64(), end.as_secs_f64()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // --- HOVER PREVIEW ---
+
+    /// Audition `duration` seconds of a clip from `offset_in_clip` (clip-relative), at -6 dB,
+    /// without touching the transport. A new call cancels the previous preview with a short fade.
+    pub fn preview_clip_at(&self, track_index: usize, clip_index: usize, offset_in_clip: f64, duration: f64) -> anyhow::Result<()> {
+        if self.recorder.lock().map_err(|_| anyhow::anyhow!("Failed to lock recorder"))?.is_some() {
+            return Err(anyhow::anyhow!("Preview is disabled while recording"));
+        }
+        let (path, source_sr, source_ch, file_pos, length, sample_rate, channels) = self.engine.with(move |eng| -> anyhow::Result<_> {
+            let track = eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?;
+            let clip = track.clips.get(clip_index).ok_or(anyhow::anyhow!("Clip not found"))?;
+            let offset = Duration::from_secs_f64(offset_in_clip.max(0.0)).min(clip.length());
+            let length = Duration::from_secs_f64(duration.max(0.0)).min(clip.length() - offset);
+            // The preview plays the source unstretched, from the same spot
+            let file_pos = clip.offset_time() + offset.div_f64(clip.stretch());
+            Ok((clip.path.clone(), clip.source_sr, clip.source_ch, file_pos, length.div_f64(clip.stretch()), eng.sample_rate, eng.channels))
+        })??;
+        if length.is_zero() {
+            return Ok(());
+        }
+
+        // Opening the decoder happens here, not on the audio thread
+        let voice = crate::engine::preview::PreviewVoice::new(path, source_sr, source_ch, file_pos, length, sample_rate, channels)?;
+        self.engine.with(move |eng| eng.start_preview(voice))?;
+        Ok(())
+    }
+
+    pub fn stop_preview(&self) {
+        let _ = self.engine.post(move |eng| eng.stop_preview());
+    }
+
+    // --- LISTEN ---
+
+    /// PFL/AFL a track on the cue output (exclusive: releases any other listening track).
+    /// Returns whether the listen signal is heard (`Engine::listen_audible`): false when the
+    /// cue has no outputs and `set_cue_on_speakers` is off.
+    pub fn set_track_listen(&self, track_index: usize, mode: crate::engine::track::ListenMode) -> anyhow::Result<bool> {
+        self.engine.with(move |eng| -> anyhow::Result<bool> {
+            eng.set_track_listen(track_index, mode)?;
+            Ok(eng.listen_audible())
+        })?
+    }
+
+    // --- NOTES ---
+
+    /// Undoable; consecutive edits of the same note within an editing session are one undo step.
+    pub fn set_track_notes(&self, track_index: usize, notes: String) -> anyhow::Result<()> {
+        let (track_id, old_notes) = self.engine.with(move |eng| -> anyhow::Result<_> {
+            let track = eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?;
+            Ok((track.id, track.notes.clone()))
+        })??;
+        self.apply_notes(NoteTarget::Track(track_id), old_notes, notes)
+    }
+
+    pub fn set_clip_notes(&self, track_index: usize, clip_index: usize, notes: String) -> anyhow::Result<()> {
+        let (track_id, old_notes) = self.engine.with(move |eng| -> anyhow::Result<_> {
+            let track = eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?;
+            let clip = track.clips.get(clip_index).ok_or(anyhow::anyhow!("Clip not found"))?;
+            Ok((track.id, clip.notes.clone()))
+        })??;
+        self.apply_notes(NoteTarget::Clip(track_id, clip_index), old_notes, notes)
+    }
+
+    fn apply_notes(&self, target: NoteTarget, old_notes: String, new_notes: String) -> anyhow::Result<()> {
+        if new_notes.len() > crate::engine::track::MAX_NOTES_BYTES {
+            return Err(anyhow::anyhow!("Notes are limited to {} KB", crate::engine::track::MAX_NOTES_BYTES / 1024));
+        }
+        if new_notes == old_notes {
+            return Ok(());
+        }
+        let cmd = Box::new(SetNotes { target, old_notes, new_notes });
+        if let Ok(mut session) = self.lock_session() {
+            session.apply_coalesced(&self.engine, cmd)?;
+        }
+        Ok(())
+    }
+
+    // --- CROSSFADES ---
+
+    /// Crossfade length (seconds) where a clip overlaps the one before it; `None` fades over
+    /// the whole overlap. Undoable; a drag coalesces into one step.
+    pub fn set_clip_crossfade(&self, track_index: usize, clip_index: usize, length: Option<f64>) -> anyhow::Result<()> {
+        if length.is_some_and(|secs| !secs.is_finite() || secs < 0.0) {
+            return Err(anyhow::anyhow!("Crossfade length must be a positive number of seconds"));
+        }
+        let new = length.map(Duration::from_secs_f64);
+        let (track_id, old) = self.engine.with(move |eng| -> anyhow::Result<_> {
+            eng.ensure_not_frozen(track_index)?;
+            let track = eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?;
+            let clip = track.clips.get(clip_index).ok_or(anyhow::anyhow!("Clip not found"))?;
+            Ok((track.id, clip.crossfade))
+        })??;
+        if new == old {
+            return Ok(());
+        }
+        let cmd = Box::new(SetClipCrossfade { track_id, clip_index, old, new });
+        if let Ok(mut session) = self.lock_session() {
+            session.apply_coalesced(&self.engine, cmd)?;
+        }
+        Ok(())
+    }
+
+    // --- MUTE REGIONS ---
+
+    /// Silence `start..end` (seconds into the clip). Overlapping regions merge. Undoable.
+    pub fn add_clip_mute_region(&self, track_index: usize, clip_index: usize, start: f64, end: f64) -> anyhow::Result<()> {
+        let (track_id, old_regions, clip_len) = self.clip_mute_state(track_index, clip_index)?;
+        if !start.is_finite() || !end.is_finite() {
+            return Err(anyhow::anyhow!("Invalid mute region"));
+        }
+        let (start, end) = (start.max(0.0), end.min(clip_len));
+        if end <= start {
+            return Err(anyhow::anyhow!("Mute region must lie inside the clip and have a length"));
+        }
+        let mut new_regions = old_regions.clone();
+        mute_regions::insert(&mut new_regions, MuteRegion { start, end });
+        self.apply_mute_regions(track_id, clip_index, old_regions, new_regions, "Add Mute Region")
+    }
+
+    /// Remove one region by its index in `clip_mute_regions`. Undoable.
+    pub fn remove_clip_mute_region(&self, track_index: usize, clip_index: usize, region_index: usize) -> anyhow::Result<()> {
+        let (track_id, old_regions, _) = self.clip_mute_state(track_index, clip_index)?;
+        if region_index >= old_regions.len() {
+            return Err(anyhow::anyhow!("Mute region not found"));
+        }
+        let mut new_regions = old_regions.clone();
+        new_regions.remove(region_index);
+        self.apply_mute_regions(track_id, clip_index, old_regions, new_regions, "Remove Mute Region")
+    }
+
+    /// Sorted, non-overlapping, in seconds from the clip start.
+    pub fn clip_mute_regions(&self, track_index: usize, clip_index: usize) -> anyhow::Result<Vec<MuteRegion>> {
+        self.clip_mute_state(track_index, clip_index).map(|(_, regions, _)| regions)
+    }
+
+    fn clip_mute_state(&self, track_index: usize, clip_index: usize) -> anyhow::Result<(crate::engine::TrackId, Vec<MuteRegion>, f64)> {
+        self.engine.with(move |eng| {
+            let track = eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?;
+            let clip = track.clips.get(clip_index).ok_or(anyhow::anyhow!("Clip not found"))?;
+            Ok((track.id, clip.mute_regions.clone(), clip.length().as_secs_f64()))
+        })?
+    }
+
+    fn apply_mute_regions(
+        &self,
+        track_id: crate::engine::TrackId,
+        clip_index: usize,
+        old_regions: Vec<MuteRegion>,
+        new_regions: Vec<MuteRegion>,
+        label: &'static str,
+    ) -> anyhow::Result<()> {
+        let cmd = Box::new(SetMuteRegions { track_id, clip_index, old_regions, new_regions, label });
+        if let Ok(mut session) = self.lock_session() {
+            session.apply(&self.engine, cmd)?;
+        }
+        Ok(())
+    }
+
+    /// Every track and clip with a non-empty note, in timeline order per track.
+    pub fn list_annotated(&self) -> Vec<Annotation> {
+        self.engine.with(Self::collect_annotations).unwrap_or_default()
+    }
+
+    fn collect_annotations(eng: &mut Engine) -> Vec<Annotation> {
+        let mut out = Vec::new();
+        for t in eng.tracks() {
+            if !t.notes.trim().is_empty() {
+                out.push(Annotation {
+                    track_id: t.id.0,
+                    track_name: t.name.clone(),
+                    clip_index: None,
+                    clip_number: None,
+                    start_time: None,
+                    notes: t.notes.clone(),
+                });
+            }
+            for (i, c) in t.clips.iter().enumerate().filter(|(_, c)| !c.notes.trim().is_empty()) {
+                out.push(Annotation {
+                    track_id: t.id.0,
+                    track_name: t.name.clone(),
+                    clip_index: Some(i),
+                    clip_number: Some(c.clip_number),
+                    start_time: Some(c.start().as_secs_f64()),
+                    notes: c.notes.clone(),
+                });
+            }
         }
-
-        // 🚀 FIX: Re-sync decoders
-        let pos = self.position();
-        self.seek(pos);
-
-        Ok(())
+        out
     }
 
-    pub fn delete_clip(&self, track_index: usize, clip_index: usize) -> anyhow::Result<()> {
-        let (track_id, clip_data) = {
-            let eng = self.engine.lock().unwrap();
-            let track = eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?;
-            let clip = track.clips.get(clip_index).ok_or(anyhow::anyhow!("Clip not found"))?;
-            
-            let data = DeletedClipData {
-                path: clip.path.clone(),
-                start_time: clip.start_time,
-                offset: clip.offset,
-                duration: clip.duration,
-                source_duration: clip.source_duration,
-                source_sr: clip.source_sr,
-                source_ch: clip.source_ch,
-            };
-            (track.id, data)
-        };
-    
-        let cmd = Box::new(DeleteClip {
+    /// Place a finished recording on a track as one undoable step ("Record Take").
+    pub fn commit_record_take(&self, track_index: usize, path: String, start_time: f64, offset: f64) -> anyhow::Result<()> {
+        // Recordings are our own WAVs: the header gives exact metadata without decoding
+        let reader = hound::WavReader::open(&path)?;
+        let spec = reader.spec();
+        let source_duration = Frames(reader.duration() as u64);
+        drop(reader);
+
+        // The take's clip counts in the file's own frames
+        let rate = spec.sample_rate;
+        let offset = Seconds(offset).to_frames(rate).min(source_duration);
+        let duration = source_duration - offset;
+        crate::engine::track::ClipEditError::check_duration(duration.to_duration(rate))?;
+        let track_id = self.engine.with(move |eng| -> anyhow::Result<_> {
+            Ok(eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?.id)
+        })??;
+
+        let cmd = Box::new(RecordTake {
             track_id,
-            clip_index,
-            clip_data,
+            clip_data: DeletedClipData {
+                path,
+                start_time: Seconds(start_time).to_frames(rate),
+                offset,
+                duration,
+                source_duration,
+                rate,
+                source_sr: spec.sample_rate,
+                source_ch: spec.channels as usize,
+                notes: String::new(),
+                mute_regions: Vec::new(),
+                stretch: 1.0,
+                varispeed: false,
+                crossfade: None,
+            },
         });
-        
-        if let Ok(mut session) = self.session.lock() {
-            session.apply(&self.engine, cmd)?;
-        }
 
-        if let Ok(mut eng) = self.engine.lock() {
-            if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id == track_id) {
-                track.renumber_clips();
-            }
-        }
+        self.lock_session().map_err(|_| anyhow::anyhow!("Lock error"))?.apply(&self.engine, cmd)?;
 
-        // 🚀 FIX: Re-sync decoders
+        // Re-sync decoders
         let pos = self.position();
         self.seek(pos);
 
@@ -717,56 +2357,84 @@ impl AudioRuntime {
     // KEEP THIS ONE
     pub fn update_eq(&self, track_index: usize, band_index: usize, params: EqParams) {
         // PRO FIX: Send directly to the lock-free queue! Bypasses the Undo Session for AI adjustments.
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::UpdateEq(track_index, band_index, params));
+        self.send(EngineCommand::UpdateEq(track_index, band_index, params));
+    }
+
+    /// One undo step back to a neutral mix: 0 dB, center, nothing muted or soloed and
+    /// optionally every insert bypassed. Faders ramp over one block, so it is safe mid-playback.
+    pub fn reset_mixer(&self, options: MixerResetOptions) -> anyhow::Result<()> {
+        let before: Vec<MixState> = self.engine.with(|eng| eng.tracks().iter().map(MixState::capture).collect())?;
+        if before.is_empty() {
+            return Ok(());
+        }
+        if let Ok(mut session) = self.lock_session() {
+            session.apply(&self.engine, Box::new(ResetMixer { before, options }))?;
+        }
+        Ok(())
+    }
+
+    /// Skip the track's EQ, compressor and reverb without losing their settings. Undoable.
+    pub fn set_track_fx_bypass(&self, track_index: usize, bypass: bool) -> anyhow::Result<()> {
+        let track_id = self.engine.with(move |eng| -> anyhow::Result<_> {
+            let track = eng.tracks().get(track_index).ok_or(anyhow::anyhow!("Track not found"))?;
+            Ok((track.fx_bypass != bypass).then_some(track.id))
+        })??;
+        let Some(track_id) = track_id else { return Ok(()) };
+        if let Ok(mut session) = self.lock_session() {
+            session.apply(&self.engine, Box::new(SetFxBypass { track_id, bypass }))?;
+        }
+        Ok(())
     }
 
     // NEW Helper
     pub fn set_track_mute(&self, track_index: usize, state: bool) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::SetTrackMute(track_index, state));
+        self.send(EngineCommand::SetTrackMute(track_index, state));
     }
 
     pub fn get_eq_state(&self, track_index: usize) -> Vec<EqParams> {
-        if let Ok(eng) = self.engine.lock() {
-            if let Some(track) = eng.tracks().get(track_index) {
-                return track.track_eq.get_state();
+        self.engine
+            .with(move |eng| eng.tracks().get(track_index).map(|track| track.track_eq.get_state()))
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    /// Magnitude response of the track's EQ, or of one of its EQ inserts, at `points`
+    /// log-spaced frequencies.
+    pub fn eq_response(&self, track_index: usize, insert_id: Option<u32>, points: usize) -> Result<crate::effects::equalizer::EqResponse, String> {
+        self.engine.with(move |eng| -> Result<crate::effects::equalizer::EqResponse, String> {
+            let track = eng.tracks().get(track_index).ok_or("Track not found")?;
+            match insert_id {
+                Some(id) => track.inserts.eq_response(id, points).map_err(|e| e.to_string()),
+                None => Ok(track.track_eq.frequency_response(points)),
             }
-        }
-        Vec::new()
+        }).map_err(|e| e.to_string())?
     }
 
     pub fn update_compressor(&self, track_index: usize, params: CompressorParams) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::UpdateCompressor(track_index, params));
+        self.send(EngineCommand::UpdateCompressor(track_index, params));
     }
 
     pub fn get_compressor_state(&self, track_index: usize) -> CompressorParams {
-        if let Ok(eng) = self.engine.lock() {
-            if let Some(track) = eng.tracks().get(track_index) {
-                return track.track_compressor.get_params();
-            }
-        }
-        // Fallback default if track isn't found
-        CompressorParams {
-            is_active: false,
-            threshold_db: -20.0,
-            ratio: 4.0,
-            attack_ms: 5.0,
-            release_ms: 50.0,
-            makeup_gain_db: 0.0,
-        }
+        self.engine
+            .with(move |eng| eng.tracks().get(track_index).map(|track| track.track_compressor.get_params()))
+            .ok()
+            .flatten()
+            // Fallback default if track isn't found
+            .unwrap_or_default()
     }
 
     pub fn set_effect_param(&self, track_index: usize, effect: String, param: String, value: f32) {
-        let _ = self.command_tx.lock().unwrap().try_send(EngineCommand::SetEffectParam(track_index, effect, param, value));
+        self.send(EngineCommand::SetEffectParam(track_index, effect, param, value));
     }
 
     pub fn get_reverb_state(&self, track_index: usize) -> ReverbParams {
-        if let Ok(eng) = self.engine.lock() {
-            if let Some(track) = eng.tracks().get(track_index) {
-                return track.track_reverb.get_params();
-            }
-        }
-        // Fallback default
-        ReverbParams { is_active: false, room_size: 0.8, damping: 0.5, mix: 0.3, width: 1.0, pre_delay_ms: 10.0, low_cut_hz: 100.0, high_cut_hz: 8000.0 } // <--- CHANGED is_active to false
+        self.engine
+            .with(move |eng| eng.tracks().get(track_index).map(|track| track.track_reverb.get_params()))
+            .ok()
+            .flatten()
+            // Fallback default
+            .unwrap_or_default()
     }
 
     // FIX: Corrected Reset Methods (No Delta, Just Reset)
@@ -781,36 +2449,117 @@ impl AudioRuntime {
     // --- SAVE / LOAD / EXPORT (Primary for Main.rs) ---
 
     pub fn save_project(&self, path: String) -> Result<(), String> {
-        let session = self.session.lock().map_err(|_| "Lock error")?;
+        let mut session = self.lock_session().map_err(|_| "Lock error")?;
         let master_gain = self.master_gain();
-        session.save_project(&self.engine, &path, master_gain)
+        session.save_project(&self.engine, &path, master_gain, Some(self.audio_prefs()))
             .map_err(|e| e.to_string())
     }
 
-    pub fn load_project(&self, path: String) -> Result<(), String> {
-        let mut session = self.session.lock().map_err(|_| "Lock error")?;
-        let new_master_gain = session.load_project(&self.engine, &path)
+    /// Counts, media size and formats of the current project. Cached until the next edit;
+    /// the media is stat-ed here, not on the audio thread.
+    pub fn project_stats(&self) -> Result<ProjectStats, String> {
+        let revision = self.engine.state().content_revision;
+        if let Some((cached, stats)) = self.stats_cache.lock().map_err(|_| "Lock error")?.as_ref() {
+            if *cached == revision {
+                return Ok(stats.clone());
+            }
+        }
+        let (revision, input) = self
+            .engine
+            .with(|eng| (eng.content_revision(), StatsInput::gather(eng)))
             .map_err(|e| e.to_string())?;
-            
-        if let Ok(mut g) = self.master_gain.lock() {
-            *g = new_master_gain;
+
+        let stats = input.compute();
+        *self.stats_cache.lock().map_err(|_| "Lock error")? = Some((revision, stats.clone()));
+        Ok(stats)
+    }
+
+    /// How many `project.json.N` backups each save keeps (0 disables them).
+    pub fn set_backup_count(&self, count: usize) {
+        if let Ok(mut session) = self.lock_session() {
+            session.backup_count = count;
         }
-        Ok(())
+    }
+
+    pub fn list_backups(&self, path: &str) -> Vec<crate::session::serialization::BackupInfo> {
+        crate::session::serialization::list_backups(path)
+    }
+
+    /// Replace the project file with backup `index`. Load the project afterwards to use it.
+    pub fn restore_backup(&self, path: &str, index: usize) -> Result<(), String> {
+        let keep = self.lock_session().map(|s| s.backup_count).unwrap_or(crate::session::serialization::DEFAULT_BACKUP_COUNT);
+        crate::session::serialization::restore_backup(path, index, keep).map_err(|e| e.to_string())
+    }
+
+    /// Loads a project. Returns how its saved audio settings were applied, if it had any.
+    pub fn load_project(&mut self, path: String) -> Result<Option<AppliedAudioPrefs>, String> {
+        let manifest = ProjectManifest::load_from_disk(&path).map_err(|e| e.to_string())?;
+
+        // Switch device/rate first so tracks are built at the final sample rate
+        let applied = match manifest.audio_prefs.as_ref() {
+            Some(prefs) => Some(self.apply_audio_prefs(prefs)),
+            None => {
+                if let Err(e) = self.clear_audio_prefs() {
+                    rt_warn!("⚠️ Could not return to the default audio settings: {}", e);
+                }
+                None
+            }
+        };
+
+        let mut session = self.lock_session().map_err(|_| "Lock error")?;
+        let new_master_gain = session.load_manifest(&self.engine, manifest)
+            .map_err(|e| e.to_string())?;
+        session.history.attach_to_project(&path);
+        drop(session);
+        self.sync_meter_registry();
+
+        self.master_gain.store(new_master_gain.to_bits(), Ordering::Relaxed);
+        Ok(applied)
     }
 
     pub fn export_project(&self, path: String) -> Result<(), String> {
+        self.export_project_with(path, crate::session::export::ExportOptions::default())
+    }
+
+    pub fn export_project_with(&self, path: String, options: crate::session::export::ExportOptions) -> Result<(), String> {
+        if self.bounce.lock().map_err(|_| "Lock error")?.as_ref().is_some_and(|b| b.path() == path) {
+            return Err(format!("{} is being written by a realtime bounce", path));
+        }
+        if !self.exporting.lock().map_err(|_| "Lock error")?.insert(path.clone()) {
+            return Err(format!("{} is already being exported", path));
+        }
+        let result = self.export_project_inner(&path, &options);
+        if let Ok(mut exporting) = self.exporting.lock() {
+            exporting.remove(&path);
+        }
+        result?;
+        self.log_event("Export", path, Vec::new());
+        Ok(())
+    }
+
+    fn export_project_inner(&self, path: &str, options: &crate::session::export::ExportOptions) -> Result<(), String> {
         // FIX: Rename session to _session to suppress unused variable warning
-        let _session = self.session.lock().map_err(|_| "Lock error")?;
-        let eng = self.engine.lock().unwrap();
+        let _session = self.lock_session().map_err(|_| "Lock error")?;
+        let manifest = self.engine.with(Self::export_manifest).map_err(|e| e.to_string())?;
+        crate::session::export::export_project_to_wav_with(&manifest, path, options)
+            .map_err(|e| e.to_string())
+    }
 
+    // What the offline export renders: the project as it stands, without markers or prefs
+    fn export_manifest(eng: &mut Engine) -> ProjectManifest {
         let tracks: Vec<crate::session::serialization::TrackState> = eng.tracks().iter().map(|t| {
             
             // 1. Map the clips first
             let clips = t.clips.iter().map(|c| crate::session::serialization::ClipState {
                 path: c.path.clone(),
-                start_time: c.start_time.as_secs_f64(),
-                offset: c.offset.as_secs_f64(),
-                duration: c.duration.as_secs_f64(),
+                start_time: c.start().as_secs_f64(),
+                offset: c.offset_time().as_secs_f64(),
+                duration: c.length().as_secs_f64(),
+                notes: c.notes.clone(),
+                mute_regions: c.mute_regions.clone(),
+                stretch: c.stretch(),
+                varispeed: c.varispeed(),
+                crossfade: c.crossfade.map(|d| d.as_secs_f64()),
             }).collect();
 
             // 2. Create the TrackState
@@ -826,18 +2575,328 @@ impl AudioRuntime {
                 compressor: Some(t.track_compressor.get_params()),
                 eq: Some(t.track_eq.get_state()),
                 reverb: Some(t.track_reverb.get_params()),
+                record_safe: t.record_safe,
+                record_inputs: t.record_inputs.clone(),
+                record_device: t.record_device.clone(),
+                solo_safe: t.solo_safe,
+                notes: t.notes.clone(),
+                fx_bypass: t.fx_bypass,
+                inserts: t.inserts.states(),
+                automation_mode: t.automation_mode,
+                kind: t.kind,
+                kind_manual: t.kind_manual,
+                sends: t.sends.iter().map(|s| crate::session::serialization::SendState { bus_id: s.bus_id, level: s.level }).collect(),
+                group: t.group,
             }
         }).collect();
 
-        let manifest = crate::session::serialization::ProjectManifest {
+        crate::session::serialization::ProjectManifest {
             version: 1,
             master_gain: eng.master_gain,
             bpm: eng.transport.tempo.bpm as f32,
             tracks,
-        };
+            audio_prefs: None,
+            solo_policy: eng.solo_policy,
+            solo_mode: eng.solo_mode,
+            arm_exclusive: eng.arm_exclusive,
+            return_to_start_on_stop: eng.return_to_start_on_stop,
+            preroll: eng.preroll,
+            end_action: eng.end_action,
+            master_pitch_shift: eng.master_pitch_shift(),
+            master_soft_clip: eng.master_soft_clip(),
+            master_limiter: eng.master_limiter(),
+            time_signature: eng.transport.tempo.signature,
+            meter_changes: eng.transport.tempo.meter_changes().to_vec(),
+            markers: Vec::new(),
+            aux_buses: eng.aux_buses().iter().map(crate::session::serialization::AuxBusState::from_bus).collect(),
+            groups: eng.groups().iter().map(crate::session::serialization::GroupState::from_group).collect(),
+        }
+    }
 
-        crate::session::export::export_project_to_wav(&manifest, &path)
-            .map_err(|e| e.to_string())
+    // --- REALTIME BOUNCE ---
+
+    /// Record the live master output to `path` from the current position while playback
+    /// carries on (starting it if needed), so live tweaks end up in the file.
+    pub fn start_realtime_bounce(&self, path: String, options: BounceOptions) -> Result<(), String> {
+        if let Some(active) = self.multitrack.lock().map_err(|_| "Lock error")?.as_ref() {
+            return Err(format!("A multitrack capture into {} is already running", active.dir()));
+        }
+        let mut bounce = self.bounce.lock().map_err(|_| "Lock error")?;
+        if let Some(active) = bounce.as_ref().filter(|b| !b.is_finished()) {
+            return Err(format!("A realtime bounce to {} is already running", active.path()));
+        }
+        if self.exporting.lock().map_err(|_| "Lock error")?.contains(&path) {
+            return Err(format!("{} is already being exported", path));
+        }
+        // A bounce that stopped by itself but was never collected is finalized already
+        if let Some(old) = bounce.take() {
+            let _ = old.stop();
+            self.engine.with(|eng| eng.clear_master_tap()).map_err(|e| e.to_string())?;
+        }
+
+        let (sample_rate, channels, max_frames) = self.free_master_format(options.stop_at_project_end, false)?;
+        // ~4 s of headroom for the writer thread
+        let capacity = sample_rate as usize * channels * 4;
+        let (tap, consumer, dropped) = crate::engine::tap::MasterTap::new(capacity);
+        let started = RealtimeBounce::start(path, sample_rate, channels, options, max_frames, consumer, dropped)
+            .map_err(|e| e.to_string())?;
+        let installed = self.engine.with(move |eng| -> Result<(), String> {
+            if eng.has_master_tap() {
+                return Err("The master output is already being captured".into());
+            }
+            eng.set_master_tap(tap);
+            // Straight from the playhead: a pre-roll would be printed too
+            if !eng.transport.playing {
+                eng.play();
+            }
+            Ok(())
+        });
+        match installed {
+            Ok(Ok(())) => {
+                *bounce = Some(started);
+                Ok(())
+            }
+            Ok(Err(e)) => {
+                let _ = started.stop();
+                Err(e)
+            }
+            Err(e) => {
+                let _ = started.stop();
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// Sample rate, channels and (with `to_project_end`) the frames left before the project
+    /// end, checking that the master isn't captured already.
+    fn free_master_format(&self, to_project_end: bool, needs_tracks: bool) -> Result<(u32, usize, Option<u64>), String> {
+        self.engine.with(move |eng| -> Result<_, String> {
+            if eng.has_master_tap() {
+                return Err("The master output is already being captured".into());
+            }
+            if needs_tracks && eng.tracks().is_empty() {
+                return Err("No tracks to print".into());
+            }
+            let max_frames = if to_project_end {
+                let remaining = eng.project_end().saturating_sub(eng.transport.time());
+                if remaining.is_zero() {
+                    return Err("Playhead is past the end of the project".into());
+                }
+                Some(Frames::from_duration(remaining, eng.sample_rate).0)
+            } else {
+                None
+            };
+            Ok((eng.sample_rate, eng.channels, max_frames))
+        }).map_err(|e| e.to_string())?
+    }
+
+    /// Finalize the running (or self-finished) bounce and report on the file.
+    pub fn stop_realtime_bounce(&self) -> Result<BounceResult, String> {
+        let bounce = self.bounce.lock().map_err(|_| "Lock error")?.take().ok_or("No realtime bounce is running")?;
+        let _ = self.engine.post(move |eng| eng.clear_master_tap());
+        let result = bounce.stop().map_err(|e| e.to_string())?;
+        self.log_event("Realtime Bounce", result.path.clone(), Vec::new());
+        Ok(result)
+    }
+
+    /// `Some(finished)` while a bounce exists; `finished` flips once it hit the project end.
+    pub fn realtime_bounce_status(&self) -> Option<bool> {
+        self.bounce.lock().ok()?.as_ref().map(RealtimeBounce::is_finished)
+    }
+
+    // --- LOOPBACK RECORDING ---
+
+    /// Record what the master plays (after every effect and the master gain, including the
+    /// monitored input but not the click) to `path` as a take. Only rolling blocks are
+    /// written; unlike a realtime bounce it neither starts playback nor ends at the project
+    /// end, and the take belongs at the playhead it was started from.
+    pub fn start_loopback_recording(&self, path: String) -> Result<(), String> {
+        let mut loopback = self.loopback.lock().map_err(|_| "Lock error")?;
+        if let Some(active) = loopback.as_ref() {
+            return Err(format!("The master output is already being recorded to {}", active.bounce.path()));
+        }
+        if self.exporting.lock().map_err(|_| "Lock error")?.contains(&path) {
+            return Err(format!("{} is already being exported", path));
+        }
+
+        let (sample_rate, channels, _) = self.free_master_format(false, false)?;
+        // ~4 s of headroom for the writer thread
+        let capacity = sample_rate as usize * channels * 4;
+        let (tap, consumer, dropped) = crate::engine::tap::MasterTap::new(capacity);
+        let options = BounceOptions { stop_at_project_end: false, ..BounceOptions::default() };
+        let bounce = RealtimeBounce::start(path, sample_rate, channels, options, None, consumer, dropped)
+            .map_err(|e| e.to_string())?;
+        let installed = self.engine.with(move |eng| -> Result<f64, String> {
+            if eng.has_master_tap() {
+                return Err("The master output is already being captured".into());
+            }
+            eng.set_master_tap(tap);
+            Ok(eng.transport.time().as_secs_f64())
+        });
+        match installed {
+            Ok(Ok(start_time)) => {
+                *loopback = Some(LoopbackTake { bounce, start_time, channels });
+                Ok(())
+            }
+            Ok(Err(e)) => {
+                let _ = bounce.stop();
+                Err(e)
+            }
+            Err(e) => {
+                let _ = bounce.stop();
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// Finalize the loopback take. The result has no track yet and no latency to skip: the
+    /// master is captured exactly as rendered.
+    pub fn stop_loopback_recording(&self) -> Result<crate::recorder::RecordingResult, String> {
+        let take = self.loopback.lock().map_err(|_| "Lock error")?.take().ok_or("The master output is not being recorded")?;
+        let _ = self.engine.post(move |eng| eng.clear_master_tap());
+        let result = take.bounce.stop().map_err(|e| e.to_string())?;
+        Ok(crate::recorder::RecordingResult {
+            track_id: None,
+            path: result.path,
+            channels: take.channels,
+            sample_rate: result.sample_rate,
+            duration: result.duration,
+            start_time: take.start_time,
+            offset: 0.0,
+            gaps: Vec::new(),
+            dropped_frames: result.dropped_samples / take.channels.max(1) as u64,
+        })
+    }
+
+    pub fn is_loopback_recording(&self) -> bool {
+        self.loopback.lock().map(|l| l.is_some()).unwrap_or(false)
+    }
+
+    // --- MULTITRACK PRINT ---
+
+    /// Like a realtime bounce, but every track is also printed (post-fader, post-effects)
+    /// to `{dir}/{track}.wav`, next to the master. All files start on the same frame.
+    pub fn start_multitrack_capture(&self, dir: String, options: BounceOptions) -> Result<(), String> {
+        let mut multitrack = self.multitrack.lock().map_err(|_| "Lock error")?;
+        if let Some(active) = multitrack.as_ref().filter(|m| !m.is_finished()) {
+            return Err(format!("A multitrack capture into {} is already running", active.dir()));
+        }
+        if let Some(old) = multitrack.take() {
+            let _ = old.stop();
+            self.engine
+                .with(|eng| {
+                    eng.clear_master_tap();
+                    eng.clear_track_taps();
+                })
+                .map_err(|e| e.to_string())?;
+        }
+        // The master half needs the master tap a standalone bounce would be holding
+        let mut bounce = self.bounce.lock().map_err(|_| "Lock error")?;
+        if let Some(active) = bounce.as_ref().filter(|b| !b.is_finished()) {
+            return Err(format!("A realtime bounce to {} is already running", active.path()));
+        }
+        if let Some(old) = bounce.take() {
+            let _ = old.stop();
+            self.engine.with(|eng| eng.clear_master_tap()).map_err(|e| e.to_string())?;
+        }
+        drop(bounce);
+
+        let (sample_rate, channels, max_frames) = self.free_master_format(options.stop_at_project_end, true)?;
+        let tracks = self
+            .engine
+            .with(|eng| eng.tracks().iter().map(|t| (t.id, t.name.clone())).collect::<Vec<_>>())
+            .map_err(|e| e.to_string())?;
+
+        // ~4 s of headroom per writer thread
+        let capacity = sample_rate as usize * channels * 4;
+        let mut taps = Vec::with_capacity(tracks.len());
+        let mut feeds = Vec::with_capacity(tracks.len());
+        for (track_id, name) in tracks {
+            let (tap, consumer, dropped) = crate::engine::tap::MasterTap::new(capacity);
+            taps.push((track_id, tap));
+            feeds.push(TrackFeed { track_id: track_id.0, name, consumer, dropped });
+        }
+        let (master_tap, master_consumer, master_dropped) = crate::engine::tap::MasterTap::new(capacity);
+        let started = MultitrackCapture::start(
+            dir, sample_rate, channels, options, max_frames, master_consumer, master_dropped, feeds,
+        )
+        .map_err(|e| e.to_string())?;
+
+        // Installed in one edit, so every tap sees the same first block
+        let installed = self.engine.with(move |eng| -> Result<(), String> {
+            if eng.has_master_tap() {
+                return Err("The master output is already being captured".into());
+            }
+            eng.set_master_tap(master_tap);
+            eng.set_track_taps(taps);
+            // Straight from the playhead: a pre-roll would be printed too
+            if !eng.transport.playing {
+                eng.play();
+            }
+            Ok(())
+        });
+        match installed {
+            Ok(Ok(())) => {
+                *multitrack = Some(started);
+                Ok(())
+            }
+            Ok(Err(e)) => {
+                let _ = started.stop();
+                Err(e)
+            }
+            Err(e) => {
+                let _ = started.stop();
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// Finalize every file of the running (or self-finished) capture. Per-track drop counts
+    /// are in the result.
+    pub fn stop_multitrack_capture(&self) -> Result<MultitrackResult, String> {
+        let capture = self.multitrack.lock().map_err(|_| "Lock error")?.take().ok_or("No multitrack capture is running")?;
+        let _ = self.engine.post(|eng| {
+            eng.clear_master_tap();
+            eng.clear_track_taps();
+        });
+        let result = capture.stop().map_err(|e| e.to_string())?;
+        self.log_event("Multitrack Print", format!("{} tracks into {}", result.tracks.len(), result.dir), Vec::new());
+        Ok(result)
+    }
+
+    /// `Some((dir, tracks, finished))` while a capture exists.
+    pub fn multitrack_capture_status(&self) -> Option<(String, usize, bool)> {
+        let guard = self.multitrack.lock().ok()?;
+        guard.as_ref().map(|m| (m.dir().to_string(), m.track_count(), m.is_finished()))
+    }
+
+    // --- EDIT HISTORY ---
+
+    fn lock_session(&self) -> Result<SessionGuard<'_>, std::sync::PoisonError<std::sync::MutexGuard<'_, Session>>> {
+        self.session.lock().map(|session| SessionGuard(Some(session)))
+    }
+
+    /// Log a non-undoable action (import, export, ...) to the edit history.
+    pub fn log_event(&self, action: &str, details: String, track_ids: Vec<u32>) {
+        if let Ok(mut session) = self.lock_session() {
+            session.history.record(action, details, track_ids);
+        }
+    }
+
+    /// Newest first.
+    pub fn edit_history(&self, limit: usize, offset: usize) -> Vec<HistoryEntry> {
+        self.lock_session().map(|s| s.history.page(limit, offset)).unwrap_or_default()
+    }
+
+    /// Called (on the editing thread) for every new history entry, after the session lock
+    /// is released, so the listener may call back into the runtime.
+    pub fn set_history_listener<F>(&self, listener: F)
+    where
+        F: Fn(&HistoryEntry) + Send + Sync + 'static,
+    {
+        if let Ok(mut session) = self.lock_session() {
+            session.history.set_listener(Arc::new(listener));
+        }
     }
 
     // --- COMPATIBILITY WRAPPERS (For daw_controller.rs) ---
@@ -846,29 +2905,29 @@ impl AudioRuntime {
         self.save_project(path).map_err(|e| anyhow::anyhow!(e))
     }
 
-    pub fn load_session(&self, path: String) -> anyhow::Result<()> {
-        self.load_project(path).map_err(|e| anyhow::anyhow!(e))
+    pub fn load_session(&mut self, path: String) -> anyhow::Result<()> {
+        self.load_project(path).map(|_| ()).map_err(|e| anyhow::anyhow!(e))
     }
 
     pub fn get_tracks_list(&self) -> Vec<FrontendTrackInfo> {
-        if let Ok(eng) = self.engine.lock() {
-
-            // --- ADDED: Sync the registry quietly whenever the UI asks for track data ---
-            if let Ok(mut reg) = self.meter_registry.lock() {
-                reg.clear();
-                for t in eng.tracks() {
-                    reg.insert(t.id.0, t.meters.clone());
-                }
-            }
+        // --- ADDED: Sync the registry quietly whenever the UI asks for track data ---
+        self.sync_meter_registry();
 
+        self.engine.with(|eng| {
             eng.tracks().iter().map(|t| {
                 // Map the clips
-                let clips = t.clips.iter().map(|c| FrontendClipInfo {
+                let spans: Vec<_> = t.clips.iter().map(|c| c.span()).collect();
+                let clips = t.clips.iter().enumerate().map(|(i, c)| FrontendClipInfo {
                     path: c.path.clone(),
-                    start_time: c.start_time.as_secs_f64(),
-                    duration: c.duration.as_secs_f64(),
-                    offset: c.offset.as_secs_f64(),
+                    start_time: c.start().as_secs_f64(),
+                    duration: c.length().as_secs_f64(),
+                    offset: c.offset_time().as_secs_f64(),
                     clip_number: c.clip_number, // <--- NEW
+                    notes: c.notes.clone(),
+                    mute_regions: c.mute_regions.clone(),
+                    stretch: c.stretch(),
+                    varispeed: c.varispeed(),
+                    crossfade: crate::engine::crossfades::crossfade_in(&spans, i).map(|d| d.as_secs_f64()),
                 }).collect();
 
                 FrontendTrackInfo {
@@ -879,10 +2938,21 @@ impl AudioRuntime {
                     pan: t.pan,
                     muted: t.muted,
                     solo: t.solo,
+                    armed: t.armed,
+                    record_safe: t.record_safe,
+                    solo_safe: t.solo_safe,
+                    notes: t.notes.clone(),
+                    listen: t.listen,
+                    fx_bypass: t.fx_bypass,
+                    kind: t.kind,
+                    kind_manual: t.kind_manual,
                     clips, // <--- Add the clips here
                     compressor: Some(t.track_compressor.get_params()),
                     eq: Some(t.track_eq.get_state()),
                     reverb: Some(t.track_reverb.get_params()),
+                    sends: t.sends.clone(),
+                    group: t.group,
+                    frozen: t.is_frozen(),
                     // Convert stored dB values into Linear values (0.0 to ~2.0) for Svelte UI rendering
                     volume_automation: t.volume_automation.nodes().iter().map(|n| crate::engine::automation::AutomationNode {
                         time: n.time,
@@ -890,14 +2960,13 @@ impl AudioRuntime {
                     }).collect(),
                 }
             }).collect()
-        } else {
-            Vec::new()
-        }
-    }       
+        }).unwrap_or_default()
+    }
 
     // --- DEBUG ---
     pub fn debug_snapshot(&self) -> Option<EngineSnapshot> {
-        if let Ok(eng) = self.engine.lock() {
+        self.engine.with(|eng| {
+            let any_solo = eng.any_solo();
             let tracks = eng
                 .tracks()
                 .iter()
@@ -906,52 +2975,79 @@ impl AudioRuntime {
                     pan: t.pan,
                     muted: t.muted,
                     solo: t.solo,
+                    audible: eng.is_track_audible(t, any_solo),
+                    armed: t.armed,
+                    record_safe: t.record_safe,
+                    solo_safe: t.solo_safe,
+                    listen: t.listen,
                 })
                 .collect();
-            Some(EngineSnapshot { tracks })
-        } else {
-            None
-        }
+            EngineSnapshot { tracks }
+        }).ok()
+    }
+
+    /// Set (or clear) a track's kind by hand. The classifier never touches it afterwards.
+    pub fn set_track_kind(&self, track_id: u32, kind: Option<crate::engine::track::TrackKind>) -> Result<(), String> {
+        self.engine.with(move |eng| -> Result<(), String> {
+            let track = eng.tracks_mut().iter_mut().find(|t| t.id.0 == track_id).ok_or("Track not found")?;
+            track.kind = kind;
+            track.kind_manual = true;
+            Ok(())
+        }).map_err(|e| e.to_string())?
+    }
+
+    /// Store a classifier guess. Returns false (and changes nothing) when the user already
+    /// picked a kind for the track or the track is gone.
+    pub fn apply_detected_kind(&self, track_id: u32, kind: crate::engine::track::TrackKind) -> bool {
+        self.engine
+            .with(move |eng| match eng.tracks_mut().iter_mut().find(|t| t.id.0 == track_id) {
+                Some(track) if !track.kind_manual => {
+                    track.kind = Some(kind);
+                    true
+                }
+                _ => false,
+            })
+            .unwrap_or(false)
     }
 
     pub fn set_track_name(&self, track_index: usize, name: String) {
-        if let Ok(mut eng) = self.engine.lock() {
+        let _ = self.engine.post(move |eng| {
             if let Some(track) = eng.tracks_mut().get_mut(track_index) {
                 track.name = name;
             }
-        }
+        });
     }
 
     
     // UPDATED: Now takes 'track_id: u32' instead of index
     pub fn set_clip_duration(&self, track_id: u32, duration: f64) -> Result<(), String> {
-        if let Ok(mut eng) = self.engine.lock() {
+        self.engine.with(move |eng| {
             // Iterate to find the track with the matching ID
             if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id.0 == track_id) {
                 if let Some(clip) = track.clips.first_mut() {
-                    clip.duration = Duration::from_secs_f64(duration);
+                    let duration = Duration::from_secs_f64(duration.max(0.0));
+                    crate::engine::track::ClipEditError::check_duration(duration).map_err(|e| e.to_string())?;
+                    clip.duration = Frames::from_duration(duration, clip.frame_rate());
                     return Ok(());
                 } else {
                     return Err(format!("Track {} exists but has no clips (Empty Track)", track_id));
                 }
             }
-            return Err(format!("Track ID {} not found", track_id));
-        }
-        Err("Failed to lock engine".to_string())
+            Err(format!("Track ID {} not found", track_id))
+        }).map_err(|e| e.to_string())?
     }
 
     pub fn get_volume_automation(&self, track_id: u32) -> Result<Vec<crate::engine::automation::AutomationNode<f32>>, String> {
-        if let Ok(eng) = self.engine.lock() {
+        self.engine.with(move |eng| {
             if let Some(track) = eng.tracks().iter().find(|t| t.id.0 == track_id) {
                 return Ok(track.volume_automation.nodes().to_vec());
             }
-            return Err(format!("Track ID {} not found", track_id));
-        }
-        Err("Failed to lock engine".to_string())
+            Err(format!("Track ID {} not found", track_id))
+        }).map_err(|e| e.to_string())?
     }
 
     pub fn add_volume_automation_node(&self, track_id: u32, time: u64, value: f32) -> Result<(), String> {
-        if let Ok(mut eng) = self.engine.lock() {
+        self.engine.with(move |eng| {
             let sample_rate = eng.sample_rate as f64; // Grab SR for time calculations
             
             if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id.0 == track_id) {
@@ -965,12 +3061,8 @@ impl AudioRuntime {
                     
                     // 2. Find the absolute end of the track's audio to place the End Anchor
                     let mut max_time_secs = 600.0; // Fallback: 10 minutes if track is empty
-                    if let Some(last_clip) = track.clips.iter().max_by(|a, b| {
-                        let a_end = a.start_time + a.duration;
-                        let b_end = b.start_time + b.duration;
-                        a_end.cmp(&b_end)
-                    }) {
-                        max_time_secs = (last_clip.start_time + last_clip.duration).as_secs_f64();
+                    if let Some(end) = track.clips.iter().map(|c| c.end()).max() {
+                        max_time_secs = end.as_secs_f64();
                     }
                     
                     // Place the end anchor 60 seconds past the last clip to ensure the UI line stays flat to the right
@@ -980,7 +3072,7 @@ impl AudioRuntime {
 
                 // --- DEBUG LOG ---
                 let time_sec = time as f64 / sample_rate;
-                println!("🎚️ Engine Stored Node -> Track: {} | Time: {:.3}s (Sample: {}) | Value: {:.2} dB", 
+                rt_info!("🎚️ Engine Stored Node -> Track: {} | Time: {:.3}s (Sample: {}) | Value: {:.2} dB", 
                     track_id, time_sec, time, value
                 );
                 // -----------------
@@ -990,31 +3082,41 @@ impl AudioRuntime {
                 
                 return Ok(());
             }
-            return Err(format!("Track ID {} not found", track_id));
-        }
-        Err("Failed to lock engine".to_string())
+            Err(format!("Track ID {} not found", track_id))
+        }).map_err(|e| e.to_string())?
     }
 
     pub fn remove_volume_automation_node(&self, track_id: u32, time: u64) -> Result<(), String> {
-        if let Ok(mut eng) = self.engine.lock() {
+        self.engine.with(move |eng| {
             if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id.0 == track_id) {
                 track.volume_automation.remove_node_at_time(time);
                 return Ok(());
             }
-            return Err(format!("Track ID {} not found", track_id));
-        }
-        Err("Failed to lock engine".to_string())
+            Err(format!("Track ID {} not found", track_id))
+        }).map_err(|e| e.to_string())?
     }
 
     pub fn clear_volume_automation(&self, track_id: u32) -> Result<(), String> {
-        if let Ok(mut eng) = self.engine.lock() {
+        self.engine.with(move |eng| {
             if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id.0 == track_id) {
                 track.volume_automation.clear();
                 return Ok(());
             }
-            return Err(format!("Track ID {} not found", track_id));
+            Err(format!("Track ID {} not found", track_id))
+        }).map_err(|e| e.to_string())?
+    }
+
+    // Point the meter registry at the engine's current tracks (after tracks come or go)
+    fn sync_meter_registry(&self) {
+        let Ok(meters) = self.engine.with(|eng| {
+            eng.tracks().iter().map(|t| (t.id.0, t.meters.clone())).collect::<Vec<_>>()
+        }) else {
+            return;
+        };
+        if let Ok(mut reg) = self.meter_registry.lock() {
+            reg.clear();
+            reg.extend(meters);
         }
-        Err("Failed to lock engine".to_string())
     }
 
     // --- ADD NEW METHOD TO AUDIORUNTIME ---
@@ -1045,20 +3147,22 @@ impl AudioRuntime {
     // --- NEW: Expose offline analysis data for AI ---
     pub fn get_all_track_analysis(&self) -> Vec<TrackAnalysisPayload> {
         let mut results = Vec::new();
-        if let Ok(eng) = self.engine.lock() {
-            for t in eng.tracks() {
-                // Safely lock the analysis profile. If it's still computing, it will return None.
-                let profile = if let Ok(guard) = t.analysis.lock() {
-                    guard.clone()
-                } else {
-                    None
-                };
-                
-                results.push(TrackAnalysisPayload {
-                    track_id: t.id.0,
-                    analysis: profile,
-                });
-            }
+        let tracks = self
+            .engine
+            .with(|eng| eng.tracks().iter().map(|t| (t.id.0, t.analysis.clone())).collect::<Vec<_>>())
+            .unwrap_or_default();
+        for (track_id, analysis) in tracks {
+            // Safely lock the analysis profile. If it's still computing, it will return None.
+            let profile = if let Ok(guard) = analysis.lock() {
+                guard.clone()
+            } else {
+                None
+            };
+            
+            results.push(TrackAnalysisPayload {
+                track_id,
+                analysis: profile,
+            });
         }
         results
     }
@@ -1142,11 +3246,11 @@ impl AudioRuntime {
                         };
                         
                         // FIX: Apply directly to engine to prevent UI race condition
-                        if let Ok(mut engine) = self.engine.lock() {
+                        self.engine.with(move |engine| {
                             if let Some(track) = engine.tracks_mut().get_mut(idx) {
                                 track.track_eq.update_band(band_index, params);
                             }
-                        }
+                        })?;
                     }
                 },
                 AiAction::UpdateCompressor { track_id, threshold_db, ratio, attack_ms, release_ms, makeup_gain_db, is_active } => {
@@ -1158,20 +3262,21 @@ impl AudioRuntime {
                             attack_ms: attack_ms.clamp(0.1, 200.0),
                             release_ms: release_ms.clamp(10.0, 1000.0),
                             makeup_gain_db: makeup_gain_db.clamp(0.0, 24.0),
+                            ..Default::default()
                         };
                         
                         // FIX: Apply directly to engine
-                        if let Ok(mut engine) = self.engine.lock() {
+                        self.engine.with(move |engine| {
                             if let Some(track) = engine.tracks_mut().get_mut(idx) {
                                 track.track_compressor.set_params(params);
                             }
-                        }
+                        })?;
                     }
                 },
                 AiAction::UpdateReverb { track_id, room_size, damping, pre_delay_ms, mix, width, low_cut_hz, high_cut_hz, is_active } => {
                     if let Some(idx) = resolve(track_id) {
                         // FIX: Apply directly to engine using the batch setter
-                        if let Ok(mut engine) = self.engine.lock() {
+                        self.engine.with(move |engine| {
                             if let Some(track) = engine.tracks_mut().get_mut(idx) {
                                 let mut p = track.track_reverb.get_params();
                                 if let Some(v) = is_active { p.is_active = v; }
@@ -1185,7 +3290,7 @@ impl AudioRuntime {
                                 
                                 track.track_reverb.set_params(p);
                             }
-                        }
+                        })?;
                     }
                 },
                 AiAction::ClearVolumeAutomation { track_id } => {
@@ -1219,12 +3324,14 @@ impl AudioRuntime {
 
                 AiAction::AutoGainStage { track_id, target_lufs } => {
                     if let Some(idx) = resolve(track_id) {
-                        if let Ok(mut engine) = self.engine.lock() {
+                        // 1. Pull the exact LUFS from the background analyzer
+                        let analysis = self.engine.with(move |engine| engine.tracks().get(idx).map(|t| t.analysis.clone()))?;
+                        let current_lufs = analysis.and_then(|analysis| match analysis.lock() {
+                            Ok(guard) => guard.as_ref().map(|p| p.integrated_loudness_db),
+                            Err(_) => None,
+                        });
+                        self.engine.with(move |engine| {
                             if let Some(track) = engine.tracks_mut().get_mut(idx) {
-                                // 1. Pull the exact LUFS from the background analyzer
-                                let current_lufs = if let Ok(guard) = track.analysis.lock() {
-                                    guard.as_ref().map(|p| p.integrated_loudness_db)
-                                } else { None };
 
                                 // 2. Perform the Math
                                 if let Some(lufs) = current_lufs {
@@ -1235,13 +3342,13 @@ impl AudioRuntime {
                                     let new_gain = (track.gain * linear_multiplier).clamp(0.0, 2.0);
                                     
                                     track.gain = new_gain;
-                                    println!("🎚️ Auto-Gain Stage [Track {}]: {} LUFS -> {} LUFS | Delta: {:.2} dB | New Linear Fader: {:.3}", 
+                                    rt_info!("🎚️ Auto-Gain Stage [Track {}]: {} LUFS -> {} LUFS | Delta: {:.2} dB | New Linear Fader: {:.3}", 
                                         track_id, lufs, target_lufs, delta_db, new_gain);
                                 } else {
-                                    println!("⚠️ Auto-Gain Stage failed: No analysis profile ready yet for Track {}.", track_id);
+                                    rt_warn!("⚠️ Auto-Gain Stage failed: No analysis profile ready yet for Track {}.", track_id);
                                 }
                             }
-                        }
+                        })?;
                     }
                 },
 
@@ -1254,98 +3361,77 @@ impl AudioRuntime {
 
                     let engine_sample_rate = self.sample_rate();
 
-                    if let Ok(mut engine) = self.engine.lock() {
-                        // FIX 1: Match t.id.0 against track_id as u32
-                        if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id.0 == track_id as u32) {
-                            let mut all_rider_nodes = Vec::new();
-
-                            // FIX 2: Extract ALL temporal metadata (start_time, offset, duration)
-                            let clips_meta: Vec<(String, f64, f64, f64)> = track.clips.iter().map(|c| {
+                    // FIX 1: Match t.id.0 against track_id as u32
+                    // FIX 2: Extract ALL temporal metadata (start_time, offset, duration)
+                    let clips_meta = self.engine.with(move |engine| {
+                        engine.tracks().iter().find(|t| t.id.0 == track_id as u32).map(|track| {
+                            track.clips.iter().map(|c| {
                                 (
                                     c.path.clone(), 
-                                    c.start_time.as_secs_f64(), 
-                                    c.offset.as_secs_f64(), 
-                                    c.duration.as_secs_f64()
+                                    c.start().as_secs_f64(), 
+                                    c.offset_time().as_secs_f64(), 
+                                    c.length().as_secs_f64()
                                 )
-                            }).collect();
+                            }).collect::<Vec<(String, f64, f64, f64)>>()
+                        })
+                    })?;
+                    // Decoding and analysis happen here; the engine only receives the curve
+                    if let Some(clips_meta) = clips_meta {
+                            let mut all_rider_nodes = Vec::new();
 
                            for (path, start_time_sec, offset_sec, duration_sec) in clips_meta {
                                 
-                                // --- AUDIO CACHE CHECK ---
-                                let cache_result = {
-                                    let mut cache = self.decode_cache.lock().unwrap();
-                                    if let Some(cached) = cache.get(&path) {
-                                        Some((cached.0.clone(), cached.1, cached.2)) // Grab Arc clone
-                                    } else {
-                                        // Decode and cache if not found
-                                        if let Ok((data, sr, ch)) = crate::bpm::adapter::decode_to_vec(&path) {
-                                            let data_arc = Arc::new(data);
-                                            cache.insert(path.clone(), (data_arc.clone(), sr, ch));
-                                            Some((data_arc, sr, ch))
-                                        } else {
-                                            None
-                                        }
-                                    }
-                                };
-
-                                if let Some((audio_data, source_sr, source_ch)) = cache_result {
-
-                                    // --- THE TIME DOMAIN FIX ---
-                                    // The file starts at 0, but the clip might be trimmed (offset) and cropped (duration).
-                                    // We MUST slice the raw audio buffer so the AI only analyzes what is visible on the timeline!
-                                    let start_sample = (offset_sec * source_sr as f64).round() as usize * source_ch;
-                                    let len_samples = (duration_sec * source_sr as f64).round() as usize * source_ch;
-                                    let end_sample = (start_sample + len_samples).min(audio_data.len());
-                                    
-                                    let sliced_audio = if start_sample < audio_data.len() {
-                                        &audio_data[start_sample..end_sample]
-                                    } else {
-                                        &[] // Prevent panics if offset is completely out of bounds
-                                    };
-
-                                    let mut clip_nodes = crate::engine::automation::generate_rider_automation(
-                                        sliced_audio, 
-                                        source_ch, 
-                                        source_sr, // Process at native sample rate for accurate RMS
-                                        start_time_sec,
-                                        target_lufs, 
-                                        boost,
-                                        cut,
-                                        smooth,
-                                        window,
-                                        gate_threshold
-                                    );
-
-                                    // Because the clip might be 44.1kHz but the engine is 48kHz,
-                                    // we align the generated node timestamps to the engine's actual sample rate.
-                                    let sample_rate_ratio = engine_sample_rate as f64 / source_sr as f64;
-                                    for node in clip_nodes.iter_mut() {
-                                        node.time = (node.time as f64 * sample_rate_ratio).round() as u64;
-                                    }
-
-                                    all_rider_nodes.append(&mut clip_nodes);
+                                // --- THE TIME DOMAIN FIX ---
+                                // The file starts at 0, but the clip might be trimmed (offset) and cropped (duration).
+                                // Decode exactly what is visible on the timeline, however far into a long file it sits
+                                // (the shared analysis cache only holds the start of long files)
+                                let (sliced_audio, source_sr, source_ch) = crate::bpm::adapter::decode_range(&path, offset_sec, duration_sec)
+                                    .map_err(|e| anyhow::anyhow!("Vocal rider could not decode {}: {}", path, e))?;
+                                let mut clip_nodes = crate::engine::automation::generate_rider_automation(
+                                    &sliced_audio, 
+                                    source_ch, 
+                                    source_sr, // Process at native sample rate for accurate RMS
+                                    start_time_sec,
+                                    target_lufs, 
+                                    boost,
+                                    cut,
+                                    smooth,
+                                    window,
+                                    gate_threshold
+                                );
+
+                                // Because the clip might be 44.1kHz but the engine is 48kHz,
+                                // we align the generated node timestamps to the engine's actual sample rate.
+                                let sample_rate_ratio = engine_sample_rate as f64 / source_sr as f64;
+                                for node in clip_nodes.iter_mut() {
+                                    node.time = (node.time as f64 * sample_rate_ratio).round() as u64;
                                 }
+
+                                all_rider_nodes.append(&mut clip_nodes);
                             }
 
                             // ==========================================
                             // 📍 INSERT DEBUG BLOCK HERE 📍
                             // ==========================================
-                            println!("--- VOCAL RIDER DEBUG ---");
-                            println!("Engine Sample Rate: {}", engine_sample_rate);
+                            rt_debug!("--- VOCAL RIDER DEBUG ---");
+                            rt_debug!("Engine Sample Rate: {}", engine_sample_rate);
                             for (i, node) in all_rider_nodes.iter().enumerate().take(10) {
                                 let time_in_seconds = node.time as f64 / engine_sample_rate as f64;
-                                println!("Node {}: Time: {:.3}s | Gain: {:.2} dB", i, time_in_seconds, node.value);
+                                rt_debug!("Node {}: Time: {:.3}s | Gain: {:.2} dB", i, time_in_seconds, node.value);
                             }
-                            println!("-------------------------");
+                            rt_debug!("-------------------------");
                             // ==========================================
 
                             // FIX 5: Use the safe, public AutomationCurve API. 
                             // insert_node handles binary-search sorting automatically!
-                            track.volume_automation.clear();
-                            for node in all_rider_nodes {
-                                track.volume_automation.insert_node(node.time, node.value);
-                            }
-                        }
+                            self.engine.with(move |engine| {
+                                if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id.0 == track_id as u32) {
+                                    track.volume_automation.clear();
+                                    for node in all_rider_nodes {
+                                        track.volume_automation.insert_node(node.time, node.value);
+                                    }
+                                }
+                            })?;
                     }
                 },
                 AiAction::SeparateStems { .. } => {
@@ -1359,4 +3445,276 @@ impl AudioRuntime {
         Ok(())
     }
 
-}
\ No newline at end of file
+}
+
+fn supports_sample_rate(device: &cpal::Device, channels: u16, rate: u32) -> bool {
+    device
+        .supported_output_configs()
+        .map(|mut configs| {
+            configs.any(|c| c.channels() == channels && c.min_sample_rate().0 <= rate && rate <= c.max_sample_rate().0)
+        })
+        .unwrap_or(false)
+}
+
+/// Widest channel layout the device offers at `rate`.
+fn max_output_channels(device: &cpal::Device, rate: u32) -> Option<u16> {
+    device
+        .supported_output_configs()
+        .ok()?
+        .filter(|c| c.min_sample_rate().0 <= rate && rate <= c.max_sample_rate().0)
+        .filter(|c| c.sample_format() == cpal::SampleFormat::F32)
+        .map(|c| c.channels())
+        .max()
+}
+
+fn supports_buffer_size(device: &cpal::Device, frames: u32) -> bool {
+    match device.default_output_config().map(|c| *c.buffer_size()) {
+        Ok(cpal::SupportedBufferSize::Range { min, max }) => (min..=max).contains(&frames),
+        Ok(cpal::SupportedBufferSize::Unknown) => true,
+        Err(_) => false,
+    }
+}
+
+// Marker position from the UI, in seconds
+fn marker_time(secs: f64) -> anyhow::Result<Duration> {
+    if !secs.is_finite() || secs < 0.0 {
+        return Err(anyhow::anyhow!("Invalid marker time {}", secs));
+    }
+    Ok(Duration::from_secs_f64(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest_file(name: &str, audio_prefs: Option<AudioPrefs>) -> String {
+        let path = crate::test_util::temp_path(&format!("runtime_{}", name)).with_extension("json");
+        let mut manifest: ProjectManifest =
+            serde_json::from_value(serde_json::json!({ "version": 1, "master_gain": 1.0, "bpm": 120.0, "tracks": [] })).unwrap();
+        manifest.audio_prefs = audio_prefs;
+        manifest.save_to_disk_with_backups(&path.to_string_lossy(), 0).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn loading_a_project_without_prefs_drops_the_last_overrides() {
+        let mut runtime = AudioRuntime::new(None).unwrap();
+        runtime.requested_sample_rate = Some(96_000);
+        runtime.requested_buffer_size = Some(64);
+        runtime.requested_block_size = Some(128);
+
+        let path = manifest_file("no_prefs", None);
+        let applied = runtime.load_project(path.clone()).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(applied.is_none());
+        assert_eq!(runtime.requested_sample_rate, None);
+        assert_eq!(runtime.requested_buffer_size, None);
+        assert_eq!(runtime.requested_block_size, None);
+    }
+
+    #[test]
+    fn loading_a_project_with_prefs_reports_each_one() {
+        let mut runtime = AudioRuntime::new(None).unwrap();
+        let prefs = AudioPrefs { sample_rate: Some(48_000), buffer_size: Some(256), output_device: None, block_size: Some(256) };
+
+        let path = manifest_file("prefs", Some(prefs.clone()));
+        let applied = runtime.load_project(path.clone()).unwrap().unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(applied.requested, prefs);
+        // Whatever the hardware, every preference is either honored or explained
+        if applied.actual.sample_rate != Some(48_000) {
+            assert!(!applied.mismatches.is_empty());
+        }
+        if applied.actual.block_size != Some(256) {
+            assert!(!applied.mismatches.is_empty());
+        }
+    }
+
+    #[test]
+    fn clearing_prefs_without_overrides_leaves_the_stream_alone() {
+        let mut runtime = AudioRuntime::new(None).unwrap();
+        let before = runtime.audio_prefs();
+        runtime.clear_audio_prefs().unwrap();
+        assert_eq!(runtime.audio_prefs(), before);
+    }
+
+    #[test]
+    fn recording_skips_record_safe_tracks_the_frontend_sent() {
+        let runtime = AudioRuntime::new(None).unwrap();
+        runtime.create_empty_track().unwrap();
+        runtime.create_empty_track().unwrap();
+        let ids: Vec<u32> = runtime.get_tracks_list().iter().map(|t| t.id).collect();
+        runtime.set_record_safe(1, true).unwrap();
+
+        let target = |track_id: Option<u32>| crate::recorder::RecordTarget {
+            track_id,
+            path: std::path::PathBuf::from(format!("take_{:?}.wav", track_id)),
+            input_channels: vec![0],
+            device: None,
+        };
+
+        let kept = runtime.recordable_targets(vec![target(Some(ids[0])), target(Some(ids[1])), target(None)]).unwrap();
+        let kept: Vec<Option<u32>> = kept.iter().map(|t| t.track_id).collect();
+        assert_eq!(kept, vec![Some(ids[0]), None]);
+
+        assert!(runtime.recordable_targets(vec![target(Some(ids[1]))]).is_err());
+        assert!(runtime.recordable_targets(Vec::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn history_listener_runs_once_the_session_is_unlocked() {
+        let session = Arc::new(Mutex::new(Session::new()));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (probe, sink) = (session.clone(), seen.clone());
+        session.lock().unwrap().history.set_listener(Arc::new(move |entry: &HistoryEntry| {
+            sink.lock().unwrap().push((entry.action.clone(), probe.try_lock().is_ok()));
+        }));
+
+        {
+            let mut guard = SessionGuard(Some(session.lock().unwrap()));
+            guard.history.record("Export", String::new(), Vec::new());
+            guard.history.record("Import", String::new(), Vec::new());
+            assert!(seen.lock().unwrap().is_empty());
+        }
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(*seen, vec![("Export".to_string(), true), ("Import".to_string(), true)]);
+    }
+
+    /// One second of stereo 48 kHz: a sawtooth on the left, silence on the right.
+    fn tone_wav(name: &str) -> String {
+        let samples: Vec<f32> = (0..48_000).flat_map(|n| [(n % 100) as f32 / 327.68, 0.0]).collect();
+        crate::test_util::wav(&format!("runtime_{}", name), 48_000, 2, &samples)
+    }
+
+    #[test]
+    fn notes_round_trip_through_a_saved_project() {
+        let wav = tone_wav("notes");
+        let runtime = AudioRuntime::new(None).unwrap();
+        runtime.add_track_with_clips("Vox".into(), &[(wav.clone(), 0.0), (wav.clone(), 2.0)]).unwrap();
+        runtime.create_empty_track().unwrap();
+
+        let track_note = "re-sing line 2 — “softer” 🎤 ハモり";
+        let clip_note = "Ünïcödé\nzweite Zeile\t✓";
+        runtime.set_track_notes(0, track_note.into()).unwrap();
+        runtime.set_clip_notes(0, 1, clip_note.into()).unwrap();
+
+        let annotated = runtime.list_annotated();
+        assert_eq!(annotated.len(), 2);
+        assert_eq!((annotated[0].clip_index, annotated[0].notes.as_str()), (None, track_note));
+        assert_eq!((annotated[1].clip_index, annotated[1].notes.as_str()), (Some(1), clip_note));
+        assert_eq!(annotated[1].start_time, Some(2.0));
+
+        let project = crate::test_util::temp_path("runtime_notes").with_extension("json").to_string_lossy().into_owned();
+        runtime.save_project(project.clone()).unwrap();
+        let mut reloaded = AudioRuntime::new(None).unwrap();
+        reloaded.load_project(project.clone()).unwrap();
+        let tracks = reloaded.get_tracks_list();
+        let _ = std::fs::remove_file(&project);
+        let _ = std::fs::remove_file(&wav);
+
+        assert_eq!(tracks[0].notes, track_note);
+        assert_eq!(tracks[0].clips[0].notes, "");
+        assert_eq!(tracks[0].clips[1].notes, clip_note);
+        assert_eq!(tracks[1].notes, "");
+        assert_eq!(reloaded.list_annotated().len(), 2);
+    }
+
+    #[test]
+    fn note_edits_coalesce_into_one_undo_and_are_size_limited() {
+        let runtime = AudioRuntime::new(None).unwrap();
+        runtime.create_empty_track().unwrap();
+        for draft in ["r", "re-", "re-sing"] {
+            runtime.set_track_notes(0, draft.into()).unwrap();
+        }
+        assert_eq!(runtime.get_tracks_list()[0].notes, "re-sing");
+        runtime.undo();
+        assert_eq!(runtime.get_tracks_list()[0].notes, "");
+
+        let too_long = "é".repeat(crate::engine::track::MAX_NOTES_BYTES / 2 + 1);
+        assert!(runtime.set_track_notes(0, too_long).is_err());
+        assert!(runtime.set_track_notes(0, "ok".into()).is_ok());
+        assert!(runtime.set_track_notes(9, "missing".into()).is_err());
+    }
+
+    #[test]
+    fn panic_reaches_the_engine_with_or_without_an_output_stream() {
+        let runtime = AudioRuntime::new(None).unwrap();
+        runtime.panic();
+        runtime.panic(); // a second press before the callback ran is harmless
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while !runtime.is_monitor_muted() {
+            assert!(std::time::Instant::now() < deadline, "panic never reached the engine");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn mute_regions_merge_travel_with_the_clip_and_undo() {
+        let wav = tone_wav("mute_regions");
+        let runtime = AudioRuntime::new(None).unwrap();
+        runtime.add_track_with_clips("Vox".into(), &[(wav.clone(), 0.0)]).unwrap();
+        let _ = std::fs::remove_file(&wav);
+
+        runtime.add_clip_mute_region(0, 0, 0.2, 0.4).unwrap();
+        runtime.add_clip_mute_region(0, 0, 0.3, 0.6).unwrap();
+        assert_eq!(runtime.clip_mute_regions(0, 0).unwrap(), vec![MuteRegion { start: 0.2, end: 0.6 }]);
+
+        // Clip-relative: moving the clip leaves them where they were in it
+        runtime.move_clip(0, 0, 5.0).unwrap();
+        assert_eq!(runtime.clip_mute_regions(0, 0).unwrap(), vec![MuteRegion { start: 0.2, end: 0.6 }]);
+
+        runtime.undo(); // the move
+        runtime.undo(); // the merge
+        assert_eq!(runtime.clip_mute_regions(0, 0).unwrap(), vec![MuteRegion { start: 0.2, end: 0.4 }]);
+        runtime.remove_clip_mute_region(0, 0, 0).unwrap();
+        assert!(runtime.clip_mute_regions(0, 0).unwrap().is_empty());
+        runtime.undo();
+        assert_eq!(runtime.clip_mute_regions(0, 0).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn a_poisoned_engine_is_rebuilt_and_plays_again() {
+        let wav = tone_wav("rebuild");
+        let mut runtime = AudioRuntime::new(None).unwrap();
+        runtime.add_track_with_clips("Vox".into(), &[(wav.clone(), 0.0)]).unwrap();
+        runtime.engine.with(|eng| eng.seek(Duration::from_millis(250))).unwrap();
+
+        // A command that panics halfway takes the engine down
+        let failed = runtime.engine.with(|_| panic!("command failed"));
+        assert_eq!(failed, Err::<(), _>(crate::engine::handle::EngineUnavailable::Poisoned));
+        assert!(runtime.engine.is_poisoned());
+        assert!(runtime.create_empty_track().is_err());
+
+        let report = runtime.rebuild().unwrap();
+        assert_eq!(report.source, SnapshotSource::Poisoned);
+        assert_eq!(report.tracks, 1);
+        assert!((report.position_secs - 0.25).abs() < 1e-3, "{}", report.position_secs);
+        assert!(!runtime.engine.is_poisoned());
+
+        // The project came back, edits go through, and the track is heard
+        let tracks = runtime.get_tracks_list();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].name, "Vox");
+        runtime.create_empty_track().unwrap();
+        runtime.engine.with(|eng| eng.play()).unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        let mut heard = false;
+        while !heard && std::time::Instant::now() < deadline {
+            heard = runtime
+                .engine
+                .with(|eng| {
+                    let channels = eng.channels;
+                    let mut out = vec![0.0f32; 256 * channels];
+                    eng.render(&mut out, &[]);
+                    out.iter().any(|s| s.abs() > 1e-4)
+                })
+                .unwrap();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let _ = std::fs::remove_file(&wav);
+        assert!(heard, "nothing played after the rebuild");
+    }
+}
@@ -1,55 +1,295 @@
 // src/bpm/adapter.rs
 use anyhow::{anyhow, Result};
-use std::fs::File;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
 use symphonia::core::audio::SampleBuffer;
 use symphonia::core::codecs::DecoderOptions;
-use symphonia::core::formats::FormatOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, SeekMode, SeekTo};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
+use symphonia::core::units::Time;
 use symphonia::default::{get_codecs, get_probe};
 use crate::bpm::{BpmDetector, BpmOptions};
+use crate::bpm::cache::DecodedPcm;
+use crate::decoder::damage::Recovery;
+use crate::decoder::dsp::{self, ChannelMap};
+use crate::decoder::remote::open_source;
+
+/// Ceiling for analysis decodes: the first 10 minutes of a file, whatever its rate and
+/// channel count (~230 MB of f32 for 48 kHz stereo).
+pub const ANALYSIS_MAX_SECS: f64 = 600.0;
+
+/// How often (in packets) the cancel token is checked.
+const CANCEL_CHECK_PACKETS: usize = 32;
+
+/// Decoded interleaved samples, sample rate, channel count.
+pub type DecodedAudio = (Vec<f32>, u32, usize);
+
+#[derive(Debug)]
+pub enum DecodeError {
+    /// `max_samples` was reached. `partial` holds everything decoded up to the cap.
+    TooLarge { decoded_secs: f64, partial: DecodedAudio },
+    Cancelled { path: String },
+    Failed { path: String, position_secs: f64, source: anyhow::Error },
+}
+
+impl DecodeError {
+    fn failed(path: &str, position_secs: f64, source: impl Into<anyhow::Error>) -> Self {
+        DecodeError::Failed { path: path.to_string(), position_secs, source: source.into() }
+    }
+
+    /// Samples decoded before the ceiling, for callers happy to work on a prefix.
+    pub fn into_partial(self) -> Option<DecodedAudio> {
+        match self {
+            DecodeError::TooLarge { partial, .. } => Some(partial),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::TooLarge { decoded_secs, .. } => {
+                write!(f, "file too large to decode into memory (stopped after {:.1}s)", decoded_secs)
+            }
+            DecodeError::Cancelled { path } => write!(f, "decode of {} cancelled", path),
+            DecodeError::Failed { path, position_secs, source } => {
+                write!(f, "failed to decode {} at {:.2}s: {}", path, position_secs, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::Failed { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 pub fn analyze_bpm_for_file(path: &str) -> Result<Option<f32>> {
-    let (samples, sample_rate, channels) = decode_to_vec(path)?;
+    let pcm = decode_for_analysis(path)?;
     let mut det = BpmDetector::new(2048);
     let opts = BpmOptions { compute_beats: true, ..Default::default() };
-    if let Some(res) = det.detect(&samples, channels, sample_rate, opts) {
+    if let Some(res) = det.detect(&pcm.samples, pcm.channels, pcm.sample_rate, opts) {
         Ok(Some(res.bpm))
     } else {
         Ok(None)
     }
 }
 
-pub fn decode_to_vec(path: &str) -> Result<(Vec<f32>, u32, usize)> {
-    let file = File::open(path)?;
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
-    let probed = get_probe().format(&Default::default(), mss, &FormatOptions::default(), &MetadataOptions::default())?;
+/// Whole-file decode with no ceiling. Prefer `decode_to_vec_limited` for user files.
+pub fn decode_to_vec(path: &str) -> Result<DecodedAudio> {
+    decode_to_vec_limited(path, None, None).map_err(anyhow::Error::from)
+}
+
+/// Capped decode for analysis (BPM, profiles, loudness). Analysis doesn't need the whole
+/// of a huge file, so past `ANALYSIS_MAX_SECS` the decoded prefix comes back with
+/// `truncated` set instead of an error. Prefer `cache::shared().get`, which shares decodes.
+pub fn decode_for_analysis(path: &str) -> std::result::Result<DecodedPcm, DecodeError> {
+    decode_capped(path, ANALYSIS_MAX_SECS)
+}
+
+fn decode_capped(path: &str, max_secs: f64) -> std::result::Result<DecodedPcm, DecodeError> {
+    let mut samples = Vec::<f32>::new();
+    let mut truncated = false;
+    let (sample_rate, channels, _) = decode_chunks(path, None, |chunk, sample_rate, channels| {
+        let max = (max_secs * sample_rate as f64) as usize * channels;
+        let room = max.saturating_sub(samples.len());
+        if chunk.len() > room {
+            samples.extend_from_slice(&chunk[..room]);
+            truncated = true;
+            return ControlFlow::Break(());
+        }
+        samples.extend_from_slice(chunk);
+        ControlFlow::Continue(())
+    })?;
+
+    if truncated {
+        rt_warn!("⚠️ [Analyzer] {} is longer than {:.0}s, analysing the start only", path, max_secs);
+    }
+    Ok(DecodedPcm { samples, sample_rate, channels, truncated })
+}
+
+/// `len_secs` of audio starting `start_secs` into the file, e.g. the part of a file a clip
+/// plays. Unlike `decode_for_analysis` there is no ceiling: callers ask for what they need.
+pub fn decode_range(path: &str, start_secs: f64, len_secs: f64) -> std::result::Result<DecodedAudio, DecodeError> {
+    let mut out = Vec::<f32>::new();
+    let (sample_rate, channels, _) = decode_chunks_from(path, start_secs, None, |chunk, sample_rate, channels| {
+        let want = (len_secs.max(0.0) * sample_rate as f64).round() as usize * channels;
+        let room = want.saturating_sub(out.len());
+        out.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        if out.len() >= want { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+    })?;
+    Ok((out, sample_rate, channels))
+}
+
+/// Decode into memory, stopping with `TooLarge` once `max_samples` interleaved samples
+/// are held and with `Cancelled` when `cancel` is set. Errors carry the path and position.
+pub fn decode_to_vec_limited(
+    path: &str,
+    max_samples: Option<usize>,
+    cancel: Option<&AtomicBool>,
+) -> std::result::Result<DecodedAudio, DecodeError> {
+    let mut out = Vec::<f32>::new();
+    let mut capped = false;
+    let (sample_rate, channels, _) = decode_chunks(path, cancel, |samples, _, channels| {
+        out.extend_from_slice(samples);
+        // Memory ceiling (trimmed to whole frames)
+        match max_samples {
+            Some(max) if out.len() >= max => {
+                out.truncate((max / channels.max(1)) * channels.max(1));
+                capped = true;
+                ControlFlow::Break(())
+            }
+            _ => ControlFlow::Continue(()),
+        }
+    })?;
+
+    if capped {
+        let decoded_secs = out.len() as f64 / channels.max(1) as f64 / sample_rate.max(1) as f64;
+        rt_warn!("⚠️ [Analyzer] {} exceeds the decode ceiling, stopped at {:.1}s", path, decoded_secs);
+        return Err(DecodeError::TooLarge { decoded_secs, partial: (out, sample_rate, channels) });
+    }
+    rt_info!("📊 [Analyzer] Decoded {} samples", out.len());
+    Ok((out, sample_rate, channels))
+}
+
+/// Decode packet by packet, handing each block of interleaved samples (whole frames, in
+/// the locked format) to `on_chunk` with the sample rate and channel count. Nothing is kept
+/// in between, so memory stays flat whatever the file length; `on_chunk` can stop the
+/// decode early by returning `Break`. Returns the format and the number of frames handed out.
+pub fn decode_chunks(
+    path: &str,
+    cancel: Option<&AtomicBool>,
+    on_chunk: impl FnMut(&[f32], u32, usize) -> ControlFlow<()>,
+) -> std::result::Result<(u32, usize, u64), DecodeError> {
+    decode_chunks_from(path, 0.0, cancel, on_chunk)
+}
+
+/// `decode_chunks` starting `start_secs` into the file: the reader is seeked there and the
+/// first chunk begins on that exact frame. Sources that can't seek are decoded from the
+/// start and the frames before `start_secs` dropped. A file that ends well before the
+/// length its container states fails with the position it ran out at.
+pub fn decode_chunks_from(
+    path: &str,
+    start_secs: f64,
+    cancel: Option<&AtomicBool>,
+    mut on_chunk: impl FnMut(&[f32], u32, usize) -> ControlFlow<()>,
+) -> std::result::Result<(u32, usize, u64), DecodeError> {
+    let source = open_source(path).map_err(|e| DecodeError::failed(path, 0.0, e))?;
+    let mss = MediaSourceStream::new(source, Default::default());
+    let probed = get_probe()
+        .format(&Default::default(), mss, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| DecodeError::failed(path, 0.0, e))?;
     let mut format = probed.format;
-    let track = format.default_track().ok_or_else(|| anyhow!("no default audio track"))?;
+    let track = format
+        .default_track()
+        .ok_or_else(|| DecodeError::failed(path, 0.0, anyhow!("no default audio track")))?;
     let track_id = track.id;
     let codec_params = track.codec_params.clone();
-    
-    let mut decoder = get_codecs().make(&codec_params, &DecoderOptions::default())?;
+
+    let mut decoder = get_codecs()
+        .make(&codec_params, &DecoderOptions::default())
+        .map_err(|e| DecodeError::failed(path, 0.0, e))?;
     let mut sample_buf: Option<SampleBuffer<f32>> = None;
-    let mut out = Vec::<f32>::new();
-    
-    let mut sample_rate = 44100;
-    let mut channels = 2;
+    let mut remapped = Vec::<f32>::new();
+    let mut silence = Vec::<f32>::new();
+    let mut layout_map: Option<ChannelMap> = None; // file's channel order -> the usual one
+
+    let mut sample_rate = codec_params.sample_rate.unwrap_or(44100);
+    let mut channels = codec_params.channels.map(|c| c.count()).unwrap_or(2);
+    let mut recovery = Recovery::new(path, codec_params.time_base, sample_rate);
     let mut format_locked = false;
+    let mut packets = 0usize;
+    let mut frames = 0u64;
 
-    // println!("📂 [Analyzer] Opening file: {}", path);
+    // An accurate seek lands on the packet holding `start_secs`; the part of it before
+    // that point is dropped from the head of the output
+    let start_secs = start_secs.max(0.0);
+    let mut lead_secs = 0.0;
+    if start_secs > 0.0 {
+        let time = Time::new(start_secs.trunc() as u64, start_secs.fract());
+        match format.seek(SeekMode::Accurate, SeekTo::Time { time, track_id: Some(track_id) }) {
+            Ok(seeked) => {
+                lead_secs = codec_params.time_base.map_or(0.0, |tb| {
+                    let t = tb.calc_time(seeked.required_ts.saturating_sub(seeked.actual_ts));
+                    t.seconds as f64 + t.frac
+                });
+                recovery.seeked(start_secs - lead_secs);
+                decoder.reset();
+            }
+            Err(e) => {
+                rt_warn!("⚠️ [Analyzer] {} can't seek to {:.2}s ({}), decoding from the start", path, start_secs, e);
+                lead_secs = start_secs;
+            }
+        }
+    }
+    let mut skip: Option<u64> = None; // frames still to drop, once the rate is known
+    let mut emit = |chunk: &[f32], sample_rate: u32, channels: usize| {
+        let skip = skip.get_or_insert_with(|| (lead_secs * sample_rate as f64).round() as u64);
+        let dropped = (*skip).min((chunk.len() / channels.max(1)) as u64);
+        *skip -= dropped;
+        match &chunk[dropped as usize * channels..] {
+            [] => ControlFlow::Continue(()),
+            rest => on_chunk(rest, sample_rate, channels),
+        }
+    };
+
+    let position = |frames: u64, sr: u32| start_secs - lead_secs + frames as f64 / sr.max(1) as f64;
+    let mut ran_out = false; // the stream ended, rather than `on_chunk` stopping it
 
     loop {
+        packets += 1;
+        if packets % CANCEL_CHECK_PACKETS == 0 && cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+            return Err(DecodeError::Cancelled { path: path.to_string() });
+        }
+
         let packet = match format.next_packet() {
             Ok(p) => p,
-            Err(_) => break,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                ran_out = true;
+                break;
+            }
+            Err(SymphoniaError::DecodeError(_)) if recovery.bad_read() => continue,
+            Err(e) => {
+                // Nothing usable at all is an error; a bad tail just ends the file early
+                if frames == 0 {
+                    return Err(DecodeError::failed(path, 0.0, e));
+                }
+                rt_warn!("⚠️ [Analyzer] {} ends early at {:.2}s: {}", path, position(frames, sample_rate), e);
+                break;
+            }
         };
         if packet.track_id() != track_id { continue; }
-        
+
         let decoded = match decoder.decode(&packet) {
             Ok(d) => d,
+            // Damaged packet: silence in its place keeps what follows in time (once the
+            // format is known; before that there is nothing to keep in line with)
+            Err(SymphoniaError::IoError(_)) | Err(SymphoniaError::DecodeError(_)) => match recovery.bad_packet(&packet) {
+                Some(fill) if fill > 0 && format_locked => {
+                    silence.clear();
+                    silence.resize(fill * channels, 0.0);
+                    frames += fill as u64;
+                    if emit(&silence, sample_rate, channels).is_break() {
+                        break;
+                    }
+                    continue;
+                }
+                Some(_) => continue,
+                None => {
+                    rt_warn!("⚠️ [Analyzer] {} too damaged past {:.2}s, stopping", path, position(frames, sample_rate));
+                    break;
+                }
+            },
             Err(_) => continue,
         };
+        recovery.decoded(&packet, decoded.frames());
 
         let spec = decoded.spec();
         let current_channels = spec.channels.count();
@@ -61,7 +301,17 @@ pub fn decode_to_vec(path: &str) -> Result<(Vec<f32>, u32, usize)> {
                 sample_rate = current_rate;
                 channels = current_channels;
                 format_locked = true;
-                println!("🔍 [Analyzer] Locked Format: {} Hz / {} Ch", sample_rate, channels);
+                // Consumers only see a channel count, so a surround file whose mask orders
+                // its speakers differently is put in the order that count implies
+                let file_layout = dsp::layout_from_symphonia(spec.channels);
+                let usual = dsp::layout_for(channels);
+                let (mut have, mut want) = (file_layout.clone(), usual.clone());
+                have.sort();
+                want.sort();
+                if channels > 2 && file_layout != usual && have == want {
+                    layout_map = Some(ChannelMap::between(&file_layout, &usual));
+                }
+                rt_info!("🔍 [Analyzer] Locked Format: {} Hz / {} Ch", sample_rate, channels);
             } else {
                 continue;
             }
@@ -75,28 +325,188 @@ pub fn decode_to_vec(path: &str) -> Result<(Vec<f32>, u32, usize)> {
         buf.copy_interleaved_ref(decoded);
         let new_samples = buf.samples();
 
-        // 3. Handle Channel Mismatch (The Fix)
-        if current_channels == channels {
-            // Perfect match
-            out.extend_from_slice(new_samples);
-        } else if current_channels == 1 && channels == 2 {
-            // File is Stereo, packet is Mono -> Duplicate L to R
-            for &s in new_samples {
-                out.push(s);
-                out.push(s);
+        // 3. Handle Channel Mismatch: packets are mapped onto the locked channel count
+        let chunk: &[f32] = match &layout_map {
+            Some(map) if current_channels == channels => {
+                map.apply(new_samples, &mut remapped);
+                &remapped
             }
-        } else if current_channels == 2 && channels == 1 {
-            // File is Mono, packet is Stereo -> Downmix
-            for pair in new_samples.chunks(2) {
-                let mono = (pair[0] + pair[1]) * 0.5;
-                out.push(mono);
+            None if current_channels == channels => new_samples,
+            _ => {
+                ChannelMap::new(current_channels, channels).apply(new_samples, &mut remapped);
+                &remapped
             }
-        } else {
-            // Complex mismatch (e.g. 5.1 to Stereo) - Skip to avoid crash
-            // (In a real scenario, you might want to map channels here)
+        };
+
+        frames += (chunk.len() / channels.max(1)) as u64;
+        if emit(chunk, sample_rate, channels).is_break() {
+            break;
         }
     }
-    
-    println!("📊 [Analyzer] Decoded {} samples", out.len());
-    Ok((out, sample_rate, channels))
-}
\ No newline at end of file
+
+    // A file cut short (an interrupted copy or download) reads like one that ended: the
+    // container's stated length tells them apart. A packet or two of slack covers codecs
+    // whose stated length leaves out their padding.
+    if let (true, Some(stated)) = (ran_out, codec_params.n_frames) {
+        let ended_at = position(frames, sample_rate);
+        let read = (ended_at * sample_rate as f64).round() as u64;
+        let slack = 2 * codec_params.max_frames_per_packet.unwrap_or(0);
+        if read + slack < stated {
+            let missing = anyhow!("the file ends {} frames before its stated length", stated - read);
+            return Err(DecodeError::failed(path, ended_at, missing));
+        }
+    }
+
+    let lead = (lead_secs * sample_rate as f64).round() as u64;
+    Ok((sample_rate, channels, frames.saturating_sub(lead)))
+}
+
+/// Sample rate, channel count and length in frames, without keeping any audio in memory.
+/// Uses the container's frame count when present, otherwise decodes and counts.
+pub fn probe_audio(path: &str) -> std::result::Result<(u32, usize, u64), DecodeError> {
+    let source = open_source(path).map_err(|e| DecodeError::failed(path, 0.0, e))?;
+    let mss = MediaSourceStream::new(source, Default::default());
+    let probed = get_probe()
+        .format(&Default::default(), mss, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| DecodeError::failed(path, 0.0, e))?;
+    let mut format = probed.format;
+    let track = format
+        .default_track()
+        .ok_or_else(|| DecodeError::failed(path, 0.0, anyhow!("no default audio track")))?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    if let (Some(sr), Some(ch), Some(frames)) = (params.sample_rate, params.channels, params.n_frames) {
+        return Ok((sr, ch.count(), frames));
+    }
+
+    let mut decoder = get_codecs()
+        .make(&params, &DecoderOptions::default())
+        .map_err(|e| DecodeError::failed(path, 0.0, e))?;
+    let mut sample_rate = params.sample_rate.unwrap_or(0);
+    let mut channels = params.channels.map(|c| c.count()).unwrap_or(0);
+    let mut frames = 0u64;
+    let mut recovery = Recovery::new(path, params.time_base, sample_rate);
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(p) => p,
+            Err(SymphoniaError::DecodeError(_)) if recovery.bad_read() => continue,
+            Err(_) => break,
+        };
+        if packet.track_id() != track_id { continue; }
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                if sample_rate == 0 { sample_rate = decoded.spec().rate; }
+                if channels == 0 { channels = decoded.spec().channels.count(); }
+                frames += decoded.frames() as u64;
+                recovery.decoded(&packet, decoded.frames());
+            }
+            // Counted like the decoders play it: a filled packet keeps its length
+            Err(SymphoniaError::IoError(_)) | Err(SymphoniaError::DecodeError(_)) => match recovery.bad_packet(&packet) {
+                Some(fill) => frames += fill as u64,
+                None => break,
+            },
+            Err(_) => {}
+        }
+    }
+
+    if sample_rate == 0 || channels == 0 {
+        return Err(DecodeError::failed(path, 0.0, anyhow!("no decodable audio")));
+    }
+    Ok((sample_rate, channels, frames))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Mono 8 kHz ramp: sample n holds n / 32768, so any decoded sample says where it came from
+    fn ramp_wav(name: &str, frames: u32) -> String {
+        let samples: Vec<f32> = (0..frames).map(|n| n as f32 / 32768.0).collect();
+        crate::test_util::wav(&format!("adapter_{}", name), 8_000, 1, &samples)
+    }
+
+    fn index_of(sample: f32) -> u32 {
+        (sample * 32768.0).round() as u32
+    }
+
+    #[test]
+    fn analysis_decode_stops_at_the_ceiling_in_seconds() {
+        let path = ramp_wav("capped", 16_000);
+        let capped = decode_capped(&path, 0.5).unwrap();
+        let whole = decode_capped(&path, 10.0).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(capped.truncated);
+        assert_eq!(capped.frames(), 4_000);
+        assert!(!whole.truncated);
+        assert_eq!(whole.frames(), 16_000);
+    }
+
+    #[test]
+    fn range_decode_returns_exactly_the_requested_frames() {
+        let path = ramp_wav("range", 16_000);
+        let (samples, sample_rate, channels) = decode_range(&path, 1.25, 0.5).unwrap();
+        let past_end = decode_range(&path, 1.75, 1.0).unwrap().0;
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!((sample_rate, channels), (8_000, 1));
+        assert_eq!(samples.len(), 4_000);
+        assert_eq!(index_of(samples[0]), 10_000);
+        assert_eq!(index_of(samples[3_999]), 13_999);
+        // Only what the file has
+        assert_eq!(past_end.len(), 2_000);
+    }
+
+    #[test]
+    fn decode_errors_name_the_file() {
+        let Err(err) = decode_for_analysis("/nonexistent/haven_missing.wav") else { panic!("decoded a missing file") };
+        assert!(err.to_string().contains("/nonexistent/haven_missing.wav"));
+    }
+
+    #[test]
+    fn a_set_cancel_flag_stops_the_decode() {
+        // Long enough to reach a cancel check
+        let path = ramp_wav("cancel", 400_000);
+        let cancel = AtomicBool::new(true);
+        let result = decode_to_vec_limited(&path, None, Some(&cancel));
+        let _ = std::fs::remove_file(&path);
+
+        match result {
+            Err(DecodeError::Cancelled { path: cancelled }) => assert_eq!(cancelled, path),
+            other => panic!("expected Cancelled, got {:?}", other.map(|(s, _, _)| s.len())),
+        }
+    }
+
+    #[test]
+    fn the_sample_cap_returns_the_decoded_prefix() {
+        let path = ramp_wav("too_large", 16_000);
+        let result = decode_to_vec_limited(&path, Some(5_000), None);
+        let _ = std::fs::remove_file(&path);
+
+        let Err(DecodeError::TooLarge { decoded_secs, partial }) = result else { panic!("expected TooLarge") };
+        assert!((decoded_secs - 0.625).abs() < 1e-9);
+        let (samples, sample_rate, channels) = partial;
+        assert_eq!((samples.len(), sample_rate, channels), (5_000, 8_000, 1));
+        assert_eq!(index_of(samples[0]), 0);
+        assert_eq!(index_of(samples[4_999]), 4_999);
+    }
+
+    #[test]
+    fn a_file_cut_short_fails_where_it_ends() {
+        let path = ramp_wav("cut_short", 16_000);
+        // Keep the header and 10 000 of the 16 000 four-byte frames it promises
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        let header = file.metadata().unwrap().len() - 16_000 * 4;
+        file.set_len(header + 10_000 * 4).unwrap();
+        let result = decode_to_vec_limited(&path, None, None);
+        let _ = std::fs::remove_file(&path);
+
+        let Err(err) = result else { panic!("decoded a file cut short") };
+        let message = err.to_string();
+        assert!(matches!(err, DecodeError::Failed { .. }));
+        assert!(message.contains(&path), "{}", message);
+        assert!(message.contains("at 1.25s"), "{}", message);
+    }
+}
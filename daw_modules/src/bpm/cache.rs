@@ -0,0 +1,499 @@
+// src/bpm/cache.rs
+
+// One decode per file for everything that reads audio off disk: clip probing, track
+// analysis, the AI rider, project-load waveforms. Entries are keyed by path and checked
+// against the file's modification time and length on every lookup, so a file rewritten in
+// place (a re-recorded take, a re-bounced stem) is decoded again instead of served stale;
+// remote (https) clips are keyed by their URL alone. Decoded PCM is held up to a byte
+// budget (settable at runtime) and evicted least recently used; format metadata is tiny and
+// kept for every file. Evicted PCM is spilled to a raw f32 file in the spill directory,
+// which reads back much faster than decoding again; past the spill budget the oldest spill
+// files go and those files are decoded from scratch the next time they're asked for.
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use super::adapter::{self, DecodeError};
+
+/// PCM held by the shared cache before the least recently used files are dropped (~512 MB).
+pub const DEFAULT_CACHE_BUDGET_BYTES: usize = 512 * 1024 * 1024;
+/// Smallest budget `set_budget_bytes` accepts; below it most sessions would thrash.
+pub const MIN_CACHE_BUDGET_BYTES: usize = 32 * 1024 * 1024;
+/// Spilled PCM kept on disk before the oldest spill files are deleted (~4 GB).
+pub const DEFAULT_SPILL_BUDGET_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+static SPILL_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Where evicted PCM is spilled; call once at startup. Leftovers from an earlier run are
+/// deleted. Defaults to a folder in the temp directory.
+pub fn set_spill_dir(dir: PathBuf) {
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = SPILL_DIR.set(dir);
+}
+
+fn spill_dir() -> &'static Path {
+    SPILL_DIR.get_or_init(|| std::env::temp_dir().join(format!("haven-decode-spill-{}", std::process::id())))
+}
+
+/// A decoded file, shared by every consumer.
+pub struct DecodedPcm {
+    pub samples: Vec<f32>, // interleaved
+    pub sample_rate: u32,
+    pub channels: usize,
+    /// The analysis ceiling was hit: `samples` is a prefix of the file.
+    pub truncated: bool,
+}
+
+impl DecodedPcm {
+    pub fn frames(&self) -> u64 {
+        (self.samples.len() / self.channels.max(1)) as u64
+    }
+
+    fn bytes(&self) -> usize {
+        self.samples.len() * std::mem::size_of::<f32>()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &str) -> Option<Self> {
+        if crate::decoder::remote::is_remote(path) {
+            // Remote files are fetched once and taken as immutable
+            return Some(Self { modified: None, len: 0 });
+        }
+        let meta = std::fs::metadata(Path::new(path)).ok()?;
+        Some(Self { modified: meta.modified().ok(), len: meta.len() })
+    }
+}
+
+/// Evicted PCM on disk: raw little-endian f32, interleaved.
+#[derive(Clone)]
+struct Spill {
+    path: PathBuf,
+    bytes: u64,
+    sample_rate: u32,
+    channels: usize,
+    truncated: bool,
+}
+
+impl Spill {
+    fn write(path: PathBuf, pcm: &DecodedPcm) -> std::io::Result<Self> {
+        std::fs::create_dir_all(spill_dir())?;
+        let mut out = BufWriter::new(std::fs::File::create(&path)?);
+        for s in &pcm.samples {
+            out.write_all(&s.to_le_bytes())?;
+        }
+        out.flush()?;
+        Ok(Self {
+            path,
+            bytes: pcm.bytes() as u64,
+            sample_rate: pcm.sample_rate,
+            channels: pcm.channels,
+            truncated: pcm.truncated,
+        })
+    }
+
+    fn read(&self) -> std::io::Result<DecodedPcm> {
+        let raw = std::fs::read(&self.path)?;
+        if raw.len() as u64 != self.bytes {
+            return Err(std::io::Error::other("spill file has the wrong length"));
+        }
+        let samples = raw.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+        Ok(DecodedPcm { samples, sample_rate: self.sample_rate, channels: self.channels, truncated: self.truncated })
+    }
+}
+
+struct Entry {
+    stamp: FileStamp,
+    format: Option<(u32, usize, u64)>, // sample rate, channels, frames
+    pcm: Option<Arc<DecodedPcm>>,
+    spill: Option<Spill>, // set once the PCM was evicted to disk
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<String, Entry>,
+    pcm_bytes: usize,
+    spill_bytes: u64,
+    tick: u64,
+}
+
+impl Inner {
+    // Forget an entry, with its PCM and its spill file
+    fn remove(&mut self, path: &str) {
+        if let Some(old) = self.entries.remove(path) {
+            self.pcm_bytes -= old.pcm.map(|p| p.bytes()).unwrap_or(0);
+            if let Some(spill) = old.spill {
+                self.spill_bytes -= spill.bytes;
+                let _ = std::fs::remove_file(spill.path);
+            }
+        }
+    }
+
+    // Least recently used PCM out of memory until it fits `budget`; `keep` is never picked
+    fn evict(&mut self, budget: usize, keep: Option<&str>) -> Vec<(String, FileStamp, Arc<DecodedPcm>)> {
+        let mut evicted = Vec::new();
+        while self.pcm_bytes > budget {
+            let victim = self
+                .entries
+                .iter()
+                .filter(|(p, e)| e.pcm.is_some() && Some(p.as_str()) != keep)
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(p, _)| p.clone());
+            let Some(victim) = victim else { break };
+            if let Some(e) = self.entries.get_mut(&victim) {
+                if let Some(pcm) = e.pcm.take() {
+                    self.pcm_bytes -= pcm.bytes();
+                    evicted.push((victim, e.stamp, pcm));
+                }
+            }
+        }
+        evicted
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodeCacheStats {
+    pub files: usize,
+    pub decoded_files: usize,
+    pub pcm_bytes: usize,
+    pub budget_bytes: usize,
+    pub spilled_files: usize,
+    pub spill_bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+    /// Lookups served by reading a spill file back instead of decoding.
+    pub spill_reads: u64,
+}
+
+/// One file's share of the cache, for the memory report.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMemory {
+    pub path: String,
+    pub pcm_bytes: usize,  // held in memory
+    pub spill_bytes: u64,  // on disk
+    pub truncated: bool,
+}
+
+/// Where decoded audio currently lives, largest files first.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsage {
+    pub budget_bytes: usize,
+    pub pcm_bytes: usize,
+    pub spill_budget_bytes: u64,
+    pub spill_bytes: u64,
+    pub spill_dir: String,
+    pub files: Vec<FileMemory>,
+}
+
+pub struct DecodedAudioCache {
+    inner: Mutex<Inner>,
+    budget_bytes: AtomicUsize,
+    spill_budget_bytes: u64,
+    hits: std::sync::atomic::AtomicU64,
+    misses: std::sync::atomic::AtomicU64,
+    spill_reads: std::sync::atomic::AtomicU64,
+}
+
+static SHARED: OnceLock<DecodedAudioCache> = OnceLock::new();
+
+/// The process-wide cache.
+pub fn shared() -> &'static DecodedAudioCache {
+    SHARED.get_or_init(|| DecodedAudioCache::new(DEFAULT_CACHE_BUDGET_BYTES))
+}
+
+impl DecodedAudioCache {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            budget_bytes: AtomicUsize::new(budget_bytes),
+            spill_budget_bytes: DEFAULT_SPILL_BUDGET_BYTES,
+            hits: Default::default(),
+            misses: Default::default(),
+            spill_reads: Default::default(),
+        }
+    }
+
+    pub fn budget_bytes(&self) -> usize {
+        self.budget_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Change the memory cap (at least `MIN_CACHE_BUDGET_BYTES`); PCM over the new cap is
+    /// spilled right away.
+    pub fn set_budget_bytes(&self, bytes: usize) {
+        let bytes = bytes.max(MIN_CACHE_BUDGET_BYTES);
+        self.budget_bytes.store(bytes, Ordering::Relaxed);
+        let evicted = match self.inner.lock() {
+            Ok(mut inner) => inner.evict(bytes, None),
+            Err(_) => return,
+        };
+        self.spill(evicted);
+    }
+
+    /// Decoded PCM for `path` (capped like `decode_for_analysis`), decoding on a miss.
+    /// The decode runs outside the lock, so other files are served meanwhile.
+    pub fn get(&self, path: &str) -> Result<Arc<DecodedPcm>, DecodeError> {
+        let stamp = FileStamp::of(path);
+        if let Some(stamp) = stamp {
+            if let Some(pcm) = self.lookup(path, stamp, |e| e.pcm.clone()) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(pcm);
+            }
+            if let Some(pcm) = self.read_spill(path, stamp) {
+                self.spill_reads.fetch_add(1, Ordering::Relaxed);
+                let pcm = Arc::new(pcm);
+                self.store_pcm(path, stamp, pcm.clone());
+                return Ok(pcm);
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let pcm = Arc::new(adapter::decode_for_analysis(path)?);
+        if let Some(stamp) = stamp {
+            self.store_pcm(path, stamp, pcm.clone());
+        }
+        Ok(pcm)
+    }
+
+    /// Sample rate, channel count and length in frames, like `adapter::probe_audio`, served
+    /// from the cache when the file was seen before.
+    pub fn probe(&self, path: &str) -> Result<(u32, usize, u64), DecodeError> {
+        let stamp = FileStamp::of(path);
+        if let Some(stamp) = stamp {
+            if let Some(format) = self.lookup(path, stamp, |e| e.format) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(format);
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let format = adapter::probe_audio(path)?;
+        if let Some(stamp) = stamp {
+            self.remember_format(path, stamp, format);
+        }
+        Ok(format)
+    }
+
+    /// Record the format of a file decoded elsewhere (e.g. a streaming pass), so later
+    /// probes skip opening it.
+    pub fn note_format(&self, path: &str, sample_rate: u32, channels: usize, frames: u64) {
+        if let Some(stamp) = FileStamp::of(path) {
+            self.remember_format(path, stamp, (sample_rate, channels, frames));
+        }
+    }
+
+    pub fn invalidate(&self, path: &str) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.remove(path);
+        }
+    }
+
+    /// Drop everything, spill files included.
+    pub fn clear(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            let paths: Vec<String> = inner.entries.keys().cloned().collect();
+            for path in paths {
+                inner.remove(&path);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> DecodeCacheStats {
+        let (files, decoded_files, pcm_bytes, spilled_files, spill_bytes) = match self.inner.lock() {
+            Ok(inner) => (
+                inner.entries.len(),
+                inner.entries.values().filter(|e| e.pcm.is_some()).count(),
+                inner.pcm_bytes,
+                inner.entries.values().filter(|e| e.spill.is_some()).count(),
+                inner.spill_bytes,
+            ),
+            Err(_) => (0, 0, 0, 0, 0),
+        };
+        DecodeCacheStats {
+            files,
+            decoded_files,
+            pcm_bytes,
+            budget_bytes: self.budget_bytes(),
+            spilled_files,
+            spill_bytes,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            spill_reads: self.spill_reads.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let (pcm_bytes, spill_bytes, mut files) = match self.inner.lock() {
+            Ok(inner) => {
+                let files = inner
+                    .entries
+                    .iter()
+                    .filter(|(_, e)| e.pcm.is_some() || e.spill.is_some())
+                    .map(|(path, e)| FileMemory {
+                        path: path.clone(),
+                        pcm_bytes: e.pcm.as_ref().map(|p| p.bytes()).unwrap_or(0),
+                        spill_bytes: e.spill.as_ref().map(|s| s.bytes).unwrap_or(0),
+                        truncated: match (&e.pcm, &e.spill) {
+                            (Some(pcm), _) => pcm.truncated,
+                            (None, Some(spill)) => spill.truncated,
+                            (None, None) => false,
+                        },
+                    })
+                    .collect();
+                (inner.pcm_bytes, inner.spill_bytes, files)
+            }
+            Err(_) => (0, 0, Vec::new()),
+        };
+        files.sort_by(|a, b| (b.pcm_bytes as u64 + b.spill_bytes).cmp(&(a.pcm_bytes as u64 + a.spill_bytes)));
+        MemoryUsage {
+            budget_bytes: self.budget_bytes(),
+            pcm_bytes,
+            spill_budget_bytes: self.spill_budget_bytes,
+            spill_bytes,
+            spill_dir: spill_dir().to_string_lossy().to_string(),
+            files,
+        }
+    }
+
+    /// Read from the entry for `path` if it still matches the file on disk; a stale entry
+    /// is dropped.
+    fn lookup<T>(&self, path: &str, stamp: FileStamp, read: impl Fn(&Entry) -> Option<T>) -> Option<T> {
+        let mut inner = self.inner.lock().ok()?;
+        inner.tick += 1;
+        let tick = inner.tick;
+        let entry = inner.entries.get_mut(path)?;
+        if entry.stamp == stamp {
+            entry.last_used = tick;
+            return read(entry);
+        }
+        inner.remove(path);
+        None
+    }
+
+    // PCM spilled for `path` at `stamp`, read back outside the lock. A spill file that
+    // can't be read is forgotten, so the file is decoded again.
+    fn read_spill(&self, path: &str, stamp: FileStamp) -> Option<DecodedPcm> {
+        let spill = self.lookup(path, stamp, |e| e.spill.clone())?;
+        match spill.read() {
+            Ok(pcm) => Some(pcm),
+            Err(e) => {
+                rt_warn!("⚠️ Spilled audio for {} unreadable ({}), decoding again", path, e);
+                if let Ok(mut inner) = self.inner.lock() {
+                    if let Some(lost) = inner.entries.get_mut(path).and_then(|e| e.spill.take()) {
+                        inner.spill_bytes -= lost.bytes;
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    // Write evicted PCM to disk (outside the lock) and record it on entries that still
+    // describe the same file; then trim spill files to the spill budget.
+    fn spill(&self, evicted: Vec<(String, FileStamp, Arc<DecodedPcm>)>) {
+        for (path, stamp, pcm) in evicted {
+            let already = self.inner.lock().ok().is_some_and(|inner| {
+                inner.entries.get(&path).is_some_and(|e| e.stamp == stamp && e.spill.is_some())
+            });
+            if already {
+                continue;
+            }
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            path.hash(&mut hasher);
+            let file = spill_dir().join(format!("{:016x}.f32", hasher.finish()));
+            let spill = match Spill::write(file, &pcm) {
+                Ok(spill) => spill,
+                Err(e) => {
+                    rt_warn!("⚠️ Couldn't spill decoded audio for {}: {}", path, e);
+                    continue;
+                }
+            };
+            let Ok(mut guard) = self.inner.lock() else { return };
+            let inner = &mut *guard;
+            match inner.entries.get_mut(&path).filter(|e| e.stamp == stamp) {
+                Some(entry) => {
+                    let added = spill.bytes;
+                    if let Some(old) = entry.spill.replace(spill) {
+                        inner.spill_bytes -= old.bytes;
+                    }
+                    inner.spill_bytes += added;
+                }
+                None => {
+                    let _ = std::fs::remove_file(&spill.path); // the file changed meanwhile
+                }
+            }
+        }
+
+        let Ok(mut inner) = self.inner.lock() else { return };
+        while inner.spill_bytes > self.spill_budget_bytes {
+            let oldest = inner
+                .entries
+                .iter()
+                .filter(|(_, e)| e.spill.is_some())
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(p, _)| p.clone());
+            let Some(oldest) = oldest else { break };
+            if let Some(spill) = inner.entries.get_mut(&oldest).and_then(|e| e.spill.take()) {
+                inner.spill_bytes -= spill.bytes;
+                let _ = std::fs::remove_file(spill.path);
+            }
+        }
+    }
+
+    /// The entry for `path` at `stamp`, replacing one for an older version of the file.
+    fn entry<'a>(inner: &'a mut Inner, path: &str, stamp: FileStamp) -> &'a mut Entry {
+        inner.tick += 1;
+        let tick = inner.tick;
+        if inner.entries.get(path).is_some_and(|e| e.stamp != stamp) {
+            inner.remove(path);
+        }
+        let entry = inner
+            .entries
+            .entry(path.to_string())
+            .or_insert(Entry { stamp, format: None, pcm: None, spill: None, last_used: tick });
+        entry.last_used = tick;
+        entry
+    }
+
+    fn remember_format(&self, path: &str, stamp: FileStamp, format: (u32, usize, u64)) {
+        if let Ok(mut inner) = self.inner.lock() {
+            Self::entry(&mut inner, path, stamp).format = Some(format);
+        }
+    }
+
+    fn store_pcm(&self, path: &str, stamp: FileStamp, pcm: Arc<DecodedPcm>) {
+        let bytes = pcm.bytes();
+        let budget = self.budget_bytes();
+        if bytes > budget {
+            return; // would evict everything else and still not fit
+        }
+        let Ok(mut inner) = self.inner.lock() else { return };
+        let entry = Self::entry(&mut inner, path, stamp);
+        if !pcm.truncated {
+            entry.format = Some((pcm.sample_rate, pcm.channels, pcm.frames()));
+        }
+        let replaced = entry.pcm.replace(pcm).map(|p| p.bytes()).unwrap_or(0);
+        inner.pcm_bytes = inner.pcm_bytes - replaced + bytes;
+
+        // Least recently used PCM goes to disk first; the format stays
+        let evicted = inner.evict(budget, Some(path));
+        drop(inner);
+        if !evicted.is_empty() {
+            self.spill(evicted);
+        }
+    }
+}
@@ -78,52 +78,157 @@ impl BpmDetector {
         if mag_frames.len() < 4 { return None; }
 
         // novelty: multi-band flux
-        let mut novelty = multi_band_flux(&mag_frames, opts.band_count);
-        if novelty.len() < 8 { return None; }
-
-        // smooth & normalize
-        moving_average_inplace(&mut novelty, 3);
-        let norm = normalize_peak(&novelty);
-
-        // autocorr by FFT
-        let (lag_min, lag_max) = bpm_range_to_lag_range(opts.min_bpm, opts.max_bpm, env_rate);
-        if lag_max <= lag_min + 2 { return None; }
-        let lag_scores = autocorrelate_range_fft(&norm, lag_min, lag_max, &mut self.planner);
-
-        // fold
-        let folded = fold_bpm_candidates(&lag_scores, env_rate, 60.0, 200.0);
-        if folded.is_empty() { return None; }
-
-        // candidate vec
-        let mut cand_vec: Vec<(f32, f32)> = folded.into_iter().map(|(k, v)| (k as f32 / 10.0, v)).collect();
-        cand_vec.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-
-        // comb refine top N
-        let top_n = cand_vec.len().min(6);
-        let mut refined = Vec::with_capacity(top_n);
-        for i in 0..top_n {
-            let (bpm, base_score) = cand_vec[i];
-            let score = comb_score(&norm, bpm, env_rate);
-            refined.push((bpm, score + 0.05 * base_score));
+        let novelty = multi_band_flux(&mag_frames, opts.band_count);
+        tempo_from_novelty(novelty, sample_rate, hop, env_rate, &opts, &mut self.planner)
+    }
+}
+
+/// Tempo detection fed block by block, for files too long to hold in memory. Only the
+/// onset envelope (one value per hop) is kept; `finish` gives what `BpmDetector::detect`
+/// would on the whole signal.
+pub struct BpmStream {
+    planner: FftPlanner<f32>,
+    window: Vec<f32>,
+    opts: BpmOptions,
+    channels: usize,
+    sample_rate: u32,
+    hop: usize,
+    mono: Vec<f32>,  // downmixed samples not yet covered by a full window
+    pos: usize,      // start of the next window in `mono`
+    inbuf: Vec<Complex<f32>>,
+    prev_mag: Vec<f32>,
+    mag: Vec<f32>,
+    band_edges: Vec<usize>,
+    novelty: Vec<f32>, // one flux value per analysis frame
+    sum_sq: f64,       // of the mono signal, for the silence check
+    frames: u64,
+}
+
+impl BpmStream {
+    pub fn new(channels: usize, sample_rate: u32, opts: BpmOptions) -> Self {
+        let n = opts.window_size.next_power_of_two();
+        let half = n / 2 + 1;
+        let mut planner = FftPlanner::<f32>::new();
+        let _ = planner.plan_fft_forward(n); // warm-up
+        Self {
+            planner,
+            window: hann_window(n),
+            channels,
+            sample_rate,
+            hop: opts.hop_size.max(1),
+            mono: Vec::with_capacity(n * 4),
+            pos: 0,
+            inbuf: vec![Complex::zero(); n],
+            prev_mag: Vec::new(),
+            mag: vec![0.0; half],
+            band_edges: band_edges(half, opts.band_count),
+            novelty: Vec::new(),
+            sum_sq: 0.0,
+            frames: 0,
+            opts,
         }
-        refined.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        let primary = refined[0];
-        let confidence = confidence_from_candidates(&refined);
-
-        // beats
-        let beat_times = if opts.compute_beats {
-            compute_beats_from_novelty(&norm, primary.0, env_rate, hop, sample_rate as usize)
-        } else { Vec::new() };
-
-        Some(BpmResult {
-            bpm: (primary.0 * 100.0).round() / 100.0,
-            confidence,
-            candidates: refined,
-            beat_times,
-        })
+    }
+
+    /// Feed the next block of interleaved audio (whole frames).
+    pub fn push(&mut self, audio: &[f32]) {
+        if self.channels == 0 { return; }
+        let n = self.window.len();
+        let fft = self.planner.plan_fft_forward(n);
+        for frame in audio.chunks_exact(self.channels) {
+            let mono = frame.iter().sum::<f32>() / self.channels as f32;
+            self.sum_sq += (mono as f64) * (mono as f64);
+            self.mono.push(mono);
+        }
+        self.frames += (audio.len() / self.channels) as u64;
+
+        while self.pos + n <= self.mono.len() {
+            for k in 0..n {
+                self.inbuf[k].re = self.mono[self.pos + k] * self.window[k];
+                self.inbuf[k].im = 0.0;
+            }
+            fft.process(&mut self.inbuf);
+            for (m, c) in self.mag.iter_mut().zip(&self.inbuf) {
+                *m = c.norm();
+            }
+            // The first frame has nothing to differ from, like in `multi_band_flux`
+            let flux = if self.prev_mag.is_empty() { 0.0 } else { band_flux(&self.prev_mag, &self.mag, &self.band_edges) };
+            self.novelty.push(flux);
+            std::mem::swap(&mut self.prev_mag, &mut self.mag);
+            if self.mag.is_empty() {
+                self.mag = vec![0.0; self.prev_mag.len()];
+            }
+            self.pos += self.hop;
+        }
+        let consumed = self.pos.min(self.mono.len());
+        self.mono.drain(..consumed);
+        self.pos -= consumed;
+    }
+
+    pub fn finish(mut self) -> Option<BpmResult> {
+        if self.channels == 0 || self.frames == 0 { return None; }
+        let rms = (self.sum_sq / self.frames as f64).sqrt() as f32;
+        if rms < self.opts.silence_threshold { return None; }
+        if self.novelty.len() < 4 { return None; }
+        let env_rate = if self.opts.env_rate > 0.0 { self.opts.env_rate } else { self.sample_rate as f32 / self.hop as f32 };
+        let mut novelty = std::mem::take(&mut self.novelty);
+        scale_to_peak(&mut novelty);
+        tempo_from_novelty(novelty, self.sample_rate, self.hop, env_rate, &self.opts, &mut self.planner)
     }
 }
 
+/// Everything after the onset envelope: autocorrelation, folding, comb refinement, beats.
+fn tempo_from_novelty(
+    mut novelty: Vec<f32>,
+    sample_rate: u32,
+    hop: usize,
+    env_rate: f32,
+    opts: &BpmOptions,
+    planner: &mut FftPlanner<f32>,
+) -> Option<BpmResult> {
+    if novelty.len() < 8 { return None; }
+
+    // smooth & normalize
+    moving_average_inplace(&mut novelty, 3);
+    let norm = normalize_peak(&novelty);
+
+    // autocorr by FFT
+    let (lag_min, lag_max) = bpm_range_to_lag_range(opts.min_bpm, opts.max_bpm, env_rate);
+    if lag_max <= lag_min + 2 { return None; }
+    let lag_scores = autocorrelate_range_fft(&norm, lag_min, lag_max, planner);
+
+    // fold
+    let folded = fold_bpm_candidates(&lag_scores, env_rate, 60.0, 200.0);
+    if folded.is_empty() { return None; }
+
+    // candidate vec
+    let mut cand_vec: Vec<(f32, f32)> = folded.into_iter().map(|(k, v)| (k as f32 / 10.0, v)).collect();
+    cand_vec.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    // comb refine top N
+    let top_n = cand_vec.len().min(6);
+    let mut refined = Vec::with_capacity(top_n);
+    for i in 0..top_n {
+        let (bpm, base_score) = cand_vec[i];
+        let score = comb_score(&norm, bpm, env_rate);
+        refined.push((bpm, score + 0.05 * base_score));
+    }
+    refined.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    let primary = refined[0];
+    let confidence = confidence_from_candidates(&refined);
+
+    // beats
+    let beat_times = if opts.compute_beats {
+        compute_beats_from_novelty(&norm, primary.0, env_rate, hop, sample_rate as usize)
+    } else { Vec::new() };
+
+    Some(BpmResult {
+        bpm: (primary.0 * 100.0).round() / 100.0,
+        confidence,
+        candidates: refined,
+        beat_times,
+    })
+}
+
 // ---------- Helper functions (same approach as earlier) ----------
 
 fn quick_rms(audio: &[f32], channels: usize) -> f32 {
@@ -175,37 +280,49 @@ fn multi_band_flux(mag_frames: &Vec<Vec<f32>>, band_count: usize) -> Vec<f32> {
     if mag_frames.len() < 2 { return vec![]; }
     let bins = mag_frames[0].len();
     let mut novelty = vec![0.0f32; mag_frames.len()];
+    let band_edges = band_edges(bins, band_count);
+    for t in 1..mag_frames.len() {
+        novelty[t] = band_flux(&mag_frames[t - 1], &mag_frames[t], &band_edges);
+    }
+    scale_to_peak(&mut novelty);
+    novelty
+}
+
+fn band_edges(bins: usize, band_count: usize) -> Vec<usize> {
     let mut band_edges = Vec::with_capacity(band_count + 1);
     for i in 0..=band_count {
         let edge = ((i as f32 / band_count as f32) * (bins as f32)).round() as usize;
         band_edges.push(edge.min(bins));
     }
-    for t in 1..mag_frames.len() {
-        let prev = &mag_frames[t - 1];
-        let cur = &mag_frames[t];
-        let mut sum_flux = 0.0f32;
-        for bidx in 0..band_count {
-            let start = band_edges[bidx];
-            let end = band_edges[bidx + 1];
-            let mut band_flux = 0.0f32;
-            for k in start..end {
-                let diff = cur[k] - prev[k];
-                if diff > 0.0 { band_flux += diff; }
-            }
-            let weight = match bidx {
-                0 => 1.0,
-                1 => 1.0,
-                _ => 0.8,
-            };
-            sum_flux += weight * band_flux;
+    band_edges
+}
+
+/// Weighted positive spectral flux from `prev` to `cur`, summed over the bands.
+fn band_flux(prev: &[f32], cur: &[f32], band_edges: &[usize]) -> f32 {
+    let mut sum_flux = 0.0f32;
+    for bidx in 0..band_edges.len().saturating_sub(1) {
+        let start = band_edges[bidx];
+        let end = band_edges[bidx + 1];
+        let mut band_flux = 0.0f32;
+        for k in start..end {
+            let diff = cur[k] - prev[k];
+            if diff > 0.0 { band_flux += diff; }
         }
-        novelty[t] = sum_flux;
+        let weight = match bidx {
+            0 => 1.0,
+            1 => 1.0,
+            _ => 0.8,
+        };
+        sum_flux += weight * band_flux;
     }
+    sum_flux
+}
+
+fn scale_to_peak(novelty: &mut [f32]) {
     let maxv = novelty.iter().cloned().fold(0./0., f32::max);
     if maxv > 0.0 {
-        for v in &mut novelty { *v /= maxv; }
+        for v in novelty.iter_mut() { *v /= maxv; }
     }
-    novelty
 }
 
 fn normalize_peak(x: &[f32]) -> Vec<f32> {
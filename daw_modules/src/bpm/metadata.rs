@@ -0,0 +1,116 @@
+// src/bpm/metadata.rs
+
+// Tags, cover art and format of an audio file, read without decoding it. Tags can sit in
+// the container probe (ID3v2 ahead of an MP3 stream) or in the format itself (Vorbis
+// comments, MP4 atoms); the format's own revision wins when both have one. The length
+// comes from the shared decode cache's probe, which counts decoded frames when the
+// container doesn't say, so it's exact even for VBR MP3s without a header.
+
+use anyhow::anyhow;
+use std::path::Path;
+use symphonia::core::codecs::CODEC_TYPE_NULL;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey};
+use symphonia::core::probe::Hint;
+use symphonia::default::{get_codecs, get_probe};
+
+use super::adapter::DecodeError;
+use super::cache;
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Artwork {
+    pub media_type: String, // e.g. "image/jpeg"
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub artwork: Option<Artwork>, // front cover when tagged as such, else the first picture
+    pub codec: Option<String>,    // short codec name, e.g. "mp3", "flac"
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub frames: u64,
+    pub duration_secs: f64,
+}
+
+impl FileMetadata {
+    /// "Artist - Title" when both are tagged, the title alone otherwise.
+    pub fn display_name(&self) -> Option<String> {
+        match (&self.title, &self.artist) {
+            (Some(title), Some(artist)) => Some(format!("{} - {}", artist, title)),
+            (Some(title), None) => Some(title.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Fill whatever `meta` is still missing from one metadata revision.
+fn read_revision(meta: &mut FileMetadata, rev: &MetadataRevision) {
+    for tag in rev.tags() {
+        let slot = match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => &mut meta.title,
+            Some(StandardTagKey::Artist) => &mut meta.artist,
+            Some(StandardTagKey::AlbumArtist) if meta.artist.is_none() => &mut meta.artist,
+            Some(StandardTagKey::Album) => &mut meta.album,
+            _ => continue,
+        };
+        let value = tag.value.to_string();
+        let value = value.trim();
+        if slot.is_none() && !value.is_empty() {
+            *slot = Some(value.to_string());
+        }
+    }
+    if meta.artwork.is_none() {
+        let visuals = rev.visuals();
+        let picked = visuals
+            .iter()
+            .find(|v| v.usage == Some(StandardVisualKey::FrontCover))
+            .or_else(|| visuals.first());
+        if let Some(v) = picked {
+            meta.artwork = Some(Artwork { media_type: v.media_type.clone(), data: v.data.to_vec() });
+        }
+    }
+}
+
+pub fn probe_file_metadata(path: &str) -> Result<FileMetadata, DecodeError> {
+    let failed = |e: anyhow::Error| DecodeError::Failed { path: path.to_string(), position_secs: 0.0, source: e };
+
+    let source = crate::decoder::remote::open_source(path).map_err(|e| failed(e.into()))?;
+    let mss = MediaSourceStream::new(source, Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = Path::new(path).extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut probed = get_probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| failed(e.into()))?;
+
+    let mut meta = FileMetadata::default();
+
+    // The format's own tags first, then anything found while probing the container
+    if let Some(rev) = probed.format.metadata().current() {
+        read_revision(&mut meta, rev);
+    }
+    if let Some(rev) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+        read_revision(&mut meta, rev);
+    }
+
+    let track = probed.format.default_track().ok_or_else(|| failed(anyhow!("no default audio track")))?;
+    if track.codec_params.codec != CODEC_TYPE_NULL {
+        meta.codec = get_codecs().get_codec(track.codec_params.codec).map(|d| d.short_name.to_string());
+    }
+    drop(probed);
+
+    let (sample_rate, channels, frames) = cache::shared().probe(path)?;
+    meta.sample_rate = sample_rate;
+    meta.channels = channels;
+    meta.frames = frames;
+    meta.duration_secs = if sample_rate > 0 { frames as f64 / sample_rate as f64 } else { 0.0 };
+    Ok(meta)
+}
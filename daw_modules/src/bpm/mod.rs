@@ -1,6 +1,8 @@
 pub mod detector;
 pub mod utils;
 pub mod adapter;
+pub mod cache;
+pub mod metadata;
 
-pub use detector::{BpmDetector, BpmOptions, BpmResult};
+pub use detector::{BpmDetector, BpmOptions, BpmResult, BpmStream};
 pub use adapter::analyze_bpm_for_file;
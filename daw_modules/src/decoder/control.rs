@@ -1,8 +1,71 @@
 // src/decoder/control.rs
 
+use ringbuf::traits::Split;
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
 /// Commands the decoder thread can handle (extend as needed).
 pub enum DecoderCmd {
     Seek(Duration),
+    /// Timeline length / source length; 1.0 turns the time stretch off.
+    SetStretch(f64),
+    /// Resample to this rate from now on (varispeed); followed by a seek.
+    SetOutputRate(u32),
+    /// Source frames per output frame (1.0 = normal speed); pitch follows, like a tape.
+    /// Retunes the running resampler, so it applies on the fly without a seek.
+    SetRate(f64),
+}
+
+/// Commands queued for a decoder before `try_push` refuses more. The decoder drains its
+/// queue before every packet, so this only fills up when its thread has stopped.
+pub const COMMAND_CAPACITY: usize = 64;
+
+/// Sending end of a decoder's command queue. The ring is allocated up front, so a push
+/// never allocates: clips are seeked from the audio callback.
+pub type CmdSender = HeapProd<DecoderCmd>;
+pub type CmdReceiver = HeapCons<DecoderCmd>;
+
+pub fn command_queue() -> (CmdSender, CmdReceiver) {
+    HeapRb::<DecoderCmd>::new(COMMAND_CAPACITY).split()
+}
+
+/// Seek handshake between a decoder and the reader of its ring buffer. The reader can't
+/// tell stale samples (decoded before the decoder got to a seek) from fresh ones, so the
+/// decoder counts every sample it pushes and, once it has handled the latest seek, says
+/// from which sample on the audio is the new position's.
+#[derive(Default)]
+pub struct SeekSync {
+    requested: AtomicU64, // seeks asked for
+    acked: AtomicU64,     // seeks handled
+    fresh_from: AtomicU64, // samples pushed before the last handled seek
+}
+
+impl SeekSync {
+    /// Reader: call before sending `DecoderCmd::Seek`.
+    pub fn request(&self) {
+        self.requested.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Decoder: the seeks asked for so far; pass the result to `ack` once they're handled.
+    pub fn pending(&self) -> u64 {
+        self.requested.load(Ordering::Acquire)
+    }
+
+    /// Decoder: a seek is waiting, so whatever is being pushed is already stale.
+    pub fn is_stale(&self) -> bool {
+        self.requested.load(Ordering::Acquire) != self.acked.load(Ordering::Acquire)
+    }
+
+    /// Decoder: seeks up to `seeks` are handled; samples from index `pushed` on are fresh.
+    pub fn ack(&self, seeks: u64, pushed: u64) {
+        self.fresh_from.store(pushed, Ordering::Relaxed);
+        self.acked.store(seeks, Ordering::Release);
+    }
+
+    /// Reader: the index of the first fresh sample, or `None` while a seek is unhandled.
+    pub fn fresh_from(&self) -> Option<u64> {
+        let acked = self.acked.load(Ordering::Acquire);
+        (acked == self.requested.load(Ordering::Acquire)).then(|| self.fresh_from.load(Ordering::Relaxed))
+    }
 }
@@ -0,0 +1,160 @@
+// src/decoder/damage.rs
+
+// Corrupt and partial audio files. Symphonia reports a damaged packet as a decode error
+// and a broken stretch of container as a demux error; either used to end the file on the
+// spot. Decoders now step over them instead: a damaged packet up to `MAX_FILL_SECS` long
+// is replaced by silence of the same length, so everything after it stays in time, and a
+// longer one is skipped. Only a long run of failures in a row still ends the file. Every
+// damaged range is recorded per file (playback, analysis and export all decode the same
+// files, so a range is only reported once) and handed to the host's listener, if any.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use symphonia::core::formats::Packet;
+use symphonia::core::units::TimeBase;
+
+/// Damaged packets up to this long are filled with silence; longer ones are dropped.
+pub const MAX_FILL_SECS: f64 = 1.0;
+/// Failures in a row after which the rest of the file is given up on.
+pub const MAX_CONSECUTIVE_ERRORS: usize = 64;
+
+/// Ranges closer than this are one range.
+const MERGE_SECS: f64 = 0.001;
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DamagedRange {
+    pub start_secs: f64,
+    pub end_secs: f64,
+    /// Replaced by silence (timing kept) rather than skipped.
+    pub filled: bool,
+}
+
+type Listener = Box<dyn Fn(&str, DamagedRange) + Send + Sync>;
+
+static LISTENER: OnceLock<Listener> = OnceLock::new();
+static RANGES: OnceLock<Mutex<HashMap<String, Vec<DamagedRange>>>> = OnceLock::new();
+
+/// Called with every newly found (or grown) damaged range. Set once, at startup; it runs
+/// on decoder threads, so it must not block.
+pub fn set_listener(listener: impl Fn(&str, DamagedRange) + Send + Sync + 'static) {
+    let _ = LISTENER.set(Box::new(listener));
+}
+
+/// Record a damaged stretch of `path`, merging it into a range it touches.
+pub fn report(path: &str, range: DamagedRange) {
+    let Ok(mut map) = RANGES.get_or_init(Default::default).lock() else { return };
+    let list = map.entry(path.to_string()).or_default();
+    let touching = list.iter_mut().find(|r| {
+        r.filled == range.filled
+            && range.start_secs <= r.end_secs + MERGE_SECS
+            && r.start_secs <= range.end_secs + MERGE_SECS
+    });
+    let reported = match touching {
+        Some(r) if r.start_secs <= range.start_secs && range.end_secs <= r.end_secs => return, // known
+        Some(r) => {
+            r.start_secs = r.start_secs.min(range.start_secs);
+            r.end_secs = r.end_secs.max(range.end_secs);
+            *r
+        }
+        None => {
+            list.push(range);
+            list.sort_by(|a, b| a.start_secs.total_cmp(&b.start_secs));
+            range
+        }
+    };
+    drop(map);
+
+    rt_warn!(
+        "⚠️ Damaged audio in {} at {:.2}s-{:.2}s ({})",
+        path,
+        reported.start_secs,
+        reported.end_secs,
+        if reported.filled { "filled with silence" } else { "skipped" }
+    );
+    if let Some(listener) = LISTENER.get() {
+        listener(path, reported);
+    }
+}
+
+/// Damaged ranges found in `path` so far, in timeline order.
+pub fn damaged_ranges(path: &str) -> Vec<DamagedRange> {
+    RANGES
+        .get()
+        .and_then(|m| m.lock().ok())
+        .and_then(|m| m.get(path).cloned())
+        .unwrap_or_default()
+}
+
+/// Per-decode bookkeeping: where the stream is and how many failures came in a row.
+pub struct Recovery {
+    path: String,
+    time_base: Option<TimeBase>,
+    sample_rate: u32,
+    position_secs: f64,  // end of the last packet read
+    packet_frames: u64,  // length of the last good packet, for packets without a duration
+    consecutive: usize,
+}
+
+impl Recovery {
+    pub fn new(path: &str, time_base: Option<TimeBase>, sample_rate: u32) -> Self {
+        Self {
+            path: path.to_string(),
+            time_base,
+            sample_rate: sample_rate.max(1),
+            position_secs: 0.0,
+            packet_frames: 0,
+            consecutive: 0,
+        }
+    }
+
+    fn secs(&self, ts: u64) -> Option<f64> {
+        self.time_base.map(|tb| {
+            let t = tb.calc_time(ts);
+            t.seconds as f64 + t.frac
+        })
+    }
+
+    /// After a seek, for streams whose packets carry no timestamps.
+    pub fn seeked(&mut self, secs: f64) {
+        self.position_secs = secs;
+        self.consecutive = 0;
+    }
+
+    /// `packet` decoded into `frames` frames.
+    pub fn decoded(&mut self, packet: &Packet, frames: usize) {
+        self.consecutive = 0;
+        self.packet_frames = frames as u64;
+        self.position_secs = match (self.secs(packet.ts()), self.secs(packet.dur())) {
+            (Some(ts), Some(dur)) if packet.dur() > 0 => ts + dur,
+            _ => self.position_secs + frames as f64 / self.sample_rate as f64,
+        };
+    }
+
+    /// `packet` failed to decode. Returns the frames of silence to put in its place (0 to
+    /// skip it), or `None` once too many failures came in a row.
+    pub fn bad_packet(&mut self, packet: &Packet) -> Option<usize> {
+        self.consecutive += 1;
+        let start = self.secs(packet.ts()).unwrap_or(self.position_secs);
+        let dur = match self.secs(packet.dur()) {
+            Some(d) if packet.dur() > 0 => d,
+            _ => self.packet_frames as f64 / self.sample_rate as f64,
+        };
+        self.position_secs = start + dur;
+        let filled = dur <= MAX_FILL_SECS;
+        report(&self.path, DamagedRange { start_secs: start, end_secs: start + dur, filled });
+        if self.consecutive > MAX_CONSECUTIVE_ERRORS {
+            return None;
+        }
+        Some(if filled { (dur * self.sample_rate as f64).round() as usize } else { 0 })
+    }
+
+    /// The demuxer failed mid-file. True to keep reading (it resyncs on the next packet),
+    /// false once too many failures came in a row.
+    pub fn bad_read(&mut self) -> bool {
+        self.consecutive += 1;
+        let at = self.position_secs;
+        report(&self.path, DamagedRange { start_secs: at, end_secs: at, filled: false });
+        self.consecutive <= MAX_CONSECUTIVE_ERRORS
+    }
+}
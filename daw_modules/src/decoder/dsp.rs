@@ -1,5 +1,10 @@
 // src/decoder/dsp.rs
 
+use rubato::{
+    calculate_cutoff, Resampler, SincFixedIn, SincInterpolationParameters,
+    SincInterpolationType, WindowFunction,
+};
+
 pub fn append_interleaved_to_planar(
     interleaved: &[f32],
     planar: &mut [Vec<f32>],
@@ -45,58 +50,420 @@ pub fn interleave(planar: &mut [Vec<f32>]) -> Vec<f32> {
     out
 }
 
+/// Up/down-mix by channel count, assuming the usual layout for each count (see `layout_for`).
 pub fn updown_mix_interleaved(input: &[f32], in_ch: usize, out_ch: usize) -> Vec<f32> {
     if in_ch == out_ch {
         return input.to_vec();
     }
-    let frames = input.len() / in_ch;
-    let mut out = vec![0.0f32; frames * out_ch];
+    let mut out = Vec::new();
+    ChannelMap::new(in_ch, out_ch).apply(input, &mut out);
+    out
+}
 
-    match (in_ch, out_ch) {
-        (1, 2) => {
-            for f in 0..frames {
-                let m = input[f];
-                out[f * 2] = m;
-                out[f * 2 + 1] = m;
-            }
+// --- CHANNEL MAPPING ---
+
+/// Where a channel is meant to be heard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Speaker {
+    FrontLeft,
+    FrontRight,
+    FrontCentre,
+    Lfe,
+    RearLeft,
+    RearRight,
+    RearCentre,
+    SideLeft,
+    SideRight,
+    /// Anything else (height channels, unlabelled ones past 7.1).
+    Other,
+}
+
+const MINUS_3DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// The layout files without a channel mask get: WAV/FLAC (SMPTE) order up to 7.1.
+pub fn layout_for(channels: usize) -> Vec<Speaker> {
+    use Speaker::*;
+    match channels {
+        1 => vec![FrontCentre],
+        2 => vec![FrontLeft, FrontRight],
+        3 => vec![FrontLeft, FrontRight, FrontCentre],
+        4 => vec![FrontLeft, FrontRight, RearLeft, RearRight],
+        5 => vec![FrontLeft, FrontRight, FrontCentre, RearLeft, RearRight],
+        6 => vec![FrontLeft, FrontRight, FrontCentre, Lfe, RearLeft, RearRight],
+        7 => vec![FrontLeft, FrontRight, FrontCentre, Lfe, RearCentre, SideLeft, SideRight],
+        8 => vec![FrontLeft, FrontRight, FrontCentre, Lfe, RearLeft, RearRight, SideLeft, SideRight],
+        n => vec![Other; n],
+    }
+}
+
+/// Speakers of a decoded stream, in the order its channels come in.
+pub fn layout_from_symphonia(channels: symphonia::core::audio::Channels) -> Vec<Speaker> {
+    use symphonia::core::audio::Channels as C;
+    channels
+        .iter()
+        .map(|c| match c {
+            C::FRONT_LEFT => Speaker::FrontLeft,
+            C::FRONT_RIGHT => Speaker::FrontRight,
+            C::FRONT_CENTRE => Speaker::FrontCentre,
+            C::LFE1 => Speaker::Lfe,
+            C::REAR_LEFT => Speaker::RearLeft,
+            C::REAR_RIGHT => Speaker::RearRight,
+            C::REAR_CENTRE => Speaker::RearCentre,
+            C::SIDE_LEFT => Speaker::SideLeft,
+            C::SIDE_RIGHT => Speaker::SideRight,
+            _ => Speaker::Other,
+        })
+        .collect()
+}
+
+// Where a speaker missing from the output is folded to: the first option whose speakers
+// the output all has. An empty list (the LFE) drops the channel.
+fn fold_options(speaker: Speaker) -> &'static [&'static [(Speaker, f32)]] {
+    use Speaker::*;
+    match speaker {
+        FrontLeft => &[&[(FrontCentre, MINUS_3DB)]],
+        FrontRight => &[&[(FrontCentre, MINUS_3DB)]],
+        FrontCentre => &[&[(FrontLeft, MINUS_3DB), (FrontRight, MINUS_3DB)]],
+        RearLeft => &[&[(SideLeft, 1.0)], &[(FrontLeft, MINUS_3DB)], &[(FrontCentre, 0.5)]],
+        RearRight => &[&[(SideRight, 1.0)], &[(FrontRight, MINUS_3DB)], &[(FrontCentre, 0.5)]],
+        SideLeft => &[&[(RearLeft, 1.0)], &[(FrontLeft, MINUS_3DB)], &[(FrontCentre, 0.5)]],
+        SideRight => &[&[(RearRight, 1.0)], &[(FrontRight, MINUS_3DB)], &[(FrontCentre, 0.5)]],
+        RearCentre => &[
+            &[(RearLeft, MINUS_3DB), (RearRight, MINUS_3DB)],
+            &[(SideLeft, MINUS_3DB), (SideRight, MINUS_3DB)],
+            &[(FrontLeft, 0.5), (FrontRight, 0.5)],
+            &[(FrontCentre, 0.5)],
+        ],
+        Lfe | Other => &[],
+    }
+}
+
+/// Gains from every input channel to every output channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelMap {
+    inputs: usize,
+    outputs: usize,
+    gains: Vec<f32>, // row per output channel, column per input channel
+}
+
+impl ChannelMap {
+    pub fn identity(channels: usize) -> Self {
+        let mut map = Self::silent(channels, channels);
+        for c in 0..channels {
+            map.set(c, c, 1.0);
         }
-        (2, 1) => {
-            for f in 0..frames {
-                let l = input[f * 2];
-                let r = input[f * 2 + 1];
-                out[f] = 0.5 * (l + r);
-            }
+        map
+    }
+
+    fn silent(inputs: usize, outputs: usize) -> Self {
+        Self { inputs, outputs, gains: vec![0.0; inputs * outputs] }
+    }
+
+    fn set(&mut self, output: usize, input: usize, gain: f32) {
+        self.gains[output * self.inputs + input] = gain;
+    }
+
+    /// Default mapping between channel counts. Mono goes to every output, stereo to a mono
+    /// output is averaged and spread over wider outputs pair by pair; anything with a known
+    /// surround layout is mapped speaker by speaker (see `between`).
+    pub fn new(inputs: usize, outputs: usize) -> Self {
+        if inputs == outputs {
+            return Self::identity(inputs);
         }
-        _ if out_ch < in_ch => {
-            let factor = in_ch as f32 / out_ch as f32;
-            for f in 0..frames {
-                for oc in 0..out_ch {
-                    let start = (oc as f32 * factor).floor() as usize;
-                    let end = (((oc + 1) as f32 * factor).ceil() as usize).min(in_ch);
-                    let mut acc = 0.0f32;
-                    let mut n = 0usize;
-                    for ic in start..end {
-                        acc += input[f * in_ch + ic];
-                        n += 1;
+        let (in_layout, out_layout) = (layout_for(inputs), layout_for(outputs));
+        let surround = inputs > 2 && !in_layout.contains(&Speaker::Other) && !out_layout.contains(&Speaker::Other);
+        let mut map = Self::silent(inputs, outputs);
+        match (inputs, outputs) {
+            (0, _) | (_, 0) => {}
+            (1, _) => map.gains.fill(1.0),
+            (2, 1) => map.gains.fill(0.5),
+            _ if surround => return Self::between(&in_layout, &out_layout),
+            _ if outputs < inputs => {
+                // Unknown layout: average neighbouring inputs into each output
+                let factor = inputs as f32 / outputs as f32;
+                for o in 0..outputs {
+                    let start = (o as f32 * factor).floor() as usize;
+                    let end = (((o + 1) as f32 * factor).ceil() as usize).min(inputs);
+                    let n = end.saturating_sub(start).max(1) as f32;
+                    for i in start..end {
+                        map.set(o, i, 1.0 / n);
                     }
-                    out[f * out_ch + oc] = if n > 0 { acc / n as f32 } else { 0.0 };
+                }
+            }
+            _ => {
+                for o in 0..outputs {
+                    map.set(o, o % inputs, 1.0);
                 }
             }
         }
-        _ => {
-            for f in 0..frames {
-                for oc in 0..out_ch {
-                    let ic = oc % in_ch;
-                    out[f * out_ch + oc] = input[f * in_ch + ic];
+        map
+    }
+
+    /// Speaker-aware mapping: channels the output has go straight through, the rest fold
+    /// down the way an ITU/Dolby Lo/Ro downmix does (centre and surrounds at -3 dB into the
+    /// fronts, LFE dropped). Not normalized: loud surround material can sum past 0 dBFS,
+    /// which the float mix carries to the master limiter.
+    pub fn between(input: &[Speaker], output: &[Speaker]) -> Self {
+        let mut map = Self::silent(input.len(), output.len());
+        let slot = |s: Speaker| output.iter().position(|&o| o == s && s != Speaker::Other);
+        for (i, &speaker) in input.iter().enumerate() {
+            if let Some(o) = slot(speaker) {
+                map.set(o, i, 1.0);
+                continue;
+            }
+            let option = fold_options(speaker).iter().find(|opt| opt.iter().all(|&(s, _)| slot(s).is_some()));
+            for &(s, gain) in option.copied().unwrap_or_default() {
+                if let Some(o) = slot(s) {
+                    map.set(o, i, gain);
                 }
             }
         }
+        map
     }
 
-    out
+    /// Mapping for a decoded stream whose speakers are known. Falls back to `new` for mono,
+    /// stereo and layouts with unlabelled channels.
+    pub fn for_layout(input: &[Speaker], outputs: usize) -> Self {
+        let out_layout = layout_for(outputs);
+        if input.len() <= 2 || input.contains(&Speaker::Other) || out_layout.contains(&Speaker::Other) {
+            return Self::new(input.len(), outputs);
+        }
+        Self::between(input, &out_layout)
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+
+    pub fn gain(&self, output: usize, input: usize) -> f32 {
+        self.gains[output * self.inputs + input]
+    }
+
+    /// Map interleaved `input` (whole frames of `inputs` channels) into `out`.
+    pub fn apply(&self, input: &[f32], out: &mut Vec<f32>) {
+        out.clear();
+        if self.inputs == 0 {
+            return;
+        }
+        let frames = input.len() / self.inputs;
+        out.resize(frames * self.outputs, 0.0);
+        for (src, dst) in input.chunks_exact(self.inputs).zip(out.chunks_exact_mut(self.outputs.max(1))) {
+            for (o, d) in dst.iter_mut().enumerate() {
+                let row = &self.gains[o * self.inputs..(o + 1) * self.inputs];
+                *d = row.iter().zip(src).map(|(g, s)| g * s).sum();
+            }
+        }
+    }
 }
 
 #[inline]
 pub fn fade_samples_ms(sample_rate: u32, ms: u32) -> usize {
     ((sample_rate as u64 * ms as u64) / 1000) as usize
 }
+
+// --- OFFLINE RESAMPLING ---
+
+/// Trade-off for `offline_resample`. Streaming playback keeps its own resampler in `decoder::resample`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResampleQuality {
+    Fast,
+    #[default]
+    Balanced,
+    Best,
+}
+
+/// Resample a whole interleaved buffer from `from_rate` to `to_rate`.
+///
+/// The tail is flushed, so the result is aligned
+/// with the input and always holds exactly `round(frames * to_rate / from_rate)` frames.
+/// Used by export (and anything else rendering a complete buffer); not realtime safe.
+pub fn offline_resample(
+    input: &[f32],
+    channels: usize,
+    from_rate: u32,
+    to_rate: u32,
+    quality: ResampleQuality,
+) -> Vec<f32> {
+    if channels == 0 || input.len() < channels {
+        return Vec::new();
+    }
+    let in_frames = input.len() / channels;
+    let input = &input[..in_frames * channels];
+    if from_rate == to_rate || from_rate == 0 || to_rate == 0 {
+        return input.to_vec();
+    }
+
+    let ratio = to_rate as f64 / from_rate as f64;
+    let expected = (in_frames as f64 * ratio).round() as usize;
+
+    let (sinc_len, oversampling_factor, interpolation) = match quality {
+        ResampleQuality::Fast => (64, 64, SincInterpolationType::Linear),
+        ResampleQuality::Balanced => (128, 128, SincInterpolationType::Linear),
+        ResampleQuality::Best => (256, 256, SincInterpolationType::Cubic),
+    };
+    let window = WindowFunction::BlackmanHarris2;
+    let params = SincInterpolationParameters {
+        sinc_len,
+        f_cutoff: calculate_cutoff(sinc_len, window),
+        interpolation,
+        oversampling_factor,
+        window,
+    };
+
+    let chunk_size = 1024;
+    let mut resampler = match SincFixedIn::<f32>::new(ratio, 1.0, params, chunk_size, channels) {
+        Ok(r) => r,
+        Err(e) => {
+            rt_warn!("⚠️ Offline resampler init failed ({}), falling back to linear", e);
+            return linear_resample(input, channels, in_frames, expected);
+        }
+    };
+    // `SincFixedIn` starts reading half a filter before the first input frame, so its
+    // output is already aligned with the input (`output_delay()` is not to be trimmed)
+
+    let mut planar = vec![Vec::with_capacity(in_frames); channels];
+    append_interleaved_to_planar(input, &mut planar, channels);
+
+    let mut out: Vec<Vec<f32>> = vec![Vec::with_capacity(expected + chunk_size); channels];
+
+    // 1. Full chunks
+    let mut pos = 0;
+    while in_frames - pos >= resampler.input_frames_next() {
+        let n = resampler.input_frames_next();
+        let block: Vec<&[f32]> = planar.iter().map(|c| &c[pos..pos + n]).collect();
+        match resampler.process(&block, None) {
+            Ok(o) => append_planar(&mut out, o),
+            Err(_) => break,
+        }
+        pos += n;
+    }
+
+    // 2. Remainder (zero-padded internally by rubato)
+    if pos < in_frames {
+        let block: Vec<&[f32]> = planar.iter().map(|c| &c[pos..]).collect();
+        if let Ok(o) = resampler.process_partial(Some(&block), None) {
+            append_planar(&mut out, o);
+        }
+    }
+
+    // 3. Flush the filter tail until the output covers the whole input
+    while out[0].len() < expected {
+        match resampler.process_partial::<Vec<f32>>(None, None) {
+            Ok(o) if !o.is_empty() && !o[0].is_empty() => append_planar(&mut out, o),
+            _ => break,
+        }
+    }
+
+    // 4. Pin the length
+    let mut result = vec![0.0f32; expected * channels];
+    for (ch, data) in out.iter().enumerate() {
+        for f in 0..expected {
+            result[f * channels + ch] = data.get(f).copied().unwrap_or(0.0);
+        }
+    }
+    result
+}
+
+fn append_planar(out: &mut [Vec<f32>], block: Vec<Vec<f32>>) {
+    for (dst, src) in out.iter_mut().zip(block) {
+        dst.extend_from_slice(&src);
+    }
+}
+
+fn linear_resample(input: &[f32], channels: usize, in_frames: usize, out_frames: usize) -> Vec<f32> {
+    let mut out = vec![0.0f32; out_frames * channels];
+    if in_frames == 0 {
+        return out;
+    }
+    let step = in_frames as f64 / out_frames.max(1) as f64;
+    for f in 0..out_frames {
+        let src = f as f64 * step;
+        let i0 = (src.floor() as usize).min(in_frames - 1);
+        let i1 = (i0 + 1).min(in_frames - 1);
+        let t = (src - i0 as f64) as f32;
+        for ch in 0..channels {
+            let a = input[i0 * channels + ch];
+            let b = input[i1 * channels + ch];
+            out[f * channels + ch] = a + (b - a) * t;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    fn sine(freq: f64, rate: u32, frames: usize) -> Vec<f32> {
+        (0..frames).map(|i| (TAU * freq * i as f64 / rate as f64).sin() as f32 * 0.5).collect()
+    }
+
+    /// Amplitude of `freq` in `signal` and the RMS of what is left once it is removed,
+    /// measured away from the edges.
+    fn fit(signal: &[f32], freq: f64, rate: u32) -> (f64, f64) {
+        let body = &signal[signal.len() / 8..signal.len() * 7 / 8];
+        let start = signal.len() / 8;
+        let (mut s, mut c) = (0.0, 0.0);
+        for (i, x) in body.iter().enumerate() {
+            let phase = TAU * freq * (start + i) as f64 / rate as f64;
+            s += *x as f64 * phase.sin();
+            c += *x as f64 * phase.cos();
+        }
+        let (s, c) = (2.0 * s / body.len() as f64, 2.0 * c / body.len() as f64);
+        let residual = body.iter().enumerate().map(|(i, x)| {
+            let phase = TAU * freq * (start + i) as f64 / rate as f64;
+            let r = *x as f64 - (s * phase.sin() + c * phase.cos());
+            r * r
+        });
+        ((s * s + c * c).sqrt(), (residual.sum::<f64>() / body.len() as f64).sqrt())
+    }
+
+    #[test]
+    fn resampled_sine_keeps_its_frequency_and_is_clean() {
+        for (from, to) in [(44_100, 48_000), (48_000, 44_100), (48_000, 96_000)] {
+            let input = sine(1_000.0, from, from as usize);
+            let out = offline_resample(&input, 1, from, to, ResampleQuality::Best);
+            let (amplitude, residual) = fit(&out, 1_000.0, to);
+            assert!((amplitude - 0.5).abs() < 0.01, "{from}->{to}: amplitude {amplitude}");
+            // THD+N below -60 dB
+            assert!(residual / amplitude < 1e-3, "{from}->{to}: residual {residual}");
+        }
+    }
+
+    #[test]
+    fn resampled_length_matches_the_ideal() {
+        for (from, to, frames) in [(44_100, 48_000, 44_100), (48_000, 44_100, 12_345), (22_050, 48_000, 1_000)] {
+            for channels in [1, 2] {
+                let input = vec![0.25f32; frames * channels];
+                let out = offline_resample(&input, channels, from, to, ResampleQuality::Balanced);
+                let ideal = frames as f64 * to as f64 / from as f64;
+                let got = (out.len() / channels) as f64;
+                assert_eq!(out.len() % channels, 0);
+                assert!((got - ideal).abs() <= 2.0, "{from}->{to}: {got} frames, ideal {ideal}");
+            }
+        }
+    }
+
+    #[test]
+    fn resampling_is_aligned_with_the_input() {
+        // A step halfway through stays halfway through
+        let mut input = vec![0.0f32; 4_410];
+        input[2_205..].fill(1.0);
+        for quality in [ResampleQuality::Fast, ResampleQuality::Balanced, ResampleQuality::Best] {
+            let out = offline_resample(&input, 1, 44_100, 48_000, quality);
+            let crossing = out.iter().position(|&x| x >= 0.5).unwrap();
+            assert!((crossing as i64 - 2_400).abs() <= 2, "{quality:?}: step at {crossing}");
+        }
+    }
+
+    #[test]
+    fn same_rate_is_a_copy() {
+        let input = sine(440.0, 48_000, 1_000);
+        assert_eq!(offline_resample(&input, 1, 48_000, 48_000, ResampleQuality::Fast), input);
+    }
+}
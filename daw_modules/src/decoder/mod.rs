@@ -1,23 +1,27 @@
 // src/decoder/mod.rs
 
+// Realtime/decoder threads: log through rt_log, never std streams.
+#![deny(clippy::print_stdout, clippy::print_stderr)]
+
 pub mod control;
+pub mod damage;
 pub mod dsp;
 pub mod output;
 pub mod resample;
 pub mod pipe;
+pub mod remote;
+pub mod stretch;
 
 use anyhow::anyhow;
-use ringbuf::traits::Producer as RbProducer;
+use ringbuf::traits::{Consumer, Observer, Producer as RbProducer};
 use rubato::Resampler; // for .reset()
-use std::fs::File;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    mpsc::{channel, Receiver, Sender},
     Arc,
 };
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
-use symphonia::core::audio::{AudioBufferRef, SampleBuffer};
+use symphonia::core::audio::{AudioBufferRef, Channels, SampleBuffer};
 use symphonia::core::codecs::DecoderOptions;
 use symphonia::core::errors::Error as SymphoniaError;
 use symphonia::core::formats::{FormatOptions, SeekMode, SeekTo};
@@ -25,7 +29,11 @@ use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::default::{get_codecs, get_probe};
 
-pub use control::DecoderCmd;
+pub use control::{CmdReceiver, CmdSender, DecoderCmd, SeekSync};
+
+/// Seeks land this far ahead of the target and decode up to it, so codecs that need a
+/// packet or two of history (MP3's bit reservoir) have settled by the first kept sample.
+const SEEK_PREROLL: Duration = Duration::from_millis(100);
 
 pub struct Decoder<P>
 where
@@ -37,14 +45,22 @@ where
     output_channels: usize,
     source_sample_rate: u32,
     output_sample_rate: u32,
-    cmd_rx: Receiver<DecoderCmd>,
+    cmd_rx: CmdReceiver,
     post_seek_fade_samples: usize,
+    stretcher: Option<stretch::TimeStretcher>, // after resampling, at the output rate
+    rate: f64, // varispeed on top of the rate conversion (source frames per output frame)
+    channel_layout: Option<Channels>, // of the last decoded packet
+    channel_map: Option<dsp::ChannelMap>, // None: channels pass straight through (or mix by count)
+    seek_sync: Arc<SeekSync>,
+    pushed: u64, // samples pushed into the ring buffer so far, for `seek_sync`
+    resampler_delay_left: usize, // output frames of resampler delay line still to drop
 }
 
 impl<P> Decoder<P>
 where
     P: RbProducer<Item = f32> + Send + 'static,
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new_with_ctrl(
         path: String,
         producer: P,
@@ -53,7 +69,8 @@ where
         output_channels: usize,
         source_sample_rate: u32,
         output_sample_rate: u32,
-        cmd_rx: Receiver<DecoderCmd>,
+        cmd_rx: CmdReceiver,
+        seek_sync: Arc<SeekSync>,
     ) -> Self {
         Self {
             path,
@@ -64,13 +81,20 @@ where
             output_sample_rate,
             cmd_rx,
             post_seek_fade_samples: 0,
+            stretcher: None,
+            rate: 1.0,
+            channel_layout: None,
+            channel_map: None,
+            seek_sync,
+            pushed: 0,
+            resampler_delay_left: 0,
         }
     }
 
     pub fn spawn(self) -> JoinHandle<()> {
         thread::spawn(move || {
             if let Err(e) = self.run() {
-                eprintln!("Decoder thread error: {e}");
+                rt_error!("Decoder thread error: {e}");
             }
         })
     }
@@ -78,8 +102,8 @@ where
     // In src/decoder/mod.rs -> impl Decoder -> fn run
 
     fn run(mut self) -> Result<(), anyhow::Error> {
-        let file = File::open(&self.path)?;
-        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+        let source = remote::open_source(&self.path)?;
+        let mss = MediaSourceStream::new(source, Default::default());
         let probed = get_probe().format(
             &Default::default(),
             mss,
@@ -96,6 +120,7 @@ where
         let mut decoder = get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
         let mut sample_buf: Option<SampleBuffer<f32>> = None;
         let actual_rate = track.codec_params.sample_rate.unwrap_or(self.source_sample_rate);
+        let mut recovery = damage::Recovery::new(&self.path, track.codec_params.time_base, actual_rate);
 
         let mut resampler =
             resample::build_resampler(
@@ -103,52 +128,125 @@ where
                 self.output_sample_rate,
                 self.output_channels)?;
         let mut stage_planar: Vec<Vec<f32>> = vec![Vec::with_capacity(4096); self.output_channels];
+        self.resampler_delay_left = resampler.as_ref().map(|r| r.output_delay()).unwrap_or(0);
+
+        // After a seek: the target, in track timestamps; decoded audio before it is dropped
+        let time_base = track.codec_params.time_base;
+        let mut skip_until: Option<u64> = None;
 
         // Flag to track "End of File"
         let mut eof_reached = false;
+        let mut tail_flushed = false; // resampler leftovers pushed out after EOF
 
         loop {
-            // --- FIX 1: Handle Disconnects (Exit Thread) ---
-            // Use a loop to process all pending commands
-            loop {
-                match self.cmd_rx.try_recv() {
-                    Ok(cmd) => match cmd {
-                        DecoderCmd::Seek(target) => {
-                            let seconds = target.as_secs();
-                            let frac = target.subsec_nanos() as f64 / 1_000_000_000f64;
-                            let time = symphonia::core::units::Time::new(seconds, frac);
-                            
-                            // Try to seek
-                            if let Err(e) = format.seek(
-                                SeekMode::Accurate,
-                                SeekTo::Time {
-                                    time,
-                                    track_id: Some(track_id),
-                                },
-                            ) {
-                                eprintln!("Seek error: {}", e);
+            // Handle every pending command, then exit once the controller is gone (app closed)
+            while let Some(cmd) = self.cmd_rx.try_pop() {
+                match cmd {
+                    DecoderCmd::Seek(target) => {
+                        let seeks = self.seek_sync.pending();
+                        let to_time = |d: Duration| {
+                            symphonia::core::units::Time::new(d.as_secs(), d.subsec_nanos() as f64 / 1_000_000_000f64)
+                        };
+                        let from = target.saturating_sub(SEEK_PREROLL);
+
+                        // Try to seek
+                        if let Err(e) = format.seek(
+                            SeekMode::Accurate,
+                            SeekTo::Time {
+                                time: to_time(from),
+                                track_id: Some(track_id),
+                            },
+                        ) {
+                            // Nothing to play from there (e.g. past the end): stay silent
+                            // rather than carry on from the old position
+                            rt_error!("Seek error: {}", e);
+                            skip_until = None;
+                            eof_reached = true;
+                            tail_flushed = true;
+                        } else {
+                            // Seek Success -> Reset EOF
+                            eof_reached = false; 
+                            tail_flushed = false;
+                            recovery.seeked(from.as_secs_f64());
+                            decoder.reset();
+                            // The demuxer lands on a packet at or before `from`: decode
+                            // from there and keep only what starts at `target`
+                            skip_until = time_base.map(|tb| tb.calc_timestamp(to_time(target)));
+                        }
+
+                        // Clear buffers on seek
+                        sample_buf = None;
+                        for ch in &mut stage_planar { ch.clear(); }
+                        if let Some(r) = &mut resampler { r.reset(); }
+                        if let Some(s) = &mut self.stretcher { s.reset(); }
+                        self.resampler_delay_left = resampler.as_ref().map(|r| r.output_delay()).unwrap_or(0);
+                        self.post_seek_fade_samples =
+                            dsp::fade_samples_ms(self.output_sample_rate, 10) * self.output_channels;
+                        self.seek_sync.ack(seeks, self.pushed);
+                    }
+                    // The clip seeks right after, which flushes what was decoded at the old ratio
+                    DecoderCmd::SetStretch(ratio) => {
+                        self.stretcher = (ratio != 1.0).then(|| {
+                            stretch::TimeStretcher::new(ratio, self.output_channels, self.output_sample_rate)
+                        });
+                    }
+                    DecoderCmd::SetOutputRate(rate) => {
+                        let ratio = resample::playback_ratio(actual_rate, rate, self.rate);
+                        match resample::build_resampler_for_ratio(ratio, self.output_channels) {
+                            Ok(r) => {
+                                resampler = r;
+                                self.output_sample_rate = rate;
+                                for ch in &mut stage_planar { ch.clear(); }
+                            }
+                            Err(e) => rt_error!("Resampler rebuild failed: {}", e),
+                        }
+                    }
+                    DecoderCmd::SetRate(rate) => {
+                        let rate = if rate.is_finite() { rate.clamp(resample::MIN_RATE, resample::MAX_RATE) } else { 1.0 };
+                        if rate != self.rate {
+                            let ratio = resample::playback_ratio(actual_rate, self.output_sample_rate, rate);
+                            // Ramp the running resampler to the new ratio; only a change past
+                            // its range (or from no resampler at all) needs a new one, whose
+                            // fresh delay line costs a few ms of the staged audio's alignment
+                            if resampler.as_mut().is_some_and(|r| r.set_resample_ratio(ratio, true).is_ok()) {
+                                self.rate = rate;
                             } else {
-                                // Seek Success -> Reset EOF
-                                eof_reached = false; 
+                                match resample::build_resampler_for_ratio(ratio, self.output_channels) {
+                                    Ok(r) => {
+                                        if r.is_none() {
+                                            for ch in &mut stage_planar { ch.clear(); }
+                                        }
+                                        resampler = r;
+                                        self.rate = rate;
+                                    }
+                                    Err(e) => rt_error!("Resampler rebuild failed: {}", e),
+                                }
                             }
-
-                            // Clear buffers on seek
-                            sample_buf = None;
-                            for ch in &mut stage_planar { ch.clear(); }
-                            if let Some(r) = &mut resampler { r.reset(); }
-                            self.post_seek_fade_samples =
-                                dsp::fade_samples_ms(self.output_sample_rate, 10) * self.output_channels;
                         }
-                    },
-                    // No more commands right now -> Break inner loop, continue decoding
-                    Err(std::sync::mpsc::TryRecvError::Empty) => break, 
-                    // Controller Disconnected -> APP CLOSED -> Return to exit thread
-                    Err(std::sync::mpsc::TryRecvError::Disconnected) => return Ok(()), 
+                    }
                 }
             }
+            if !self.cmd_rx.write_is_held() {
+                return Ok(());
+            }
 
             // 2. If at EOF, just wait.
             if eof_reached {
+                // Files (or tails) shorter than one resampler chunk would otherwise never be heard
+                if !tail_flushed {
+                    tail_flushed = true;
+                    if let Some(r) = resampler.as_mut() {
+                        self.flush_resampler(r, &mut stage_planar);
+                    }
+                    if let Some(s) = self.stretcher.as_mut() {
+                        let mut tail = Vec::new();
+                        s.finish(&mut tail);
+                        let sync = &self.seek_sync;
+                        self.pushed += output::push_with_fade(&mut self.producer, &tail, &mut self.post_seek_fade_samples, || {
+                            sync.is_stale()
+                        }) as u64;
+                    }
+                }
                 thread::sleep(Duration::from_millis(10));
                 continue;
             }
@@ -164,6 +262,8 @@ where
                     eof_reached = true; // Mark EOF
                     continue; 
                 }
+                // A damaged stretch of container: the demuxer resyncs on the next packet
+                Err(SymphoniaError::DecodeError(_)) if recovery.bad_read() => continue,
                 Err(_) => {
                     eof_reached = true; // Treat error as EOF
                     continue; 
@@ -175,39 +275,42 @@ where
             match decoder.decode(&packet) {
                 Ok(decoded) => {
                     let decoded_ch = decoded.spec().channels.count();
+                    recovery.decoded(&packet, decoded.frames());
+                    self.update_channel_map(decoded.spec().channels);
 
                     if sample_buf.is_none() {
                         let capacity = decoded.capacity() as u64;
                         sample_buf = Some(SampleBuffer::<f32>::new(capacity, *decoded.spec()));
                     }
+                    let frames = decoded.frames();
                     let buf = sample_buf.as_mut().unwrap();
 
-                    copy_interleaved_into_f32(buf, decoded);
-                    let src_interleaved = buf.samples();
-
-                    if resampler.is_some() {
-                        if decoded_ch == self.output_channels {
-                            dsp::append_interleaved_to_planar(src_interleaved, &mut stage_planar, self.output_channels);
-                        } else {
-                            let mixed = dsp::updown_mix_interleaved(src_interleaved, decoded_ch, self.output_channels);
-                            dsp::append_interleaved_to_planar(&mixed, &mut stage_planar, self.output_channels);
+                    // Pre-roll after a seek: drop what comes before the target
+                    let mut skip = 0usize;
+                    if let (Some(until), Some(tb)) = (skip_until, time_base) {
+                        if packet.ts() < until {
+                            let ahead = tb.calc_time(until - packet.ts());
+                            skip = ((ahead.seconds as f64 + ahead.frac) * actual_rate as f64).round() as usize;
                         }
-
-                        while let Some(mut out_block) = resample::try_process_exact(resampler.as_mut().unwrap(), &mut stage_planar) {
-                            let interleaved_out = dsp::interleave(out_block.as_mut_slice());
-                            output::push_with_fade(&mut self.producer, &interleaved_out, &mut self.post_seek_fade_samples);
-                        }
-                    } else {
-                        if decoded_ch == self.output_channels {
-                            output::push_with_fade(&mut self.producer, src_interleaved, &mut self.post_seek_fade_samples);
-                        } else {
-                            let mixed = dsp::updown_mix_interleaved(src_interleaved, decoded_ch, self.output_channels);
-                            output::push_with_fade(&mut self.producer, &mixed, &mut self.post_seek_fade_samples);
+                        if skip < frames {
+                            skip_until = None;
                         }
                     }
+
+                    copy_interleaved_into_f32(buf, decoded);
+                    if skip < frames {
+                        self.push_source(&buf.samples()[skip * decoded_ch..], decoded_ch, &mut resampler, &mut stage_planar);
+                    }
                 }
-                Err(SymphoniaError::IoError(_)) => continue,
-                Err(SymphoniaError::DecodeError(_)) => continue,
+                // A damaged packet: silence in its place keeps the rest of the file in time
+                Err(SymphoniaError::IoError(_)) | Err(SymphoniaError::DecodeError(_)) => match recovery.bad_packet(&packet) {
+                    Some(0) => {}
+                    Some(frames) => {
+                        let silence = vec![0.0f32; frames * self.output_channels];
+                        self.push_source(&silence, self.output_channels, &mut resampler, &mut stage_planar);
+                    }
+                    None => eof_reached = true,
+                },
                 Err(_) => {
                     eof_reached = true;
                 }
@@ -218,6 +321,103 @@ where
             }
         }
     }
+
+    // Surround files are mapped by speaker position when the stream names its channels
+    fn update_channel_map(&mut self, layout: Channels) {
+        if self.channel_layout == Some(layout) {
+            return;
+        }
+        self.channel_layout = Some(layout);
+        let map = dsp::ChannelMap::for_layout(&dsp::layout_from_symphonia(layout), self.output_channels);
+        self.channel_map = (map != dsp::ChannelMap::identity(self.output_channels)).then_some(map);
+    }
+
+    fn remix(&self, src_interleaved: &[f32], src_channels: usize) -> Vec<f32> {
+        match &self.channel_map {
+            Some(map) if map.inputs() == src_channels => {
+                let mut mixed = Vec::with_capacity(src_interleaved.len() / src_channels.max(1) * self.output_channels);
+                map.apply(src_interleaved, &mut mixed);
+                mixed
+            }
+            _ => dsp::updown_mix_interleaved(src_interleaved, src_channels, self.output_channels),
+        }
+    }
+
+    // Decoded (or filled-in) source audio: channel mapping, then the resampler if any
+    fn push_source(
+        &mut self,
+        src_interleaved: &[f32],
+        src_channels: usize,
+        resampler: &mut Option<rubato::SincFixedIn<f32>>,
+        stage_planar: &mut [Vec<f32>],
+    ) {
+        match resampler.as_mut() {
+            Some(r) => {
+                if src_channels == self.output_channels && self.channel_map.is_none() {
+                    dsp::append_interleaved_to_planar(src_interleaved, stage_planar, self.output_channels);
+                } else {
+                    let mixed = self.remix(src_interleaved, src_channels);
+                    dsp::append_interleaved_to_planar(&mixed, stage_planar, self.output_channels);
+                }
+
+                while let Some(mut out_block) = resample::try_process_exact(r, stage_planar) {
+                    let interleaved_out = dsp::interleave(out_block.as_mut_slice());
+                    self.emit_resampled(&interleaved_out);
+                }
+            }
+            None => {
+                if src_channels == self.output_channels && self.channel_map.is_none() {
+                    self.emit(src_interleaved);
+                } else {
+                    let mixed = self.remix(src_interleaved, src_channels);
+                    self.emit(&mixed);
+                }
+            }
+        }
+    }
+
+    /// Push whatever is still staged (less than a chunk) plus the resampler's delay line.
+    fn flush_resampler(&mut self, resampler: &mut rubato::SincFixedIn<f32>, stage_planar: &mut [Vec<f32>]) {
+        let mut blocks = Vec::with_capacity(2);
+        if let Some(mut rest) = resample::drain_remaining_planar(stage_planar) {
+            match resample::process_partial_some(resampler, &mut rest) {
+                Ok(Some(out)) => blocks.push(out),
+                Ok(None) => {}
+                Err(e) => rt_warn!("Resampler flush failed: {}", e),
+            }
+        }
+        if let Ok(Some(out)) = resample::process_partial_none(resampler) {
+            blocks.push(out);
+        }
+        for mut block in blocks {
+            let interleaved_out = dsp::interleave(block.as_mut_slice());
+            self.emit_resampled(&interleaved_out);
+        }
+    }
+
+    // Resampler output starts with its delay line; dropping that keeps a seek's first
+    // sample at the target instead of a few ms later
+    fn emit_resampled(&mut self, interleaved: &[f32]) {
+        let skip = (self.resampler_delay_left * self.output_channels).min(interleaved.len());
+        self.resampler_delay_left -= skip / self.output_channels.max(1);
+        if skip < interleaved.len() {
+            self.emit(&interleaved[skip..]);
+        }
+    }
+
+    // Last stage before the ring buffer: time stretch (if any), then the post-seek fade
+    fn emit(&mut self, interleaved: &[f32]) {
+        let sync = &self.seek_sync;
+        let pushed = match self.stretcher.as_mut() {
+            Some(stretcher) => {
+                let mut stretched = Vec::with_capacity((interleaved.len() as f64 * stretcher.ratio()) as usize + 64);
+                stretcher.process(interleaved, &mut stretched);
+                output::push_with_fade(&mut self.producer, &stretched, &mut self.post_seek_fade_samples, || sync.is_stale())
+            }
+            None => output::push_with_fade(&mut self.producer, interleaved, &mut self.post_seek_fade_samples, || sync.is_stale()),
+        };
+        self.pushed += pushed as u64;
+    }
 }
 
 pub fn spawn_decoder_with_ctrl<P>(
@@ -228,11 +428,38 @@ pub fn spawn_decoder_with_ctrl<P>(
     output_channels: usize,
     source_sample_rate: u32,
     output_sample_rate: u32,
-) -> (JoinHandle<()>, Sender<control::DecoderCmd>)
+) -> (JoinHandle<()>, CmdSender)
 where
     P: RbProducer<Item = f32> + Send + 'static,
 {
-    let (tx, rx) = channel();
+    let (handle, tx, _) = spawn_decoder_synced(
+        path,
+        producer,
+        is_playing,
+        source_channels,
+        output_channels,
+        source_sample_rate,
+        output_sample_rate,
+    );
+    (handle, tx)
+}
+
+/// Like `spawn_decoder_with_ctrl`, plus the seek handshake for readers that need to tell
+/// stale samples from fresh ones after a seek (see `SeekSync`).
+pub fn spawn_decoder_synced<P>(
+    path: String,
+    producer: P,
+    is_playing: Arc<AtomicBool>,
+    source_channels: usize,
+    output_channels: usize,
+    source_sample_rate: u32,
+    output_sample_rate: u32,
+) -> (JoinHandle<()>, CmdSender, Arc<SeekSync>)
+where
+    P: RbProducer<Item = f32> + Send + 'static,
+{
+    let (tx, rx) = control::command_queue();
+    let sync = Arc::new(SeekSync::default());
     let handle = Decoder::new_with_ctrl(
         path,
         producer,
@@ -242,9 +469,10 @@ where
         source_sample_rate,
         output_sample_rate,
         rx,
+        sync.clone(),
     )
     .spawn();
-    (handle, tx)
+    (handle, tx, sync)
 }
 
 #[allow(dead_code)]
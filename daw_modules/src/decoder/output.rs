@@ -3,11 +3,15 @@
 use ringbuf::traits::Producer as RbProducer;
 use std::time::Duration;
 
+/// Push `data`, ramping in the first `post_seek_fade_samples`, waiting for room as needed.
+/// Gives up early when the reader is gone or `stale` says a seek made the rest pointless.
+/// Returns the samples pushed.
 pub fn push_with_fade<P: RbProducer<Item = f32>>(
     producer: &mut P,
     data: &[f32],
     post_seek_fade_samples: &mut usize,
-) {
+    stale: impl Fn() -> bool,
+) -> usize {
     let mut idx = 0usize;
 
     if *post_seek_fade_samples > 0 && idx < data.len() {
@@ -18,6 +22,7 @@ pub fn push_with_fade<P: RbProducer<Item = f32>>(
             loop {
                 match producer.try_push(s) {
                     Ok(()) => break,
+                    Err(_) if !producer.read_is_held() || stale() => return idx + i,
                     Err(_) => std::thread::park_timeout(Duration::from_micros(200)),
                 }
             }
@@ -29,7 +34,10 @@ pub fn push_with_fade<P: RbProducer<Item = f32>>(
     while idx < data.len() {
         match producer.try_push(data[idx]) {
             Ok(()) => idx += 1,
+            // The track was removed (nobody will ever drain the buffer) or a seek is waiting
+            Err(_) if !producer.read_is_held() || stale() => return idx,
             Err(_) => std::thread::park_timeout(Duration::from_micros(200)),
         }
     }
+    idx
 }
@@ -7,15 +7,14 @@ use symphonia::core::errors::Error as SymphoniaError;
 use symphonia::core::formats::{FormatReader, SeekMode, SeekTo};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::default::{get_codecs, get_probe};
-use std::fs::File;
 
 /// Open a media file and return a format reader plus the default track id.
 // In src/decoder/pipe.rs
 
 /// Open a media file and return a format reader plus the default track id.
 pub fn open_and_probe(path: &str) -> Result<(Box<dyn FormatReader>, u32)> {
-    let file = File::open(path)?;
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let source = super::remote::open_source(path)?;
+    let mss = MediaSourceStream::new(source, Default::default());
     let probed = get_probe().format(
         &Default::default(),
         mss,
@@ -0,0 +1,279 @@
+// src/decoder/remote.rs
+
+// Clips from https URLs. The first open of a URL starts a download thread that writes the
+// file into the remote cache directory; readers get a `MediaSource` over that growing file
+// which blocks until the bytes they ask for have arrived, so probing and playback start
+// as soon as the header is in rather than when the whole file is. A finished download is
+// marked with a `.complete` file next to it and reused by later sessions. Remote files
+// are taken as immutable: a URL is fetched once per cache directory. The HTTP client is
+// behind the `remote` feature; without it only downloads already cached can be opened.
+
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use symphonia::core::io::MediaSource;
+
+/// Give up on a stalled download after this long without a byte.
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+#[cfg(feature = "remote")]
+const CHUNK_BYTES: usize = 64 * 1024;
+
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+static DOWNLOADS: OnceLock<Mutex<HashMap<String, Arc<RemoteFile>>>> = OnceLock::new();
+
+/// Where downloads go; call once at startup. Defaults to a folder in the temp directory.
+pub fn set_cache_dir(dir: PathBuf) {
+    let _ = CACHE_DIR.set(dir);
+}
+
+fn cache_dir() -> &'static Path {
+    CACHE_DIR.get_or_init(|| std::env::temp_dir().join("haven-remote-media"))
+}
+
+/// True for paths that name a remote file rather than one on disk.
+pub fn is_remote(path: &str) -> bool {
+    let lower = path.get(..8).unwrap_or("").to_ascii_lowercase();
+    lower == "https://" || lower.starts_with("http://")
+}
+
+#[derive(Default)]
+struct Progress {
+    received: u64,
+    total: Option<u64>,
+    done: bool,
+    error: Option<String>,
+}
+
+/// One URL being (or already) downloaded into the cache.
+pub struct RemoteFile {
+    url: String,
+    path: PathBuf,
+    progress: Mutex<Progress>,
+    arrived: Condvar,
+}
+
+impl RemoteFile {
+    /// The cache file; complete once `is_complete`.
+    pub fn local_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.progress.lock().map(|p| p.done && p.error.is_none()).unwrap_or(false)
+    }
+
+    /// (bytes received, total when the server said)
+    pub fn progress(&self) -> (u64, Option<u64>) {
+        self.progress.lock().map(|p| (p.received, p.total)).unwrap_or((0, None))
+    }
+
+    /// Block until at least `bytes` have arrived or the download ended; returns how many
+    /// are available.
+    fn wait_for(&self, bytes: u64) -> io::Result<u64> {
+        let mut p = self.progress.lock().map_err(|_| io::Error::other("download state poisoned"))?;
+        loop {
+            if let Some(e) = &p.error {
+                return Err(io::Error::other(format!("download of {} failed: {}", self.url, e)));
+            }
+            if p.received >= bytes || p.done {
+                return Ok(p.received);
+            }
+            let before = p.received;
+            let (guard, timeout) = self
+                .arrived
+                .wait_timeout(p, STALL_TIMEOUT)
+                .map_err(|_| io::Error::other("download state poisoned"))?;
+            p = guard;
+            if timeout.timed_out() && p.received == before && !p.done {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, format!("download of {} stalled", self.url)));
+            }
+        }
+    }
+
+    /// Total length, waiting for the end of the download when the server didn't say.
+    fn total_len(&self) -> io::Result<u64> {
+        if let Some(total) = self.progress().1 {
+            return Ok(total);
+        }
+        self.wait_for(u64::MAX)
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Progress)) {
+        if let Ok(mut p) = self.progress.lock() {
+            f(&mut p);
+        }
+        self.arrived.notify_all();
+    }
+}
+
+fn cache_name(url: &str) -> String {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    url.hash(&mut hasher);
+    // Keep the extension: it's the format hint for probing
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| e.len() <= 5 && e.chars().all(|c| c.is_ascii_alphanumeric()));
+    match ext {
+        Some(ext) => format!("{:016x}.{}", hasher.finish(), ext.to_ascii_lowercase()),
+        None => format!("{:016x}", hasher.finish()),
+    }
+}
+
+fn marker_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".complete");
+    path.with_file_name(name)
+}
+
+/// The download for `url`, started if this is the first time it's asked for. Only https
+/// is accepted.
+pub fn fetch(url: &str) -> Result<Arc<RemoteFile>> {
+    if !url.get(..8).is_some_and(|s| s.eq_ignore_ascii_case("https://")) {
+        return Err(anyhow!("Only https links can be streamed: {}", url));
+    }
+    let downloads = DOWNLOADS.get_or_init(Default::default);
+    let mut map = downloads.lock().map_err(|_| anyhow!("Download registry poisoned"))?;
+    if let Some(existing) = map.get(url) {
+        let failed = existing.progress.lock().map(|p| p.error.is_some()).unwrap_or(true);
+        if !failed {
+            return Ok(existing.clone());
+        }
+        // A failed download is retried on the next request
+    }
+
+    std::fs::create_dir_all(cache_dir())?;
+    let path = cache_dir().join(cache_name(url));
+    let remote = Arc::new(RemoteFile {
+        url: url.to_string(),
+        path: path.clone(),
+        progress: Mutex::new(Progress::default()),
+        arrived: Condvar::new(),
+    });
+
+    let cached_len = std::fs::metadata(&path).ok().filter(|_| marker_path(&path).exists()).map(|m| m.len());
+    if let Some(len) = cached_len {
+        remote.update(|p| *p = Progress { received: len, total: Some(len), done: true, error: None });
+    } else {
+        if !cfg!(feature = "remote") {
+            return Err(anyhow!("Streaming https links needs the `remote` feature: {}", url));
+        }
+        File::create(&path)?; // readers can open it right away
+        let dl = remote.clone();
+        thread::spawn(move || {
+            if let Err(e) = download(&dl) {
+                rt_warn!("⚠️ Download of {} failed: {}", dl.url, e);
+                dl.update(|p| p.error = Some(e.to_string()));
+            }
+        });
+    }
+    map.insert(url.to_string(), remote.clone());
+    Ok(remote)
+}
+
+#[cfg(not(feature = "remote"))]
+fn download(_remote: &RemoteFile) -> Result<()> {
+    Err(anyhow!("built without the `remote` feature"))
+}
+
+#[cfg(feature = "remote")]
+fn download(remote: &RemoteFile) -> Result<()> {
+    use std::io::Write;
+
+    let client = reqwest::blocking::Client::builder().timeout(None).build()?;
+    let mut response = client.get(&remote.url).send()?.error_for_status()?;
+    let total = response.content_length();
+    remote.update(|p| p.total = total);
+    rt_info!("🌐 Streaming {} ({})", remote.url, total.map(|t| format!("{} bytes", t)).unwrap_or_else(|| "unknown length".into()));
+
+    let mut out = File::create(&remote.path)?;
+    let mut buf = vec![0u8; CHUNK_BYTES];
+    loop {
+        let n = response.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        out.write_all(&buf[..n])?;
+        out.flush()?;
+        remote.update(|p| p.received += n as u64);
+    }
+    out.sync_all()?;
+    File::create(marker_path(&remote.path))?;
+    remote.update(|p| {
+        p.total = Some(p.received);
+        p.done = true;
+    });
+    rt_info!("✅ Cached {} at {}", remote.url, remote.path.display());
+    Ok(())
+}
+
+/// Reader over a download in progress.
+pub struct RemoteStream {
+    remote: Arc<RemoteFile>,
+    file: File,
+    pos: u64,
+}
+
+impl RemoteStream {
+    pub fn open(remote: Arc<RemoteFile>) -> io::Result<Self> {
+        let file = File::open(&remote.path)?;
+        Ok(Self { remote, file, pos: 0 })
+    }
+}
+
+impl Read for RemoteStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let available = self.remote.wait_for(self.pos + 1)?;
+        if self.pos >= available {
+            return Ok(0); // end of the file
+        }
+        let n = buf.len().min((available - self.pos) as usize);
+        self.file.seek(SeekFrom::Start(self.pos))?;
+        let read = self.file.read(&mut buf[..n])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for RemoteStream {
+    fn seek(&mut self, to: SeekFrom) -> io::Result<u64> {
+        let target = match to {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+            SeekFrom::End(d) => self.remote.total_len()?.checked_add_signed(d),
+        };
+        // Reads past what has arrived wait for it, so the position can run ahead
+        self.pos = target.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start"))?;
+        Ok(self.pos)
+    }
+}
+
+impl MediaSource for RemoteStream {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.remote.progress().1
+    }
+}
+
+/// A local file or a remote stream, ready for `MediaSourceStream`.
+pub fn open_source(path: &str) -> io::Result<Box<dyn MediaSource>> {
+    if is_remote(path) {
+        let remote = fetch(path).map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(Box::new(RemoteStream::open(remote)?))
+    } else {
+        Ok(Box::new(File::open(path)?))
+    }
+}
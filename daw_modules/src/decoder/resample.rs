@@ -7,15 +7,28 @@ use rubato::{
 };
 use crate::decoder::dsp;
 
+/// Range of `DecoderCmd::SetRate`.
+pub const MIN_RATE: f64 = 0.25;
+pub const MAX_RATE: f64 = 4.0;
+
 pub fn build_resampler(
     src_rate: u32,
     dst_rate: u32,
     channels: usize,
 ) -> Result<Option<SincFixedIn<f32>>> {
-    if src_rate == dst_rate {
+    build_resampler_for_ratio(playback_ratio(src_rate, dst_rate, 1.0), channels)
+}
+
+/// Output frames per source frame when `src_rate` plays at `dst_rate`, `rate` times as fast.
+pub fn playback_ratio(src_rate: u32, dst_rate: u32, rate: f64) -> f64 {
+    dst_rate as f64 / (src_rate as f64 * rate)
+}
+
+/// `None` for a ratio of 1: the samples pass straight through.
+pub fn build_resampler_for_ratio(ratio: f64, channels: usize) -> Result<Option<SincFixedIn<f32>>> {
+    if ratio == 1.0 {
         return Ok(None);
     }
-    let ratio = dst_rate as f64 / src_rate as f64;
     let sinc_len = 256usize;
     let window = WindowFunction::BlackmanHarris2;
     let f_cutoff = calculate_cutoff(sinc_len, window);
@@ -0,0 +1,161 @@
+// src/decoder/stretch.rs
+
+// Time stretch without pitch change (WSOLA). The input is cut into Hann-windowed grains read
+// at the stretched rate and overlap-added at a fixed hop. Each grain is nudged, within a few
+// milliseconds, to where it best continues the previous one, which keeps the waveform
+// coherent instead of phasey. Meant for conforming loops and stems to the project tempo, not
+// for extreme ratios.
+
+/// Stretch ratios (timeline length / source length) a clip may use.
+pub const MIN_STRETCH: f64 = 0.5;
+pub const MAX_STRETCH: f64 = 2.0;
+
+const GRAIN_MS: f64 = 40.0;
+const SEARCH_MS: f64 = 10.0;
+
+pub struct TimeStretcher {
+    ratio: f64,
+    channels: usize,
+    grain: usize,  // frames, even
+    hop: usize,    // output hop, half a grain
+    search: usize, // frames either side of the nominal grain position
+    window: Vec<f32>,
+    input: Vec<f32>,     // interleaved, from the oldest frame still needed
+    nominal: f64,        // where the next grain would start unnudged, in frames into `input`
+    prev: Option<usize>, // start of the previous grain, in frames into `input`
+    overlap: Vec<f32>,   // second half of the previous grain, windowed
+    skip: usize,         // output frames still to drop while priming
+}
+
+impl TimeStretcher {
+    /// `ratio` is output length / input length (2.0 plays at half speed).
+    pub fn new(ratio: f64, channels: usize, sample_rate: u32) -> Self {
+        let channels = channels.max(1);
+        let grain = ((GRAIN_MS / 1000.0 * sample_rate as f64) as usize).max(64) & !1;
+        let hop = grain / 2;
+        // Periodic Hann: two half-overlapped windows sum to exactly one
+        let window = (0..grain)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / grain as f32).cos())
+            .collect();
+        let mut stretcher = Self {
+            ratio: ratio.clamp(MIN_STRETCH, MAX_STRETCH),
+            channels,
+            grain,
+            hop,
+            search: (SEARCH_MS / 1000.0 * sample_rate as f64) as usize,
+            window,
+            input: Vec::new(),
+            nominal: 0.0,
+            prev: None,
+            overlap: Vec::new(),
+            skip: 0,
+        };
+        stretcher.reset();
+        stretcher
+    }
+
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    /// Forget buffered audio (after a seek).
+    pub fn reset(&mut self) {
+        // Lead-in so the first real grain is the second half of one and needs no fade in;
+        // the padding's worth of output is dropped, which keeps input and output aligned
+        let pad = (self.hop as f64 / self.ratio).round() as usize;
+        self.input.clear();
+        self.input.resize(pad * self.channels, 0.0);
+        self.nominal = 0.0;
+        self.prev = None;
+        self.overlap.clear();
+        self.overlap.resize(self.hop * self.channels, 0.0);
+        self.skip = self.hop;
+    }
+
+    /// Feed interleaved input; whatever output is ready is appended to `out`.
+    pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        self.input.extend_from_slice(input);
+        let ch = self.channels;
+
+        loop {
+            let frames = self.input.len() / ch;
+            let nominal = self.nominal.round() as usize;
+            if frames < nominal + self.search + self.grain {
+                break;
+            }
+            let start = match self.prev {
+                Some(prev) => self.best_start(prev + self.hop, nominal.saturating_sub(self.search), nominal + self.search),
+                None => nominal,
+            };
+
+            // First half of the grain completes the previous one; the second half waits
+            let skip = self.skip.min(self.hop);
+            self.skip -= skip;
+            let (rise, fall) = self.window.split_at(self.hop);
+            for (f, (&w_in, &w_out)) in rise.iter().zip(fall).enumerate() {
+                let head = (start + f) * ch;
+                let tail = (start + self.hop + f) * ch;
+                for c in 0..ch {
+                    let sample = self.overlap[f * ch + c] + self.input[head + c] * w_in;
+                    if f >= skip {
+                        out.push(sample);
+                    }
+                    self.overlap[f * ch + c] = self.input[tail + c] * w_out;
+                }
+            }
+
+            self.nominal += self.hop as f64 / self.ratio;
+
+            // Drop input no later grain can reach
+            let keep_from = (self.nominal.round() as usize).saturating_sub(self.search).min(start);
+            self.input.drain(..keep_from * ch);
+            self.nominal -= keep_from as f64;
+            self.prev = Some(start - keep_from);
+        }
+    }
+
+    /// Push out what is still buffered (end of the input).
+    pub fn finish(&mut self, out: &mut Vec<f32>) {
+        let silence = vec![0.0; (self.grain + 2 * self.search + self.hop) * self.channels];
+        self.process(&silence, out);
+    }
+
+    // Start within `lo..=hi` whose opening half-grain best matches the audio at `target`
+    // (normalized cross-correlation of the channel sum, coarsely sampled)
+    fn best_start(&self, target: usize, lo: usize, hi: usize) -> usize {
+        let ch = self.channels;
+        let mono = |frame: usize| self.input[frame * ch..(frame + 1) * ch].iter().sum::<f32>();
+        let reference: Vec<f32> = (0..self.hop).step_by(4).map(|i| mono(target + i)).collect();
+
+        let mut best = (target.clamp(lo, hi), f32::MIN);
+        for cand in (lo..=hi).step_by(2) {
+            let (mut dot, mut energy) = (0.0f32, 1e-9f32);
+            for (k, r) in reference.iter().enumerate() {
+                let x = mono(cand + k * 4);
+                dot += x * r;
+                energy += x * x;
+            }
+            let score = dot / energy.sqrt();
+            if score > best.1 {
+                best = (cand, score);
+            }
+        }
+        best.0
+    }
+}
+
+/// Stretch a whole interleaved buffer (export, freeze). The result is exactly
+/// `frames * ratio` frames long.
+pub fn stretch_interleaved(input: &[f32], channels: usize, sample_rate: u32, ratio: f64) -> Vec<f32> {
+    let channels = channels.max(1);
+    if ratio == 1.0 {
+        return input.to_vec();
+    }
+    let mut stretcher = TimeStretcher::new(ratio, channels, sample_rate);
+    let frames = ((input.len() / channels) as f64 * stretcher.ratio()).round() as usize;
+    let mut out = Vec::with_capacity(frames * channels + stretcher.grain * channels);
+    stretcher.process(input, &mut out);
+    stretcher.finish(&mut out);
+    out.resize(frames * channels, 0.0);
+    out
+}
@@ -0,0 +1,550 @@
+// daw_modules/src/effects/chain.rs
+
+// A track's insert chain: any number of EQs, compressors, reverbs, delays, stereo
+// wideners and pitch shifters in a user-set order, each with its own bypass and (EQ
+// aside) a dry/wet blend. It runs after the track's built-in strip (EQ, compressor,
+// reverb) and before the fader. Every insert has an id that stays put when the chain is
+// reordered, so the UI can address it; edits allocate and happen under the engine lock,
+// processing never does.
+//
+// Inserts also carry parameter lanes (effect automation). Outside a write pass a lane sets
+// its parameter every block. A pass (see `AutomationMode`) is opened by a knob move or by
+// the transport starting in Write mode; while it runs the lane is ignored, knob moves
+// overwrite it, and when the transport stops it is punched out at the held value. Opening
+// and closing passes happens on the audio thread, into node capacity reserved up front.
+
+use serde::{Deserialize, Serialize};
+
+use super::compressor::{CompressorNode, CompressorParams};
+use super::delay::{DelayNode, DelayParams};
+use super::equalizer::{EqParams, EqResponse, TrackEq};
+use super::pitch_shift::{PitchShiftNode, PitchShiftParams};
+use super::reverb::{ReverbNode, ReverbParams};
+use super::stereo_width::{StereoWidthNode, StereoWidthParams};
+use super::Effect;
+use crate::engine::automation::AutomationCurve;
+use crate::engine::pdc::PdcDelay;
+
+/// Most inserts one track can hold.
+pub const MAX_INSERTS: usize = 8;
+/// Spare nodes kept per lane, for passes opened and closed on the audio thread.
+const RESERVED_NODES: usize = 64;
+
+/// Settings of one insert, by kind.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", content = "params", rename_all = "camelCase")]
+pub enum InsertParams {
+    Eq(Vec<EqParams>),
+    Compressor(CompressorParams),
+    Reverb(ReverbParams),
+    Delay(DelayParams),
+    StereoWidth(StereoWidthParams),
+    PitchShift(PitchShiftParams),
+}
+
+/// One insert as saved and as the UI lists it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InsertState {
+    pub id: u32,
+    #[serde(default)]
+    pub bypass: bool,
+    /// Wet share of the output, 0.0 (dry) to 1.0 (effect only).
+    #[serde(default = "full_wet", skip_serializing_if = "is_full_wet")]
+    pub mix: f32,
+    pub effect: InsertParams,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub automation: Vec<ParamLane>,
+}
+
+/// Automation of one insert parameter.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ParamLane {
+    /// Field name in the insert's serialized params (`threshold_db`, `timeMs`, ...).
+    pub param: String,
+    pub curve: AutomationCurve<f32>,
+    #[serde(skip)]
+    pass: Option<u64>, // time of the last node written by the running pass
+}
+
+impl ParamLane {
+    fn new(param: &str) -> Self {
+        Self { param: param.to_string(), curve: AutomationCurve::new(), pass: None }
+    }
+
+    /// Record `value` at `time`, replacing what the pass has run over since its last node.
+    /// A new pass first pins `before` just ahead of it, so the curve up to there is kept.
+    fn write(&mut self, time: u64, before: f32, value: f32) {
+        match self.pass {
+            Some(last) => self.curve.remove_between(last, time),
+            None => self.curve.insert_node(time.saturating_sub(1), before),
+        }
+        self.curve.insert_node(time, value);
+        self.curve.reserve(RESERVED_NODES);
+        self.pass = Some(time);
+    }
+}
+
+fn full_wet() -> f32 {
+    1.0
+}
+
+fn is_full_wet(mix: &f32) -> bool {
+    *mix >= 1.0
+}
+
+impl InsertParams {
+    /// EQ stays fully wet: blended with its dry signal a filter just smears phase.
+    pub fn supports_mix(&self) -> bool {
+        !matches!(self, InsertParams::Eq(_))
+    }
+
+    /// Parameters a lane can drive, by serialized field name. EQ has none.
+    pub fn automatable_params(&self) -> &'static [&'static str] {
+        match self {
+            InsertParams::Eq(_) => &[],
+            InsertParams::Compressor(_) => &["threshold_db", "ratio", "attack_ms", "release_ms", "makeup_gain_db"],
+            InsertParams::Reverb(_) => &["room_size", "damping", "mix", "width", "pre_delay_ms", "low_cut_hz", "high_cut_hz"],
+            InsertParams::Delay(_) => &["timeMs", "feedback", "mix"],
+            InsertParams::StereoWidth(_) => &["midGainDb", "sideGainDb", "monoBelowHz"],
+            InsertParams::PitchShift(_) => &["semitones"],
+        }
+    }
+
+    fn field_mut(&mut self, param: &str) -> Option<&mut f32> {
+        Some(match (self, param) {
+            (InsertParams::Compressor(p), "threshold_db") => &mut p.threshold_db,
+            (InsertParams::Compressor(p), "ratio") => &mut p.ratio,
+            (InsertParams::Compressor(p), "attack_ms") => &mut p.attack_ms,
+            (InsertParams::Compressor(p), "release_ms") => &mut p.release_ms,
+            (InsertParams::Compressor(p), "makeup_gain_db") => &mut p.makeup_gain_db,
+            (InsertParams::Reverb(p), "room_size") => &mut p.room_size,
+            (InsertParams::Reverb(p), "damping") => &mut p.damping,
+            (InsertParams::Reverb(p), "mix") => &mut p.mix,
+            (InsertParams::Reverb(p), "width") => &mut p.width,
+            (InsertParams::Reverb(p), "pre_delay_ms") => &mut p.pre_delay_ms,
+            (InsertParams::Reverb(p), "low_cut_hz") => &mut p.low_cut_hz,
+            (InsertParams::Reverb(p), "high_cut_hz") => &mut p.high_cut_hz,
+            (InsertParams::Delay(p), "timeMs") => &mut p.time_ms,
+            (InsertParams::Delay(p), "feedback") => &mut p.feedback,
+            (InsertParams::Delay(p), "mix") => &mut p.mix,
+            (InsertParams::StereoWidth(p), "midGainDb") => &mut p.mid_gain_db,
+            (InsertParams::StereoWidth(p), "sideGainDb") => &mut p.side_gain_db,
+            (InsertParams::StereoWidth(p), "monoBelowHz") => &mut p.mono_below_hz,
+            (InsertParams::PitchShift(p), "semitones") => &mut p.semitones,
+            _ => return None,
+        })
+    }
+}
+
+enum InsertNode {
+    Eq(TrackEq),
+    Compressor(CompressorNode),
+    Reverb(ReverbNode),
+    Delay(DelayNode),
+    StereoWidth(StereoWidthNode),
+    PitchShift(PitchShiftNode),
+}
+
+impl InsertNode {
+    fn new(params: &InsertParams, channels: usize, sample_rate: u32) -> Self {
+        match params {
+            InsertParams::Eq(bands) => {
+                let mut eq = TrackEq::new(sample_rate, channels);
+                eq.set_state(bands.clone());
+                InsertNode::Eq(eq)
+            }
+            InsertParams::Compressor(p) => {
+                let node = CompressorNode::new(sample_rate as f32, channels);
+                node.set_params(*p);
+                InsertNode::Compressor(node)
+            }
+            InsertParams::Reverb(p) => {
+                let node = ReverbNode::new(sample_rate as f32);
+                node.set_params(*p);
+                InsertNode::Reverb(node)
+            }
+            InsertParams::Delay(p) => InsertNode::Delay(DelayNode::new(*p, channels, sample_rate)),
+            InsertParams::StereoWidth(p) => InsertNode::StereoWidth(StereoWidthNode::new(*p, sample_rate)),
+            InsertParams::PitchShift(p) => InsertNode::PitchShift(PitchShiftNode::new(*p, channels, sample_rate)),
+        }
+    }
+
+    fn params(&self) -> InsertParams {
+        match self {
+            InsertNode::Eq(eq) => InsertParams::Eq(eq.get_state()),
+            InsertNode::Compressor(node) => InsertParams::Compressor(node.get_params()),
+            InsertNode::Reverb(node) => InsertParams::Reverb(node.get_params()),
+            InsertNode::Delay(node) => InsertParams::Delay(node.get_params()),
+            InsertNode::StereoWidth(node) => InsertParams::StereoWidth(node.get_params()),
+            InsertNode::PitchShift(node) => InsertParams::PitchShift(node.get_params()),
+        }
+    }
+
+    /// False when `params` belong to another kind of effect.
+    fn set_params(&mut self, params: &InsertParams) -> bool {
+        match (self, params) {
+            (InsertNode::Eq(eq), InsertParams::Eq(bands)) => eq.set_state(bands.clone()),
+            (InsertNode::Compressor(node), InsertParams::Compressor(p)) => node.set_params(*p),
+            (InsertNode::Reverb(node), InsertParams::Reverb(p)) => node.set_params(*p),
+            (InsertNode::Delay(node), InsertParams::Delay(p)) => node.set_params(*p),
+            (InsertNode::StereoWidth(node), InsertParams::StereoWidth(p)) => node.set_params(*p),
+            (InsertNode::PitchShift(node), InsertParams::PitchShift(p)) => node.set_params(*p),
+            _ => return false,
+        }
+        true
+    }
+
+    fn effect(&mut self) -> &mut dyn Effect {
+        match self {
+            InsertNode::Eq(eq) => eq,
+            InsertNode::Compressor(node) => node,
+            InsertNode::Reverb(node) => node,
+            InsertNode::Delay(node) => node,
+            InsertNode::StereoWidth(node) => node,
+            InsertNode::PitchShift(node) => node,
+        }
+    }
+
+    fn latency_frames(&self) -> usize {
+        match self {
+            InsertNode::Eq(eq) => eq.latency_frames(),
+            InsertNode::Compressor(node) => node.latency_frames(),
+            InsertNode::Reverb(node) => node.latency_frames(),
+            InsertNode::Delay(node) => node.latency_frames(),
+            InsertNode::StereoWidth(node) => node.latency_frames(),
+            InsertNode::PitchShift(node) => node.latency_frames(),
+        }
+    }
+}
+
+struct Insert {
+    id: u32,
+    bypass: bool,
+    mix: f32,
+    mix_heard: f32,              // as of the end of the last block, ramping toward `mix`
+    dry_delay: Option<PdcDelay>, // lines the dry signal up with a latent effect; from the first blend on
+    node: InsertNode,
+    automation: Vec<ParamLane>,
+}
+
+impl Insert {
+    fn new(id: u32, bypass: bool, mix: f32, node: InsertNode, channels: usize) -> Self {
+        let mut insert = Self { id, bypass, mix: 1.0, mix_heard: 1.0, dry_delay: None, node, automation: Vec::new() };
+        insert.set_mix(mix, channels);
+        insert.mix_heard = insert.mix;
+        insert
+    }
+
+    fn state(&self) -> InsertState {
+        InsertState {
+            id: self.id,
+            bypass: self.bypass,
+            mix: self.mix,
+            effect: self.node.params(),
+            automation: self.automation.clone(),
+        }
+    }
+
+    /// Lanes that don't fit the effect (renamed fields, another kind) are dropped.
+    fn set_automation(&mut self, lanes: &[ParamLane]) {
+        let params = self.node.params();
+        let automatable = params.automatable_params();
+        self.automation = lanes
+            .iter()
+            .filter(|l| automatable.contains(&l.param.as_str()))
+            .map(|l| {
+                let mut lane = ParamLane { pass: None, ..l.clone() };
+                lane.curve.reserve(RESERVED_NODES);
+                lane
+            })
+            .collect();
+    }
+
+    /// Audio thread: lanes outside a pass set their parameters for the block at `time`.
+    fn apply_automation(&mut self, time: u64) {
+        if !self.automation.iter().any(|l| l.pass.is_none() && !l.curve.nodes().is_empty()) {
+            return;
+        }
+        let mut params = self.node.params();
+        let mut changed = false;
+        for lane in self.automation.iter().filter(|l| l.pass.is_none() && !l.curve.nodes().is_empty()) {
+            if let Some(field) = params.field_mut(&lane.param) {
+                let value = lane.curve.get_value_at_time(time, *field);
+                changed |= *field != value;
+                *field = value;
+            }
+        }
+        if changed {
+            self.node.set_params(&params);
+        }
+    }
+
+    /// Set `params`, recording every automatable value it changes at `time`.
+    fn write_params(&mut self, params: &InsertParams, time: u64) -> bool {
+        let mut before = self.node.params();
+        if !self.node.set_params(params) {
+            return false;
+        }
+        let mut after = self.node.params(); // sanitized
+        for &param in before.automatable_params() {
+            let (Some(&mut old), Some(&mut new)) = (before.field_mut(param), after.field_mut(param)) else {
+                continue;
+            };
+            if old == new {
+                continue;
+            }
+            let index = match self.automation.iter().position(|l| l.param == param) {
+                Some(index) => index,
+                None => {
+                    self.automation.push(ParamLane::new(param));
+                    self.automation.len() - 1
+                }
+            };
+            self.automation[index].write(time, old, new);
+        }
+        true
+    }
+
+    /// Audio thread: every lane starts a pass at `time`, holding the current value.
+    fn begin_pass(&mut self, time: u64) {
+        if self.automation.is_empty() {
+            return;
+        }
+        let mut params = self.node.params();
+        for lane in &mut self.automation {
+            if let (None, Some(&mut value)) = (lane.pass, params.field_mut(&lane.param)) {
+                let before = lane.curve.get_value_at_time(time.saturating_sub(1), value);
+                lane.curve.try_insert_node(time.saturating_sub(1), before);
+                lane.curve.try_insert_node(time, value);
+                lane.pass = Some(time);
+            }
+        }
+    }
+
+    /// Audio thread: close the running passes at `time`. The value written last holds up
+    /// to there; the old curve takes over after it.
+    fn end_pass(&mut self, time: u64) {
+        if self.automation.iter().all(|l| l.pass.is_none()) {
+            return;
+        }
+        let mut params = self.node.params();
+        for lane in &mut self.automation {
+            let Some(last) = lane.pass.take() else { continue };
+            if let Some(&mut value) = params.field_mut(&lane.param) {
+                lane.curve.remove_between(last, time);
+                lane.curve.try_insert_node(time, value);
+            }
+        }
+    }
+
+    /// Allocates the dry delay the first time the insert is blended.
+    fn set_mix(&mut self, mix: f32, channels: usize) {
+        self.mix = if mix.is_finite() && self.node.params().supports_mix() { mix.clamp(0.0, 1.0) } else { 1.0 };
+        if self.mix < 1.0 && self.dry_delay.is_none() {
+            self.dry_delay = Some(PdcDelay::new(channels));
+        }
+    }
+
+    /// Run the effect and blend it with the dry signal, `dry` being scratch space.
+    fn process(&mut self, buffer: &mut [f32], channels: usize, dry: &mut Vec<f32>) {
+        let blended = self.mix < 1.0 || self.mix_heard < 1.0;
+        let Some(dry_delay) = self.dry_delay.as_mut().filter(|_| blended) else {
+            self.node.effect().process_block(buffer, channels);
+            return;
+        };
+        dry.clear();
+        dry.extend_from_slice(buffer);
+        self.node.effect().process_block(buffer, channels);
+        dry_delay.set_delay_frames(self.node.latency_frames());
+        dry_delay.process(dry);
+
+        let channels = channels.max(1);
+        let step = (self.mix - self.mix_heard) / (buffer.len() / channels).max(1) as f32;
+        for (frame, dry) in buffer.chunks_exact_mut(channels).zip(dry.chunks_exact(channels)) {
+            self.mix_heard += step;
+            for (wet, &dry) in frame.iter_mut().zip(dry) {
+                *wet = dry + (*wet - dry) * self.mix_heard;
+            }
+        }
+        self.mix_heard = self.mix;
+    }
+}
+
+pub struct EffectChain {
+    inserts: Vec<Insert>,
+    next_id: u32,
+    channels: usize,
+    sample_rate: u32,
+    dry: Vec<f32>, // dry copy of the block for blended inserts, reused
+}
+
+impl EffectChain {
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        Self { inserts: Vec::with_capacity(MAX_INSERTS), next_id: 1, channels, sample_rate, dry: Vec::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inserts.is_empty()
+    }
+
+    /// An active reverb or delay keeps sounding after the input stops.
+    pub fn has_tail(&self) -> bool {
+        self.inserts
+            .iter()
+            .any(|i| !i.bypass && matches!(i.node, InsertNode::Reverb(_) | InsertNode::Delay(_)))
+    }
+
+    pub fn states(&self) -> Vec<InsertState> {
+        self.inserts.iter().map(Insert::state).collect()
+    }
+
+    /// Response curve of an EQ insert (see `TrackEq::frequency_response`).
+    pub fn eq_response(&self, id: u32, points: usize) -> anyhow::Result<EqResponse> {
+        match &self.inserts[self.position(id)?].node {
+            InsertNode::Eq(eq) => Ok(eq.frequency_response(points)),
+            _ => Err(anyhow::anyhow!("Insert {} is not an EQ", id)),
+        }
+    }
+
+    /// Replace the whole chain (project load, undo of a track delete), ids included.
+    pub fn set_states(&mut self, states: &[InsertState]) {
+        self.inserts = states
+            .iter()
+            .take(MAX_INSERTS)
+            .map(|s| {
+                let node = InsertNode::new(&s.effect, self.channels, self.sample_rate);
+                let mut insert = Insert::new(s.id, s.bypass, s.mix, node, self.channels);
+                insert.set_automation(&s.automation);
+                insert
+            })
+            .collect();
+        self.next_id = self.inserts.iter().map(|i| i.id + 1).max().unwrap_or(1);
+    }
+
+    /// Add an insert at `index` (the end when `None` or past it). Returns its id.
+    pub fn add(&mut self, params: &InsertParams, index: Option<usize>) -> anyhow::Result<u32> {
+        if self.inserts.len() >= MAX_INSERTS {
+            return Err(anyhow::anyhow!("A track can hold at most {} inserts", MAX_INSERTS));
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let insert = Insert::new(id, false, 1.0, InsertNode::new(params, self.channels, self.sample_rate), self.channels);
+        let index = index.unwrap_or(self.inserts.len()).min(self.inserts.len());
+        self.inserts.insert(index, insert);
+        Ok(id)
+    }
+
+    pub fn remove(&mut self, id: u32) -> anyhow::Result<InsertState> {
+        let index = self.position(id)?;
+        Ok(self.inserts.remove(index).state())
+    }
+
+    /// Move an insert to `index` (clamped to the chain).
+    pub fn move_insert(&mut self, id: u32, index: usize) -> anyhow::Result<()> {
+        let from = self.position(id)?;
+        let insert = self.inserts.remove(from);
+        let index = index.min(self.inserts.len());
+        self.inserts.insert(index, insert);
+        Ok(())
+    }
+
+    pub fn set_bypass(&mut self, id: u32, bypass: bool) -> anyhow::Result<()> {
+        let index = self.position(id)?;
+        self.inserts[index].bypass = bypass;
+        Ok(())
+    }
+
+    /// Dry/wet blend of an insert (clamped to 0..1), parallel-compression style.
+    pub fn set_mix(&mut self, id: u32, mix: f32) -> anyhow::Result<()> {
+        let index = self.position(id)?;
+        let insert = &mut self.inserts[index];
+        if !insert.node.params().supports_mix() {
+            return Err(anyhow::anyhow!("An EQ insert can't be blended with its dry signal"));
+        }
+        insert.set_mix(mix, self.channels);
+        Ok(())
+    }
+
+    /// New settings for an insert. Same kind keeps its state (reverb tail, delay echoes);
+    /// another kind replaces the effect in place.
+    pub fn set_params(&mut self, id: u32, params: &InsertParams) -> anyhow::Result<()> {
+        let index = self.position(id)?;
+        let (channels, sample_rate) = (self.channels, self.sample_rate);
+        let insert = &mut self.inserts[index];
+        if !insert.node.set_params(params) {
+            insert.node = InsertNode::new(params, channels, sample_rate);
+            insert.set_mix(insert.mix, channels); // an EQ goes back to fully wet
+            insert.automation.clear();
+        }
+        Ok(())
+    }
+
+    /// `set_params` during a write pass: the moved parameters are recorded at `time`
+    /// (timeline frames). Switching to another kind of effect records nothing.
+    pub fn write_params(&mut self, id: u32, params: &InsertParams, time: u64) -> anyhow::Result<()> {
+        let index = self.position(id)?;
+        if !self.inserts[index].write_params(params, time) {
+            return self.set_params(id, params);
+        }
+        Ok(())
+    }
+
+    /// Drop an insert's lane for `param`, or all its lanes.
+    pub fn clear_automation(&mut self, id: u32, param: Option<&str>) -> anyhow::Result<()> {
+        let index = self.position(id)?;
+        let lanes = &mut self.inserts[index].automation;
+        match param {
+            Some(param) => lanes.retain(|l| l.param != param),
+            None => lanes.clear(),
+        }
+        Ok(())
+    }
+
+    /// Audio thread: drive the parameters from their lanes for the block at `time`.
+    pub fn apply_automation(&mut self, time: u64) {
+        for insert in &mut self.inserts {
+            insert.apply_automation(time);
+        }
+    }
+
+    /// Audio thread: open a pass on every lane (the transport starts in Write mode).
+    pub fn begin_automation_pass(&mut self, time: u64) {
+        for insert in &mut self.inserts {
+            insert.begin_pass(time);
+        }
+    }
+
+    /// Audio thread: close the running passes (the transport stops or jumps).
+    pub fn end_automation_pass(&mut self, time: u64) {
+        for insert in &mut self.inserts {
+            insert.end_pass(time);
+        }
+    }
+
+    /// Rebuilds every insert (delay lines and filters depend on the rate). Allocates.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        let states = self.states();
+        self.set_states(&states);
+    }
+
+    fn position(&self, id: u32) -> anyhow::Result<usize> {
+        self.inserts
+            .iter()
+            .position(|i| i.id == id)
+            .ok_or_else(|| anyhow::anyhow!("Insert {} not found", id))
+    }
+}
+
+impl Effect for EffectChain {
+    fn process_block(&mut self, buffer: &mut [f32], channels: usize) {
+        for insert in self.inserts.iter_mut().filter(|i| !i.bypass) {
+            insert.process(buffer, channels, &mut self.dry);
+        }
+    }
+
+    fn latency_frames(&self) -> usize {
+        self.inserts.iter().filter(|i| !i.bypass).map(|i| i.node.latency_frames()).sum()
+    }
+}
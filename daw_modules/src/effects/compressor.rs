@@ -8,6 +8,10 @@ use std::sync::atomic::{AtomicU32, AtomicBool, Ordering}; // <--- ADDED AtomicBo
 
 use serde::{Deserialize, Serialize};
 
+/// Longest lookahead, in ms; the delay line is sized for it up front.
+pub const MAX_LOOKAHEAD_MS: f32 = 10.0;
+const MAX_KNEE_DB: f32 = 24.0;
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct CompressorParams {
     pub is_active: bool, // <--- ADDED BYPASS FLAG
@@ -16,6 +20,42 @@ pub struct CompressorParams {
     pub attack_ms: f32,
     pub release_ms: f32,
     pub makeup_gain_db: f32,
+    /// Width of the soft knee around the threshold, 0.0 (hard) to 24.0 dB.
+    #[serde(default)]
+    pub knee_db: f32,
+    /// How far the detector runs ahead of the audio, 0.0 to 10.0 ms; also the latency added.
+    #[serde(default)]
+    pub lookahead_ms: f32,
+}
+
+impl Default for CompressorParams {
+    fn default() -> Self {
+        Self {
+            is_active: false,
+            threshold_db: -20.0,
+            ratio: 4.0,
+            attack_ms: 5.0,
+            release_ms: 50.0,
+            makeup_gain_db: 0.0,
+            knee_db: 6.0,
+            lookahead_ms: 0.0,
+        }
+    }
+}
+
+/// Static curve: gain reduction (dB, positive) for a detector level, with the knee
+/// blending quadratically from no reduction into the full ratio.
+fn gain_reduction_db(level_db: f32, threshold: f32, ratio: f32, knee: f32) -> f32 {
+    let overshoot = level_db - threshold;
+    let slope = 1.0 - 1.0 / ratio;
+    if 2.0 * overshoot <= -knee {
+        0.0
+    } else if 2.0 * overshoot.abs() < knee {
+        let x = overshoot + knee * 0.5;
+        slope * x * x / (2.0 * knee)
+    } else {
+        slope * overshoot
+    }
 }
 
 /// Helper to safely store f32 in an AtomicU32 for real-time safe parameter updates
@@ -37,26 +77,39 @@ pub struct CompressorNode {
     attack_ms: AtomicU32,
     release_ms: AtomicU32,
     makeup_gain_db: AtomicU32,
+    knee_db: AtomicU32,
+    lookahead_ms: AtomicU32,
 
     // --- Internal DSP State ---
     sample_rate: f32,
+    channels: usize,
     envelope: f32,
+    lookahead: Vec<f32>, // interleaved delay line for the audio path, MAX_LOOKAHEAD_MS long
+    lookahead_write: usize, // frame index into `lookahead`
 }
 
 impl CompressorNode {
-    /// Initialize a new Compressor with default tracking settings
-    pub fn new(sample_rate: f32) -> Self {
+    /// Initialize a new Compressor with default tracking settings (see `CompressorParams::default`).
+    /// Allocates the lookahead line for `channels`.
+    pub fn new(sample_rate: f32, channels: usize) -> Self {
+        let d = CompressorParams::default();
+        let channels = channels.max(1);
+        let max_lookahead_frames = (MAX_LOOKAHEAD_MS * 0.001 * sample_rate).ceil() as usize + 1;
         Self {
-            is_active: AtomicBool::new(false), // <--- Default to ON
-            // Default: -20dB threshold, 4:1 ratio, 5ms attack, 50ms release
-            threshold_db: f32_to_atomic(-20.0),
-            ratio: f32_to_atomic(4.0),
-            attack_ms: f32_to_atomic(5.0),
-            release_ms: f32_to_atomic(50.0),
-            makeup_gain_db: f32_to_atomic(0.0),
+            is_active: AtomicBool::new(d.is_active),
+            threshold_db: f32_to_atomic(d.threshold_db),
+            ratio: f32_to_atomic(d.ratio),
+            attack_ms: f32_to_atomic(d.attack_ms),
+            release_ms: f32_to_atomic(d.release_ms),
+            makeup_gain_db: f32_to_atomic(d.makeup_gain_db),
+            knee_db: f32_to_atomic(d.knee_db),
+            lookahead_ms: f32_to_atomic(d.lookahead_ms),
 
             sample_rate,
+            channels,
             envelope: 0.0,
+            lookahead: vec![0.0; max_lookahead_frames * channels],
+            lookahead_write: 0,
         }
     }
 
@@ -86,6 +139,21 @@ impl CompressorNode {
         self.makeup_gain_db.store(db.to_bits(), Ordering::Relaxed);
     }
 
+    pub fn set_knee(&self, db: f32) {
+        let db = if db.is_finite() { db.clamp(0.0, MAX_KNEE_DB) } else { 0.0 };
+        self.knee_db.store(db.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_lookahead(&self, ms: f32) {
+        let ms = if ms.is_finite() { ms.clamp(0.0, MAX_LOOKAHEAD_MS) } else { 0.0 };
+        self.lookahead_ms.store(ms.to_bits(), Ordering::Relaxed);
+    }
+
+    fn lookahead_frames(&self) -> usize {
+        let frames = (atomic_to_f32(&self.lookahead_ms) * 0.001 * self.sample_rate).round() as usize;
+        frames.min(self.lookahead.len() / self.channels - 1)
+    }
+
     pub fn get_params(&self) -> CompressorParams {
         CompressorParams {
             is_active: self.is_active.load(Ordering::Relaxed), // <--- READ BYPASS
@@ -94,6 +162,8 @@ impl CompressorNode {
             attack_ms: atomic_to_f32(&self.attack_ms),
             release_ms: atomic_to_f32(&self.release_ms),
             makeup_gain_db: atomic_to_f32(&self.makeup_gain_db),
+            knee_db: atomic_to_f32(&self.knee_db),
+            lookahead_ms: atomic_to_f32(&self.lookahead_ms),
         }
     }
 
@@ -104,11 +174,21 @@ impl CompressorNode {
         self.set_attack(params.attack_ms);
         self.set_release(params.release_ms);
         self.set_makeup_gain(params.makeup_gain_db);
+        self.set_knee(params.knee_db);
+        self.set_lookahead(params.lookahead_ms);
+    }
+
+    /// Forget the envelope and the lookahead line; the parameters are kept.
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+        self.lookahead.fill(0.0);
+        self.lookahead_write = 0;
     }
 
     // --- DSP Processing (Called continuously by the Audio Engine Thread) ---
 
-    /// Processes a chunk of audio samples in place. 
+    /// Processes a chunk of interleaved audio in place (the channel count given to `new`).
+    /// Channels share one detector, so the stereo image doesn't shift under compression.
     /// GUARANTEE: No locks, no blocking, no allocations.
     pub fn process(&mut self, buffer: &mut [f32]) {
         // --- ZERO CPU TRUE BYPASS ---
@@ -123,6 +203,10 @@ impl CompressorNode {
         let attack = atomic_to_f32(&self.attack_ms);
         let release = atomic_to_f32(&self.release_ms);
         let makeup = atomic_to_f32(&self.makeup_gain_db);
+        let knee = atomic_to_f32(&self.knee_db);
+        let delay = self.lookahead_frames();
+        let channels = self.channels;
+        let line_frames = self.lookahead.len() / channels;
 
         // Calculate time constants based on sample rate
         let attack_coef = (-1.0 / (attack * 0.001 * self.sample_rate)).exp();
@@ -130,9 +214,9 @@ impl CompressorNode {
         
         let makeup_linear = 10.0_f32.powf(makeup / 20.0);
 
-        for sample in buffer.iter_mut() {
-            // Step A: Detect signal level (Peak analysis)
-            let input_level = sample.abs();
+        for frame in buffer.chunks_exact_mut(channels) {
+            // Step A: Detect signal level (Peak analysis, loudest channel)
+            let input_level = frame.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
             
             // Step B: Envelope Follower
             if input_level > self.envelope {
@@ -144,19 +228,39 @@ impl CompressorNode {
             // Step C: Convert envelope to decibels
             let env_db = 20.0 * self.envelope.max(1e-5).log10();
 
-            // Step D: Calculate Gain Reduction
-            let mut gain_reduction_db = 0.0;
-            if env_db > threshold {
-                let overshoot = env_db - threshold;
-                // Apply the ratio calculation
-                gain_reduction_db = overshoot * (1.0 - (1.0 / ratio));
-            }
+            // Step D: Calculate Gain Reduction (soft knee around the threshold)
+            let gain_reduction_db = gain_reduction_db(env_db, threshold, ratio, knee);
 
             // Step E: Convert Gain Reduction back to linear multiplier
             let gain_reduction_linear = 10.0_f32.powf(-gain_reduction_db / 20.0);
 
-            // Step F: Apply gain reduction and makeup gain to the audio sample
-            *sample *= gain_reduction_linear * makeup_linear;
+            // Step F: The audio trails the detector by the lookahead, so the gain is
+            // already down when a transient arrives
+            if delay > 0 {
+                let w = self.lookahead_write * channels;
+                let r = (self.lookahead_write + line_frames - delay) % line_frames * channels;
+                for (c, sample) in frame.iter_mut().enumerate() {
+                    self.lookahead[w + c] = *sample;
+                    *sample = self.lookahead[r + c];
+                }
+                self.lookahead_write = (self.lookahead_write + 1) % line_frames;
+            }
+
+            // Step G: Apply gain reduction and makeup gain to the audio frame
+            let gain = gain_reduction_linear * makeup_linear;
+            for sample in frame.iter_mut() {
+                *sample *= gain;
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+impl super::Effect for CompressorNode {
+    fn process_block(&mut self, buffer: &mut [f32], _channels: usize) {
+        self.process(buffer);
+    }
+
+    fn latency_frames(&self) -> usize {
+        if self.is_active.load(Ordering::Relaxed) { self.lookahead_frames() } else { 0 }
+    }
+}
@@ -0,0 +1,101 @@
+// daw_modules/src/effects/delay.rs
+
+// Feedback delay, meant for aux buses (it runs 100% wet there). Each channel has its own
+// line, sized for the longest delay up front so changing the time never allocates.
+
+use serde::{Deserialize, Serialize};
+
+use super::Effect;
+
+pub const MAX_DELAY_MS: f32 = 2000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DelayParams {
+    pub is_active: bool,
+    pub time_ms: f32,  // 1.0 to 2000.0
+    pub feedback: f32, // 0.0 to 0.95
+    pub mix: f32,      // 0.0 (Dry) to 1.0 (Wet)
+}
+
+impl Default for DelayParams {
+    fn default() -> Self {
+        Self { is_active: true, time_ms: 375.0, feedback: 0.35, mix: 1.0 }
+    }
+}
+
+impl DelayParams {
+    /// Ranges enforced; non-finite values fall back to the defaults.
+    pub fn sanitized(self) -> Self {
+        let d = Self::default();
+        let clamp = |v: f32, lo: f32, hi: f32, fallback: f32| if v.is_finite() { v.clamp(lo, hi) } else { fallback };
+        Self {
+            time_ms: clamp(self.time_ms, 1.0, MAX_DELAY_MS, d.time_ms),
+            feedback: clamp(self.feedback, 0.0, 0.95, d.feedback),
+            mix: clamp(self.mix, 0.0, 1.0, d.mix),
+            ..self
+        }
+    }
+}
+
+pub struct DelayNode {
+    params: DelayParams,
+    sample_rate: u32,
+    delay_frames: usize,
+    lines: Vec<Vec<f32>>, // per channel
+    write: usize,
+}
+
+impl DelayNode {
+    pub fn new(params: DelayParams, channels: usize, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.max(1);
+        let len = (MAX_DELAY_MS / 1000.0 * sample_rate as f32).ceil() as usize + 1;
+        let mut node = Self {
+            params: DelayParams::default(),
+            sample_rate,
+            delay_frames: 1,
+            lines: (0..channels.max(1)).map(|_| vec![0.0; len]).collect(),
+            write: 0,
+        };
+        node.set_params(params);
+        node
+    }
+
+    pub fn get_params(&self) -> DelayParams {
+        self.params
+    }
+
+    /// Never allocates; the echoes already in the line carry on at the new time.
+    pub fn set_params(&mut self, params: DelayParams) {
+        let params = params.sanitized();
+        let len = self.lines[0].len();
+        self.delay_frames = ((params.time_ms / 1000.0 * self.sample_rate as f32).round() as usize).clamp(1, len - 1);
+        self.params = params;
+    }
+
+    pub fn reset(&mut self) {
+        self.lines.iter_mut().for_each(|l| l.fill(0.0));
+        self.write = 0;
+    }
+}
+
+impl Effect for DelayNode {
+    fn process_block(&mut self, buffer: &mut [f32], channels: usize) {
+        if !self.params.is_active {
+            return;
+        }
+        let channels = channels.max(1);
+        let len = self.lines[0].len();
+        let (feedback, mix) = (self.params.feedback, self.params.mix);
+
+        for frame in buffer.chunks_exact_mut(channels) {
+            let read = (self.write + len - self.delay_frames) % len;
+            for (sample, line) in frame.iter_mut().zip(self.lines.iter_mut()) {
+                let echo = line[read];
+                line[self.write] = *sample + echo * feedback;
+                *sample = *sample * (1.0 - mix) + echo * mix;
+            }
+            self.write = (self.write + 1) % len;
+        }
+    }
+}
@@ -16,7 +16,7 @@ pub enum EqFilterType {
 }
 
 // 2. Parameters
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct EqParams {
     pub filter_type: EqFilterType,
     pub freq: f32, // Hz
@@ -37,6 +37,20 @@ impl Default for EqParams {
     }
 }
 
+/// Points in a response curve unless the caller asks otherwise.
+pub const EQ_RESPONSE_POINTS: usize = 256;
+const EQ_RESPONSE_MIN_HZ: f32 = 20.0;
+const EQ_RESPONSE_MAX_HZ: f32 = 20_000.0;
+
+/// Combined magnitude response of the active bands, for drawing the EQ curve.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EqResponse {
+    /// Log-spaced from 20 Hz to 20 kHz (or just below Nyquist).
+    pub freqs: Vec<f32>,
+    pub magnitudes_db: Vec<f32>,
+}
+
 // 3. The DSP Processor
 pub struct EqBand {
     coeffs: Coefficients<f32>,
@@ -117,10 +131,24 @@ impl EqBand {
                 filter.update_coefficients(self.coeffs);
             }
         } else {
-            println!("⚠️ Failed to calculate coefficients for Freq {}", safe_freq);
+            rt_warn!("⚠️ Failed to calculate coefficients for Freq {}", safe_freq);
         }
     }
 
+    /// |H(e^jw)| of the current coefficients at `freq`, in dB.
+    fn magnitude_db(&self, freq: f32) -> f32 {
+        let c = &self.coeffs;
+        let w = 2.0 * std::f64::consts::PI * freq as f64 / self.sr as f64;
+        let (cos1, sin1, cos2, sin2) = (w.cos(), w.sin(), (2.0 * w).cos(), (2.0 * w).sin());
+        // Numerator and denominator evaluated at z^-1 = e^-jw
+        let num_re = c.b0 as f64 + c.b1 as f64 * cos1 + c.b2 as f64 * cos2;
+        let num_im = -(c.b1 as f64 * sin1 + c.b2 as f64 * sin2);
+        let den_re = 1.0 + c.a1 as f64 * cos1 + c.a2 as f64 * cos2;
+        let den_im = -(c.a1 as f64 * sin1 + c.a2 as f64 * sin2);
+        let power = (num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im).max(1e-30);
+        (10.0 * power.max(1e-30).log10()) as f32
+    }
+
     #[inline]
     pub fn process(&mut self, sample: f32, channel_idx: usize) -> f32 {
         if self.params.active {
@@ -133,6 +161,17 @@ impl EqBand {
         }
         sample
     }
+
+    /// Run one channel of an interleaved buffer through this band. The filter state stays
+    /// in registers for the whole pass instead of being looked up per sample.
+    fn process_channel(&mut self, buffer: &mut [f32], channels: usize, channel_idx: usize) {
+        let Some(filter) = self.filters.get_mut(channel_idx) else { return };
+        for sample in buffer.iter_mut().skip(channel_idx).step_by(channels) {
+            let out = filter.run(*sample);
+            // Denormal protection
+            *sample = if out.abs() < 1e-20 { 0.0 } else { out };
+        }
+    }
 }
 
 // 4. The Chain
@@ -189,15 +228,14 @@ impl TrackEq {
         }
     }
 
-    // Zero-allocation in-place processing
+    // Zero-allocation in-place processing. Band by band and channel by channel (the bands
+    // are serial per channel, so the result is the same as frame by frame); bypassed bands
+    // cost nothing.
     pub fn process_buffer(&mut self, buffer: &mut [f32], channels: usize) {
-        for frame in buffer.chunks_mut(channels) {
-            for (ch, sample) in frame.iter_mut().enumerate() {
-                let mut s = *sample;
-                for band in &mut self.bands {
-                    s = band.process(s, ch);
-                }
-                *sample = s;
+        let channels = channels.max(1);
+        for band in self.bands.iter_mut().filter(|b| b.params.active) {
+            for ch in 0..channels {
+                band.process_channel(buffer, channels, ch);
             }
         }
     }
@@ -206,6 +244,40 @@ impl TrackEq {
         self.bands.iter().map(|b| b.params).collect()
     }
 
+    /// Take over `source`'s band settings, recalculating only the bands that changed.
+    /// Never allocates; the filter state stays this EQ's own.
+    pub fn follow(&mut self, source: &TrackEq) {
+        for (band, src) in self.bands.iter_mut().zip(&source.bands) {
+            if band.params != src.params {
+                band.update(src.params);
+            }
+        }
+    }
+
+    /// Clear every band's filter memory.
+    pub fn reset(&mut self) {
+        for filter in self.bands.iter_mut().flat_map(|b| b.filters.iter_mut()) {
+            filter.reset_state();
+        }
+    }
+
+    /// Response of the whole EQ at `points` log-spaced frequencies, from the same
+    /// coefficients the audio runs through. Bypassed bands are flat.
+    pub fn frequency_response(&self, points: usize) -> EqResponse {
+        let points = points.max(2);
+        let sr = self.bands.first().map_or(48_000, |b| b.sr);
+        let max_hz = EQ_RESPONSE_MAX_HZ.min(sr as f32 * 0.499);
+        let ratio = (max_hz / EQ_RESPONSE_MIN_HZ).ln();
+        let freqs: Vec<f32> = (0..points)
+            .map(|i| EQ_RESPONSE_MIN_HZ * (ratio * i as f32 / (points - 1) as f32).exp())
+            .collect();
+        let magnitudes_db = freqs
+            .iter()
+            .map(|&f| self.bands.iter().filter(|b| b.params.active).map(|b| b.magnitude_db(f)).sum())
+            .collect();
+        EqResponse { freqs, magnitudes_db }
+    }
+
     pub fn set_state(&mut self, state: Vec<EqParams>) {
         // Loop through the saved parameters and apply them to the corresponding bands
         for (i, params) in state.into_iter().enumerate() {
@@ -215,4 +287,10 @@ impl TrackEq {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+impl super::Effect for TrackEq {
+    fn process_block(&mut self, buffer: &mut [f32], channels: usize) {
+        self.process_buffer(buffer, channels);
+    }
+}
@@ -0,0 +1,214 @@
+// daw_modules/src/effects/limiter.rs
+
+// Master brickwall limiter with lookahead. Peaks above the threshold are held down to it and
+// the result is raised so the threshold lands on the ceiling; nothing leaves louder than the
+// ceiling. The gain needed by every incoming frame (linked across channels) goes through a
+// running minimum over the lookahead window and then a moving average of the same length,
+// which ramps the gain down smoothly and still reaches the required value by the time that
+// frame leaves the delay line. Recovery follows the release time.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use super::Effect;
+
+/// Longest lookahead; the delay lines are sized for it up front.
+const MAX_LOOKAHEAD_MS: f32 = 5.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LimiterParams {
+    pub enabled: bool,
+    pub threshold_db: f32, // -24.0 to 0.0; lowering it makes the mix louder
+    pub ceiling_db: f32,   // -12.0 to 0.0; highest sample peak that gets out
+    pub release_ms: f32,   // 1.0 to 1000.0
+    pub lookahead_ms: f32, // 0.1 to 5.0; also the latency the limiter adds
+}
+
+impl Default for LimiterParams {
+    // Transparent until the mix goes over full scale
+    fn default() -> Self {
+        Self { enabled: true, threshold_db: 0.0, ceiling_db: 0.0, release_ms: 100.0, lookahead_ms: 1.5 }
+    }
+}
+
+impl LimiterParams {
+    /// Ranges enforced; non-finite values fall back to the defaults.
+    pub fn sanitized(self) -> Self {
+        let d = Self::default();
+        let clamp = |v: f32, lo: f32, hi: f32, fallback: f32| if v.is_finite() { v.clamp(lo, hi) } else { fallback };
+        Self {
+            threshold_db: clamp(self.threshold_db, -24.0, 0.0, d.threshold_db),
+            ceiling_db: clamp(self.ceiling_db, -12.0, 0.0, d.ceiling_db),
+            release_ms: clamp(self.release_ms, 1.0, 1000.0, d.release_ms),
+            lookahead_ms: clamp(self.lookahead_ms, 0.1, MAX_LOOKAHEAD_MS, d.lookahead_ms),
+            ..self
+        }
+    }
+}
+
+/// Gain reduction of the last block (positive dB), for the UI. Lock-free.
+#[derive(Debug, Default)]
+pub struct GainReductionMeter {
+    db: AtomicU32,
+}
+
+impl GainReductionMeter {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.db.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, db: f32) {
+        self.db.store(db.to_bits(), Ordering::Relaxed);
+    }
+}
+
+pub struct LimiterNode {
+    params: LimiterParams,
+    sample_rate: u32,
+    window: usize, // lookahead in frames; the audio is delayed by window - 1
+    threshold: f32,
+    makeup: f32,
+    ceiling: f32,
+    release_coef: f32,
+    delay: Vec<VecDeque<f32>>, // per channel
+    // Running minimum of the required gain: (frame, gain), gains increasing front to back
+    minimum: VecDeque<(u64, f32)>,
+    // Last `window` outputs of the running minimum and their sum
+    average: VecDeque<f32>,
+    average_sum: f64,
+    envelope: f32,
+    frame: u64,
+    block_reduction: f32, // smallest gain applied in the current block
+}
+
+impl LimiterNode {
+    /// Channels beyond `channels` pass through untouched (and undelayed).
+    pub fn new(params: LimiterParams, channels: usize, sample_rate: u32) -> Self {
+        let capacity = (MAX_LOOKAHEAD_MS / 1000.0 * sample_rate.max(1) as f32).ceil() as usize + 1;
+        let mut node = Self {
+            params: LimiterParams::default(),
+            sample_rate: sample_rate.max(1),
+            window: 1,
+            threshold: 1.0,
+            makeup: 1.0,
+            ceiling: 1.0,
+            release_coef: 0.0,
+            delay: (0..channels.max(1)).map(|_| VecDeque::with_capacity(capacity)).collect(),
+            minimum: VecDeque::with_capacity(capacity),
+            average: VecDeque::with_capacity(capacity),
+            average_sum: 0.0,
+            envelope: 1.0,
+            frame: 0,
+            block_reduction: 1.0,
+        };
+        node.set_params(params);
+        node
+    }
+
+    pub fn get_params(&self) -> LimiterParams {
+        self.params
+    }
+
+    /// Never allocates. A new lookahead (or turning it on) starts from a clean state.
+    pub fn set_params(&mut self, params: LimiterParams) {
+        let params = params.sanitized();
+        let window = ((params.lookahead_ms / 1000.0 * self.sample_rate as f32).round() as usize).max(1);
+        let restart = window != self.window || params.enabled != self.params.enabled;
+        self.threshold = 10.0_f32.powf(params.threshold_db / 20.0);
+        self.ceiling = 10.0_f32.powf(params.ceiling_db / 20.0);
+        self.makeup = self.ceiling / self.threshold;
+        let release_frames = params.release_ms / 1000.0 * self.sample_rate as f32;
+        self.release_coef = 1.0 - (-1.0 / release_frames.max(1.0)).exp();
+        self.window = window;
+        self.params = params;
+        if restart {
+            self.reset();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.delay.iter_mut().for_each(VecDeque::clear);
+        self.minimum.clear();
+        self.average.clear();
+        self.average_sum = 0.0;
+        self.envelope = 1.0;
+        self.frame = 0;
+    }
+
+    /// Gain reduction of the last processed block, in positive dB.
+    pub fn gain_reduction_db(&self) -> f32 {
+        -20.0 * self.block_reduction.max(1e-6).log10()
+    }
+
+    // Gain for the frame leaving the delay line, given the peak of the frame entering it
+    #[inline]
+    fn next_gain(&mut self, peak: f32) -> f32 {
+        let required = if peak > self.threshold { self.threshold / peak } else { 1.0 };
+
+        // Running minimum over the last `window` frames
+        while self.minimum.back().is_some_and(|&(_, g)| g >= required) {
+            self.minimum.pop_back();
+        }
+        self.minimum.push_back((self.frame, required));
+        let oldest = self.frame.saturating_sub(self.window as u64 - 1);
+        while self.minimum.front().is_some_and(|&(f, _)| f < oldest) {
+            self.minimum.pop_front();
+        }
+        let held = self.minimum.front().map(|&(_, g)| g).unwrap_or(1.0);
+        self.frame += 1;
+
+        // Moving average of the held gain: a smooth ramp that never overshoots
+        self.average.push_back(held);
+        self.average_sum += held as f64;
+        if self.average.len() > self.window {
+            self.average_sum -= self.average.pop_front().unwrap_or(1.0) as f64;
+        }
+        // Frames not seen yet count as unity
+        let missing = self.window - self.average.len();
+        let smoothed = ((self.average_sum + missing as f64) / self.window as f64) as f32;
+
+        self.envelope = if smoothed < self.envelope {
+            smoothed
+        } else {
+            self.envelope + (smoothed - self.envelope) * self.release_coef
+        };
+        self.envelope
+    }
+}
+
+impl Effect for LimiterNode {
+    fn process_block(&mut self, buffer: &mut [f32], channels: usize) {
+        self.block_reduction = 1.0;
+        if !self.params.enabled {
+            return;
+        }
+        let channels = channels.max(1);
+        let limited = self.delay.len().min(channels);
+        let delay = self.window - 1;
+
+        for frame in buffer.chunks_exact_mut(channels) {
+            let peak = frame[..limited].iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+            let gain = self.next_gain(peak);
+            self.block_reduction = self.block_reduction.min(gain);
+
+            for (c, sample) in frame.iter_mut().enumerate().take(limited) {
+                let line = &mut self.delay[c];
+                line.push_back(*sample);
+                let delayed = if line.len() > delay { line.pop_front().unwrap_or(0.0) } else { 0.0 };
+                // The clamp only catches float rounding; the gain already got it there
+                *sample = (delayed * gain * self.makeup).clamp(-self.ceiling, self.ceiling);
+            }
+        }
+    }
+
+    fn latency_frames(&self) -> usize {
+        if self.params.enabled { self.window - 1 } else { 0 }
+    }
+}
@@ -1,4 +1,22 @@
 // daw_modules/src/effects/mod.rs
 pub mod equalizer;
 pub mod compressor;
-pub mod reverb;
\ No newline at end of file
+pub mod reverb;
+pub mod delay;
+pub mod soft_clip;
+pub mod limiter;
+pub mod chain;
+pub mod stereo_width;
+pub mod pitch_shift;
+
+/// Common interface for insert effects working on interleaved blocks.
+/// Implementations must stay realtime safe: no locks, no allocations.
+pub trait Effect: Send {
+    fn process_block(&mut self, buffer: &mut [f32], channels: usize);
+
+    /// Delay this effect adds to its output, in frames (lookahead, linear phase, ...).
+    /// The engine uses it for plugin delay compensation.
+    fn latency_frames(&self) -> usize {
+        0
+    }
+}
@@ -0,0 +1,251 @@
+// daw_modules/src/effects/pitch_shift.rs
+
+// Phase-vocoder pitch shifter (key changes, ±12 semitones). Each channel is cut into
+// Hann-windowed frames overlapping 4x; every bin's true frequency is estimated from its
+// phase advance, scaled by the pitch ratio and resynthesized at the shifted bin. With
+// formants preserved, the spectral envelope (a moving average of the magnitudes, wider
+// than a harmonic spacing) is divided out before the shift and the unshifted envelope put
+// back after it, so voices change key without turning into chipmunks. The output trails
+// the input by one frame minus a hop, reported as latency for delay compensation. FFTs are
+// planned and every buffer allocated up front.
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use super::Effect;
+
+/// Analysis frame in samples.
+const FRAME: usize = 2048;
+/// Frames overlap this many times.
+const OVERSAMPLE: usize = 4;
+const HOP: usize = FRAME / OVERSAMPLE;
+const BINS: usize = FRAME / 2 + 1;
+/// Width of the envelope smoothing; wider than the harmonic spacing of a voice.
+const ENVELOPE_HZ: f32 = 400.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PitchShiftParams {
+    pub is_active: bool,
+    pub semitones: f32, // -12.0 to +12.0, fractions allowed for detuning
+    pub preserve_formants: bool,
+}
+
+/// Off by default: even an unshifted pitch shifter adds its latency.
+impl Default for PitchShiftParams {
+    fn default() -> Self {
+        Self { is_active: false, semitones: 0.0, preserve_formants: false }
+    }
+}
+
+impl PitchShiftParams {
+    /// Ranges enforced; a non-finite shift falls back to none.
+    pub fn sanitized(self) -> Self {
+        let semitones = if self.semitones.is_finite() { self.semitones.clamp(-12.0, 12.0) } else { 0.0 };
+        Self { semitones, ..self }
+    }
+
+    fn ratio(&self) -> f32 {
+        2f32.powf(self.semitones / 12.0)
+    }
+}
+
+/// Analysis and overlap-add state of one channel.
+struct ChannelState {
+    input: Vec<f32>,      // FRAME samples, the newest frame being collected
+    output: Vec<f32>,     // finished samples of the last hop
+    accum: Vec<f32>,      // overlap-add accumulator, 2 * FRAME
+    last_phase: Vec<f32>, // per bin, from the previous analysis
+    sum_phase: Vec<f32>,  // per bin, running synthesis phase
+}
+
+impl ChannelState {
+    fn new() -> Self {
+        Self {
+            input: vec![0.0; FRAME],
+            output: vec![0.0; FRAME],
+            accum: vec![0.0; 2 * FRAME],
+            last_phase: vec![0.0; BINS],
+            sum_phase: vec![0.0; BINS],
+        }
+    }
+
+    fn clear(&mut self) {
+        for buf in [&mut self.input, &mut self.output, &mut self.accum, &mut self.last_phase, &mut self.sum_phase] {
+            buf.fill(0.0);
+        }
+    }
+}
+
+pub struct PitchShiftNode {
+    params: PitchShiftParams,
+    envelope_radius: usize, // bins either side averaged into the envelope
+    forward: Arc<dyn Fft<f32>>,
+    inverse: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    channels: Vec<ChannelState>,
+    position: usize, // shared fill position in every channel's frame, LATENCY..FRAME
+    was_active: bool,
+    // Per-frame work buffers, shared by the channels
+    spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    magnitude: Vec<f32>,
+    frequency: Vec<f32>, // true frequency per bin, in bins
+    envelope: Vec<f32>,
+    running: Vec<f32>, // prefix sums for the envelope
+    shifted_magnitude: Vec<f32>,
+    shifted_frequency: Vec<f32>,
+}
+
+impl PitchShiftNode {
+    /// Allocates and plans the FFTs; build it off the audio thread.
+    pub fn new(params: PitchShiftParams, channels: usize, sample_rate: u32) -> Self {
+        let mut planner = FftPlanner::<f32>::new();
+        let forward = planner.plan_fft_forward(FRAME);
+        let inverse = planner.plan_fft_inverse(FRAME);
+        let scratch_len = forward.get_inplace_scratch_len().max(inverse.get_inplace_scratch_len());
+        let bin_hz = sample_rate.max(1) as f32 / FRAME as f32;
+        Self {
+            params: params.sanitized(),
+            envelope_radius: ((ENVELOPE_HZ * 0.5 / bin_hz).round() as usize).max(1),
+            forward,
+            inverse,
+            window: (0..FRAME).map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FRAME as f32).cos()).collect(),
+            channels: (0..channels.max(1)).map(|_| ChannelState::new()).collect(),
+            position: Self::LATENCY,
+            was_active: false,
+            spectrum: vec![Complex::new(0.0, 0.0); FRAME],
+            scratch: vec![Complex::new(0.0, 0.0); scratch_len],
+            magnitude: vec![0.0; BINS],
+            frequency: vec![0.0; BINS],
+            envelope: vec![0.0; BINS],
+            running: vec![0.0; BINS + 1],
+            shifted_magnitude: vec![0.0; BINS],
+            shifted_frequency: vec![0.0; BINS],
+        }
+    }
+
+    /// Frames the output trails the input by while active.
+    pub const LATENCY: usize = FRAME - HOP;
+
+    pub fn get_params(&self) -> PitchShiftParams {
+        self.params
+    }
+
+    /// Never allocates; the analysis state carries over, so a key change doesn't click.
+    pub fn set_params(&mut self, params: PitchShiftParams) {
+        self.params = params.sanitized();
+    }
+
+    fn process_frame(&mut self, channel: usize) {
+        let ratio = self.params.ratio();
+        let expected = 2.0 * PI * HOP as f32 / FRAME as f32; // phase advance of bin 1 per hop
+        let state = &mut self.channels[channel];
+
+        for ((bin, &x), &w) in self.spectrum.iter_mut().zip(&state.input).zip(&self.window) {
+            *bin = Complex::new(x * w, 0.0);
+        }
+        self.forward.process_with_scratch(&mut self.spectrum, &mut self.scratch);
+
+        // Analysis: magnitude and true frequency (in bins) of every bin
+        for k in 0..BINS {
+            let (magnitude, phase) = self.spectrum[k].to_polar();
+            let mut delta = phase - state.last_phase[k] - k as f32 * expected;
+            state.last_phase[k] = phase;
+            delta -= 2.0 * PI * (delta / (2.0 * PI)).round(); // wrap to -pi..pi
+            self.magnitude[k] = 2.0 * magnitude;
+            self.frequency[k] = k as f32 + delta * OVERSAMPLE as f32 / (2.0 * PI);
+        }
+
+        let preserve = self.params.preserve_formants;
+        if preserve {
+            // Moving average of the magnitudes, then flatten the spectrum by it
+            self.running[0] = 0.0;
+            for k in 0..BINS {
+                self.running[k + 1] = self.running[k] + self.magnitude[k];
+            }
+            let radius = self.envelope_radius;
+            for k in 0..BINS {
+                let (lo, hi) = (k.saturating_sub(radius), (k + radius + 1).min(BINS));
+                self.envelope[k] = ((self.running[hi] - self.running[lo]) / (hi - lo) as f32).max(1e-9);
+                self.magnitude[k] /= self.envelope[k];
+            }
+        }
+
+        // Move every bin to its shifted position
+        self.shifted_magnitude.fill(0.0);
+        self.shifted_frequency.fill(0.0);
+        for k in 0..BINS {
+            let target = (k as f32 * ratio).round() as usize;
+            if target < BINS {
+                self.shifted_magnitude[target] += self.magnitude[k];
+                self.shifted_frequency[target] = self.frequency[k] * ratio;
+            }
+        }
+        if preserve {
+            for (m, &e) in self.shifted_magnitude.iter_mut().zip(&self.envelope) {
+                *m *= e;
+            }
+        }
+
+        // Synthesis: accumulate phase at each bin's new frequency; negative bins stay empty
+        // and the real part is doubled below
+        for k in 0..BINS {
+            let deviation = self.shifted_frequency[k] - k as f32;
+            state.sum_phase[k] = (state.sum_phase[k] + k as f32 * expected + deviation * 2.0 * PI / OVERSAMPLE as f32)
+                .rem_euclid(2.0 * PI);
+            self.spectrum[k] = Complex::from_polar(self.shifted_magnitude[k], state.sum_phase[k]);
+        }
+        self.spectrum[BINS..].fill(Complex::new(0.0, 0.0));
+        self.inverse.process_with_scratch(&mut self.spectrum, &mut self.scratch);
+
+        let scale = 2.0 / ((FRAME / 2) * OVERSAMPLE) as f32;
+        for ((acc, bin), &w) in state.accum.iter_mut().zip(&self.spectrum).zip(&self.window) {
+            *acc += 2.0 * w * bin.re * scale;
+        }
+        state.output[..HOP].copy_from_slice(&state.accum[..HOP]);
+        state.accum.copy_within(HOP.., 0);
+        let len = state.accum.len();
+        state.accum[len - HOP..].fill(0.0);
+        state.input.copy_within(HOP.., 0);
+    }
+}
+
+impl Effect for PitchShiftNode {
+    fn process_block(&mut self, buffer: &mut [f32], channels: usize) {
+        if !self.params.is_active {
+            self.was_active = false;
+            return;
+        }
+        if !self.was_active {
+            // Don't replay what was left in the frames when last switched off
+            self.channels.iter_mut().for_each(ChannelState::clear);
+            self.position = Self::LATENCY;
+            self.was_active = true;
+        }
+        let channels = channels.max(1);
+        let shifted = channels.min(self.channels.len());
+        for frame in buffer.chunks_exact_mut(channels) {
+            let read = self.position - Self::LATENCY;
+            for (c, sample) in frame.iter_mut().enumerate().take(shifted) {
+                let state = &mut self.channels[c];
+                state.input[self.position] = *sample;
+                *sample = state.output[read];
+            }
+            self.position += 1;
+            if self.position == FRAME {
+                for c in 0..shifted {
+                    self.process_frame(c);
+                }
+                self.position = Self::LATENCY;
+            }
+        }
+    }
+
+    fn latency_frames(&self) -> usize {
+        if self.params.is_active { Self::LATENCY } else { 0 }
+    }
+}
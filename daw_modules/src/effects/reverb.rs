@@ -1,5 +1,11 @@
 // daw_modules/src/effects/reverb.rs
 
+// Freeverb-style stereo reverb: a pre-delay, a band-limited (low/high cut) mono feed,
+// eight parallel damped combs and four series allpasses per side, then a width/mix matrix.
+// Every delay line is allocated in `new`, so it is safe on the audio thread, on a track
+// or an aux bus. Parameters are atomics: the UI writes them while the audio thread runs,
+// and they are read once per block.
+
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +16,7 @@ const STEREO_SPREAD: usize = 23;
 const MAX_PRE_DELAY_MS: f32 = 500.0;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ReverbParams {
     pub is_active: bool,
     pub room_size: f32,    // 0.0 to 1.0
@@ -21,6 +28,53 @@ pub struct ReverbParams {
     pub high_cut_hz: f32,  // 1000.0 to 20000.0
 }
 
+impl Default for ReverbParams {
+    fn default() -> Self {
+        Self {
+            is_active: false,
+            room_size: 0.8,
+            damping: 0.5,
+            mix: 0.3,
+            width: 1.0,
+            pre_delay_ms: 10.0,
+            low_cut_hz: 100.0,
+            high_cut_hz: 8000.0,
+        }
+    }
+}
+
+impl ReverbParams {
+    /// Clamped to the ranges above (a room size past 1.0 would make the combs run away).
+    pub fn sanitized(self) -> Self {
+        let finite = |v: f32, fallback: f32| if v.is_finite() { v } else { fallback };
+        let d = Self::default();
+        Self {
+            is_active: self.is_active,
+            room_size: finite(self.room_size, d.room_size).clamp(0.0, 1.0),
+            damping: finite(self.damping, d.damping).clamp(0.0, 1.0),
+            mix: finite(self.mix, d.mix).clamp(0.0, 1.0),
+            width: finite(self.width, d.width).clamp(0.0, 1.0),
+            pre_delay_ms: finite(self.pre_delay_ms, d.pre_delay_ms).clamp(0.0, MAX_PRE_DELAY_MS),
+            low_cut_hz: finite(self.low_cut_hz, d.low_cut_hz).clamp(20.0, 1000.0),
+            high_cut_hz: finite(self.high_cut_hz, d.high_cut_hz).clamp(1000.0, 20000.0),
+        }
+    }
+}
+
+// Per-block values derived from the parameters
+struct Coefficients {
+    feedback: f32,
+    damp: f32,
+    wet1: f32,
+    wet2: f32,
+    dry: f32,
+    a0_lp: f32,
+    b1_lp: f32,
+    a0_hp: f32,
+    b1_hp: f32,
+    pre_delay: usize, // frames
+}
+
 fn f32_to_atomic(val: f32) -> AtomicU32 {
     AtomicU32::new(val.to_bits())
 }
@@ -39,7 +93,7 @@ struct DelayLine {
 impl DelayLine {
     fn new(size: usize) -> Self {
         Self {
-            buffer: vec![0.0; size],
+            buffer: vec![0.0; size.max(1)],
             index: 0,
         }
     }
@@ -49,6 +103,13 @@ impl DelayLine {
         self.buffer[self.index]
     }
 
+    /// The sample written `delay` writes ago (0 = the one just written).
+    #[inline]
+    fn read_back(&self, delay: usize) -> f32 {
+        let len = self.buffer.len();
+        self.buffer[(self.index + len - 1 - delay.min(len - 1)) % len]
+    }
+
     #[inline]
     fn write_and_advance(&mut self, value: f32) {
         self.buffer[self.index] = value;
@@ -166,17 +227,18 @@ impl ReverbNode {
             allpasses_r.push(AllpassFilter::new(r_len));
         }
 
-        let max_pre_delay_samples = (MAX_PRE_DELAY_MS * sample_rate / 1000.0) as usize;
+        let max_pre_delay_samples = (MAX_PRE_DELAY_MS * sample_rate / 1000.0) as usize + 1;
+        let d = ReverbParams::default();
 
         Self {
-            is_active: AtomicBool::new(false),
-            room_size: f32_to_atomic(0.8),
-            damping: f32_to_atomic(0.5),
-            mix: f32_to_atomic(0.3),
-            width: f32_to_atomic(1.0),
-            pre_delay_ms: f32_to_atomic(10.0),
-            low_cut_hz: f32_to_atomic(100.0),
-            high_cut_hz: f32_to_atomic(8000.0),
+            is_active: AtomicBool::new(d.is_active),
+            room_size: f32_to_atomic(d.room_size),
+            damping: f32_to_atomic(d.damping),
+            mix: f32_to_atomic(d.mix),
+            width: f32_to_atomic(d.width),
+            pre_delay_ms: f32_to_atomic(d.pre_delay_ms),
+            low_cut_hz: f32_to_atomic(d.low_cut_hz),
+            high_cut_hz: f32_to_atomic(d.high_cut_hz),
             
             sample_rate,
             pre_delay_buffer: DelayLine::new(max_pre_delay_samples),
@@ -191,21 +253,24 @@ impl ReverbNode {
 
     // --- Dynamic Param Setters (For your new generic routing system) ---
     pub fn set_param(&self, param_name: &str, value: f32) {
+        let mut params = self.get_params();
         match param_name {
-            "room_size" => self.room_size.store(value.to_bits(), Ordering::Relaxed),
-            "damping" => self.damping.store(value.to_bits(), Ordering::Relaxed),
-            "mix" => self.mix.store(value.to_bits(), Ordering::Relaxed),
-            "width" => self.width.store(value.to_bits(), Ordering::Relaxed),
-            "pre_delay" => self.pre_delay_ms.store(value.to_bits(), Ordering::Relaxed),
-            "low_cut" => self.low_cut_hz.store(value.to_bits(), Ordering::Relaxed),
-            "high_cut" => self.high_cut_hz.store(value.to_bits(), Ordering::Relaxed),
-            "active" => self.is_active.store(value > 0.5, Ordering::Relaxed),
-            _ => {}
+            "room_size" => params.room_size = value,
+            "damping" => params.damping = value,
+            "mix" => params.mix = value,
+            "width" => params.width = value,
+            "pre_delay" => params.pre_delay_ms = value,
+            "low_cut" => params.low_cut_hz = value,
+            "high_cut" => params.high_cut_hz = value,
+            "active" => params.is_active = value > 0.5,
+            _ => return,
         }
+        self.set_params(params);
     }
 
     // --- BATCH PARAM SETTER (Required for AI and Undo/Redo) ---
     pub fn set_params(&self, params: ReverbParams) {
+        let params = params.sanitized();
         self.is_active.store(params.is_active, Ordering::Relaxed);
         self.room_size.store(params.room_size.to_bits(), Ordering::Relaxed);
         self.damping.store(params.damping.to_bits(), Ordering::Relaxed);
@@ -229,68 +294,95 @@ impl ReverbNode {
         }
     }
 
-    // --- ZERO ALLOCATION AUDIO LOOP ---
-    #[inline]
-    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
-        if !self.is_active.load(Ordering::Relaxed) {
-            return (left, right);
-        }
-
-        // 1. Read atomics (Lock-free)
-        let room_size = atomic_to_f32(&self.room_size) * 0.28 + 0.7; // Scale to Freeverb range
-        let damp = atomic_to_f32(&self.damping) * 0.4;
+    fn coefficients(&self) -> Coefficients {
         let mix = atomic_to_f32(&self.mix);
         let width = atomic_to_f32(&self.width);
-        let wet1 = mix * (width / 2.0 + 0.5);
-        let wet2 = mix * ((1.0 - width) / 2.0);
-        let dry = 1.0 - mix;
 
         // EQ Coefficients calculation (Simple one-pole mapping)
         let hc_rad = (2.0 * std::f32::consts::PI * atomic_to_f32(&self.high_cut_hz)) / self.sample_rate;
         let b1_lp = (-hc_rad).exp();
-        let a0_lp = 1.0 - b1_lp;
-
         let lc_rad = (2.0 * std::f32::consts::PI * atomic_to_f32(&self.low_cut_hz)) / self.sample_rate;
         let b1_hp = (-lc_rad).exp();
-        let a0_hp = 1.0 - b1_hp;
 
-        // 2. Pre-Delay
-        let input_mono = (left + right) * 0.5;
-        let pre_delayed = self.pre_delay_buffer.read();
-        
-        // Write to pre-delay. Index wrapping based on actual pre-delay parameter.
-        let pd_samples = (atomic_to_f32(&self.pre_delay_ms) * self.sample_rate / 1000.0) as usize;
-        let pd_write_idx = (self.pre_delay_buffer.index + pd_samples).clamp(0, self.pre_delay_buffer.buffer.len() - 1);
-        self.pre_delay_buffer.buffer[pd_write_idx] = input_mono;
-        self.pre_delay_buffer.index = (self.pre_delay_buffer.index + 1) % self.pre_delay_buffer.buffer.len();
+        Coefficients {
+            feedback: atomic_to_f32(&self.room_size) * 0.28 + 0.7, // Scale to Freeverb range
+            damp: atomic_to_f32(&self.damping) * 0.4,
+            wet1: mix * (width / 2.0 + 0.5),
+            wet2: mix * ((1.0 - width) / 2.0),
+            dry: 1.0 - mix,
+            a0_lp: 1.0 - b1_lp,
+            b1_lp,
+            a0_hp: 1.0 - b1_hp,
+            b1_hp,
+            pre_delay: (atomic_to_f32(&self.pre_delay_ms) * self.sample_rate / 1000.0) as usize,
+        }
+    }
+
+    // --- ZERO ALLOCATION AUDIO LOOP ---
+    /// One frame. Reads the parameters every call; `process_block` reads them once per block.
+    #[inline]
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if !self.is_active.load(Ordering::Relaxed) {
+            return (left, right);
+        }
+        let c = self.coefficients();
+        self.process_frame(left, right, &c)
+    }
+
+    #[inline]
+    fn process_frame(&mut self, left: f32, right: f32, c: &Coefficients) -> (f32, f32) {
+        // 1. Pre-Delay (0 ms passes the input straight through)
+        self.pre_delay_buffer.write_and_advance((left + right) * 0.5);
+        let pre_delayed = self.pre_delay_buffer.read_back(c.pre_delay);
 
-        // 3. Apply Reverb Input EQ (Low Cut / High Cut)
+        // 2. Apply Reverb Input EQ (Low Cut / High Cut)
         let mut reverb_input = pre_delayed;
-        reverb_input = self.hp_l.process_hp(reverb_input, a0_hp, b1_hp);
-        reverb_input = self.lp_l.process_lp(reverb_input, a0_lp, b1_lp);
+        reverb_input = self.hp_l.process_hp(reverb_input, c.a0_hp, c.b1_hp);
+        reverb_input = self.lp_l.process_lp(reverb_input, c.a0_lp, c.b1_lp);
 
         // Attenuate input to avoid clipping in the combs
         let reverb_input = reverb_input * 0.015;
 
-        // 4. Parallel Comb Filters
+        // 3. Parallel Comb Filters
         let mut out_l = 0.0;
         let mut out_r = 0.0;
-        
-        for i in 0..8 {
-            out_l += self.combs_l[i].process(reverb_input, damp, room_size);
-            out_r += self.combs_r[i].process(reverb_input, damp, room_size);
+        for (comb_l, comb_r) in self.combs_l.iter_mut().zip(&mut self.combs_r) {
+            out_l += comb_l.process(reverb_input, c.damp, c.feedback);
+            out_r += comb_r.process(reverb_input, c.damp, c.feedback);
         }
 
-        // 5. Series Allpass Filters
-        for i in 0..4 {
-            out_l = self.allpasses_l[i].process(out_l);
-            out_r = self.allpasses_r[i].process(out_r);
+        // 4. Series Allpass Filters
+        for (ap_l, ap_r) in self.allpasses_l.iter_mut().zip(&mut self.allpasses_r) {
+            out_l = ap_l.process(out_l);
+            out_r = ap_r.process(out_r);
         }
 
-        // 6. Stereo Mix Matrix
-        let final_l = (out_l * wet1) + (out_r * wet2) + (left * dry);
-        let final_r = (out_r * wet1) + (out_l * wet2) + (right * dry);
+        // 5. Stereo Mix Matrix
+        let final_l = (out_l * c.wet1) + (out_r * c.wet2) + (left * c.dry);
+        let final_r = (out_r * c.wet1) + (out_l * c.wet2) + (right * c.dry);
 
         (final_l, final_r)
     }
-}
\ No newline at end of file
+}
+
+impl super::Effect for ReverbNode {
+    fn process_block(&mut self, buffer: &mut [f32], channels: usize) {
+        if !self.is_active.load(Ordering::Relaxed) {
+            return;
+        }
+        let c = self.coefficients();
+        if channels >= 2 {
+            for frame in buffer.chunks_exact_mut(channels) {
+                let (l, r) = self.process_frame(frame[0], frame[1], &c);
+                frame[0] = l;
+                frame[1] = r;
+            }
+        } else {
+            // Fallback for mono tracks
+            for s in buffer.iter_mut() {
+                let (l, _) = self.process_frame(*s, *s, &c);
+                *s = l;
+            }
+        }
+    }
+}
@@ -0,0 +1,363 @@
+// daw_modules/src/effects/soft_clip.rs
+
+// Master soft clipper: an optional saturation stage at the end of the mix, ahead of the
+// brickwall limiter (it used to be the only protection, a fixed tanh; now it is off unless
+// chosen). The curve, drive and output trim are selectable, and the shaper can run 2x/4x
+// oversampled through cascaded half-band FIR filters so the harmonics it creates above
+// Nyquist are filtered out instead of folding back as aliasing. Bypass is a true bypass:
+// the block comes out bit for bit. Every mode (bypass included) has the latency of the 4x
+// path, so switching never moves the master in time.
+
+use serde::{Deserialize, Serialize};
+
+use super::Effect;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SoftClipCurve {
+    #[default]
+    Tanh,
+    /// x - x³/3, scaled to reach full scale at the knee; gentler than tanh.
+    Cubic,
+    Hard,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Oversampling {
+    #[default]
+    Off,
+    X2,
+    X4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SoftClipParams {
+    pub bypass: bool,
+    pub curve: SoftClipCurve,
+    pub drive_db: f32,  // 0.0 to 24.0
+    pub output_db: f32, // -24.0 to 6.0
+    pub oversampling: Oversampling,
+}
+
+impl Default for SoftClipParams {
+    // Bypassed: the limiter keeps the master legal; saturation is a sound choice
+    fn default() -> Self {
+        Self { bypass: true, curve: SoftClipCurve::Tanh, drive_db: 0.0, output_db: 0.0, oversampling: Oversampling::Off }
+    }
+}
+
+impl SoftClipParams {
+    /// Ranges enforced; non-finite values fall back to 0 dB.
+    pub fn sanitized(self) -> Self {
+        let db = |v: f32, lo: f32, hi: f32| if v.is_finite() { v.clamp(lo, hi) } else { 0.0 };
+        Self { drive_db: db(self.drive_db, 0.0, 24.0), output_db: db(self.output_db, -24.0, 6.0), ..self }
+    }
+}
+
+// Taps of the filter next to the base rate (steep: passband to ~0.42 fs, ~74 dB stopband)
+// and of the inner 4x stage (only has to clear the band above the outer stage's passband).
+const OUTER_TAPS: usize = 63;
+const INNER_TAPS: usize = 23;
+
+/// Latency of every mode: the 4x path's filters (73 samples at 2x) plus one 2x sample of
+/// padding to land on a whole frame. The other modes delay their dry signal to match.
+pub const SOFT_CLIP_LATENCY_FRAMES: usize = 37;
+// The 2x path's filters alone
+const X2_FILTER_FRAMES: usize = (OUTER_TAPS - 1) / 2;
+
+/// Half-band lowpass at a quarter of its own rate (Blackman-windowed sinc). Every other
+/// tap is zero, so only the non-zero ones are stored.
+struct HalfBand {
+    taps: Vec<(usize, f32)>, // (delay, coefficient)
+    history: Vec<f32>,
+    pos: usize,
+}
+
+impl HalfBand {
+    fn new(len: usize) -> Self {
+        let center = (len - 1) as f32 / 2.0;
+        let mut coeffs: Vec<f32> = (0..len)
+            .map(|n| {
+                let x = n as f32 - center;
+                let sinc = if x == 0.0 { 1.0 } else { (std::f32::consts::PI * x * 0.5).sin() / (std::f32::consts::PI * x * 0.5) };
+                let phase = 2.0 * std::f32::consts::PI * n as f32 / (len - 1) as f32;
+                let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+                0.5 * sinc * window
+            })
+            .collect();
+        let sum: f32 = coeffs.iter().sum();
+        coeffs.iter_mut().for_each(|c| *c /= sum);
+
+        Self {
+            taps: coeffs.into_iter().enumerate().filter(|(_, c)| c.abs() > 1e-6).collect(),
+            history: vec![0.0; len],
+            pos: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.pos = 0;
+    }
+
+    #[inline]
+    fn push(&mut self, x: f32) {
+        self.pos = (self.pos + 1) % self.history.len();
+        self.history[self.pos] = x;
+    }
+
+    #[inline]
+    fn output(&self) -> f32 {
+        let len = self.history.len();
+        self.taps.iter().map(|&(k, c)| c * self.history[(self.pos + len - k) % len]).sum()
+    }
+
+    /// One sample in, two out (zero-stuffed, gain restored).
+    #[inline]
+    fn upsample(&mut self, x: f32) -> [f32; 2] {
+        self.push(x * 2.0);
+        let a = self.output();
+        self.push(0.0);
+        [a, self.output()]
+    }
+
+    /// Two samples in, one out (taken at `a`, which keeps the delay a whole base sample).
+    #[inline]
+    fn downsample(&mut self, a: f32, b: f32) -> f32 {
+        self.push(a);
+        let y = self.output();
+        self.push(b);
+        y
+    }
+}
+
+struct ChannelState {
+    up_outer: HalfBand,
+    up_inner: HalfBand,
+    down_inner: HalfBand,
+    down_outer: HalfBand,
+    pad: f32, // the 4x path's one sample of padding at 2x
+    // Input history, always written, so a mode change reads on without a gap
+    dry: [f32; SOFT_CLIP_LATENCY_FRAMES + 1],
+    dry_pos: usize,
+}
+
+impl ChannelState {
+    fn new() -> Self {
+        Self {
+            up_outer: HalfBand::new(OUTER_TAPS),
+            up_inner: HalfBand::new(INNER_TAPS),
+            down_inner: HalfBand::new(INNER_TAPS),
+            down_outer: HalfBand::new(OUTER_TAPS),
+            pad: 0.0,
+            dry: [0.0; SOFT_CLIP_LATENCY_FRAMES + 1],
+            dry_pos: 0,
+        }
+    }
+
+    /// Record `x` and return the input from `frames` ago.
+    #[inline]
+    fn delay_dry(&mut self, x: f32, frames: usize) -> f32 {
+        let len = self.dry.len();
+        self.dry_pos = (self.dry_pos + 1) % len;
+        self.dry[self.dry_pos] = x;
+        self.dry[(self.dry_pos + len - frames) % len]
+    }
+
+    fn reset(&mut self) {
+        self.up_outer.reset();
+        self.up_inner.reset();
+        self.down_inner.reset();
+        self.down_outer.reset();
+        self.pad = 0.0;
+    }
+}
+
+pub struct SoftClipNode {
+    params: SoftClipParams,
+    drive: f32,  // linear
+    output: f32, // linear
+    channels: Vec<ChannelState>, // filters for every mode are allocated up front
+}
+
+impl SoftClipNode {
+    /// Channels beyond `channels` pass through untouched.
+    pub fn new(params: SoftClipParams, channels: usize) -> Self {
+        let mut node = Self {
+            params: SoftClipParams::default(),
+            drive: 1.0,
+            output: 1.0,
+            channels: (0..channels.max(1)).map(|_| ChannelState::new()).collect(),
+        };
+        node.set_params(params);
+        node
+    }
+
+    pub fn get_params(&self) -> SoftClipParams {
+        self.params
+    }
+
+    /// Never allocates. Switching the oversampling clears the filter state (the dry
+    /// history carries on).
+    pub fn set_params(&mut self, params: SoftClipParams) {
+        let params = params.sanitized();
+        if params.oversampling != self.params.oversampling {
+            self.channels.iter_mut().for_each(ChannelState::reset);
+        }
+        self.drive = 10.0_f32.powf(params.drive_db / 20.0);
+        self.output = 10.0_f32.powf(params.output_db / 20.0);
+        self.params = params;
+    }
+
+    #[inline]
+    fn shape(&self, x: f32) -> f32 {
+        let x = x * self.drive;
+        match self.params.curve {
+            SoftClipCurve::Tanh => x.tanh(),
+            SoftClipCurve::Cubic => {
+                let x = (x / 1.5).clamp(-1.0, 1.0);
+                1.5 * (x - x * x * x / 3.0)
+            }
+            SoftClipCurve::Hard => x.clamp(-1.0, 1.0),
+        }
+    }
+}
+
+impl Effect for SoftClipNode {
+    fn process_block(&mut self, buffer: &mut [f32], channels: usize) {
+        let channels = channels.max(1);
+        let oversampling = self.params.oversampling;
+
+        for frame in buffer.chunks_exact_mut(channels) {
+            for (c, sample) in frame.iter_mut().enumerate().take(self.channels.len()) {
+                let x = *sample;
+                if self.params.bypass {
+                    *sample = self.channels[c].delay_dry(x, SOFT_CLIP_LATENCY_FRAMES);
+                    continue;
+                }
+                let y = match oversampling {
+                    Oversampling::Off => {
+                        let x = self.channels[c].delay_dry(x, SOFT_CLIP_LATENCY_FRAMES);
+                        self.shape(x)
+                    }
+                    Oversampling::X2 => {
+                        let x = self.channels[c].delay_dry(x, SOFT_CLIP_LATENCY_FRAMES - X2_FILTER_FRAMES);
+                        let [a, b] = self.channels[c].up_outer.upsample(x);
+                        let (a, b) = (self.shape(a), self.shape(b));
+                        self.channels[c].down_outer.downsample(a, b)
+                    }
+                    Oversampling::X4 => {
+                        self.channels[c].delay_dry(x, 0);
+                        let [a, b] = self.channels[c].up_outer.upsample(x);
+                        let [a1, a2] = self.channels[c].up_inner.upsample(a);
+                        let [b1, b2] = self.channels[c].up_inner.upsample(b);
+                        let (a1, a2, b1, b2) = (self.shape(a1), self.shape(a2), self.shape(b1), self.shape(b2));
+                        let st = &mut self.channels[c];
+                        let a = st.down_inner.downsample(a1, a2);
+                        let b = st.down_inner.downsample(b1, b2);
+                        let earlier = std::mem::replace(&mut st.pad, b);
+                        st.down_outer.downsample(earlier, a)
+                    }
+                };
+                *sample = y * self.output;
+            }
+        }
+    }
+
+    fn latency_frames(&self) -> usize {
+        SOFT_CLIP_LATENCY_FRAMES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustfft::{num_complex::Complex, FftPlanner};
+
+    const SR: f32 = 48_000.0;
+
+    fn sine(freq: f32, amp: f32, frames: usize) -> Vec<f32> {
+        (0..frames).map(|n| amp * (std::f32::consts::TAU * freq * n as f32 / SR).sin()).collect()
+    }
+
+    fn node(bypass: bool, curve: SoftClipCurve, oversampling: Oversampling) -> SoftClipNode {
+        SoftClipNode::new(SoftClipParams { bypass, curve, oversampling, ..SoftClipParams::default() }, 1)
+    }
+
+    #[test]
+    fn every_mode_has_the_same_latency() {
+        let input = sine(1000.0, 0.5, 4096);
+        // The hard curve is linear below full scale, so only the delay (and filter ripple) shows
+        for (bypass, oversampling, tolerance) in [
+            (true, Oversampling::Off, 0.0),
+            (false, Oversampling::Off, 0.0),
+            (false, Oversampling::X2, 1e-2),
+            (false, Oversampling::X4, 1e-2),
+        ] {
+            let mut clip = node(bypass, SoftClipCurve::Hard, oversampling);
+            assert_eq!(clip.latency_frames(), SOFT_CLIP_LATENCY_FRAMES);
+            let mut out = input.clone();
+            clip.process_block(&mut out, 1);
+            let error = (256..out.len())
+                .map(|n| (out[n] - input[n - SOFT_CLIP_LATENCY_FRAMES]).abs())
+                .fold(0.0, f32::max);
+            assert!(error <= tolerance, "{oversampling:?} (bypass {bypass}) is off by {error}");
+        }
+    }
+
+    #[test]
+    fn bypass_is_bit_transparent_and_switching_keeps_the_timing() {
+        let input = sine(440.0, 1.5, 2048);
+        let mut clip = node(false, SoftClipCurve::Tanh, Oversampling::X4);
+        let mut out = input.clone();
+        let (first, second) = out.split_at_mut(1024);
+        clip.process_block(first, 1);
+        clip.set_params(SoftClipParams { bypass: true, ..clip.get_params() });
+        clip.process_block(second, 1);
+        // The dry history ran on under the 4x path: bypass picks up exactly where it left off
+        for n in 1024..2048 {
+            assert_eq!(out[n].to_bits(), input[n - SOFT_CLIP_LATENCY_FRAMES].to_bits());
+        }
+    }
+
+    // Strongest component away from the 15 kHz fundamental, in dB relative to it
+    fn aliasing_db(out: &[f32]) -> f32 {
+        let len = out.len();
+        let mut spectrum: Vec<Complex<f32>> = out
+            .iter()
+            .enumerate()
+            .map(|(n, &s)| {
+                let window = 0.5 - 0.5 * (std::f32::consts::TAU * n as f32 / len as f32).cos();
+                Complex::new(s * window, 0.0)
+            })
+            .collect();
+        FftPlanner::new().plan_fft_forward(len).process(&mut spectrum);
+        let magnitudes: Vec<f32> = spectrum[..len / 2].iter().map(|c| c.norm()).collect();
+        let fundamental_bin = (15_000.0 * len as f32 / SR).round() as usize;
+        let fundamental = magnitudes[fundamental_bin - 2..=fundamental_bin + 2].iter().copied().fold(0.0, f32::max);
+        let spur = magnitudes
+            .iter()
+            .enumerate()
+            .filter(|&(bin, _)| bin > 4 && bin.abs_diff(fundamental_bin) > 4)
+            .map(|(_, &m)| m)
+            .fold(0.0, f32::max);
+        20.0 * (spur / fundamental).log10()
+    }
+
+    #[test]
+    fn oversampled_tanh_folds_back_at_least_40_db_less() {
+        // Every harmonic of 15 kHz is above Nyquist: whatever lands in band is aliasing
+        let input = sine(15_000.0, 1.0, 16_384 + 1024);
+        let alias = |oversampling| {
+            let mut out = input.clone();
+            node(false, SoftClipCurve::Tanh, oversampling).process_block(&mut out, 1);
+            aliasing_db(&out[1024..])
+        };
+        let plain = alias(Oversampling::Off);
+        let x4 = alias(Oversampling::X4);
+        assert!(plain > -60.0, "the plain path should alias audibly ({plain:.1} dB)");
+        assert!(x4 <= plain - 40.0, "4x: {x4:.1} dB, plain: {plain:.1} dB");
+    }
+}
@@ -0,0 +1,126 @@
+// daw_modules/src/effects/stereo_width.rs
+
+// Mid/side widener. The stereo pair is split into mid (L+R) and side (L-R), each gets its
+// own gain, and the side is high-passed below `mono_below_hz` so the low end stays mono
+// (and survives a mono fold-down). Gain changes ramp across a block. Wider buses only
+// touch their first two channels; a mono bus passes through untouched.
+
+use biquad::{Biquad, Coefficients, DirectForm2Transposed, ToHertz, Type, Q_BUTTERWORTH_F32};
+use serde::{Deserialize, Serialize};
+
+use super::Effect;
+
+/// Below 20 Hz the mono-bass filter is off.
+const MONO_BELOW_OFF_HZ: f32 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct StereoWidthParams {
+    pub is_active: bool,
+    pub mid_gain_db: f32,   // -24.0 to +12.0
+    pub side_gain_db: f32,  // -60.0 (near mono) to +12.0
+    pub mono_below_hz: f32, // 0 (off) or 20.0 to 500.0
+}
+
+impl Default for StereoWidthParams {
+    fn default() -> Self {
+        Self { is_active: true, mid_gain_db: 0.0, side_gain_db: 0.0, mono_below_hz: 0.0 }
+    }
+}
+
+impl StereoWidthParams {
+    /// Ranges enforced; non-finite values fall back to the defaults.
+    pub fn sanitized(self) -> Self {
+        let d = Self::default();
+        let clamp = |v: f32, lo: f32, hi: f32, fallback: f32| if v.is_finite() { v.clamp(lo, hi) } else { fallback };
+        let mono_below = clamp(self.mono_below_hz, 0.0, 500.0, d.mono_below_hz);
+        Self {
+            mid_gain_db: clamp(self.mid_gain_db, -24.0, 12.0, d.mid_gain_db),
+            side_gain_db: clamp(self.side_gain_db, -60.0, 12.0, d.side_gain_db),
+            mono_below_hz: if mono_below < MONO_BELOW_OFF_HZ { 0.0 } else { mono_below },
+            ..self
+        }
+    }
+}
+
+fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+pub struct StereoWidthNode {
+    params: StereoWidthParams,
+    sample_rate: u32,
+    mid_gain: f32,  // as heard at the end of the last block
+    side_gain: f32,
+    side_high_pass: Option<DirectForm2Transposed<f32>>, // None while mono-bass is off
+}
+
+impl StereoWidthNode {
+    pub fn new(params: StereoWidthParams, sample_rate: u32) -> Self {
+        let params = params.sanitized();
+        let mut node = Self {
+            params,
+            sample_rate: sample_rate.max(1),
+            mid_gain: db_to_linear(params.mid_gain_db),
+            side_gain: db_to_linear(params.side_gain_db),
+            side_high_pass: None,
+        };
+        node.set_params(params);
+        node
+    }
+
+    pub fn get_params(&self) -> StereoWidthParams {
+        self.params
+    }
+
+    /// Never allocates; a new crossover keeps the filter's state.
+    pub fn set_params(&mut self, params: StereoWidthParams) {
+        let params = params.sanitized();
+        let cutoff = params.mono_below_hz.min(self.sample_rate as f32 * 0.45);
+        self.side_high_pass = if params.mono_below_hz > 0.0 {
+            Coefficients::<f32>::from_params(Type::HighPass, self.sample_rate.hz(), cutoff.hz(), Q_BUTTERWORTH_F32)
+                .ok()
+                .map(|coeffs| match self.side_high_pass.take() {
+                    Some(mut filter) => {
+                        filter.update_coefficients(coeffs);
+                        filter
+                    }
+                    None => DirectForm2Transposed::<f32>::new(coeffs),
+                })
+        } else {
+            None
+        };
+        self.params = params;
+    }
+}
+
+impl Effect for StereoWidthNode {
+    fn process_block(&mut self, buffer: &mut [f32], channels: usize) {
+        if !self.params.is_active || channels < 2 {
+            return;
+        }
+        let frames = buffer.len() / channels;
+        if frames == 0 {
+            return;
+        }
+        let (mid_target, side_target) = (db_to_linear(self.params.mid_gain_db), db_to_linear(self.params.side_gain_db));
+        let mid_step = (mid_target - self.mid_gain) / frames as f32;
+        let side_step = (side_target - self.side_gain) / frames as f32;
+
+        for frame in buffer.chunks_exact_mut(channels) {
+            self.mid_gain += mid_step;
+            self.side_gain += side_step;
+            let mid = (frame[0] + frame[1]) * 0.5;
+            let mut side = (frame[0] - frame[1]) * 0.5;
+            if let Some(filter) = self.side_high_pass.as_mut() {
+                side = filter.run(side);
+                if side.abs() < 1e-20 { side = 0.0; } // denormal protection
+            }
+            let (mid, side) = (mid * self.mid_gain, side * self.side_gain);
+            frame[0] = mid + side;
+            frame[1] = mid - side;
+        }
+        self.mid_gain = mid_target;
+        self.side_gain = side_target;
+    }
+}
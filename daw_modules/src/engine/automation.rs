@@ -2,6 +2,27 @@
 
 use serde::{Deserialize, Serialize};
 
+/// How a track's effect parameter lanes behave while the transport runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AutomationMode {
+    /// Lanes drive the parameters; knob moves aren't recorded.
+    #[default]
+    Read,
+    /// From the play position on, every lane is overwritten: with the knob while it moves,
+    /// with the value it was left at otherwise.
+    Write,
+    /// A parameter starts writing when its knob first moves and holds its last value
+    /// until playback stops.
+    Latch,
+}
+
+impl AutomationMode {
+    pub fn is_writing(self) -> bool {
+        self != AutomationMode::Read
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct AutomationNode<T> {
     pub time: u64, // Position in samples
@@ -40,6 +61,27 @@ impl AutomationCurve<f32> {
         }
     }
 
+    /// Like `insert_node`, but only into spare capacity, so it never allocates (audio thread).
+    /// Returns false when the node didn't fit.
+    pub fn try_insert_node(&mut self, time: u64, value: f32) -> bool {
+        let exact = self.nodes.binary_search_by_key(&time, |n| n.time).is_ok();
+        if !exact && self.nodes.len() == self.nodes.capacity() {
+            return false;
+        }
+        self.insert_node(time, value);
+        true
+    }
+
+    /// Make room for `additional` nodes, so that many `try_insert_node` calls succeed.
+    pub fn reserve(&mut self, additional: usize) {
+        self.nodes.reserve(additional);
+    }
+
+    /// Drop every node after `after` up to and including `through`. Never allocates.
+    pub fn remove_between(&mut self, after: u64, through: u64) {
+        self.nodes.retain(|n| n.time <= after || n.time > through);
+    }
+
     /// Removes a node at a specific time, returning true if found and removed.
     pub fn remove_node_at_time(&mut self, time: u64) -> bool {
         if let Ok(pos) = self.nodes.binary_search_by_key(&time, |n| n.time) {
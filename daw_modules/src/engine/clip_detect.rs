@@ -0,0 +1,81 @@
+// src/engine/clip_detect.rs
+
+// Over detection on the master, before the clipper and limiter hide it. The audio thread
+// counts samples beyond 0 dBFS and, per clipping block, records where on the timeline it
+// happened and which track was hottest at that moment. The UI polls the event counter and
+// clears the indicator; everything crosses through atomics.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use super::track::TrackId;
+
+const NO_TRACK: u32 = u32::MAX;
+
+/// The latest over, as the UI shows it.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipEvent {
+    /// Timeline position of the last clipping block, in seconds.
+    pub timestamp: f64,
+    /// Loudest track (post-fader) in that block; `None` if no track was playing (e.g. previews).
+    pub track_id: Option<u32>,
+    /// Samples over 0 dBFS since the indicator was last cleared.
+    pub clipped_samples: u64,
+}
+
+/// The lock-free bridge: the audio thread reports, the UI reads and clears.
+pub struct ClipReadout {
+    clipped_samples: AtomicU64,
+    events: AtomicU64, // bumped per clipping block; never reset, so watchers can diff it
+    timestamp: AtomicU64,
+    track: AtomicU32,
+}
+
+impl ClipReadout {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            clipped_samples: AtomicU64::new(0),
+            events: AtomicU64::new(0),
+            timestamp: AtomicU64::new(0.0f64.to_bits()),
+            track: AtomicU32::new(NO_TRACK),
+        })
+    }
+
+    /// Number of clipping blocks so far; changes whenever a new over comes in.
+    pub fn event_count(&self) -> u64 {
+        self.events.load(Ordering::Acquire)
+    }
+
+    /// The last over, or `None` while the indicator is clear.
+    pub fn latest(&self) -> Option<ClipEvent> {
+        let clipped_samples = self.clipped_samples.load(Ordering::Relaxed);
+        if clipped_samples == 0 {
+            return None;
+        }
+        let track = self.track.load(Ordering::Relaxed);
+        Some(ClipEvent {
+            timestamp: f64::from_bits(self.timestamp.load(Ordering::Relaxed)),
+            track_id: (track != NO_TRACK).then_some(track),
+            clipped_samples,
+        })
+    }
+
+    pub fn clear(&self) {
+        self.clipped_samples.store(0, Ordering::Relaxed);
+        self.track.store(NO_TRACK, Ordering::Relaxed);
+    }
+
+    /// Audio thread: count the overs in a pre-limiter master block.
+    pub fn scan(&self, buffer: &[f32], position_secs: f64, hottest: Option<TrackId>) {
+        let overs = buffer.iter().filter(|s| s.abs() > 1.0).count() as u64;
+        if overs == 0 {
+            return;
+        }
+        self.clipped_samples.fetch_add(overs, Ordering::Relaxed);
+        self.timestamp.store(position_secs.to_bits(), Ordering::Relaxed);
+        self.track.store(hottest.map_or(NO_TRACK, |id| id.0), Ordering::Relaxed);
+        self.events.fetch_add(1, Ordering::Release);
+    }
+}
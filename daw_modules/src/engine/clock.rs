@@ -0,0 +1,261 @@
+// src/engine/clock.rs
+
+// Transport clock for the UI. After every render block the engine publishes how many frames
+// the device has pulled, where the playhead is and when that happened (monotonic host time).
+// The UI extrapolates from the latest pair instead of polling the position and guessing.
+// Fields are published under a seqlock so readers never block the audio thread and never
+// see half of one block and half of the next.
+//
+// Accuracy: the pair itself is exact to the frame. What the UI adds is the age of the
+// snapshot (measured on read) plus the IPC hop, typically well under 1 ms, so a playhead
+// extrapolated from it and corrected a few times a second stays within a fraction of a
+// video frame. The published position is what was last rendered; the audio callback also
+// reports the device's output latency (from the stream timestamps), and
+// `audible_position` takes it off so the playhead matches what is leaving the speakers.
+// Positions are timeline frames at `sample_rate`; under varispeed the playhead moves
+// `playback_rate` timeline seconds per wall-clock second and extrapolation follows suit.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering, fence};
+use std::time::Instant;
+
+use serde::Serialize;
+
+/// One published (frames rendered, host time) pair with the transport state of that block.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSync {
+    /// Bumped on every seek, play and pause; extrapolations from an older generation are stale.
+    pub generation: u64,
+    /// Frames the device has pulled since the clock was created (runs while paused too).
+    pub frames_rendered: u64,
+    /// Playhead at the end of the published block.
+    pub position_frames: u64,
+    pub sample_rate: u32,
+    /// Varispeed rate of the published block (1.0 = normal speed).
+    pub playback_rate: f64,
+    pub playing: bool,
+    /// Playhead at the first block of this generation (where play or a seek started).
+    pub generation_start_frames: u64,
+    /// Frames between rendering and the speakers, as last reported by the device (0 if unknown).
+    pub output_latency_frames: u64,
+    /// Seconds between the publish and this snapshot being read.
+    pub age_secs: f64,
+}
+
+impl TimeSync {
+    /// Playhead in seconds at the moment the snapshot was read.
+    pub fn position_now(&self) -> f64 {
+        let published = self.position_frames as f64 / self.sample_rate.max(1) as f64;
+        if self.playing { published + self.age_secs * self.playback_rate } else { published }
+    }
+
+    /// Playhead in seconds as heard: `position_now` minus the output latency, never before
+    /// the point playback (or the last seek) started from.
+    pub fn audible_position(&self) -> f64 {
+        let sr = self.sample_rate.max(1) as f64;
+        let now = self.position_now();
+        if !self.playing {
+            return now;
+        }
+        let floor = self.generation_start_frames as f64 / sr;
+        (now - self.output_latency_frames as f64 / sr * self.playback_rate).max(floor.min(now))
+    }
+}
+
+pub struct TransportClock {
+    epoch: Instant,
+    seq: AtomicU64, // odd while a publish is in progress
+    pending_generation: AtomicU64, // bumped by the engine, published with the next block
+    generation: AtomicU64,
+    frames_rendered: AtomicU64,
+    position_frames: AtomicU64,
+    host_nanos: AtomicU64, // publish time, nanoseconds since `epoch`
+    sample_rate: AtomicU32,
+    playback_rate: AtomicU64, // f64 bits
+    playing: AtomicBool,
+    generation_start_frames: AtomicU64,
+    output_latency_frames: AtomicU64, // written by the audio callback, outside the seqlock
+    finished_count: AtomicU64, // times playback ran past the project end
+    finished_at_frames: AtomicU64, // the project end it last ran past
+}
+
+impl TransportClock {
+    pub fn new() -> std::sync::Arc<Self> {
+        std::sync::Arc::new(Self {
+            epoch: Instant::now(),
+            seq: AtomicU64::new(0),
+            pending_generation: AtomicU64::new(0),
+            generation: AtomicU64::new(0),
+            frames_rendered: AtomicU64::new(0),
+            position_frames: AtomicU64::new(0),
+            host_nanos: AtomicU64::new(0),
+            sample_rate: AtomicU32::new(44100),
+            playback_rate: AtomicU64::new(1.0f64.to_bits()),
+            playing: AtomicBool::new(false),
+            generation_start_frames: AtomicU64::new(0),
+            output_latency_frames: AtomicU64::new(0),
+            finished_count: AtomicU64::new(0),
+            finished_at_frames: AtomicU64::new(0),
+        })
+    }
+
+    /// Mark a discontinuity (seek, play, pause). Visible from the next publish on.
+    pub fn bump_generation(&self) {
+        self.pending_generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Output latency of the device, from the callback's stream timestamps. Slow-moving, so
+    /// it is a plain atomic next to the seqlocked pair.
+    pub fn set_output_latency(&self, frames: u64) {
+        self.output_latency_frames.store(frames, Ordering::Relaxed);
+    }
+
+    /// Playback ran past the end of the last clip at `end_frames`. Outside the seqlock,
+    /// like the output latency: the UI only polls for a changed count.
+    pub fn note_finished(&self, end_frames: u64) {
+        self.finished_at_frames.store(end_frames, Ordering::Relaxed);
+        self.finished_count.fetch_add(1, Ordering::Release);
+    }
+
+    /// (times playback reached the project end, the end it last reached in frames)
+    pub fn finished(&self) -> (u64, u64) {
+        let count = self.finished_count.load(Ordering::Acquire);
+        (count, self.finished_at_frames.load(Ordering::Relaxed))
+    }
+
+    /// Called once per block by the engine (single writer: it holds the engine lock).
+    pub fn publish(&self, block_frames: usize, position_frames: u64, sample_rate: u32, playback_rate: f64, playing: bool) {
+        let now = self.epoch.elapsed().as_nanos() as u64;
+        let generation = self.pending_generation.load(Ordering::Relaxed);
+        self.seq.fetch_add(1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        if self.generation.load(Ordering::Relaxed) != generation {
+            // `position_frames` is the end of this block; the generation began at its start
+            let advanced = (block_frames as f64 * playback_rate).round() as u64;
+            let start = if playing { position_frames.saturating_sub(advanced) } else { position_frames };
+            self.generation_start_frames.store(start, Ordering::Relaxed);
+        }
+        self.generation.store(generation, Ordering::Relaxed);
+        self.frames_rendered.fetch_add(block_frames as u64, Ordering::Relaxed);
+        self.position_frames.store(position_frames, Ordering::Relaxed);
+        self.host_nanos.store(now, Ordering::Relaxed);
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+        self.playback_rate.store(playback_rate.to_bits(), Ordering::Relaxed);
+        self.playing.store(playing, Ordering::Relaxed);
+        self.seq.fetch_add(1, Ordering::Release);
+    }
+
+    /// Latest consistent pair. Never blocks; retries only while a publish is mid-write.
+    pub fn snapshot(&self) -> TimeSync {
+        loop {
+            let before = self.seq.load(Ordering::Acquire);
+            if before & 1 == 1 {
+                std::hint::spin_loop();
+                continue;
+            }
+            let generation = self.generation.load(Ordering::Relaxed);
+            let frames_rendered = self.frames_rendered.load(Ordering::Relaxed);
+            let position_frames = self.position_frames.load(Ordering::Relaxed);
+            let host_nanos = self.host_nanos.load(Ordering::Relaxed);
+            let sample_rate = self.sample_rate.load(Ordering::Relaxed);
+            let playback_rate = f64::from_bits(self.playback_rate.load(Ordering::Relaxed));
+            let playing = self.playing.load(Ordering::Relaxed);
+            let generation_start_frames = self.generation_start_frames.load(Ordering::Relaxed);
+            fence(Ordering::Acquire);
+            if self.seq.load(Ordering::Relaxed) != before {
+                continue;
+            }
+            let now = self.epoch.elapsed().as_nanos() as u64;
+            return TimeSync {
+                generation,
+                frames_rendered,
+                position_frames,
+                sample_rate,
+                playback_rate,
+                playing,
+                generation_start_frames,
+                output_latency_frames: self.output_latency_frames.load(Ordering::Relaxed),
+                age_secs: now.saturating_sub(host_nanos) as f64 / 1e9,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn published_pairs_only_move_forward() {
+        let clock = TransportClock::new();
+        let mut last = clock.snapshot();
+        for block in 1..=200u64 {
+            clock.publish(256, block * 256, 48_000, 1.0, true);
+            let sync = clock.snapshot();
+            assert_eq!(sync.frames_rendered, block * 256);
+            assert!(sync.position_frames > last.position_frames);
+            assert!(sync.position_now() >= last.position_now());
+            assert_eq!(sync.generation, 0);
+            last = sync;
+        }
+        // Paused: the device keeps pulling frames, the playhead holds
+        clock.publish(256, last.position_frames, 48_000, 1.0, false);
+        let paused = clock.snapshot();
+        assert!(paused.frames_rendered > last.frames_rendered);
+        assert_eq!(paused.position_now(), last.position_frames as f64 / 48_000.0);
+    }
+
+    #[test]
+    fn a_generation_starts_at_the_first_block_after_the_bump() {
+        let clock = TransportClock::new();
+        clock.publish(256, 256, 48_000, 1.0, true);
+        clock.bump_generation();
+        assert_eq!(clock.snapshot().generation, 0, "only visible with the next block");
+
+        // Seek to 10 s: that block renders 10 s..10 s + 256 frames
+        clock.publish(256, 480_256, 48_000, 1.0, true);
+        let sync = clock.snapshot();
+        assert_eq!(sync.generation, 1);
+        assert_eq!(sync.generation_start_frames, 480_000);
+
+        // Later blocks of the same generation keep its start
+        clock.publish(256, 480_512, 48_000, 1.0, true);
+        assert_eq!(clock.snapshot().generation_start_frames, 480_000);
+    }
+
+    #[test]
+    fn audible_position_takes_off_the_latency_but_not_past_the_start() {
+        let clock = TransportClock::new();
+        clock.set_output_latency(4_800); // 100 ms
+        clock.bump_generation();
+        clock.publish(480, 48_480, 48_000, 1.0, true); // play from 1 s
+        let sync = clock.snapshot();
+        assert_eq!(sync.audible_position(), 1.0, "clamped to where playback started");
+
+        for n in 2..=20u64 {
+            clock.publish(480, 48_000 + n * 480, 48_000, 1.0, true);
+        }
+        let sync = clock.snapshot();
+        assert!((sync.position_now() - sync.audible_position() - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn readers_never_see_half_a_publish() {
+        let clock = TransportClock::new();
+        let reader = clock.clone();
+        let handle = std::thread::spawn(move || {
+            let mut last = 0;
+            for _ in 0..100_000 {
+                let sync = reader.snapshot();
+                // Published together: the playhead is always twice the frames pulled
+                assert_eq!(sync.position_frames, sync.frames_rendered * 2);
+                assert!(sync.frames_rendered >= last);
+                last = sync.frames_rendered;
+            }
+        });
+        for block in 1..=100_000u64 {
+            clock.publish(1, block * 2, 48_000, 2.0, true);
+        }
+        handle.join().unwrap();
+    }
+}
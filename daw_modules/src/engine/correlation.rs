@@ -0,0 +1,118 @@
+// src/engine/correlation.rs
+
+// Stereo correlation / goniometer on the master bus. The audio thread keeps smoothed
+// L*R, L² and R² energies and publishes the correlation coefficient (+1 mono, 0 wide,
+// -1 out of phase), plus a decimated ring of recent L/R pairs for the goniometer.
+// Each pair is packed into one u64 so the UI never sees half of a point.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// L/R pairs kept for the goniometer.
+pub const GONIOMETER_POINTS: usize = 512;
+/// Keep one frame out of this many (512 points ≈ 46 ms at 44.1 kHz).
+const DECIMATION: usize = 4;
+/// Averaging time of the correlation coefficient.
+const INTEGRATION_SECS: f32 = 0.3;
+/// Below this the signal counts as silence and the coefficient falls back to 0.
+const SILENCE_ENERGY: f32 = 1e-10;
+
+/// What the UI draws: the coefficient and the recent points, oldest first.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CorrelationReading {
+    pub correlation: f32,
+    pub points: Vec<[f32; 2]>,
+}
+
+/// The lock-free bridge: the audio thread writes, the UI reads.
+pub struct CorrelationReadout {
+    correlation: AtomicU32,
+    points: Vec<AtomicU64>,
+    write_pos: AtomicUsize,
+}
+
+fn pack(l: f32, r: f32) -> u64 {
+    ((l.to_bits() as u64) << 32) | r.to_bits() as u64
+}
+
+fn unpack(bits: u64) -> [f32; 2] {
+    [f32::from_bits((bits >> 32) as u32), f32::from_bits(bits as u32)]
+}
+
+impl CorrelationReadout {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            correlation: AtomicU32::new(0.0f32.to_bits()),
+            points: (0..GONIOMETER_POINTS).map(|_| AtomicU64::new(pack(0.0, 0.0))).collect(),
+            write_pos: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn correlation(&self) -> f32 {
+        f32::from_bits(self.correlation.load(Ordering::Relaxed))
+    }
+
+    pub fn reading(&self) -> CorrelationReading {
+        let start = self.write_pos.load(Ordering::Acquire);
+        let points = (0..GONIOMETER_POINTS)
+            .map(|i| unpack(self.points[(start + i) % GONIOMETER_POINTS].load(Ordering::Relaxed)))
+            .collect();
+        CorrelationReading { correlation: self.correlation(), points }
+    }
+}
+
+/// Owned by the audio thread. Never allocates.
+pub struct CorrelationMeter {
+    coeff: f32, // one-pole smoothing per frame
+    lr: f32,
+    ll: f32,
+    rr: f32,
+    decimate: usize,
+    write_pos: usize,
+}
+
+impl CorrelationMeter {
+    pub fn new(sample_rate: u32) -> Self {
+        let frames = INTEGRATION_SECS * sample_rate.max(1) as f32;
+        Self {
+            coeff: (-1.0 / frames).exp(),
+            lr: 0.0,
+            ll: 0.0,
+            rr: 0.0,
+            decimate: 0,
+            write_pos: 0,
+        }
+    }
+
+    /// Measure an interleaved master block. Mono (or wider) buses use the first two channels;
+    /// a mono bus reads as fully correlated.
+    pub fn process_block(&mut self, buffer: &[f32], channels: usize, readout: &CorrelationReadout) {
+        let channels = channels.max(1);
+        let k = self.coeff;
+        for frame in buffer.chunks_exact(channels) {
+            let l = frame[0];
+            let r = if channels > 1 { frame[1] } else { l };
+            self.lr = k * self.lr + (1.0 - k) * l * r;
+            self.ll = k * self.ll + (1.0 - k) * l * l;
+            self.rr = k * self.rr + (1.0 - k) * r * r;
+
+            self.decimate += 1;
+            if self.decimate >= DECIMATION {
+                self.decimate = 0;
+                readout.points[self.write_pos].store(pack(l, r), Ordering::Relaxed);
+                self.write_pos = (self.write_pos + 1) % GONIOMETER_POINTS;
+            }
+        }
+        readout.write_pos.store(self.write_pos, Ordering::Release);
+
+        let energy = self.ll * self.rr;
+        let correlation = if energy > SILENCE_ENERGY * SILENCE_ENERGY {
+            (self.lr / energy.sqrt()).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+        readout.correlation.store(correlation.to_bits(), Ordering::Relaxed);
+    }
+}
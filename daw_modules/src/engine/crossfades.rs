@@ -0,0 +1,101 @@
+// src/engine/crossfades.rs
+
+// Automatic crossfades. Where two clips of a track overlap, the one starting later fades in
+// and the other fades out on an equal-power (sin/cos) curve instead of the two just summing.
+// The fade starts where the later clip starts and lasts the whole overlap unless that clip
+// asks for a shorter one; past the fade only the later clip is heard. A clip lying entirely
+// inside another hands back to it at its end the same way. Used by playback, export and freeze
+// alike, so all three sound the same.
+
+use std::f32::consts::FRAC_PI_2;
+use std::time::Duration;
+
+use super::time::Frames;
+
+/// A clip's place on the timeline, as far as crossfading is concerned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: Duration,
+    pub end: Duration,
+    /// Fade length where this clip enters an earlier one; `None` fades over the whole overlap.
+    pub crossfade: Option<Duration>,
+}
+
+// One overlap: `incoming` starts inside `outgoing`
+struct Overlap {
+    start: Duration,
+    end: Duration,
+    fade: Duration,
+    nested: bool, // incoming ends first and hands back to outgoing
+}
+
+fn overlap(outgoing: &Span, incoming: &Span) -> Option<Overlap> {
+    let end = outgoing.end.min(incoming.end);
+    if incoming.start >= end {
+        return None;
+    }
+    let nested = incoming.end < outgoing.end;
+    let len = end - incoming.start;
+    // A nested clip needs room to fade out again
+    let limit = if nested { len / 2 } else { len };
+    let fade = incoming.crossfade.map_or(limit, |d| d.min(limit));
+    Some(Overlap { start: incoming.start, end, fade, nested })
+}
+
+/// Length of the crossfade where clip `index` enters an earlier clip, if it overlaps one.
+/// `spans` must be sorted by start (as a track keeps its clips).
+pub fn crossfade_in(spans: &[Span], index: usize) -> Option<Duration> {
+    let incoming = spans.get(index)?;
+    spans[..index].iter().filter_map(|outgoing| overlap(outgoing, incoming)).map(|o| o.fade).max()
+}
+
+/// Apply the crossfade gains of clip `index` to its interleaved audio, which starts at timeline
+/// frame `first_frame`. `spans` are all the track's clips, sorted by start.
+pub fn apply(buf: &mut [f32], channels: usize, first_frame: u64, index: usize, spans: &[Span], sample_rate: u32) {
+    let channels = channels.max(1);
+    let Some(own) = spans.get(index) else {
+        return;
+    };
+    let last_frame = first_frame + (buf.len() / channels) as u64;
+
+    for (other_index, other) in spans.iter().enumerate() {
+        if other_index == index {
+            continue;
+        }
+        // Clips are sorted, so the earlier index is the one being entered
+        let is_incoming = other_index < index;
+        let o = if is_incoming { overlap(other, own) } else { overlap(own, other) };
+        let Some(o) = o else {
+            continue;
+        };
+        let start = Frames::from_duration(o.start, sample_rate).0;
+        let end = Frames::from_duration(o.end, sample_rate).0;
+        let fade = Frames::from_duration(o.fade, sample_rate).0;
+        let from = start.max(first_frame);
+        let to = end.min(last_frame);
+        if from >= to {
+            continue;
+        }
+
+        for t in from..to {
+            // Position along the fade: 0 = outgoing only, 1 = incoming only
+            let x = if fade > 0 && t - start < fade {
+                ((t - start) as f32 + 0.5) / fade as f32
+            } else if o.nested && fade > 0 && end - t <= fade {
+                ((end - t) as f32 - 0.5) / fade as f32
+            } else {
+                1.0
+            };
+            let gain = match (is_incoming, x >= 1.0) {
+                (true, _) => (x * FRAC_PI_2).sin(),
+                (false, true) => 0.0,
+                (false, false) => (x * FRAC_PI_2).cos(),
+            };
+            if gain == 1.0 {
+                continue;
+            }
+            let i = (t - first_frame) as usize * channels;
+            buf[i..i + channels].iter_mut().for_each(|s| *s *= gain);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// src/engine/cue.rs
+
+// Monitor (cue) blend: lets a performer balance "me" against "the band" while tracking.
+// Applied only where live input and playback meet in the output callback, so the
+// record ring buffers, track faders and offline bounces never see it.
+
+/// Upper bound for either blend level (+6 dB).
+pub const MAX_CUE_LEVEL: f32 = 2.0;
+
+pub struct CueBlend {
+    input_target: f32,
+    playback_target: f32,
+    input: f32,
+    playback: f32,
+}
+
+impl CueBlend {
+    pub fn new() -> Self {
+        Self { input_target: 1.0, playback_target: 1.0, input: 1.0, playback: 1.0 }
+    }
+
+    pub fn set_levels(&mut self, input: f32, playback: f32) {
+        self.input_target = sanitize(input);
+        self.playback_target = sanitize(playback);
+    }
+
+    /// Target (input, playback) levels.
+    pub fn levels(&self) -> (f32, f32) {
+        (self.input_target, self.playback_target)
+    }
+
+    /// `out` holds the playback mix; `live_in` the monitored input (same layout).
+    /// Both gains ramp linearly across the block so changes never click.
+    /// When nothing is being monitored, playback glides back to unity.
+    pub fn mix(&mut self, out: &mut [f32], live_in: &[f32], channels: usize, monitoring: bool) {
+        let channels = channels.max(1);
+        let frames = out.len() / channels;
+        if frames == 0 {
+            return;
+        }
+
+        let playback_target = if monitoring { self.playback_target } else { 1.0 };
+        let in_step = (self.input_target - self.input) / frames as f32;
+        let pb_step = (playback_target - self.playback) / frames as f32;
+
+        for (frame, live) in out.chunks_exact_mut(channels).zip(live_in.chunks_exact(channels)) {
+            self.input += in_step;
+            self.playback += pb_step;
+            for (o, l) in frame.iter_mut().zip(live) {
+                *o = *o * self.playback + *l * self.input;
+            }
+        }
+
+        // Land exactly on target (no float drift)
+        self.input = self.input_target;
+        self.playback = playback_target;
+    }
+}
+
+impl Default for CueBlend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn sanitize(level: f32) -> f32 {
+    if level.is_finite() { level.clamp(0.0, MAX_CUE_LEVEL) } else { 1.0 }
+}
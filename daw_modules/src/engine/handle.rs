@@ -0,0 +1,556 @@
+// src/engine/handle.rs
+
+// Who owns the engine. While a stream runs, the engine lives in the audio callback
+// (`EngineSlot`) and nobody locks it: a control thread boxes its edit, pushes it onto a
+// lock-free queue and, when it needs a result, waits for it on a one-slot reply ring. The
+// callback runs queued edits between blocks and pushes every spent box onto a second ring,
+// so it is freed on the control side rather than on the audio thread; tracks and clips an
+// edit removed go back the same way. With no stream the
+// engine is parked in the handle and edits run inline.
+//
+// After every block the callback publishes an `EngineState` through a triple buffer, so the
+// common getters (playing, position, rate) never wait for a callback.
+//
+// Edits run on the audio thread, between blocks: keep them short. Work that opens files or
+// renders is prepared by the caller first, or done with the engine detached
+// (`with_detached`; the callback plays silence meanwhile).
+
+use ringbuf::traits::{Consumer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use super::{Discarded, Engine};
+use super::triple_buffer::{self, Reader, Writer};
+
+/// Edits waiting for the callback before `post` refuses more.
+const QUEUE_CAPACITY: usize = 1024;
+/// Edits run per callback; a burst carries over to the next one instead of eating a block.
+const EDITS_PER_BLOCK: usize = 64;
+/// How long a control thread waits for the callback to run its edit or hand the engine back.
+pub const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+/// Poll interval while waiting for a reply.
+const REPLY_POLL: Duration = Duration::from_micros(250);
+
+/// The engine can't be reached: an edit panicked, or the callback stopped answering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineUnavailable {
+    Poisoned,
+    Timeout,
+    Lost,
+}
+
+impl std::fmt::Display for EngineUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Poisoned => write!(f, "The audio engine stopped after an error; rebuild it"),
+            Self::Timeout => write!(f, "The audio engine did not respond in time"),
+            Self::Lost => write!(f, "The audio engine was lost with its stream; rebuild it"),
+        }
+    }
+}
+
+impl std::error::Error for EngineUnavailable {}
+
+/// Transport state as of the last rendered block, readable without a round trip.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EngineState {
+    pub playing: bool,
+    pub recording: bool,
+    pub monitor_muted: bool,
+    pub position: Duration,
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub bpm: f64,
+    pub playback_rate: f64,
+    pub latency_frames: usize,
+    pub content_revision: u64,
+}
+
+impl EngineState {
+    pub fn capture(eng: &Engine) -> Self {
+        Self {
+            playing: eng.transport.playing,
+            recording: eng.recording,
+            monitor_muted: eng.monitor_muted,
+            position: eng.transport.time(),
+            sample_rate: eng.sample_rate,
+            channels: eng.channels,
+            bpm: eng.transport.tempo.bpm,
+            playback_rate: eng.playback_rate(),
+            latency_frames: eng.latency_frames(),
+            content_revision: eng.content_revision(),
+        }
+    }
+}
+
+trait Job: Send {
+    fn run(&mut self, eng: &mut Engine);
+}
+
+// An edit and, if the caller waits for it, where its result goes
+struct Call<F, R> {
+    edit: Option<F>,
+    reply: Option<HeapProd<R>>,
+}
+
+impl<F, R> Job for Call<F, R>
+where
+    F: FnOnce(&mut Engine) -> R + Send,
+    R: Send,
+{
+    fn run(&mut self, eng: &mut Engine) {
+        let Some(edit) = self.edit.take() else { return };
+        let result = edit(eng);
+        if let Some(reply) = self.reply.as_mut() {
+            // One slot, one result: there is always room
+            let _ = reply.try_push(result);
+        }
+    }
+}
+
+// What the callback is done with, to be freed on the control side
+#[allow(clippy::large_enum_variant)]
+enum Spent {
+    Job(#[allow(dead_code)] Box<dyn Job>),
+    Discarded(#[allow(dead_code)] Discarded),
+}
+
+enum Message {
+    Run(Box<dyn Job>),
+    Attach(Box<Engine>),
+    Detach,
+}
+
+/// The callback's side: owns the engine while a stream runs.
+pub struct EngineSlot {
+    engine: Option<Box<Engine>>,
+    inbox: HeapCons<Message>,
+    spent: HeapProd<Spent>,
+    returned: HeapProd<Box<Engine>>,
+    state: Writer<EngineState>,
+    poisoned: Arc<AtomicBool>,
+}
+
+impl EngineSlot {
+    /// Run the queued edits, then `render` with the engine and publish its state. Returns
+    /// false (nothing rendered) while the engine is away or after a panic.
+    pub fn process(&mut self, render: impl FnOnce(&mut Engine)) -> bool {
+        for _ in 0..EDITS_PER_BLOCK {
+            let Some(message) = self.inbox.try_pop() else { break };
+            match message {
+                Message::Attach(engine) => self.engine = Some(engine),
+                Message::Detach => {
+                    if let Some(engine) = self.engine.take() {
+                        // Room for exactly one; `attach` only sends one at a time
+                        let _ = self.returned.try_push(engine);
+                    }
+                }
+                Message::Run(mut job) => {
+                    let poisoned = self.poisoned.load(Ordering::Relaxed);
+                    if let Some(eng) = self.engine.as_deref_mut().filter(|_| !poisoned) {
+                        if panic::catch_unwind(AssertUnwindSafe(|| job.run(eng))).is_err() {
+                            self.poisoned.store(true, Ordering::Release);
+                        }
+                        let spent = &mut self.spent;
+                        eng.drain_discarded(|item| Self::retire(spent, Spent::Discarded(item)));
+                    }
+                    Self::retire(&mut self.spent, Spent::Job(job));
+                }
+            }
+        }
+
+        if self.poisoned.load(Ordering::Acquire) {
+            return false;
+        }
+        let Some(eng) = self.engine.as_deref_mut() else { return false };
+        if panic::catch_unwind(AssertUnwindSafe(|| render(&mut *eng))).is_err() {
+            self.poisoned.store(true, Ordering::Release);
+            return false;
+        }
+        self.state.publish(&EngineState::capture(eng));
+        true
+    }
+
+    fn retire(spent: &mut HeapProd<Spent>, item: Spent) {
+        if let Err(item) = spent.try_push(item) {
+            // The control side hasn't collected in a long while; free it here
+            drop(item);
+        }
+    }
+}
+
+enum Home {
+    Parked(Box<Engine>),
+    Live,
+    /// The callback never handed it back; only `replace` brings an engine back.
+    Lost,
+}
+
+// The control side of the current slot
+struct Link {
+    outbox: HeapProd<Message>,
+    spent: HeapCons<Spent>,
+    returned: HeapCons<Box<Engine>>,
+    state: Reader<EngineState>,
+}
+
+struct Control {
+    home: Home,
+    link: Option<Link>,
+}
+
+impl Control {
+    // Free the boxes the callback is done with
+    fn collect(&mut self) {
+        if let Some(link) = self.link.as_mut() {
+            while link.spent.try_pop().is_some() {}
+        }
+    }
+}
+
+/// The control threads' side. One lock serializes them; the callback never takes it.
+pub struct EngineHandle {
+    control: Mutex<Control>,
+    poisoned: Arc<AtomicBool>,
+}
+
+impl EngineHandle {
+    pub fn new(engine: Engine) -> Self {
+        Self {
+            control: Mutex::new(Control { home: Home::Parked(Box::new(engine)), link: None }),
+            poisoned: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn control(&self) -> MutexGuard<'_, Control> {
+        self.control.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// A slot for a new stream's callback. The engine stays parked until `attach`, so a
+    /// stream that fails to start takes nothing with it. Detach from the old slot first.
+    pub fn open_slot(&self) -> EngineSlot {
+        let (outbox, inbox) = HeapRb::<Message>::new(QUEUE_CAPACITY).split();
+        let (spent_tx, spent_rx) = HeapRb::<Spent>::new(QUEUE_CAPACITY).split();
+        let (returned_tx, returned_rx) = HeapRb::<Box<Engine>>::new(1).split();
+        let (state_tx, state_rx) = triple_buffer::triple_buffer(EngineState::default());
+        let mut control = self.control();
+        control.link = Some(Link { outbox, spent: spent_rx, returned: returned_rx, state: state_rx });
+        EngineSlot {
+            engine: None,
+            inbox,
+            spent: spent_tx,
+            returned: returned_tx,
+            state: state_tx,
+            poisoned: self.poisoned.clone(),
+        }
+    }
+
+    /// Hand the parked engine to the slot's callback.
+    pub fn attach(&self) {
+        let mut control = self.control();
+        let Control { home, link } = &mut *control;
+        let (Some(link), Home::Parked(_)) = (link.as_mut(), &*home) else { return };
+        let Home::Parked(engine) = std::mem::replace(home, Home::Live) else { unreachable!() };
+        if let Err(Message::Attach(engine)) = link.outbox.try_push(Message::Attach(engine)) {
+            *home = Home::Parked(engine);
+        }
+    }
+
+    /// Take the engine back from the callback (it plays silence from then on). False when
+    /// the callback didn't answer within `timeout`; the engine is then lost with the stream.
+    pub fn detach(&self, timeout: Duration) -> bool {
+        let mut control = self.control();
+        Self::detach_locked(&mut control, timeout)
+    }
+
+    fn detach_locked(control: &mut Control, timeout: Duration) -> bool {
+        match control.home {
+            Home::Parked(_) => return true,
+            Home::Lost => return false,
+            Home::Live => {}
+        }
+        let Some(link) = control.link.as_mut() else {
+            control.home = Home::Lost;
+            return false;
+        };
+        if link.outbox.try_push(Message::Detach).is_err() {
+            control.home = Home::Lost;
+            return false;
+        }
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(engine) = link.returned.try_pop() {
+                control.home = Home::Parked(engine);
+                control.collect();
+                return true;
+            }
+            if Instant::now() > deadline {
+                control.home = Home::Lost;
+                return false;
+            }
+            std::thread::sleep(REPLY_POLL);
+        }
+    }
+
+    /// Run `edit` on the engine and wait for its result: between two blocks when a stream
+    /// runs, inline otherwise.
+    pub fn with<R, F>(&self, edit: F) -> Result<R, EngineUnavailable>
+    where
+        F: FnOnce(&mut Engine) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        if self.is_poisoned() {
+            return Err(EngineUnavailable::Poisoned);
+        }
+        let mut control = self.control();
+        control.collect();
+        let Control { home, link } = &mut *control;
+        match home {
+            Home::Parked(engine) => self.run_inline(engine, edit),
+            Home::Lost => Err(EngineUnavailable::Lost),
+            Home::Live => {
+                let link = link.as_mut().ok_or(EngineUnavailable::Lost)?;
+                let (reply_tx, mut reply_rx) = HeapRb::<R>::new(1).split();
+                let job: Box<dyn Job> = Box::new(Call { edit: Some(edit), reply: Some(reply_tx) });
+                if link.outbox.try_push(Message::Run(job)).is_err() {
+                    return Err(EngineUnavailable::Timeout);
+                }
+                let deadline = Instant::now() + REPLY_TIMEOUT;
+                loop {
+                    if let Some(result) = reply_rx.try_pop() {
+                        return Ok(result);
+                    }
+                    if self.is_poisoned() {
+                        return Err(EngineUnavailable::Poisoned);
+                    }
+                    if Instant::now() > deadline {
+                        return Err(EngineUnavailable::Timeout);
+                    }
+                    std::thread::sleep(REPLY_POLL);
+                }
+            }
+        }
+    }
+
+    /// Queue `edit` without waiting for it (inline when no stream runs). Fails when the
+    /// queue is full.
+    pub fn post<F>(&self, edit: F) -> Result<(), EngineUnavailable>
+    where
+        F: FnOnce(&mut Engine) + Send + 'static,
+    {
+        if self.is_poisoned() {
+            return Err(EngineUnavailable::Poisoned);
+        }
+        let mut control = self.control();
+        control.collect();
+        let Control { home, link } = &mut *control;
+        match home {
+            Home::Parked(engine) => self.run_inline(engine, edit),
+            Home::Lost => Err(EngineUnavailable::Lost),
+            Home::Live => {
+                let link = link.as_mut().ok_or(EngineUnavailable::Lost)?;
+                let job: Box<dyn Job> = Box::new(Call::<F, ()> { edit: Some(edit), reply: None });
+                link.outbox.try_push(Message::Run(job)).map_err(|_| EngineUnavailable::Timeout)
+            }
+        }
+    }
+
+    /// Run a long edit (loading a project, restoring a snapshot) on this thread with the
+    /// engine taken out of the callback, which plays silence until it is handed back.
+    pub fn with_detached<R>(&self, edit: impl FnOnce(&mut Engine) -> R) -> Result<R, EngineUnavailable> {
+        if self.is_poisoned() {
+            return Err(EngineUnavailable::Poisoned);
+        }
+        let mut control = self.control();
+        let was_live = matches!(control.home, Home::Live);
+        if !Self::detach_locked(&mut control, REPLY_TIMEOUT) {
+            return Err(EngineUnavailable::Lost);
+        }
+        let Home::Parked(engine) = &mut control.home else { return Err(EngineUnavailable::Lost) };
+        let result = self.run_inline(engine, edit);
+        drop(control);
+        if was_live {
+            self.attach();
+        }
+        result
+    }
+
+    fn run_inline<R>(&self, engine: &mut Engine, edit: impl FnOnce(&mut Engine) -> R) -> Result<R, EngineUnavailable> {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| edit(engine)));
+        engine.drain_discarded(drop);
+        match result {
+            Ok(result) => Ok(result),
+            Err(_) => {
+                self.poisoned.store(true, Ordering::Release);
+                Err(EngineUnavailable::Poisoned)
+            }
+        }
+    }
+
+    /// Read the engine even after a panic poisoned it (taking it out of the callback first).
+    /// `None` when it can't be got back or the read panics too.
+    pub fn salvage<R>(&self, read: impl FnOnce(&Engine) -> R) -> Option<R> {
+        let mut control = self.control();
+        if !Self::detach_locked(&mut control, REPLY_TIMEOUT) {
+            return None;
+        }
+        let Home::Parked(engine) = &control.home else { return None };
+        panic::catch_unwind(AssertUnwindSafe(|| read(engine))).ok()
+    }
+
+    /// Swap in a fresh, parked engine and clear the poison; returns the old one if it was
+    /// at home. Detach and stop the old stream first.
+    pub fn replace(&self, engine: Engine) -> Option<Engine> {
+        let mut control = self.control();
+        control.link = None;
+        let old = std::mem::replace(&mut control.home, Home::Parked(Box::new(engine)));
+        self.poisoned.store(false, Ordering::Release);
+        match old {
+            Home::Parked(engine) => Some(*engine),
+            Home::Live | Home::Lost => None,
+        }
+    }
+
+    /// An edit or a render panicked. Edits are refused until `replace`.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Acquire)
+    }
+
+    /// Whether the engine is in a running callback (false while parked or lost).
+    pub fn is_live(&self) -> bool {
+        matches!(self.control().home, Home::Live)
+    }
+
+    /// The callback never handed the engine back; it needs a `replace`.
+    pub fn is_lost(&self) -> bool {
+        matches!(self.control().home, Home::Lost)
+    }
+
+    /// The transport as of the last block (or of the parked engine right now).
+    pub fn state(&self) -> EngineState {
+        let mut control = self.control();
+        let Control { home, link } = &mut *control;
+        match (home, link.as_mut()) {
+            (Home::Parked(engine), _) => EngineState::capture(engine),
+            (_, Some(link)) => link.state.read(),
+            (_, None) => EngineState::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stands in for the audio callback: renders blocks until told to stop
+    fn spawn_callback(mut slot: EngineSlot, stop: Arc<AtomicBool>) -> std::thread::JoinHandle<EngineSlot> {
+        std::thread::spawn(move || {
+            let mut out = vec![0.0f32; 256];
+            let live = vec![0.0f32; 256];
+            while !stop.load(Ordering::Relaxed) {
+                slot.process(|eng| eng.render(&mut out, &live));
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            slot
+        })
+    }
+
+    #[test]
+    fn edits_run_inline_while_parked() {
+        let handle = EngineHandle::new(Engine::new(48_000, 2));
+        handle.with(|eng| eng.add_empty_track()).unwrap();
+        assert_eq!(handle.with(|eng| eng.tracks().len()).unwrap(), 1);
+        assert_eq!(handle.state().sample_rate, 48_000);
+    }
+
+    #[test]
+    fn edits_reach_the_engine_inside_the_callback() {
+        let handle = EngineHandle::new(Engine::new(48_000, 2));
+        let stop = Arc::new(AtomicBool::new(false));
+        let callback = spawn_callback(handle.open_slot(), stop.clone());
+        handle.attach();
+        assert!(handle.is_live());
+
+        handle.post(|eng| eng.master_gain = 0.5).unwrap();
+        handle.with(|eng| eng.add_empty_track()).unwrap();
+        assert_eq!(handle.with(|eng| (eng.tracks().len(), eng.master_gain)).unwrap(), (1, 0.5));
+
+        // Handed back intact, and edits run inline again
+        assert!(handle.detach(REPLY_TIMEOUT));
+        assert!(!handle.is_live());
+        assert_eq!(handle.with(|eng| eng.tracks().len()).unwrap(), 1);
+        stop.store(true, Ordering::Relaxed);
+        callback.join().unwrap();
+    }
+
+    #[test]
+    fn state_is_published_after_each_block() {
+        let handle = EngineHandle::new(Engine::new(44_100, 2));
+        let stop = Arc::new(AtomicBool::new(false));
+        let callback = spawn_callback(handle.open_slot(), stop.clone());
+        handle.attach();
+        handle.with(|eng| eng.play()).unwrap();
+        // The reply comes before the block is rendered; wait for one more
+        handle.with(|_| ()).unwrap();
+        let state = handle.state();
+        assert!(state.playing);
+        assert_eq!(state.sample_rate, 44_100);
+        stop.store(true, Ordering::Relaxed);
+        callback.join().unwrap();
+    }
+
+    #[test]
+    fn a_panicking_edit_poisons_until_replaced() {
+        let handle = EngineHandle::new(Engine::new(48_000, 2));
+        let stop = Arc::new(AtomicBool::new(false));
+        let callback = spawn_callback(handle.open_slot(), stop.clone());
+        handle.attach();
+        handle.with(|eng| eng.add_empty_track()).unwrap();
+
+        assert_eq!(handle.with(|_| panic!("edit failed")), Err::<(), _>(EngineUnavailable::Poisoned));
+        assert!(handle.is_poisoned());
+        assert_eq!(handle.with(|_| ()), Err(EngineUnavailable::Poisoned));
+        // Still readable for a recovery snapshot
+        assert_eq!(handle.salvage(|eng| eng.tracks().len()), Some(1));
+
+        stop.store(true, Ordering::Relaxed);
+        callback.join().unwrap();
+        handle.replace(Engine::new(48_000, 2));
+        assert!(!handle.is_poisoned());
+        assert_eq!(handle.with(|eng| eng.tracks().len()).unwrap(), 0);
+    }
+
+    #[test]
+    fn detach_gives_up_on_a_callback_that_never_runs() {
+        let handle = EngineHandle::new(Engine::new(48_000, 2));
+        let _slot = handle.open_slot();
+        handle.attach();
+        assert!(!handle.detach(Duration::from_millis(20)));
+        assert_eq!(handle.with(|_| ()), Err(EngineUnavailable::Lost));
+    }
+
+    #[test]
+    fn removed_tracks_are_freed_on_the_control_side() {
+        let handle = EngineHandle::new(Engine::new(48_000, 2));
+        let stop = Arc::new(AtomicBool::new(false));
+        let callback = spawn_callback(handle.open_slot(), stop.clone());
+        handle.attach();
+        let id = handle.with(|eng| eng.add_empty_track()).unwrap();
+        let meters = handle.with(|eng| Arc::downgrade(&eng.tracks()[0].meters)).unwrap();
+
+        handle.with(move |eng| eng.remove_track(id)).unwrap().unwrap();
+        // Not dropped inside the callback: it waits for the control side to collect it
+        assert!(meters.upgrade().is_some());
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        while meters.upgrade().is_some() && Instant::now() < deadline {
+            handle.with(|_| ()).unwrap();
+        }
+        assert!(meters.upgrade().is_none());
+
+        stop.store(true, Ordering::Relaxed);
+        callback.join().unwrap();
+    }
+}
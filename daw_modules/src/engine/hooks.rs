@@ -0,0 +1,81 @@
+// src/engine/hooks.rs
+
+// Per-block hook for host integrations (custom IPC, hardware LEDs, ...).
+//
+// REALTIME CONTRACT: a `BlockCallback` runs on the audio thread at the end of
+// every `Engine::render`. It must not allocate, lock, block, do I/O or log through
+// std streams. Anything slower than a few microseconds belongs behind `relay_channel`.
+
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::time::Duration;
+
+/// Max tracks carried by a relayed `BlockSnapshot` (fixed so relaying never allocates).
+pub const MAX_RELAY_TRACKS: usize = 64;
+
+/// Post-fader peak of one track for the block just rendered (linear).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TrackPeak {
+    pub track_id: u32,
+    pub peak_l: f32,
+    pub peak_r: f32,
+}
+
+impl TrackPeak {
+    /// Absolute peak of each side of one interleaved block (mono reads the same on both).
+    pub fn of_block(track_id: u32, block: &[f32], channels: usize) -> Self {
+        let mut peak = TrackPeak { track_id, peak_l: 0.0, peak_r: 0.0 };
+        for frame in block.chunks_exact(channels.max(1)) {
+            peak.peak_l = peak.peak_l.max(frame[0].abs());
+            peak.peak_r = peak.peak_r.max(frame.get(1).unwrap_or(&frame[0]).abs());
+        }
+        peak
+    }
+}
+
+/// What the engine just rendered. Borrowed, valid only for the duration of the call.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockInfo<'a> {
+    /// Transport position at the first frame of the block.
+    pub start: Duration,
+    pub frames: usize,
+    pub playing: bool,
+    pub track_peaks: &'a [TrackPeak],
+}
+
+/// Realtime hook. See the contract at the top of this file.
+pub type BlockCallback = Box<dyn FnMut(BlockInfo<'_>) + Send>;
+
+/// Owned copy of a `BlockInfo`, for consumers off the audio thread.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockSnapshot {
+    pub start: Duration,
+    pub frames: usize,
+    pub playing: bool,
+    track_count: usize,
+    peaks: [TrackPeak; MAX_RELAY_TRACKS],
+}
+
+impl BlockSnapshot {
+    /// Peaks of the first `MAX_RELAY_TRACKS` tracks.
+    pub fn track_peaks(&self) -> &[TrackPeak] {
+        &self.peaks[..self.track_count]
+    }
+}
+
+/// Non-realtime variant: returns a callback to install on the engine and the receiving end.
+/// Blocks are dropped (never waited on) when the consumer falls `capacity` blocks behind.
+pub fn relay_channel(capacity: usize) -> (BlockCallback, Receiver<BlockSnapshot>) {
+    let (tx, rx): (SyncSender<BlockSnapshot>, _) = mpsc::sync_channel(capacity.max(1));
+    let callback: BlockCallback = Box::new(move |info: BlockInfo<'_>| {
+        let mut snapshot = BlockSnapshot {
+            start: info.start,
+            frames: info.frames,
+            playing: info.playing,
+            track_count: info.track_peaks.len().min(MAX_RELAY_TRACKS),
+            peaks: [TrackPeak::default(); MAX_RELAY_TRACKS],
+        };
+        snapshot.peaks[..snapshot.track_count].copy_from_slice(&info.track_peaks[..snapshot.track_count]);
+        let _ = tx.try_send(snapshot);
+    });
+    (callback, rx)
+}
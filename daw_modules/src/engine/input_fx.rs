@@ -0,0 +1,51 @@
+// src/engine/input_fx.rs
+
+// Effects on the monitored input. While a track is armed, the live input runs through
+// copies of that track's EQ and compressor, so whoever is tracking hears the processed
+// sound. The copies follow the track's settings every block but keep their own filter
+// state: playback on the same track is untouched. Compressor lookahead is left out here,
+// since a monitored signal can't be delay compensated and any delay is heard against the
+// performance; the recorded take stays dry either way.
+
+use crate::effects::compressor::{CompressorNode, CompressorParams};
+use crate::effects::equalizer::TrackEq;
+
+use super::track::{Track, TrackId};
+
+pub struct InputFx {
+    eq: TrackEq,
+    compressor: CompressorNode,
+    source: Option<TrackId>, // track the copies last followed
+}
+
+impl InputFx {
+    /// Allocates the filters and the compressor's delay line.
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        Self {
+            eq: TrackEq::new(sample_rate, channels),
+            compressor: CompressorNode::new(sample_rate as f32, channels),
+            source: None,
+        }
+    }
+
+    /// Process `live` (interleaved, `channels` wide) through `track`'s EQ and compressor;
+    /// passes it through untouched without a track or with the track's effects bypassed.
+    /// Never allocates.
+    pub fn process(&mut self, live: &mut [f32], channels: usize, track: Option<&Track>) {
+        let Some(track) = track.filter(|t| !t.fx_bypass) else {
+            self.source = None;
+            return;
+        };
+        if self.source != Some(track.id) {
+            // A different track: don't carry the last one's filter state over
+            self.eq.reset();
+            self.compressor.reset();
+            self.source = Some(track.id);
+        }
+        self.eq.follow(&track.track_eq);
+        self.compressor.set_params(CompressorParams { lookahead_ms: 0.0, ..track.track_compressor.get_params() });
+
+        self.eq.process_buffer(live, channels);
+        self.compressor.process(live);
+    }
+}
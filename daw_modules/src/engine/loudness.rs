@@ -0,0 +1,308 @@
+// src/engine/loudness.rs
+
+// EBU R128 / ITU-R BS.1770 loudness of the master bus. The audio thread K-weights each
+// block, sums it into 100 ms slices and derives momentary (400 ms), short-term (3 s) and
+// gated integrated loudness, plus a 4x oversampled true peak. Integrated loudness keeps
+// its gating blocks in a fixed histogram (0.1 LU bins), so it runs for hours without
+// allocating. Readings are published through atomics, like the level meters.
+
+use serde::Serialize;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Reported for silence and before enough audio has been measured.
+pub const LOUDNESS_FLOOR: f32 = -70.0;
+
+const SLICE_MS: f64 = 100.0;
+const MOMENTARY_SLICES: usize = 4; // 400 ms
+const SHORT_TERM_SLICES: usize = 30; // 3 s
+const RELATIVE_GATE_LU: f64 = 10.0;
+
+// Histogram of gating blocks from the absolute gate up to +10 LUFS
+const HIST_MIN: f64 = -70.0;
+const HIST_STEP: f64 = 0.1;
+const HIST_BINS: usize = 800;
+
+// True-peak interpolator: 4 phases of a 48-tap windowed sinc
+const OVERSAMPLE: usize = 4;
+const TAPS_PER_PHASE: usize = 12;
+
+/// Loudness as the UI reads it. LUFS and dBTP, `LOUDNESS_FLOOR` when there is nothing to show.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LoudnessReading {
+    pub momentary: f32,
+    pub short_term: f32,
+    pub integrated: f32,
+    pub true_peak_db: f32,
+}
+
+/// The lock-free bridge: the audio thread writes, the UI reads and asks for resets.
+pub struct LoudnessReadout {
+    momentary: AtomicU32,
+    short_term: AtomicU32,
+    integrated: AtomicU32,
+    true_peak_db: AtomicU32,
+    reset: AtomicBool,
+}
+
+impl LoudnessReadout {
+    pub fn new() -> Arc<Self> {
+        let floor = LOUDNESS_FLOOR.to_bits();
+        Arc::new(Self {
+            momentary: AtomicU32::new(floor),
+            short_term: AtomicU32::new(floor),
+            integrated: AtomicU32::new(floor),
+            true_peak_db: AtomicU32::new(floor),
+            reset: AtomicBool::new(false),
+        })
+    }
+
+    pub fn reading(&self) -> LoudnessReading {
+        let load = |a: &AtomicU32| f32::from_bits(a.load(Ordering::Relaxed));
+        LoudnessReading {
+            momentary: load(&self.momentary),
+            short_term: load(&self.short_term),
+            integrated: load(&self.integrated),
+            true_peak_db: load(&self.true_peak_db),
+        }
+    }
+
+    /// Start integrated loudness and true peak over; the audio thread picks it up next block.
+    pub fn request_reset(&self) {
+        self.reset.store(true, Ordering::Relaxed);
+    }
+
+    fn publish(&self, reading: LoudnessReading) {
+        self.momentary.store(reading.momentary.to_bits(), Ordering::Relaxed);
+        self.short_term.store(reading.short_term.to_bits(), Ordering::Relaxed);
+        self.integrated.store(reading.integrated.to_bits(), Ordering::Relaxed);
+        self.true_peak_db.store(reading.true_peak_db.to_bits(), Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+// The two BS.1770 K-weighting stages (head shelf, then RLB high-pass) at any rate
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let fs = sample_rate.max(1) as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    [shelf, high_pass]
+}
+
+fn energy_to_lufs(energy: f64) -> f64 {
+    if energy <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    -0.691 + 10.0 * energy.log10()
+}
+
+fn floored(lufs: f64) -> f32 {
+    (lufs as f32).max(LOUDNESS_FLOOR)
+}
+
+/// Owned by the audio thread. Allocates only in `new`.
+pub struct LoudnessMeter {
+    channels: usize,
+    filters: Vec<[Biquad; 2]>,
+    slice_frames: usize,
+    slice_pos: usize,
+    slice_energy: f64,                  // K-weighted sum of squares over channels, current slice
+    slices: [f64; SHORT_TERM_SLICES],   // mean square of the last slices, ring
+    slice_index: usize,
+    slices_filled: usize,
+    hist_count: Vec<u64>,   // gating blocks per 0.1 LU bin
+    hist_energy: Vec<f64>,  // their summed energy
+    phases: Vec<[f32; TAPS_PER_PHASE]>,
+    history: Vec<[f32; TAPS_PER_PHASE]>, // last input samples per channel, newest last
+    true_peak: f32,
+    reading: LoudnessReading,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+
+        // Windowed sinc at the original Nyquist, split into polyphase branches
+        let len = OVERSAMPLE * TAPS_PER_PHASE;
+        let center = (len - 1) as f64 / 2.0;
+        let mut phases = vec![[0.0f32; TAPS_PER_PHASE]; OVERSAMPLE];
+        for n in 0..len {
+            let t = (n as f64 - center) / OVERSAMPLE as f64;
+            let sinc = if t == 0.0 { 1.0 } else { (PI * t).sin() / (PI * t) };
+            let window = 0.5 - 0.5 * (2.0 * PI * (n as f64 + 0.5) / len as f64).cos();
+            phases[n % OVERSAMPLE][n / OVERSAMPLE] = (sinc * window) as f32;
+        }
+
+        Self {
+            channels,
+            filters: vec![k_weighting(sample_rate); channels],
+            slice_frames: ((sample_rate as f64 * SLICE_MS / 1000.0) as usize).max(1),
+            slice_pos: 0,
+            slice_energy: 0.0,
+            slices: [0.0; SHORT_TERM_SLICES],
+            slice_index: 0,
+            slices_filled: 0,
+            hist_count: vec![0; HIST_BINS],
+            hist_energy: vec![0.0; HIST_BINS],
+            phases,
+            history: vec![[0.0; TAPS_PER_PHASE]; channels],
+            true_peak: 0.0,
+            reading: LoudnessReading {
+                momentary: LOUDNESS_FLOOR,
+                short_term: LOUDNESS_FLOOR,
+                integrated: LOUDNESS_FLOOR,
+                true_peak_db: LOUDNESS_FLOOR,
+            },
+        }
+    }
+
+    fn reset(&mut self) {
+        for filters in &mut self.filters {
+            filters.iter_mut().for_each(|f| f.z = [0.0; 2]);
+        }
+        self.slice_pos = 0;
+        self.slice_energy = 0.0;
+        self.slices = [0.0; SHORT_TERM_SLICES];
+        self.slice_index = 0;
+        self.slices_filled = 0;
+        self.hist_count.fill(0);
+        self.hist_energy.fill(0.0);
+        self.history.iter_mut().for_each(|h| *h = [0.0; TAPS_PER_PHASE]);
+        self.true_peak = 0.0;
+        self.reading = LoudnessReading {
+            momentary: LOUDNESS_FLOOR,
+            short_term: LOUDNESS_FLOOR,
+            integrated: LOUDNESS_FLOOR,
+            true_peak_db: LOUDNESS_FLOOR,
+        };
+    }
+
+    /// Measure an interleaved master block and publish to `readout`.
+    pub fn process_block(&mut self, buffer: &[f32], readout: &LoudnessReadout) {
+        if readout.reset.swap(false, Ordering::Relaxed) {
+            self.reset();
+        }
+        self.measure(buffer);
+        readout.publish(self.reading);
+    }
+
+    /// Loudness of everything measured so far.
+    pub fn reading(&self) -> LoudnessReading {
+        self.reading
+    }
+
+    /// Measure an interleaved block without publishing it (e.g. off the audio thread).
+    pub fn measure(&mut self, buffer: &[f32]) {
+        let channels = self.channels;
+
+        for frame in buffer.chunks_exact(channels) {
+            for (c, &sample) in frame.iter().enumerate() {
+                let [shelf, high_pass] = &mut self.filters[c];
+                let weighted = high_pass.process(shelf.process(sample as f64));
+                self.slice_energy += weighted * weighted;
+
+                let history = &mut self.history[c];
+                history.copy_within(1.., 0);
+                history[TAPS_PER_PHASE - 1] = sample;
+                for phase in &self.phases {
+                    let y: f32 = phase.iter().rev().zip(history.iter()).map(|(h, x)| h * x).sum();
+                    self.true_peak = self.true_peak.max(y.abs());
+                }
+            }
+
+            self.slice_pos += 1;
+            if self.slice_pos >= self.slice_frames {
+                self.finish_slice();
+            }
+        }
+
+        self.reading.true_peak_db = if self.true_peak > 0.0 {
+            (20.0 * self.true_peak.log10()).max(LOUDNESS_FLOOR)
+        } else {
+            LOUDNESS_FLOOR
+        };
+    }
+
+    fn finish_slice(&mut self) {
+        self.slices[self.slice_index] = self.slice_energy / self.slice_frames as f64;
+        self.slice_index = (self.slice_index + 1) % SHORT_TERM_SLICES;
+        self.slices_filled = (self.slices_filled + 1).min(SHORT_TERM_SLICES);
+        self.slice_pos = 0;
+        self.slice_energy = 0.0;
+
+        if self.slices_filled < MOMENTARY_SLICES {
+            return;
+        }
+        let momentary = self.mean_of_last(MOMENTARY_SLICES);
+        self.reading.momentary = floored(energy_to_lufs(momentary));
+        self.reading.short_term = floored(energy_to_lufs(self.mean_of_last(self.slices_filled)));
+
+        // Every 400 ms window, stepped by 100 ms (75% overlap), is a gating block
+        let lufs = energy_to_lufs(momentary);
+        if lufs > HIST_MIN {
+            let bin = (((lufs - HIST_MIN) / HIST_STEP) as usize).min(HIST_BINS - 1);
+            self.hist_count[bin] += 1;
+            self.hist_energy[bin] += momentary;
+            self.reading.integrated = self.integrated();
+        }
+    }
+
+    fn mean_of_last(&self, count: usize) -> f64 {
+        let sum: f64 = (1..=count)
+            .map(|back| self.slices[(self.slice_index + SHORT_TERM_SLICES - back) % SHORT_TERM_SLICES])
+            .sum();
+        sum / count as f64
+    }
+
+    // Absolute gate (-70 LUFS) is the histogram floor; the relative gate sits 10 LU below
+    // the loudness of everything above it
+    fn integrated(&self) -> f32 {
+        let mean_from = |first: usize| {
+            let count: u64 = self.hist_count[first..].iter().sum();
+            let energy: f64 = self.hist_energy[first..].iter().sum();
+            (count > 0).then(|| energy / count as f64)
+        };
+        let Some(ungated) = mean_from(0) else {
+            return LOUDNESS_FLOOR;
+        };
+        let relative_gate = energy_to_lufs(ungated) - RELATIVE_GATE_LU;
+        let first = (((relative_gate - HIST_MIN) / HIST_STEP).ceil().max(0.0) as usize).min(HIST_BINS - 1);
+        mean_from(first).map_or(LOUDNESS_FLOOR, |gated| floored(energy_to_lufs(gated)))
+    }
+}
@@ -0,0 +1,105 @@
+// src/engine/markers.rs
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// Color for markers added without one (a UI palette class, like track colors).
+pub const DEFAULT_MARKER_COLOR: &str = "bg-orange-500";
+
+/// A named point on the timeline. `id` stays put while the marker is moved or renamed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marker {
+    pub id: u32,
+    pub time: Duration,
+    pub name: String,
+    pub color: String,
+}
+
+/// A marker as the timeline ruler sees it.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkerInfo {
+    pub id: u32,
+    pub time: f64,
+    pub name: String,
+    pub color: String,
+}
+
+impl From<&Marker> for MarkerInfo {
+    fn from(m: &Marker) -> Self {
+        Self { id: m.id, time: m.time.as_secs_f64(), name: m.name.clone(), color: m.color.clone() }
+    }
+}
+
+/// Markers kept sorted by time, so navigation is a binary search.
+#[derive(Clone, Debug, Default)]
+pub struct Markers {
+    items: Vec<Marker>,
+    next_id: u32,
+}
+
+impl Markers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a marker with a fresh id and returns the id.
+    pub fn add(&mut self, time: Duration, name: String, color: String) -> u32 {
+        let id = self.reserve_id();
+        self.insert(Marker { id, time, name, color });
+        id
+    }
+
+    /// A fresh id for a marker that goes in later through `insert` (an undoable add).
+    pub fn reserve_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.saturating_add(1);
+        id
+    }
+
+    /// Puts back a marker with its own id (undo, restore), replacing any with that id.
+    pub fn insert(&mut self, marker: Marker) {
+        self.remove(marker.id);
+        self.next_id = self.next_id.max(marker.id.saturating_add(1));
+        let idx = self.items.partition_point(|m| m.time <= marker.time);
+        self.items.insert(idx, marker);
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<Marker> {
+        let index = self.items.iter().position(|m| m.id == id)?;
+        Some(self.items.remove(index))
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Marker> {
+        self.items.iter().find(|m| m.id == id)
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Marker> {
+        self.items.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// First marker strictly after `pos` (+ `tolerance`).
+    pub fn next_after(&self, pos: Duration, tolerance: Duration) -> Option<Duration> {
+        let i = self.items.partition_point(|m| m.time <= pos + tolerance);
+        self.items.get(i).map(|m| m.time)
+    }
+
+    /// Last marker strictly before `pos` (- `tolerance`).
+    pub fn previous_before(&self, pos: Duration, tolerance: Duration) -> Option<Duration> {
+        let limit = pos.saturating_sub(tolerance);
+        let i = self.items.partition_point(|m| m.time < limit);
+        i.checked_sub(1).map(|i| self.items[i].time)
+    }
+}
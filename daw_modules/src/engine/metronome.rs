@@ -0,0 +1,216 @@
+// src/engine/metronome.rs
+
+// Metronome click: a short decaying sine on every musical beat of the `TempoMap` (quarter
+// notes in x/4, eighths in x/8), pitched up on the first beat of the bar. Beats come from
+// the tempo map as the block renders, so tempo and meter changes apply at once. Where it
+// goes (master, cue, both) and when it sounds (always while playing, only while
+// recording, only during the count-in) are resolved into plain flags when the config
+// changes, so the render path only tests booleans.
+
+use serde::{Deserialize, Serialize};
+
+/// Click length; the envelope has decayed below -60 dB by then.
+const CLICK_SECS: f64 = 0.04;
+/// Envelope time constant.
+const CLICK_DECAY_SECS: f64 = 0.006;
+const ACCENT_HZ: f64 = 1500.0;
+const BEAT_HZ: f64 = 1000.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClickDestination {
+    #[default]
+    Master,
+    /// Only the cue (headphone) output. Without a routed cue bus the cue is the master.
+    Cue,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClickMode {
+    /// Whenever the transport plays (including the count-in).
+    #[default]
+    Always,
+    /// While recording, and during a count-in (which only happens before a take).
+    RecordingOnly,
+    /// During the count-in only; silent once the take starts.
+    CountInOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MetronomeConfig {
+    pub enabled: bool,
+    /// Overall click level, on top of the accent/beat gains.
+    pub volume_db: f32, // -60.0 to 6.0
+    pub destination: ClickDestination,
+    pub mode: ClickMode,
+    pub accent_gain_db: f32, // -60.0 to 6.0
+    pub beat_gain_db: f32,   // -60.0 to 6.0
+    /// Bars of click before the transport starts, for `play_with_count_in`.
+    pub count_in_bars: u32, // 0 to 4
+}
+
+impl Default for MetronomeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            volume_db: 0.0,
+            destination: ClickDestination::Master,
+            mode: ClickMode::Always,
+            accent_gain_db: -6.0,
+            beat_gain_db: -12.0,
+            count_in_bars: 1,
+        }
+    }
+}
+
+impl MetronomeConfig {
+    /// Ranges enforced; non-finite gains fall back to the defaults.
+    pub fn sanitized(self) -> Self {
+        let defaults = Self::default();
+        let db = |v: f32, fallback: f32| if v.is_finite() { v.clamp(-60.0, 6.0) } else { fallback };
+        Self {
+            volume_db: db(self.volume_db, defaults.volume_db),
+            accent_gain_db: db(self.accent_gain_db, defaults.accent_gain_db),
+            beat_gain_db: db(self.beat_gain_db, defaults.beat_gain_db),
+            count_in_bars: self.count_in_bars.min(4),
+            ..self
+        }
+    }
+}
+
+pub struct Metronome {
+    config: MetronomeConfig,
+    // Resolved from the config in `set_config`
+    to_master: bool,
+    to_cue: bool,
+    when_playing: bool,
+    when_recording: bool,
+    when_counting_in: bool,
+    accent: Vec<f32>, // click shapes at unit gain
+    beat: Vec<f32>,
+    accent_gain: f32,
+    beat_gain: f32,
+    // Click still sounding from the previous block: (is accent, next sample)
+    ringing: Option<(bool, usize)>,
+}
+
+impl Metronome {
+    pub fn new(sample_rate: u32) -> Self {
+        let mut metronome = Self {
+            config: MetronomeConfig::default(),
+            to_master: false,
+            to_cue: false,
+            when_playing: false,
+            when_recording: false,
+            when_counting_in: false,
+            accent: click_shape(ACCENT_HZ, sample_rate),
+            beat: click_shape(BEAT_HZ, sample_rate),
+            accent_gain: 0.0,
+            beat_gain: 0.0,
+            ringing: None,
+        };
+        metronome.set_config(MetronomeConfig::default());
+        metronome
+    }
+
+    pub fn config(&self) -> MetronomeConfig {
+        self.config
+    }
+
+    /// Never allocates. Returns the config as applied (sanitized).
+    pub fn set_config(&mut self, config: MetronomeConfig) -> MetronomeConfig {
+        let config = config.sanitized();
+        let on = config.enabled;
+        self.to_master = on && matches!(config.destination, ClickDestination::Master | ClickDestination::Both);
+        self.to_cue = on && matches!(config.destination, ClickDestination::Cue | ClickDestination::Both);
+        self.when_playing = on && config.mode == ClickMode::Always;
+        self.when_recording = on && matches!(config.mode, ClickMode::Always | ClickMode::RecordingOnly);
+        self.when_counting_in = on;
+        self.accent_gain = 10.0_f32.powf((config.volume_db + config.accent_gain_db) / 20.0);
+        self.beat_gain = 10.0_f32.powf((config.volume_db + config.beat_gain_db) / 20.0);
+        self.config = config;
+        config
+    }
+
+    /// Whether the click sounds in this state of the transport.
+    #[inline]
+    pub fn is_active(&self, recording: bool, counting_in: bool) -> bool {
+        if counting_in {
+            self.when_counting_in
+        } else if recording {
+            self.when_recording
+        } else {
+            self.when_playing
+        }
+    }
+
+    /// Where the click goes, as (master, cue). With `cue_routed` false a cue-only click
+    /// lands on the master, since that is what the performer hears.
+    #[inline]
+    pub fn targets(&self, cue_routed: bool) -> (bool, bool) {
+        if cue_routed { (self.to_master, self.to_cue) } else { (self.to_master || self.to_cue, false) }
+    }
+
+    /// Forget a click that was sounding (after a seek or stop).
+    pub fn reset(&mut self) {
+        self.ringing = None;
+    }
+
+    /// Add this block's clicks to `master` and/or `cue`. `first_frame` is the beat clock
+    /// at the block start (the song position, or the frames elapsed in the count-in) and
+    /// `next_beat` gives the first beat at or after a frame, and whether it starts a bar.
+    pub fn render(
+        &mut self,
+        master: &mut [f32],
+        cue: &mut [f32],
+        to_master: bool,
+        to_cue: bool,
+        channels: usize,
+        first_frame: u64,
+        mut next_beat: impl FnMut(u64) -> (u64, bool),
+    ) {
+        let channels = channels.max(1);
+        let frames = master.len() / channels;
+        if !(to_master || to_cue) {
+            return;
+        }
+
+        let mut next = next_beat(first_frame);
+        for f in 0..frames {
+            let pos = first_frame + f as u64;
+            if pos >= next.0 {
+                self.ringing = Some((next.1, 0));
+                next = next_beat(pos + 1);
+            }
+            let Some((accent, i)) = self.ringing else { continue };
+            let (shape, gain) = if accent { (&self.accent, self.accent_gain) } else { (&self.beat, self.beat_gain) };
+            let Some(&s) = shape.get(i) else {
+                self.ringing = None;
+                continue;
+            };
+            let s = s * gain;
+            let at = f * channels;
+            if to_master {
+                master[at..at + channels].iter_mut().for_each(|x| *x += s);
+            }
+            if to_cue && cue.len() >= at + channels {
+                cue[at..at + channels].iter_mut().for_each(|x| *x += s);
+            }
+            self.ringing = Some((accent, i + 1));
+        }
+    }
+}
+
+fn click_shape(freq: f64, sample_rate: u32) -> Vec<f32> {
+    let sr = sample_rate.max(1) as f64;
+    let len = (CLICK_SECS * sr) as usize;
+    (0..len)
+        .map(|n| {
+            let t = n as f64 / sr;
+            ((2.0 * std::f64::consts::PI * freq * t).sin() * (-t / CLICK_DECAY_SECS).exp()) as f32
+        })
+        .collect()
+}
@@ -1,13 +1,299 @@
 // src/engine/mixer.rs
 
+// Track summing, plus aux send/return buses: every track can feed any bus through a
+// post-fader send level, each bus runs its own effect chain (reverb, delay) and its
+// return is summed into the mix ahead of the master processing.
+//
+// Tracks can also belong to a group: their output is summed into the group's buffer
+// instead of the mix, and the group's fader scales the submix on its way to the master.
+// A group's mute and solo act as if set on every member (see `Engine::track_mute_solo`).
+//
+// Bus returns get the same plus-delay compensation as tracks: every return waits for the
+// most latent bus chain, and the dry mix is held back by that much before they're summed.
+
+use serde::{Deserialize, Serialize};
+use super::pdc::{PdcDelay, MAX_PDC_FRAMES};
+use super::simd;
+use super::smoothing::{self, Smoothed};
 use super::track::Track;
-use std::time::Duration;
+use crate::effects::delay::{DelayNode, DelayParams};
+use crate::effects::reverb::{ReverbNode, ReverbParams};
+use crate::effects::Effect;
+use super::time::Frames;
+
+/// Most aux buses a project can have.
+pub const MAX_AUX_BUSES: usize = 8;
+
+/// Most track groups a project can have.
+pub const MAX_GROUPS: usize = 16;
+
+/// One insert of a bus's effect chain, as saved and as the UI edits it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(tag = "kind", content = "params", rename_all = "camelCase")]
+pub enum BusEffectParams {
+    Reverb(ReverbParams),
+    Delay(DelayParams),
+}
+
+enum BusEffect {
+    Reverb(ReverbNode),
+    Delay(DelayNode),
+}
+
+impl BusEffect {
+    fn new(params: BusEffectParams, channels: usize, sample_rate: u32) -> Self {
+        match params {
+            BusEffectParams::Reverb(p) => {
+                let node = ReverbNode::new(sample_rate as f32);
+                node.set_params(p);
+                BusEffect::Reverb(node)
+            }
+            BusEffectParams::Delay(p) => BusEffect::Delay(DelayNode::new(p, channels, sample_rate)),
+        }
+    }
+
+    fn params(&self) -> BusEffectParams {
+        match self {
+            BusEffect::Reverb(node) => BusEffectParams::Reverb(node.get_params()),
+            BusEffect::Delay(node) => BusEffectParams::Delay(node.get_params()),
+        }
+    }
+
+    /// False when `params` belong to another kind of effect.
+    fn set_params(&mut self, params: BusEffectParams) -> bool {
+        match (self, params) {
+            (BusEffect::Reverb(node), BusEffectParams::Reverb(p)) => node.set_params(p),
+            (BusEffect::Delay(node), BusEffectParams::Delay(p)) => node.set_params(p),
+            _ => return false,
+        }
+        true
+    }
+
+    fn process_block(&mut self, buffer: &mut [f32], channels: usize) {
+        match self {
+            BusEffect::Reverb(node) => node.process_block(buffer, channels),
+            BusEffect::Delay(node) => node.process_block(buffer, channels),
+        }
+    }
+
+    fn latency_frames(&self) -> usize {
+        match self {
+            BusEffect::Reverb(node) => node.latency_frames(),
+            BusEffect::Delay(node) => node.latency_frames(),
+        }
+    }
+}
+
+/// A track's post-fader feed into an aux bus.
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct AuxSend {
+    pub bus_id: u32,
+    pub level: f32, // linear, 0.0 to 2.0 like the fader
+    #[serde(skip)]
+    smooth: Smoothed, // level as heard, ramping toward `level`
+}
+
+impl AuxSend {
+    pub fn new(bus_id: u32, level: f32) -> Self {
+        Self { bus_id, level, smooth: Smoothed::new(level) }
+    }
+}
+
+/// A bus as the mixer UI sees it.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AuxBusInfo {
+    pub id: u32,
+    pub name: String,
+    pub gain: f32,
+    pub muted: bool,
+    pub effects: Vec<BusEffectParams>,
+}
+
+pub struct AuxBus {
+    pub id: u32,
+    pub name: String,
+    pub gain: f32,
+    pub muted: bool,
+    effects: Vec<BusEffect>,
+    channels: usize,
+    sample_rate: u32,
+    buffer: Vec<f32>, // sends summed for the current block
+    gain_smooth: Smoothed,
+    pdc: PdcDelay, // compensation delay (most latent bus chain - own latency)
+}
+
+impl AuxBus {
+    pub fn new(id: u32, name: String, channels: usize, sample_rate: u32) -> Self {
+        Self {
+            id,
+            name,
+            gain: 1.0,
+            muted: false,
+            effects: Vec::new(),
+            channels,
+            sample_rate,
+            buffer: Vec::with_capacity(2048 * channels),
+            gain_smooth: Smoothed::new(1.0),
+            pdc: PdcDelay::new(channels),
+        }
+    }
+
+    /// Delay the chain adds to the return, in frames.
+    pub fn latency_frames(&self) -> usize {
+        self.effects.iter().map(BusEffect::latency_frames).sum()
+    }
+
+    pub fn effects(&self) -> Vec<BusEffectParams> {
+        self.effects.iter().map(BusEffect::params).collect()
+    }
+
+    /// Replace the chain. Inserts that keep their kind at the same position only get new
+    /// settings, so a reverb tail or the echoes of a delay carry on.
+    pub fn set_effects(&mut self, chain: &[BusEffectParams]) {
+        self.effects.truncate(chain.len());
+        for (i, &params) in chain.iter().enumerate() {
+            match self.effects.get_mut(i) {
+                Some(effect) => {
+                    if !effect.set_params(params) {
+                        *effect = BusEffect::new(params, self.channels, self.sample_rate);
+                    }
+                }
+                None => self.effects.push(BusEffect::new(params, self.channels, self.sample_rate)),
+            }
+        }
+    }
+
+    /// Rebuilds the chain (delay lines are sized by the rate). Allocates.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        let chain = self.effects();
+        self.sample_rate = sample_rate;
+        self.effects.clear();
+        self.set_effects(&chain);
+    }
+
+    pub fn info(&self) -> AuxBusInfo {
+        AuxBusInfo { id: self.id, name: self.name.clone(), gain: self.gain, muted: self.muted, effects: self.effects() }
+    }
+
+    /// Start a block of `samples` with no input.
+    pub fn begin_block(&mut self, samples: usize) {
+        self.buffer.clear();
+        self.buffer.resize(samples, 0.0);
+    }
+
+    /// Add `input` through `send` (its level ramps), starting `offset` samples into the block.
+    pub fn add_send(&mut self, offset: usize, input: &[f32], send: &mut AuxSend) {
+        let channels = self.channels.max(1);
+        send.smooth.set_target(send.level, smoothing::ramp_frames(self.sample_rate));
+        let Some(dest) = self.buffer.get_mut(offset..) else { return };
+        if !send.smooth.is_ramping() && send.level <= 0.0 {
+            return;
+        }
+        simd::add_ramped(dest, input, channels, &mut send.smooth);
+    }
+
+    /// Run the chain over this block's input and add the return, at the bus gain, to `out`.
+    pub fn mix_into(&mut self, out: &mut [f32]) {
+        let channels = self.channels.max(1);
+        for effect in &mut self.effects {
+            effect.process_block(&mut self.buffer, channels);
+        }
+        self.pdc.process(&mut self.buffer);
+        let target = if self.muted { 0.0 } else { self.gain };
+        self.gain_smooth.set_target(target, smoothing::ramp_frames(self.sample_rate));
+        simd::add_ramped(out, &self.buffer, channels, &mut self.gain_smooth);
+    }
+}
+
+/// Line the bus returns up with the most latent one. Returns that latency, which the dry
+/// mix has to be delayed by as well.
+pub fn compensate_buses(buses: &mut [AuxBus]) -> usize {
+    let max_latency = buses.iter().map(AuxBus::latency_frames).max().unwrap_or(0).min(MAX_PDC_FRAMES);
+    for bus in buses {
+        let own = bus.latency_frames().min(max_latency);
+        bus.pdc.set_delay_frames(max_latency - own);
+    }
+    max_latency
+}
+
+/// A group as the mixer UI sees it (members are the tracks whose `group` is `id`).
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupInfo {
+    pub id: u32,
+    pub name: String,
+    pub gain: f32,
+    pub muted: bool,
+    pub solo: bool,
+}
+
+pub struct GroupBus {
+    pub id: u32,
+    pub name: String,
+    pub gain: f32,
+    pub muted: bool, // mutes every member
+    pub solo: bool,  // solos every member
+    channels: usize,
+    sample_rate: u32,
+    buffer: Vec<f32>, // members summed for the current block
+    gain_smooth: Smoothed,
+}
+
+impl GroupBus {
+    pub fn new(id: u32, name: String, channels: usize, sample_rate: u32) -> Self {
+        Self {
+            id,
+            name,
+            gain: 1.0,
+            muted: false,
+            solo: false,
+            channels,
+            sample_rate,
+            buffer: Vec::with_capacity(2048 * channels),
+            gain_smooth: Smoothed::new(1.0),
+        }
+    }
+
+    pub fn info(&self) -> GroupInfo {
+        GroupInfo { id: self.id, name: self.name.clone(), gain: self.gain, muted: self.muted, solo: self.solo }
+    }
+
+    /// Start a block of `samples` with no members summed.
+    pub fn begin_block(&mut self, samples: usize) {
+        self.buffer.clear();
+        self.buffer.resize(samples, 0.0);
+    }
+
+    /// Where the members of this group are summed.
+    pub fn buffer_mut(&mut self) -> &mut [f32] {
+        &mut self.buffer
+    }
+
+    /// Add the submix, at the group gain (ramped), to `out`. Mute is left to the members,
+    /// which ramp out on their own (and take their sends with them).
+    pub fn mix_into(&mut self, out: &mut [f32]) {
+        let channels = self.channels.max(1);
+        self.gain_smooth.set_target(self.gain, smoothing::ramp_frames(self.sample_rate));
+        if !self.gain_smooth.is_ramping() && (self.gain - 1.0).abs() <= 0.001 {
+            simd::add(out, &self.buffer);
+            return;
+        }
+        simd::add_ramped(out, &self.buffer, channels, &mut self.gain_smooth);
+    }
+}
 
 pub struct Mixer {
     channels: usize,
     // temp_mix: Vec<f32>,
     mix_buffer: Vec<f32>,
     scratch_buffer: Vec<f32>,
+    buses: Vec<AuxBus>,
+    next_bus_id: u32,
+    groups: Vec<GroupBus>,
+    next_group_id: u32,
+    dry_pdc: PdcDelay, // holds the dry mix back by the bus returns' latency
 }
 
 impl Mixer {
@@ -17,6 +303,142 @@ impl Mixer {
             channels,
             mix_buffer: Vec::with_capacity(initial_capacity),
             scratch_buffer: Vec::with_capacity(initial_capacity),
+            buses: Vec::with_capacity(MAX_AUX_BUSES),
+            next_bus_id: 0,
+            groups: Vec::with_capacity(MAX_GROUPS),
+            next_group_id: 0,
+            dry_pdc: PdcDelay::new(channels),
+        }
+    }
+
+    // --- GROUPS ---
+
+    pub fn groups(&self) -> &[GroupBus] {
+        &self.groups
+    }
+
+    pub fn groups_mut(&mut self) -> &mut [GroupBus] {
+        &mut self.groups
+    }
+
+    pub fn group_mut(&mut self, id: u32) -> Option<&mut GroupBus> {
+        self.groups.iter_mut().find(|g| g.id == id)
+    }
+
+    /// (muted, soloed) of a group; a track outside any group gets (false, false).
+    pub fn group_mute_solo(&self, group: Option<u32>) -> (bool, bool) {
+        group
+            .and_then(|id| self.groups.iter().find(|g| g.id == id))
+            .map_or((false, false), |g| (g.muted, g.solo))
+    }
+
+    /// A fresh id for a group that goes in through `insert_group`.
+    pub fn reserve_group_id(&mut self) -> u32 {
+        let id = self.next_group_id;
+        self.next_group_id = self.next_group_id.saturating_add(1);
+        id
+    }
+
+    /// Adds a group, keeping its id (replacing any group with that id).
+    pub fn insert_group(&mut self, group: GroupBus) -> anyhow::Result<()> {
+        self.groups.retain(|g| g.id != group.id);
+        if self.groups.len() >= MAX_GROUPS {
+            return Err(anyhow::anyhow!("At most {} groups are supported", MAX_GROUPS));
+        }
+        self.next_group_id = self.next_group_id.max(group.id.saturating_add(1));
+        self.groups.push(group);
+        Ok(())
+    }
+
+    pub fn remove_group(&mut self, id: u32) -> Option<GroupBus> {
+        let index = self.groups.iter().position(|g| g.id == id)?;
+        Some(self.groups.remove(index))
+    }
+
+    pub fn clear_groups(&mut self) {
+        self.groups.clear();
+    }
+
+    /// Sum every group's submix into the mix.
+    pub fn mix_groups(&mut self) {
+        for group in &mut self.groups {
+            group.mix_into(&mut self.mix_buffer);
+        }
+    }
+
+    // --- AUX BUSES ---
+
+    pub fn buses(&self) -> &[AuxBus] {
+        &self.buses
+    }
+
+    pub fn bus_mut(&mut self, id: u32) -> Option<&mut AuxBus> {
+        self.buses.iter_mut().find(|b| b.id == id)
+    }
+
+    /// A fresh id for a bus that goes in through `insert_bus`.
+    pub fn reserve_bus_id(&mut self) -> u32 {
+        let id = self.next_bus_id;
+        self.next_bus_id = self.next_bus_id.saturating_add(1);
+        id
+    }
+
+    /// Adds a bus, keeping its id (replacing any bus with that id).
+    pub fn insert_bus(&mut self, bus: AuxBus) -> anyhow::Result<()> {
+        self.buses.retain(|b| b.id != bus.id);
+        if self.buses.len() >= MAX_AUX_BUSES {
+            return Err(anyhow::anyhow!("At most {} aux buses are supported", MAX_AUX_BUSES));
+        }
+        self.next_bus_id = self.next_bus_id.max(bus.id.saturating_add(1));
+        self.buses.push(bus);
+        Ok(())
+    }
+
+    pub fn remove_bus(&mut self, id: u32) -> Option<AuxBus> {
+        let index = self.buses.iter().position(|b| b.id == id)?;
+        Some(self.buses.remove(index))
+    }
+
+    pub fn clear_buses(&mut self) {
+        self.buses.clear();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        for bus in &mut self.buses {
+            bus.set_sample_rate(sample_rate);
+        }
+        for group in &mut self.groups {
+            group.sample_rate = sample_rate;
+        }
+    }
+
+    /// Feed the last track rendered into the buses it sends to.
+    pub fn send_last_track(&mut self, sends: &mut [AuxSend], samples: usize) {
+        let input = &self.scratch_buffer[..samples.min(self.scratch_buffer.len())];
+        for send in sends {
+            if let Some(bus) = self.buses.iter_mut().find(|b| b.id == send.bus_id) {
+                bus.add_send(0, input, send);
+            }
+        }
+    }
+
+    /// Latency of the most latent bus chain, in frames (what the dry mix is delayed by).
+    pub fn bus_latency_frames(&self) -> usize {
+        self.dry_pdc.delay_frames()
+    }
+
+    /// Re-align the bus returns and the dry mix after chains changed.
+    pub fn update_delay_compensation(&mut self) {
+        let latency = compensate_buses(&mut self.buses);
+        self.dry_pdc.set_delay_frames(latency);
+    }
+
+    /// Sum every bus return into the mix, after holding the dry mix back to meet them.
+    /// Direct outs leave before this; the engine delays them by `bus_latency_frames` too.
+    pub fn mix_buses(&mut self) {
+        self.dry_pdc.process(&mut self.mix_buffer);
+        for bus in &mut self.buses {
+            bus.mix_into(&mut self.mix_buffer);
         }
     }
 
@@ -30,6 +452,12 @@ impl Mixer {
         }
         
         self.mix_buffer[..needed].fill(0.0);
+        for bus in &mut self.buses {
+            bus.begin_block(needed);
+        }
+        for group in &mut self.groups {
+            group.begin_block(needed);
+        }
     }
 
     // UPDATED Signature
@@ -38,7 +466,7 @@ impl Mixer {
         track: &mut Track, 
         frames: usize, 
         channels: usize, 
-        engine_time: Duration, 
+        block_start: Frames, 
         sample_rate: u32,
         is_audible: bool
     ) {
@@ -47,33 +475,65 @@ impl Mixer {
         let total_samples = frames * self.channels;
         // let mut temp = vec![0.0f32; frames * channels];
 
+        // The track ramps itself out (and back in) when soloed out, so its output is always mixed
+        track.audible = is_audible;
+
         // Pass time info to track
         let written_frames = track.render_into(
             &mut self.scratch_buffer[..total_samples],
             channels, 
-            engine_time, 
+            block_start, 
             sample_rate
         );
 
-        if is_audible && written_frames > 0 {
+        if written_frames > 0 {
+            // Group members go through their group's submix
             let samples = written_frames * channels;
-            for i in 0..samples {
-                self.mix_buffer[i] += self.scratch_buffer[i]
-            }
+            let dest = match track.group.and_then(|id| self.groups.iter_mut().find(|g| g.id == id)) {
+                Some(group) => group.buffer_mut(),
+                None => &mut self.mix_buffer[..],
+            };
+            simd::add(dest, &self.scratch_buffer[..samples]);
         }
     }
 
-    pub fn mix_into(&self, out: &mut [f32], channels: usize) {
+    /// Like `render_track`, but into the track's direct-out buffer instead of the mix.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_track_direct(
+        &mut self,
+        track: &mut Track,
+        frames: usize,
+        channels: usize,
+        block_start: Frames,
+        sample_rate: u32,
+        is_audible: bool,
+        dest: &mut [f32],
+    ) {
         debug_assert_eq!(channels, self.channels);
-        let len = out.len().min(self.mix_buffer.len());
-        
-        for i in 0..len {
-            let sample = self.mix_buffer[i];
-            if sample.abs() < 1e-10 {
-                out[i] = 0.0;
-                continue;
-            }
-            out[i] = sample.tanh();
+
+        let total_samples = frames * self.channels;
+        track.audible = is_audible;
+        let written_frames = track.render_into(
+            &mut self.scratch_buffer[..total_samples],
+            channels,
+            block_start,
+            sample_rate
+        );
+
+        if written_frames > 0 {
+            let samples = (written_frames * channels).min(dest.len());
+            dest[..samples].copy_from_slice(&self.scratch_buffer[..samples]);
         }
     }
+
+    /// The last track rendered (either path), until the next `render_track*` call.
+    pub fn last_track_output(&self, samples: usize) -> &[f32] {
+        &self.scratch_buffer[..samples.min(self.scratch_buffer.len())]
+    }
+
+    pub fn mix_into(&self, out: &mut [f32], channels: usize) {
+        debug_assert_eq!(channels, self.channels);
+        // Saturation is the engine's master soft clip; only denormals are cleaned up here
+        simd::copy_flushed(out, &self.mix_buffer);
+    }
 }
\ No newline at end of file
@@ -1,10 +1,35 @@
 // src/engine/mod.rs
 
+// Realtime/decoder threads: log through rt_log, never std streams.
+#![deny(clippy::print_stdout, clippy::print_stderr)]
+
 pub mod track;
 pub mod mixer;
 pub mod time;
 pub mod metering;
 pub mod automation;
+pub mod pdc;
+pub mod cue;
+pub mod input_fx;
+pub mod hooks;
+pub mod markers;
+pub mod navigation;
+pub mod output_routing;
+pub mod tap;
+pub mod panic;
+pub mod preview;
+pub mod mute_regions;
+pub mod clock;
+pub mod metronome;
+pub mod smoothing;
+pub mod simd;
+pub mod crossfades;
+pub mod loudness;
+pub mod correlation;
+pub mod clip_detect;
+pub mod spectrum;
+pub mod triple_buffer;
+pub mod handle;
 
 pub use track::{Track, TrackId, TrackState};
 pub use mixer::Mixer;
@@ -13,43 +38,265 @@ pub use time::TempoMap;
 
 use std::time::Duration;
 use metering::{TrackMeters, MeterState}; // <--- ADD THIS IMPORT
+use automation::AutomationMode;
 use std::sync::Arc;
+use crate::effects::Effect;
+use crate::effects::limiter::{GainReductionMeter, LimiterNode, LimiterParams};
+use crate::effects::pitch_shift::{PitchShiftNode, PitchShiftParams};
+use crate::effects::soft_clip::{SoftClipNode, SoftClipParams};
+
+/// Range of the master varispeed.
+pub const MIN_PLAYBACK_RATE: f64 = 0.25;
+pub const MAX_PLAYBACK_RATE: f64 = 2.0;
+
+/// How mute and solo combine. One rule for realtime render, export and snapshots.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SoloPolicy {
+    /// A soloed track plays even if it is also muted.
+    #[default]
+    SoloOverridesMute,
+    /// Mute always silences, solo or not.
+    MuteAlwaysWins,
+}
+
+impl SoloPolicy {
+    /// Whether a track is heard, given its flags and whether any track is soloed.
+    pub fn is_audible(self, muted: bool, solo: bool, any_solo: bool) -> bool {
+        match self {
+            SoloPolicy::SoloOverridesMute => if any_solo { solo } else { !muted },
+            SoloPolicy::MuteAlwaysWins => !muted && (!any_solo || solo),
+        }
+    }
+}
+
+/// How far before the play position playback starts, so the lead-up to an edit is heard.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(tag = "kind", content = "value", rename_all = "camelCase")]
+pub enum PreRoll {
+    #[default]
+    Off,
+    /// Whole bars in the meter at the play position.
+    Bars(u32),
+    Seconds(f64),
+}
+
+/// What the transport does once playback runs past the end of the last clip.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum EndAction {
+    /// Keep rolling into silence.
+    #[default]
+    Continue,
+    /// Pause at the end.
+    Stop,
+    /// Jump back to zero and keep playing.
+    Loop,
+}
+
+/// Longest pre-roll, whatever the unit.
+pub const MAX_PREROLL: Duration = Duration::from_secs(60);
+
+/// How soloing a track combines with the solos already on.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SoloMode {
+    /// Solos add up.
+    #[default]
+    Additive,
+    /// Soloing a track or group clears every other solo.
+    Exclusive,
+}
+
+/// A track or clip an edit took out of the engine. Both own decoder threads and large
+/// buffers, so they are dropped on a control thread rather than in the audio callback.
+#[allow(clippy::large_enum_variant)] // boxing a track would allocate in the callback
+pub enum Discarded {
+    Track(Track),
+    Clip(track::Clip),
+}
+
+/// Why a track could not be armed. Serialized as-is to the UI.
+#[derive(serde::Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ArmError {
+    RecordSafe { track_id: u32 },
+    TrackNotFound { index: usize },
+    EngineUnavailable { reason: String },
+}
+
+impl std::fmt::Display for ArmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArmError::RecordSafe { track_id } => write!(f, "Track {} is record-safe and cannot be armed", track_id),
+            ArmError::TrackNotFound { index } => write!(f, "Track index {} out of bounds", index),
+            ArmError::EngineUnavailable { reason } => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ArmError {}
 
 #[derive(Clone, Debug)]
 pub struct Transport {
-    pub position: Duration,
+    pub position: time::Frames, // at `rate`
+    rate: u32, // the timeline rate (kept by the engine through sample rate and varispeed changes)
     pub playing: bool,
     pub tempo: TempoMap,
 }
 
+impl Transport {
+    /// Rate `position` counts at.
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    pub fn time(&self) -> Duration {
+        self.position.to_duration(self.rate)
+    }
+
+    pub fn set_time(&mut self, time: Duration) {
+        self.position = time::Frames::from_duration(time, self.rate);
+    }
+
+    // Same instant, counted at the new rate
+    fn set_rate(&mut self, rate: u32) {
+        self.position = self.position.rescale(self.rate, rate);
+        self.rate = rate;
+    }
+}
+
 pub struct Engine {
     pub transport: Transport,
     pub sample_rate: u32,
     pub channels: usize,
     pub master_gain: f32, // <--- New Field
+    master_gain_smooth: smoothing::Smoothed, // master gain as heard, ramping toward `master_gain`
     pub master_meter: Arc<TrackMeters>, // <--- NEW: Lock-free atomic state
+    pub clock: Arc<clock::TransportClock>, // published after every block, read by the UI
     master_meter_state: MeterState,     // <--- NEW: Stateful DSP Calculator
+    pub loudness: Arc<loudness::LoudnessReadout>, // master LUFS / true peak, shared with the UI
+    loudness_meter: loudness::LoudnessMeter,
+    pub correlation: Arc<correlation::CorrelationReadout>, // master phase correlation / goniometer
+    correlation_meter: correlation::CorrelationMeter,
+    pub clip_detect: Arc<clip_detect::ClipReadout>, // pre-limiter overs on the master
     tracks: Vec<Track>,
     mixer: Mixer,
     next_id: u32,
+    pub cue: cue::CueBlend, // <--- NEW: Monitor blend (input vs playback)
+    pub cue_active: bool,   // set by the runtime while an input is being monitored
+    pub cue_on_speakers: bool, // without cue outputs, the speakers play the cue mix instead of the master
+    pub input_monitor: Option<crate::recorder::monitor::Monitor>, // live input fed to `render` by the runtime
+    pub solo_policy: SoloPolicy,
+    pub solo_mode: SoloMode,
+    pub arm_exclusive: bool, // arming a track disarms every other track
+    pub return_to_start_on_stop: bool, // stop goes to zero instead of where play started
+    play_start: Duration, // where the last play began, the stop return position
+    pub end_action: EndAction, // what happens when playback reaches the project end
+    project_end: (u64, Duration), // (content revision it was computed at, end of the last clip)
+    block_callback: Option<hooks::BlockCallback>,
+    block_peaks: Vec<hooks::TrackPeak>, // preallocated scratch for BlockInfo
+    pub markers: markers::Markers,
+    edit_points: navigation::EditPoints, // lazily rebuilt clip boundaries
+    content_revision: u64, // bumped on every track/clip change, for caches outside the engine
+    pub output_routing: output_routing::OutputRouting, // change through set_bus_output_channels
+    cue_bus: Vec<f32>,                 // cue mix while the cue bus has its own outputs
+    input_fx: input_fx::InputFx, // armed track's EQ and compressor on the monitored input
+    live_bus: Vec<f32>,          // the monitored input after `input_fx`
+    direct_outs: Vec<(TrackId, Vec<f32>, pdc::PdcDelay)>, // per-track direct out buffers (routed tracks only), delayed like the dry mix
+    master_tap: Option<tap::MasterTap>, // fed with the finished master while playing
+    master_pitch: PitchShiftNode, // key change on the summed mix, first in the master chain
+    master_clip: SoftClipNode, // saturation on the summed mix, before the cue blend and master gain
+    master_limiter: LimiterNode, // brickwall, last: after master gain and the monitored input
+    pub limiter_meter: Arc<GainReductionMeter>, // limiter gain reduction, shared with the UI
+    track_taps: Vec<(TrackId, tap::MasterTap)>, // multitrack print: post-fader track outputs
+    analyzers: Vec<(spectrum::SpectrumTarget, spectrum::SpectrumAnalyzer)>, // spectrum taps (post-fader / master)
+    panic: panic::PanicRamp,
+    panic_gains: Vec<f32>, // per-frame ramp gains, reused every block
+    pub monitor_muted: bool, // set by a panic; only an explicit unmute clears it
+    listen_bus: Vec<f32>,    // PFL/AFL signal of the listening track, for the cue output
+    previews: Vec<preview::PreviewVoice>, // hover previews; older ones are fading out
+    preview_reaper: preview::PreviewReaper, // drops finished previews off the audio thread
+    ping_pending: bool, // latency calibration: click at the start of the next block
+    ping_sent: Option<std::time::Instant>, // when the last calibration click was rendered
+    metronome: metronome::Metronome,
+    pub recording: bool, // set by the runtime while a take is being recorded
+    count_in_remaining: u64, // frames of count-in left; the transport waits until 0
+    count_in_elapsed: u64,
+    pub preroll: PreRoll,
+    lead_in_remaining: u64, // frames of silence before timeline zero (seeked to a negative position)
+    playback_rate: f64, // varispeed: timeline seconds per second of output
+    snap: time::SnapResolution, // grid clip moves land on
+    discarded: Vec<Discarded>, // removed tracks, until `drain_discarded`
 }
 
 impl Engine {
     pub fn new(sample_rate: u32, channels: usize) -> Self {
         Self {
             transport: Transport {
-                position: Duration::from_secs(0),
+                position: time::Frames::ZERO,
+                rate: sample_rate,
                 playing: false,
                 tempo: TempoMap::default(),
             },
             sample_rate,
             channels,
             master_gain: 1.0, // <--- FIXED: Initialized here (Default 1.0 = 100%)
+            master_gain_smooth: smoothing::Smoothed::new(1.0),
             master_meter: TrackMeters::new(),                        // <--- NEW
+            clock: clock::TransportClock::new(),
             master_meter_state: MeterState::new(sample_rate as f32), // <--- NEW
+            loudness: loudness::LoudnessReadout::new(),
+            loudness_meter: loudness::LoudnessMeter::new(sample_rate, channels),
+            correlation: correlation::CorrelationReadout::new(),
+            correlation_meter: correlation::CorrelationMeter::new(sample_rate),
+            clip_detect: clip_detect::ClipReadout::new(),
             tracks: Vec::new(),
             mixer: Mixer::new(channels),
             next_id: 0,
+            cue: cue::CueBlend::new(),
+            cue_active: false,
+            cue_on_speakers: false,
+            input_monitor: None,
+            solo_policy: SoloPolicy::default(),
+            solo_mode: SoloMode::default(),
+            arm_exclusive: false,
+            return_to_start_on_stop: false,
+            play_start: Duration::ZERO,
+            end_action: EndAction::Continue,
+            project_end: (u64::MAX, Duration::ZERO),
+            block_callback: None,
+            block_peaks: Vec::new(),
+            markers: markers::Markers::new(),
+            edit_points: navigation::EditPoints::new(),
+            content_revision: 0,
+            output_routing: output_routing::OutputRouting::new(channels),
+            cue_bus: Vec::with_capacity(4096 * channels),
+            input_fx: input_fx::InputFx::new(sample_rate, channels),
+            live_bus: Vec::with_capacity(4096 * channels),
+            direct_outs: Vec::new(),
+            master_tap: None,
+            master_pitch: PitchShiftNode::new(PitchShiftParams::default(), channels, sample_rate),
+            master_clip: SoftClipNode::new(SoftClipParams::default(), channels),
+            master_limiter: LimiterNode::new(LimiterParams::default(), channels, sample_rate),
+            limiter_meter: GainReductionMeter::new(),
+            track_taps: Vec::new(),
+            analyzers: Vec::new(),
+            panic: panic::PanicRamp::new(),
+            panic_gains: vec![1.0; 4096],
+            monitor_muted: false,
+            listen_bus: Vec::with_capacity(4096 * channels),
+            previews: Vec::with_capacity(4),
+            preview_reaper: preview::PreviewReaper::new(),
+            ping_pending: false,
+            ping_sent: None,
+            metronome: metronome::Metronome::new(sample_rate),
+            recording: false,
+            count_in_remaining: 0,
+            count_in_elapsed: 0,
+            preroll: PreRoll::Off,
+            lead_in_remaining: 0,
+            playback_rate: 1.0,
+            snap: time::SnapResolution::Off,
+            discarded: Vec::with_capacity(16),
         }
     }
 
@@ -57,8 +304,69 @@ impl Engine {
         self.transport.tempo.bpm = bpm as f64;
     }
 
+    pub fn snap_resolution(&self) -> time::SnapResolution {
+        self.snap
+    }
+
+    pub fn set_snap_resolution(&mut self, resolution: time::SnapResolution) {
+        self.snap = resolution;
+    }
+
+    /// `secs` moved to the nearest line of the snap grid (unchanged while snapping is off).
+    pub fn snap_time(&self, secs: f64) -> f64 {
+        let position = Duration::from_secs_f64(secs.max(0.0));
+        self.transport.tempo.snap(position, self.snap).as_secs_f64()
+    }
+
+    pub fn playback_rate(&self) -> f64 {
+        self.playback_rate
+    }
+
+    /// Varispeed (speed and pitch together), clamped to `MIN_PLAYBACK_RATE..=MAX_PLAYBACK_RATE`.
+    /// Returns the rate applied.
+    pub fn set_playback_rate(&mut self, rate: f64) -> f64 {
+        let rate = if rate.is_finite() { rate.clamp(MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE) } else { 1.0 };
+        if rate != self.playback_rate {
+            self.playback_rate = rate;
+            self.transport.set_rate(self.timeline_rate());
+            self.count_in_remaining = 0;
+            self.lead_in_remaining = 0;
+            self.clock.bump_generation();
+        }
+        rate
+    }
+
+    // Rate the timeline is rendered at. Under varispeed the tracks decode at this rate and the
+    // device plays it back at `sample_rate`, so every frame <-> time conversion on the
+    // timeline (clips, automation, clicks, the transport) uses it instead.
+    fn timeline_rate(&self) -> u32 {
+        (self.sample_rate as f64 / self.playback_rate).round() as u32
+    }
+
+    // Tracks or clips changed: drop the derived caches.
+    fn content_changed(&mut self) {
+        self.edit_points.invalidate();
+        self.content_revision += 1;
+    }
+
+    /// Changes whenever tracks or clips may have changed; equal revisions mean equal content.
+    pub fn content_revision(&self) -> u64 {
+        self.content_revision
+    }
+
+    /// Give the tracks (in order) the ids they had before a rebuild; extra tracks keep theirs.
+    pub fn reassign_track_ids(&mut self, ids: &[u32]) {
+        for (track, &id) in self.tracks.iter_mut().zip(ids) {
+            track.id = TrackId(id);
+        }
+        let highest = self.tracks.iter().map(|t| t.id.0 + 1).max().unwrap_or(0);
+        self.next_id = self.next_id.max(highest);
+        self.content_changed();
+    }
+
     pub fn clear_tracks(&mut self) {
-        self.tracks.clear();
+        self.discarded.extend(self.tracks.drain(..).map(Discarded::Track));
+        self.content_changed();
     }
 
     // --- NEW: Create a generic empty track ---
@@ -68,6 +376,28 @@ impl Engine {
         let id = TrackId(self.next_id);
         self.next_id += 1; 
 
+        let track = self.new_track(id);
+        self.tracks.push(track);
+        self.content_changed();
+        self.block_peaks.reserve(self.tracks.len()); // keep render() allocation-free
+        id
+    }
+
+    /// Empty track with a given id at `index` (undoing a track delete).
+    pub fn insert_empty_track(&mut self, index: usize, id: TrackId) -> anyhow::Result<()> {
+        if self.tracks.iter().any(|t| t.id == id) {
+            return Err(anyhow::anyhow!("Track id {} is already in use", id.0));
+        }
+        let track = self.new_track(id);
+        self.tracks.insert(index.min(self.tracks.len()), track);
+        self.next_id = self.next_id.max(id.0 + 1);
+        self.content_changed();
+        self.block_peaks.reserve(self.tracks.len());
+        Ok(())
+    }
+
+    // Fresh track with an unused color
+    fn new_track(&self, id: TrackId) -> Track {
         // 1. Define Palette
         let colors = [
             "bg-brand-blue", "bg-brand-red", "bg-purple-500", 
@@ -100,26 +430,439 @@ impl Engine {
         };
 
         // 6. Create Track
-        let track = Track::new(
+        Track::new(
             id, 
             format!("Track {}", id.0 + 1), 
             chosen_color,
             self.sample_rate, 
             self.channels
-        );
-        self.tracks.push(track);
-        id
+        )
+    }
+
+    /// Install a hook that runs at the end of every `render`, ON THE AUDIO THREAD.
+    /// It must not allocate, lock or block (see `engine::hooks`). A panicking hook is removed.
+    /// Use `hooks::relay_channel` for consumers that can't honor that.
+    pub fn set_block_callback(&mut self, callback: hooks::BlockCallback) {
+        self.block_peaks.reserve(self.tracks.len());
+        self.block_callback = Some(callback);
+    }
+
+    pub fn clear_block_callback(&mut self) {
+        self.block_callback = None;
+    }
+
+    /// End of the last clip on the timeline.
+    pub fn project_end(&self) -> Duration {
+        self.tracks
+            .iter()
+            .flat_map(|t| t.clips.iter())
+            .map(|c| c.end())
+            .max()
+            .unwrap_or(Duration::ZERO)
+    }
+
+    // `project_end` recomputed only when the content changed, for the per-block end check
+    fn cached_project_end(&mut self) -> Duration {
+        if self.project_end.0 != self.content_revision {
+            self.project_end = (self.content_revision, self.project_end());
+        }
+        self.project_end.1
+    }
+
+    // Playback crossed the end of the last clip in this block
+    fn reach_project_end(&mut self, end: Duration) {
+        // In the clock's frames: the transport position (at the timeline rate) rescaled the
+        // way `render` publishes it, so under varispeed too the end matches the playhead
+        let rate = self.transport.rate();
+        self.clock.note_finished(time::Frames::from_duration(end, rate).rescale(rate, self.sample_rate).0);
+        match self.end_action {
+            EndAction::Continue => {}
+            EndAction::Stop => {
+                self.pause();
+                self.seek(end);
+            }
+            EndAction::Loop => self.seek(Duration::ZERO),
+        }
+    }
+
+    /// Holes between timeline zero and the project end where no clip plays on any audible
+    /// track, at least `min_gap` long; silence before the first clip counts. Silence inside
+    /// clips is not looked at. A project with no clips has no gaps.
+    pub fn find_gaps(&self, min_gap: Duration) -> Vec<(Duration, Duration)> {
+        self.find_gaps_with(min_gap, true)
+    }
+
+    /// `fades_are_gaps`: a stretch covered only by a clip's edge fade counts as a gap.
+    /// Clips that touch or overlap (on any audible track) play as one region, so only the
+    /// outer edges of that region are trimmed; a butt splice is never a gap.
+    pub fn find_gaps_with(&self, min_gap: Duration, fades_are_gaps: bool) -> Vec<(Duration, Duration)> {
+        let any_solo = self.any_solo();
+        let fade = if fades_are_gaps { track::CLIP_EDGE_FADE } else { Duration::ZERO };
+        let eps = Duration::from_millis(1); // clips at different rates may miss by a frame
+
+        let mut spans: Vec<(Duration, Duration)> = self
+            .tracks
+            .iter()
+            .filter(|t| self.is_track_audible(t, any_solo))
+            .flat_map(|t| t.clips.iter())
+            .map(|c| (c.start(), c.end()))
+            .collect();
+        spans.sort();
+
+        let mut regions: Vec<(Duration, Duration)> = Vec::new();
+        for (start, end) in spans {
+            match regions.last_mut() {
+                Some(last) if start <= last.1 + eps => last.1 = last.1.max(end),
+                _ => regions.push((start, end)),
+            }
+        }
+
+        let mut gaps = Vec::new();
+        let mut covered_until = Duration::ZERO;
+        for (start, end) in regions.into_iter().filter(|(s, e)| *e - *s > fade * 2) {
+            let (start, end) = (start + fade, end - fade);
+            if start > covered_until && start - covered_until >= min_gap {
+                gaps.push((covered_until, start));
+            }
+            covered_until = end;
+        }
+        gaps
+    }
+
+    /// Put a spectrum analyzer on a track's post-fader output or the master (no-op if one
+    /// is there already). Allocates; not for the audio thread.
+    pub fn attach_spectrum(&mut self, target: spectrum::SpectrumTarget) -> anyhow::Result<Arc<spectrum::SpectrumReadout>> {
+        if let spectrum::SpectrumTarget::Track(id) = target {
+            if !self.tracks.iter().any(|t| t.id == id) {
+                return Err(anyhow::anyhow!("Track {} not found", id.0));
+            }
+        }
+        if let Some(readout) = self.spectrum_readout(target) {
+            return Ok(readout);
+        }
+        let analyzer = spectrum::SpectrumAnalyzer::new(self.sample_rate);
+        let readout = analyzer.readout();
+        self.analyzers.push((target, analyzer));
+        Ok(readout)
+    }
+
+    pub fn detach_spectrum(&mut self, target: spectrum::SpectrumTarget) {
+        self.analyzers.retain(|(t, _)| *t != target);
+    }
+
+    pub fn spectrum_readout(&self, target: spectrum::SpectrumTarget) -> Option<Arc<spectrum::SpectrumReadout>> {
+        self.analyzers.iter().find(|(t, _)| *t == target).map(|(_, a)| a.readout())
+    }
+
+    /// Start copying the master output (while playing) into `tap`.
+    pub fn set_master_tap(&mut self, tap: tap::MasterTap) {
+        self.master_tap = Some(tap);
+    }
+
+    pub fn clear_master_tap(&mut self) {
+        self.master_tap = None;
+    }
+
+    /// Start copying each listed track's post-fader output (while playing) into its tap.
+    pub fn set_track_taps(&mut self, taps: Vec<(TrackId, tap::MasterTap)>) {
+        self.track_taps = taps;
+    }
+
+    pub fn clear_track_taps(&mut self) {
+        self.track_taps.clear();
+    }
+
+    pub fn has_master_tap(&self) -> bool {
+        self.master_tap.is_some()
+    }
+
+    pub fn master_pitch_shift(&self) -> PitchShiftParams {
+        self.master_pitch.get_params()
+    }
+
+    pub fn set_master_pitch_shift(&mut self, params: PitchShiftParams) {
+        self.master_pitch.set_params(params);
+    }
+
+    pub fn master_soft_clip(&self) -> SoftClipParams {
+        self.master_clip.get_params()
+    }
+
+    pub fn set_master_soft_clip(&mut self, params: SoftClipParams) {
+        self.master_clip.set_params(params);
+    }
+
+    pub fn master_limiter(&self) -> LimiterParams {
+        self.master_limiter.get_params()
+    }
+
+    pub fn set_master_limiter(&mut self, params: LimiterParams) {
+        self.master_limiter.set_params(params);
+    }
+
+    /// Switch to the device's rate, rebuilding what is sized by it (click sounds, limiter
+    /// delay lines). Allocates; call it before the stream starts, not from the callback.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        if sample_rate == self.sample_rate {
+            return;
+        }
+        self.sample_rate = sample_rate;
+        self.transport.set_rate(self.timeline_rate());
+        let metronome = self.metronome.config();
+        self.metronome = metronome::Metronome::new(sample_rate);
+        self.metronome.set_config(metronome);
+        self.master_limiter = LimiterNode::new(self.master_limiter.get_params(), self.channels, sample_rate);
+        self.master_pitch = PitchShiftNode::new(self.master_pitch.get_params(), self.channels, sample_rate);
+        self.input_fx = input_fx::InputFx::new(sample_rate, self.channels);
+        self.mixer.set_sample_rate(sample_rate);
+        self.loudness_meter = loudness::LoudnessMeter::new(sample_rate, self.channels);
+        self.correlation_meter = correlation::CorrelationMeter::new(sample_rate);
+        for (_, analyzer) in &mut self.analyzers {
+            analyzer.set_sample_rate(sample_rate);
+        }
+        for track in &mut self.tracks {
+            track.inserts.set_sample_rate(sample_rate);
+        }
+    }
+
+    // --- GROUPS ---
+
+    /// Mute and solo of a track with its group's folded in.
+    pub fn track_mute_solo(&self, track: &Track) -> (bool, bool) {
+        let (group_muted, group_solo) = self.mixer.group_mute_solo(track.group);
+        (track.muted || group_muted, track.solo || group_solo)
+    }
+
+    /// Whether anything is soloed, directly or through its group.
+    pub fn any_solo(&self) -> bool {
+        self.tracks.iter().any(|t| self.track_mute_solo(t).1)
+    }
+
+    /// Whether a track is heard under the solo policy, given `any_solo()`. Solo-safe tracks
+    /// ignore other tracks' solos; their own mute still applies.
+    pub fn is_track_audible(&self, track: &Track, any_solo: bool) -> bool {
+        let (muted, solo) = self.track_mute_solo(track);
+        self.solo_policy.is_audible(muted, solo, any_solo && !track.solo_safe)
+    }
+
+    /// Toggle a track's solo; in `SoloMode::Exclusive` soloing it clears every other solo.
+    pub fn toggle_solo(&mut self, index: usize) {
+        let Some(solo) = self.tracks.get(index).map(|t| !t.solo) else {
+            return;
+        };
+        if solo && self.solo_mode == SoloMode::Exclusive {
+            self.clear_solo();
+        }
+        self.tracks[index].solo = solo;
+    }
+
+    /// Solo or unsolo a group; in `SoloMode::Exclusive` soloing it clears every other solo.
+    pub fn set_group_solo(&mut self, id: u32, solo: bool) -> anyhow::Result<()> {
+        if self.mixer.group_mut(id).is_none() {
+            return Err(anyhow::anyhow!("Group {} not found", id));
+        }
+        if solo && self.solo_mode == SoloMode::Exclusive {
+            self.clear_solo();
+        }
+        if let Some(group) = self.mixer.group_mut(id) {
+            group.solo = solo;
+        }
+        Ok(())
+    }
+
+    /// Unsolo every track and group.
+    pub fn clear_solo(&mut self) {
+        for t in &mut self.tracks {
+            t.solo = false;
+        }
+        self.clear_group_solo();
+    }
+
+    pub fn groups(&self) -> &[mixer::GroupBus] {
+        self.mixer.groups()
+    }
+
+    pub fn group_mut(&mut self, id: u32) -> Option<&mut mixer::GroupBus> {
+        self.mixer.group_mut(id)
+    }
+
+    /// New empty group at unity. Returns its id.
+    pub fn add_group(&mut self, name: String) -> anyhow::Result<u32> {
+        let id = self.mixer.reserve_group_id();
+        self.insert_group(mixer::GroupBus::new(id, name, self.channels, self.sample_rate))?;
+        Ok(id)
+    }
+
+    /// Puts back a group with its own id (project load).
+    pub fn insert_group(&mut self, group: mixer::GroupBus) -> anyhow::Result<()> {
+        self.mixer.insert_group(group)
+    }
+
+    /// Removes the group; its members go straight to the master again.
+    pub fn remove_group(&mut self, id: u32) -> anyhow::Result<()> {
+        self.mixer.remove_group(id).ok_or_else(|| anyhow::anyhow!("Group {} not found", id))?;
+        for track in self.tracks.iter_mut().filter(|t| t.group == Some(id)) {
+            track.group = None;
+        }
+        Ok(())
+    }
+
+    pub fn clear_group_solo(&mut self) {
+        self.mixer.groups_mut().iter_mut().for_each(|g| g.solo = false);
+    }
+
+    pub fn clear_groups(&mut self) {
+        self.mixer.clear_groups();
+        for track in &mut self.tracks {
+            track.group = None;
+        }
+    }
+
+    /// Put a track in a group (`None` takes it out).
+    pub fn set_track_group(&mut self, track_index: usize, group: Option<u32>) -> anyhow::Result<()> {
+        if let Some(id) = group.filter(|&id| self.mixer.groups().iter().all(|g| g.id != id)) {
+            return Err(anyhow::anyhow!("Group {} not found", id));
+        }
+        let track = self
+            .tracks
+            .get_mut(track_index)
+            .ok_or_else(|| anyhow::anyhow!("Track index {} out of bounds", track_index))?;
+        track.group = group;
+        Ok(())
+    }
+
+    // --- AUX BUSES ---
+
+    pub fn aux_buses(&self) -> &[mixer::AuxBus] {
+        self.mixer.buses()
+    }
+
+    pub fn aux_bus_mut(&mut self, id: u32) -> Option<&mut mixer::AuxBus> {
+        self.mixer.bus_mut(id)
+    }
+
+    /// New bus with an empty chain, at unity. Returns its id.
+    pub fn add_aux_bus(&mut self, name: String) -> anyhow::Result<u32> {
+        let id = self.mixer.reserve_bus_id();
+        self.insert_aux_bus(mixer::AuxBus::new(id, name, self.channels, self.sample_rate))?;
+        Ok(id)
+    }
+
+    /// Puts back a bus with its own id (project load).
+    pub fn insert_aux_bus(&mut self, bus: mixer::AuxBus) -> anyhow::Result<()> {
+        self.mixer.insert_bus(bus)
+    }
+
+    /// Removes the bus and every send into it.
+    pub fn remove_aux_bus(&mut self, id: u32) -> anyhow::Result<()> {
+        self.mixer.remove_bus(id).ok_or_else(|| anyhow::anyhow!("Aux bus {} not found", id))?;
+        for track in &mut self.tracks {
+            track.sends.retain(|s| s.bus_id != id);
+        }
+        Ok(())
+    }
+
+    pub fn clear_aux_buses(&mut self) {
+        self.mixer.clear_buses();
+        for track in &mut self.tracks {
+            track.sends.clear();
+        }
+    }
+
+    /// Post-fader send level (linear, clamped to 0..2) from a track into a bus.
+    pub fn set_track_send(&mut self, track_index: usize, bus_id: u32, level: f32) -> anyhow::Result<()> {
+        if self.mixer.buses().iter().all(|b| b.id != bus_id) {
+            return Err(anyhow::anyhow!("Aux bus {} not found", bus_id));
+        }
+        let track = self
+            .tracks
+            .get_mut(track_index)
+            .ok_or_else(|| anyhow::anyhow!("Track index {} out of bounds", track_index))?;
+        let level = if level.is_finite() { level.clamp(0.0, 2.0) } else { 0.0 };
+        match track.sends.iter_mut().find(|s| s.bus_id == bus_id) {
+            Some(send) => send.level = level,
+            None => track.sends.push(mixer::AuxSend::new(bus_id, level)),
+        }
+        Ok(())
+    }
+
+    // --- METRONOME ---
+
+    pub fn metronome_config(&self) -> metronome::MetronomeConfig {
+        self.metronome.config()
+    }
+
+    /// Returns the config as applied (ranges clamped).
+    pub fn set_metronome_config(&mut self, config: metronome::MetronomeConfig) -> metronome::MetronomeConfig {
+        self.metronome.set_config(config)
+    }
+
+    // --- OUTPUT ROUTING ---
+
+    /// Send a bus to a pair of physical output channels (0-based).
+    pub fn set_bus_output_channels(
+        &mut self,
+        bus: output_routing::OutputBus,
+        left: usize,
+        right: usize,
+    ) -> Result<(), output_routing::RoutingError> {
+        self.output_routing.set_bus_output_channels(bus, left, right)?;
+        self.sync_direct_outs();
+        Ok(())
+    }
+
+    pub fn clear_bus_output(&mut self, bus: output_routing::OutputBus) {
+        self.output_routing.clear_bus(bus);
+        self.sync_direct_outs();
+    }
+
+    /// Re-apply saved routes (e.g. after a device change). Returns the ones that didn't fit.
+    pub fn restore_output_routing(&mut self, routes: &[output_routing::BusRoute]) -> Vec<output_routing::RoutingError> {
+        let errors = self.output_routing.restore(routes);
+        self.sync_direct_outs();
+        errors
+    }
+
+    /// Called when the output stream is (re)opened.
+    pub fn set_output_channels(&mut self, device_channels: usize) -> Vec<output_routing::BusRoute> {
+        let dropped = self.output_routing.set_device_channels(device_channels);
+        self.sync_direct_outs();
+        dropped
+    }
+
+    /// Interleaved stereo block of a non-master bus from the last `render`.
+    pub fn bus_buffer(&self, bus: output_routing::OutputBus) -> Option<&[f32]> {
+        match bus {
+            output_routing::OutputBus::Master => None,
+            output_routing::OutputBus::Cue => Some(&self.cue_bus),
+            output_routing::OutputBus::Track(id) => {
+                self.direct_outs.iter().find(|(t, _, _)| t.0 == id).map(|(_, buf, _)| buf.as_slice())
+            }
+        }
+    }
+
+    // Direct-out buffers are (re)allocated here, never in render()
+    fn sync_direct_outs(&mut self) {
+        let wanted: Vec<TrackId> = self.output_routing.direct_outs().map(TrackId).collect();
+        self.direct_outs.retain(|(id, _, _)| wanted.contains(id));
+        for id in wanted {
+            if !self.direct_outs.iter().any(|(t, _, _)| *t == id) {
+                self.direct_outs.push((id, Vec::with_capacity(4096 * self.channels), pdc::PdcDelay::new(self.channels)));
+            }
+        }
     }
 
     // --- NEW: Add a Clip to an existing Track ---
     // --- NEW: Add a Clip to an existing Track ---
     pub fn add_clip(&mut self, track_index: usize, path: String, start_time_secs: f64) -> anyhow::Result<()> {
+        self.ensure_not_frozen(track_index)?;
+        self.content_changed();
         let sample_rate = self.sample_rate;
         let channels = self.channels;
         let start_time = Duration::from_secs_f64(start_time_secs);
 
         // Ensure this is still here!
-        let current_pos = Some(self.transport.position); 
+        let current_pos = Some(self.transport.time()); 
 
         if let Some(track) = self.tracks.get_mut(track_index) {
             track.add_clip(path, start_time, sample_rate, channels, current_pos)?;
@@ -130,13 +873,79 @@ impl Engine {
         }
     }
 
-    pub fn remove_track(&mut self, index: usize) -> anyhow::Result<()> {
-        if index < self.tracks.len() {
-            self.tracks.remove(index);
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Track index out of bounds"))
+    /// `add_clip` with the clip already opened (off the audio thread). Returns the track's
+    /// analysis slot so the caller can start `Track::analyze_in_background`.
+    pub fn add_prepared_clip(
+        &mut self,
+        track_index: usize,
+        clip: track::Clip,
+    ) -> anyhow::Result<Arc<std::sync::Mutex<Option<crate::analyzer::AnalysisProfile>>>> {
+        self.ensure_not_frozen(track_index)?;
+        if track_index >= self.tracks.len() {
+            return Err(anyhow::anyhow!("Track index {} out of bounds", track_index));
         }
+        self.content_changed();
+        let current_pos = Some(self.transport.time());
+        let track = &mut self.tracks[track_index];
+        track.add_prepared_clip(clip, current_pos);
+        Ok(Arc::clone(&track.analysis))
+    }
+
+    /// Removes the track and returns the index it had. It is dropped (ending its clips'
+    /// decoder threads) by whoever drains `drain_discarded`. The other tracks keep their ids.
+    pub fn remove_track(&mut self, id: TrackId) -> anyhow::Result<usize> {
+        let index = self
+            .tracks
+            .iter()
+            .position(|t| t.id == id)
+            .ok_or_else(|| anyhow::anyhow!("Track {} not found", id.0))?;
+        let track = self.tracks.remove(index);
+        self.discarded.push(Discarded::Track(track));
+        self.track_taps.retain(|(t, _)| *t != id);
+        self.analyzers.retain(|(t, _)| *t != spectrum::SpectrumTarget::Track(id));
+        self.content_changed();
+        Ok(index)
+    }
+
+    /// Hand every track and clip removed since the last call to `drop_off_thread`. The
+    /// callback passes them back to the control side with its spent edits.
+    pub fn drain_discarded(&mut self, mut drop_off_thread: impl FnMut(Discarded)) {
+        for item in self.discarded.drain(..) {
+            drop_off_thread(item);
+        }
+        for track in &mut self.tracks {
+            for clip in track.take_discarded() {
+                drop_off_thread(Discarded::Clip(clip));
+            }
+        }
+    }
+
+    /// Put the tracks in `order`, which must list every track id exactly once.
+    pub fn reorder_tracks(&mut self, order: &[TrackId]) -> anyhow::Result<()> {
+        if order.len() != self.tracks.len() {
+            return Err(anyhow::anyhow!("Expected {} track ids, got {}", self.tracks.len(), order.len()));
+        }
+        let mut positions = Vec::with_capacity(order.len());
+        for id in order {
+            let pos = self
+                .tracks
+                .iter()
+                .position(|t| t.id == *id)
+                .ok_or_else(|| anyhow::anyhow!("Track {} not found", id.0))?;
+            if positions.contains(&pos) {
+                return Err(anyhow::anyhow!("Track {} listed twice", id.0));
+            }
+            positions.push(pos);
+        }
+        let mut old: Vec<Option<Track>> = std::mem::take(&mut self.tracks).into_iter().map(Some).collect();
+        self.tracks = positions.into_iter().filter_map(|pos| old[pos].take()).collect();
+        self.content_changed();
+        Ok(())
+    }
+
+    /// Track ids in display order.
+    pub fn track_order(&self) -> Vec<TrackId> {
+        self.tracks.iter().map(|t| t.id).collect()
     }
 
     // --- UPDATED: Wrapper for backward compatibility ---
@@ -159,10 +968,12 @@ impl Engine {
     }
 
     pub fn split_clip(&mut self, track_index: usize, time_secs: f64) -> anyhow::Result<()> {
+        self.ensure_not_frozen(track_index)?;
+        self.content_changed();
         let split_time = Duration::from_secs_f64(time_secs);
         
         if let Some(track) = self.tracks.get_mut(track_index) {
-             track.split_at_time(split_time, self.sample_rate, self.channels)?;
+             track.split_at_time(split_time, self.channels)?;
              Ok(())
         } else {
             Err(anyhow::anyhow!("Track not found"))
@@ -170,6 +981,8 @@ impl Engine {
     }
 
     pub fn merge_clip_with_next(&mut self, track_index: usize, clip_index: usize) -> anyhow::Result<()> {
+        self.ensure_not_frozen(track_index)?;
+        self.content_changed();
         if let Some(track) = self.tracks.get_mut(track_index) {
             track.merge_next(clip_index)
         } else {
@@ -178,6 +991,8 @@ impl Engine {
     }
 
     pub fn delete_clip(&mut self, track_index: usize, clip_index: usize) -> anyhow::Result<()> {
+        self.ensure_not_frozen(track_index)?;
+        self.content_changed();
         if let Some(track) = self.tracks.get_mut(track_index) {
             track.delete_clip(clip_index)
         } else {
@@ -185,99 +1000,1515 @@ impl Engine {
         }
     }
 
+    // --- FREEZE ---
+
+    /// Play the file at `path` (rendered by `session::freeze`) in place of the track's clips and inserts.
+    /// `rendered` is the track's bounce, opened (`Clip::new`) off the audio thread.
+    pub fn freeze_track(&mut self, track_index: usize, rendered: track::Clip) -> anyhow::Result<()> {
+        let track = self
+            .tracks
+            .get(track_index)
+            .ok_or_else(|| anyhow::anyhow!("Track index {} out of bounds", track_index))?;
+        if track.is_frozen() {
+            return Err(anyhow::anyhow!("Track is already frozen"));
+        }
+        let position = self.transport.time();
+        self.tracks[track_index].freeze(rendered, position);
+        Ok(())
+    }
+
+    /// Returns the path of the rendered file, `None` if the track wasn't frozen.
+    pub fn unfreeze_track(&mut self, track_index: usize) -> anyhow::Result<Option<String>> {
+        let position = self.transport.time();
+        let track = self
+            .tracks
+            .get_mut(track_index)
+            .ok_or_else(|| anyhow::anyhow!("Track index {} out of bounds", track_index))?;
+        Ok(track.unfreeze(position))
+    }
+
+    /// Clip edits would be silently ignored while the rendered file plays.
+    pub fn ensure_not_frozen(&self, track_index: usize) -> anyhow::Result<()> {
+        if self.tracks.get(track_index).is_some_and(|t| t.is_frozen()) {
+            return Err(anyhow::anyhow!("Track is frozen; unfreeze it to edit clips"));
+        }
+        Ok(())
+    }
+
+    /// Mutable access may change clips, so it also invalidates the edit-point cache (and bumps the revision).
     pub fn tracks_mut(&mut self) -> &mut [Track] {
+        self.content_changed();
         &mut self.tracks
     }
 
     pub fn play(&mut self) {
+        if !self.transport.playing {
+            self.play_start = self.transport.time();
+            self.begin_automation_passes();
+        }
+        self.clock.bump_generation();
         self.transport.playing = true;
         for t in &mut self.tracks {
             t.set_state(TrackState::Playing);
         }
     }
 
-    pub fn pause(&mut self) {
-        self.transport.playing = false;
-        for t in &mut self.tracks {
-            t.set_state(TrackState::Paused);
+    /// Play from `preroll` before the playhead, so the lead-up is heard; `stop` still returns
+    /// to the playhead. A plain `play` while already playing or with no pre-roll set.
+    pub fn play_with_preroll(&mut self) {
+        let edit_point = self.transport.time();
+        let length = self.preroll_length(edit_point);
+        if self.transport.playing || length.is_zero() {
+            self.play();
+            return;
         }
+        self.seek_signed(edit_point.as_secs_f64() - length.as_secs_f64());
+        self.play();
+        self.play_start = edit_point;
     }
 
-    pub fn seek(&mut self, pos: Duration) {
-        self.transport.position = pos;
-        for t in &mut self.tracks {
-            t.seek(pos);
-        }
+    /// Length of the pre-roll before `position`, capped at `MAX_PREROLL`.
+    pub fn preroll_length(&self, position: Duration) -> Duration {
+        let secs = match self.preroll {
+            PreRoll::Off => 0.0,
+            PreRoll::Bars(bars) => {
+                let tempo = &self.transport.tempo;
+                if tempo.bpm > 0.0 { bars as f64 * tempo.seconds_per_bar_in(tempo.signature_at(position)) } else { 0.0 }
+            }
+            PreRoll::Seconds(secs) if secs.is_finite() => secs,
+            PreRoll::Seconds(_) => 0.0,
+        };
+        Duration::from_secs_f64(secs.clamp(0.0, MAX_PREROLL.as_secs_f64()))
     }
 
-    pub fn move_clip(&mut self, track_index: usize, clip_index: usize, new_start: f64) -> anyhow::Result<()> {
-        if let Some(track) = self.tracks.get_mut(track_index) {
-            track.move_clip(clip_index, std::time::Duration::from_secs_f64(new_start));
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Track index {} out of bounds", track_index))
+    /// Play after `count_in_bars` of click (from the metronome config). Without a count-in
+    /// configured this is a plain `play`.
+    pub fn play_with_count_in(&mut self) {
+        let config = self.metronome.config();
+        let signature = self.transport.tempo.signature_at(self.transport.time());
+        let frames = config.count_in_bars as f64
+            * self.transport.tempo.frames_per_bar_in(signature, self.timeline_rate());
+        self.play();
+        if config.enabled && frames >= 1.0 {
+            self.count_in_remaining = frames.round() as u64;
+            self.count_in_elapsed = 0;
         }
     }
 
-    pub fn render(&mut self, out: &mut [f32], live_in: &[f32]) {
-        // 1. Always start with a silent buffer
-        out.fill(0.0);
+    pub fn is_counting_in(&self) -> bool {
+        self.transport.playing && self.count_in_remaining > 0
+    }
 
-        // 2. Only mix tracks and apply gain if we are playing
-        if self.transport.playing {
-            let channels = self.channels;
-            let frames = out.len() / channels;
+    /// Playing from a negative position: the transport waits at zero until the song starts.
+    pub fn is_leading_in(&self) -> bool {
+        self.transport.playing && self.lead_in_remaining > 0
+    }
 
-            self.mixer.begin_block(frames);
+    pub fn pause(&mut self) {
+        self.limiter_meter.set(0.0);
+        self.clock.bump_generation();
+        self.count_in_remaining = 0;
+        self.lead_in_remaining = 0;
+        self.metronome.reset();
+        if self.transport.playing {
+            self.end_automation_passes();
+        }
+        self.transport.playing = false;
+        for t in &mut self.tracks {
+            t.set_state(TrackState::Paused);
+        }
+    }
+
+    /// Timeline frame effect automation is written at, or `None` while nothing plays
+    /// (stopped, counting in, waiting out a lead-in).
+    pub fn automation_write_time(&self) -> Option<u64> {
+        let rolling = self.transport.playing && self.count_in_remaining == 0 && self.lead_in_remaining == 0;
+        rolling.then_some(self.transport.position.0)
+    }
+
+    /// Change how a track's insert lanes behave; a pass running on the track is closed
+    /// (leaving Write or Latch) or opened (entering Write) at the playhead.
+    pub fn set_automation_mode(&mut self, track_index: usize, mode: AutomationMode) -> anyhow::Result<()> {
+        let time = self.transport.position.0;
+        let playing = self.transport.playing;
+        let track = self
+            .tracks
+            .get_mut(track_index)
+            .ok_or_else(|| anyhow::anyhow!("Track index {} out of bounds", track_index))?;
+        if playing && mode != track.automation_mode {
+            track.inserts.end_automation_pass(time);
+            if mode == AutomationMode::Write {
+                track.inserts.begin_automation_pass(time);
+            }
+        }
+        track.automation_mode = mode;
+        Ok(())
+    }
+
+    fn begin_automation_passes(&mut self) {
+        let time = self.transport.position.0;
+        for t in self.tracks.iter_mut().filter(|t| t.automation_mode == AutomationMode::Write) {
+            t.inserts.begin_automation_pass(time);
+        }
+    }
+
+    fn end_automation_passes(&mut self) {
+        let time = self.transport.position.0;
+        for t in &mut self.tracks {
+            t.inserts.end_automation_pass(time);
+        }
+    }
+
+    /// Pause and return the playhead: to zero with `return_to_start_on_stop`, otherwise to
+    /// where the last play began. Stopping while stopped also goes back to zero.
+    pub fn stop(&mut self) {
+        let was_playing = self.transport.playing;
+        self.pause();
+        let target = if self.return_to_start_on_stop || !was_playing { Duration::ZERO } else { self.play_start };
+        self.seek(target);
+    }
+
+    /// Seek to `secs`, which may be negative (pre-roll from near the top of the song): the
+    /// transport then holds at zero for that long once playing, silent apart from the click.
+    pub fn seek_signed(&mut self, secs: f64) {
+        self.seek(Duration::from_secs_f64(secs.max(0.0)));
+        if secs < 0.0 {
+            self.lead_in_remaining = time::Seconds(-secs).to_frames(self.timeline_rate()).0;
+        }
+    }
+
+    pub fn seek(&mut self, pos: Duration) {
+        self.clock.bump_generation();
+        self.lead_in_remaining = 0;
+        // Passes can't span a jump: close them here and reopen at the new position
+        if self.transport.playing {
+            self.end_automation_passes();
+        }
+        self.transport.set_time(pos);
+        if self.transport.playing {
+            self.begin_automation_passes();
+        }
+        self.metronome.reset();
+        for t in &mut self.tracks {
+            t.seek(pos);
+        }
+    }
+
+    /// Fade everything out over `panic::PANIC_RAMP`, then pause and flush the decoders.
+    /// The monitor stays muted until `monitor_muted` is cleared explicitly.
+    pub fn panic(&mut self) {
+        self.monitor_muted = true;
+        self.stop_preview();
+        if self.transport.playing {
+            self.panic.trigger(self.sample_rate);
+        } else {
+            self.finish_panic();
+        }
+    }
+
+    fn finish_panic(&mut self) {
+        self.pause();
+        // Seeking in place drops whatever the decoders had buffered
+        let pos = self.transport.time();
+        self.seek(pos);
+        rt_warn!("🛑 Panic: output silenced, transport paused, monitor muted");
+    }
+
+    // --- RECORD ARM ---
+
+    /// Arm or disarm a track. Returns the ids of every armed track afterwards.
+    pub fn set_track_armed(&mut self, index: usize, armed: bool) -> Result<Vec<TrackId>, ArmError> {
+        let track = self.tracks.get(index).ok_or(ArmError::TrackNotFound { index })?;
+        if armed && track.record_safe {
+            return Err(ArmError::RecordSafe { track_id: track.id.0 });
+        }
+
+        if armed && self.arm_exclusive {
+            for t in &mut self.tracks {
+                t.armed = false;
+            }
+        }
+        self.tracks[index].armed = armed;
+        Ok(self.armed_tracks())
+    }
+
+    /// Marking a track record-safe also disarms it.
+    pub fn set_record_safe(&mut self, index: usize, safe: bool) -> Result<(), ArmError> {
+        let track = self.tracks.get_mut(index).ok_or(ArmError::TrackNotFound { index })?;
+        track.record_safe = safe;
+        if safe {
+            track.armed = false;
+        }
+        Ok(())
+    }
+
+    pub fn armed_tracks(&self) -> Vec<TrackId> {
+        self.tracks.iter().filter(|t| t.armed).map(|t| t.id).collect()
+    }
+
+    // --- HOVER PREVIEW ---
+
+    /// Start a preview voice; whatever was previewing fades out underneath it.
+    pub fn start_preview(&mut self, voice: preview::PreviewVoice) {
+        self.stop_preview();
+        self.previews.push(voice);
+    }
+
+    pub fn stop_preview(&mut self) {
+        for voice in &mut self.previews {
+            voice.stop();
+        }
+    }
+
+    pub fn is_previewing(&self) -> bool {
+        self.previews.iter().any(|v| !v.is_finished())
+    }
+
+    // Previews into the master; finished voices go to the reaper rather than being dropped here
+    fn mix_previews(&mut self, out: &mut [f32]) {
+        for voice in &mut self.previews {
+            voice.mix_into(out);
+        }
+        let mut i = 0;
+        while i < self.previews.len() {
+            if !self.previews[i].is_finished() {
+                i += 1;
+                continue;
+            }
+            let voice = self.previews.swap_remove(i);
+            if let Some(voice) = self.preview_reaper.retire(voice) {
+                self.previews.push(voice); // the reaper is behind: next block
+                break;
+            }
+        }
+    }
+
+    /// A take is being recorded (or not). Starting one cuts any hover preview, which must
+    /// not bleed into the take through the speakers.
+    pub fn set_recording(&mut self, recording: bool) {
+        if recording {
+            self.stop_preview();
+        }
+        self.recording = recording;
+    }
+
+    // --- LATENCY CALIBRATION ---
+
+    /// Play a click at the start of the next block, on every channel, after every gain.
+    pub fn request_latency_ping(&mut self) {
+        self.ping_pending = true;
+        self.ping_sent = None;
+    }
+
+    /// When the requested click was rendered, once it has been.
+    pub fn take_latency_ping(&mut self) -> Option<std::time::Instant> {
+        self.ping_sent.take()
+    }
+
+    // 2 ms of 1 kHz at -6 dB: short enough to time, long enough for a speaker to reproduce
+    fn render_latency_ping(&mut self, out: &mut [f32]) {
+        let channels = self.channels.max(1);
+        let sr = self.sample_rate.max(1) as f32;
+        let burst = (0.002 * sr) as usize;
+        for (i, frame) in out.chunks_mut(channels).take(burst).enumerate() {
+            let s = 0.5 * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / sr).sin();
+            frame.iter_mut().for_each(|x| *x += s);
+        }
+        self.ping_pending = false;
+        self.ping_sent = Some(std::time::Instant::now());
+    }
+
+    // --- LISTEN (PFL / AFL) ---
+
+    /// Put a track in listen; any other listening track is released. `Off` releases this one.
+    pub fn set_track_listen(&mut self, index: usize, mode: track::ListenMode) -> anyhow::Result<()> {
+        if index >= self.tracks.len() {
+            return Err(anyhow::anyhow!("Track index {} out of bounds", index));
+        }
+        for (i, t) in self.tracks.iter_mut().enumerate() {
+            t.listen = if i == index { mode } else { track::ListenMode::Off };
+        }
+        Ok(())
+    }
+
+    /// Whether PFL/AFL reaches an output: the cue's own outputs, or the speakers with
+    /// `cue_on_speakers`. Otherwise the listen signal is rendered but nobody hears it.
+    pub fn listen_audible(&self) -> bool {
+        self.output_routing.cue_routed() || self.cue_on_speakers
+    }
+
+    /// The listening track and its mode, if any.
+    pub fn listening(&self) -> Option<(TrackId, track::ListenMode)> {
+        self.tracks.iter().find(|t| t.listen != track::ListenMode::Off).map(|t| (t.id, t.listen))
+    }
+
+    // --- NAVIGATION ---
+    // Each returns the position seeked to (None = nothing in that direction, playhead unchanged).
+
+    pub fn next_marker(&self) -> Option<Duration> {
+        self.markers.next_after(self.transport.time(), navigation::NAV_TOLERANCE)
+    }
+
+    pub fn previous_marker(&self) -> Option<Duration> {
+        self.markers.previous_before(self.transport.time(), navigation::NAV_TOLERANCE)
+    }
+
+    pub fn next_edit_point(&mut self) -> Option<Duration> {
+        self.edit_points.next_after(&self.tracks, self.transport.time())
+    }
+
+    pub fn previous_edit_point(&mut self) -> Option<Duration> {
+        self.edit_points.previous_before(&self.tracks, self.transport.time())
+    }
+
+    pub fn seek_to_next_marker(&mut self) -> Option<Duration> {
+        let target = self.next_marker()?;
+        self.seek(target);
+        Some(target)
+    }
+
+    pub fn seek_to_previous_marker(&mut self) -> Option<Duration> {
+        let target = self.previous_marker()?;
+        self.seek(target);
+        Some(target)
+    }
+
+    pub fn seek_to_next_edit_point(&mut self) -> Option<Duration> {
+        let target = self.next_edit_point()?;
+        self.seek(target);
+        Some(target)
+    }
+
+    pub fn seek_to_previous_edit_point(&mut self) -> Option<Duration> {
+        let target = self.previous_edit_point()?;
+        self.seek(target);
+        Some(target)
+    }
+
+    /// Move a clip to `new_start`, snapped to the grid; returns where it landed.
+    pub fn move_clip(&mut self, track_index: usize, clip_index: usize, new_start: f64) -> anyhow::Result<f64> {
+        self.ensure_not_frozen(track_index)?;
+        self.content_changed();
+        let position = self.transport.time();
+        let new_start = self.snap_time(new_start);
+        if let Some(track) = self.tracks.get_mut(track_index) {
+            track.move_clip(clip_index, std::time::Duration::from_secs_f64(new_start), position);
+            Ok(new_start)
+        } else {
+            Err(anyhow::anyhow!("Track index {} out of bounds", track_index))
+        }
+    }
+
+    /// Move a clip's left edge to `new_start_secs`; the audio stays put on the timeline.
+    pub fn trim_clip_start(&mut self, track_index: usize, clip_index: usize, new_start_secs: f64) -> anyhow::Result<()> {
+        self.ensure_not_frozen(track_index)?;
+        self.content_changed();
+        let position = self.transport.time();
+        let track = self
+            .tracks
+            .get_mut(track_index)
+            .ok_or_else(|| anyhow::anyhow!("Track index {} out of bounds", track_index))?;
+        track.trim_clip_start(clip_index, Duration::from_secs_f64(new_start_secs.max(0.0)), position)
+    }
+
+    /// Time stretch a clip to `ratio` (timeline length / source length) from its current start.
+    pub fn set_clip_stretch(&mut self, track_index: usize, clip_index: usize, ratio: f64) -> anyhow::Result<()> {
+        self.ensure_not_frozen(track_index)?;
+        self.content_changed();
+        let position = self.transport.time();
+        let track = self
+            .tracks
+            .get_mut(track_index)
+            .ok_or_else(|| anyhow::anyhow!("Track index {} out of bounds", track_index))?;
+        let clip = track.clips.get(clip_index).ok_or_else(|| anyhow::anyhow!("Clip index out of bounds"))?;
+        let bounds = clip.with_stretch(ratio)?;
+        track.set_clip_bounds(clip_index, bounds, position)
+    }
+
+    /// Move a clip's right edge to `new_end_secs`.
+    pub fn trim_clip_end(&mut self, track_index: usize, clip_index: usize, new_end_secs: f64) -> anyhow::Result<()> {
+        self.ensure_not_frozen(track_index)?;
+        self.content_changed();
+        let position = self.transport.time();
+        let track = self
+            .tracks
+            .get_mut(track_index)
+            .ok_or_else(|| anyhow::anyhow!("Track index {} out of bounds", track_index))?;
+        track.trim_clip_end(clip_index, Duration::from_secs_f64(new_end_secs.max(0.0)), position)
+    }
+
+    /// Crossfade length where a clip overlaps the one before it; `None` fades over the whole overlap.
+    pub fn set_clip_crossfade(&mut self, track_index: usize, clip_index: usize, length: Option<Duration>) -> anyhow::Result<()> {
+        self.ensure_not_frozen(track_index)?;
+        self.content_changed();
+        let track = self
+            .tracks
+            .get_mut(track_index)
+            .ok_or_else(|| anyhow::anyhow!("Track index {} out of bounds", track_index))?;
+        track.set_clip_crossfade(clip_index, length)
+    }
+
+    /// Project latency in frames: the largest insert latency of any track plus that of the
+    /// most latent aux bus chain, the master pitch shifter and the master soft clipper.
+    pub fn latency_frames(&self) -> usize {
+        self.track_latency_frames()
+            + self.mixer.bus_latency_frames()
+            + self.master_pitch.latency_frames()
+            + self.master_clip.latency_frames()
+    }
+
+    fn track_latency_frames(&self) -> usize {
+        self.tracks.iter().map(|t| t.latency_frames()).max().unwrap_or(0)
+    }
+
+    // Plus-delay-only PDC: every track waits for the most latent one, then every bus
+    // return (and the dry mix) for the most latent bus chain.
+    fn update_delay_compensation(&mut self) {
+        let max_latency = self.track_latency_frames();
+        for track in &mut self.tracks {
+            let own = track.latency_frames();
+            track.set_pdc_delay_frames(max_latency - own);
+        }
+        self.mixer.update_delay_compensation();
+    }
+
+    pub fn render(&mut self, out: &mut [f32], live_in: &[f32]) {
+        // A count-in or lead-in running out inside this block: render up to its last frame,
+        // then the song from there, so the song starts on the frame and not a block late
+        let waiting_frames = if self.is_counting_in() {
+            self.count_in_remaining
+        } else if self.is_leading_in() {
+            self.lead_in_remaining
+        } else {
+            0
+        };
+        if waiting_frames > 0 && waiting_frames < (out.len() / self.channels.max(1)) as u64 {
+            let split = waiting_frames as usize * self.channels.max(1);
+            let (head, tail) = out.split_at_mut(split);
+            let (live_head, live_tail) = live_in.split_at(split.min(live_in.len()));
+            self.render(head, live_head);
+            self.render(tail, live_tail);
+            return;
+        }
+
+        // 1. Always start with a silent buffer
+        out.fill(0.0);
+
+        // The monitored input goes through the armed track's EQ and compressor
+        let mut live_bus = std::mem::take(&mut self.live_bus);
+        live_bus.clear();
+        live_bus.extend_from_slice(live_in);
+        if self.cue_active {
+            let armed = self.tracks.iter().find(|t| t.armed);
+            self.input_fx.process(&mut live_bus, self.channels, armed);
+        }
+        let live_in = &live_bus[..];
+
+        let block_start = self.transport.time();
+        let cue_routed = self.output_routing.cue_routed();
+        let listening = self.tracks.iter().any(|t| t.listen != track::ListenMode::Off);
+        self.cue_bus.clear();
+        self.cue_bus.resize(out.len(), 0.0);
+        for (_, buf, _) in &mut self.direct_outs {
+            buf.clear();
+            buf.resize(out.len(), 0.0);
+        }
+
+        // During a count-in (and a lead-in before zero) only the click and the monitored input are heard
+        let counting_in = self.is_counting_in();
+        let leading_in = !counting_in && self.is_leading_in();
+        if counting_in {
+            self.render_count_in(out, live_in, cue_routed);
+        } else if leading_in {
+            self.render_lead_in(out, live_in, cue_routed);
+        }
+        let waiting = counting_in || leading_in;
+        let rendering = self.transport.playing && !waiting;
+
+        // Tracks that don't render this block report silence
+        self.block_peaks.clear();
+        if self.block_callback.is_some() {
+            self.block_peaks.extend(self.tracks.iter().map(|t| hooks::TrackPeak { track_id: t.id.0, ..Default::default() }));
+        }
+
+        // 2. Only mix tracks and apply gain if we are playing
+        if rendering {
+            let channels = self.channels;
+            let frames = out.len() / channels;
+
+            self.mixer.begin_block(frames);
 
             let current_pos = self.transport.position;
+            debug_assert_eq!(self.transport.rate(), self.timeline_rate());
             let sr = self.sample_rate;
+            let timeline_sr = self.timeline_rate();
+
+            // Transport position stays the musical position; the delay lives in the tracks
+            self.update_delay_compensation();
 
             // --- NON-DESTRUCTIVE SOLO LOGIC ---
-            let any_solo = self.tracks.iter().any(|t| t.solo);
+            let any_solo = self.any_solo();
+            let policy = self.solo_policy;
+            let mut hottest: Option<(TrackId, f32)> = None; // blamed for overs on the master
 
-            for track in &mut self.tracks {
-                let is_audible = if any_solo {
-                    track.solo
-                } else {
-                    !track.muted
-                };
+            for (index, track) in self.tracks.iter_mut().enumerate() {
+                let (group_muted, group_solo) = self.mixer.group_mute_solo(track.group);
+                let is_audible = policy.is_audible(track.muted || group_muted, track.solo || group_solo, any_solo && !track.solo_safe);
 
                 let effectively_audible = is_audible && track.gain > 0.001;
 
+                let tap = self.track_taps.iter_mut().find(|(id, _)| *id == track.id).map(|(_, t)| t);
+                let target = spectrum::SpectrumTarget::Track(track.id);
+                let analyzer = self.analyzers.iter_mut().find(|(t, _)| *t == target).map(|(_, a)| a);
                 if matches!(track.state(), TrackState::Playing) {
-                     self.mixer.render_track(
-                        track, 
-                        frames, 
-                        channels, 
-                        current_pos,
-                        sr, 
-                        effectively_audible);
+                    // Tracks with a direct out bypass the master
+                    match self.direct_outs.iter_mut().find(|(id, _, _)| *id == track.id) {
+                        Some((_, direct, _)) => self.mixer.render_track_direct(
+                            track,
+                            frames,
+                            channels,
+                            current_pos,
+                            timeline_sr,
+                            effectively_audible,
+                            direct),
+                        None => self.mixer.render_track(
+                            track, 
+                            frames, 
+                            channels, 
+                            current_pos,
+                            timeline_sr, 
+                            effectively_audible),
+                    }
+                    // Sends feed the buses whether or not the track goes to the master
+                    self.mixer.send_last_track(&mut track.sends, frames * channels);
+                    if let Some(tap) = tap {
+                        tap.push(self.mixer.last_track_output(frames * channels));
+                    }
+                    if let Some(analyzer) = analyzer {
+                        analyzer.process_block(self.mixer.last_track_output(frames * channels), channels);
+                    }
+                    if let Some(slot) = self.block_peaks.get_mut(index) {
+                        *slot = hooks::TrackPeak::of_block(track.id.0, self.mixer.last_track_output(frames * channels), channels);
+                    }
+                    if effectively_audible {
+                        let peak = self.mixer.last_track_output(frames * channels).iter().fold(0.0f32, |m, s| m.max(s.abs()));
+                        if hottest.is_none_or(|(_, p)| peak > p) {
+                            hottest = Some((track.id, peak));
+                        }
+                    }
+                } else {
+                    if let Some(tap) = tap {
+                        tap.push_silence(frames * channels);
+                    }
+                    if let Some(analyzer) = analyzer {
+                        analyzer.push_silence(frames);
+                    }
                 }
             }
 
+            self.mixer.mix_groups();
+            self.mixer.mix_buses();
+            // Direct outs skip the buses but wait for their returns like the dry mix, so they
+            // stay aligned with the master (silent blocks too, to keep the lines running)
+            let bus_latency = self.mixer.bus_latency_frames();
+            for (_, buf, delay) in &mut self.direct_outs {
+                delay.set_delay_frames(bus_latency);
+                delay.process(buf);
+            }
             self.mixer.mix_into(out, channels);
+            self.master_pitch.process_block(out, channels);
+            self.clip_detect.scan(out, current_pos.to_seconds(timeline_sr).0, hottest.map(|(id, _)| id));
+            // Hover previews join here, so the limiter and a panic ramp catch them too
+            self.mix_previews(out);
+            self.master_clip.process_block(out, channels);
+
+            // Listen signal (already picked off inside the track), replacing the cue mix
+            self.listen_bus.clear();
+            if let Some(t) = self.tracks.iter().find(|t| t.listen != track::ListenMode::Off) {
+                self.listen_bus.extend_from_slice(t.listen_buffer());
+            }
+            self.listen_bus.resize(out.len(), 0.0);
 
-            // --- NEW: Add Live Monitor Audio BEFORE Master Gain ---
-            for (i, sample) in out.iter_mut().enumerate() {
-                *sample += live_in[i];
+            // The performer's mix is always built on its own copy, so the blend never
+            // reaches the master
+            if listening {
+                self.cue_bus.copy_from_slice(&self.listen_bus);
+            } else {
+                self.cue_bus.copy_from_slice(out);
+                if !self.monitor_muted {
+                    self.cue.mix(&mut self.cue_bus, live_in, channels, self.cue_active);
+                }
             }
+            self.sum_monitor_into_master(out, live_in, cue_routed);
 
-            // Apply Master Gain
-            if (self.master_gain - 1.0).abs() > 0.001 {
+            // Apply Master Gain, ramped so fader moves don't zipper
+            self.master_gain_smooth.set_target(self.master_gain, smoothing::ramp_frames(sr));
+            if self.master_gain_smooth.is_ramping() {
+                for frame in out.chunks_mut(channels.max(1)) {
+                    let g = self.master_gain_smooth.next();
+                    frame.iter_mut().for_each(|sample| *sample *= g);
+                }
+            } else if (self.master_gain - 1.0).abs() > 0.001 {
                 for sample in out.iter_mut() {
                     *sample *= self.master_gain;
                 }
             }
 
-            // Advance Transport Time
-            let secs = frames as f64 / self.sample_rate as f64;
-            self.transport.position += Duration::from_secs_f64(secs);
+            // Nothing after this may push the master past the ceiling
+            self.limit_master(out);
+
+            // Panic ramp scales every bus, after all gains
+            let mut panic_done = false;
+            if self.panic.is_active() {
+                if self.panic_gains.len() < frames {
+                    self.panic_gains.resize(frames, 1.0); // only for blocks longer than any before
+                }
+                let gains = &mut self.panic_gains[..frames];
+                panic_done = self.panic.advance(gains);
+                panic::apply_gains(out, gains, channels);
+                panic::apply_gains(&mut self.cue_bus, gains, channels);
+                panic::apply_gains(&mut self.listen_bus, gains, channels);
+                for (_, buf, _) in &mut self.direct_outs {
+                    panic::apply_gains(buf, gains, channels);
+                }
+            }
+
+            if let Some(tap) = self.master_tap.as_mut() {
+                tap.push(out);
+            }
+
+            // Click after the tap, so a realtime bounce never prints it
+            if !self.panic.is_active() && self.metronome.is_active(self.recording, false) {
+                let pos = current_pos.0;
+                self.render_click(out, cue_routed, pos);
+            }
+
+            // Advance Transport Time (in frames, so long sessions don't drift)
+            self.transport.position += time::Frames(frames as u64);
+
+            if panic_done {
+                self.finish_panic();
+            } else if !self.recording {
+                let end = self.cached_project_end();
+                if block_start < end && self.transport.time() >= end {
+                    self.reach_project_end(end);
+                }
+            }
+        }
+
+        // Hover previews play without the transport too, still through the limiter
+        if !rendering && !self.previews.is_empty() {
+            self.mix_previews(out);
+            self.limit_master(out);
         }
 
         // 3. ALWAYS process meter (Ultra-Clean Architecture)
         // If playing = true, it measures the real audio.
         // If playing = false, it measures the 0.0 buffer and gracefully decays to -inf.
         self.master_meter_state.process_block(out, self.channels, &self.master_meter);
+        self.loudness_meter.process_block(out, &self.loudness);
+        self.correlation_meter.process_block(out, self.channels, &self.correlation);
+        if let Some((_, analyzer)) = self.analyzers.iter_mut().find(|(t, _)| *t == spectrum::SpectrumTarget::Master) {
+            analyzer.process_block(out, self.channels);
+        }
+
+        // Asked for: the speakers play the cue mix (the listen signal while a track is in
+        // listen, see `listen_audible`); the master above is still the real mix
+        if !cue_routed && self.cue_on_speakers && self.transport.playing {
+            out.copy_from_slice(&self.cue_bus);
+        }
+
+        // 4. Host hook, last so it sees the finished block
+        if self.block_callback.is_some() {
+            self.run_block_callback(block_start, out.len() / self.channels.max(1));
+        }
+
+        if self.ping_pending {
+            self.render_latency_ping(out);
+        }
+
+        // 5. Timing pair for the UI playhead
+        let position = self.transport.position.rescale(self.transport.rate(), self.sample_rate);
+        let playing = self.transport.playing && !waiting;
+        self.clock.publish(out.len() / self.channels.max(1), position.0, self.sample_rate, self.playback_rate, playing);
+        self.live_bus = live_bus;
+    }
+
+    // Count-in block: click and monitor only, the song position holds. `render` splits the
+    // block the count-in ends in, so this never runs past it. The clock's generation carries
+    // on: the song starts where the count-in held the playhead.
+    fn render_count_in(&mut self, out: &mut [f32], live_in: &[f32], cue_routed: bool) {
+        let channels = self.channels;
+        let frames = (out.len() / channels.max(1)) as u64;
+
+        if !self.monitor_muted {
+            self.cue.mix(&mut self.cue_bus, live_in, channels, self.cue_active);
+        }
+        self.sum_monitor_into_master(out, live_in, cue_routed);
+        if self.metronome.is_active(self.recording, true) {
+            self.render_count_in_click(out, cue_routed);
+        }
+
+        self.count_in_elapsed += frames;
+        self.count_in_remaining = self.count_in_remaining.saturating_sub(frames);
+    }
+
+    fn render_lead_in(&mut self, out: &mut [f32], live_in: &[f32], cue_routed: bool) {
+        let channels = self.channels;
+        let frames = (out.len() / channels.max(1)) as u64;
+
+        if !self.monitor_muted {
+            self.cue.mix(&mut self.cue_bus, live_in, channels, self.cue_active);
+        }
+        self.sum_monitor_into_master(out, live_in, cue_routed);
+        if self.metronome.is_active(self.recording, false) {
+            // Bar 1's meter carried back before zero; counting from a whole number of bars
+            // earlier keeps the frames positive and the downbeats in place
+            let (to_master, to_cue) = self.click_targets(cue_routed);
+            let signature = self.transport.tempo.signature;
+            let frames_per_bar = self.transport.tempo.frames_per_bar_in(signature, self.timeline_rate());
+            let frames_per_beat = self.transport.tempo.frames_per_beat_in(signature, self.timeline_rate());
+            let bars = (self.lead_in_remaining as f64 / frames_per_bar.max(1.0)).ceil();
+            let first_frame = ((bars * frames_per_bar).round() as u64).saturating_sub(self.lead_in_remaining);
+            self.metronome.render(out, &mut self.cue_bus, to_master, to_cue, channels, first_frame, |f| {
+                time::next_beat_in_meter(f, frames_per_beat, signature.numerator)
+            });
+        }
+
+        self.lead_in_remaining = self.lead_in_remaining.saturating_sub(frames);
+    }
+
+    // The brickwall and its meter, the last stage of the master (only the panic ramp follows)
+    fn limit_master(&mut self, out: &mut [f32]) {
+        self.master_limiter.process_block(out, self.channels);
+        self.limiter_meter.set(self.master_limiter.gain_reduction_db());
+    }
+
+    // The monitored input at unity on the master, when the speakers are what the performer
+    // hears and the cue mix isn't taking them over. The blend never applies here.
+    fn sum_monitor_into_master(&self, out: &mut [f32], live_in: &[f32], cue_routed: bool) {
+        if cue_routed || self.cue_on_speakers || self.monitor_muted || !self.cue_active {
+            return;
+        }
+        for (o, l) in out.iter_mut().zip(live_in) {
+            *o += *l;
+        }
+    }
+
+    // With the cue mix on the speakers a click goes to the cue as well, so it is still heard
+    fn click_targets(&self, cue_routed: bool) -> (bool, bool) {
+        match self.metronome.targets(cue_routed) {
+            (to_master, _) if !cue_routed && self.cue_on_speakers => (to_master, to_master),
+            targets => targets,
+        }
+    }
+
+    // Clicks for the block starting at song frame `first_frame`
+    fn render_click(&mut self, out: &mut [f32], cue_routed: bool, first_frame: u64) {
+        let (to_master, to_cue) = self.click_targets(cue_routed);
+        let (tempo, sr) = (&self.transport.tempo, self.timeline_rate());
+        self.metronome.render(out, &mut self.cue_bus, to_master, to_cue, self.channels, first_frame, |f| {
+            tempo.next_beat(f, sr)
+        });
+    }
+
+    // Count-in clicks: whole bars in the meter at the play position
+    fn render_count_in_click(&mut self, out: &mut [f32], cue_routed: bool) {
+        let (to_master, to_cue) = self.click_targets(cue_routed);
+        let signature = self.transport.tempo.signature_at(self.transport.time());
+        let frames_per_beat = self.transport.tempo.frames_per_beat_in(signature, self.timeline_rate());
+        self.metronome.render(out, &mut self.cue_bus, to_master, to_cue, self.channels, self.count_in_elapsed, |f| {
+            time::next_beat_in_meter(f, frames_per_beat, signature.numerator)
+        });
+    }
+
+    // Peaks were taken from each track's output in the render loop above
+    fn run_block_callback(&mut self, start: Duration, frames: usize) {
+        let info = hooks::BlockInfo {
+            start,
+            frames,
+            playing: self.transport.playing,
+            track_peaks: &self.block_peaks,
+        };
+        let Some(callback) = self.block_callback.as_mut() else { return };
+
+        // Never let a host bug take down the audio thread
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| callback(info)));
+        if result.is_err() {
+            rt_error!("❌ Block callback panicked; it has been removed");
+            self.block_callback = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use output_routing::OutputBus;
+
+    const BLOCK: usize = 256;
+
+    // Playing, monitoring a steady input of 0.2 with the cue blend at (input 0.5, playback 0.25)
+    fn monitoring_engine() -> Engine {
+        let mut eng = Engine::new(48_000, 2);
+        eng.cue_active = true;
+        eng.cue.set_levels(0.5, 0.25);
+        eng.play();
+        eng
+    }
+
+    // Second block, once the blend has ramped to its target
+    fn render_twice(eng: &mut Engine) -> Vec<f32> {
+        let live = vec![0.2f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        eng.render(&mut out, &live);
+        eng.render(&mut out, &live);
+        out
+    }
+
+    fn all_near(buf: &[f32], value: f32) -> bool {
+        buf.iter().all(|s| (s - value).abs() < 1e-6)
+    }
+
+    #[test]
+    fn blend_never_reaches_the_speakers_unless_asked() {
+        let mut eng = monitoring_engine();
+        let out = render_twice(&mut eng);
+        // Monitored input at unity on the master; the blend only shapes the cue
+        assert!(all_near(&out, 0.2));
+        assert!(all_near(eng.bus_buffer(OutputBus::Cue).unwrap(), 0.1));
+    }
+
+    #[test]
+    fn cue_on_speakers_plays_the_blend() {
+        let mut eng = monitoring_engine();
+        eng.cue_on_speakers = true;
+        let out = render_twice(&mut eng);
+        assert!(all_near(&out, 0.1));
+    }
+
+    #[test]
+    fn block_callback_sees_each_blocks_own_track_peak() {
+        let path = crate::test_util::wav("block_peak", 48_000, 1, &[0.25; 48_000]);
+
+        let mut eng = Engine::new(48_000, 2);
+        eng.add_track(path.clone()).unwrap();
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        eng.set_block_callback(Box::new(move |info| {
+            *sink.lock().unwrap() = info.track_peaks.to_vec();
+        }));
+        eng.play();
+
+        // Clips stream from a decoder thread: wait until the track is sounding
+        let live = vec![0.0f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        loop {
+            eng.render(&mut out, &live);
+            if out.iter().all(|s| s.abs() > 0.0) {
+                // Past the fade-ins, one track at unity into the master: its block peak is the master's
+                for _ in 0..20 {
+                    eng.render(&mut out, &live);
+                }
+                let peak = seen.lock().unwrap()[0].peak_l;
+                let master = out.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+                assert!((peak - master).abs() < 1e-4, "{peak} vs {master}");
+                break;
+            }
+            assert!(std::time::Instant::now() < deadline, "track never started");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        // The very next block after stopping reads silence, not what the meters last held
+        eng.pause();
+        eng.render(&mut out, &live);
+        let peaks = seen.lock().unwrap().clone();
+        assert_eq!((peaks[0].peak_l, peaks[0].peak_r), (0.0, 0.0));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn transport_counts_whole_frames_at_44k1() {
+        let mut eng = Engine::new(44_100, 2);
+        eng.seek(Duration::from_secs_f64(0.1));
+        assert_eq!(eng.transport.position, time::Frames(4_410));
+        eng.play();
+        let live = vec![0.0f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        for _ in 0..1_000 {
+            eng.render(&mut out, &live);
+        }
+        assert_eq!(eng.transport.position, time::Frames(4_410 + 1_000 * BLOCK as u64));
+
+        // Varispeed counts at another rate, the playhead stays on the same frame
+        let before = eng.transport.time();
+        eng.set_playback_rate(0.5);
+        assert_eq!(eng.transport.rate(), 88_200);
+        eng.set_playback_rate(1.0);
+        assert_eq!(eng.transport.time(), before);
+    }
+
+    #[test]
+    fn routed_cue_keeps_the_master_dry() {
+        let mut eng = monitoring_engine();
+        eng.set_output_channels(4);
+        eng.set_bus_output_channels(OutputBus::Cue, 2, 3).unwrap();
+        let out = render_twice(&mut eng);
+        assert!(all_near(&out, 0.0));
+        assert!(all_near(eng.bus_buffer(OutputBus::Cue).unwrap(), 0.1));
+    }
+
+    #[test]
+    fn exclusive_arm_disarms_every_other_track() {
+        let mut eng = Engine::new(48_000, 2);
+        let a = eng.add_empty_track();
+        let b = eng.add_empty_track();
+        let c = eng.add_empty_track();
+
+        // Without exclusive mode arms accumulate
+        assert_eq!(eng.set_track_armed(0, true).unwrap(), vec![a]);
+        assert_eq!(eng.set_track_armed(1, true).unwrap(), vec![a, b]);
+
+        eng.arm_exclusive = true;
+        assert_eq!(eng.set_track_armed(2, true).unwrap(), vec![c]);
+        // Disarming in exclusive mode leaves the others alone
+        assert_eq!(eng.set_track_armed(0, false).unwrap(), vec![c]);
+        assert_eq!(eng.armed_tracks(), vec![c]);
+    }
+
+    #[test]
+    fn record_safe_tracks_cannot_be_armed() {
+        let mut eng = Engine::new(48_000, 2);
+        let a = eng.add_empty_track();
+        let b = eng.add_empty_track();
+        eng.set_track_armed(0, true).unwrap();
+
+        // Marking an armed track record-safe disarms it
+        eng.set_record_safe(0, true).unwrap();
+        assert!(eng.armed_tracks().is_empty());
+        assert_eq!(eng.set_track_armed(0, true), Err(ArmError::RecordSafe { track_id: a.0 }));
+
+        // Exclusive arming elsewhere is unaffected, and a refused arm disarms nothing
+        eng.arm_exclusive = true;
+        assert_eq!(eng.set_track_armed(1, true).unwrap(), vec![b]);
+        assert!(eng.set_track_armed(0, true).is_err());
+        assert_eq!(eng.armed_tracks(), vec![b]);
+
+        assert_eq!(eng.set_track_armed(5, true), Err(ArmError::TrackNotFound { index: 5 }));
+    }
+
+    // One-second mono clip file, for tests that only care where clips sit
+    fn second_wav(name: &str) -> String {
+        tiny_wav(name, 48_000, 48_000)
+    }
+
+    fn secs(gaps: Vec<(Duration, Duration)>) -> Vec<(f64, f64)> {
+        gaps.into_iter().map(|(a, b)| ((a.as_secs_f64() * 1e4).round() / 1e4, (b.as_secs_f64() * 1e4).round() / 1e4)).collect()
+    }
+
+    #[test]
+    fn touching_and_overlapping_clips_leave_no_gap() {
+        let wav = second_wav("gaps");
+        let mut eng = Engine::new(48_000, 2);
+        eng.add_empty_track();
+        eng.add_empty_track();
+        // Track 0: butt splice at 1 s, then a clip at 3 s. Track 1 overlaps the splice and
+        // touches the 3 s clip's end.
+        eng.add_clip(0, wav.clone(), 0.0).unwrap();
+        eng.add_clip(0, wav.clone(), 1.0).unwrap();
+        eng.add_clip(0, wav.clone(), 3.0).unwrap();
+        eng.add_clip(1, wav.clone(), 0.5).unwrap();
+        eng.add_clip(1, wav.clone(), 4.0).unwrap();
+        eng.add_clip(1, wav.clone(), 6.0).unwrap();
+        let _ = std::fs::remove_file(&wav);
+
+        // Edge fades only widen the real gaps, at the outer edges of each run of clips
+        assert_eq!(secs(eng.find_gaps_with(Duration::from_millis(50), true)), vec![(1.995, 3.005), (4.995, 6.005)]);
+        assert_eq!(secs(eng.find_gaps_with(Duration::from_millis(50), false)), vec![(2.0, 3.0), (5.0, 6.0)]);
+        assert!(eng.find_gaps_with(Duration::from_millis(1500), true).is_empty());
+    }
+
+    #[test]
+    fn gaps_ignore_inaudible_tracks() {
+        let wav = second_wav("gaps_mute");
+        let mut eng = Engine::new(48_000, 2);
+        eng.add_empty_track();
+        eng.add_empty_track();
+        eng.add_clip(0, wav.clone(), 0.0).unwrap();
+        eng.add_clip(0, wav.clone(), 2.0).unwrap();
+        eng.add_clip(1, wav.clone(), 1.0).unwrap(); // fills track 0's gap
+        let _ = std::fs::remove_file(&wav);
+
+        assert!(eng.find_gaps_with(Duration::from_millis(50), true).is_empty());
+        eng.tracks_mut()[1].muted = true;
+        assert_eq!(secs(eng.find_gaps_with(Duration::from_millis(50), false)), vec![(1.0, 2.0)]);
+    }
+
+    #[test]
+    fn silence_before_the_first_clip_is_a_gap() {
+        let wav = second_wav("gaps_leading");
+        let mut eng = Engine::new(48_000, 2);
+        eng.add_empty_track();
+        eng.add_clip(0, wav.clone(), 2.0).unwrap();
+        let _ = std::fs::remove_file(&wav);
+
+        assert_eq!(secs(eng.find_gaps_with(Duration::from_millis(50), true)), vec![(0.0, 2.005)]);
+        assert_eq!(secs(eng.find_gaps_with(Duration::from_millis(50), false)), vec![(0.0, 2.0)]);
+    }
+
+    #[test]
+    fn an_empty_project_has_no_gaps() {
+        let mut eng = Engine::new(48_000, 2);
+        assert!(eng.find_gaps_with(Duration::from_millis(50), true).is_empty());
+        eng.add_empty_track();
+        assert!(eng.find_gaps_with(Duration::ZERO, true).is_empty());
+        assert!(eng.find_gaps_with(Duration::ZERO, false).is_empty());
+    }
+
+    #[test]
+    fn panic_fades_every_output_below_minus_80_dbfs_then_pauses() {
+        let path = crate::test_util::wav("panic", 48_000, 1, &[0.5; 96_000]);
+
+        let mut eng = Engine::new(48_000, 2);
+        eng.add_track(path.clone()).unwrap();
+        eng.play();
+        let live = vec![0.0f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        loop {
+            eng.render(&mut out, &live);
+            if out.iter().all(|s| s.abs() > 0.0) {
+                break;
+            }
+            assert!(std::time::Instant::now() < deadline, "track never started");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let _ = std::fs::remove_file(&path);
+
+        eng.panic();
+        assert!(eng.transport.playing, "the fade runs before the transport stops");
+        let ramp_frames = (panic::PANIC_RAMP.as_secs_f64() * 48_000.0).ceil() as usize;
+        let mut rendered = 0;
+        let mut last_peak = f32::MAX;
+        while eng.transport.playing {
+            eng.render(&mut out, &live);
+            rendered += BLOCK;
+            assert!(rendered < ramp_frames + BLOCK, "still playing after {} frames", rendered);
+            let peak = out.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            assert!(peak <= last_peak + 1e-6, "the fade never gets louder");
+            last_peak = peak;
+        }
+        // The block the ramp ended in finishes below -80 dBFS
+        let tail = out[out.len() - 2..].iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(tail < 1e-4, "{} dBFS at the end of the ramp", 20.0 * tail.log10());
+        assert!(eng.monitor_muted);
+
+        // And it stays there
+        for _ in 0..10 {
+            eng.render(&mut out, &live);
+            assert!(out.iter().all(|s| s.abs() < 1e-4));
+        }
+    }
+
+    #[test]
+    fn listen_takes_over_the_speakers_only_when_asked() {
+        let wav = second_wav("listen");
+        let mut eng = Engine::new(48_000, 2);
+        eng.add_track(wav.clone()).unwrap();
+        eng.tracks_mut()[0].gain = 0.0; // PFL still hears it, the master doesn't
+        eng.set_track_listen(0, track::ListenMode::Pfl).unwrap();
+        eng.play();
+        let live = vec![0.0f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while eng.tracks()[0].listen_buffer().iter().all(|&s| s == 0.0) {
+            assert!(std::time::Instant::now() < deadline, "track never started");
+            std::thread::sleep(Duration::from_millis(5));
+            eng.render(&mut out, &live);
+        }
+        let _ = std::fs::remove_file(&wav);
+        for _ in 0..20 {
+            eng.render(&mut out, &live); // past the fader's ramp down
+        }
+
+        // No cue outputs and not asked to: the speakers keep playing the master
+        assert!(!eng.listen_audible());
+        eng.render(&mut out, &live);
+        assert!(out.iter().all(|&s| s == 0.0));
+
+        eng.cue_on_speakers = true;
+        assert!(eng.listen_audible());
+        eng.render(&mut out, &live);
+        assert_eq!(out, eng.tracks()[0].listen_buffer());
+        assert!(out.iter().any(|&s| s != 0.0));
+    }
+
+    // Render until the preview sounds; false if it never did
+    fn render_until_previewing(eng: &mut Engine, out: &mut [f32], live: &[f32]) -> bool {
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while std::time::Instant::now() < deadline {
+            eng.render(out, live);
+            if out.iter().any(|&s| s != 0.0) {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        false
+    }
+
+    #[test]
+    fn recording_cuts_a_preview_and_the_voice_leaves_the_engine() {
+        let wav = second_wav("preview_rec");
+        let mut eng = Engine::new(48_000, 2);
+        eng.start_preview(preview::PreviewVoice::new(wav.clone(), 48_000, 1, Duration::ZERO, Duration::from_secs(1), 48_000, 2).unwrap());
+        let live = vec![0.0f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        assert!(render_until_previewing(&mut eng, &mut out, &live), "preview never started");
+        let _ = std::fs::remove_file(&wav);
+
+        eng.set_recording(true);
+        for _ in 0..4 {
+            eng.render(&mut out, &live); // the 5 ms fade
+        }
+        assert!(!eng.is_previewing());
+        assert!(eng.previews.is_empty(), "finished voices are handed to the reaper");
+        eng.render(&mut out, &live);
+        assert!(out.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn previews_go_through_the_limiter() {
+        // way over full scale, even at the preview's -6 dB
+        let wav = crate::test_util::wav("preview_hot", 48_000, 1, &[8.0; 48_000]);
+
+        let live = vec![0.0f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        for playing in [false, true] {
+            let mut eng = Engine::new(48_000, 2);
+            if playing {
+                eng.play();
+            }
+            eng.start_preview(preview::PreviewVoice::new(wav.clone(), 48_000, 1, Duration::ZERO, Duration::from_secs(1), 48_000, 2).unwrap());
+            assert!(render_until_previewing(&mut eng, &mut out, &live), "preview never started");
+            for _ in 0..20 {
+                eng.render(&mut out, &live);
+                assert!(out.iter().all(|s| s.abs() <= 1.0), "transport playing: {playing}");
+            }
+        }
+        let _ = std::fs::remove_file(&wav);
+    }
+
+    #[test]
+    fn render_publishes_a_monotonic_clock_and_seeks_start_a_generation() {
+        let mut eng = Engine::new(48_000, 2);
+        eng.add_empty_track();
+        let live = vec![0.0f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        eng.play();
+        let mut last = eng.clock.snapshot();
+        for _ in 0..50 {
+            eng.render(&mut out, &live);
+            let sync = eng.clock.snapshot();
+            assert_eq!(sync.frames_rendered, last.frames_rendered + BLOCK as u64);
+            assert!(sync.position_frames >= last.position_frames);
+            last = sync;
+        }
+
+        eng.seek(Duration::from_secs(3));
+        eng.render(&mut out, &live);
+        let sync = eng.clock.snapshot();
+        assert!(sync.generation > last.generation);
+        assert_eq!(sync.generation_start_frames, 3 * 48_000);
+    }
+
+    #[test]
+    fn fx_bypass_crossfades_instead_of_stepping() {
+        // Fully wet 500 ms delay: the fx path stays silent for a while, the dry path does not
+        let wav = second_wav("bypass_fade");
+        let mut eng = Engine::new(48_000, 2);
+        eng.add_track(wav.clone()).unwrap();
+        let delay = crate::effects::delay::DelayParams { is_active: true, time_ms: 500.0, feedback: 0.0, mix: 1.0 };
+        eng.tracks_mut()[0].inserts.add(&crate::effects::chain::InsertParams::Delay(delay), None).unwrap();
+        eng.tracks_mut()[0].fx_bypass = true;
+        eng.play();
+        let live = vec![0.0f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while out.iter().all(|&s| s == 0.0) {
+            assert!(std::time::Instant::now() < deadline, "track never started");
+            std::thread::sleep(Duration::from_millis(5));
+            eng.render(&mut out, &live);
+        }
+        let _ = std::fs::remove_file(&wav);
+
+        // Back through the delay: its line was idle while bypassed, so it goes quiet
+        eng.tracks_mut()[0].fx_bypass = false;
+        for _ in 0..10 {
+            eng.render(&mut out, &live);
+        }
+        assert!(out.iter().all(|&s| s.abs() < 1e-6));
+
+        eng.tracks_mut()[0].fx_bypass = true;
+        let mut left: Vec<f32> = Vec::new();
+        for _ in 0..4 {
+            eng.render(&mut out, &live);
+            left.extend(out.iter().step_by(2));
+        }
+        let level = *left.last().unwrap();
+        assert!(level > 0.01, "bypassed: the dry signal");
+        let ramp = smoothing::ramp_frames(48_000) as f32;
+        let steepest = left.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0f32, f32::max);
+        assert!(steepest < 2.0 * level / ramp, "step of {steepest} towards {level}");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn bypassed_fx_keep_their_latency() {
+        let wav = second_wav("bypass_latency");
+        // First audible frame of a clip at 1 s, with or without a latent insert (bypassed)
+        let first_sound = |latent: bool| {
+            let mut eng = Engine::new(48_000, 2);
+            eng.add_empty_track();
+            eng.add_clip(0, wav.clone(), 1.0).unwrap();
+            let mut latency = 0;
+            if latent {
+                let shift = crate::effects::pitch_shift::PitchShiftParams { is_active: true, ..Default::default() };
+                eng.tracks_mut()[0].inserts.add(&crate::effects::chain::InsertParams::PitchShift(shift), None).unwrap();
+                latency = eng.tracks()[0].latency_frames();
+                eng.tracks_mut()[0].fx_bypass = true;
+                assert_eq!(eng.tracks()[0].latency_frames(), latency, "bypassing keeps the latency");
+            }
+            eng.play();
+            let live = vec![0.0f32; BLOCK * 2];
+            let mut out = vec![0.0f32; BLOCK * 2];
+            let mut left: Vec<f32> = Vec::new();
+            while left.len() < 50_000 + latency {
+                std::thread::sleep(Duration::from_micros(500)); // the decoder has a second to fill up
+                eng.render(&mut out, &live);
+                left.extend(out.iter().step_by(2));
+            }
+            (left.iter().position(|s| s.abs() > 1e-6).unwrap(), latency)
+        };
+        let (dry, _) = first_sound(false);
+        let (bypassed, latency) = first_sound(true);
+        let _ = std::fs::remove_file(&wav);
+        assert!(latency > 0);
+        assert_eq!(bypassed, dry + latency);
+    }
+
+    fn tiny_wav(name: &str, frames: usize, sample_rate: u32) -> String {
+        crate::test_util::wav(&format!("engine_{}", name), sample_rate, 1, &vec![0.5; frames])
+    }
+
+    #[test]
+    fn the_finished_frame_matches_the_playhead_under_varispeed() {
+        let wav = tiny_wav("finished_varispeed", 10_007, 48_000);
+        let mut eng = Engine::new(48_000, 2);
+        eng.add_track(wav.clone()).unwrap();
+        eng.set_playback_rate(1.7); // the timeline counts coarser than the device
+        eng.end_action = EndAction::Stop;
+        eng.play();
+        let live = vec![0.0f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        for _ in 0..400 {
+            if !eng.transport.playing {
+                break;
+            }
+            eng.render(&mut out, &live);
+        }
+        // One more block publishes the stopped position
+        eng.render(&mut out, &live);
+        let _ = std::fs::remove_file(&wav);
+
+        let (count, finished_at) = eng.clock.finished();
+        assert_eq!(count, 1);
+        assert_eq!(finished_at, eng.clock.snapshot().position_frames);
+    }
+
+    #[test]
+    fn a_file_shorter_than_a_resampler_chunk_is_heard() {
+        let wav = tiny_wav("tiny_resampled", 40, 44_100); // under 1 ms, and not at the engine rate
+        let mut eng = Engine::new(48_000, 2);
+        eng.add_track(wav.clone()).unwrap();
+        let live = vec![0.0f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        let mut heard = false;
+        while !heard && std::time::Instant::now() < deadline {
+            // Restart from the top until the decoder has flushed the tail
+            eng.pause();
+            eng.seek(Duration::ZERO);
+            std::thread::sleep(Duration::from_millis(200));
+            eng.play();
+            eng.render(&mut out, &live);
+            heard = out.iter().any(|&s| s != 0.0);
+        }
+        let _ = std::fs::remove_file(&wav);
+        assert!(heard, "nothing of the tiny file came out");
+    }
+
+    #[test]
+    fn splitting_at_a_clip_edge_is_refused_with_a_structured_error() {
+        let wav = second_wav("split_edge");
+        let mut eng = Engine::new(48_000, 2);
+        eng.add_empty_track();
+        eng.add_clip(0, wav.clone(), 1.0).unwrap();
+        let _ = std::fs::remove_file(&wav);
+
+        for at in [1.0002, 1.9998] {
+            let err = eng.split_clip(0, at).unwrap_err();
+            assert!(matches!(err.downcast_ref::<track::ClipEditError>(), Some(track::ClipEditError::TooShort { .. })), "{err}");
+        }
+        assert_eq!(eng.tracks()[0].clips.len(), 1);
+        assert!(eng.trim_clip_end(0, 0, 1.0005).is_err());
+        assert_eq!(eng.tracks()[0].clips[0].length(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn random_edits_on_tiny_clips_never_panic_or_go_non_finite() {
+        use rand::{Rng, SeedableRng};
+        let tiny = tiny_wav("fuzz_tiny", 3, 44_100);
+        let short = tiny_wav("fuzz_short", 480, 48_000); // 10 ms
+        let mut eng = Engine::new(48_000, 2);
+        eng.add_empty_track();
+        eng.add_empty_track();
+        for i in 0..4 {
+            eng.add_clip(i % 2, if i % 2 == 0 { tiny.clone() } else { short.clone() }, i as f64 * 0.004).unwrap();
+        }
+        eng.play();
+        let live = vec![0.0f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(2504);
+
+        for _ in 0..2000 {
+            let track = rng.random_range(0..2);
+            let clips = eng.tracks()[track].clips.len();
+            let clip = rng.random_range(0..clips.max(1));
+            let at = rng.random_range(0.0..0.03);
+            // Refusals are fine; panics and broken clips are not
+            let _ = match rng.random_range(0..6) {
+                0 => eng.split_clip(track, at),
+                1 => eng.trim_clip_start(track, clip, at),
+                2 => eng.trim_clip_end(track, clip, at),
+                3 => eng.move_clip(track, clip, at).map(|_| ()),
+                4 => {
+                    eng.seek(Duration::from_secs_f64(at));
+                    Ok(())
+                }
+                _ if clips > 1 => eng.delete_clip(track, clip),
+                _ => Ok(()),
+            };
+            eng.render(&mut out, &live);
+            assert!(out.iter().all(|s| s.is_finite()));
+            for t in eng.tracks() {
+                assert!(t.clips.iter().all(|c| c.end() >= c.start()));
+            }
+        }
+        let _ = std::fs::remove_file(&tiny);
+        let _ = std::fs::remove_file(&short);
+    }
+
+    fn click_engine(mode: metronome::ClickMode) -> Engine {
+        let mut eng = Engine::new(48_000, 2);
+        eng.set_metronome_config(metronome::MetronomeConfig {
+            enabled: true,
+            mode,
+            count_in_bars: 1,
+            ..Default::default()
+        });
+        eng
+    }
+
+    // Whether the first block after `start` holds a click (an empty project is otherwise silent)
+    fn clicks(eng: &mut Engine, start: impl FnOnce(&mut Engine)) -> bool {
+        eng.pause();
+        eng.seek(Duration::ZERO);
+        start(eng);
+        let live = vec![0.0f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        eng.render(&mut out, &live);
+        out.iter().any(|s| s.abs() > 1e-3)
+    }
+
+    #[test]
+    fn each_click_mode_sounds_only_where_it_should() {
+        use metronome::ClickMode;
+        // (mode, count-in, playing, recording)
+        for (mode, expected) in [
+            (ClickMode::Always, [true, true, true]),
+            (ClickMode::RecordingOnly, [true, false, true]),
+            (ClickMode::CountInOnly, [true, false, false]),
+        ] {
+            let mut eng = click_engine(mode);
+            let heard = [
+                clicks(&mut eng, |e| e.play_with_count_in()),
+                clicks(&mut eng, |e| {
+                    e.set_recording(false);
+                    e.play()
+                }),
+                clicks(&mut eng, |e| {
+                    e.set_recording(true);
+                    e.play()
+                }),
+            ];
+            assert_eq!(heard, expected, "{mode:?}: (count-in, playing, recording)");
+        }
+    }
+
+    #[test]
+    fn a_disabled_metronome_never_clicks() {
+        let mut eng = click_engine(metronome::ClickMode::Always);
+        eng.set_metronome_config(metronome::MetronomeConfig { enabled: false, ..eng.metronome_config() });
+        assert!(!clicks(&mut eng, |e| e.play_with_count_in()));
+        assert!(!clicks(&mut eng, |e| e.play()));
+    }
+
+    #[test]
+    fn the_song_starts_on_the_exact_frame_the_count_in_ends() {
+        let mut eng = click_engine(metronome::ClickMode::CountInOnly);
+        eng.play_with_count_in();
+        let count_in = eng.count_in_remaining as usize;
+        // A block size that doesn't divide the bar, so the count-in ends mid-block
+        let block = 700;
+        assert_ne!(count_in % block, 0);
+        let live = vec![0.0f32; block * 2];
+        let mut out = vec![0.0f32; block * 2];
+        eng.render(&mut out, &live);
+        let generation = eng.clock.snapshot().generation;
+        for _ in 0..count_in / block {
+            eng.render(&mut out, &live);
+        }
+        assert!(!eng.is_counting_in());
+        assert_eq!(eng.transport.position.0 as usize, block - count_in % block);
+        assert_eq!(eng.clock.snapshot().generation, generation, "the count-in running out is no discontinuity");
+    }
+
+    #[test]
+    fn the_limiter_catches_master_gain_and_the_monitor() {
+        let wav = tiny_wav("limiter_last", 48_000, 48_000);
+        let mut eng = monitoring_engine();
+        eng.pause();
+        eng.add_track(wav.clone()).unwrap();
+        eng.master_gain = 2.0;
+        eng.play();
+        let live = vec![0.6f32; BLOCK * 2];
+        let mut out = vec![0.0f32; BLOCK * 2];
+        let mut peak = 0.0f32;
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        // Once the track is in and the fader has ramped up, the sum is well over full scale
+        let mut loud_blocks = 0;
+        while loud_blocks < 20 && std::time::Instant::now() < deadline {
+            eng.render(&mut out, &live);
+            let block_peak = out.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            if block_peak > 0.9 {
+                loud_blocks += 1;
+                peak = peak.max(block_peak);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let _ = std::fs::remove_file(&wav);
+        assert_eq!(loud_blocks, 20, "the mix never got loud");
+        assert!(peak <= 1.0 + 1e-4, "peak {peak} got past the limiter");
+        assert!(eng.limiter_meter.get() > 1.0, "the limiter should be working hard");
+    }
+}
@@ -0,0 +1,133 @@
+// src/engine/mute_regions.rs
+
+// Mute regions: stretches of a clip silenced without splitting it ("mute this chorus word").
+// They are clip-relative, so they travel with the clip when it moves. Each region ramps
+// down and back up inside its own bounds, so the audio around it is left untouched.
+
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::time::Seconds;
+
+/// Ramp at each edge of a region (from full level to silence).
+pub const MUTE_REGION_RAMP: Duration = Duration::from_millis(3);
+
+/// Seconds from the clip start (not the source file).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MuteRegion {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Sort, drop empty regions and merge overlapping or touching ones.
+pub fn normalize(regions: &mut Vec<MuteRegion>) {
+    regions.retain(|r| r.end > r.start);
+    regions.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let mut merged: Vec<MuteRegion> = Vec::with_capacity(regions.len());
+    for r in regions.drain(..) {
+        match merged.last_mut() {
+            Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+            _ => merged.push(r),
+        }
+    }
+    *regions = merged;
+}
+
+/// Add a region, merging it with any it overlaps.
+pub fn insert(regions: &mut Vec<MuteRegion>, region: MuteRegion) {
+    regions.push(region);
+    normalize(regions);
+}
+
+/// The parts of `regions` inside `from..to`, re-based so `from` becomes 0 (splits and trims).
+pub fn slice(regions: &[MuteRegion], from: f64, to: f64) -> Vec<MuteRegion> {
+    regions
+        .iter()
+        .filter(|r| r.end > from && r.start < to)
+        .map(|r| MuteRegion { start: r.start.max(from) - from, end: r.end.min(to) - from })
+        .collect()
+}
+
+/// Silence the regions in an interleaved block whose first frame is `first_frame` frames
+/// into the clip. Regions must be normalized (non-overlapping).
+pub fn apply(buf: &mut [f32], channels: usize, first_frame: u64, regions: &[MuteRegion], sample_rate: u32) {
+    let channels = channels.max(1);
+    let block_end = first_frame + (buf.len() / channels) as u64;
+    let ramp = (MUTE_REGION_RAMP.as_secs_f64() * sample_rate as f64).max(1.0) as f32;
+
+    for r in regions {
+        let start = Seconds(r.start).to_frames(sample_rate).0;
+        let end = Seconds(r.end).to_frames(sample_rate).0;
+        for f in start.max(first_frame)..end.min(block_end) {
+            // Distance to the nearer edge decides how deep into the ramp this frame is
+            let depth = ((f - start).min(end - f) as f32 / ramp).min(1.0);
+            let base = (f - first_frame) as usize * channels;
+            for s in &mut buf[base..base + channels] {
+                *s *= 1.0 - depth;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(start: f64, end: f64) -> MuteRegion {
+        MuteRegion { start, end }
+    }
+
+    #[test]
+    fn overlapping_and_touching_regions_merge_and_empty_ones_go() {
+        let mut regions = vec![region(2.0, 3.0), region(0.5, 1.0), region(0.8, 1.5), region(1.5, 1.7), region(4.0, 4.0), region(5.0, 4.5)];
+        normalize(&mut regions);
+        assert_eq!(regions, vec![region(0.5, 1.7), region(2.0, 3.0)]);
+
+        // One that spans several swallows them
+        insert(&mut regions, region(0.0, 2.5));
+        assert_eq!(regions, vec![region(0.0, 3.0)]);
+    }
+
+    #[test]
+    fn slice_trims_and_rebases() {
+        let regions = vec![region(0.5, 1.5), region(2.0, 3.0)];
+        assert_eq!(slice(&regions, 1.0, 2.5), vec![region(0.0, 0.5), region(1.0, 1.5)]);
+        assert!(slice(&regions, 1.5, 2.0).is_empty());
+    }
+
+    #[test]
+    fn edges_ramp_over_three_ms_and_the_middle_is_silent() {
+        let rate = 48_000;
+        let ramp = (MUTE_REGION_RAMP.as_secs_f64() * rate as f64) as usize; // 144 frames
+        let (start, end) = (1000usize, 2000usize);
+        let mut buf = vec![1.0f32; 3000 * 2];
+        apply(&mut buf, 2, 0, &[region(start as f64 / rate as f64, end as f64 / rate as f64)], rate);
+        let left: Vec<f32> = buf.iter().step_by(2).copied().collect();
+
+        assert!(left[..start].iter().all(|&s| s == 1.0), "untouched before");
+        assert!(left[end..].iter().all(|&s| s == 1.0), "untouched after");
+        assert!(left[start + ramp..end - ramp].iter().all(|&s| s == 0.0));
+        // Linear ramps, down and back up, symmetric
+        assert!(left[start..start + ramp].windows(2).all(|w| w[1] < w[0]));
+        assert!(left[end - ramp..end].windows(2).all(|w| w[1] > w[0]));
+        assert!((left[start + ramp / 2] - 0.5).abs() < 0.01);
+        assert!((left[start + 10] - left[end - 10]).abs() < 1e-6);
+        assert_eq!(buf.chunks(2).filter(|f| f[0] != f[1]).count(), 0, "every channel alike");
+    }
+
+    #[test]
+    fn block_boundaries_do_not_change_the_result() {
+        let rate = 44_100;
+        let regions = vec![region(0.01, 0.02), region(0.021, 0.05)];
+        let mut whole = vec![1.0f32; 4096];
+        apply(&mut whole, 1, 0, &regions, rate);
+
+        let mut blocks = vec![1.0f32; 4096];
+        for (i, block) in blocks.chunks_mut(100).enumerate() {
+            apply(block, 1, i as u64 * 100, &regions, rate);
+        }
+        assert_eq!(whole, blocks);
+    }
+}
@@ -0,0 +1,59 @@
+// src/engine/navigation.rs
+
+// Transport navigation targets.
+// Edit points are the union of every clip start/end across all tracks. Projects can
+// have thousands of clips, so the sorted list is rebuilt lazily (dirty flag set by
+// every clip edit) and queried with a binary search.
+
+use super::track::Track;
+use std::time::Duration;
+
+/// Navigation ignores targets this close to the playhead, so repeated
+/// "next" presses keep moving even while the transport is rolling.
+pub const NAV_TOLERANCE: Duration = Duration::from_millis(1);
+
+#[derive(Default)]
+pub struct EditPoints {
+    points: Vec<Duration>,
+    dirty: bool,
+}
+
+impl EditPoints {
+    pub fn new() -> Self {
+        Self { points: Vec::new(), dirty: true }
+    }
+
+    /// Call whenever clips are added, removed, moved or resized.
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn next_after(&mut self, tracks: &[Track], pos: Duration) -> Option<Duration> {
+        let points = self.points(tracks);
+        let i = points.partition_point(|&t| t <= pos + NAV_TOLERANCE);
+        points.get(i).copied()
+    }
+
+    pub fn previous_before(&mut self, tracks: &[Track], pos: Duration) -> Option<Duration> {
+        let limit = pos.saturating_sub(NAV_TOLERANCE);
+        let points = self.points(tracks);
+        let i = points.partition_point(|&t| t < limit);
+        i.checked_sub(1).map(|i| points[i])
+    }
+
+    fn points(&mut self, tracks: &[Track]) -> &[Duration] {
+        if self.dirty {
+            self.points.clear();
+            for track in tracks {
+                for clip in &track.clips {
+                    self.points.push(clip.start());
+                    self.points.push(clip.end());
+                }
+            }
+            self.points.sort_unstable();
+            self.points.dedup();
+            self.dirty = false;
+        }
+        &self.points
+    }
+}
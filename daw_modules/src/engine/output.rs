@@ -20,7 +20,7 @@ pub fn run_engine_example() -> anyhow::Result<()> {
 
     let engine_cb = engine.clone();
 
-    let err_fn = |err| eprintln!("Engine output error: {err}");
+    let err_fn = |err| rt_error!("Engine output error: {err}");
 
     let stream = device.build_output_stream(
         &config,
@@ -0,0 +1,247 @@
+// src/engine/output_routing.rs
+
+// Output routing matrix for multi-output interfaces.
+// The engine renders logical stereo buses (master, cue, direct track outs); this layer
+// decides which physical device channels each bus's L/R lands on. Buses sharing a
+// channel are summed; device channels nobody is routed to stay silent. A bus with both
+// sides on one channel (e.g. the master on a mono device) is folded down at -6 dB.
+
+use serde::{Deserialize, Serialize};
+
+/// Upper bound on tracks with a direct out (their buffers are allocated once).
+pub const MAX_DIRECT_OUTS: usize = 8;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(tag = "kind", content = "trackId", rename_all = "camelCase")]
+pub enum OutputBus {
+    Master,
+    /// Performer's cue mix (playback + monitored input through the monitor blend).
+    /// While routed, live input is no longer mixed into the master.
+    Cue,
+    /// Post-fader track signal. A track with a direct out is taken out of the master.
+    Track(u32),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BusRoute {
+    pub bus: OutputBus,
+    /// 0-based device channels.
+    pub left: usize,
+    pub right: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RoutingError {
+    ChannelOutOfRange { channel: usize, device_channels: usize },
+    TooManyDirectOuts { max: usize },
+}
+
+impl std::fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoutingError::ChannelOutOfRange { channel, device_channels } => {
+                write!(f, "output channel {} does not exist (device has {})", channel + 1, device_channels)
+            }
+            RoutingError::TooManyDirectOuts { max } => write!(f, "at most {} tracks can have a direct out", max),
+        }
+    }
+}
+
+impl std::error::Error for RoutingError {}
+
+#[derive(Clone, Debug)]
+pub struct OutputRouting {
+    routes: Vec<BusRoute>,
+    device_channels: usize,
+}
+
+impl OutputRouting {
+    /// Master on the first pair (or both sides on channel 0 for a mono device).
+    pub fn new(device_channels: usize) -> Self {
+        let device_channels = device_channels.max(1);
+        Self {
+            routes: vec![default_master(device_channels)],
+            device_channels,
+        }
+    }
+
+    pub fn device_channels(&self) -> usize {
+        self.device_channels
+    }
+
+    /// Snapshot for the UI / settings.
+    pub fn routes(&self) -> &[BusRoute] {
+        &self.routes
+    }
+
+    pub fn route_for(&self, bus: OutputBus) -> Option<BusRoute> {
+        self.routes.iter().find(|r| r.bus == bus).copied()
+    }
+
+    pub fn set_bus_output_channels(&mut self, bus: OutputBus, left: usize, right: usize) -> Result<(), RoutingError> {
+        for channel in [left, right] {
+            if channel >= self.device_channels {
+                return Err(RoutingError::ChannelOutOfRange { channel, device_channels: self.device_channels });
+            }
+        }
+        if matches!(bus, OutputBus::Track(_)) && self.route_for(bus).is_none() && self.direct_outs().count() >= MAX_DIRECT_OUTS {
+            return Err(RoutingError::TooManyDirectOuts { max: MAX_DIRECT_OUTS });
+        }
+
+        match self.routes.iter_mut().find(|r| r.bus == bus) {
+            Some(route) => { route.left = left; route.right = right; }
+            None => self.routes.push(BusRoute { bus, left, right }),
+        }
+        Ok(())
+    }
+
+    /// Unroute a bus. The master can't be unrouted; it goes back to the first pair.
+    pub fn clear_bus(&mut self, bus: OutputBus) {
+        if bus == OutputBus::Master {
+            let master = default_master(self.device_channels);
+            let _ = self.set_bus_output_channels(bus, master.left, master.right);
+        } else {
+            self.routes.retain(|r| r.bus != bus);
+        }
+    }
+
+    /// Replace everything with saved routes; the ones that don't fit this device are skipped.
+    pub fn restore(&mut self, routes: &[BusRoute]) -> Vec<RoutingError> {
+        *self = Self::new(self.device_channels);
+        routes
+            .iter()
+            .filter_map(|r| self.set_bus_output_channels(r.bus, r.left, r.right).err())
+            .collect()
+    }
+
+    /// Adapt to a new device. Routes that no longer fit are dropped and returned.
+    pub fn set_device_channels(&mut self, device_channels: usize) -> Vec<BusRoute> {
+        let device_channels = device_channels.max(1);
+        self.device_channels = device_channels;
+        let (kept, dropped): (Vec<_>, Vec<_>) = self
+            .routes
+            .iter()
+            .partition(|r| r.left < device_channels && r.right < device_channels);
+        self.routes = kept;
+        if self.route_for(OutputBus::Master).is_none() {
+            self.routes.insert(0, default_master(device_channels));
+        }
+        dropped
+    }
+
+    pub fn cue_routed(&self) -> bool {
+        self.route_for(OutputBus::Cue).is_some()
+    }
+
+    /// Track ids that have a direct out.
+    pub fn direct_outs(&self) -> impl Iterator<Item = u32> + '_ {
+        self.routes.iter().filter_map(|r| match r.bus {
+            OutputBus::Track(id) => Some(id),
+            _ => None,
+        })
+    }
+
+    /// Write stereo bus buffers into the interleaved device buffer.
+    /// `bus_buffer` returns the interleaved stereo block of a bus, if it has one this block.
+    pub fn interleave<'a>(&self, data: &mut [f32], bus_buffer: impl Fn(OutputBus) -> Option<&'a [f32]>) {
+        data.fill(0.0);
+        let ch = self.device_channels;
+        for route in &self.routes {
+            let Some(src) = bus_buffer(route.bus) else { continue };
+            for (frame, pair) in data.chunks_exact_mut(ch).zip(src.chunks_exact(2)) {
+                if route.left == route.right {
+                    frame[route.left] += 0.5 * (pair[0] + pair[1]);
+                } else {
+                    frame[route.left] += pair[0];
+                    frame[route.right] += pair[1];
+                }
+            }
+        }
+    }
+}
+
+fn default_master(device_channels: usize) -> BusRoute {
+    BusRoute { bus: OutputBus::Master, left: 0, right: if device_channels > 1 { 1 } else { 0 } }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Constant stereo block per bus: left = base, right = base + 0.01
+    fn block(base: f32, frames: usize) -> Vec<f32> {
+        (0..frames).flat_map(|_| [base, base + 0.01]).collect()
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn eight_channel_device_gets_each_bus_on_its_own_pair() {
+        let mut routing = OutputRouting::new(8);
+        routing.set_bus_output_channels(OutputBus::Cue, 2, 3).unwrap();
+        routing.set_bus_output_channels(OutputBus::Track(7), 4, 5).unwrap();
+        routing.set_bus_output_channels(OutputBus::Track(9), 5, 4).unwrap(); // swapped, shares 4/5
+
+        let (master, cue, t7, t9) = (block(0.1, 16), block(0.2, 16), block(0.3, 16), block(0.4, 16));
+        let mut data = vec![1.0f32; 8 * 16];
+        routing.interleave(&mut data, |bus| match bus {
+            OutputBus::Master => Some(&master[..]),
+            OutputBus::Cue => Some(&cue[..]),
+            OutputBus::Track(7) => Some(&t7[..]),
+            OutputBus::Track(9) => Some(&t9[..]),
+            OutputBus::Track(_) => None,
+        });
+
+        for frame in data.chunks_exact(8) {
+            assert!(close(frame[0], 0.1) && close(frame[1], 0.11));
+            assert!(close(frame[2], 0.2) && close(frame[3], 0.21));
+            // Buses sharing a channel are summed
+            assert!(close(frame[4], 0.3 + 0.41) && close(frame[5], 0.31 + 0.4));
+            // Unrouted channels are silent, whatever was in the buffer
+            assert_eq!(&frame[6..], &[0.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn eight_channel_routes_are_checked_and_trimmed_for_smaller_devices() {
+        let mut routing = OutputRouting::new(8);
+        routing.set_bus_output_channels(OutputBus::Cue, 6, 7).unwrap();
+        routing.set_bus_output_channels(OutputBus::Track(1), 2, 3).unwrap();
+        assert_eq!(
+            routing.set_bus_output_channels(OutputBus::Track(2), 7, 8),
+            Err(RoutingError::ChannelOutOfRange { channel: 8, device_channels: 8 })
+        );
+        for id in 2..=MAX_DIRECT_OUTS as u32 {
+            routing.set_bus_output_channels(OutputBus::Track(id), 4, 5).unwrap();
+        }
+        assert_eq!(
+            routing.set_bus_output_channels(OutputBus::Track(99), 4, 5),
+            Err(RoutingError::TooManyDirectOuts { max: MAX_DIRECT_OUTS })
+        );
+
+        let dropped = routing.set_device_channels(4);
+        assert!(dropped.iter().any(|r| r.bus == OutputBus::Cue));
+        assert!(!routing.cue_routed());
+        assert_eq!(routing.route_for(OutputBus::Track(1)), Some(BusRoute { bus: OutputBus::Track(1), left: 2, right: 3 }));
+    }
+
+    #[test]
+    fn mono_device_folds_the_master_down_at_half_gain() {
+        let routing = OutputRouting::new(1);
+        assert_eq!(routing.route_for(OutputBus::Master), Some(BusRoute { bus: OutputBus::Master, left: 0, right: 0 }));
+
+        let master = block(0.5, 4);
+        let mut data = vec![0.0f32; 4];
+        routing.interleave(&mut data, |bus| (bus == OutputBus::Master).then_some(&master[..]));
+        assert!(data.iter().all(|&s| close(s, 0.5 * (0.5 + 0.51))));
+
+        // A full-scale mono-compatible master stays at full scale
+        let full = [1.0f32; 8];
+        routing.interleave(&mut data, |bus| (bus == OutputBus::Master).then_some(&full[..]));
+        assert!(data.iter().all(|&s| close(s, 1.0)));
+    }
+}
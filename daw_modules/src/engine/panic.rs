@@ -0,0 +1,138 @@
+// src/engine/panic.rs
+
+// "Panic" fade-out: ramps every output bus to silence over a short, fixed time from
+// inside the render callback, then tells the engine to stop. Kept separate from the
+// master gain (which the runtime overwrites every block) so nothing can fight it.
+
+use std::time::Duration;
+
+/// How long the fade to silence takes.
+pub const PANIC_RAMP: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PanicState {
+    Idle,
+    /// `gain` falls by `step` per frame until it reaches 0.
+    Ramping { gain: f32, step: f32 },
+}
+
+pub struct PanicRamp {
+    state: PanicState,
+}
+
+impl PanicRamp {
+    pub fn new() -> Self {
+        Self { state: PanicState::Idle }
+    }
+
+    /// Start (or keep) fading. Re-triggering mid-ramp never makes it louder.
+    pub fn trigger(&mut self, sample_rate: u32) {
+        let frames = (PANIC_RAMP.as_secs_f64() * sample_rate as f64).max(1.0) as f32;
+        let gain = match self.state {
+            PanicState::Ramping { gain, .. } => gain,
+            PanicState::Idle => 1.0,
+        };
+        self.state = PanicState::Ramping { gain, step: 1.0 / frames };
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state != PanicState::Idle
+    }
+
+    /// Gain for each frame of this block: fills `gains`, one per frame.
+    /// Returns true when the ramp reached silence during this block.
+    pub fn advance(&mut self, gains: &mut [f32]) -> bool {
+        let PanicState::Ramping { mut gain, step } = self.state else {
+            gains.fill(1.0);
+            return false;
+        };
+        for g in gains.iter_mut() {
+            gain = (gain - step).max(0.0);
+            *g = gain;
+        }
+        if gain <= 0.0 {
+            self.state = PanicState::Idle;
+            true
+        } else {
+            self.state = PanicState::Ramping { gain, step };
+            false
+        }
+    }
+}
+
+impl Default for PanicRamp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Scale an interleaved block frame by frame.
+pub fn apply_gains(buf: &mut [f32], gains: &[f32], channels: usize) {
+    for (frame, g) in buf.chunks_exact_mut(channels.max(1)).zip(gains) {
+        for s in frame {
+            *s *= g;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUS_80_DB: f32 = 1e-4;
+
+    #[test]
+    fn ramp_is_below_minus_80_dbfs_by_the_end_of_the_fade() {
+        let sample_rate = 48_000;
+        let ramp_frames = (PANIC_RAMP.as_secs_f64() * sample_rate as f64) as usize;
+        let mut ramp = PanicRamp::new();
+        ramp.trigger(sample_rate);
+
+        let mut gains = vec![0.0; ramp_frames];
+        assert!(ramp.advance(&mut gains), "silent within {:?}", PANIC_RAMP);
+        assert!(gains.windows(2).all(|w| w[1] <= w[0]), "never gets louder");
+        assert!(gains[ramp_frames - 1] < MINUS_80_DB);
+        assert!(!ramp.is_active());
+
+        // Idle again: unity, and nothing reported
+        let mut after = vec![0.0; 64];
+        assert!(!ramp.advance(&mut after));
+        assert!(after.iter().all(|&g| g == 1.0));
+    }
+
+    #[test]
+    fn block_size_does_not_change_the_ramp() {
+        let mut whole = PanicRamp::new();
+        whole.trigger(44_100);
+        let mut one = vec![0.0; 1000];
+        whole.advance(&mut one);
+
+        let mut split = PanicRamp::new();
+        split.trigger(44_100);
+        let mut blocks = vec![0.0; 1000];
+        for chunk in blocks.chunks_mut(37) {
+            split.advance(chunk);
+        }
+        assert_eq!(one, blocks);
+    }
+
+    #[test]
+    fn retriggering_mid_ramp_never_makes_it_louder() {
+        let mut ramp = PanicRamp::new();
+        ramp.trigger(48_000);
+        let mut gains = vec![0.0; 1000];
+        ramp.advance(&mut gains);
+        let reached = gains[999];
+
+        ramp.trigger(48_000);
+        ramp.advance(&mut gains[..1]);
+        assert!(gains[0] <= reached);
+    }
+
+    #[test]
+    fn apply_gains_scales_whole_frames() {
+        let mut buf = vec![1.0, -1.0, 1.0, -1.0];
+        apply_gains(&mut buf, &[0.5, 0.0], 2);
+        assert_eq!(buf, vec![0.5, -0.5, 0.0, -0.0]);
+    }
+}
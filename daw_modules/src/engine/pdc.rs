@@ -0,0 +1,146 @@
+// src/engine/pdc.rs
+
+// Plugin delay compensation (plus-delay only).
+// Every track is delayed by (project latency - own latency) so latent inserts
+// (lookahead limiters, pitch shifters, ...) stay sample-aligned with dry tracks.
+
+/// Upper bound for compensation, in frames (~370ms @ 44.1k). Buffers are allocated once.
+pub const MAX_PDC_FRAMES: usize = 16_384;
+
+/// Fixed-capacity interleaved delay line. Zero allocations after construction.
+pub struct PdcDelay {
+    buf: Vec<f32>,
+    channels: usize,
+    delay_frames: usize,
+    write_frame: usize,
+}
+
+impl PdcDelay {
+    pub fn new(channels: usize) -> Self {
+        let channels = channels.max(1);
+        Self {
+            buf: vec![0.0; (MAX_PDC_FRAMES + 1) * channels],
+            channels,
+            delay_frames: 0,
+            write_frame: 0,
+        }
+    }
+
+    pub fn delay_frames(&self) -> usize {
+        self.delay_frames
+    }
+
+    /// Change the delay. Clears the line so stale audio never replays at the new offset.
+    pub fn set_delay_frames(&mut self, frames: usize) {
+        let frames = frames.min(MAX_PDC_FRAMES);
+        if frames != self.delay_frames {
+            self.delay_frames = frames;
+            self.buf.fill(0.0);
+            self.write_frame = 0;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.buf.fill(0.0);
+        self.write_frame = 0;
+    }
+
+    /// Delay an interleaved block in place.
+    pub fn process(&mut self, block: &mut [f32]) {
+        if self.delay_frames == 0 {
+            return;
+        }
+        let ch = self.channels;
+        let len_frames = self.buf.len() / ch;
+
+        for frame in block.chunks_exact_mut(ch) {
+            let read_frame = (self.write_frame + len_frames - self.delay_frames) % len_frames;
+            let w = self.write_frame * ch;
+            let r = read_frame * ch;
+            for c in 0..ch {
+                self.buf[w + c] = frame[c];
+                frame[c] = self.buf[r + c];
+            }
+            self.write_frame = (self.write_frame + 1) % len_frames;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::Effect;
+
+    /// Stands in for a lookahead effect: plays its input `latency` frames late and says so.
+    struct FakeLatency {
+        line: PdcDelay,
+    }
+
+    impl FakeLatency {
+        fn new(latency: usize) -> Self {
+            let mut line = PdcDelay::new(2);
+            line.set_delay_frames(latency);
+            Self { line }
+        }
+    }
+
+    impl Effect for FakeLatency {
+        fn process_block(&mut self, buffer: &mut [f32], _channels: usize) {
+            self.line.process(buffer);
+        }
+
+        fn latency_frames(&self) -> usize {
+            self.line.delay_frames()
+        }
+    }
+
+    fn impulse_at(frame: usize, frames: usize) -> Vec<f32> {
+        let mut block = vec![0.0; frames * 2];
+        block[frame * 2] = 1.0;
+        block[frame * 2 + 1] = 1.0;
+        block
+    }
+
+    fn first_hit(block: &[f32]) -> Option<usize> {
+        block.chunks(2).position(|f| f[0] != 0.0)
+    }
+
+    #[test]
+    fn latent_and_dry_tracks_land_on_the_same_frame() {
+        let mut latent = FakeLatency::new(256);
+        let project_latency = latent.latency_frames();
+        let mut dry = PdcDelay::new(2);
+        dry.set_delay_frames(project_latency);
+        let mut latent_comp = PdcDelay::new(2);
+        latent_comp.set_delay_frames(project_latency - latent.latency_frames());
+
+        // Both play an impulse at frame 100, rendered in 128-frame blocks
+        let (mut a, mut b) = (impulse_at(100, 1024), impulse_at(100, 1024));
+        for (a, b) in a.chunks_mut(256).zip(b.chunks_mut(256)) {
+            latent.process_block(a, 2);
+            latent_comp.process(a);
+            dry.process(b);
+        }
+        assert_eq!(first_hit(&a), Some(356));
+        assert_eq!(first_hit(&b), first_hit(&a));
+    }
+
+    #[test]
+    fn changing_the_delay_drops_what_was_in_the_line() {
+        let mut line = PdcDelay::new(2);
+        line.set_delay_frames(64);
+        let mut block = impulse_at(10, 32);
+        line.process(&mut block);
+        line.set_delay_frames(16);
+        let mut next = vec![0.0; 128];
+        line.process(&mut next);
+        assert_eq!(first_hit(&next), None);
+    }
+
+    #[test]
+    fn delay_is_capped() {
+        let mut line = PdcDelay::new(1);
+        line.set_delay_frames(MAX_PDC_FRAMES * 2);
+        assert_eq!(line.delay_frames(), MAX_PDC_FRAMES);
+    }
+}
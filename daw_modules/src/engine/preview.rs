@@ -0,0 +1,141 @@
+// src/engine/preview.rs
+
+// Hover preview: a short audition of a clip's source from any point, mixed straight into
+// the master output at a fixed level, independent of the transport. Each voice owns its
+// own small decoder (the same DecoderHandle the clips stream through).
+
+use std::time::Duration;
+
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapProd, HeapRb};
+
+use super::track::DecoderHandle;
+
+/// Fixed preview level (-6 dB).
+pub const PREVIEW_GAIN: f32 = 0.501_187;
+
+/// Fade in/out so starting, cancelling or ending a preview never clicks.
+const PREVIEW_FADE: Duration = Duration::from_millis(5);
+
+/// Finished voices the audio thread can hand over before the reaper catches up.
+const RETIRED_CAPACITY: usize = 16;
+const REAP_INTERVAL: Duration = Duration::from_millis(50);
+
+pub struct PreviewVoice {
+    decoder: DecoderHandle,
+    channels: usize,
+    remaining_frames: usize,
+    fade_frames: usize,
+    gain: f32, // 0..=1 envelope, scaled by PREVIEW_GAIN on output
+    stopping: bool,
+    scratch: Vec<f32>,
+}
+
+impl PreviewVoice {
+    /// Starts streaming `path` from `file_pos` (source time) for `duration`.
+    pub fn new(
+        path: String,
+        source_sr: u32,
+        source_ch: usize,
+        file_pos: Duration,
+        duration: Duration,
+        output_sr: u32,
+        output_ch: usize,
+    ) -> anyhow::Result<Self> {
+        let mut decoder = DecoderHandle::new_for_engine(path, source_ch, output_ch, source_sr, output_sr)?;
+        decoder.seek(file_pos);
+        decoder.set_playing(true);
+
+        Ok(Self {
+            decoder,
+            channels: output_ch.max(1),
+            remaining_frames: (duration.as_secs_f64() * output_sr as f64) as usize,
+            fade_frames: ((PREVIEW_FADE.as_secs_f64() * output_sr as f64) as usize).max(1),
+            gain: 0.0,
+            stopping: false,
+            scratch: Vec::with_capacity(4096 * output_ch.max(1)),
+        })
+    }
+
+    /// Fade out quickly and finish.
+    pub fn stop(&mut self) {
+        self.stopping = true;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.remaining_frames == 0 || (self.stopping && self.gain <= 0.0)
+    }
+
+    /// Add this block of the preview into `out` (interleaved, `channels` wide).
+    pub fn mix_into(&mut self, out: &mut [f32]) {
+        let frames = (out.len() / self.channels).min(self.remaining_frames);
+        if frames == 0 {
+            return;
+        }
+        self.scratch.clear();
+        self.scratch.resize(frames * self.channels, 0.0);
+        let written = self.decoder.mix_interleaved(&mut self.scratch, frames, self.channels);
+
+        let step = 1.0 / self.fade_frames as f32;
+        for (i, (frame, src)) in out
+            .chunks_exact_mut(self.channels)
+            .zip(self.scratch.chunks_exact(self.channels))
+            .take(written)
+            .enumerate()
+        {
+            // Fade out for a stop, or ahead of the natural end
+            let left = self.remaining_frames - i;
+            let fading_out = self.stopping || left <= self.fade_frames;
+            self.gain = if fading_out {
+                (self.gain - step).max(0.0).min(left as f32 * step)
+            } else {
+                (self.gain + step).min(1.0)
+            };
+            let g = self.gain * PREVIEW_GAIN;
+            for (o, s) in frame.iter_mut().zip(src) {
+                *o += s * g;
+            }
+        }
+
+        // A starved decoder still uses up preview time (it never runs over)
+        self.remaining_frames -= frames;
+        if self.stopping && self.gain <= 0.0 {
+            self.remaining_frames = 0;
+        }
+    }
+}
+
+/// Takes finished voices off the audio thread and drops them on a thread of its own: a
+/// voice owns a decoder thread and a large ring buffer, which the callback must not free.
+pub struct PreviewReaper {
+    retired: HeapProd<PreviewVoice>,
+}
+
+impl PreviewReaper {
+    pub fn new() -> Self {
+        let (retired, mut dead) = HeapRb::<PreviewVoice>::new(RETIRED_CAPACITY).split();
+        let spawned = std::thread::Builder::new().name("preview-reaper".into()).spawn(move || {
+            // Runs until the engine holding the other end is gone
+            while dead.write_is_held() {
+                dead.clear();
+                std::thread::sleep(REAP_INTERVAL);
+            }
+            dead.clear();
+        });
+        if let Err(e) = spawned {
+            rt_warn!("⚠️ Preview reaper not started ({}); finished previews stay queued", e);
+        }
+        Self { retired }
+    }
+
+    /// Hand over a finished voice. Gives it back when the reaper is behind.
+    pub fn retire(&mut self, voice: PreviewVoice) -> Option<PreviewVoice> {
+        self.retired.try_push(voice).err()
+    }
+}
+
+impl Default for PreviewReaper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
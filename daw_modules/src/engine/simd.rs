@@ -0,0 +1,98 @@
+// src/engine/simd.rs
+
+// Buffer kernels for the render hot paths (summing, gain, pan). `std::simd` is nightly-only,
+// so the loops walk fixed-size lanes instead: with the bounds known at compile time and no
+// branches inside, LLVM turns each lane into vector adds and multiplies (SSE/AVX, NEON).
+// The tail that doesn't fill a lane runs scalar.
+
+use super::smoothing::Smoothed;
+
+/// Samples per lane; 8 f32 fill an AVX register (two SSE/NEON ones).
+const LANES: usize = 8;
+
+/// Below this a sample is flushed to zero, so decaying tails don't go denormal.
+pub const DENORMAL_FLOOR: f32 = 1e-10;
+
+#[inline(always)]
+fn zip_lanes(dst: &mut [f32], src: &[f32], op: impl Fn(&mut f32, f32)) {
+    let len = dst.len().min(src.len());
+    let mut dst = dst[..len].chunks_exact_mut(LANES);
+    let mut src = src[..len].chunks_exact(LANES);
+    for (d, s) in (&mut dst).zip(&mut src) {
+        let d: &mut [f32; LANES] = d.try_into().unwrap();
+        let s: &[f32; LANES] = s.try_into().unwrap();
+        for (d, &s) in d.iter_mut().zip(s) {
+            op(d, s);
+        }
+    }
+    for (d, &s) in dst.into_remainder().iter_mut().zip(src.remainder()) {
+        op(d, s);
+    }
+}
+
+/// `dst += src`
+pub fn add(dst: &mut [f32], src: &[f32]) {
+    zip_lanes(dst, src, |d, s| *d += s);
+}
+
+/// `dst += src * gain`
+pub fn add_scaled(dst: &mut [f32], src: &[f32], gain: f32) {
+    zip_lanes(dst, src, |d, s| *d += s * gain);
+}
+
+/// `dst = src`, with denormal-range samples flushed to zero.
+pub fn copy_flushed(dst: &mut [f32], src: &[f32]) {
+    zip_lanes(dst, src, |d, s| *d = if s.abs() < DENORMAL_FLOOR { 0.0 } else { s });
+}
+
+/// Multiply each interleaved frame by per-channel `gains` (channel `c` by `gains[c]`, the
+/// rest of a wider frame by `rest`). Covers constant gain and a settled pan in one pass.
+pub fn scale_frames(buffer: &mut [f32], channels: usize, gains: &[f32], rest: f32) {
+    let channels = channels.max(1);
+    let gain_of = |c: usize| gains.get(c).copied().unwrap_or(rest);
+    if LANES % channels != 0 {
+        for frame in buffer.chunks_mut(channels) {
+            for (c, s) in frame.iter_mut().enumerate() {
+                *s *= gain_of(c);
+            }
+        }
+        return;
+    }
+    // A lane holds whole frames, so one gain pattern fits every lane
+    let pattern: [f32; LANES] = std::array::from_fn(|i| gain_of(i % channels));
+    let mut lanes = buffer.chunks_exact_mut(LANES);
+    for lane in &mut lanes {
+        let lane: &mut [f32; LANES] = lane.try_into().unwrap();
+        for (s, g) in lane.iter_mut().zip(&pattern) {
+            *s *= g;
+        }
+    }
+    for (s, g) in lanes.into_remainder().iter_mut().zip(&pattern) {
+        *s *= g;
+    }
+}
+
+/// `dst += src * gain`, with `gain` following `smooth` frame by frame. Once the ramp has
+/// settled the rest of the block takes the vector path (or a plain sum at unity).
+pub fn add_ramped(dst: &mut [f32], src: &[f32], channels: usize, smooth: &mut Smoothed) {
+    let channels = channels.max(1);
+    let len = dst.len().min(src.len());
+    let mut done = 0;
+    while done < len && smooth.is_ramping() {
+        let gain = smooth.next();
+        let end = (done + channels).min(len);
+        for (d, s) in dst[done..end].iter_mut().zip(&src[done..end]) {
+            *d += s * gain;
+        }
+        done = end;
+    }
+    if done >= len {
+        return;
+    }
+    let gain = smooth.current();
+    if gain == 1.0 {
+        add(&mut dst[done..len], &src[done..len]);
+    } else if gain != 0.0 {
+        add_scaled(&mut dst[done..len], &src[done..len], gain);
+    }
+}
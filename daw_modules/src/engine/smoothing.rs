@@ -0,0 +1,78 @@
+// src/engine/smoothing.rs
+
+// Parameter smoothing for faders: a new gain or pan is reached through a short linear ramp
+// instead of a step, so moving it while audio plays doesn't zipper. The ramp is fixed in
+// time rather than tied to the block, so small buffers don't make it click and large ones
+// don't make it sluggish.
+
+use std::time::Duration;
+
+/// How long a gain or pan change takes to land.
+pub const PARAM_RAMP: Duration = Duration::from_millis(10);
+
+pub fn ramp_frames(sample_rate: u32) -> usize {
+    (PARAM_RAMP.as_secs_f64() * sample_rate as f64).round().max(1.0) as usize
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Smoothed {
+    current: f32,
+    target: f32,
+    step: f32,
+    remaining: usize,
+}
+
+impl Smoothed {
+    pub fn new(value: f32) -> Self {
+        Self { current: value, target: value, step: 0.0, remaining: 0 }
+    }
+
+    /// Ramp to `target` over `frames`. Setting the target already ramped to keeps the ramp going.
+    pub fn set_target(&mut self, target: f32, frames: usize) {
+        if target == self.target {
+            return;
+        }
+        let frames = frames.max(1);
+        self.target = target;
+        self.step = (target - self.current) / frames as f32;
+        self.remaining = frames;
+    }
+
+    /// Jump to `value` with no ramp.
+    pub fn snap(&mut self, value: f32) {
+        *self = Self::new(value);
+    }
+
+    pub fn is_ramping(&self) -> bool {
+        self.remaining > 0
+    }
+
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    /// Value for the next frame.
+    #[inline]
+    pub fn next(&mut self) -> f32 {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            self.current = if self.remaining == 0 { self.target } else { self.current + self.step };
+        }
+        self.current
+    }
+
+    /// Move on `frames` without producing values (a block that wasn't rendered).
+    pub fn skip(&mut self, frames: usize) {
+        if frames >= self.remaining {
+            self.current = self.target;
+            self.remaining = 0;
+        } else {
+            self.remaining -= frames;
+            self.current += self.step * frames as f32;
+        }
+    }
+}
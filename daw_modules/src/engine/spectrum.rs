@@ -0,0 +1,161 @@
+// src/engine/spectrum.rs
+
+// Spectrum analyzer taps. An analyzer sits on a track's post-fader output or on the
+// master, folds it to mono and runs a Hann-windowed FFT every half window. Magnitudes
+// fall back at a fixed rate (like a meter) and are published per bin through atomics;
+// the UI takes a `SpectrumFrame` whenever it redraws. The FFT is planned and every
+// buffer allocated when the analyzer is attached, never on the audio thread.
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use super::track::TrackId;
+
+/// FFT length in frames; the spectrum has half as many bins.
+pub const SPECTRUM_SIZE: usize = 2048;
+/// Lowest magnitude reported, in dBFS.
+pub const SPECTRUM_FLOOR_DB: f32 = -120.0;
+/// How fast a bin falls back once its level drops, in dB per second.
+const FALL_DB_PER_SEC: f32 = 40.0;
+
+/// Where an analyzer listens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectrumTarget {
+    Master,
+    Track(TrackId),
+}
+
+/// One readout of an analyzer, as the UI draws it.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SpectrumFrame {
+    pub sample_rate: u32,
+    /// Width of one bin in Hz; bin `i` is centered on `i * bin_hz`.
+    pub bin_hz: f32,
+    /// Bumped for every FFT; equal values mean nothing new.
+    pub frame: u64,
+    pub magnitudes_db: Vec<f32>,
+}
+
+/// The lock-free bridge: the audio thread writes the bins, the UI reads them.
+pub struct SpectrumReadout {
+    bins: Vec<AtomicU32>,
+    sample_rate: AtomicU32,
+    frame: AtomicU64,
+}
+
+impl SpectrumReadout {
+    fn new(sample_rate: u32) -> Arc<Self> {
+        Arc::new(Self {
+            bins: (0..SPECTRUM_SIZE / 2).map(|_| AtomicU32::new(SPECTRUM_FLOOR_DB.to_bits())).collect(),
+            sample_rate: AtomicU32::new(sample_rate),
+            frame: AtomicU64::new(0),
+        })
+    }
+
+    pub fn snapshot(&self) -> SpectrumFrame {
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed);
+        SpectrumFrame {
+            sample_rate,
+            bin_hz: sample_rate as f32 / SPECTRUM_SIZE as f32,
+            frame: self.frame.load(Ordering::Relaxed),
+            magnitudes_db: self.bins.iter().map(|b| f32::from_bits(b.load(Ordering::Relaxed))).collect(),
+        }
+    }
+}
+
+/// Owned by the audio thread.
+pub struct SpectrumAnalyzer {
+    readout: Arc<SpectrumReadout>,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    gain: f32,       // turns a full-scale sine into 0 dB
+    input: Vec<f32>, // mono, the last SPECTRUM_SIZE frames, oldest first
+    filled: usize,   // frames collected since the last FFT
+    buffer: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    levels: Vec<f32>, // dB per bin as published
+    fall_per_frame: f32,
+}
+
+impl SpectrumAnalyzer {
+    /// Allocates and plans the FFT; attach from a control thread.
+    pub fn new(sample_rate: u32) -> Self {
+        let fft = FftPlanner::<f32>::new().plan_fft_forward(SPECTRUM_SIZE);
+        let scratch = vec![Complex::new(0.0, 0.0); fft.get_inplace_scratch_len()];
+        let window: Vec<f32> = (0..SPECTRUM_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / SPECTRUM_SIZE as f32).cos())
+            .collect();
+        let gain = 2.0 / window.iter().sum::<f32>();
+        let mut analyzer = Self {
+            readout: SpectrumReadout::new(sample_rate),
+            fft,
+            window,
+            gain,
+            input: vec![0.0; SPECTRUM_SIZE],
+            filled: 0,
+            buffer: vec![Complex::new(0.0, 0.0); SPECTRUM_SIZE],
+            scratch,
+            levels: vec![SPECTRUM_FLOOR_DB; SPECTRUM_SIZE / 2],
+            fall_per_frame: 0.0,
+        };
+        analyzer.set_sample_rate(sample_rate);
+        analyzer
+    }
+
+    pub fn readout(&self) -> Arc<SpectrumReadout> {
+        self.readout.clone()
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.readout.sample_rate.store(sample_rate, Ordering::Relaxed);
+        let hop = (SPECTRUM_SIZE / 2) as f32;
+        self.fall_per_frame = FALL_DB_PER_SEC * hop / sample_rate.max(1) as f32;
+    }
+
+    /// Feed an interleaved block.
+    pub fn process_block(&mut self, buffer: &[f32], channels: usize) {
+        let channels = channels.max(1);
+        let scale = 1.0 / channels as f32;
+        for frame in buffer.chunks_exact(channels) {
+            self.push(frame.iter().sum::<f32>() * scale);
+        }
+    }
+
+    /// Feed `frames` of silence (the source rendered nothing), so the display falls back.
+    pub fn push_silence(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.push(0.0);
+        }
+    }
+
+    fn push(&mut self, sample: f32) {
+        // Half-window hop: the first half of `input` already holds the previous frames
+        let hop = SPECTRUM_SIZE / 2;
+        self.input[hop + self.filled] = sample;
+        self.filled += 1;
+        if self.filled == hop {
+            self.analyze();
+            self.input.copy_within(hop.., 0);
+            self.filled = 0;
+        }
+    }
+
+    fn analyze(&mut self) {
+        for ((b, &x), &w) in self.buffer.iter_mut().zip(&self.input).zip(&self.window) {
+            *b = Complex::new(x * w, 0.0);
+        }
+        self.fft.process_with_scratch(&mut self.buffer, &mut self.scratch);
+
+        for ((level, bin), out) in self.levels.iter_mut().zip(&self.buffer).zip(&self.readout.bins) {
+            let magnitude = bin.norm() * self.gain;
+            let db = if magnitude > 0.0 { (20.0 * magnitude.log10()).max(SPECTRUM_FLOOR_DB) } else { SPECTRUM_FLOOR_DB };
+            *level = db.max(*level - self.fall_per_frame);
+            out.store(level.to_bits(), Ordering::Relaxed);
+        }
+        self.readout.frame.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,41 @@
+// src/engine/tap.rs
+
+// Master capture tap: copies every rendered (post master gain) block into a ring
+// buffer for a consumer off the audio thread, e.g. a realtime bounce. The same tap sits on
+// each track's post-fader output for a multitrack print.
+
+use ringbuf::traits::{Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+pub struct MasterTap {
+    producer: HeapProd<f32>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl MasterTap {
+    /// Returns the tap to install on the engine, the consumer, and a counter of samples
+    /// that didn't fit (the consumer fell behind).
+    pub fn new(capacity_samples: usize) -> (Self, HeapCons<f32>, Arc<AtomicU64>) {
+        let (producer, consumer) = HeapRb::<f32>::new(capacity_samples.max(1)).split();
+        let dropped = Arc::new(AtomicU64::new(0));
+        (Self { producer, dropped: dropped.clone() }, consumer, dropped)
+    }
+
+    /// Realtime-safe: never blocks or allocates.
+    pub fn push(&mut self, block: &[f32]) {
+        let written = self.producer.push_slice(block);
+        if written < block.len() {
+            self.dropped.fetch_add((block.len() - written) as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Push `samples` zeros (a block the source didn't render), keeping files aligned.
+    pub fn push_silence(&mut self, samples: usize) {
+        let written = self.producer.push_iter(std::iter::repeat_n(0.0, samples));
+        if written < samples {
+            self.dropped.fetch_add((samples - written) as u64, Ordering::Relaxed);
+        }
+    }
+}
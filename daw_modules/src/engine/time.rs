@@ -1,9 +1,109 @@
 // src/engine/time.rs
 
+use std::ops::{Add, AddAssign, Sub};
 use std::time::Duration;
 use serde::{Serialize, Deserialize};
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+// --- Unit-safe time ---
+// Engine math happens in `Frames`; `Seconds`/`Duration` only at the edges (UI, manifest).
+// All seconds -> frames conversions round to the nearest frame, in one place.
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// A position or length in sample frames (one frame = one sample on every channel).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Frames(pub u64);
+
+/// A position or length in seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Seconds(pub f64);
+
+impl Frames {
+    pub const ZERO: Frames = Frames(0);
+
+    /// Nearest frame. Negative or non-finite input clamps to zero.
+    pub fn from_seconds(secs: Seconds, sample_rate: u32) -> Self {
+        if !secs.0.is_finite() || secs.0 <= 0.0 {
+            return Frames::ZERO;
+        }
+        Frames((secs.0 * sample_rate as f64).round() as u64)
+    }
+
+    /// Nearest frame, computed in integer nanoseconds (no f64 drift on long sessions).
+    pub fn from_duration(d: Duration, sample_rate: u32) -> Self {
+        let n = d.as_nanos() * sample_rate as u128;
+        Frames(((n + NANOS_PER_SEC / 2) / NANOS_PER_SEC) as u64)
+    }
+
+    pub fn to_seconds(self, sample_rate: u32) -> Seconds {
+        Seconds(self.0 as f64 / sample_rate.max(1) as f64)
+    }
+
+    /// Round-trips exactly through `from_duration` at the same rate.
+    pub fn to_duration(self, sample_rate: u32) -> Duration {
+        let sr = sample_rate.max(1) as u128;
+        let nanos = (self.0 as u128 * NANOS_PER_SEC + sr / 2) / sr;
+        Duration::from_nanos(nanos as u64)
+    }
+
+    pub fn as_usize(self) -> usize {
+        self.0 as usize
+    }
+
+    /// The same instant counted at another rate, to the nearest frame (integer math).
+    pub fn rescale(self, from_rate: u32, to_rate: u32) -> Frames {
+        if from_rate == to_rate {
+            return self;
+        }
+        let from = from_rate.max(1) as u128;
+        Frames(((self.0 as u128 * to_rate as u128 + from / 2) / from) as u64)
+    }
+
+    pub fn saturating_sub(self, rhs: Frames) -> Frames {
+        Frames(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl Add for Frames {
+    type Output = Frames;
+    fn add(self, rhs: Frames) -> Frames { Frames(self.0 + rhs.0) }
+}
+
+impl AddAssign for Frames {
+    fn add_assign(&mut self, rhs: Frames) { self.0 += rhs.0; }
+}
+
+impl Sub for Frames {
+    type Output = Frames;
+    fn sub(self, rhs: Frames) -> Frames { Frames(self.0 - rhs.0) }
+}
+
+impl Seconds {
+    pub fn to_frames(self, sample_rate: u32) -> Frames {
+        Frames::from_seconds(self, sample_rate)
+    }
+
+    /// Clamped at zero, since Duration can't be negative.
+    pub fn to_duration(self) -> Duration {
+        if self.0.is_finite() && self.0 > 0.0 { Duration::from_secs_f64(self.0) } else { Duration::ZERO }
+    }
+}
+
+impl From<Duration> for Seconds {
+    fn from(d: Duration) -> Self { Seconds(d.as_secs_f64()) }
+}
+
+impl Add for Seconds {
+    type Output = Seconds;
+    fn add(self, rhs: Seconds) -> Seconds { Seconds(self.0 + rhs.0) }
+}
+
+impl Sub for Seconds {
+    type Output = Seconds;
+    fn sub(self, rhs: Seconds) -> Seconds { Seconds(self.0 - rhs.0) }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TimeSignature {
     pub numerator: u32,   // e.g., 4
     pub denominator: u32, // e.g., 4
@@ -27,11 +127,51 @@ pub struct GridLine {
 }
 
 
+/// Grid that clip moves snap to. A session setting, not saved with the project.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SnapResolution {
+    #[default]
+    Off,
+    Bar,
+    /// The meter's beat (a quarter in 4/4, an eighth in 6/8).
+    Beat,
+    Eighth,
+    Sixteenth,
+}
+
+impl TimeSignature {
+    /// Numerator 1-32, denominator a power of two up to 32.
+    pub fn is_valid(&self) -> bool {
+        (1..=32).contains(&self.numerator) && matches!(self.denominator, 1 | 2 | 4 | 8 | 16 | 32)
+    }
+}
+
+/// A meter change taking effect at the start of `bar` (1-indexed, always > 1; bar 1 is
+/// `TempoMap::signature`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MeterChange {
+    pub bar: u32,
+    pub signature: TimeSignature,
+}
+
+// A run of bars in one meter
+#[derive(Clone, Copy, Debug)]
+struct MeterSegment {
+    first_bar: u32,
+    start_secs: f64,
+    signature: TimeSignature,
+}
+
 /// The "Brain" that relates Real Time (Seconds) to Musical Time (Bars/Beats).
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TempoMap {
     pub bpm: f64,
+    /// Meter from bar 1 until the first change.
     pub signature: TimeSignature,
+    /// Sorted by bar, one per bar.
+    #[serde(default)]
+    meter_changes: Vec<MeterChange>,
 }
 
 impl Default for TempoMap {
@@ -39,6 +179,7 @@ impl Default for TempoMap {
         Self {
             bpm: 120.0,
             signature: TimeSignature::default(),
+            meter_changes: Vec::new(),
         }
     }
 }
@@ -48,6 +189,102 @@ impl TempoMap {
         Self {
             bpm,
             signature: TimeSignature { numerator, denominator },
+            meter_changes: Vec::new(),
+        }
+    }
+
+    pub fn meter_changes(&self) -> &[MeterChange] {
+        &self.meter_changes
+    }
+
+    /// Switch to `signature` from the start of `bar` (1-indexed). Bar 1 sets the initial
+    /// meter; an existing change at that bar is replaced.
+    pub fn set_meter_change(&mut self, bar: u32, signature: TimeSignature) -> Result<(), String> {
+        if !signature.is_valid() {
+            return Err(format!("Invalid time signature {}/{}", signature.numerator, signature.denominator));
+        }
+        match bar {
+            0 => return Err("Bars are numbered from 1".into()),
+            1 => self.signature = signature,
+            _ => match self.meter_changes.binary_search_by_key(&bar, |c| c.bar) {
+                Ok(i) => self.meter_changes[i].signature = signature,
+                Err(i) => self.meter_changes.insert(i, MeterChange { bar, signature }),
+            },
+        }
+        Ok(())
+    }
+
+    /// Remove the change at `bar`; false if there was none.
+    pub fn remove_meter_change(&mut self, bar: u32) -> bool {
+        let before = self.meter_changes.len();
+        self.meter_changes.retain(|c| c.bar != bar);
+        self.meter_changes.len() != before
+    }
+
+    /// Replace every change (from a manifest). Invalid and duplicate entries are dropped.
+    pub fn set_meter_changes(&mut self, changes: &[MeterChange]) {
+        self.meter_changes.clear();
+        for change in changes.iter().filter(|c| c.bar > 1) {
+            let _ = self.set_meter_change(change.bar, change.signature);
+        }
+    }
+
+    pub fn signature_at_bar(&self, bar: u32) -> TimeSignature {
+        self.segment_where(|seg| seg.first_bar <= bar).0.signature
+    }
+
+    pub fn signature_at(&self, position: Duration) -> TimeSignature {
+        let secs = position.as_secs_f64();
+        self.segment_where(|seg| seg.start_secs <= secs + 1e-9).0.signature
+    }
+
+    pub fn seconds_per_bar_in(&self, signature: TimeSignature) -> f64 {
+        self.seconds_per_quarter_note() * signature.numerator as f64 * (4.0 / signature.denominator as f64)
+    }
+
+    // Last segment whose start satisfies `started`, with the start of the one after it.
+    // Walks the changes in place: no allocation, so the audio thread can use it.
+    fn segment_where(&self, started: impl Fn(&MeterSegment) -> bool) -> (MeterSegment, Option<f64>) {
+        let mut seg = MeterSegment { first_bar: 1, start_secs: 0.0, signature: self.signature };
+        for change in &self.meter_changes {
+            let next = MeterSegment {
+                first_bar: change.bar,
+                start_secs: seg.start_secs + (change.bar - seg.first_bar) as f64 * self.seconds_per_bar_in(seg.signature),
+                signature: change.signature,
+            };
+            if !started(&next) {
+                return (seg, Some(next.start_secs));
+            }
+            seg = next;
+        }
+        (seg, None)
+    }
+
+    fn segments(&self) -> Vec<(MeterSegment, Option<f64>)> {
+        let mut segments = Vec::with_capacity(self.meter_changes.len() + 1);
+        let mut seg = MeterSegment { first_bar: 1, start_secs: 0.0, signature: self.signature };
+        for change in &self.meter_changes {
+            let start_secs = seg.start_secs + (change.bar - seg.first_bar) as f64 * self.seconds_per_bar_in(seg.signature);
+            segments.push((seg, Some(start_secs)));
+            seg = MeterSegment { first_bar: change.bar, start_secs, signature: change.signature };
+        }
+        segments.push((seg, None));
+        segments
+    }
+
+    /// First beat at or after `frame`, as (frame, is the first beat of a bar). Follows
+    /// the meter changes; realtime safe.
+    pub fn next_beat(&self, frame: u64, sample_rate: u32) -> (u64, bool) {
+        let sr = sample_rate as f64;
+        let (seg, next_start) = self.segment_where(|seg| (seg.start_secs * sr).round() as u64 <= frame);
+        let seg_start = (seg.start_secs * sr).round() as u64;
+        let frames_per_beat = self.frames_per_beat_in(seg.signature, sample_rate);
+        let beat = next_beat_in_meter(frame - seg_start, frames_per_beat, seg.signature.numerator);
+        let beat = (seg_start.saturating_add(beat.0), beat.1);
+        match next_start.map(|s| (s * sr).round() as u64) {
+            // The meter changes before that beat: the next one is the new bar's downbeat
+            Some(next) if next <= beat.0 => (next, true),
+            _ => beat,
         }
     }
 
@@ -68,14 +305,24 @@ impl TempoMap {
         self.seconds_per_musical_beat() * self.signature.numerator as f64
     }
 
+    /// Length of a musical beat of `signature` in (fractional) frames, for sample-accurate beat grids.
+    pub fn frames_per_beat_in(&self, signature: TimeSignature, sample_rate: u32) -> f64 {
+        self.seconds_per_quarter_note() * (4.0 / signature.denominator as f64) * sample_rate as f64
+    }
+
+    pub fn frames_per_bar_in(&self, signature: TimeSignature, sample_rate: u32) -> f64 {
+        self.seconds_per_bar_in(signature) * sample_rate as f64
+    }
+
     /// Convert exact Duration to a Bar/Beat representation for the UI Transport.
     /// Returns (bar, beat, percentage_of_beat)
     pub fn timestamp_to_musical(&self, position: Duration) -> (u32, u32, f64) {
         let total_seconds = position.as_secs_f64();
-        let seconds_per_beat = self.seconds_per_musical_beat();
+        let (seg, _) = self.segment_where(|seg| seg.start_secs <= total_seconds + 1e-9);
+        let seconds_per_beat = self.seconds_per_quarter_note() * (4.0 / seg.signature.denominator as f64);
         
-        let total_beats = total_seconds / seconds_per_beat;
-        let beats_per_bar = self.signature.numerator as f64;
+        let total_beats = (total_seconds - seg.start_secs).max(0.0) / seconds_per_beat;
+        let beats_per_bar = seg.signature.numerator as f64;
 
         let bar_index = (total_beats / beats_per_bar).floor();
         let beat_in_bar = total_beats % beats_per_bar;
@@ -83,69 +330,154 @@ impl TempoMap {
         // Bars are usually 1-indexed for humans, but 0-indexed for math.
         // We return 1-indexed Bars (1, 2, 3...) and 1-indexed Beats.
         (
-            bar_index as u32 + 1, 
+            seg.first_bar + bar_index as u32, 
             beat_in_bar.floor() as u32 + 1, 
             beat_in_bar.fract()
         )
     }
 
+    /// Nearest grid line to `position` at `resolution`. Like the drawn grid, subdivisions
+    /// restart at every bar line and follow the meter changes.
+    pub fn snap(&self, position: Duration, resolution: SnapResolution) -> Duration {
+        if self.bpm <= 0.0 || resolution == SnapResolution::Off {
+            return position;
+        }
+        let secs = position.as_secs_f64();
+        let (seg, next_start) = self.segment_where(|seg| seg.start_secs <= secs + 1e-9);
+        let seconds_per_bar = self.seconds_per_bar_in(seg.signature);
+        let seconds_per_step = match resolution {
+            SnapResolution::Off | SnapResolution::Bar => seconds_per_bar,
+            SnapResolution::Beat => self.seconds_per_quarter_note() * 4.0 / seg.signature.denominator as f64,
+            SnapResolution::Eighth => self.seconds_per_quarter_note() / 2.0,
+            SnapResolution::Sixteenth => self.seconds_per_quarter_note() / 4.0,
+        };
+
+        let bar = ((secs - seg.start_secs) / seconds_per_bar).floor().max(0.0);
+        let bar_start = seg.start_secs + bar * seconds_per_bar;
+        let bar_end = (bar_start + seconds_per_bar).min(next_start.unwrap_or(f64::INFINITY));
+        let before = bar_start + ((secs - bar_start) / seconds_per_step).floor().max(0.0) * seconds_per_step;
+        let after = (before + seconds_per_step).min(bar_end);
+        let snapped = if secs - before <= after - secs { before } else { after };
+        Duration::from_secs_f64(snapped.max(0.0))
+    }
+
     /// Generates grid lines (in Seconds) for a specific time range.
     /// This is what the Frontend will ask for to draw the grid.
     /// `resolution`: 1 = bars, 4 = quarter notes, 8 = eighth notes, 16 = sixteenths
+    /// Subdivisions restart at every bar line, so odd meters (7/8) and meter changes stay aligned.
     pub fn get_grid_lines(&self, start: Duration, end: Duration, resolution: u32) -> Vec<GridLine> {
-        let spq = self.seconds_per_quarter_note();
-        let quarters_per_bar = self.signature.numerator as f64 * (4.0 / self.signature.denominator as f64);
-        
-        // Grid Resolution strictly follows standard note divisions (1=bar, 4=quarter, 8=eighth)
-        let quarters_per_step = if resolution == 1 {
-            quarters_per_bar
-        } else {
-            4.0 / resolution as f64
-        };
-
-        let seconds_per_step = quarters_per_step * spq;
-        
         let start_sec = start.as_secs_f64();
         let end_sec = end.as_secs_f64();
-
-        // 1. Calculate the starting STEP INDEX (Integer)
-        // This aligns us perfectly to the grid, regardless of scroll position
-        let mut step_index = (start_sec / seconds_per_step).ceil() as u64;
         let mut lines = Vec::new();
+        if self.bpm <= 0.0 {
+            return lines;
+        }
 
-        // Calculate once outside the loop
-        let steps_per_bar = (quarters_per_bar / quarters_per_step).round() as u64;
-
-        // 2. Loop by Integer Steps (No float accumulation drift)
-        loop {
-            let time = step_index as f64 * seconds_per_step;
-            if time > end_sec + 0.001 {
-                break;
+        for (seg, next_start) in self.segments() {
+            let seg_end = next_start.unwrap_or(f64::INFINITY);
+            if seg_end <= start_sec || seg.start_secs > end_sec + 0.001 {
+                continue;
             }
-
-            // 3. Calculate Bar/Beat Logic
-            let is_bar_start = if steps_per_bar == 0 {
-                true 
+            let seconds_per_bar = self.seconds_per_bar_in(seg.signature);
+            // Grid Resolution strictly follows standard note divisions (1=bar, 4=quarter, 8=eighth)
+            let seconds_per_step = if resolution <= 1 {
+                seconds_per_bar
             } else {
-                step_index % steps_per_bar == 0
+                self.seconds_per_quarter_note() * 4.0 / resolution as f64
             };
 
-            // Calculate Bar Number (1-indexed)
-            let bar_number = if steps_per_bar == 0 {
-                step_index as u32 + 1
-            } else {
-                (step_index / steps_per_bar) as u32 + 1
-            };
+            // Loop by Integer Steps (No float accumulation drift)
+            let mut bar = ((start_sec - seg.start_secs) / seconds_per_bar).floor().max(0.0) as u32;
+            loop {
+                let bar_start = seg.start_secs + bar as f64 * seconds_per_bar;
+                if bar_start > end_sec + 0.001 || bar_start >= seg_end - 1e-9 {
+                    break;
+                }
+                let bar_end = (bar_start + seconds_per_bar).min(seg_end);
+                let mut step = 0u32;
+                loop {
+                    let time = bar_start + step as f64 * seconds_per_step;
+                    if time >= bar_end - 1e-9 || time > end_sec + 0.001 {
+                        break;
+                    }
+                    if time >= start_sec - 1e-9 {
+                        lines.push(GridLine {
+                            time,
+                            is_bar_start: step == 0,
+                            bar_number: seg.first_bar + bar,
+                        });
+                    }
+                    step += 1;
+                }
+                bar += 1;
+            }
+        }
 
-            lines.push(GridLine {
-                time,
-                is_bar_start,
-                bar_number,
-            });
+        lines
+    }
+}
 
-            step_index += 1;
+/// First beat at or after `frame` on a grid that starts with a downbeat at frame 0, as
+/// (frame, is the first beat of a bar). `u64::MAX` when the beat is too short to click.
+pub fn next_beat_in_meter(frame: u64, frames_per_beat: f64, beats_per_bar: u32) -> (u64, bool) {
+    if frames_per_beat < 1.0 {
+        return (u64::MAX, false);
+    }
+    let mut beat = (frame as f64 / frames_per_beat).ceil() as u64;
+    let mut at = (beat as f64 * frames_per_beat).round() as u64;
+    if at < frame {
+        beat += 1;
+        at = (beat as f64 * frames_per_beat).round() as u64;
+    }
+    (at, beat % beats_per_bar.max(1) as u64 == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SR: u32 = 44_100;
+
+    #[test]
+    fn seconds_round_to_the_nearest_frame_at_44k1() {
+        // 0.1 s is 4410 frames, though 0.1 * 44100.0 is 4410.000000000001 in f64
+        assert_eq!(Seconds(0.1).to_frames(SR), Frames(4_410));
+        // Half a frame either side of 1 s
+        assert_eq!(Seconds(1.0 + 0.49 / 44_100.0).to_frames(SR), Frames(44_100));
+        assert_eq!(Seconds(1.0 + 0.51 / 44_100.0).to_frames(SR), Frames(44_101));
+        assert_eq!(Seconds(-1.0).to_frames(SR), Frames::ZERO);
+        assert_eq!(Seconds(f64::NAN).to_frames(SR), Frames::ZERO);
+    }
+
+    #[test]
+    fn durations_round_trip_every_frame_at_44k1() {
+        // A frame at 44.1 kHz is 22675.73... ns, so naive truncation loses a frame
+        for frame in (0..44_100u64).chain([10 * 3_600 * 44_100 + 12_345]) {
+            let d = Frames(frame).to_duration(SR);
+            assert_eq!(Frames::from_duration(d, SR), Frames(frame), "frame {frame}");
         }
-        
-        lines
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn long_sessions_do_not_drift() {
+        // Ten hours of 512-frame blocks lands on the exact frame
+        let blocks = 10 * 3_600 * SR as u64 / 512;
+        let mut pos = Frames::ZERO;
+        for _ in 0..blocks {
+            pos += Frames(512);
+        }
+        assert_eq!(pos, Frames(blocks * 512));
+        assert_eq!(Frames::from_duration(pos.to_duration(SR), SR), pos);
+    }
+
+    #[test]
+    fn rescaling_to_a_higher_rate_and_back_is_exact() {
+        for frame in [0u64, 1, 441, 12_345, 44_099, 44_100, 1_234_567] {
+            let up = Frames(frame).rescale(SR, 48_000);
+            assert_eq!(up.rescale(48_000, SR), Frames(frame));
+        }
+        assert_eq!(Frames(44_100).rescale(SR, 48_000), Frames(48_000));
+        assert_eq!(Frames(1).rescale(SR, 48_000), Frames(1)); // 1.088 rounds down
+        assert_eq!(Frames(7).rescale(SR, SR), Frames(7));
+    }
+}
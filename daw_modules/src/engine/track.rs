@@ -2,27 +2,128 @@
 
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    mpsc::Sender,
     Arc,
 };
 use std::thread::JoinHandle;
 use std::time::Duration;
 
-use ringbuf::traits::{Split, Consumer};
+use ringbuf::traits::{Split, Consumer, Producer};
 use ringbuf::HeapRb;
 use ringbuf::wrap::caching::Caching;
 use ringbuf::storage::Heap;
 use ringbuf::SharedRb;
 // use ringbuf::traits::Consumer;
 
-use crate::decoder::{spawn_decoder_with_ctrl, DecoderCmd};
-use crate::bpm::adapter;
+use crate::decoder::{spawn_decoder_synced, CmdSender, DecoderCmd, SeekSync};
+use crate::decoder::stretch::{MAX_STRETCH, MIN_STRETCH};
+use crate::bpm::cache;
 use crate::effects::equalizer::TrackEq;
 use crate::effects::compressor::CompressorNode;
 use crate::effects::reverb::ReverbNode;
+use crate::effects::chain::EffectChain;
 use crate::engine::metering::{MeterState, TrackMeters}; 
 use crate::analyzer::AnalysisProfile;
-use crate::engine::automation::AutomationCurve; 
+use crate::engine::automation::{AutomationCurve, AutomationMode};
+use crate::engine::pdc::PdcDelay;
+use crate::engine::simd;
+use crate::effects::Effect;
+use crate::engine::time::Frames;
+use crate::engine::mute_regions::{self, MuteRegion};
+use crate::engine::crossfades::{self, Span};
+use crate::engine::smoothing::{self, Smoothed};
+use crate::engine::mixer::AuxSend;
+
+/// Upper bound for track / clip notes (bytes of UTF-8).
+pub const MAX_NOTES_BYTES: usize = 16 * 1024;
+
+/// Anti-click ramp applied at both edges of every clip while rendering.
+pub const CLIP_EDGE_FADE: Duration = Duration::from_millis(5);
+
+/// Shortest clip an edit may produce. Imports of tiny files are still allowed to play.
+pub const MIN_CLIP_DURATION: Duration = Duration::from_millis(1);
+
+/// Why a clip edit was refused. Serialized as-is to the UI.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum ClipEditError {
+    /// The edit would leave a clip shorter than `MIN_CLIP_DURATION`.
+    TooShort { duration_secs: f64, min_secs: f64 },
+    /// The time stretch is outside `MIN_STRETCH..=MAX_STRETCH`.
+    StretchOutOfRange { ratio: f64, min: f64, max: f64 },
+}
+
+impl ClipEditError {
+    /// `Ok` when `duration` is long enough to be a clip.
+    pub fn check_duration(duration: Duration) -> Result<(), Self> {
+        if duration < MIN_CLIP_DURATION {
+            return Err(ClipEditError::TooShort {
+                duration_secs: duration.as_secs_f64(),
+                min_secs: MIN_CLIP_DURATION.as_secs_f64(),
+            });
+        }
+        Ok(())
+    }
+
+    /// `Ok` when the clip can be stretched by `ratio`.
+    pub fn check_stretch(ratio: f64) -> Result<(), Self> {
+        if !(MIN_STRETCH..=MAX_STRETCH).contains(&ratio) {
+            return Err(ClipEditError::StretchOutOfRange { ratio, min: MIN_STRETCH, max: MAX_STRETCH });
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ClipEditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClipEditError::TooShort { duration_secs, min_secs } => write!(
+                f,
+                "Clip would be {:.3} ms long; the minimum is {:.0} ms",
+                duration_secs * 1000.0,
+                min_secs * 1000.0
+            ),
+            ClipEditError::StretchOutOfRange { ratio, min, max } => {
+                write!(f, "Time stretch {:.3}x is outside {}x to {}x", ratio, min, max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ClipEditError {}
+
+/// Where a clip sits on the timeline and in its file: everything a trim changes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipBounds {
+    pub start_time: Frames, // at the clip's frame rate
+    pub offset: Frames,     // at the source rate
+    pub duration: Frames,
+    pub stretch: f64,
+    pub varispeed: bool, // the stretch is played tape-style, pitch following speed
+    pub mute_regions: Vec<MuteRegion>, // clip-relative, so they shift with the left edge
+}
+
+/// Listen (solo-in-place on the cue output) tap point of a track.
+/// A monitoring control: never saved with the project.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ListenMode {
+    #[default]
+    Off,
+    /// Pre-fader: after the inserts, before gain/pan/mute.
+    Pfl,
+    /// After-fader: what the track sends to the mix.
+    Afl,
+}
+
+/// What a track holds, shown as its icon. Set by the user or guessed from the audio on import.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrackKind {
+    Drums,
+    Bass,
+    Vocals,
+    Other,
+}
 
 /// Identifier for a track.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -41,9 +142,10 @@ pub struct DecoderHandle {
     consumer: Caching<Arc<SharedRb<Heap<f32>>>, false, true>,
     _decoder_thread: JoinHandle<()>,
     is_playing: Arc<AtomicBool>,
-    seek_tx: Sender<DecoderCmd>,
-    #[allow(dead_code)]
-    output_sample_rate: u32,
+    seek_tx: CmdSender, // preallocated: seeks come from the audio callback
+    seek_sync: Arc<SeekSync>,
+    popped: u64, // samples taken from the ring buffer so far, against `seek_sync`
+    output_sample_rate: u32, // the engine's timeline rate; differs from the device under varispeed
     #[allow(dead_code)]
     output_channels: usize,
 }
@@ -61,8 +163,7 @@ impl DecoderHandle {
 
         let is_playing = Arc::new(AtomicBool::new(true));
 
-        // spawn_decoder_with_ctrl returns (JoinHandle, Sender<DecoderCmd>).
-        let (decoder_thread, seek_tx) = spawn_decoder_with_ctrl(
+        let (decoder_thread, seek_tx, seek_sync) = spawn_decoder_synced(
             path,
             producer,
             is_playing.clone(),
@@ -77,6 +178,8 @@ impl DecoderHandle {
             _decoder_thread: decoder_thread,
             is_playing,
             seek_tx,
+            seek_sync,
+            popped: 0,
             output_sample_rate,
             output_channels,
         })
@@ -86,20 +189,69 @@ impl DecoderHandle {
         self.is_playing.store(playing, Ordering::Relaxed);
     }
 
+    /// Takes effect from the next seek.
+    pub fn set_stretch(&mut self, ratio: f64) {
+        let _ = self.seek_tx.try_push(DecoderCmd::SetStretch(ratio));
+    }
+
+    pub fn output_rate(&self) -> u32 {
+        self.output_sample_rate
+    }
+
+    /// Playback speed on top of the rate conversion, pitch included (1.0 = normal).
+    /// Applies on the fly.
+    pub fn set_rate(&mut self, rate: f64) {
+        let _ = self.seek_tx.try_push(DecoderCmd::SetRate(rate));
+    }
+
+    /// Takes effect from the next seek.
+    pub fn set_output_rate(&mut self, rate: u32) {
+        self.output_sample_rate = rate;
+        let _ = self.seek_tx.try_push(DecoderCmd::SetOutputRate(rate));
+    }
+
     // --- UPDATED: Seek now clears buffer to fix delay ---
     pub fn seek(&mut self, pos: Duration) {
-        // 1. Tell decoder to seek
-        let _ = self.seek_tx.send(DecoderCmd::Seek(pos));
+        // 1. Tell decoder to seek; what it pushes until it gets there is dropped unheard
+        self.seek_sync.request();
+        if self.seek_tx.try_push(DecoderCmd::Seek(pos)).is_err() {
+            // Only when the decoder thread has stopped; the next seek that gets through
+            // settles this one too
+            rt_warn!("⚠️ Decoder command queue full; seek dropped");
+        }
         
         // 2. Clear buffer instantly to remove old audio
         // FIX: Use try_pop() instead of pop()
-        while self.consumer.try_pop().is_some() {}
+        while self.consumer.try_pop().is_some() {
+            self.popped += 1;
+        }
+    }
+
+    // Throw away samples decoded before the last seek was handled. False while the
+    // decoder hasn't caught up, so nothing may be read yet.
+    fn drop_stale(&mut self) -> bool {
+        let Some(fresh_from) = self.seek_sync.fresh_from() else {
+            while self.consumer.try_pop().is_some() {
+                self.popped += 1;
+            }
+            return false;
+        };
+        while self.popped < fresh_from {
+            if self.consumer.try_pop().is_none() {
+                return false;
+            }
+            self.popped += 1;
+        }
+        true
     }
 
     /// Read up to `frames` of interleaved f32 into `dst`. Returns frames actually written.
     /// Read samples and ADD them to the destination buffer (Mixing).
     /// Returns the number of frames actually mixed.
     pub fn mix_interleaved(&mut self, dst: &mut [f32], frames: usize, channels: usize) -> usize {
+        if !self.drop_stale() {
+            return 0;
+        }
         let samples_needed = frames * channels;
         let mut mixed_count = 0usize;
 
@@ -113,29 +265,40 @@ impl DecoderHandle {
                 break; // Buffer empty
             }
         }
+        self.popped += mixed_count as u64;
 
         mixed_count / channels
     }
     /// This keeps the ring buffer in sync when the track is muted.
     pub fn consume(&mut self, frames: usize, channels: usize) {
+        if !self.drop_stale() {
+            return;
+        }
         let samples_needed = frames * channels;
         for _ in 0..samples_needed {
             if self.consumer.try_pop().is_none() {
                 break;
             }
+            self.popped += 1;
         }
     }
 }
 
 pub struct Clip {
     pub path: String,
-    pub start_time: Duration, // Position on timeline
-    pub offset: Duration,     // Start offset in the file (trimming)
-    pub duration: Duration,
-    pub source_duration: Duration, // full file length (never changes)
+    pub start_time: Frames, // Position on timeline, at `rate`
+    pub offset: Frames,     // Start offset in the file (trimming), at `source_sr`
+    pub duration: Frames,   // Length on the timeline, at `rate`
+    pub source_duration: Frames, // full file length at `source_sr` (never changes)
     pub source_sr: u32,
-    pub source_ch: usize,   // Duration on timeline
+    pub source_ch: usize,
     pub clip_number: usize, // <--- NEW: Backend controlled ID
+    pub notes: String,
+    pub mute_regions: Vec<MuteRegion>, // normalized, clip-relative
+    rate: u32, // the timeline fields count at the engine rate the clip was made at, for good
+    stretch: f64, // timeline length / source length; offset is in source time, duration in timeline time
+    varispeed: bool, // play the stretch by resampling (pitch follows) instead of time stretching
+    pub crossfade: Option<Duration>, // fade length where this clip overlaps an earlier one (None = whole overlap)
     decoder: DecoderHandle,
 }
 
@@ -144,23 +307,24 @@ impl Clip {
         
         // 1. Probe to get metadata AND Calculate Duration
         // We need the exact duration to prevent "Seek out of range" errors.
-        let (samples, source_sr, source_ch) = match adapter::decode_to_vec(&path) {
-            Ok((s, sr, ch)) => (s, sr, ch),
+        // Metadata only: the decoder streams, so the samples never need to be held here.
+        let (source_sr, source_ch, source_frames) = match cache::shared().probe(&path) {
+            Ok(meta) => meta,
             Err(e) => {
-                println!("⚠️ Clip Probe Failed, using fallback: {}", e);
-                (Vec::new(), 44100, 2) // Fallback
+                rt_warn!("⚠️ Clip Probe Failed, using fallback: {}", e);
+                (44100, 2, 0) // Fallback
             }
         };
 
-        // Calculate Duration: Total Samples / Channels / Sample Rate
-        let duration_secs = if source_sr > 0 && source_ch > 0 {
-            samples.len() as f64 / source_ch as f64 / source_sr as f64
-        } else {
-            0.0
-        };
-        let duration = Duration::from_secs_f64(duration_secs);
+        let source_duration = if source_sr > 0 && source_ch > 0 { Frames(source_frames) } else { Frames::ZERO };
 
-        println!("📎 Clip: {} | Dur: {:.2}s | {}Hz {}ch", path, duration_secs, source_sr, source_ch);
+        rt_info!(
+            "📎 Clip: {} | Dur: {:.2}s | {}Hz {}ch",
+            path,
+            source_duration.to_seconds(source_sr).0,
+            source_sr,
+            source_ch
+        );
 
         // 2. Create Decoder
         let decoder = DecoderHandle::new_for_engine(
@@ -175,23 +339,31 @@ impl Clip {
 
         Ok(Self {
             path,
-            start_time,
-            offset: Duration::ZERO,
-            duration, // <--- FIX: Use Actual Duration
-            source_duration: duration,
+            start_time: Frames::from_duration(start_time, output_sr),
+            offset: Frames::ZERO,
+            duration: source_duration.rescale(source_sr, output_sr), // <--- FIX: Use Actual Duration
+            source_duration,
             source_sr: source_sr,
             source_ch: source_ch,
             clip_number: 0,
+            notes: String::new(),
+            mute_regions: Vec::new(),
+            rate: output_sr,
+            stretch: 1.0,
+            varispeed: false,
+            crossfade: None,
             decoder,
         })
     }
 
+    /// A clip whose file is already known. `start_time` and `duration` count at `output_sr`,
+    /// `offset` and `source_duration` at `source_sr`.
     pub fn new_known(
         path: String, 
-        start_time: Duration, 
-        offset: Duration,
-        duration: Duration,          // timeline length
-        source_duration: Duration,   // full file length
+        start_time: Frames, 
+        offset: Frames,
+        duration: Frames,          // timeline length
+        source_duration: Frames,   // full file length
         source_sr: u32,
         source_ch: usize,
         output_sr: u32, 
@@ -217,11 +389,17 @@ impl Clip {
             source_sr,
             source_ch,
             clip_number: 0,
+            notes: String::new(),
+            mute_regions: Vec::new(),
+            rate: output_sr,
+            stretch: 1.0,
+            varispeed: false,
+            crossfade: None,
             decoder,
         };
         
         // Ensure the decoder internal buffer is at the right spot
-        clip.seek(start_time);
+        clip.seek(clip.start());
 
         Ok(clip)
     }
@@ -230,22 +408,173 @@ impl Clip {
         self.decoder.set_playing(playing);
     }
 
+    /// Rate `start_time` and `duration` count at. It never changes, so edits (and their undo)
+    /// stay exact across device rate changes; clips made before and after one can differ, so
+    /// anything comparing two clips goes through `start`/`end`.
+    pub fn frame_rate(&self) -> u32 {
+        self.rate
+    }
+
+    pub fn start(&self) -> Duration {
+        self.start_time.to_duration(self.rate)
+    }
+
+    pub fn end(&self) -> Duration {
+        self.end_frame().to_duration(self.rate)
+    }
+
+    /// End on the timeline, at `frame_rate`.
+    pub fn end_frame(&self) -> Frames {
+        self.start_time + self.duration
+    }
+
+    /// Length on the timeline.
+    pub fn length(&self) -> Duration {
+        self.duration.to_duration(self.rate)
+    }
+
+    /// Trim into the source file.
+    pub fn offset_time(&self) -> Duration {
+        self.offset.to_duration(self.source_sr)
+    }
+
+    // Timeline frames <-> the source frames they play, through the stretch
+    fn to_source(&self, timeline: Frames) -> Frames {
+        let source = timeline.rescale(self.rate, self.source_sr);
+        Frames((source.0 as f64 / self.stretch).round() as u64)
+    }
+
+    fn to_timeline(&self, source: Frames) -> Frames {
+        Frames((source.0 as f64 * self.stretch).round() as u64).rescale(self.source_sr, self.rate)
+    }
+
+    pub fn stretch(&self) -> f64 {
+        self.stretch
+    }
+
+    pub fn span(&self) -> Span {
+        Span { start: self.start(), end: self.end(), crossfade: self.crossfade }
+    }
+
+    /// Play stretched by `ratio` (no pitch change). Leaves `duration` alone and takes effect
+    /// from the next seek; `with_stretch` + `Track::set_clip_bounds` does the whole edit.
+    pub fn set_stretch(&mut self, ratio: f64) {
+        if ratio != self.stretch {
+            self.stretch = ratio;
+            self.apply_stretch();
+        }
+    }
+
+    pub fn varispeed(&self) -> bool {
+        self.varispeed
+    }
+
+    /// Play the stretch as varispeed: faster and higher, or slower and lower, like a tape.
+    /// The timeline length is the same either way; takes effect from the next seek.
+    pub fn set_varispeed(&mut self, on: bool) {
+        if on != self.varispeed {
+            self.varispeed = on;
+            self.apply_stretch();
+        }
+    }
+
+    // One of the two decoder stages carries the stretch, the other stays neutral
+    fn apply_stretch(&mut self) {
+        if self.varispeed {
+            self.decoder.set_stretch(1.0);
+            self.decoder.set_rate(1.0 / self.stretch);
+        } else {
+            self.decoder.set_stretch(self.stretch);
+            self.decoder.set_rate(1.0);
+        }
+    }
+
+    pub fn bounds(&self) -> ClipBounds {
+        ClipBounds {
+            start_time: self.start_time,
+            offset: self.offset,
+            duration: self.duration,
+            stretch: self.stretch,
+            varispeed: self.varispeed,
+            mute_regions: self.mute_regions.clone(),
+        }
+    }
+
+    /// Bounds with the left edge at `new_start`; the audio stays where it is on the timeline.
+    /// Clamped to the start of the file (and of the timeline) and to the right edge.
+    pub fn with_start(&self, new_start: Duration) -> Result<ClipBounds, ClipEditError> {
+        let end = self.end_frame();
+        let earliest = self.start_time.saturating_sub(self.to_timeline(self.offset));
+        let new_start = Frames::from_duration(new_start, self.rate).clamp(earliest, end);
+        let duration = end - new_start;
+        ClipEditError::check_duration(duration.to_duration(self.rate))?;
+
+        // The offset moves in source time
+        let (offset, shift) = if new_start >= self.start_time {
+            let d = new_start - self.start_time;
+            (self.offset + self.to_source(d), d.to_seconds(self.rate).0)
+        } else {
+            let d = self.start_time - new_start;
+            (self.offset.saturating_sub(self.to_source(d)), -d.to_seconds(self.rate).0)
+        };
+        Ok(ClipBounds {
+            start_time: new_start,
+            offset,
+            duration,
+            stretch: self.stretch,
+            varispeed: self.varispeed,
+            mute_regions: mute_regions::slice(&self.mute_regions, shift, f64::INFINITY),
+        })
+    }
+
+    /// Bounds with the right edge at `new_end`, clamped to the end of the file.
+    pub fn with_end(&self, new_end: Duration) -> Result<ClipBounds, ClipEditError> {
+        let latest = self.start_time + self.to_timeline(self.source_duration.saturating_sub(self.offset));
+        let duration = Frames::from_duration(new_end, self.rate).clamp(self.start_time, latest) - self.start_time;
+        ClipEditError::check_duration(duration.to_duration(self.rate))?;
+        Ok(ClipBounds {
+            start_time: self.start_time,
+            offset: self.offset,
+            duration,
+            stretch: self.stretch,
+            varispeed: self.varispeed,
+            mute_regions: mute_regions::slice(&self.mute_regions, 0.0, duration.to_seconds(self.rate).0),
+        })
+    }
+
+    /// Bounds stretched to `ratio` (timeline length / source length) from the same start and
+    /// offset: the clip gets longer or shorter and its mute regions scale with it.
+    pub fn with_stretch(&self, ratio: f64) -> Result<ClipBounds, ClipEditError> {
+        ClipEditError::check_stretch(ratio)?;
+        let scale = ratio / self.stretch;
+        let duration = Frames((self.duration.0 as f64 * scale).round() as u64);
+        ClipEditError::check_duration(duration.to_duration(self.rate))?;
+        Ok(ClipBounds {
+            start_time: self.start_time,
+            offset: self.offset,
+            duration,
+            stretch: ratio,
+            varispeed: self.varispeed,
+            mute_regions: self
+                .mute_regions
+                .iter()
+                .map(|r| MuteRegion { start: r.start * scale, end: r.end * scale })
+                .collect(),
+        })
+    }
+
     pub fn seek(&mut self, global_pos: Duration) {
-        // Source-file playback position (seconds into the original file)
-        let file_pos = if global_pos >= self.start_time {
-            global_pos - self.start_time + self.offset
+        // Source-file playback position (frames into the original file)
+        let global = Frames::from_duration(global_pos, self.rate);
+        let file_pos = if global >= self.start_time {
+            self.to_source(global - self.start_time) + self.offset
         } else {
             self.offset
         };
 
-        // Guard against seeking past end of the *source file*.
-        // NOTE: This requires you to store the full source duration in the Clip.
-        // If you don't have it yet, temporarily remove this guard.
-        if file_pos >= self.source_duration {
-            return;
-        }
-
-        self.decoder.seek(file_pos);
+        // Past the end of the source the decoder just goes quiet; seeking there still drops
+        // whatever was buffered for the old position
+        self.decoder.seek(file_pos.min(self.source_duration).to_duration(self.source_sr));
     }
 
     // [ADD THIS METHOD TO impl Clip]
@@ -261,23 +590,23 @@ impl Clip {
     ) -> anyhow::Result<Self> {
         // 1. Probe the file to get REAL source metadata (SR, Channels, Length)
         // We need this because the save file might not have technical file details
-        let (samples, source_sr, source_ch) = match adapter::decode_to_vec(&path) {
-            Ok((s, sr, ch)) => (s, sr, ch),
+        let (source_sr, source_ch, source_frames) = match cache::shared().probe(&path) {
+            Ok(meta) => meta,
             Err(e) => {
-                println!("⚠️ Clip Recovery Probe Failed: {}", e);
-                (Vec::new(), 44100, 2)
+                rt_warn!("⚠️ Clip Recovery Probe Failed: {}", e);
+                (44100, 2, 0)
             }
         };
 
         // Calculate REAL source duration
-        let source_dur_secs = if source_sr > 0 && source_ch > 0 {
-            samples.len() as f64 / source_ch as f64 / source_sr as f64
-        } else {
-            0.0
-        };
-        let source_duration = Duration::from_secs_f64(source_dur_secs);
+        let source_duration = if source_sr > 0 && source_ch > 0 { Frames(source_frames) } else { Frames::ZERO };
 
-        println!("♻️ Recovered: {} | Src: {:.2}s | Trim: {:.2}s", path, source_dur_secs, duration.as_secs_f64());
+        rt_info!(
+            "♻️ Recovered: {} | Src: {:.2}s | Trim: {:.2}s",
+            path,
+            source_duration.to_seconds(source_sr).0,
+            duration.as_secs_f64()
+        );
 
         // 2. Create Decoder
         let decoder = DecoderHandle::new_for_engine(
@@ -291,13 +620,19 @@ impl Clip {
         // 3. Construct Clip using the PROBED source info + SAVED trim info
         let mut clip = Self {
             path,
-            start_time,
-            offset,          // <--- RESPECT SAVED OFFSET
-            duration,        // <--- RESPECT SAVED DURATION
+            start_time: Frames::from_duration(start_time, output_sr),
+            offset: Frames::from_duration(offset, source_sr),       // <--- RESPECT SAVED OFFSET
+            duration: Frames::from_duration(duration, output_sr),   // <--- RESPECT SAVED DURATION
             source_duration, // <--- USE PROBED SOURCE LENGTH
             source_sr,
             source_ch,
             clip_number: 0,
+            notes: String::new(),
+            mute_regions: Vec::new(),
+            rate: output_sr,
+            stretch: 1.0,
+            varispeed: false,
+            crossfade: None,
             decoder,
         };
 
@@ -318,15 +653,43 @@ pub struct Track {
     pub pan: f32, // -1.0 left, 0 center, +1.0 right
     pub muted: bool,
     pub solo: bool,
+    pub armed: bool,
+    pub record_inputs: Vec<usize>, // 0-based hardware inputs a take captures (empty = all of them)
+    pub record_device: Option<String>, // input device those belong to (None = the default input)
+    pub record_safe: bool, // can never be armed (e.g. the reference mix)
+    pub solo_safe: bool,   // keeps playing while other tracks are soloed (e.g. a reverb return)
+    pub notes: String,
+    pub kind: Option<TrackKind>,
+    pub kind_manual: bool, // chosen by the user; the classifier never overrides it
+    pub listen: ListenMode, // set through Engine::set_track_listen (one track at a time)
+    listen_buffer: Vec<f32>, // PFL/AFL copy of the last block
     state: TrackState,
     pub clips: Vec<Clip>,
+    clip_spans: Vec<Span>, // crossfade layout of `clips`, refilled every block
+    clip_buffer: Vec<f32>, // one clip's audio for the current block, reused
+    frozen: Option<Clip>, // rendered clips + inserts, playing instead of them (see session::freeze)
+    discarded: Vec<Clip>, // removed clips, waiting to be dropped off the audio thread
     pub track_eq: TrackEq,
     pub track_compressor: CompressorNode,
     pub track_reverb: ReverbNode,
+    pub inserts: EffectChain, // user insert chain, after the built-in strip
+    pub fx_bypass: bool, // skip EQ, compressor, reverb and the insert chain (settings are kept)
+    bypass_mix: Smoothed, // 0 through the fx, 1 bypassed; toggling crossfades over `smoothing::PARAM_RAMP`
+    bypass_delay: PdcDelay, // the dry signal, held back by the chain's latency
+    bypass_dry: Vec<f32>, // dry copy of the block, reused
     pub meters: std::sync::Arc<TrackMeters>, // <--- Shared with UI
     meter_state: MeterState,                 // <--- Owned by Audio Thread
     pub analysis: Arc<std::sync::Mutex<Option<AnalysisProfile>>>,
     pub volume_automation: AutomationCurve<f32>, 
+    pub automation_mode: AutomationMode, // how the insert parameter lanes read and write
+    pub sends: Vec<AuxSend>, // post-fader feeds into aux buses (set through Engine::set_track_send)
+    pub group: Option<u32>, // group bus this track is summed into (set through Engine::set_track_group)
+    pdc: PdcDelay, // compensation delay set by the engine (project latency - own latency)
+    pub(crate) audible: bool, // solo gate, set by the mixer before each block
+    // Fader as heard; gain, pan, mute and solo changes ramp over `smoothing::PARAM_RAMP`
+    // instead of stepping
+    gain_smooth: Smoothed,
+    pan_smooth: Smoothed,
     // --- Track Start Time (for Drag & Drop) ---
 }
 
@@ -379,16 +742,73 @@ impl Track {
             pan: 0.0,
             muted: false,
             solo: false,
+            armed: false,
+            record_inputs: Vec::new(),
+            record_device: None,
+            record_safe: false,
+            solo_safe: false,
+            notes: String::new(),
+            kind: None,
+            kind_manual: false,
+            listen: ListenMode::Off,
+            listen_buffer: Vec::with_capacity(4096 * channels),
             state: TrackState::Stopped,
             clips: Vec::new(),
+            clip_spans: Vec::new(),
+            clip_buffer: Vec::new(),
+            frozen: None,
+            discarded: Vec::with_capacity(4),
             track_eq: TrackEq::new(sample_rate, channels),
-            track_compressor: CompressorNode::new(sample_rate as f32),
+            track_compressor: CompressorNode::new(sample_rate as f32, channels),
             track_reverb: ReverbNode::new(sample_rate as f32),
+            inserts: EffectChain::new(channels, sample_rate),
+            fx_bypass: false,
+            bypass_mix: Smoothed::new(0.0),
+            bypass_delay: PdcDelay::new(channels),
+            bypass_dry: Vec::with_capacity(4096 * channels),
             meters: TrackMeters::new(),                      
             meter_state: MeterState::new(sample_rate as f32),
             analysis: Arc::new(std::sync::Mutex::new(None)),
             volume_automation: AutomationCurve::new(),
+            automation_mode: AutomationMode::Read,
+            sends: Vec::new(),
+            group: None,
+            pdc: PdcDelay::new(channels),
+            audible: true,
+            gain_smooth: Smoothed::new(1.0),
+            pan_smooth: Smoothed::new(0.0),
+        }
+    }
+
+    /// Fader gain this block ramps towards (0 while muted or soloed out).
+    fn target_gain(&self) -> f32 {
+        if self.muted || !self.audible { 0.0 } else { self.gain }
+    }
+
+    /// PFL/AFL signal of the last rendered block (empty when not listening or not playing).
+    pub fn listen_buffer(&self) -> &[f32] {
+        &self.listen_buffer
+    }
+
+    /// Total latency of this track's inserts, in frames. Bypassing them keeps it: the dry
+    /// signal is delayed to match, so toggling the bypass never moves the track in time.
+    pub fn latency_frames(&self) -> usize {
+        if self.frozen.is_some() {
+            return 0;
         }
+        self.track_eq.latency_frames()
+            + self.track_compressor.latency_frames()
+            + self.track_reverb.latency_frames()
+            + self.inserts.latency_frames()
+    }
+
+    /// Compensation delay currently applied to this track's output.
+    pub fn pdc_delay_frames(&self) -> usize {
+        self.pdc.delay_frames()
+    }
+
+    pub(crate) fn set_pdc_delay_frames(&mut self, frames: usize) {
+        self.pdc.set_delay_frames(frames);
     }
 
     // Helper to add a clip (used by Engine)
@@ -402,14 +822,20 @@ impl Track {
     ) -> anyhow::Result<()> {
         
 
-        // ✅ Clone the path FIRST, before `Clip::new` eats it
-        let file_path = path.clone();
-
         // 1. Create the clip
         // Note: Clip::new defaults to full length. 
         // If you are calling this from a loader, use 'restore_clip' instead!
-        let mut clip = Clip::new(path, start_time, sr, ch)?;
+        let clip = Clip::new(path, start_time, sr, ch)?;
+        let file_path = clip.path.clone();
+        self.add_prepared_clip(clip, current_time);
+        // --- NEW: Trigger Background Analysis ---
+        Self::analyze_in_background(Arc::clone(&self.analysis), file_path);
+        Ok(())
+    }
 
+    /// Adds a clip opened elsewhere: `Clip::new` reads the file, so a caller on the audio
+    /// thread's side does that first, and starts `analyze_in_background` itself afterwards.
+    pub fn add_prepared_clip(&mut self, mut clip: Clip, current_time: Option<Duration>) {
         // 3. Sync Position: If we know the current engine time, seek the clip immediately!
         if let Some(time) = current_time {
             clip.seek(time);
@@ -422,21 +848,20 @@ impl Track {
 
         self.clips.push(clip);
         self.renumber_clips();
-        // --- NEW: Trigger Background Analysis ---
-        // --- NEW: Trigger Background Analysis ---
-        let analysis_ref = Arc::clone(&self.analysis);
+    }
+
+    /// Fills `analysis` from `file_path` on a new thread.
+    pub fn analyze_in_background(analysis_ref: Arc<std::sync::Mutex<Option<AnalysisProfile>>>, file_path: String) {
         std::thread::spawn(move || {
-            println!("🔍 Starting background analysis for: {}", file_path);
-            if let Ok((samples, source_sr, source_ch)) = crate::bpm::adapter::decode_to_vec(&file_path) {
-                let profile = crate::analyzer::analyze_audio_buffer(&samples, source_ch, source_sr);
+            rt_info!("🔍 Starting background analysis for: {}", file_path);
+            if let Ok(pcm) = cache::shared().get(&file_path) {
+                let profile = crate::analyzer::analyze_audio_buffer(&pcm.samples, pcm.channels, pcm.sample_rate);
                 if let Ok(mut guard) = analysis_ref.lock() {
                     *guard = Some(profile);
                 }
-                println!("✅ Analysis complete for: {}", file_path);
+                rt_info!("✅ Analysis complete for: {}", file_path);
             }
         });
-
-        Ok(())
     }
     
 
@@ -446,17 +871,56 @@ impl Track {
 
     pub fn set_state(&mut self, st: TrackState) {
         self.state = st;
-        for clip in &self.clips {
-            clip.set_playing(matches!(st, TrackState::Playing));
+        // While frozen the clips' decoders stay idle
+        let playing = matches!(st, TrackState::Playing);
+        match &self.frozen {
+            Some(rendered) => rendered.set_playing(playing),
+            None => self.clips.iter().for_each(|clip| clip.set_playing(playing)),
         }
-            
     }
 
     pub fn seek(&mut self, global_pos: Duration) {
         // Seek ALL clips so they are ready when the playhead hits them
-        for clip in &mut self.clips {
+        for clip in self.clips.iter_mut().chain(self.frozen.as_mut()) {
             clip.seek(global_pos);
         }
+        // Drop compensation audio from the old position
+        self.pdc.reset();
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.is_some()
+    }
+
+    /// Play `rendered` (this track's clips printed through its inserts, starting at timeline
+    /// zero) in place of the clips and inserts.
+    pub fn freeze(&mut self, mut rendered: Clip, position: Duration) {
+        self.clips.iter().for_each(|clip| clip.set_playing(false));
+        rendered.seek(position);
+        rendered.set_playing(self.is_playing());
+        self.frozen = Some(rendered);
+        self.pdc.reset();
+    }
+
+    /// Back to the clips and inserts. Returns the rendered file's path.
+    pub fn unfreeze(&mut self, position: Duration) -> Option<String> {
+        let rendered = self.frozen.take()?;
+        rendered.set_playing(false);
+        let playing = self.is_playing();
+        for clip in &mut self.clips {
+            clip.seek(position);
+            clip.set_playing(playing);
+        }
+        self.pdc.reset();
+        let path = rendered.path.clone();
+        self.discarded.push(rendered);
+        Some(path)
+    }
+
+    /// Clips removed since the last call. Each owns a decoder thread, so the engine hands
+    /// them to a control thread to drop (see `Engine::drain_discarded`).
+    pub(crate) fn take_discarded(&mut self) -> std::vec::Drain<'_, Clip> {
+        self.discarded.drain(..)
     }
 
     // pub fn is_active(&self) -> bool {
@@ -470,13 +934,13 @@ impl Track {
     // --- NEW: Automatically numbers clips sequentially from left to right ---
     pub fn renumber_clips(&mut self) {
         // Ensure clips are strictly sorted by timeline position
-        self.clips.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+        self.clips.sort_by_key(|c| c.start());
         
-        println!("🔍 DEBUG: Renumbering {} clips on Track ID: {}", self.clips.len(), self.id.0);
+        rt_debug!("🔍 DEBUG: Renumbering {} clips on Track ID: {}", self.clips.len(), self.id.0);
         
         for (i, clip) in self.clips.iter_mut().enumerate() {
             clip.clip_number = i + 1;
-            println!("   -> Set Clip at {:.2}s to Number {}", clip.start_time.as_secs_f64(), clip.clip_number);
+            rt_debug!("   -> Set Clip at {:.2}s to Number {}", clip.start().as_secs_f64(), clip.clip_number);
         }
     }
 
@@ -485,18 +949,13 @@ impl Track {
            return Err(anyhow::anyhow!("No next clip to merge"));
        }
     
-       let eps = 0.001; // 1ms tolerance
+       let eps = Duration::from_millis(1); // 1ms tolerance
     
        let left = &self.clips[clip_index];
        let right = &self.clips[clip_index + 1];
     
-       let left_start = left.start_time.as_secs_f64();
-       let left_dur = left.duration.as_secs_f64();
-       let left_end = left_start + left_dur;
-       let right_start = right.start_time.as_secs_f64();
-    
        // Must touch on timeline
-       if (right_start - left_end).abs() > eps {
+       if right.start().abs_diff(left.end()) > eps {
            return Err(anyhow::anyhow!("Clips are not adjacent on the timeline"));
        }
     
@@ -505,17 +964,29 @@ impl Track {
            return Err(anyhow::anyhow!("Clips have different source paths"));
        }
     
+       if left.stretch != right.stretch || left.varispeed != right.varispeed {
+           return Err(anyhow::anyhow!("Clips have different time stretch"));
+       }
+
        // Must be contiguous in source file
-       let left_src_end = (left.offset + left.duration).as_secs_f64();
-       let right_src_start = right.offset.as_secs_f64();
-       if (right_src_start - left_src_end).abs() > eps {
+       let left_src_end = left.offset + left.to_source(left.duration);
+       if right.offset.0.abs_diff(left_src_end.0) > Frames::from_duration(eps, left.source_sr).0 {
            return Err(anyhow::anyhow!("Clips are not contiguous in source"));
        }
     
        // Apply merge: extend left, remove right
        let right_duration = self.clips[clip_index + 1].duration;
-       self.clips[clip_index].duration += right_duration;
-       self.clips.remove(clip_index + 1);
+       let seam = self.clips[clip_index].length().as_secs_f64();
+       let right_regions: Vec<MuteRegion> = self.clips[clip_index + 1].mute_regions.iter()
+           .map(|r| MuteRegion { start: r.start + seam, end: r.end + seam })
+           .collect();
+       let left = &mut self.clips[clip_index];
+       left.duration += right_duration;
+       for region in right_regions {
+           mute_regions::insert(&mut left.mute_regions, region);
+       }
+       let right = self.clips.remove(clip_index + 1);
+       self.discarded.push(right);
        self.renumber_clips();
     
        Ok(())
@@ -525,31 +996,32 @@ impl Track {
     pub fn split_at_time(
         &mut self,
         split_time: Duration,
-        output_sr: u32,
         output_ch: usize
     ) -> anyhow::Result<()> {
 
-        let split_secs = split_time.as_secs_f64();
-
         // Find the index first so we can insert next to it
 
         for (i, clip) in self.clips.iter_mut().enumerate() {
-            let start = clip.start_time.as_secs_f64();
-            let end = start + clip.duration.as_secs_f64();
+            let start = clip.start_time;
+            let end = clip.end_frame();
+            let split = Frames::from_duration(split_time, clip.rate);
 
-            if split_secs > start && split_secs < end {
+            if split > start && split < end {
 
-                let relative_split_secs = split_secs - start;
-                let relative_split = Duration::from_secs_f64(relative_split_secs);
+                let relative_split = split - start;
 
-                let right_start = split_time;
-                let right_offset = clip.offset + relative_split;
+                let right_start = split;
+                let right_offset = clip.offset + clip.to_source(relative_split);
                 let right_duration = clip.duration - relative_split;
 
+                // Splitting right at an edge would leave a sliver that can't be edited or heard
+                ClipEditError::check_duration(relative_split.to_duration(clip.rate))?;
+                ClipEditError::check_duration(right_duration.to_duration(clip.rate))?;
+
                 // Left side becomes shorter on the timeline
                 clip.duration = relative_split;
 
-                let new_clip = Clip::new_known(
+                let mut new_clip = Clip::new_known(
                     clip.path.clone(),
                     right_start,
                     right_offset,
@@ -557,16 +1029,25 @@ impl Track {
                     clip.source_duration,
                     clip.source_sr,
                     clip.source_ch,
-                    output_sr,
+                    clip.rate,
                     output_ch
                 )?;
+                new_clip.notes = clip.notes.clone(); // both halves keep the annotation
+                if clip.stretch != 1.0 || clip.varispeed {
+                    new_clip.set_stretch(clip.stretch);
+                    new_clip.set_varispeed(clip.varispeed);
+                    new_clip.seek(new_clip.start());
+                }
+                let split_secs = relative_split.to_seconds(clip.rate).0;
+                new_clip.mute_regions = mute_regions::slice(&clip.mute_regions, split_secs, f64::INFINITY);
+                clip.mute_regions = mute_regions::slice(&clip.mute_regions, 0.0, split_secs);
 
                 // IMPORTANT: preserve full file duration + metadata
                 // If your new_known doesn't set these yet, update it to do so.
                 self.clips.insert(i + 1, new_clip);
                 self.renumber_clips();
 
-                println!("✂️ Split successful @ {:.2}s", split_secs);
+                rt_info!("✂️ Split successful @ {:.2}s", split_time.as_secs_f64());
                 break;
             }
         }
@@ -575,114 +1056,192 @@ impl Track {
     }
 
 
-    pub fn move_clip(&mut self, clip_index: usize, new_start: Duration) {
+    pub fn move_clip(&mut self, clip_index: usize, new_start: Duration, position: Duration) {
         if let Some(clip) = self.clips.get_mut(clip_index) {
-            clip.start_time = new_start;
-            // Optionally seek immediately if the clip is currently playing 
-            // so the change is audible instantly without restart
-            // clip.seek(...) 
+            clip.start_time = Frames::from_duration(new_start, clip.rate);
+            // Re-seek so the change is audible instantly without restart
+            clip.seek(position);
         }
         // 🚀 FIX: Re-sort and assign stable IDs after moving
         self.renumber_clips();
     }
 
+    /// Apply bounds from `Clip::with_start`/`with_end` (or saved for undo) and re-seek the clip.
+    pub fn set_clip_bounds(&mut self, clip_index: usize, bounds: ClipBounds, position: Duration) -> anyhow::Result<()> {
+        let clip = self.clips.get_mut(clip_index).ok_or_else(|| anyhow::anyhow!("Clip index out of bounds"))?;
+        ClipEditError::check_duration(bounds.duration.to_duration(clip.rate))?;
+        ClipEditError::check_stretch(bounds.stretch)?;
+        clip.start_time = bounds.start_time;
+        clip.offset = bounds.offset;
+        clip.duration = bounds.duration;
+        clip.set_stretch(bounds.stretch);
+        clip.set_varispeed(bounds.varispeed);
+        clip.mute_regions = bounds.mute_regions;
+        clip.seek(position);
+        self.renumber_clips();
+        Ok(())
+    }
+
+    /// Move the left edge of a clip to `new_start`, revealing or hiding audio.
+    pub fn trim_clip_start(&mut self, clip_index: usize, new_start: Duration, position: Duration) -> anyhow::Result<()> {
+        let clip = self.clips.get(clip_index).ok_or_else(|| anyhow::anyhow!("Clip index out of bounds"))?;
+        let bounds = clip.with_start(new_start)?;
+        self.set_clip_bounds(clip_index, bounds, position)
+    }
+
+    /// Move the right edge of a clip to `new_end`.
+    pub fn trim_clip_end(&mut self, clip_index: usize, new_end: Duration, position: Duration) -> anyhow::Result<()> {
+        let clip = self.clips.get(clip_index).ok_or_else(|| anyhow::anyhow!("Clip index out of bounds"))?;
+        let bounds = clip.with_end(new_end)?;
+        self.set_clip_bounds(clip_index, bounds, position)
+    }
+
+    /// Crossfade length where a clip enters an earlier one (`None` when it overlaps none).
+    pub fn clip_crossfade(&self, clip_index: usize) -> Option<Duration> {
+        let spans: Vec<Span> = self.clips.iter().map(Clip::span).collect();
+        crossfades::crossfade_in(&spans, clip_index)
+    }
+
+    /// Set the crossfade where a clip enters the clip it overlaps; `None` fades over the whole
+    /// overlap. Longer than the overlap is fine, it is clamped while playing.
+    pub fn set_clip_crossfade(&mut self, clip_index: usize, length: Option<Duration>) -> anyhow::Result<()> {
+        if clip_index >= self.clips.len() {
+            return Err(anyhow::anyhow!("Clip index out of bounds"));
+        }
+        if length.is_some() && self.clip_crossfade(clip_index).is_none() {
+            return Err(anyhow::anyhow!("Clip does not overlap an earlier clip"));
+        }
+        self.clips[clip_index].crossfade = length;
+        Ok(())
+    }
+
     pub fn delete_clip(&mut self, clip_index: usize) -> anyhow::Result<()> {
         if clip_index < self.clips.len() {
-            self.clips.remove(clip_index);
+            let clip = self.clips.remove(clip_index);
+            self.discarded.push(clip);
             self.renumber_clips();
-            println!("🗑️ Deleted clip at index {}", clip_index);
+            rt_info!("🗑️ Deleted clip at index {}", clip_index);
             Ok(())
         } else {
             Err(anyhow::anyhow!("Clip index out of bounds"))
         }
     }
 
-    /// Pull `frames` of interleaved f32 into `dst`.
+    /// Pull `frames` of interleaved f32 into `dst`, starting at timeline frame `block_start`
+    /// (counted at `sample_rate`, the timeline rate).
     /// Handles start_time offset logic.
     pub fn render_into(
         &mut self, 
         dst: &mut [f32], 
         channels: usize, 
-        engine_time: Duration, 
+        block_start: Frames, 
         sample_rate: u32
     ) -> usize {
         dst.fill(0.0);
+        self.listen_buffer.clear();
 
         if !self.is_playing(){
+            // Nothing is heard, so the next block starts from the current settings
+            self.gain_smooth.snap(self.target_gain());
+            self.pan_smooth.snap(self.pan);
+            self.bypass_mix.snap(if self.fx_bypass { 1.0 } else { 0.0 });
             return 0;
         }
 
-        // 1. Calculate time overlap
-        
-        // let current_secs = engine_time.as_secs_f64();
-        let buffer_duration = (dst.len() / channels) as f64 / sample_rate as f64;
-        let start_secs = engine_time.as_secs_f64();
-        let end_secs = start_secs + buffer_duration;
+        // 1. Calculate time overlap (all in frames at the timeline rate)
+        let frames = dst.len() / channels;
+        let block_end = block_start + Frames(frames as u64);
 
         let mut active_clips = 0;
 
         // --- NEW: Calculate Automation Boundaries in dB ---
-        let frames = dst.len() / channels;
-        let start_sample = (start_secs * sample_rate as f64).round() as u64;
-        let end_sample = start_sample + frames as u64;
+        let start_sample = block_start.0;
+        let end_sample = block_end.0;
 
         // 1. Fetch from automation curve (default to 0.0 dB / unity gain if no automation exists)
         let start_gain_db = self.volume_automation.get_value_at_time(start_sample, 0.0);
         let end_gain_db = self.volume_automation.get_value_at_time(end_sample, 0.0);
 
-        // 2. Convert dB to Linear Multiplier and combine with the static Track Fader (self.gain)
-        let start_gain_linear = self.gain * 10.0_f32.powf(start_gain_db / 20.0);
-        let end_gain_linear = self.gain * 10.0_f32.powf(end_gain_db / 20.0);
+        // 2. Convert dB to Linear Multiplier. The automation is interpolated across the block;
+        // the fader (mute and solo included) ramps on its own so changes never click
+        let start_auto_linear = 10.0_f32.powf(start_gain_db / 20.0);
+        let end_auto_linear = 10.0_f32.powf(end_gain_db / 20.0);
+        let ramp = smoothing::ramp_frames(sample_rate);
+        self.gain_smooth.set_target(self.target_gain(), ramp);
+        self.pan_smooth.set_target(self.pan, ramp);
 
         // 3. Determine if we should actually mix audio or just discard it.
         // A linear gain of > 0.0001 is roughly above -80dB (threshold of hearing)
-        let is_audible = !self.muted && (start_gain_linear > 0.0001 || end_gain_linear > 0.0001);
+        let fader_peak = self.gain_smooth.current().max(self.gain_smooth.target());
+        let fader_audible = fader_peak * start_auto_linear.max(end_auto_linear) > 0.0001;
+        // PFL wants the signal even with the fader down or the track muted
+        let is_audible = fader_audible || self.listen == ListenMode::Pfl;
 
         // 1. Loop through all clips and mix them
-        // 1. Loop through all clips and mix them
-        for clip in &mut self.clips {
-            let clip_start = clip.start_time.as_secs_f64();
-            let clip_end = clip_start + clip.duration.as_secs_f64(); // <--- FIX: Use duration
+        // A frozen track plays its rendered file instead, inserts (and crossfades) already printed
+        self.clip_spans.clear();
+        if self.frozen.is_none() {
+            self.clip_spans.extend(self.clips.iter().map(Clip::span));
+        }
+        let clips = match self.frozen.as_mut() {
+            Some(rendered) => std::slice::from_mut(rendered),
+            None => &mut self.clips[..],
+        };
+        for (clip_index, clip) in clips.iter_mut().enumerate() {
+            // Varispeed changed the timeline rate: decode at the new one from here
+            if clip.decoder.output_rate() != sample_rate {
+                clip.decoder.set_output_rate(sample_rate);
+                clip.seek(block_start.to_duration(sample_rate));
+            }
+            let clip_start = clip.start_time.rescale(clip.rate, sample_rate);
+            let clip_end = clip.end_frame().rescale(clip.rate, sample_rate); // <--- FIX: Use duration
 
             // --- FIX: Check if we are entirely past the clip ---
             // If the buffer starts AFTER the clip ends, skip it.
-            if start_secs >= clip_end {
+            if block_start >= clip_end {
                 continue;
             }
             // If the buffer ends BEFORE the clip starts, skip it.
-            if end_secs <= clip_start {
+            if block_end <= clip_start {
                 continue;
             }
             // ---------------------------------------------------
 
             // Calculate buffer offset (silence before clip starts in this block)
-            let mut offset_frames = 0;
-            if start_secs < clip_start {
-                let diff = clip_start - start_secs;
-                offset_frames = (diff * sample_rate as f64).round() as usize;
-            }
+            let offset_frames = clip_start.saturating_sub(block_start).as_usize();
             
             if offset_frames * channels >= dst.len() { continue; }
 
             let mix_dst = &mut dst[(offset_frames * channels)..];
             let frames_to_mix = mix_dst.len() / channels;
 
-            let fade_frames = ((sample_rate as f32) * 0.005) as usize; // 5ms
+            let fade_frames = ((sample_rate as f32) * CLIP_EDGE_FADE.as_secs_f32()) as usize;
 
             if is_audible {
-                // Render clip audio into a temp buffer first
-                let mut temp = vec![0.0f32; frames_to_mix * channels];
-                let written = clip.decoder.mix_interleaved(&mut temp, frames_to_mix, channels);
+                // Render clip audio into a scratch buffer first
+                self.clip_buffer.clear();
+                self.clip_buffer.resize(frames_to_mix * channels, 0.0);
+                let temp = &mut self.clip_buffer[..];
+                let written = clip.decoder.mix_interleaved(temp, frames_to_mix, channels);
             
                 if written > 0 {
+                    if !clip.mute_regions.is_empty() {
+                        let first_frame = block_start.max(clip_start).0 - clip_start.0;
+                        mute_regions::apply(&mut temp[..written * channels], channels, first_frame, &clip.mute_regions, sample_rate);
+                    }
+                    if self.clip_spans.len() > 1 {
+                        let first_frame = block_start.max(clip_start).0;
+                        crossfades::apply(&mut temp[..written * channels], channels, first_frame, clip_index, &self.clip_spans, sample_rate);
+                    }
+
                     // Detect whether this engine block contains the clip start or end
-                    let start_edge_in_block = start_secs < clip_start && end_secs > clip_start;
-                    let end_edge_in_block = start_secs < clip_end && end_secs > clip_end;
+                    let start_edge_in_block = block_start < clip_start && block_end > clip_start;
+                    let end_edge_in_block = block_start < clip_end && block_end > clip_end;
                 
                     // Apply fades only if we are near an edge
                     if fade_frames > 0 && (start_edge_in_block || end_edge_in_block) {
                         apply_edge_fades(
-                            &mut temp,
+                            temp,
                             written,
                             channels,
                             fade_frames.max(1),
@@ -693,9 +1252,7 @@ impl Track {
                 
                     // Mix into the track buffer at the correct offset
                     let samples = written * channels;
-                    for i in 0..samples {
-                        mix_dst[i] += temp[i];
-                    }
+                    simd::add(&mut mix_dst[..samples], &temp[..samples]);
                 
                     active_clips += 1;
                 }
@@ -707,63 +1264,107 @@ impl Track {
 
         // --- NEW: Process Equalizer ---
         // We do this BEFORE gain/pan so the EQ is "Pre-Fader" (standard mixing practice)
-        if active_clips > 0 {
-           self.track_eq.process_buffer(dst, channels);
-           self.track_compressor.process(dst);
-
-           // --- ADDED: Process Reverb (Stereo awareness) ---
-           if channels >= 2 {
-               for i in (0..dst.len()).step_by(channels) {
-                   let (l, r) = self.track_reverb.process(dst[i], dst[i+1]);
-                   dst[i] = l;
-                   dst[i+1] = r;
+        self.bypass_mix.set_target(if self.fx_bypass { 1.0 } else { 0.0 }, ramp);
+        if active_clips > 0 && self.frozen.is_none() {
+           // The dry line always runs, so a bypass can fade over to it at any time
+           let latency = self.latency_frames();
+           self.bypass_delay.set_delay_frames(latency);
+           let blending = self.bypass_mix.is_ramping() || self.bypass_mix.current() > 0.0;
+           if blending || latency > 0 {
+               self.bypass_dry.resize(dst.len(), 0.0);
+               self.bypass_dry.copy_from_slice(dst);
+               self.bypass_delay.process(&mut self.bypass_dry);
+           }
+
+           if self.bypass_mix.is_ramping() || self.bypass_mix.current() < 1.0 {
+               self.track_eq.process_buffer(dst, channels);
+               self.track_compressor.process(dst);
+
+               // --- ADDED: Process Reverb (Stereo awareness, mono falls back inside) ---
+               self.track_reverb.process_block(dst, channels);
+
+               // User inserts, in their own order
+               if !self.inserts.is_empty() {
+                   self.inserts.apply_automation(start_sample);
+                   self.inserts.process_block(dst, channels);
                }
-           } else if channels == 1 {
-               for i in 0..dst.len() {
-                   // Fallback for mono tracks
-                   let (l, _) = self.track_reverb.process(dst[i], dst[i]); 
-                   dst[i] = l;
+           }
+
+           if blending {
+               for (frame, dry) in dst.chunks_exact_mut(channels).zip(self.bypass_dry.chunks_exact(channels)) {
+                   let mix = self.bypass_mix.next();
+                   for (s, d) in frame.iter_mut().zip(dry) {
+                       *s += (d - *s) * mix;
+                   }
                }
            }
+        } else {
+           self.bypass_mix.skip(dst.len() / channels.max(1));
+        }
+
+        // --- Listen tap 1: pre-fader ---
+        if self.listen == ListenMode::Pfl {
+            self.listen_buffer.resize(dst.len(), 0.0);
+            self.listen_buffer.copy_from_slice(dst);
+        }
+        if !fader_audible {
+            dst.fill(0.0); // rendered only for PFL
         }
 
         // Apply Gain/Pan only if we actually mixed something
-        if active_clips > 0 && is_audible {
-            // --- NEW: Calculate Per-Sample Gain Step ---
-            // We divide by (frames - 1.0) to ensure the final frame hits exact end_gain.
-            // --- NEW: Calculate Per-Sample Gain Step (Linear) ---
-            let gain_step = if frames > 1 {
-                (end_gain_linear - start_gain_linear) / (frames as f32 - 1.0)
+        if active_clips > 0 && fader_audible {
+            // Automation steps linearly so the final frame hits exact end gain
+            let auto_step = if frames > 1 {
+                (end_auto_linear - start_auto_linear) / (frames as f32 - 1.0)
             } else {
                 0.0
             };
-            
-            let mut current_gain = start_gain_linear;
-            let pan = self.pan.clamp(-1.0, 1.0);
-            
-            let (pan_l, pan_r) = if channels >= 2 {
-                let angle = (pan + 1.0) * 0.25 * std::f32::consts::PI;
+            let mut auto_gain = start_auto_linear;
+            let pan_gains = |pan: f32| {
+                let angle = (pan.clamp(-1.0, 1.0) + 1.0) * 0.25 * std::f32::consts::PI;
                 (angle.cos(), angle.sin())
-            } else {
-                (1.0, 1.0)
             };
-
-            for i in (0..dst.len()).step_by(channels) {
-                if channels >= 2 {
-                    dst[i] *= current_gain * pan_l;   
-                    dst[i+1] *= current_gain * pan_r; 
-                    for c in 2..channels {
-                        dst[i+c] *= current_gain;
+            // Constant-power law only needs recomputing while the pan moves
+            let (mut pan_l, mut pan_r) = pan_gains(self.pan_smooth.current());
+
+            // Nothing moving: one gain per channel for the whole block
+            let settled = !self.gain_smooth.is_ramping() && !self.pan_smooth.is_ramping() && auto_step == 0.0;
+            if settled {
+                let gain = self.gain_smooth.current() * auto_gain;
+                let panned = if channels >= 2 { 2 } else { 0 }; // mono isn't panned
+                simd::scale_frames(dst, channels, &[gain * pan_l, gain * pan_r][..panned], gain);
+            } else {
+                for i in (0..dst.len()).step_by(channels) {
+                    let gain = self.gain_smooth.next() * auto_gain;
+                    if self.pan_smooth.is_ramping() {
+                        (pan_l, pan_r) = pan_gains(self.pan_smooth.next());
                     }
-                } else {
-                    dst[i] *= current_gain;
+                    if channels >= 2 {
+                        dst[i] *= gain * pan_l;
+                        dst[i+1] *= gain * pan_r;
+                        for c in 2..channels {
+                            dst[i+c] *= gain;
+                        }
+                    } else {
+                        dst[i] *= gain;
+                    }
+                    auto_gain += auto_step;
                 }
-                
-                // Step the gain for the next frame block
-                current_gain += gain_step;
             }
+        } else {
+            self.gain_smooth.skip(frames);
+            self.pan_smooth.skip(frames);
         }
 
+        // --- Listen tap 2: after-fader ---
+        if self.listen == ListenMode::Afl {
+            self.listen_buffer.resize(dst.len(), 0.0);
+            self.listen_buffer.copy_from_slice(dst);
+        }
+
+        // --- PDC: line this track up with the most latent track in the project ---
+        self.pdc.process(dst);
+
         // --- ADDED: Calculate meters exactly as they sound post-fader ---
         self.meter_state.process_block(dst, channels, &self.meters);
 
@@ -800,15 +1401,15 @@ impl Track {
         // --- NEW: Trigger Background Analysis on Load ---
         let analysis_ref = Arc::clone(&self.analysis);
         std::thread::spawn(move || {
-            if let Ok((samples, source_sr, source_ch)) = crate::bpm::adapter::decode_to_vec(&file_path) {
-                let profile = crate::analyzer::analyze_audio_buffer(&samples, source_ch, source_sr);
+            if let Ok(pcm) = cache::shared().get(&file_path) {
+                let profile = crate::analyzer::analyze_audio_buffer(&pcm.samples, pcm.channels, pcm.sample_rate);
                 if let Ok(mut guard) = analysis_ref.lock() {
                     *guard = Some(profile);
                 }
             }
         });
 
-        println!("♻️ Restored clip at index {}", index);
+        rt_info!("♻️ Restored clip at index {}", index);
         Ok(())
     }
 
@@ -817,10 +1418,10 @@ impl Track {
         &mut self,
         index: usize,
         path: String,
-        start: Duration,
-        offset: Duration,
-        dur: Duration,
-        source_duration: Duration,
+        start: Frames,
+        offset: Frames,
+        dur: Frames,
+        source_duration: Frames,
         source_sr: u32,
         source_ch: usize,
         out_sr: u32,
@@ -0,0 +1,102 @@
+// src/engine/triple_buffer.rs
+
+// Single-writer, single-reader triple buffer: the audio callback publishes the latest value
+// after every block and a control thread reads the newest complete one, neither waiting on
+// the other. Three slots: the writer owns one, the reader owns one, and the third sits in
+// the middle with a "fresh" bit. Publishing swaps the writer's slot into the middle;
+// reading swaps the middle out when it is fresh. A slot only ever belongs to one side, so
+// its mutex is never contended (the writer still only `try_lock`s it).
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+const INDEX: u8 = 0b011;
+const FRESH: u8 = 0b100;
+
+struct Shared<T> {
+    slots: [Mutex<T>; 3],
+    middle: AtomicU8, // slot index | FRESH
+}
+
+pub struct Writer<T> {
+    shared: Arc<Shared<T>>,
+    back: u8,
+}
+
+pub struct Reader<T> {
+    shared: Arc<Shared<T>>,
+    front: u8,
+}
+
+/// Writer and reader of a buffer whose three slots start out as `initial`.
+pub fn triple_buffer<T: Clone>(initial: T) -> (Writer<T>, Reader<T>) {
+    let shared = Arc::new(Shared {
+        slots: [Mutex::new(initial.clone()), Mutex::new(initial.clone()), Mutex::new(initial)],
+        middle: AtomicU8::new(1),
+    });
+    (Writer { shared: shared.clone(), back: 0 }, Reader { shared, front: 2 })
+}
+
+impl<T: Clone> Writer<T> {
+    /// Realtime-safe for types whose `clone_from` doesn't allocate (e.g. `Copy` types).
+    pub fn publish(&mut self, value: &T) {
+        if let Ok(mut slot) = self.shared.slots[self.back as usize].try_lock() {
+            slot.clone_from(value);
+        }
+        let previous = self.shared.middle.swap(self.back | FRESH, Ordering::AcqRel);
+        self.back = previous & INDEX;
+    }
+}
+
+impl<T: Clone> Reader<T> {
+    /// The newest published value (the initial one until the first publish).
+    pub fn read(&mut self) -> T {
+        if self.shared.middle.load(Ordering::Acquire) & FRESH != 0 {
+            let previous = self.shared.middle.swap(self.front, Ordering::AcqRel);
+            self.front = previous & INDEX;
+        }
+        self.shared.slots[self.front as usize].lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_initial_value_before_any_publish() {
+        let (_writer, mut reader) = triple_buffer(7u32);
+        assert_eq!(reader.read(), 7);
+    }
+
+    #[test]
+    fn reads_the_newest_publish() {
+        let (mut writer, mut reader) = triple_buffer(0u32);
+        writer.publish(&1);
+        writer.publish(&2);
+        writer.publish(&3);
+        assert_eq!(reader.read(), 3);
+        // Nothing new: the same value again
+        assert_eq!(reader.read(), 3);
+        writer.publish(&4);
+        assert_eq!(reader.read(), 4);
+    }
+
+    #[test]
+    fn reader_on_another_thread_never_sees_a_torn_or_older_value() {
+        let (mut writer, mut reader) = triple_buffer((0u64, 0u64));
+        let handle = std::thread::spawn(move || {
+            let mut last = 0;
+            for _ in 0..100_000 {
+                let (a, b) = reader.read();
+                assert_eq!(a, b);
+                assert!(a >= last);
+                last = a;
+            }
+        });
+        for i in 1..=100_000u64 {
+            writer.publish(&(i, i));
+        }
+        handle.join().unwrap();
+    }
+}
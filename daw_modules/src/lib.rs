@@ -1,5 +1,7 @@
 // src/lib.rs
 
+#[macro_use]
+pub mod rt_log; // declared first so the rt_* macros are visible crate-wide
 pub mod audio;
 pub mod decoder;
 mod player;
@@ -10,7 +12,11 @@ pub mod audio_runtime;
 pub mod session;
 pub mod effects;
 pub mod analyzer;
+pub mod classifier;
+pub mod routing_check;
 pub mod ai;
+#[cfg(test)]
+mod test_util;
 
 pub mod bpm;
 pub use bpm::{BpmDetector, analyze_bpm_for_file};
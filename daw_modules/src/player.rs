@@ -1,16 +1,17 @@
 // src/player.rs
+#![deny(clippy::print_stdout, clippy::print_stderr)]
 
 use crate::audio::{build_stream, setup_output_device, OutputConfig};
-use crate::decoder::{spawn_decoder_with_ctrl, DecoderCmd};
+use crate::decoder::{spawn_decoder_with_ctrl, CmdSender, DecoderCmd};
+use crate::engine::time::Frames;
 use anyhow::Context;
 use cpal::traits::StreamTrait;
 use cpal::{SampleFormat, Stream};
-use ringbuf::{traits::Split, HeapRb};
+use ringbuf::{traits::{Producer, Split}, HeapRb};
 use std::fs::File;
 use std::sync::{
     atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
-    mpsc::Sender,
-    Arc,
+    Arc, Mutex, PoisonError,
 };
 use std::thread::JoinHandle;
 use std::time::Duration;
@@ -26,11 +27,10 @@ pub struct AudioPlayer {
     is_playing: Arc<AtomicBool>,
     volume: Arc<AtomicU32>,
     total_duration: Duration,
-    current_time_samples: Arc<AtomicU64>,
+    current_time_frames: Arc<AtomicU64>,
     output_sample_rate: u32,
-    output_channels: u16,
     // New: control channel to decoder for seek.
-    seek_tx: Sender<DecoderCmd>,
+    seek_tx: Mutex<CmdSender>,
 }
 
 impl AudioPlayer {
@@ -65,7 +65,7 @@ impl AudioPlayer {
             Duration::from_secs_f64(seconds)
         };
 
-        println!(
+        rt_info!(
             "🎧 File info: channels: {}, sample_rate: {}, duration: {:?}",
             source_channels, source_sample_rate, total_duration
         );
@@ -77,7 +77,7 @@ impl AudioPlayer {
         // --- 3. Shared State ---
         let is_playing = Arc::new(AtomicBool::new(true));
         let volume = Arc::new(AtomicU32::new(1.0f32.to_bits()));
-        let current_time_samples = Arc::new(AtomicU64::new(0));
+        let current_time_frames = Arc::new(AtomicU64::new(0));
 
         // --- 4. Output device ---
         let output = setup_output_device()?;
@@ -94,10 +94,10 @@ impl AudioPlayer {
         );
 
         // --- 6. Build and play CPAL stream ---
-        let err_fn = |err| eprintln!("An error occurred on the output audio stream: {}", err);
+        let err_fn = |err| rt_error!("An error occurred on the output audio stream: {}", err);
         let is_playing_callback = is_playing.clone();
         let volume_callback = volume.clone();
-        let current_time_callback = current_time_samples.clone();
+        let current_time_callback = current_time_frames.clone();
         let OutputConfig {
             device,
             config,
@@ -144,10 +144,9 @@ impl AudioPlayer {
             is_playing,
             volume,
             total_duration,
-            current_time_samples,
+            current_time_frames,
             output_sample_rate: output.output_sample_rate,
-            output_channels: output.output_channels as u16,
-            seek_tx,
+            seek_tx: Mutex::new(seek_tx),
         })
     }
 
@@ -167,10 +166,7 @@ impl AudioPlayer {
     }
 
     pub fn get_current_time(&self) -> Duration {
-        let samples = self.current_time_samples.load(Ordering::Relaxed) as f64;
-        let frames = samples / self.output_channels as f64;
-        let seconds = frames / self.output_sample_rate as f64;
-        Duration::from_secs_f64(seconds)
+        Frames(self.current_time_frames.load(Ordering::Relaxed)).to_duration(self.output_sample_rate)
     }
 
     pub fn pause(&self) {
@@ -184,16 +180,16 @@ impl AudioPlayer {
     pub fn toggle_playback(&self) {
         let was_playing = self.is_playing.fetch_xor(true, Ordering::Relaxed);
         if was_playing {
-            println!("\r⏸️ Paused ");
+            rt_info!("⏸️ Paused");
         } else {
-            println!("\r▶️ Playing ");
+            rt_info!("▶️ Playing");
         }
     }
 
     pub fn set_volume(&self, level: f32) {
         let new_float = level.clamp(0.0, 1.0);
         self.volume.store(new_float.to_bits(), Ordering::Relaxed);
-        println!("\r🔊 Volume: {:.0}% ", new_float * 100.0);
+        rt_info!("🔊 Volume: {:.0}%", new_float * 100.0);
     }
 
     pub fn get_volume(&self) -> f32 {
@@ -206,12 +202,14 @@ impl AudioPlayer {
 
     // Absolute seek.
     pub fn seek(&self, pos: Duration) -> Result<(), anyhow::Error> {
-        self.seek_tx.send(DecoderCmd::Seek(pos))?;
+        self.seek_tx
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .try_push(DecoderCmd::Seek(pos))
+            .map_err(|_| anyhow::anyhow!("decoder is not taking commands"))?;
         // Update UI time immediately for responsiveness.
-        let seconds = pos.as_secs_f64();
-        let frames = (seconds * self.output_sample_rate as f64).round();
-        let samples = (frames as u64) * (self.output_channels as u64);
-        self.current_time_samples.store(samples, Ordering::Relaxed);
+        let frames = Frames::from_duration(pos, self.output_sample_rate);
+        self.current_time_frames.store(frames.0, Ordering::Relaxed);
         Ok(())
     }
 
@@ -0,0 +1,111 @@
+// src/recorder/calibration.rs
+
+// Round-trip latency measurement. The default input is opened raw (no gain, no monitor),
+// a click is played through the output a few times, and each is found again in the
+// captured audio. The time from the engine rendering a click to the input receiving it
+// covers the output buffer, the converters and the input buffer: exactly how late a take
+// lands against the backing track it was played to. Both ends are placed on the wall
+// clock, the input side through the callback clock in `InputLevels`.
+
+use anyhow::Result;
+use ringbuf::{HeapRb, traits::{Consumer, Split}};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::input::{self, AudioInput, InputLane, InputLevels};
+
+/// Clicks per calibration; the median is kept.
+const PINGS: usize = 5;
+/// At least this many clicks must be found.
+const MIN_DETECTED: usize = 3;
+/// How long to look for a click after it was sent.
+const LISTEN: Duration = Duration::from_millis(1000);
+/// Quiet after each click, so its echo isn't taken for the next one.
+const SETTLE: Duration = Duration::from_millis(250);
+/// A click must stand this far above the room noise (and above 0.01 in any case).
+const NOISE_MARGIN: f32 = 4.0;
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundTripLatency {
+    pub latency_ms: f64,           // median of the measurements
+    pub measurements_ms: Vec<f64>, // one per click found
+    pub spread_ms: f64,            // largest minus smallest; high means an unreliable result
+}
+
+/// Loudest channel of each frame.
+fn frame_levels(samples: &[f32], channels: usize, out: &mut Vec<f32>) {
+    out.extend(samples.chunks_exact(channels).map(|f| f.iter().fold(0.0f32, |m, s| m.max(s.abs()))));
+}
+
+/// Measure with the output click sent by `ping`, which returns the instant the click was
+/// rendered. Blocks for a few seconds.
+pub fn measure_round_trip(mut ping: impl FnMut() -> Result<Instant>) -> Result<RoundTripLatency> {
+    let (_, channels, sample_rate) = input::probe_default_input()?;
+    let (prod, mut cons) = HeapRb::<f32>::new(sample_rate as usize * channels * 2).split();
+    let (prod_mon, _) = HeapRb::<f32>::new(1).split();
+    let levels = Arc::new(InputLevels::new(0.0));
+    let lanes = vec![InputLane { channels: (0..channels).collect(), producer: prod, overruns: Default::default() }];
+    let (_input, _, _) = AudioInput::new(lanes, prod_mon, Vec::new(), levels.clone())?; // open until we return
+
+    let mut raw = vec![0.0f32; 4096 * channels];
+    let mut frame_level = Vec::with_capacity(4096);
+    let mut read_frames: u64 = 0; // frames taken out of the ring, from the first delivered
+    let mut drain = |frame_level: &mut Vec<f32>, read_frames: &mut u64| {
+        frame_level.clear();
+        loop {
+            let n = cons.pop_slice(&mut raw);
+            if n == 0 {
+                break;
+            }
+            frame_levels(&raw[..n - n % channels], channels, frame_level);
+            *read_frames += (n / channels) as u64;
+        }
+    };
+
+    // Let the stream settle, then take the room noise
+    thread::sleep(Duration::from_millis(300));
+    drain(&mut frame_level, &mut read_frames);
+    thread::sleep(Duration::from_millis(200));
+    drain(&mut frame_level, &mut read_frames);
+    let noise = frame_level.iter().fold(0.0f32, |m, &l| m.max(l));
+    let threshold = (noise * NOISE_MARGIN).max(0.01);
+
+    let mut measurements = Vec::with_capacity(PINGS);
+    for _ in 0..PINGS {
+        drain(&mut frame_level, &mut read_frames);
+        let sent = ping()?;
+        let mut found = None;
+        while found.is_none() && sent.elapsed() < LISTEN {
+            thread::sleep(Duration::from_millis(2));
+            let first = read_frames;
+            drain(&mut frame_level, &mut read_frames);
+            found = frame_level.iter().position(|&l| l > threshold).map(|i| first + i as u64);
+        }
+        if let Some(frame) = found {
+            // When the click's frame arrived, from the callback clock
+            let (delivered, block_end) = levels.input_clock();
+            let behind = Duration::from_secs_f64(delivered.saturating_sub(frame) as f64 / sample_rate as f64);
+            if let Some(heard) = block_end.checked_sub(behind).filter(|&t| t > sent) {
+                measurements.push((heard - sent).as_secs_f64() * 1000.0);
+            }
+        }
+        thread::sleep(SETTLE);
+    }
+
+    if measurements.len() < MIN_DETECTED {
+        anyhow::bail!(
+            "Only {} of {} clicks were heard; turn the output up or move the microphone closer",
+            measurements.len(),
+            PINGS
+        );
+    }
+    let mut sorted = measurements.clone();
+    sorted.sort_by(f64::total_cmp);
+    Ok(RoundTripLatency {
+        latency_ms: sorted[sorted.len() / 2],
+        spread_ms: sorted[sorted.len() - 1] - sorted[0],
+        measurements_ms: measurements,
+    })
+}
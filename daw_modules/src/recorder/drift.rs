@@ -0,0 +1,105 @@
+// src/recorder/drift.rs
+
+// Clock drift between input devices. Two devices that both run "at 48 kHz" never run at
+// exactly the same rate, so files recorded from each slowly slide apart (a few ms per
+// minute is typical for USB gear). A take from a second device is resampled onto the
+// clock of the take's first device: both callbacks' frame counts are compared over the
+// same stretch of wall time, and the file gets as many frames per reference second as its
+// nominal rate says it should. Linear interpolation is plenty for ratios this close to 1.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::input::InputLevels;
+
+/// Don't correct until the estimate spans this long; block jitter dominates before.
+const SETTLE_SECS: f64 = 5.0;
+/// Real drift is tiny; anything beyond this is a measuring error (e.g. a stalled device).
+const MAX_DRIFT: f64 = 0.005;
+
+pub struct DriftCorrector {
+    reference: Arc<InputLevels>,
+    reference_rate: u32,
+    source: Arc<InputLevels>,
+    source_rate: u32,
+    channels: usize,
+    anchors: Option<((u64, Instant), (u64, Instant))>, // (reference, source) clocks once both ran
+    step: f64,      // source frames per output frame
+    pos: f64,       // read position: 0 is `prev`, 1 the first frame of the next block
+    prev: Vec<f32>, // last frame of the previous block
+}
+
+impl DriftCorrector {
+    /// Resample `channels`-wide audio from the device behind `source` onto the clock of
+    /// the device behind `reference`. Rates are the nominal ones of each device.
+    pub fn new(
+        reference: Arc<InputLevels>,
+        reference_rate: u32,
+        source: Arc<InputLevels>,
+        source_rate: u32,
+        channels: usize,
+    ) -> Self {
+        Self {
+            reference,
+            reference_rate,
+            source,
+            source_rate,
+            channels: channels.max(1),
+            anchors: None,
+            step: 1.0,
+            pos: 1.0,
+            prev: vec![0.0; channels.max(1)],
+        }
+    }
+
+    /// Source frames per output frame; 1.0 until the estimate has settled.
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    fn update_step(&mut self) {
+        let now = (self.reference.input_clock(), self.source.input_clock());
+        let Some(((r_frames0, r_at0), (s_frames0, s_at0))) = self.anchors else {
+            if now.0.0 > 0 && now.1.0 > 0 {
+                self.anchors = Some(now);
+            }
+            return;
+        };
+        let ((r_frames, r_at), (s_frames, s_at)) = now;
+        let r_secs = r_at.saturating_duration_since(r_at0).as_secs_f64();
+        let s_secs = s_at.saturating_duration_since(s_at0).as_secs_f64();
+        if r_secs < SETTLE_SECS || s_secs < SETTLE_SECS {
+            return;
+        }
+        // Actual over nominal rate of each device, against the same wall clock
+        let r_speed = r_frames.saturating_sub(r_frames0) as f64 / r_secs / self.reference_rate.max(1) as f64;
+        let s_speed = s_frames.saturating_sub(s_frames0) as f64 / s_secs / self.source_rate.max(1) as f64;
+        if r_speed > 0.0 && s_speed > 0.0 {
+            self.step = (s_speed / r_speed).clamp(1.0 - MAX_DRIFT, 1.0 + MAX_DRIFT);
+        }
+    }
+
+    /// Resample `input` (interleaved, whole frames) onto the reference clock into `out`.
+    pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        self.update_step();
+        out.clear();
+        let ch = self.channels;
+        let frames = input.len() / ch;
+
+        let mut pos = self.pos;
+        while pos < frames as f64 {
+            let i = pos as usize;
+            let t = (pos - i as f64) as f32;
+            for c in 0..ch {
+                let a = if i == 0 { self.prev[c] } else { input[(i - 1) * ch + c] };
+                let b = input[i * ch + c];
+                out.push(a + (b - a) * t);
+            }
+            pos += self.step;
+        }
+        self.pos = pos - frames as f64;
+        if frames > 0 {
+            self.prev.copy_from_slice(&input[(frames - 1) * ch..frames * ch]);
+        }
+    }
+}
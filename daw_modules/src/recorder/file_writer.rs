@@ -8,7 +8,8 @@ use std::path::Path;
 use std::thread;
 use std::time::{Duration, Instant};
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use crate::recorder::drift::DriftCorrector;
 use crate::recorder::live_waveform::LiveWaveform;
 use anyhow::Result;
 
@@ -18,6 +19,7 @@ pub struct FileWriter {
     writer: WavWriter<BufWriter<File>>,
     #[allow(dead_code)]
     channels: u16,
+    drift: Option<DriftCorrector>, // resamples onto another device's clock before writing
 }
 
 impl FileWriter {
@@ -36,9 +38,16 @@ impl FileWriter {
         Ok(Self {
             writer,
             channels: channels as u16,
+            drift: None,
         })
     }
 
+    /// Put the audio on another device's clock before it is written (see `drift`).
+    pub fn with_drift(mut self, drift: DriftCorrector) -> Self {
+        self.drift = Some(drift);
+        self
+    }
+
     /// Run the writer consuming f32 samples from the ring buffer consumer.
     /// C must implement ringbuf::consumer::Consumer with Item = f32.
     pub fn run<C>(mut self, mut consumer: C) -> Result<()>
@@ -103,25 +112,56 @@ impl FileWriter {
         Ok(())
     }
 
+    /// While `paused` is set the consumer is drained without writing (a full ring would
+    /// read as an overrun), and the frame the pause fell on is pushed to `gaps`.
+    #[allow(clippy::too_many_arguments)]
     pub fn run_with_waveform<C>(
         mut self,
         mut consumer: C,
         live_waveform: Arc<Mutex<LiveWaveform>>,
         channels: usize,
         record_samples: Arc<AtomicU64>,
+        paused: Arc<AtomicBool>,
+        gaps: Arc<Mutex<Vec<u64>>>,
+        stop: Arc<AtomicBool>,
     ) -> Result<()>
     where
         C: Consumer<Item = f32>,
     {
-        let mut tmp = vec![0.0f32; 4096];
+        // Whole frames per pop, so the drift resampler never sees half a frame
+        let mut tmp = vec![0.0f32; 4096 / channels.max(1) * channels.max(1)];
+        let mut resampled = Vec::with_capacity(tmp.len() * 2);
         let mut wrote_any = false;
         const GRACEFUL_IDLE_MS: u128 = 500;
         let mut idle_start: Option<Instant> = None;
+        let mut was_paused = false;
     
         loop {
+            if paused.load(Ordering::Acquire) {
+                if !was_paused {
+                    let frame = record_samples.load(Ordering::Relaxed) / channels.max(1) as u64;
+                    if let Ok(mut g) = gaps.lock() {
+                        g.push(frame);
+                    }
+                    was_paused = true;
+                }
+                consumer.clear();
+                if stop.load(Ordering::Acquire) {
+                    break;
+                }
+                idle_start = None;
+                thread::sleep(Duration::from_millis(5));
+                continue;
+            }
+            was_paused = false;
+
             let popped = consumer.pop_slice(tmp.as_mut_slice());
         
             if popped == 0 {
+                // Input stream is gone and the buffer is drained: finalize now
+                if stop.load(Ordering::Acquire) {
+                    break;
+                }
                 thread::sleep(Duration::from_millis(5));
                 if wrote_any {
                     idle_start.get_or_insert_with(Instant::now);
@@ -136,9 +176,17 @@ impl FileWriter {
         
             idle_start = None;
             wrote_any = true;
+
+            let block: &[f32] = match self.drift.as_mut() {
+                Some(drift) => {
+                    drift.process(&tmp[..popped], &mut resampled);
+                    &resampled
+                }
+                None => &tmp[..popped],
+            };
         
             // 1) Write WAV and count samples
-            for &s in &tmp[..popped] {
+            for &s in block {
                 let samp = if s.is_finite() {
                     (s.max(-1.0).min(1.0) * (i16::MAX as f32)) as i16
                 } else {
@@ -151,7 +199,7 @@ impl FileWriter {
             // 2) Update live waveform using channel 0 from interleaved data
             {
                 let mut wf = live_waveform.lock().unwrap();
-                wf.add_block(&tmp[..popped], channels);
+                wf.add_block(block, channels);
             }
         }
     
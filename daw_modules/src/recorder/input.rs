@@ -4,28 +4,291 @@ use anyhow::Result;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{SampleFormat, Stream, StreamConfig};
 use ringbuf::producer::Producer;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-/// AudioInput holds the CPAL input stream. The Producers are moved into the input callback.
-// src/recorder/input.rs
+/// Software preamp range in dB.
+pub const MIN_INPUT_GAIN_DB: f32 = -24.0;
+pub const MAX_INPUT_GAIN_DB: f32 = 24.0;
+
+/// How many hardware inputs a take captures.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CaptureMode {
+    /// One input, recorded as a mono file.
+    Mono,
+    /// Two adjacent inputs (e.g. 3/4), recorded as a stereo file.
+    Stereo,
+    /// Every input of the device, in order.
+    #[default]
+    All,
+}
+
+/// Which hardware inputs feed the recorder: `first_channel` (0-based) and the mode.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct InputSelection {
+    pub mode: CaptureMode,
+    pub first_channel: usize, // ignored for `All`
+}
+
+impl InputSelection {
+    /// The device channels to record, checked against a device with `device_channels` inputs.
+    pub fn channels(&self, device_channels: usize) -> Result<Vec<usize>> {
+        let wanted = match self.mode {
+            CaptureMode::Mono => vec![self.first_channel],
+            CaptureMode::Stereo => vec![self.first_channel, self.first_channel + 1],
+            CaptureMode::All => (0..device_channels).collect(),
+        };
+        if let Some(&bad) = wanted.iter().find(|&&c| c >= device_channels) {
+            anyhow::bail!("Input channel {} does not exist (device has {})", bad + 1, device_channels);
+        }
+        Ok(wanted)
+    }
+}
+
+/// Lock-free input controls and readouts shared with the input callback.
+/// Gain is stored as f32 bits, like the player volume, so it can be changed live while armed.
+pub struct InputLevels {
+    gain_db: AtomicU32,
+    peak: AtomicU32, // post-gain linear peak since last `take_meter`
+    rms: AtomicU32,  // post-gain RMS of the last callback block
+    clipped: AtomicBool,
+    epoch: Instant,
+    clock_seq: AtomicU64,       // odd while the callback updates the two below
+    frames: AtomicU64,          // frames delivered since the stream opened
+    block_end_nanos: AtomicU64, // when the last block arrived, since `epoch`
+    first_block_frames: AtomicU64,
+    first_block_nanos: AtomicU64, // when the first block arrived, u64::MAX before that
+}
+
+impl InputLevels {
+    pub fn new(gain_db: f32) -> Self {
+        let levels = Self {
+            gain_db: AtomicU32::new(0.0f32.to_bits()),
+            peak: AtomicU32::new(0.0f32.to_bits()),
+            rms: AtomicU32::new(0.0f32.to_bits()),
+            clipped: AtomicBool::new(false),
+            epoch: Instant::now(),
+            clock_seq: AtomicU64::new(0),
+            frames: AtomicU64::new(0),
+            block_end_nanos: AtomicU64::new(0),
+            first_block_frames: AtomicU64::new(0),
+            first_block_nanos: AtomicU64::new(u64::MAX),
+        };
+        levels.set_gain_db(gain_db);
+        levels
+    }
+
+    pub fn set_gain_db(&self, db: f32) {
+        let db = if db.is_finite() { db.clamp(MIN_INPUT_GAIN_DB, MAX_INPUT_GAIN_DB) } else { 0.0 };
+        self.gain_db.store(db.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn gain_db(&self) -> f32 {
+        f32::from_bits(self.gain_db.load(Ordering::Relaxed))
+    }
+
+    /// Returns (peak, rms) as linear values and resets the peak hold.
+    pub fn take_meter(&self) -> (f32, f32) {
+        let peak = f32::from_bits(self.peak.swap(0.0f32.to_bits(), Ordering::Relaxed));
+        let rms = f32::from_bits(self.rms.load(Ordering::Relaxed));
+        (peak, rms)
+    }
+
+    /// True once any post-gain sample reached 0 dBFS.
+    pub fn is_clipped(&self) -> bool {
+        self.clipped.load(Ordering::Relaxed)
+    }
+
+    pub fn clear_clip(&self) {
+        self.clipped.store(false, Ordering::Relaxed);
+    }
+
+    /// Frames delivered so far and when the last of them arrived. Frame `n` was captured
+    /// about `(frames - n) / sample_rate` before that instant.
+    pub fn input_clock(&self) -> (u64, Instant) {
+        loop {
+            let seq = self.clock_seq.load(Ordering::Acquire);
+            if seq % 2 == 1 {
+                std::hint::spin_loop();
+                continue;
+            }
+            let frames = self.frames.load(Ordering::Acquire);
+            let nanos = self.block_end_nanos.load(Ordering::Acquire);
+            if self.clock_seq.load(Ordering::Acquire) == seq {
+                return (frames, self.epoch + Duration::from_nanos(nanos));
+            }
+        }
+    }
+
+    /// When the first frame was captured, on the wall clock; `None` before any audio
+    /// arrived. Lines up streams of different devices that were opened one after the other.
+    pub fn first_frame_at(&self, sample_rate: u32) -> Option<Instant> {
+        let nanos = self.first_block_nanos.load(Ordering::Acquire);
+        if nanos == u64::MAX {
+            return None;
+        }
+        let frames = self.first_block_frames.load(Ordering::Relaxed);
+        let block = Duration::from_secs_f64(frames as f64 / sample_rate.max(1) as f64);
+        (self.epoch + Duration::from_nanos(nanos)).checked_sub(block)
+    }
 
+    // Called from the input callback after gain has been applied.
+    fn update(&self, block: &[f32], channels: usize) {
+        if block.is_empty() {
+            return;
+        }
+        let now = self.epoch.elapsed().as_nanos() as u64;
+        if self.first_block_nanos.load(Ordering::Relaxed) == u64::MAX {
+            self.first_block_frames.store((block.len() / channels.max(1)) as u64, Ordering::Relaxed);
+            self.first_block_nanos.store(now, Ordering::Release);
+        }
+        self.clock_seq.fetch_add(1, Ordering::AcqRel);
+        self.block_end_nanos.store(now, Ordering::Release);
+        self.frames.fetch_add((block.len() / channels.max(1)) as u64, Ordering::Release);
+        self.clock_seq.fetch_add(1, Ordering::AcqRel);
+        let mut peak = 0.0f32;
+        let mut sum_sq = 0.0f32;
+        for &s in block {
+            let a = s.abs();
+            if a > peak { peak = a; }
+            sum_sq += s * s;
+        }
+        let held = f32::from_bits(self.peak.load(Ordering::Relaxed));
+        if peak > held {
+            self.peak.store(peak.to_bits(), Ordering::Relaxed);
+        }
+        self.rms.store((sum_sq / block.len() as f32).sqrt().to_bits(), Ordering::Relaxed);
+        if peak >= 1.0 {
+            self.clipped.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for InputLevels {
+    fn default() -> Self {
+        Self::new(0.0)
+    }
+}
+
+/// AudioInput holds the CPAL input stream. The Producers are moved into the input callback.
 pub struct AudioInput {
     pub stream: Stream,
     #[allow(dead_code)]
     channels: usize,
     pub sample_rate: u32, // <--- add this
+    pub device_name: String,
+}
+
+/// One fan-out destination inside the input callback: the device channels it takes
+/// (in order), the ring buffer feeding its FileWriter and its overrun counters.
+pub struct InputLane<P> {
+    pub channels: Vec<usize>,
+    pub producer: P,
+    pub overruns: Arc<LaneOverruns>,
+}
+
+/// Frames a lane lost because its writer fell behind and the ring buffer was full.
+/// Written by the input callback, read by anyone. Positions count every frame the
+/// input offered, so they are time into the take, not into the (shorter) file.
+#[derive(Default)]
+pub struct LaneOverruns {
+    offered: AtomicU64,        // frames the input offered this lane
+    dropped: AtomicU64,        // frames of those that didn't fit
+    events: AtomicU64,         // separate runs of dropped blocks
+    last_at: AtomicU64,        // offered frame where the latest run started
+    overrunning: AtomicBool,   // the previous block dropped too
+}
+
+impl LaneOverruns {
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn events(&self) -> u64 {
+        self.events.load(Ordering::Relaxed)
+    }
+
+    /// Frame into the take where the latest overrun started.
+    pub fn last_at_frame(&self) -> u64 {
+        self.last_at.load(Ordering::Relaxed)
+    }
+
+    // Called from the input callback: `written` of `offered` frames made it into the ring.
+    fn record(&self, offered: u64, written: u64) {
+        let start = self.offered.fetch_add(offered, Ordering::Relaxed);
+        if written >= offered {
+            self.overrunning.store(false, Ordering::Relaxed);
+            return;
+        }
+        self.dropped.fetch_add(offered - written, Ordering::Relaxed);
+        if !self.overrunning.swap(true, Ordering::Relaxed) {
+            self.last_at.store(start + written, Ordering::Relaxed);
+            self.events.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The input device called `name`, or the default input for `None`.
+fn find_input_device(name: Option<&str>) -> Result<cpal::Device> {
+    let host = cpal::default_host();
+    match name {
+        None => host
+            .default_input_device()
+            .ok_or_else(|| anyhow::anyhow!("No input device available")),
+        Some(name) => host
+            .input_devices()?
+            .find(|d| d.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| anyhow::anyhow!("Input device '{}' not found", name)),
+    }
+}
+
+/// Name, channel count and sample rate of the default input device.
+pub fn probe_default_input() -> Result<(String, usize, u32)> {
+    probe_input(None)
+}
+
+/// Name, channel count and sample rate of the input called `device` (`None`: the default).
+pub fn probe_input(device: Option<&str>) -> Result<(String, usize, u32)> {
+    let device = find_input_device(device)?;
+    let config = device.default_input_config()?;
+    Ok((device.name().unwrap_or_default(), config.channels() as usize, config.sample_rate().0))
 }
 
 impl AudioInput {
-    pub fn new<PRec, PMon>(producer_rec: PRec, producer_mon: PMon)
+    /// Opens the default input and starts capture. Every lane sees the same callback
+    /// blocks, so all writers start on the same sample. The monitor gets `monitor_channels`
+    /// in that order (empty: the full device layout).
+    pub fn new<PRec, PMon>(
+        lanes: Vec<InputLane<PRec>>,
+        producer_mon: PMon,
+        monitor_channels: Vec<usize>,
+        levels: Arc<InputLevels>,
+    )
         -> Result<(Self, usize, u32)>            // <--- return sample_rate too
     where
         PRec: Producer<Item = f32> + Send + 'static,
         PMon: Producer<Item = f32> + Send + 'static,
     {
-        let host = cpal::default_host();
-        let device = host
-            .default_input_device()
-            .ok_or_else(|| anyhow::anyhow!("No input device available"))?;
+        Self::open(None, lanes, producer_mon, monitor_channels, levels)
+    }
+
+    /// Like `new`, on the input called `device` (`None`: the default input).
+    pub fn open<PRec, PMon>(
+        device: Option<&str>,
+        lanes: Vec<InputLane<PRec>>,
+        producer_mon: PMon,
+        monitor_channels: Vec<usize>,
+        levels: Arc<InputLevels>,
+    ) -> Result<(Self, usize, u32)>
+    where
+        PRec: Producer<Item = f32> + Send + 'static,
+        PMon: Producer<Item = f32> + Send + 'static,
+    {
+        let device = find_input_device(device)?;
+        let device_name = device.name().unwrap_or_default();
 
         let supported_config = device.default_input_config()?;
         let sample_format = supported_config.sample_format();
@@ -33,91 +296,120 @@ impl AudioInput {
         let channels = config.channels as usize;
         let sample_rate = config.sample_rate.0;   // <--- real input rate
 
+        for wanted in lanes.iter().map(|l| &l.channels).chain([&monitor_channels]) {
+            if let Some(&bad) = wanted.iter().find(|&&c| c >= channels) {
+                anyhow::bail!("Input channel {} does not exist (device has {})", bad + 1, channels);
+            }
+        }
+
+        let sink = InputSink::new(lanes, producer_mon, monitor_channels, levels, channels);
+
         let stream = match sample_format {
-            SampleFormat::F32 => build_stream_f32(&device, &config, producer_rec, producer_mon)?,
-            SampleFormat::I16 => build_stream_i16(&device, &config, producer_rec, producer_mon)?,
-            SampleFormat::U16 => build_stream_u16(&device, &config, producer_rec, producer_mon)?,
+            SampleFormat::F32 => build_stream::<f32, _, _>(&device, &config, sink, |s| s)?,
+            SampleFormat::I16 => build_stream::<i16, _, _>(&device, &config, sink, |s| s as f32 / i16::MAX as f32)?,
+            SampleFormat::U16 => build_stream::<u16, _, _>(&device, &config, sink, |s| (s as f32 / u16::MAX as f32) * 2.0 - 1.0)?,
             other => anyhow::bail!("Unsupported sample format: {:?}", other),
         };
 
         Ok((
-            Self { stream, channels, sample_rate },
+            Self { stream, channels, sample_rate, device_name },
             channels,
             sample_rate,
         ))
     }
 }
 
+/// Everything the input callback owns: the record lanes, the monitor producer,
+/// the shared levels and reusable scratch buffers.
+struct InputSink<PRec, PMon> {
+    lanes: Vec<InputLane<PRec>>,
+    producer_mon: PMon,
+    monitor_channels: Vec<usize>, // empty: the full device layout
+    levels: Arc<InputLevels>,
+    device_channels: usize,
+    scratch: Vec<f32>,
+    lane_scratch: Vec<f32>,
+}
 
-/// Build input stream when device sample format is f32 (no conversion needed).
-fn build_stream_f32<PRec, PMon>(
-    device: &cpal::Device,
-    config: &StreamConfig,
-    mut producer_rec: PRec,
-    mut producer_mon: PMon,
-) -> Result<Stream>
+impl<PRec, PMon> InputSink<PRec, PMon>
 where
-    PRec: Producer<Item = f32> + Send + 'static,
-    PMon: Producer<Item = f32> + Send + 'static,
+    PRec: Producer<Item = f32>,
+    PMon: Producer<Item = f32>,
 {
-    let err_fn = |err| eprintln!("Input stream error: {:?}", err);
+    fn new(
+        lanes: Vec<InputLane<PRec>>,
+        producer_mon: PMon,
+        monitor_channels: Vec<usize>,
+        levels: Arc<InputLevels>,
+        device_channels: usize,
+    ) -> Self {
+        Self {
+            lanes,
+            producer_mon,
+            monitor_channels,
+            levels,
+            device_channels: device_channels.max(1),
+            scratch: Vec::with_capacity(8192),
+            lane_scratch: Vec::with_capacity(8192),
+        }
+    }
 
-    let stream = device.build_input_stream(
-        config,
-        move |data: &[f32], _| {
-            // Push into recorder buffer; mirror into monitor buffer.
-            let mut pushed = 0usize;
-            while pushed < data.len() {
-                let slice = &data[pushed..];
-                let n = producer_rec.push_slice(slice);
-                if n == 0 {
-                    // recorder buffer full -> drop remainder
-                    break;
-                }
-                // Best-effort push into monitor buffer for same region
-                let _ = producer_mon.push_slice(&slice[..n]);
-                pushed += n;
-            }
-        },
-        err_fn,
-        None,
-    )?;
+    /// Apply input gain, meter the result, fan out to every record lane and mirror into the monitor buffer.
+    fn push<I: Iterator<Item = f32>>(&mut self, samples: I) {
+        let gain = 10.0f32.powf(self.levels.gain_db() / 20.0);
 
-    stream.play()?;
-    Ok(stream)
+        self.scratch.clear();
+        self.scratch.extend(samples.map(|s| s * gain));
+        self.levels.update(&self.scratch, self.device_channels);
+
+        for lane in self.lanes.iter_mut() {
+            pick_channels(&self.scratch, self.device_channels, &lane.channels, &mut self.lane_scratch);
+            // recorder buffer full -> drop remainder, counted so the take can be flagged.
+            // Whole frames only, so a partial push never shifts the channels in the file.
+            let width = lane.channels.len().max(1);
+            let fits = lane.producer.vacant_len().min(self.lane_scratch.len()) / width * width;
+            let written = lane.producer.push_slice(&self.lane_scratch[..fits]);
+            lane.overruns.record((self.lane_scratch.len() / width) as u64, (written / width) as u64);
+        }
+
+        // Best-effort push into monitor buffer
+        if self.monitor_channels.is_empty() {
+            let _ = self.producer_mon.push_slice(&self.scratch);
+        } else {
+            pick_channels(&self.scratch, self.device_channels, &self.monitor_channels, &mut self.lane_scratch);
+            let _ = self.producer_mon.push_slice(&self.lane_scratch);
+        }
+    }
 }
 
-/// Build input stream for i16 samples; convert to f32 before pushing.
-fn build_stream_i16<PRec, PMon>(
+/// Copy `channels` of every frame of `block` (interleaved, `device_channels` wide) into `out`.
+fn pick_channels(block: &[f32], device_channels: usize, channels: &[usize], out: &mut Vec<f32>) {
+    out.clear();
+    for frame in block.chunks_exact(device_channels) {
+        for &ch in channels {
+            out.push(frame[ch]);
+        }
+    }
+}
+
+/// Build input stream for any supported sample type; `convert` maps device samples to f32.
+fn build_stream<T, PRec, PMon>(
     device: &cpal::Device,
     config: &StreamConfig,
-    mut producer_rec: PRec,
-    mut producer_mon: PMon,
+    mut sink: InputSink<PRec, PMon>,
+    convert: fn(T) -> f32,
 ) -> Result<Stream>
 where
+    T: cpal::SizedSample + Copy + 'static,
     PRec: Producer<Item = f32> + Send + 'static,
     PMon: Producer<Item = f32> + Send + 'static,
 {
-    let err_fn = |err| eprintln!("Input stream error: {:?}", err);
+    let err_fn = |err| rt_error!("Input stream error: {:?}", err);
 
     let stream = device.build_input_stream(
         config,
-        move |data: &[i16], _| {
-            let mut conv = Vec::with_capacity(data.len());
-            for &s in data.iter() {
-                conv.push(s as f32 / i16::MAX as f32);
-            }
-
-            let mut pushed = 0usize;
-            while pushed < conv.len() {
-                let slice = &conv[pushed..];
-                let n = producer_rec.push_slice(slice);
-                if n == 0 {
-                    break;
-                }
-                let _ = producer_mon.push_slice(&slice[..n]);
-                pushed += n;
-            }
+        move |data: &[T], _| {
+            sink.push(data.iter().map(|&s| convert(s)));
         },
         err_fn,
         None,
@@ -127,43 +419,88 @@ where
     Ok(stream)
 }
 
-/// Build input stream for u16 samples; convert to f32 before pushing.
-fn build_stream_u16<PRec, PMon>(
-    device: &cpal::Device,
-    config: &StreamConfig,
-    mut producer_rec: PRec,
-    mut producer_mon: PMon,
-) -> Result<Stream>
-where
-    PRec: Producer<Item = f32> + Send + 'static,
-    PMon: Producer<Item = f32> + Send + 'static,
-{
-    let err_fn = |err| eprintln!("Input stream error: {:?}", err);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ringbuf::traits::{Consumer, Split};
+    use ringbuf::HeapRb;
 
-    let stream = device.build_input_stream(
-        config,
-        move |data: &[u16], _| {
-            let mut conv = Vec::with_capacity(data.len());
-            for &s in data.iter() {
-                let f = (s as f32 / u16::MAX as f32) * 2.0 - 1.0;
-                conv.push(f);
-            }
+    fn sink_with_gain(gain_db: f32) -> (InputSink<ringbuf::HeapProd<f32>, ringbuf::HeapProd<f32>>, ringbuf::HeapCons<f32>, Arc<InputLevels>) {
+        let (rec_tx, rec_rx) = HeapRb::<f32>::new(1024).split();
+        let (mon_tx, _mon_rx) = HeapRb::<f32>::new(1024).split();
+        let levels = Arc::new(InputLevels::new(gain_db));
+        let lane = InputLane { channels: vec![0, 1], producer: rec_tx, overruns: Arc::default() };
+        (InputSink::new(vec![lane], mon_tx, Vec::new(), levels.clone(), 2), rec_rx, levels)
+    }
 
-            let mut pushed = 0usize;
-            while pushed < conv.len() {
-                let slice = &conv[pushed..];
-                let n = producer_rec.push_slice(slice);
-                if n == 0 {
-                    break;
-                }
-                let _ = producer_mon.push_slice(&slice[..n]);
-                pushed += n;
-            }
-        },
-        err_fn,
-        None,
-    )?;
+    #[test]
+    fn gain_is_applied_before_the_file_sees_the_samples() {
+        let (mut sink, mut rec, _) = sink_with_gain(6.0);
+        sink.push([0.25f32, -0.25, 0.1, 0.0].into_iter());
+        let written: Vec<f32> = rec.pop_iter().collect();
+        let g = 10.0f32.powf(6.0 / 20.0);
+        assert_eq!(written, vec![0.25 * g, -0.25 * g, 0.1 * g, 0.0]);
+    }
 
-    stream.play()?;
-    Ok(stream)
+    #[test]
+    fn meter_and_clip_flag_read_the_boosted_signal() {
+        let (mut sink, _rec, levels) = sink_with_gain(12.0);
+        // 0.3 is safe at unity but clips after +12 dB (x3.98)
+        sink.push([0.3f32, 0.3].into_iter());
+        let (peak, rms) = levels.take_meter();
+        assert!((peak - 0.3 * 10.0f32.powf(0.6)).abs() < 1e-5);
+        assert!((rms - peak).abs() < 1e-5);
+        assert!(levels.is_clipped());
+        levels.clear_clip();
+        assert!(!levels.is_clipped());
+    }
+
+    #[test]
+    fn gain_is_clamped_to_the_preamp_range() {
+        let levels = InputLevels::new(60.0);
+        assert_eq!(levels.gain_db(), MAX_INPUT_GAIN_DB);
+        levels.set_gain_db(-60.0);
+        assert_eq!(levels.gain_db(), MIN_INPUT_GAIN_DB);
+        levels.set_gain_db(f32::NAN);
+        assert_eq!(levels.gain_db(), 0.0);
+    }
+
+    // A take through the sink while the engine monitors it with the cue blend at
+    // `levels` (changed halfway through); returns (recorded samples, last cue block)
+    fn monitored_take(levels: [(f32, f32); 2]) -> (Vec<f32>, Vec<f32>) {
+        use crate::engine::output_routing::OutputBus;
+        use crate::engine::Engine;
+
+        let (rec_tx, mut rec_rx) = HeapRb::<f32>::new(8192).split();
+        let (mon_tx, mut mon_rx) = HeapRb::<f32>::new(8192).split();
+        let lane = InputLane { channels: vec![0, 1], producer: rec_tx, overruns: Arc::default() };
+        let mut sink = InputSink::new(vec![lane], mon_tx, Vec::new(), Arc::new(InputLevels::new(3.0)), 2);
+        let mut eng = Engine::new(48_000, 2);
+        eng.cue_active = true;
+        eng.play();
+
+        let mut out = vec![0.0f32; 512];
+        let mut recorded = Vec::new();
+        for block in 0..8 {
+            let (input, playback) = levels[block / 4];
+            eng.cue.set_levels(input, playback);
+            sink.push((0..512).map(|i| ((block * 512 + i) as f32 * 0.01).sin() * 0.5));
+            let live: Vec<f32> = mon_rx.pop_iter().collect();
+            eng.render(&mut out, &live);
+            recorded.extend(rec_rx.pop_iter());
+        }
+        (recorded, eng.bus_buffer(OutputBus::Cue).unwrap().to_vec())
+    }
+
+    #[test]
+    fn the_cue_blend_never_changes_what_is_recorded() {
+        let (plain, plain_cue) = monitored_take([(1.0, 1.0), (1.0, 1.0)]);
+        let (blended, blended_cue) = monitored_take([(0.25, 0.5), (0.0, 1.0)]);
+        // The blend did reach the cue...
+        assert_ne!(plain_cue, blended_cue);
+        // ...and the record ring got the same bits either way
+        assert_eq!(plain.len(), 8 * 512);
+        assert!(plain.iter().zip(&blended).all(|(a, b)| a.to_bits() == b.to_bits()));
+        assert_eq!(plain.len(), blended.len());
+    }
 }
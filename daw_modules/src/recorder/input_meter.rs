@@ -0,0 +1,53 @@
+// src/recorder/input_meter.rs
+
+// Level check before a take. Opens the default input with no record lanes, so the input
+// trim and the peak/RMS meter run exactly as they will while recording, without writing
+// anything. Close it before a take starts: the recorder opens the device itself.
+
+use anyhow::Result;
+use ringbuf::{HeapProd, HeapRb, traits::Split};
+use std::sync::Arc;
+
+use super::input::{AudioInput, InputLane, InputLevels};
+
+pub struct InputMeter {
+    input: AudioInput,
+    levels: Arc<InputLevels>,
+}
+
+impl InputMeter {
+    /// Opens the default input with `input_gain_db` of trim (clamped to ±24).
+    pub fn start(input_gain_db: f32) -> Result<Self> {
+        let levels = Arc::new(InputLevels::new(input_gain_db));
+        // Nothing listens to the monitor feed; once the buffer is full pushes are dropped
+        let (prod_mon, _) = HeapRb::<f32>::new(1).split();
+        let lanes: Vec<InputLane<HeapProd<f32>>> = Vec::new();
+        let (input, _, _) = AudioInput::new(lanes, prod_mon, Vec::new(), levels.clone())?;
+        Ok(Self { input, levels })
+    }
+
+    pub fn set_input_gain_db(&self, db: f32) {
+        self.levels.set_gain_db(db);
+    }
+
+    pub fn input_gain_db(&self) -> f32 {
+        self.levels.gain_db()
+    }
+
+    /// Post-gain (peak, rms) since the last call, linear.
+    pub fn take_meter(&self) -> (f32, f32) {
+        self.levels.take_meter()
+    }
+
+    pub fn is_clipped(&self) -> bool {
+        self.levels.is_clipped()
+    }
+
+    pub fn clear_clip(&self) {
+        self.levels.clear_clip();
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.input.device_name
+    }
+}
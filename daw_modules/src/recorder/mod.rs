@@ -1,13 +1,22 @@
 // src/recorder/mod.rs
 
+// Realtime/decoder threads: log through rt_log, never std streams.
+#![deny(clippy::print_stdout, clippy::print_stderr)]
+
+pub mod calibration;
+pub mod drift;
 pub mod input;
+pub mod input_meter;
 pub mod file_writer;
 pub mod monitor;
 pub mod live_waveform;
+pub mod recovery;
+pub mod retro;
 
 use crate::recorder::{
+    drift::DriftCorrector,
     file_writer::FileWriter,
-    input::AudioInput,
+    input::{AudioInput, InputLevels},
     live_waveform::LiveWaveform,
     monitor::Monitor,
 };
@@ -20,82 +29,390 @@ use std::sync::{
     Mutex,
 };
 use std::thread;
+use std::time::Duration;
+
+/// One destination of a take: which hardware input channels go into which file.
+/// `input_channels` is 0-based and kept in order (e.g. `[0]` mono, `[2, 3]` stereo pair),
+/// on the input device called `device` (`None`: the default input).
+#[derive(Debug, Clone)]
+pub struct RecordTarget {
+    pub track_id: Option<u32>,
+    pub path: PathBuf,
+    pub input_channels: Vec<usize>,
+    pub device: Option<String>,
+}
+
+/// What a finished take produced for one target.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RecordingResult {
+    pub track_id: Option<u32>,
+    pub path: String,
+    pub channels: usize,
+    pub sample_rate: u32,
+    pub duration: f64,   // seconds of audio written
+    pub start_time: f64, // timeline position the take was started at
+    pub offset: f64,     // input latency to skip so the clip lines up (shared by every target)
+    pub gaps: Vec<f64>,  // seconds into the file where the take was paused and resumed
+    pub dropped_frames: u64, // input lost to a full buffer; the file is this much short
+}
+
+/// Input lost by the running take so far, over every target.
+#[derive(Debug, Clone, Copy, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverrunReport {
+    pub events: u64,
+    pub dropped_frames: u64,
+    pub last_at: f64, // seconds into the take where the latest overrun started
+}
+
+struct TargetWriter {
+    target: RecordTarget,
+    handle: Option<thread::JoinHandle<()>>,
+    frames: Arc<AtomicU64>, // samples written / channel count
+    gaps: Arc<Mutex<Vec<u64>>>, // frames of the file where a pause fell
+    overruns: Arc<input::LaneOverruns>,
+    sample_rate: u32,
+    clock: Arc<InputLevels>, // levels of the target's device, for its first-frame time
+}
+
+/// A further input device of a multi-device take.
+struct DeviceInput {
+    _input: AudioInput, // capture runs while this is alive
+    levels: Arc<InputLevels>,
+}
 
 pub struct Recorder {
-    input: AudioInput,
-    writer_handle: Option<thread::JoinHandle<()>>,
+    input: AudioInput, // reference device: monitor, meter and clock for the others
+    extra_inputs: Vec<DeviceInput>,
+    writers: Vec<TargetWriter>,
+    writer_stop: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>, // writers hold off while set; capture and metering carry on
     pub monitor: Option<Monitor>, // <--- CHANGED to Option
     pub monitor_enabled: Arc<AtomicBool>, // <--- NEW: Lock-free toggle
     live_waveform: Arc<Mutex<LiveWaveform>>,
     record_samples: Arc<AtomicU64>,
+    levels: Arc<InputLevels>, // input gain + post-gain meter, shared with the input callback
+    start_time: Duration,
+    latency: Duration,
+}
+
+/// Release the waiting writers of a take that failed to start, so their (empty) files
+/// are finalized, and wait for them.
+fn abort_writers(stop: &AtomicBool, writers: &mut [TargetWriter]) {
+    stop.store(true, Ordering::Release);
+    for w in writers.iter_mut() {
+        if let Some(h) = w.handle.take() {
+            let _ = h.join();
+        }
+    }
 }
 
 impl Recorder {
     // Use the real input sample rate from AudioInput.
     pub fn start(path: PathBuf) -> Result<Self> {
-        // Ring buffer for recording
-        let rec_capacity = 192_000;
-        let rb_rec = HeapRb::<f32>::new(rec_capacity);
-        let (prod_rec, cons_rec) = rb_rec.split();
+        Self::start_with_gain(path, 0.0, None, Duration::ZERO, Duration::ZERO)
+    }
 
-        // Ring buffer for monitoring (smaller, low-latency)
-        let mon_capacity = 192_000;
-        let rb_mon = HeapRb::<f32>::new(mon_capacity);
-        let (prod_mon, cons_mon) = rb_mon.split();
+    /// Start recording with an initial software input gain (dB, clamped to ±24).
+    /// Records `input_channels` (every device channel when `None`) into a single file,
+    /// taken at `start_time` on the timeline.
+    pub fn start_with_gain(
+        path: PathBuf,
+        input_gain_db: f32,
+        input_channels: Option<Vec<usize>>,
+        start_time: Duration,
+        latency: Duration,
+    ) -> Result<Self> {
+        let input_channels = match input_channels {
+            Some(channels) => channels,
+            None => (0..input::probe_default_input()?.1).collect(),
+        };
+        let target = RecordTarget { track_id: None, path, input_channels, device: None };
+        Self::start_targets(vec![target], input_gain_db, start_time, latency)
+    }
+
+    /// Start one take that fans input streams out to several files.
+    /// All writers exist before capture starts, and every target of a device receives the
+    /// same callback blocks, so those clips are sample-aligned at `start_time`. Targets on
+    /// other devices are drift corrected onto the first target's device and lined up by
+    /// when each stream started.
+    pub fn start_targets(
+        targets: Vec<RecordTarget>,
+        input_gain_db: f32,
+        start_time: Duration,
+        latency: Duration,
+    ) -> Result<Self> {
+        if targets.is_empty() {
+            anyhow::bail!("No record targets");
+        }
+        if let Some(t) = targets.iter().find(|t| t.input_channels.is_empty()) {
+            anyhow::bail!("Record target {:?} has no input channels", t.path);
+        }
 
-        // Input feeds both ring buffers and returns channels + sample rate
-        let (input, channels, input_sample_rate) = AudioInput::new(prod_rec, prod_mon)?;
+        // One input stream per device, in order of first use. The first device is the
+        // reference: it feeds the monitor and the meter, and the others are drift
+        // corrected onto its clock.
+        let mut devices: Vec<Option<String>> = Vec::new();
+        for t in &targets {
+            if !devices.contains(&t.device) {
+                devices.push(t.device.clone());
+            }
+        }
+        let mut device_rates = Vec::with_capacity(devices.len());
+        for device in &devices {
+            device_rates.push(input::probe_input(device.as_deref())?.2);
+        }
+        let device_levels: Vec<Arc<InputLevels>> =
+            devices.iter().map(|_| Arc::new(InputLevels::new(input_gain_db))).collect();
 
-        // Live waveform accumulator (~512 samples per bin)
+        // Live waveform accumulator (~512 samples per bin), fed by the first target
         let live_waveform = Arc::new(Mutex::new(LiveWaveform::new(512)));
-        let wf_clone = live_waveform.clone();
-
-        // Recording sample counter
+        // Recording sample counter (first target)
         let record_samples = Arc::new(AtomicU64::new(0));
-        let record_samples_clone = record_samples.clone();
+        let writer_stop = Arc::new(AtomicBool::new(false));
+        let paused = Arc::new(AtomicBool::new(false));
+
+        // A single take monitors what it records; several targets monitor the whole device
+        let monitor_channels = if targets.len() == 1 { targets[0].input_channels.clone() } else { Vec::new() };
 
-        // Writer thread: write WAV + update waveform + sample counter
-        let writer = FileWriter::new(&path, input_sample_rate, channels)?;
+        let mut lanes: Vec<Vec<_>> = devices.iter().map(|_| Vec::new()).collect();
+        let mut writers: Vec<TargetWriter> = Vec::with_capacity(targets.len());
 
-        // 5. Spawn Writer Thread
-        let writer_handle = thread::spawn(move || {
-            // Run the writer loop. We handle errors inside the thread gracefully.
-            if let Err(e) = writer.run_with_waveform(cons_rec, wf_clone, channels, record_samples_clone) {
-                eprintln!("Audio Recorder Thread Error: {}", e);
+        let abort = |writers: &mut Vec<TargetWriter>| abort_writers(&writer_stop, writers);
+
+        for (i, target) in targets.into_iter().enumerate() {
+            let device = devices.iter().position(|d| *d == target.device).unwrap_or(0);
+            let sample_rate = device_rates[device];
+
+            // Ring buffer for recording, one per target
+            let rec_capacity = 192_000;
+            let rb_rec = HeapRb::<f32>::new(rec_capacity);
+            let (prod_rec, cons_rec) = rb_rec.split();
+
+            let channels = target.input_channels.len();
+            let writer = match FileWriter::new(&target.path, sample_rate, channels) {
+                Ok(writer) if device == 0 => writer,
+                Ok(writer) => writer.with_drift(DriftCorrector::new(
+                    device_levels[0].clone(),
+                    device_rates[0],
+                    device_levels[device].clone(),
+                    sample_rate,
+                    channels,
+                )),
+                Err(e) => {
+                    abort(&mut writers);
+                    return Err(e);
+                }
+            };
+
+            // Crash marker: removed by the writer thread once the header is finalized
+            let marker = recovery::RecordingMarker {
+                track_id: target.track_id,
+                start_time: start_time.as_secs_f64(),
+                sample_rate,
+                channels,
+            };
+            if let Err(e) = recovery::write_marker(&target.path, &marker) {
+                rt_warn!("⚠️ Could not write recording marker for {:?}: {}", target.path, e);
             }
-        });
+            let marker_for = target.path.clone();
+
+            let (wf, samples) = if i == 0 {
+                (live_waveform.clone(), record_samples.clone())
+            } else {
+                (Arc::new(Mutex::new(LiveWaveform::new(512))), Arc::new(AtomicU64::new(0)))
+            };
+            let frames = samples.clone();
+            let gaps = Arc::new(Mutex::new(Vec::new()));
+            let (writer_gaps, writer_paused) = (gaps.clone(), paused.clone());
+            let stop = writer_stop.clone();
 
+            // Writer thread: write WAV + update waveform + sample counter
+            let handle = thread::spawn(move || {
+                // Run the writer loop. We handle errors inside the thread gracefully.
+                match writer.run_with_waveform(cons_rec, wf, channels, samples, writer_paused, writer_gaps, stop) {
+                    Ok(()) => recovery::remove_marker(&marker_for),
+                    Err(e) => rt_error!("Audio Recorder Thread Error: {}", e),
+                }
+            });
+
+            let overruns = Arc::new(input::LaneOverruns::default());
+            lanes[device].push(input::InputLane {
+                channels: target.input_channels.clone(),
+                producer: prod_rec,
+                overruns: overruns.clone(),
+            });
+            writers.push(TargetWriter {
+                target,
+                handle: Some(handle),
+                frames,
+                gaps,
+                overruns,
+                sample_rate,
+                clock: device_levels[device].clone(),
+            });
+        }
+
+        // Ring buffer for monitoring (smaller, low-latency)
+        let mon_capacity = 192_000;
+        let rb_mon = HeapRb::<f32>::new(mon_capacity);
+        let (prod_mon, cons_mon) = rb_mon.split();
+
+        // Inputs feed every lane + the monitor; capture starts only once all writers are running
+        let monitor_width = monitor_channels.len();
+        let mut lanes = lanes.into_iter();
+        let reference = AudioInput::open(
+            devices[0].as_deref(),
+            lanes.next().unwrap_or_default(),
+            prod_mon,
+            monitor_channels,
+            device_levels[0].clone(),
+        );
+        let (input, channels, _) = match reference {
+            Ok(v) => v,
+            Err(e) => {
+                abort(&mut writers);
+                return Err(e);
+            }
+        };
+        let mut extra_inputs = Vec::with_capacity(devices.len() - 1);
+        for ((device, device_lanes), levels) in devices.iter().skip(1).zip(lanes).zip(device_levels.iter().skip(1)) {
+            // Only the reference device is monitored
+            let (prod_none, _) = HeapRb::<f32>::new(1).split();
+            match AudioInput::open(device.as_deref(), device_lanes, prod_none, Vec::new(), levels.clone()) {
+                Ok((extra, _, _)) => extra_inputs.push(DeviceInput { _input: extra, levels: levels.clone() }),
+                Err(e) => {
+                    drop(input);
+                    drop(extra_inputs);
+                    abort(&mut writers);
+                    return Err(e);
+                }
+            }
+        }
 
         // FIX 2: Pass 'channels' to the monitor so it doesn't interleave stereo into mono
-        let monitor = Monitor::new(cons_mon, channels)?;
+        let monitor = match Monitor::new(cons_mon, if monitor_width > 0 { monitor_width } else { channels }) {
+            Ok(monitor) => monitor,
+            Err(e) => {
+                drop(input);
+                drop(extra_inputs);
+                abort(&mut writers);
+                return Err(e);
+            }
+        };
         let monitor_enabled = monitor.enabled.clone();
 
         Ok(Self {
             input,
-            writer_handle: Some(writer_handle),
+            extra_inputs,
+            writers,
+            writer_stop,
+            paused,
             monitor: Some(monitor),
             monitor_enabled,
             live_waveform,
             record_samples,
+            levels: device_levels[0].clone(),
+            start_time,
+            latency,
         })
     }
 
+    /// Overruns so far, summed over the targets; `last_at` is the latest one of any target.
+    pub fn overruns(&self) -> OverrunReport {
+        let mut report = OverrunReport::default();
+        for w in &self.writers {
+            report.events += w.overruns.events();
+            report.dropped_frames += w.overruns.dropped_frames();
+            if w.overruns.events() > 0 {
+                report.last_at = report.last_at.max(w.overruns.last_at_frame() as f64 / w.sample_rate.max(1) as f64);
+            }
+        }
+        report
+    }
+
+    /// Hold the take without finalizing it: nothing is written until `resume`, and the
+    /// point of the pause is reported in `RecordingResult::gaps`.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Release);
+    }
+
+    /// Carry on writing into the same files after `pause`.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Release);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
     pub fn is_monitor_enabled(&self) -> bool {
         self.monitor_enabled.load(Ordering::Relaxed)
     }
 
-    pub fn stop(mut self) {
-        // Drop input to stop capture
+    /// Stops capture, finalizes every file and reports one result per target.
+    pub fn stop(mut self) -> Vec<RecordingResult> {
+        let reference_start = self.levels.first_frame_at(self.input.sample_rate);
+        // Drop inputs to stop capture, then let the writers drain and finalize
         drop(self.input);
-        if let Some(h) = self.writer_handle.take() {
-            let _ = h.join();
+        self.extra_inputs.clear();
+        self.writer_stop.store(true, Ordering::Release);
+
+        let mut results = Vec::with_capacity(self.writers.len());
+        for w in self.writers.iter_mut() {
+            if let Some(h) = w.handle.take() {
+                let _ = h.join();
+            }
+            let sample_rate = w.sample_rate;
+            // Another device's stream began a little earlier or later than the reference:
+            // start the clip later, or skip the extra audio
+            let (mut start_time, mut offset) = (self.start_time.as_secs_f64(), self.latency.as_secs_f64());
+            if let (Some(reference), Some(own)) = (reference_start, w.clock.first_frame_at(sample_rate)) {
+                if own >= reference {
+                    start_time += (own - reference).as_secs_f64();
+                } else {
+                    offset += (reference - own).as_secs_f64();
+                }
+            }
+            let channels = w.target.input_channels.len();
+            let frames = w.frames.load(Ordering::Relaxed) / channels as u64;
+            // A pause at the very start or end, or one with nothing written since the last,
+            // doesn't split anything
+            let mut gaps = w.gaps.lock().map(|g| g.clone()).unwrap_or_default();
+            gaps.dedup();
+            let gaps = gaps
+                .into_iter()
+                .filter(|&f| f > 0 && f < frames)
+                .map(|f| f as f64 / sample_rate as f64)
+                .collect();
+            results.push(RecordingResult {
+                track_id: w.target.track_id,
+                path: w.target.path.to_string_lossy().to_string(),
+                channels,
+                sample_rate,
+                duration: frames as f64 / sample_rate as f64,
+                start_time,
+                offset,
+                gaps,
+                dropped_frames: w.overruns.dropped_frames(),
+            });
+            if w.overruns.dropped_frames() > 0 {
+                rt_warn!(
+                    "⚠️ Take {:?} lost {} frames in {} overrun(s); the file is shorter than the take",
+                    w.target.path,
+                    w.overruns.dropped_frames(),
+                    w.overruns.events()
+                );
+            }
         }
+        results
     }
 
-    // Recording time based on samples written and input sample rate.
+    // Recording time based on frames written by the first target and input sample rate.
     pub fn get_record_time(&self) -> std::time::Duration {
+        let channels = self.writers.first().map(|w| w.target.input_channels.len()).unwrap_or(1) as f64;
         let samples = self.record_samples.load(Ordering::Relaxed) as f64;
-        let secs = samples / self.input.sample_rate as f64;
+        let secs = samples / channels / self.input.sample_rate as f64;
         std::time::Duration::from_secs_f64(secs)
     }
 
@@ -105,15 +422,108 @@ impl Recorder {
         self.monitor_enabled.store(!cur, Ordering::Relaxed);
         
         if self.is_monitor_enabled() {
-            println!("\n🎧 Monitor ON");
+            rt_info!("🎧 Monitor ON");
         } else {
-            println!("\n🎧 Monitor OFF");
+            rt_info!("🎧 Monitor OFF");
         }
         Ok(())
     }
 
+    // --- INPUT GAIN / METERING ---
+
+    /// Software preamp, applied in the input callback before samples reach the writer and monitor.
+    /// Every device of the take shares it.
+    pub fn set_input_gain_db(&self, db: f32) {
+        self.levels.set_gain_db(db);
+        for extra in &self.extra_inputs {
+            extra.levels.set_gain_db(db);
+        }
+    }
+
+    pub fn input_gain_db(&self) -> f32 {
+        self.levels.gain_db()
+    }
+
+    /// Post-gain (peak, rms) since the last call, linear; the hottest device of the take.
+    pub fn take_input_meter(&self) -> (f32, f32) {
+        self.extra_inputs.iter().fold(self.levels.take_meter(), |(peak, rms), extra| {
+            let (p, r) = extra.levels.take_meter();
+            (peak.max(p), rms.max(r))
+        })
+    }
+
+    pub fn is_input_clipped(&self) -> bool {
+        self.levels.is_clipped() || self.extra_inputs.iter().any(|extra| extra.levels.is_clipped())
+    }
+
+    pub fn clear_input_clip(&self) {
+        self.levels.clear_clip();
+        for extra in &self.extra_inputs {
+            extra.levels.clear_clip();
+        }
+    }
+
+    pub fn input_device_name(&self) -> &str {
+        &self.input.device_name
+    }
+
     /// For UI: clone the Arc so main.rs can snapshot bins.
     pub fn live_waveform(&self) -> Arc<Mutex<LiveWaveform>> {
         self.live_waveform.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waiting_writer(stop: &Arc<AtomicBool>, finalized: &Arc<AtomicU64>) -> TargetWriter {
+        let (stop, finalized) = (stop.clone(), finalized.clone());
+        let handle = thread::spawn(move || {
+            while !stop.load(Ordering::Acquire) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            finalized.fetch_add(1, Ordering::AcqRel);
+        });
+        TargetWriter {
+            target: RecordTarget { track_id: None, path: PathBuf::new(), input_channels: vec![0], device: None },
+            handle: Some(handle),
+            frames: Arc::new(AtomicU64::new(0)),
+            gaps: Arc::new(Mutex::new(Vec::new())),
+            overruns: Arc::new(input::LaneOverruns::default()),
+            sample_rate: 48_000,
+            clock: Arc::new(InputLevels::new(0.0)),
+        }
+    }
+
+    #[test]
+    fn abort_stops_and_joins_every_started_writer() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let finalized = Arc::new(AtomicU64::new(0));
+        let mut writers: Vec<_> = (0..3).map(|_| waiting_writer(&stop, &finalized)).collect();
+
+        abort_writers(&stop, &mut writers);
+
+        assert!(stop.load(Ordering::Acquire));
+        assert_eq!(finalized.load(Ordering::Acquire), 3);
+        assert!(writers.iter().all(|w| w.handle.is_none()));
+    }
+
+    #[test]
+    fn abort_with_no_writers_only_raises_the_stop_flag() {
+        let stop = AtomicBool::new(false);
+        abort_writers(&stop, &mut []);
+        assert!(stop.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn invalid_targets_are_rejected_before_anything_starts() {
+        assert!(Recorder::start_targets(Vec::new(), 0.0, Duration::ZERO, Duration::ZERO).is_err());
+
+        let dir = crate::test_util::temp_path("recorder_no_channels");
+        let target = RecordTarget { track_id: None, path: dir.join("take.wav"), input_channels: Vec::new(), device: None };
+        let err = Recorder::start_targets(vec![target], 0.0, Duration::ZERO, Duration::ZERO).err().unwrap();
+        assert!(err.to_string().contains("no input channels"));
+        assert!(!dir.join("take.wav").exists());
+    }
+}
@@ -0,0 +1,327 @@
+// src/recorder/recovery.rs
+
+// Crash recovery for takes. A `<take>.wav.recording` marker is written next to every
+// file when capture starts and removed once the writer has finalized the WAV header.
+// A marker that survives means the app died mid-take: the PCM is on disk, but the
+// RIFF/data sizes were never written. `repair_wav` rebuilds them from the file size.
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const MARKER_EXTENSION: &str = "recording";
+
+/// Contents of the sidecar marker: enough to place a recovered take back on its track.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingMarker {
+    pub track_id: Option<u32>,
+    pub start_time: f64,
+    pub sample_rate: u32,
+    pub channels: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairedInfo {
+    pub path: String,
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub frames: u64,
+    pub duration: f64,
+    /// False when the header was already consistent and nothing was rewritten.
+    pub was_damaged: bool,
+}
+
+/// A take found (and repaired) at startup.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveredTake {
+    pub info: RepairedInfo,
+    pub track_id: Option<u32>,
+    pub start_time: f64,
+}
+
+/// `take.wav` -> `take.wav.recording`
+pub fn marker_path_for(wav_path: &Path) -> PathBuf {
+    let mut p = wav_path.as_os_str().to_os_string();
+    p.push(".");
+    p.push(MARKER_EXTENSION);
+    PathBuf::from(p)
+}
+
+pub fn write_marker(wav_path: &Path, marker: &RecordingMarker) -> Result<()> {
+    fs::write(marker_path_for(wav_path), serde_json::to_vec(marker)?)?;
+    Ok(())
+}
+
+pub fn read_marker(wav_path: &Path) -> Option<RecordingMarker> {
+    let bytes = fs::read(marker_path_for(wav_path)).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+pub fn remove_marker(wav_path: &Path) {
+    let _ = fs::remove_file(marker_path_for(wav_path));
+}
+
+/// Rewrite the RIFF and data chunk sizes of a WAV whose writer never finalized.
+/// The data chunk is assumed to run to the end of the file (that's how the recorder
+/// writes it); a trailing partial frame is cut off. The result is validated by
+/// decoding its first and last second.
+pub fn repair_wav(path: &Path) -> Result<RepairedInfo> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let file_len = file.metadata()?.len();
+
+    let mut riff = [0u8; 12];
+    file.read_exact(&mut riff).map_err(|_| anyhow!("{} is too short to be a WAV", path.display()))?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        bail!("{} has no RIFF/WAVE header", path.display());
+    }
+
+    // Walk chunks up to "data". Everything before it was written at record start, so its sizes are valid.
+    let mut pos = 12u64;
+    let mut format: Option<(u16, u32, u16)> = None; // (channels, sample rate, block align)
+    let data_pos = loop {
+        if pos + 8 > file_len {
+            bail!("{} has no data chunk", path.display());
+        }
+        let mut header = [0u8; 8];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut header)?;
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+
+        match &header[0..4] {
+            b"data" => break pos,
+            b"fmt " => {
+                let mut fmt = [0u8; 16];
+                file.read_exact(&mut fmt)?;
+                let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+                let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                let block_align = u16::from_le_bytes([fmt[12], fmt[13]]);
+                format = Some((channels, sample_rate, block_align));
+            }
+            _ => {}
+        }
+        pos += 8 + size + (size & 1);
+    };
+
+    let (channels, sample_rate, block_align) = format.ok_or_else(|| anyhow!("{} has no fmt chunk", path.display()))?;
+    if channels == 0 || sample_rate == 0 || block_align == 0 {
+        bail!("{} has an invalid fmt chunk", path.display());
+    }
+
+    let data_start = data_pos + 8;
+    let mut current = [0u8; 4];
+    file.seek(SeekFrom::Start(4))?;
+    file.read_exact(&mut current)?;
+    let declared_riff = u32::from_le_bytes(current) as u64;
+    file.seek(SeekFrom::Start(data_pos + 4))?;
+    file.read_exact(&mut current)?;
+    let declared_data = u32::from_le_bytes(current) as u64;
+
+    // A finalized header is left alone (it may be followed by other chunks we must keep)
+    let was_damaged = declared_riff == 0 || declared_data == 0 || data_start + declared_data > file_len;
+    let data_len = if was_damaged {
+        (file_len.saturating_sub(data_start) / block_align as u64) * block_align as u64
+    } else {
+        declared_data
+    };
+    if data_len > u32::MAX as u64 - data_start {
+        bail!("{} is larger than a WAV file can describe", path.display());
+    }
+
+    if was_damaged {
+        let riff_size = (data_start + data_len - 8) as u32;
+        file.seek(SeekFrom::Start(4))?;
+        file.write_all(&riff_size.to_le_bytes())?;
+        file.seek(SeekFrom::Start(data_pos + 4))?;
+        file.write_all(&(data_len as u32).to_le_bytes())?;
+        file.set_len(data_start + data_len)?;
+        file.sync_all()?;
+    }
+    drop(file);
+
+    let frames = data_len / block_align as u64;
+    validate(path, sample_rate, frames)?;
+
+    Ok(RepairedInfo {
+        path: path.to_string_lossy().to_string(),
+        sample_rate,
+        channels: channels as usize,
+        frames,
+        duration: frames as f64 / sample_rate as f64,
+        was_damaged,
+    })
+}
+
+/// Decode the first and last second of the file.
+fn validate(path: &Path, sample_rate: u32, frames: u64) -> Result<()> {
+    let mut reader = hound::WavReader::new(std::io::BufReader::new(File::open(path)?))?;
+    let spec = reader.spec();
+    let window = (sample_rate as u64).min(frames) as u32;
+    let samples = window as usize * spec.channels as usize;
+
+    let check = |reader: &mut hound::WavReader<_>, at: u32| -> Result<()> {
+        reader.seek(at)?;
+        match spec.sample_format {
+            hound::SampleFormat::Float => {
+                for s in reader.samples::<f32>().take(samples) { s?; }
+            }
+            hound::SampleFormat::Int => {
+                for s in reader.samples::<i32>().take(samples) { s?; }
+            }
+        }
+        Ok(())
+    };
+    check(&mut reader, 0)?;
+    check(&mut reader, (frames - window as u64) as u32)?;
+    Ok(())
+}
+
+/// Repair every take in `dir` that still has a marker. Markers of repaired takes are removed;
+/// takes that can't be repaired keep theirs so nothing is lost.
+pub fn recover_unfinished(dir: &Path) -> Vec<RecoveredTake> {
+    let Ok(entries) = fs::read_dir(dir) else { return Vec::new() };
+    let mut recovered = Vec::new();
+
+    for entry in entries.flatten() {
+        let marker_path = entry.path();
+        if marker_path.extension().and_then(|e| e.to_str()) != Some(MARKER_EXTENSION) {
+            continue;
+        }
+        let wav_path = marker_path.with_extension("");
+        if !wav_path.exists() {
+            // Take was deleted; the marker is all that's left
+            let _ = fs::remove_file(&marker_path);
+            continue;
+        }
+
+        let marker = read_marker(&wav_path);
+        match repair_wav(&wav_path) {
+            Ok(info) => {
+                rt_info!("🩹 Recovered take {} ({:.1}s)", info.path, info.duration);
+                remove_marker(&wav_path);
+                recovered.push(RecoveredTake {
+                    info,
+                    track_id: marker.as_ref().and_then(|m| m.track_id),
+                    start_time: marker.as_ref().map(|m| m.start_time).unwrap_or(0.0),
+                });
+            }
+            Err(e) => rt_warn!("⚠️ Could not recover take {}: {}", wav_path.display(), e),
+        }
+    }
+    recovered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SR: u32 = 8_000;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = crate::test_util::temp_path(&format!("take_recovery_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Finalized stereo 16-bit take of `frames` frames: sample n of channel c is n + c
+    fn write_take(path: &Path, frames: u32) -> Vec<i16> {
+        let spec = hound::WavSpec { channels: 2, sample_rate: SR, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        let samples: Vec<i16> = (0..frames).flat_map(|n| [n as i16, n as i16 + 1]).collect();
+        for &s in &samples {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+        samples
+    }
+
+    // What a crash leaves: RIFF and data sizes still zero, plus half a frame that never completed
+    fn crash(path: &Path) {
+        let mut bytes = fs::read(path).unwrap();
+        let data = bytes.windows(4).position(|w| w == b"data").unwrap();
+        bytes[4..8].copy_from_slice(&0u32.to_le_bytes());
+        bytes[data + 4..data + 8].copy_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&[0x12, 0x34]);
+        fs::write(path, bytes).unwrap();
+    }
+
+    fn decode(path: &Path) -> Vec<i16> {
+        hound::WavReader::open(path).unwrap().samples::<i16>().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn repair_restores_every_frame_of_a_crashed_take() {
+        let dir = scratch_dir("repair");
+        let path = dir.join("take.wav");
+        let samples = write_take(&path, SR * 3);
+        crash(&path);
+        assert!(hound::WavReader::open(&path).map(|r| r.duration()).unwrap_or(0) == 0);
+
+        let info = repair_wav(&path).unwrap();
+        let decoded = decode(&path);
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(info.was_damaged);
+        assert_eq!((info.sample_rate, info.channels, info.frames), (SR, 2, SR as u64 * 3));
+        assert_eq!(info.duration, 3.0);
+        assert_eq!(decoded, samples);
+    }
+
+    #[test]
+    fn a_finalized_take_is_left_alone() {
+        let dir = scratch_dir("intact");
+        let path = dir.join("take.wav");
+        write_take(&path, SR);
+        let before = fs::read(&path).unwrap();
+
+        let info = repair_wav(&path).unwrap();
+        let after = fs::read(&path).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(!info.was_damaged);
+        assert_eq!(info.frames, SR as u64);
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn files_that_are_not_wavs_are_refused() {
+        let dir = scratch_dir("garbage");
+        let path = dir.join("take.wav");
+        fs::write(&path, b"definitely not audio").unwrap();
+        let result = repair_wav(&path);
+        let _ = fs::remove_dir_all(&dir);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn startup_scan_repairs_marked_takes_and_drops_their_markers() {
+        let dir = scratch_dir("scan");
+        let crashed = dir.join("crashed.wav");
+        let finished = dir.join("finished.wav");
+        write_take(&crashed, SR);
+        write_take(&finished, SR);
+        crash(&crashed);
+        write_marker(&crashed, &RecordingMarker { track_id: Some(7), start_time: 12.5, sample_rate: SR, channels: 2 }).unwrap();
+        // A marker whose take was deleted is cleaned up
+        fs::write(marker_path_for(&dir.join("gone.wav")), b"{}").unwrap();
+
+        let recovered = recover_unfinished(&dir);
+        let markers_left = fs::read_dir(&dir).unwrap().flatten()
+            .filter(|e| e.path().extension().and_then(|x| x.to_str()) == Some(MARKER_EXTENSION))
+            .count();
+        let decoded = decode(&crashed).len();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].track_id, Some(7));
+        assert_eq!(recovered[0].start_time, 12.5);
+        assert!(recovered[0].info.path.ends_with("crashed.wav"));
+        assert_eq!(markers_left, 0);
+        assert_eq!(decoded, SR as usize * 2);
+    }
+}
@@ -0,0 +1,206 @@
+// src/recorder/retro.rs
+
+// Retro-record: the input is kept in a rolling buffer whether or not a take is running, so
+// a performance nobody pressed record for can still be saved. A drain thread moves the
+// captured audio into the history every few milliseconds and stamps it with where the
+// transport was when that audio arrived (input callback clock against the transport
+// clock). `capture_last` cuts the end of the history down to the last stretch the
+// transport was rolling through and writes it as a WAV, positioned on the timeline.
+
+use anyhow::Result;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use ringbuf::{HeapRb, traits::{Consumer, Split}};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::input::{self, AudioInput, InputLane, InputLevels};
+use super::RecordingResult;
+use crate::engine::clock::TransportClock;
+
+/// Range of the rolling buffer, in seconds.
+pub const MIN_RETRO_SECS: f64 = 5.0;
+pub const MAX_RETRO_SECS: f64 = 300.0;
+
+const DRAIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Where the transport was when a frame of the history arrived.
+#[derive(Clone, Copy)]
+struct Mark {
+    frame: u64,    // absolute frame index (frames appended since the buffer started)
+    position: f64, // timeline seconds at that frame
+    playing: bool,
+    generation: u64, // transport clock generation (bumped by play, pause and seek)
+    rate: f64,
+}
+
+struct History {
+    samples: VecDeque<f32>,
+    capacity: usize, // samples
+    first_frame: u64, // absolute index of the oldest frame kept
+    marks: VecDeque<Mark>,
+}
+
+pub struct RetroBuffer {
+    _input: AudioInput,
+    levels: Arc<InputLevels>,
+    history: Arc<Mutex<History>>,
+    stop: Arc<AtomicBool>,
+    drain: Option<thread::JoinHandle<()>>,
+    channels: usize,
+    sample_rate: u32,
+    seconds: f64,
+}
+
+impl RetroBuffer {
+    /// Opens the default input and keeps its last `seconds` (clamped to 5..=300) with
+    /// `input_gain_db` of trim applied.
+    pub fn start(seconds: f64, input_gain_db: f32, clock: Arc<TransportClock>) -> Result<Self> {
+        let seconds = if seconds.is_finite() { seconds.clamp(MIN_RETRO_SECS, MAX_RETRO_SECS) } else { MIN_RETRO_SECS };
+        let (_, channels, sample_rate) = input::probe_default_input()?;
+        let (prod, mut cons) = HeapRb::<f32>::new(sample_rate as usize * channels).split();
+        let (prod_mon, _) = HeapRb::<f32>::new(1).split();
+        let levels = Arc::new(InputLevels::new(input_gain_db));
+        let lanes = vec![InputLane { channels: (0..channels).collect(), producer: prod, overruns: Default::default() }];
+        let (input, _, _) = AudioInput::new(lanes, prod_mon, Vec::new(), levels.clone())?;
+
+        let capacity = (seconds * sample_rate as f64) as usize * channels;
+        let history = Arc::new(Mutex::new(History {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            first_frame: 0,
+            marks: VecDeque::new(),
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let drain = {
+            let (history, stop, levels) = (history.clone(), stop.clone(), levels.clone());
+            thread::spawn(move || {
+                let mut tmp = vec![0.0f32; 8192 * channels];
+                let mut appended: u64 = 0; // frames
+                while !stop.load(Ordering::Acquire) {
+                    thread::sleep(DRAIN_INTERVAL);
+                    let Ok(mut h) = history.lock() else { return };
+                    loop {
+                        let n = cons.pop_slice(&mut tmp);
+                        if n == 0 {
+                            break;
+                        }
+                        h.samples.extend(&tmp[..n]);
+                        appended += (n / channels) as u64;
+                    }
+                    let excess = h.samples.len().saturating_sub(h.capacity);
+                    if excess > 0 {
+                        h.samples.drain(..excess);
+                        h.first_frame += (excess / channels) as u64;
+                    }
+
+                    // Stamp the newest frame: its arrival on the input clock, mapped onto the
+                    // transport through the clock's latest publish
+                    let (delivered, block_end) = levels.input_clock();
+                    let behind = delivered.saturating_sub(appended) as f64 / sample_rate as f64;
+                    let sync = clock.snapshot();
+                    let since = Instant::now().saturating_duration_since(block_end).as_secs_f64() + behind;
+                    let position = if sync.playing { sync.position_now() - since * sync.playback_rate } else { sync.position_now() };
+                    h.marks.push_back(Mark {
+                        frame: appended,
+                        position,
+                        playing: sync.playing,
+                        generation: sync.generation,
+                        rate: sync.playback_rate,
+                    });
+                    let first_frame = h.first_frame;
+                    while h.marks.len() > 1 && h.marks[1].frame <= first_frame {
+                        h.marks.pop_front();
+                    }
+                }
+            })
+        };
+
+        Ok(Self { _input: input, levels, history, stop, drain: Some(drain), channels, sample_rate, seconds })
+    }
+
+    pub fn seconds(&self) -> f64 {
+        self.seconds
+    }
+
+    pub fn set_input_gain_db(&self, db: f32) {
+        self.levels.set_gain_db(db);
+    }
+
+    /// Write up to the last `duration` seconds the transport was rolling through to `path`
+    /// (16-bit WAV, like a take). `start_time` of the result is where that audio was heard
+    /// on the timeline; `track_id` is left for the caller and `offset` is 0.
+    pub fn capture_last(&self, duration: f64, path: &Path) -> Result<RecordingResult> {
+        let h = self.history.lock().map_err(|_| anyhow::anyhow!("Retro buffer lock poisoned"))?;
+        let channels = self.channels;
+        let sr = self.sample_rate as f64;
+
+        // The last stretch of one play: from its first mark to its last
+        let last = h
+            .marks
+            .iter()
+            .rev()
+            .find(|m| m.playing)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("The transport wasn't rolling in the buffered audio"))?;
+        let first = h
+            .marks
+            .iter()
+            .rev()
+            .skip_while(|m| m.frame > last.frame)
+            .take_while(|m| m.playing && m.generation == last.generation)
+            .last()
+            .copied()
+            .unwrap_or(last);
+
+        let wanted = (duration.max(0.0) * sr) as u64;
+        let start = last.frame.saturating_sub(wanted).max(first.frame).max(h.first_frame);
+        let end = last.frame.min(h.first_frame + (h.samples.len() / channels) as u64);
+        if end <= start {
+            anyhow::bail!("No buffered audio to capture");
+        }
+
+        let from = (start - h.first_frame) as usize * channels;
+        let to = (end - h.first_frame) as usize * channels;
+        let samples: Vec<f32> = h.samples.range(from..to).copied().collect();
+        drop(h); // the drain thread carries on while the file is written
+
+        let spec = WavSpec {
+            channels: channels as u16,
+            sample_rate: self.sample_rate,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let mut writer = WavWriter::create(path, spec)?;
+        for s in samples {
+            writer.write_sample((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+        }
+        writer.finalize()?;
+
+        let frames = end - start;
+        Ok(RecordingResult {
+            track_id: None,
+            path: path.to_string_lossy().to_string(),
+            channels,
+            sample_rate: self.sample_rate,
+            duration: frames as f64 / sr,
+            start_time: (last.position - (last.frame - start) as f64 / sr * last.rate).max(0.0),
+            offset: 0.0,
+            gaps: Vec::new(),
+            dropped_frames: 0,
+        })
+    }
+}
+
+impl Drop for RetroBuffer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.drain.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,353 @@
+// src/routing_check.rs
+
+// Feedback check for the monitor path. The signal flow is small enough to build as a graph
+// every time something changes: the input feeds the monitor, the monitor lands on the cue
+// bus (or the master), buses go to the output device, and an output device that loops back
+// into the input (virtual cables, "Stereo Mix", PulseAudio monitors, picked out by name)
+// closes the circle. Any cycle is a feedback loop; each warning carries the fixes that
+// would break it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::output_routing::{BusRoute, OutputBus};
+
+/// Name fragments of devices that hand their output back as an input (lowercase).
+const LOOPBACK_HINTS: &[&str] = &[
+    "loopback",
+    "blackhole",
+    "soundflower",
+    "vb-audio",
+    "voicemeeter",
+    "cable input",
+    "cable output",
+    "stereo mix",
+    "what u hear",
+    "wave out mix",
+    "monitor of",
+    "virtual",
+];
+
+/// Inputs that record whatever the system is playing, whichever output that is.
+const SYSTEM_CAPTURE_HINTS: &[&str] = &["stereo mix", "what u hear", "wave out mix"];
+
+/// What the check needs to know about the current setup.
+#[derive(Debug, Clone, Default)]
+pub struct RoutingSetup {
+    pub input_device: Option<String>,
+    /// An input is being monitored through the engine.
+    pub monitoring: bool,
+    pub monitor_muted: bool,
+    pub output_device: Option<String>,
+    pub routes: Vec<BusRoute>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RoutingWarningKind {
+    /// The monitored input comes back into itself.
+    FeedbackLoop,
+    /// Monitoring an input on the device it comes from. Fine on most interfaces, a loop
+    /// when the interface mixes its outputs back into the inputs.
+    MonitorOnInputDevice,
+}
+
+/// A way to break the offending path, offered to the user (or applied automatically).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RoutingFix {
+    MuteMonitor,
+    UnrouteBus { bus: OutputBus },
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingWarning {
+    pub kind: RoutingWarningKind,
+    pub message: String,
+    /// The loop, node by node ("Input 'X'", "Monitor", "Cue bus", ...).
+    pub path: Vec<String>,
+    pub fixes: Vec<RoutingFix>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Input(String),
+    Monitor,
+    Bus(OutputBus),
+    Output(String),
+}
+
+impl std::fmt::Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Node::Input(name) => write!(f, "Input '{}'", name),
+            Node::Monitor => write!(f, "Monitor"),
+            Node::Bus(OutputBus::Master) => write!(f, "Master bus"),
+            Node::Bus(OutputBus::Cue) => write!(f, "Cue bus"),
+            Node::Bus(OutputBus::Track(id)) => write!(f, "Direct out of track {}", id),
+            Node::Output(name) => write!(f, "Output '{}'", name),
+        }
+    }
+}
+
+/// Directed signal flow between the nodes.
+#[derive(Debug, Default)]
+pub struct SignalGraph {
+    nodes: Vec<Node>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl SignalGraph {
+    pub fn build(setup: &RoutingSetup) -> Self {
+        let mut graph = Self::default();
+        let cue_routed = setup.routes.iter().any(|r| r.bus == OutputBus::Cue);
+
+        let monitored = setup.input_device.as_ref().filter(|_| setup.monitoring && !setup.monitor_muted);
+        if let Some(input) = monitored {
+            graph.connect(Node::Input(input.clone()), Node::Monitor);
+            let bus = if cue_routed { OutputBus::Cue } else { OutputBus::Master };
+            graph.connect(Node::Monitor, Node::Bus(bus));
+        }
+        if let Some(output) = &setup.output_device {
+            for route in &setup.routes {
+                graph.connect(Node::Bus(route.bus), Node::Output(output.clone()));
+            }
+            if let Some(input) = setup.input_device.as_ref().filter(|input| loops_back(output, input)) {
+                graph.connect(Node::Output(output.clone()), Node::Input(input.clone()));
+            }
+        }
+        graph
+    }
+
+    pub fn connect(&mut self, from: Node, to: Node) {
+        let (a, b) = (self.index_of(from), self.index_of(to));
+        if !self.edges.contains(&(a, b)) {
+            self.edges.push((a, b));
+        }
+    }
+
+    fn index_of(&mut self, node: Node) -> usize {
+        match self.nodes.iter().position(|n| *n == node) {
+            Some(i) => i,
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// First cycle found, as its nodes in signal order (the start is not repeated).
+    pub fn find_cycle(&self) -> Option<Vec<Node>> {
+        // 0 = unvisited, 1 = on the current path, 2 = done
+        let mut state = vec![0u8; self.nodes.len()];
+        let mut path = Vec::new();
+        (0..self.nodes.len()).find_map(|start| self.visit(start, &mut state, &mut path))
+    }
+
+    fn visit(&self, node: usize, state: &mut [u8], path: &mut Vec<usize>) -> Option<Vec<Node>> {
+        match state[node] {
+            2 => return None,
+            1 => {
+                let from = path.iter().position(|&n| n == node)?;
+                return Some(path[from..].iter().map(|&n| self.nodes[n].clone()).collect());
+            }
+            _ => {}
+        }
+        state[node] = 1;
+        path.push(node);
+        for &(_, next) in self.edges.iter().filter(|(from, _)| *from == node) {
+            if let Some(cycle) = self.visit(next, state, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        state[node] = 2;
+        None
+    }
+}
+
+/// Whether the device name looks like a virtual/loopback device.
+pub fn is_loopback_device(name: &str) -> bool {
+    let name = name.to_lowercase();
+    LOOPBACK_HINTS.iter().any(|hint| name.contains(hint))
+}
+
+/// Whether what plays on `output` can come back in on `input`.
+pub fn loops_back(output: &str, input: &str) -> bool {
+    let (out_lower, in_lower) = (output.to_lowercase(), input.to_lowercase());
+    if SYSTEM_CAPTURE_HINTS.iter().any(|hint| in_lower.contains(hint)) {
+        return true;
+    }
+    // PulseAudio/PipeWire: "Monitor of Built-in Audio"
+    if let Some(source) = in_lower.strip_prefix("monitor of ") {
+        return out_lower.contains(source.trim()) || source.contains(out_lower.trim());
+    }
+    // Both ends of one virtual cable ("CABLE Input" / "CABLE Output", "BlackHole 2ch")
+    is_loopback_device(output) && is_loopback_device(input) && device_family(output) == device_family(input)
+}
+
+// Device name without its direction words, so both ends of a virtual cable compare equal
+fn device_family(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && !matches!(*w, "input" | "output" | "in" | "out" | "playback" | "recording" | "capture"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Every problem with the current setup, most serious first.
+pub fn check_routing(setup: &RoutingSetup) -> Vec<RoutingWarning> {
+    let mut warnings = Vec::new();
+
+    if let Some(cycle) = SignalGraph::build(setup).find_cycle() {
+        let mut fixes = Vec::new();
+        if cycle.contains(&Node::Monitor) {
+            fixes.push(RoutingFix::MuteMonitor);
+        }
+        // The master always has an output; any other bus on the loop can be unrouted
+        fixes.extend(cycle.iter().filter_map(|n| match n {
+            Node::Bus(bus) if *bus != OutputBus::Master => Some(RoutingFix::UnrouteBus { bus: *bus }),
+            _ => None,
+        }));
+        let path: Vec<String> = cycle.iter().map(Node::to_string).collect();
+        warnings.push(RoutingWarning {
+            kind: RoutingWarningKind::FeedbackLoop,
+            message: format!(
+                "Feedback loop: {} -> back into {}. The output device feeds the recording input, so the monitored signal keeps growing.",
+                path.join(" -> "),
+                path[0]
+            ),
+            path,
+            fixes,
+        });
+    }
+
+    let same_device = setup.input_device.is_some() && setup.input_device == setup.output_device;
+    if same_device && setup.monitoring && !setup.monitor_muted && warnings.is_empty() {
+        let device = setup.input_device.clone().unwrap_or_default();
+        warnings.push(RoutingWarning {
+            kind: RoutingWarningKind::MonitorOnInputDevice,
+            message: format!(
+                "'{}' is monitored on its own outputs. If the interface has loopback or 'mix to input' enabled, this will feed back.",
+                device
+            ),
+            path: vec![Node::Input(device.clone()).to_string(), Node::Monitor.to_string(), Node::Output(device).to_string()],
+            fixes: vec![RoutingFix::MuteMonitor],
+        });
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(bus: OutputBus, left: usize) -> BusRoute {
+        BusRoute { bus, left, right: left + 1 }
+    }
+
+    fn setup(input: &str, output: &str, routes: Vec<BusRoute>) -> RoutingSetup {
+        RoutingSetup {
+            input_device: Some(input.into()),
+            monitoring: true,
+            monitor_muted: false,
+            output_device: Some(output.into()),
+            routes,
+        }
+    }
+
+    #[test]
+    fn a_chain_without_a_way_back_has_no_cycle() {
+        let mut graph = SignalGraph::default();
+        graph.connect(Node::Input("Mic".into()), Node::Monitor);
+        graph.connect(Node::Monitor, Node::Bus(OutputBus::Master));
+        graph.connect(Node::Bus(OutputBus::Master), Node::Output("Speakers".into()));
+        // A diamond is two paths, not a loop
+        graph.connect(Node::Monitor, Node::Bus(OutputBus::Cue));
+        graph.connect(Node::Bus(OutputBus::Cue), Node::Output("Speakers".into()));
+        assert_eq!(graph.find_cycle(), None);
+    }
+
+    #[test]
+    fn a_cycle_comes_back_in_signal_order_from_where_it_closes() {
+        let mut graph = SignalGraph::default();
+        graph.connect(Node::Input("Cable Output".into()), Node::Monitor);
+        graph.connect(Node::Monitor, Node::Bus(OutputBus::Cue));
+        graph.connect(Node::Bus(OutputBus::Cue), Node::Output("Cable Input".into()));
+        graph.connect(Node::Output("Cable Input".into()), Node::Input("Cable Output".into()));
+        // A branch off the loop that leads nowhere
+        graph.connect(Node::Bus(OutputBus::Cue), Node::Output("Speakers".into()));
+        assert_eq!(
+            graph.find_cycle(),
+            Some(vec![
+                Node::Input("Cable Output".into()),
+                Node::Monitor,
+                Node::Bus(OutputBus::Cue),
+                Node::Output("Cable Input".into()),
+            ])
+        );
+
+        // A self loop is a cycle too
+        let mut graph = SignalGraph::default();
+        graph.connect(Node::Monitor, Node::Monitor);
+        assert_eq!(graph.find_cycle(), Some(vec![Node::Monitor]));
+    }
+
+    #[test]
+    fn loopback_devices_are_recognised_by_name() {
+        assert!(loops_back("CABLE Input (VB-Audio Virtual Cable)", "CABLE Output (VB-Audio Virtual Cable)"));
+        assert!(loops_back("BlackHole 2ch", "BlackHole 2ch"));
+        assert!(loops_back("Built-in Audio Analog Stereo", "Monitor of Built-in Audio Analog Stereo"));
+        assert!(loops_back("Speakers (Realtek)", "Stereo Mix (Realtek)"));
+        // Two different virtual devices, or real hardware, don't loop
+        assert!(!loops_back("BlackHole 2ch", "Soundflower (2ch)"));
+        assert!(!loops_back("Scarlett 2i2 USB", "Scarlett 2i2 USB"));
+        assert!(!loops_back("Speakers (Realtek)", "Monitor of HDMI Output"));
+        assert!(is_loopback_device("Voicemeeter Input"));
+        assert!(!is_loopback_device("MacBook Pro Speakers"));
+    }
+
+    #[test]
+    fn monitoring_through_a_virtual_cable_is_a_feedback_loop() {
+        let warnings = check_routing(&setup("CABLE Output", "CABLE Input", vec![route(OutputBus::Master, 0)]));
+        assert_eq!(warnings.len(), 1);
+        let warning = &warnings[0];
+        assert_eq!(warning.kind, RoutingWarningKind::FeedbackLoop);
+        assert_eq!(warning.path, vec!["Input 'CABLE Output'", "Monitor", "Master bus", "Output 'CABLE Input'"]);
+        // The master can't be unrouted: muting the monitor is the only way out
+        assert_eq!(warning.fixes, vec![RoutingFix::MuteMonitor]);
+        assert!(warning.message.contains("Feedback loop"));
+    }
+
+    #[test]
+    fn a_loop_through_the_cue_bus_offers_to_unroute_it() {
+        let routes = vec![route(OutputBus::Master, 0), route(OutputBus::Cue, 2)];
+        let warnings = check_routing(&setup("BlackHole 2ch", "BlackHole 2ch", routes));
+        assert_eq!(warnings.len(), 1, "the loop replaces the same-device warning");
+        assert_eq!(warnings[0].kind, RoutingWarningKind::FeedbackLoop);
+        assert!(warnings[0].path.contains(&"Cue bus".to_string()));
+        assert_eq!(warnings[0].fixes, vec![RoutingFix::MuteMonitor, RoutingFix::UnrouteBus { bus: OutputBus::Cue }]);
+    }
+
+    #[test]
+    fn a_muted_or_absent_monitor_breaks_the_loop() {
+        let mut looped = setup("CABLE Output", "CABLE Input", vec![route(OutputBus::Master, 0)]);
+        looped.monitor_muted = true;
+        assert!(check_routing(&looped).is_empty());
+        looped.monitor_muted = false;
+        looped.monitoring = false;
+        assert!(check_routing(&looped).is_empty());
+    }
+
+    #[test]
+    fn monitoring_on_the_input_device_is_a_milder_warning() {
+        let warnings = check_routing(&setup("Scarlett 2i2 USB", "Scarlett 2i2 USB", vec![route(OutputBus::Master, 0)]));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, RoutingWarningKind::MonitorOnInputDevice);
+        assert_eq!(warnings[0].fixes, vec![RoutingFix::MuteMonitor]);
+
+        // Separate devices: nothing to warn about
+        assert!(check_routing(&setup("USB Mic", "Speakers", vec![route(OutputBus::Master, 0)])).is_empty());
+    }
+}
@@ -0,0 +1,261 @@
+// src/rt_log.rs
+//
+// Realtime-safe logging.
+// Audio and decoder threads must never touch stdout/stderr directly: a slow or
+// missing console (Windows GUI build) can block the callback and cause dropouts.
+// Messages are formatted into a fixed-size stack record and pushed onto the
+// logging thread's own single-producer ring (lock-free, never blocks). A
+// background thread polls every ring into a sink (stderr by default,
+// tauri_plugin_log when the app installs one).
+
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, Once};
+use std::time::Duration;
+
+/// Max bytes kept per message. Longer messages are truncated on a char boundary.
+pub const MAX_MESSAGE_LEN: usize = 200;
+/// Records each thread's ring holds before its new messages are dropped.
+const QUEUE_CAPACITY: usize = 256;
+/// How often the drain thread polls the rings.
+const DRAIN_INTERVAL: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+/// A preformatted log line. Plain data, no heap allocation.
+pub struct LogRecord {
+    pub level: Level,
+    len: usize,
+    buf: [u8; MAX_MESSAGE_LEN],
+}
+
+impl LogRecord {
+    pub fn message(&self) -> &str {
+        // Only ever filled through `fmt::Write::write_str`, so the bytes are valid UTF-8.
+        std::str::from_utf8(&self.buf[..self.len]).unwrap_or("<invalid log record>")
+    }
+}
+
+impl fmt::Write for LogRecord {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let free = MAX_MESSAGE_LEN - self.len;
+        let mut take = s.len().min(free);
+        while take > 0 && !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.buf[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        Ok(())
+    }
+}
+
+type Sink = Box<dyn Fn(Level, &str) + Send + 'static>;
+
+static DRAIN: Once = Once::new();
+// Consumer ends of every thread's ring; only touched when a thread logs for the
+// first time and by the drain thread
+static RINGS: Mutex<Vec<HeapCons<LogRecord>>> = Mutex::new(Vec::new());
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static IN_AUDIO_THREAD: Cell<bool> = const { Cell::new(false) };
+    static PRODUCER: RefCell<Option<HeapProd<LogRecord>>> = const { RefCell::new(None) };
+}
+
+/// Starts the drain thread (idempotent). Called by `AudioRuntime::new`, but
+/// any thread may log before that; the first message starts it lazily.
+pub fn init() {
+    DRAIN.call_once(|| {
+        // Spawning happens once, normally from AudioRuntime::new on the UI thread.
+        assert_not_audio_thread("rt_log drain thread spawn");
+        std::thread::Builder::new()
+            .name("rt-log".into())
+            .spawn(drain)
+            .expect("failed to spawn rt-log thread");
+    });
+}
+
+/// Replaces the default stderr sink, e.g. with a forwarder into the `log` crate.
+pub fn set_sink<F>(sink: F)
+where
+    F: Fn(Level, &str) + Send + 'static,
+{
+    if let Ok(mut guard) = SINK.lock() {
+        *guard = Some(Box::new(sink));
+    }
+    init();
+}
+
+/// Messages lost because the queue was full.
+pub fn dropped_count() -> u64 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// Marks the current thread as the realtime audio callback and gives it its ring,
+/// so logging from the callback never allocates. Call at the top of the CPAL data
+/// callback; only the first call on a thread does any work.
+pub fn mark_audio_thread() {
+    if IN_AUDIO_THREAD.with(|flag| flag.replace(true)) {
+        return;
+    }
+    PRODUCER.with(|producer| {
+        if let Ok(mut producer) = producer.try_borrow_mut() {
+            producer.get_or_insert_with(register);
+        }
+    });
+}
+
+pub fn is_audio_thread() -> bool {
+    IN_AUDIO_THREAD.with(|flag| flag.get())
+}
+
+/// Debug-only guard for code that is about to block or write to std streams.
+/// Panics in debug builds (and therefore in tests) when reached from the audio callback.
+#[track_caller]
+pub fn assert_not_audio_thread(what: &str) {
+    debug_assert!(
+        !is_audio_thread(),
+        "realtime violation: {} called from the audio callback",
+        what
+    );
+}
+
+/// Formats and enqueues a message. Never blocks; drops the message if the ring is full.
+pub fn write(level: Level, args: fmt::Arguments) {
+    let mut record = LogRecord { level, len: 0, buf: [0; MAX_MESSAGE_LEN] };
+    let _ = record.write_fmt(args);
+
+    let pushed = PRODUCER
+        .try_with(|producer| match producer.try_borrow_mut() {
+            // A thread's first message sets up its ring (the audio thread's is made in
+            // `mark_audio_thread`)
+            Ok(mut producer) => producer.get_or_insert_with(register).try_push(record).is_ok(),
+            // Logging from inside `fmt` of a message being logged
+            Err(_) => false,
+        })
+        .unwrap_or(false);
+    if !pushed {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// A ring for the calling thread, handed to the drain thread
+fn register() -> HeapProd<LogRecord> {
+    init();
+    let (producer, consumer) = HeapRb::new(QUEUE_CAPACITY).split();
+    RINGS.lock().unwrap_or_else(|e| e.into_inner()).push(consumer);
+    producer
+}
+
+fn drain() {
+    let mut last_dropped = 0;
+    let mut batch = Vec::new();
+    loop {
+        if let Ok(mut rings) = RINGS.lock() {
+            for ring in rings.iter_mut() {
+                batch.extend(ring.pop_iter());
+            }
+            // Threads that ended leave an empty ring nobody writes to
+            rings.retain(|ring| ring.write_is_held() || !ring.is_empty());
+        }
+        let dropped = DROPPED.load(Ordering::Relaxed);
+        if dropped != last_dropped {
+            emit(Level::Warn, &format!("rt_log: {} message(s) dropped", dropped - last_dropped));
+            last_dropped = dropped;
+        }
+        for record in batch.drain(..) {
+            emit(record.level, record.message());
+        }
+        std::thread::sleep(DRAIN_INTERVAL);
+    }
+}
+
+fn emit(level: Level, msg: &str) {
+    // The sink may block on a console or a file: never from the callback
+    assert_not_audio_thread("rt_log sink write");
+    match SINK.lock() {
+        Ok(guard) => match guard.as_ref() {
+            Some(sink) => sink(level, msg),
+            None => eprintln!("[{:?}] {}", level, msg),
+        },
+        Err(_) => eprintln!("[{:?}] {}", level, msg),
+    }
+}
+
+#[macro_export]
+macro_rules! rt_error {
+    ($($arg:tt)*) => { $crate::rt_log::write($crate::rt_log::Level::Error, format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! rt_warn {
+    ($($arg:tt)*) => { $crate::rt_log::write($crate::rt_log::Level::Warn, format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! rt_info {
+    ($($arg:tt)*) => { $crate::rt_log::write($crate::rt_log::Level::Info, format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! rt_debug {
+    ($($arg:tt)*) => { $crate::rt_log::write($crate::rt_log::Level::Debug, format_args!($($arg)*)) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    #[test]
+    fn messages_from_every_thread_reach_the_sink() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink_seen = seen.clone();
+        set_sink(move |_, msg| {
+            if msg.starts_with("rt_log test ") {
+                sink_seen.lock().unwrap().push(msg.to_string());
+            }
+        });
+
+        let writers: Vec<_> = (0..4)
+            .map(|t| std::thread::spawn(move || (0..10).for_each(|i| rt_info!("rt_log test {} {}", t, i))))
+            .collect();
+        writers.into_iter().for_each(|w| w.join().unwrap());
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while seen.lock().unwrap().len() < 40 && Instant::now() < deadline {
+            std::thread::sleep(DRAIN_INTERVAL);
+        }
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 40);
+        // Per thread, in order
+        let thread_1: Vec<_> = seen.iter().filter(|m| m.starts_with("rt_log test 1 ")).cloned().collect();
+        assert_eq!(thread_1, (0..10).map(|i| format!("rt_log test 1 {}", i)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn long_messages_are_cut_on_a_char_boundary() {
+        let mut record = LogRecord { level: Level::Info, len: 0, buf: [0; MAX_MESSAGE_LEN] };
+        let _ = write!(record, "{}", "é".repeat(MAX_MESSAGE_LEN));
+        assert_eq!(record.message(), "é".repeat(MAX_MESSAGE_LEN / 2));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "realtime violation")]
+    fn the_audio_thread_never_writes_to_std_streams() {
+        mark_audio_thread();
+        emit(Level::Info, "from the callback");
+    }
+}
@@ -0,0 +1,281 @@
+// src/session/bounce.rs
+
+// Realtime bounce: writes the live master output (via the engine's master tap) to disk
+// while playback runs normally. Unlike the offline export it hears every live tweak.
+
+use anyhow::{anyhow, Result};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use ringbuf::traits::{Consumer, Observer};
+use ringbuf::HeapCons;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use crate::engine::loudness::{LoudnessMeter, LOUDNESS_FLOOR};
+
+/// Written into the file's LIST/INFO comment so a realtime pass is never mistaken for an offline one.
+pub const REALTIME_BOUNCE_TAG: &str = "Haven realtime bounce";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum BounceFormat {
+    #[default]
+    Wav16,
+    Wav24,
+    Wav32Float,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct BounceOptions {
+    #[serde(default)]
+    pub format: BounceFormat,
+    /// Finish by itself once the transport passes the last clip.
+    #[serde(default = "default_true")]
+    pub stop_at_project_end: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for BounceOptions {
+    fn default() -> Self {
+        Self { format: BounceFormat::Wav16, stop_at_project_end: true }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BounceResult {
+    pub path: String,
+    pub duration: f64,
+    pub sample_rate: u32,
+    pub peak_db: f32,
+    pub integrated_loudness_db: f32,
+    /// Samples lost because the writer fell behind the audio thread (should be 0).
+    pub dropped_samples: u64,
+    pub realtime: bool,
+}
+
+pub struct RealtimeBounce {
+    path: String,
+    stop: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<Result<BounceResult>>>,
+}
+
+impl RealtimeBounce {
+    /// Spawn the writer. `consumer` is the reading end of an installed `MasterTap`;
+    /// `max_frames` ends the bounce by itself (project end).
+    pub fn start(
+        path: String,
+        sample_rate: u32,
+        channels: usize,
+        options: BounceOptions,
+        max_frames: Option<u64>,
+        consumer: HeapCons<f32>,
+        dropped: Arc<AtomicU64>,
+    ) -> Result<Self> {
+        let writer = create_writer(&path, sample_rate, channels, options.format)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let finished = Arc::new(AtomicBool::new(false));
+        let (stop_w, finished_w, path_w) = (stop.clone(), finished.clone(), path.clone());
+        let handle = thread::Builder::new()
+            .name("realtime-bounce".into())
+            .spawn(move || {
+                // Loudness is measured as the file is written, so finishing never reads it back
+                let mut loudness = LoudnessMeter::new(sample_rate, channels);
+                let result = write_loop(writer, consumer, &stop_w, max_frames, channels, options.format, Some(&mut loudness))
+                    .and_then(|peak| finish(&path_w, sample_rate, peak, &loudness, dropped.load(Ordering::Relaxed)));
+                finished_w.store(true, Ordering::Release);
+                result
+            })?;
+
+        rt_info!("🔴 Realtime bounce started: {}", path);
+        Ok(Self { path, stop, finished, handle: Some(handle) })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// True once the writer stopped by itself (project end) or failed.
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    /// Drain what is buffered, finalize the file and report on it.
+    pub fn stop(mut self) -> Result<BounceResult> {
+        self.stop.store(true, Ordering::Release);
+        let handle = self.handle.take().ok_or_else(|| anyhow!("Bounce already stopped"))?;
+        handle.join().map_err(|_| anyhow!("Bounce writer panicked"))?
+    }
+}
+
+pub(crate) type BounceWriter = WavWriter<std::io::BufWriter<std::fs::File>>;
+
+pub(crate) fn create_writer(path: &str, sample_rate: u32, channels: usize, format: BounceFormat) -> Result<BounceWriter> {
+    let (bits_per_sample, sample_format) = match format {
+        BounceFormat::Wav16 => (16, SampleFormat::Int),
+        BounceFormat::Wav24 => (24, SampleFormat::Int),
+        BounceFormat::Wav32Float => (32, SampleFormat::Float),
+    };
+    let spec = WavSpec { channels: channels as u16, sample_rate, bits_per_sample, sample_format };
+    Ok(WavWriter::create(path, spec)?)
+}
+
+/// Drain `consumer` into `writer` until `stop` (or `max_frames`), then finalize.
+/// Everything written also goes through `loudness`, if given. Returns the sample peak (linear).
+pub(crate) fn write_loop(
+    mut writer: BounceWriter,
+    mut consumer: HeapCons<f32>,
+    stop: &AtomicBool,
+    max_frames: Option<u64>,
+    channels: usize,
+    format: BounceFormat,
+    mut loudness: Option<&mut LoudnessMeter>,
+) -> Result<f32> {
+    let mut tmp = vec![0.0f32; 4096 * channels.max(1)];
+    let max_samples = max_frames.map(|f| f * channels as u64);
+    let mut written = 0u64;
+    let mut peak = 0.0f32;
+
+    loop {
+        let stopping = stop.load(Ordering::Acquire);
+        let popped = consumer.pop_slice(&mut tmp);
+        if popped == 0 {
+            if stopping && consumer.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+            continue;
+        }
+
+        let take = match max_samples {
+            Some(max) => popped.min(max.saturating_sub(written) as usize),
+            None => popped,
+        };
+        for &s in &tmp[..take] {
+            let s = if s.is_finite() { s } else { 0.0 };
+            peak = peak.max(s.abs());
+            match format {
+                BounceFormat::Wav16 => writer.write_sample((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?,
+                BounceFormat::Wav24 => writer.write_sample((s.clamp(-1.0, 1.0) * 8_388_607.0) as i32)?,
+                BounceFormat::Wav32Float => writer.write_sample(s)?,
+            }
+        }
+        if let Some(meter) = loudness.as_deref_mut() {
+            meter.measure(&tmp[..take]);
+        }
+        written += take as u64;
+        if max_samples.is_some_and(|max| written >= max) {
+            rt_info!("🏁 Realtime bounce reached the project end");
+            break;
+        }
+    }
+
+    writer.finalize()?;
+    Ok(peak)
+}
+
+fn finish(path: &str, sample_rate: u32, peak: f32, loudness: &LoudnessMeter, dropped_samples: u64) -> Result<BounceResult> {
+    append_info_comment(path, REALTIME_BOUNCE_TAG)?;
+
+    let integrated = loudness.reading().integrated;
+    let integrated_loudness_db = if integrated > LOUDNESS_FLOOR { integrated } else { f32::NEG_INFINITY };
+    let duration = hound::WavReader::open(path).map(|r| r.duration() as f64 / sample_rate as f64).unwrap_or(0.0);
+    if dropped_samples > 0 {
+        rt_warn!("⚠️ Realtime bounce {} dropped {} samples", path, dropped_samples);
+    }
+
+    Ok(BounceResult {
+        path: path.to_string(),
+        duration,
+        sample_rate,
+        peak_db: if peak > 0.0 { 20.0 * peak.log10() } else { f32::NEG_INFINITY },
+        integrated_loudness_db,
+        dropped_samples,
+        realtime: true,
+    })
+}
+
+/// Append a LIST/INFO chunk with an ICMT comment and fix up the RIFF size.
+fn append_info_comment(path: &str, comment: &str) -> Result<()> {
+    let mut text = comment.as_bytes().to_vec();
+    text.push(0);
+    if text.len() % 2 == 1 {
+        text.push(0);
+    }
+    let mut chunk = Vec::with_capacity(text.len() + 20);
+    chunk.extend_from_slice(b"LIST");
+    chunk.extend_from_slice(&((4 + 8 + text.len()) as u32).to_le_bytes());
+    chunk.extend_from_slice(b"INFO");
+    chunk.extend_from_slice(b"ICMT");
+    chunk.extend_from_slice(&(text.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(&text);
+
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let mut riff = [0u8; 4];
+    file.seek(SeekFrom::Start(4))?;
+    file.read_exact(&mut riff)?;
+    let riff_size = u32::from_le_bytes(riff) as u64 + chunk.len() as u64;
+    if riff_size > u32::MAX as u64 {
+        return Ok(()); // no room left for metadata; the audio is what matters
+    }
+
+    file.seek(SeekFrom::End(0))?;
+    file.write_all(&chunk)?;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&(riff_size as u32).to_le_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ringbuf::traits::{Producer, Split};
+    use ringbuf::HeapRb;
+
+    const SR: u32 = 48_000;
+
+    fn bounce(name: &str, samples: &[f32]) -> BounceResult {
+        let path = crate::test_util::temp_path(&format!("bounce_{}", name)).with_extension("wav");
+        let (mut producer, consumer) = HeapRb::<f32>::new(samples.len() + 1).split();
+        assert_eq!(producer.push_slice(samples), samples.len());
+        let options = BounceOptions { format: BounceFormat::Wav32Float, stop_at_project_end: true };
+        let max_frames = Some((samples.len() / 2) as u64);
+        let bounce = RealtimeBounce::start(path.to_string_lossy().into_owned(), SR, 2, options, max_frames, consumer, Arc::new(AtomicU64::new(0))).unwrap();
+        let result = bounce.stop().unwrap();
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    #[test]
+    fn loudness_is_measured_while_writing() {
+        // 5 s of a 997 Hz tone at -20 dBFS in both channels: -20 LUFS by BS.1770
+        let amplitude = 0.1f32;
+        let samples: Vec<f32> = (0..SR as usize * 5)
+            .flat_map(|n| {
+                let s = amplitude * (2.0 * std::f32::consts::PI * 997.0 * n as f32 / SR as f32).sin();
+                [s, s]
+            })
+            .collect();
+
+        let result = bounce("tone", &samples);
+        assert!((result.integrated_loudness_db + 20.0).abs() < 0.1, "{}", result.integrated_loudness_db);
+        assert!((result.peak_db + 20.0).abs() < 0.1);
+        assert!((result.duration - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn silent_bounces_report_no_loudness() {
+        let result = bounce("silence", &vec![0.0f32; SR as usize * 2]);
+        assert_eq!(result.integrated_loudness_db, f32::NEG_INFINITY);
+        assert_eq!(result.peak_db, f32::NEG_INFINITY);
+    }
+}
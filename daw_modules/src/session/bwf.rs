@@ -0,0 +1,346 @@
+// src/session/bwf.rs
+
+// Minimal RIFF/RF64 WAV reader for ingest: the format, the size of the audio data and the
+// Broadcast Wave `bext` chunk, without touching the samples. Field recorders stamp every
+// file with an origination date plus a time reference (samples since midnight), which is
+// what lines up files recorded simultaneously on different devices.
+
+use anyhow::{anyhow, bail, Result};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Fixed part of the `bext` chunk up to and including the time reference.
+const BEXT_MIN_LEN: usize = 256 + 32 + 32 + 10 + 8 + 8;
+/// Largest chunk body read into memory (a `bext` with a long coding history is a few KiB).
+const MAX_CHUNK_BYTES: u64 = 1 << 20;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BextChunk {
+    pub description: String,
+    pub originator: String,
+    pub originator_reference: String,
+    /// "yyyy-mm-dd" (any separator).
+    pub origination_date: String,
+    /// "hh:mm:ss" (any separator).
+    pub origination_time: String,
+    /// Samples since midnight at the file's sample rate.
+    pub time_reference: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WavInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub block_align: u16,
+    pub data_bytes: Option<u64>,
+    pub bext: Option<BextChunk>,
+}
+
+impl WavInfo {
+    pub fn duration_secs(&self) -> Option<f64> {
+        let bytes = self.data_bytes?;
+        if self.block_align == 0 || self.sample_rate == 0 {
+            return None;
+        }
+        Some((bytes / self.block_align as u64) as f64 / self.sample_rate as f64)
+    }
+
+    /// Start of the recording from the `bext` stamp, in seconds since 1970-01-01 on the
+    /// recorder's (local, zone-less) clock. The time reference wins over the coarser
+    /// origination time when it is set.
+    pub fn recorded_at(&self) -> Option<f64> {
+        let bext = self.bext.as_ref()?;
+        let date = parse_fields(&bext.origination_date, [4, 2, 2])?;
+        let midnight = days_from_civil(date[0] as i64, date[1], date[2])? as f64 * 86_400.0;
+        if bext.time_reference > 0 && self.sample_rate > 0 {
+            return Some(midnight + bext.time_reference as f64 / self.sample_rate as f64);
+        }
+        let time = parse_fields(&bext.origination_time, [2, 2, 2])?;
+        if time[0] > 23 || time[1] > 59 || time[2] > 60 {
+            return None;
+        }
+        Some(midnight + (time[0] * 3600 + time[1] * 60 + time[2]) as f64)
+    }
+}
+
+pub fn read_wav_info(path: impl AsRef<Path>) -> Result<WavInfo> {
+    let file = std::fs::File::open(path)?;
+    parse_wav_info(std::io::BufReader::new(file))
+}
+
+/// Walk the chunk list. Unknown chunks are skipped; a truncated file keeps what was read.
+pub fn parse_wav_info<R: Read + Seek>(mut reader: R) -> Result<WavInfo> {
+    let mut header = [0u8; 12];
+    reader.read_exact(&mut header)?;
+    let rf64 = match &header[0..4] {
+        b"RIFF" => false,
+        b"RF64" | b"BW64" => true,
+        _ => bail!("Not a RIFF file"),
+    };
+    if &header[8..12] != b"WAVE" {
+        bail!("Not a WAVE file");
+    }
+
+    let mut info = WavInfo::default();
+    let mut has_fmt = false;
+    let mut ds64_data: Option<u64> = None;
+
+    loop {
+        let mut chunk = [0u8; 8];
+        if reader.read_exact(&mut chunk).is_err() {
+            break;
+        }
+        let id = [chunk[0], chunk[1], chunk[2], chunk[3]];
+        let size32 = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        let mut size = size32 as u64;
+
+        match &id {
+            b"fmt " => {
+                let body = read_body(&mut reader, size, 16)?;
+                info.channels = u16::from_le_bytes([body[2], body[3]]);
+                info.sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                info.block_align = u16::from_le_bytes([body[12], body[13]]);
+                has_fmt = true;
+            }
+            b"ds64" => {
+                // riffSize, dataSize, sampleCount (u64 each), then a table we don't need
+                let body = read_body(&mut reader, size, 24)?;
+                ds64_data = Some(u64::from_le_bytes(body[8..16].try_into()?));
+            }
+            // A malformed `bext` only costs the timestamp: skip it and keep the rest
+            b"bext" if size < BEXT_MIN_LEN as u64 || size > MAX_CHUNK_BYTES => {
+                if reader.seek(SeekFrom::Current(size as i64)).is_err() {
+                    break;
+                }
+            }
+            b"bext" => {
+                let mut body = vec![0u8; size as usize];
+                if reader.read_exact(&mut body).is_err() {
+                    break;
+                }
+                info.bext = Some(parse_bext(&body));
+            }
+            b"data" => {
+                if rf64 && size32 == u32::MAX {
+                    size = ds64_data.ok_or_else(|| anyhow!("RF64 data chunk without ds64"))?;
+                }
+                info.data_bytes = Some(size);
+                // Some recorders write `bext` after the audio, so keep walking
+                if reader.seek(SeekFrom::Current(size as i64)).is_err() {
+                    break;
+                }
+            }
+            _ => {
+                if reader.seek(SeekFrom::Current(size as i64)).is_err() {
+                    break;
+                }
+            }
+        }
+        // Chunks are padded to an even length
+        if size & 1 == 1 && reader.seek(SeekFrom::Current(1)).is_err() {
+            break;
+        }
+    }
+
+    if !has_fmt {
+        bail!("WAVE file without a fmt chunk");
+    }
+    Ok(info)
+}
+
+// Read a chunk body of `size` bytes that must hold at least `min` bytes
+fn read_body<R: Read>(reader: &mut R, size: u64, min: usize) -> Result<Vec<u8>> {
+    if size < min as u64 || size > MAX_CHUNK_BYTES {
+        bail!("Chunk of {} bytes is malformed", size);
+    }
+    let mut body = vec![0u8; size as usize];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+fn parse_bext(body: &[u8]) -> BextChunk {
+    let text = |range: std::ops::Range<usize>| {
+        String::from_utf8_lossy(&body[range]).trim_end_matches('\0').trim().to_string()
+    };
+    let low = u32::from_le_bytes([body[338], body[339], body[340], body[341]]) as u64;
+    let high = u32::from_le_bytes([body[342], body[343], body[344], body[345]]) as u64;
+    BextChunk {
+        description: text(0..256),
+        originator: text(256..288),
+        originator_reference: text(288..320),
+        origination_date: text(320..330),
+        origination_time: text(330..338),
+        time_reference: (high << 32) | low,
+    }
+}
+
+// Three numeric fields of the given widths, each followed by one separator character
+fn parse_fields(text: &str, widths: [usize; 3]) -> Option<[u32; 3]> {
+    let bytes = text.as_bytes();
+    let mut fields = [0u32; 3];
+    let mut at = 0;
+    for (field, width) in fields.iter_mut().zip(widths) {
+        let digits = bytes.get(at..at + width)?;
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        *field = std::str::from_utf8(digits).ok()?.parse().ok()?;
+        at += width + 1;
+    }
+    Some(fields)
+}
+
+// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's algorithm)
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some(era * 146_097 + doe - 719_468)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // 48 kHz stereo 24-bit
+    fn fmt() -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&2u16.to_le_bytes());
+        body.extend_from_slice(&48_000u32.to_le_bytes());
+        body.extend_from_slice(&(48_000u32 * 6).to_le_bytes());
+        body.extend_from_slice(&6u16.to_le_bytes());
+        body.extend_from_slice(&24u16.to_le_bytes());
+        body
+    }
+
+    fn field(text: &str, len: usize) -> Vec<u8> {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.resize(len, 0);
+        bytes
+    }
+
+    // A `bext` body as a Zoom recorder writes it, plus some coding history
+    fn bext(date: &str, time: &str, time_reference: u64) -> Vec<u8> {
+        let mut body = field("zTRK=MIC L", 256);
+        body.extend(field("ZOOM F8n", 32));
+        body.extend(field("REF0001", 32));
+        body.extend(field(date, 10));
+        body.extend(field(time, 8));
+        body.extend_from_slice(&(time_reference as u32).to_le_bytes());
+        body.extend_from_slice(&((time_reference >> 32) as u32).to_le_bytes());
+        body.extend(vec![0u8; 254 + 2 + 10 + 180]); // version, UMID, loudness, reserved
+        body.extend_from_slice(b"A=PCM,F=48000,W=24,M=stereo\r\n");
+        body
+    }
+
+    fn wav(form: &[u8; 4], chunks: &[(&[u8; 4], Vec<u8>)]) -> Cursor<Vec<u8>> {
+        let mut file = form.to_vec();
+        file.extend_from_slice(&0u32.to_le_bytes()); // nobody reads the RIFF size
+        file.extend_from_slice(b"WAVE");
+        for (id, body) in chunks {
+            file.extend_from_slice(*id);
+            file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            file.extend_from_slice(body);
+            if body.len() % 2 == 1 {
+                file.push(0);
+            }
+        }
+        Cursor::new(file)
+    }
+
+    #[test]
+    fn reads_the_bext_fields_of_a_field_recording() {
+        let time_reference = (5u64 << 32) | 7; // both halves of the 64-bit count
+        let file = wav(b"RIFF", &[(b"fmt ", fmt()), (b"bext", bext("2024-03-15", "10:30:00", time_reference)), (b"data", vec![0; 600])]);
+        let info = parse_wav_info(file).unwrap();
+
+        assert_eq!((info.sample_rate, info.channels, info.block_align), (48_000, 2, 6));
+        assert_eq!(info.data_bytes, Some(600));
+        assert_eq!(info.duration_secs(), Some(100.0 / 48_000.0));
+        let bext = info.bext.unwrap();
+        assert_eq!(bext.description, "zTRK=MIC L");
+        assert_eq!(bext.originator, "ZOOM F8n");
+        assert_eq!(bext.originator_reference, "REF0001");
+        assert_eq!(bext.origination_date, "2024-03-15");
+        assert_eq!(bext.origination_time, "10:30:00");
+        assert_eq!(bext.time_reference, time_reference);
+    }
+
+    #[test]
+    fn recorded_at_prefers_the_time_reference() {
+        // 2024-03-15 is day 19797 of the epoch
+        let midnight = 19_797.0 * 86_400.0;
+        let at = |date: &str, time: &str, time_reference: u64| {
+            let file = wav(b"RIFF", &[(b"fmt ", fmt()), (b"bext", bext(date, time, time_reference)), (b"data", vec![])]);
+            parse_wav_info(file).unwrap().recorded_at()
+        };
+        // An hour and half a second of samples after midnight, whatever the coarse time says
+        assert_eq!(at("2024-03-15", "10:30:00", 48_000 * 3600 + 24_000), Some(midnight + 3600.5));
+        // No time reference: the origination time, with any separators
+        assert_eq!(at("2024/03/15", "10.30.05", 0), Some(midnight + 10.0 * 3600.0 + 30.0 * 60.0 + 5.0));
+        // Nonsense stamps give no time at all
+        assert_eq!(at("2024-13-15", "10:30:00", 0), None);
+        assert_eq!(at("2024-03-15", "25:00:00", 0), None);
+        assert_eq!(at("", "", 48_000), None);
+    }
+
+    #[test]
+    fn a_bext_after_the_audio_is_still_found() {
+        let file = wav(b"RIFF", &[(b"fmt ", fmt()), (b"data", vec![0; 7]), (b"bext", bext("2024-03-15", "10:30:00", 1))]);
+        let info = parse_wav_info(file).unwrap();
+        assert_eq!(info.data_bytes, Some(7));
+        assert_eq!(info.bext.map(|b| b.time_reference), Some(1));
+    }
+
+    #[test]
+    fn a_malformed_bext_is_skipped_not_fatal() {
+        // Too short to hold the time reference
+        let file = wav(b"RIFF", &[(b"fmt ", fmt()), (b"bext", vec![b'x'; 20]), (b"data", vec![0; 12])]);
+        let info = parse_wav_info(file).unwrap();
+        assert_eq!(info.bext, None);
+        assert_eq!(info.data_bytes, Some(12), "the chunks after it are still read");
+
+        // Claiming more than the reader will hold, in a file that ends first
+        let mut file = wav(b"RIFF", &[(b"fmt ", fmt()), (b"data", vec![0; 12])]).into_inner();
+        file.extend_from_slice(b"bext");
+        file.extend_from_slice(&(64u32 << 20).to_le_bytes());
+        file.extend_from_slice(&[0; 100]);
+        let info = parse_wav_info(Cursor::new(file)).unwrap();
+        assert_eq!(info.bext, None);
+        assert_eq!(info.data_bytes, Some(12));
+
+        // Truncated mid-chunk: what came before is kept
+        let mut file = wav(b"RIFF", &[(b"fmt ", fmt())]).into_inner();
+        file.extend_from_slice(b"bext");
+        file.extend_from_slice(&(BEXT_MIN_LEN as u32).to_le_bytes());
+        file.extend_from_slice(&[0; 40]);
+        let info = parse_wav_info(Cursor::new(file)).unwrap();
+        assert_eq!((info.sample_rate, info.bext), (48_000, None));
+    }
+
+    #[test]
+    fn rf64_takes_the_data_size_from_ds64() {
+        let mut ds64 = vec![0u8; 28];
+        ds64[8..16].copy_from_slice(&(5u64 << 32).to_le_bytes());
+        let mut file = wav(b"RF64", &[(b"ds64", ds64), (b"fmt ", fmt())]).into_inner();
+        file.extend_from_slice(b"data");
+        file.extend_from_slice(&u32::MAX.to_le_bytes());
+        let info = parse_wav_info(Cursor::new(file)).unwrap();
+        assert_eq!(info.data_bytes, Some(5u64 << 32));
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_wave_file() {
+        assert!(parse_wav_info(Cursor::new(b"OggS\0\0\0\0WAVE".to_vec())).is_err());
+        assert!(parse_wav_info(Cursor::new(b"RIFF\0\0\0\0AVI ".to_vec())).is_err());
+        assert!(parse_wav_info(wav(b"RIFF", &[(b"data", vec![0; 4])])).is_err(), "no fmt chunk");
+    }
+}
@@ -1,11 +1,16 @@
 // src/session/commands.rs
 
 use crate::engine::{Engine, TrackId};
+use crate::engine::handle::EngineHandle;
+use crate::engine::markers::Marker;
+use crate::engine::time::Frames;
+use crate::engine::track::{Clip, ClipBounds};
+use crate::engine::mute_regions::{self, MuteRegion};
 use anyhow::Result;
 use crate::effects::equalizer::EqParams;
 use crate::effects::compressor::CompressorParams;
 use crate::effects::reverb::ReverbParams;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// The Command trait defines an action that can be executed and undone.
 /// We require Send + Sync so commands can be moved between threads if necessary.
@@ -18,6 +23,45 @@ pub trait Command: Send + Sync {
     
     /// A description for the UI (e.g., "Set Volume")
     fn name(&self) -> &str;
+
+    /// Extra context for the edit history (e.g. "0.80 -> 1.00").
+    fn details(&self) -> String { String::new() }
+
+    /// Tracks touched by this command, for the edit history.
+    fn track_ids(&self) -> Vec<TrackId> { Vec::new() }
+
+    /// Commands with the same key pushed through `push_coalesced` in quick succession
+    /// share one undo step (e.g. typing into a notes field). Only for absolute "set" commands.
+    fn coalesce_key(&self) -> Option<String> { None }
+
+    /// Swap in the newest command of a coalesced group. Only `Coalesced` accepts.
+    fn replace_last(&mut self, last: Box<dyn Command>) -> std::result::Result<(), Box<dyn Command>> { Err(last) }
+}
+
+/// One undo step made of several same-key commands: undo reverts to before the first,
+/// redo re-applies the last.
+struct Coalesced {
+    first: Box<dyn Command>,
+    last: Option<Box<dyn Command>>,
+}
+
+impl Coalesced {
+    fn latest(&self) -> &dyn Command {
+        self.last.as_deref().unwrap_or(self.first.as_ref())
+    }
+}
+
+impl Command for Coalesced {
+    fn execute(&self, engine: &mut Engine) -> Result<()> { self.latest().execute(engine) }
+    fn undo(&self, engine: &mut Engine) -> Result<()> { self.first.undo(engine) }
+    fn name(&self) -> &str { self.first.name() }
+    fn details(&self) -> String { self.latest().details() }
+    fn track_ids(&self) -> Vec<TrackId> { self.first.track_ids() }
+    fn coalesce_key(&self) -> Option<String> { self.first.coalesce_key() }
+    fn replace_last(&mut self, last: Box<dyn Command>) -> std::result::Result<(), Box<dyn Command>> {
+        self.last = Some(last);
+        Ok(())
+    }
 }
 
 /// Manages the history of commands.
@@ -26,6 +70,8 @@ pub struct CommandManager {
     redo_stack: Vec<Box<dyn Command>>,
     // We can set a max limit later to save memory, e.g., 50 steps.
     max_history: usize,
+    // Key and time of the last coalesced push, while the top of the undo stack is that group
+    open_group: Option<(String, Instant)>,
 }
 
 impl CommandManager {
@@ -34,13 +80,26 @@ impl CommandManager {
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
             max_history,
+            open_group: None,
         }
     }
 
+    // Run one side of `command` on the engine; the box comes back so it is kept (and freed)
+    // on this thread
+    fn run(engine: &EngineHandle, command: Box<dyn Command>, op: fn(&dyn Command, &mut Engine) -> Result<()>) -> Result<Box<dyn Command>> {
+        let (command, result) = engine.with(move |eng| {
+            let result = op(command.as_ref(), eng);
+            (command, result)
+        })?;
+        result?;
+        Ok(command)
+    }
+
     /// Execute a new command and push it onto the undo stack.
     /// Clears the redo stack because a new history branch is created.
-    pub fn push(&mut self, command: Box<dyn Command>, engine: &mut Engine) -> Result<()> {
-        command.execute(engine)?;
+    pub fn push(&mut self, command: Box<dyn Command>, engine: &EngineHandle) -> Result<()> {
+        let command = Self::run(engine, command, |c, eng| c.execute(eng))?;
+        self.open_group = None;
         self.undo_stack.push(command);
         self.redo_stack.clear();
         
@@ -51,9 +110,41 @@ impl CommandManager {
         Ok(())
     }
 
-    pub fn undo(&mut self, engine: &mut Engine) -> Result<bool> {
+    /// Like `push`, but a command with the same `coalesce_key` as the previous coalesced push,
+    /// arriving within `window` of it and with nothing else in between, joins that undo step.
+    /// Returns true when it joined an existing step.
+    pub fn push_coalesced(&mut self, command: Box<dyn Command>, engine: &EngineHandle, window: Duration) -> Result<bool> {
+        let Some(key) = command.coalesce_key() else {
+            self.push(command, engine)?;
+            return Ok(false);
+        };
+        let command = Self::run(engine, command, |c, eng| c.execute(eng))?;
+
+        let continues = matches!(&self.open_group, Some((k, at)) if *k == key && at.elapsed() < window);
+        let command = match self.undo_stack.last_mut() {
+            Some(top) if continues => match top.replace_last(command) {
+                Ok(()) => {
+                    self.open_group = Some((key, Instant::now()));
+                    return Ok(true);
+                }
+                Err(command) => command,
+            },
+            _ => command,
+        };
+
+        self.undo_stack.push(Box::new(Coalesced { first: command, last: None }));
+        self.redo_stack.clear();
+        if self.undo_stack.len() > self.max_history {
+            self.undo_stack.remove(0);
+        }
+        self.open_group = Some((key, Instant::now()));
+        Ok(false)
+    }
+
+    pub fn undo(&mut self, engine: &EngineHandle) -> Result<bool> {
+        self.open_group = None;
         if let Some(cmd) = self.undo_stack.pop() {
-            cmd.undo(engine)?;
+            let cmd = Self::run(engine, cmd, |c, eng| c.undo(eng))?;
             self.redo_stack.push(cmd);
             Ok(true)
         } else {
@@ -61,9 +152,10 @@ impl CommandManager {
         }
     }
 
-    pub fn redo(&mut self, engine: &mut Engine) -> Result<bool> {
+    pub fn redo(&mut self, engine: &EngineHandle) -> Result<bool> {
+        self.open_group = None;
         if let Some(cmd) = self.redo_stack.pop() {
-            cmd.execute(engine)?;
+            let cmd = Self::run(engine, cmd, |c, eng| c.execute(eng))?;
             self.undo_stack.push(cmd);
             Ok(true)
         } else {
@@ -71,6 +163,11 @@ impl CommandManager {
         }
     }
     
+    /// The command `undo` would revert next.
+    pub fn peek_undo(&self) -> Option<&dyn Command> { self.undo_stack.last().map(|c| c.as_ref()) }
+    /// The command `redo` would re-apply next.
+    pub fn peek_redo(&self) -> Option<&dyn Command> { self.redo_stack.last().map(|c| c.as_ref()) }
+
     pub fn can_undo(&self) -> bool { !self.undo_stack.is_empty() }
     pub fn can_redo(&self) -> bool { !self.redo_stack.is_empty() }
 }
@@ -101,6 +198,8 @@ impl Command for SetTrackGain {
     }
 
     fn name(&self) -> &str { "Change Gain" }
+    fn details(&self) -> String { format!("{:.2} -> {:.2}", self.old_gain, self.new_gain) }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 pub struct SetTrackPan {
@@ -125,6 +224,8 @@ impl Command for SetTrackPan {
     }
     
     fn name(&self) -> &str { "Change Pan" }
+    fn details(&self) -> String { format!("{:.2} -> {:.2}", self.old_pan, self.new_pan) }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 pub struct SetTrackMute {
@@ -149,6 +250,7 @@ impl Command for SetTrackMute {
     }
     
     fn name(&self) -> &str { "Toggle Mute" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 pub struct ToggleSolo {
@@ -166,6 +268,348 @@ impl Command for ToggleSolo {
         self.execute(engine) // Toggle is its own undo
     }
     fn name(&self) -> &str { "Toggle Solo" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
+}
+
+/// Fader-strip state of one track: what a mixer reset changes and its undo restores.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MixState {
+    pub track_id: TrackId,
+    pub gain: f32,
+    pub pan: f32,
+    pub muted: bool,
+    pub solo: bool,
+    pub fx_bypass: bool,
+}
+
+impl MixState {
+    pub fn capture(track: &crate::engine::track::Track) -> Self {
+        Self {
+            track_id: track.id,
+            gain: track.gain,
+            pan: track.pan,
+            muted: track.muted,
+            solo: track.solo,
+            fx_bypass: track.fx_bypass,
+        }
+    }
+
+    fn restore(&self, track: &mut crate::engine::track::Track) {
+        track.gain = self.gain;
+        track.pan = self.pan;
+        track.muted = self.muted;
+        track.solo = self.solo;
+        track.fx_bypass = self.fx_bypass;
+    }
+}
+
+/// Which parts of every strip a mixer reset touches.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MixerResetOptions {
+    pub gains: bool,       // faders to 0 dB
+    pub pans: bool,        // pans to center
+    pub mutes_solos: bool, // clear every mute and solo
+    pub effects: bool,     // bypass EQ / compressor / reverb
+}
+
+impl Default for MixerResetOptions {
+    fn default() -> Self {
+        Self { gains: true, pans: true, mutes_solos: true, effects: false }
+    }
+}
+
+/// Resets every track at once; a single undo puts the whole previous mix back.
+pub struct ResetMixer {
+    pub before: Vec<MixState>,
+    pub options: MixerResetOptions,
+}
+
+impl Command for ResetMixer {
+    fn execute(&self, engine: &mut Engine) -> Result<()> {
+        let o = self.options;
+        for track in engine.tracks_mut().iter_mut().filter(|t| self.before.iter().any(|m| m.track_id == t.id)) {
+            if o.gains { track.gain = 1.0; }
+            if o.pans { track.pan = 0.0; }
+            if o.mutes_solos {
+                track.muted = false;
+                track.solo = false;
+            }
+            if o.effects { track.fx_bypass = true; }
+        }
+        Ok(())
+    }
+    fn undo(&self, engine: &mut Engine) -> Result<()> {
+        for state in &self.before {
+            if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == state.track_id) {
+                state.restore(track);
+            }
+        }
+        Ok(())
+    }
+    fn name(&self) -> &str { "Reset Mixer" }
+    fn details(&self) -> String {
+        let o = self.options;
+        let parts: Vec<&str> = [(o.gains, "gains"), (o.pans, "pans"), (o.mutes_solos, "mutes/solos"), (o.effects, "effects")]
+            .into_iter()
+            .filter_map(|(on, label)| on.then_some(label))
+            .collect();
+        format!("{} track(s): {}", self.before.len(), parts.join(", "))
+    }
+    fn track_ids(&self) -> Vec<TrackId> { self.before.iter().map(|m| m.track_id).collect() }
+}
+
+pub struct SetFxBypass {
+    pub track_id: TrackId,
+    pub bypass: bool,
+}
+
+impl Command for SetFxBypass {
+    fn execute(&self, engine: &mut Engine) -> Result<()> {
+        if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == self.track_id) {
+            track.fx_bypass = self.bypass;
+        }
+        Ok(())
+    }
+    fn undo(&self, engine: &mut Engine) -> Result<()> {
+        if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == self.track_id) {
+            track.fx_bypass = !self.bypass;
+        }
+        Ok(())
+    }
+    fn name(&self) -> &str { if self.bypass { "Bypass Effects" } else { "Enable Effects" } }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
+}
+
+/// What a note is attached to. Clips are addressed by index on their track.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteTarget {
+    Track(TrackId),
+    Clip(TrackId, usize),
+}
+
+pub struct SetNotes {
+    pub target: NoteTarget,
+    pub old_notes: String,
+    pub new_notes: String,
+}
+
+impl SetNotes {
+    fn write(&self, engine: &mut Engine, notes: &str) {
+        let (track_id, clip_index) = match self.target {
+            NoteTarget::Track(id) => (id, None),
+            NoteTarget::Clip(id, index) => (id, Some(index)),
+        };
+        let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == track_id) else { return };
+        match clip_index {
+            None => track.notes = notes.to_string(),
+            Some(index) => {
+                if let Some(clip) = track.clips.get_mut(index) {
+                    clip.notes = notes.to_string();
+                }
+            }
+        }
+    }
+}
+
+impl Command for SetNotes {
+    fn execute(&self, engine: &mut Engine) -> Result<()> {
+        self.write(engine, &self.new_notes);
+        Ok(())
+    }
+    fn undo(&self, engine: &mut Engine) -> Result<()> {
+        self.write(engine, &self.old_notes);
+        Ok(())
+    }
+    fn name(&self) -> &str {
+        match self.target {
+            NoteTarget::Track(_) => "Edit Track Notes",
+            NoteTarget::Clip(..) => "Edit Clip Notes",
+        }
+    }
+    fn details(&self) -> String { format!("{} chars", self.new_notes.chars().count()) }
+    fn track_ids(&self) -> Vec<TrackId> {
+        match self.target {
+            NoteTarget::Track(id) | NoteTarget::Clip(id, _) => vec![id],
+        }
+    }
+    fn coalesce_key(&self) -> Option<String> { Some(format!("notes:{:?}", self.target)) }
+}
+
+/// Replaces a clip's mute regions wholesale (add and remove both go through this).
+pub struct SetMuteRegions {
+    pub track_id: TrackId,
+    pub clip_index: usize,
+    pub old_regions: Vec<MuteRegion>,
+    pub new_regions: Vec<MuteRegion>,
+    pub label: &'static str,
+}
+
+impl SetMuteRegions {
+    fn write(&self, engine: &mut Engine, regions: &[MuteRegion]) {
+        if let Some(clip) = engine.tracks_mut().iter_mut()
+            .find(|t| t.id == self.track_id)
+            .and_then(|t| t.clips.get_mut(self.clip_index))
+        {
+            clip.mute_regions = regions.to_vec();
+        }
+    }
+}
+
+impl Command for SetMuteRegions {
+    fn execute(&self, engine: &mut Engine) -> Result<()> {
+        self.write(engine, &self.new_regions);
+        Ok(())
+    }
+    fn undo(&self, engine: &mut Engine) -> Result<()> {
+        self.write(engine, &self.old_regions);
+        Ok(())
+    }
+    fn name(&self) -> &str { self.label }
+    fn details(&self) -> String { format!("clip {}: {} region(s)", self.clip_index, self.new_regions.len()) }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
+}
+
+/// Crossfade length where a clip enters the clip it overlaps (`None` = the whole overlap).
+pub struct SetClipCrossfade {
+    pub track_id: TrackId,
+    pub clip_index: usize,
+    pub old: Option<Duration>,
+    pub new: Option<Duration>,
+}
+
+impl SetClipCrossfade {
+    fn write(&self, engine: &mut Engine, length: Option<Duration>) -> Result<()> {
+        let index = engine.tracks().iter().position(|t| t.id == self.track_id)
+            .ok_or_else(|| anyhow::anyhow!("Track not found"))?;
+        engine.set_clip_crossfade(index, self.clip_index, length)
+    }
+}
+
+impl Command for SetClipCrossfade {
+    fn execute(&self, engine: &mut Engine) -> Result<()> {
+        self.write(engine, self.new)
+    }
+    fn undo(&self, engine: &mut Engine) -> Result<()> {
+        self.write(engine, self.old)
+    }
+    fn name(&self) -> &str { "Set Crossfade" }
+    fn details(&self) -> String {
+        match self.new {
+            Some(length) => format!("clip {}: {:.3}s", self.clip_index, length.as_secs_f64()),
+            None => format!("clip {}: whole overlap", self.clip_index),
+        }
+    }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
+    fn coalesce_key(&self) -> Option<String> { Some(format!("crossfade:{}:{}", self.track_id.0, self.clip_index)) }
+}
+
+pub struct AddMarker {
+    pub marker: Marker,
+}
+
+impl Command for AddMarker {
+    fn execute(&self, engine: &mut Engine) -> Result<()> {
+        engine.markers.insert(self.marker.clone());
+        Ok(())
+    }
+    fn undo(&self, engine: &mut Engine) -> Result<()> {
+        engine.markers.remove(self.marker.id);
+        Ok(())
+    }
+    fn name(&self) -> &str { "Add Marker" }
+    fn details(&self) -> String { format!("'{}' at {:.2}s", self.marker.name, self.marker.time.as_secs_f64()) }
+}
+
+pub struct DeleteMarker {
+    pub marker: Marker,
+}
+
+impl Command for DeleteMarker {
+    fn execute(&self, engine: &mut Engine) -> Result<()> {
+        engine.markers.remove(self.marker.id);
+        Ok(())
+    }
+    fn undo(&self, engine: &mut Engine) -> Result<()> {
+        engine.markers.insert(self.marker.clone());
+        Ok(())
+    }
+    fn name(&self) -> &str { "Delete Marker" }
+    fn details(&self) -> String { format!("'{}'", self.marker.name) }
+}
+
+/// Move, rename or recolor a marker (the id stays the same).
+pub struct EditMarker {
+    pub old: Marker,
+    pub new: Marker,
+    pub label: &'static str,
+}
+
+impl Command for EditMarker {
+    fn execute(&self, engine: &mut Engine) -> Result<()> {
+        engine.markers.insert(self.new.clone());
+        Ok(())
+    }
+    fn undo(&self, engine: &mut Engine) -> Result<()> {
+        engine.markers.insert(self.old.clone());
+        Ok(())
+    }
+    fn name(&self) -> &str { self.label }
+    fn details(&self) -> String {
+        if self.old.time != self.new.time {
+            format!("'{}' {:.2}s -> {:.2}s", self.new.name, self.old.time.as_secs_f64(), self.new.time.as_secs_f64())
+        } else {
+            format!("'{}'", self.new.name)
+        }
+    }
+}
+
+/// Remove a track. Undo rebuilds it from its saved state at the same index with the same id.
+pub struct DeleteTrack {
+    pub track_id: TrackId,
+    pub index: usize,
+    pub state: super::serialization::TrackState,
+}
+
+impl Command for DeleteTrack {
+    fn execute(&self, engine: &mut Engine) -> Result<()> {
+        engine.remove_track(self.track_id)?;
+        Ok(())
+    }
+
+    fn undo(&self, engine: &mut Engine) -> Result<()> {
+        engine.insert_empty_track(self.index, self.track_id)?;
+        let (sr, ch) = (engine.sample_rate, engine.channels);
+        let playing = engine.transport.playing;
+        let pos = engine.transport.time();
+        if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == self.track_id) {
+            super::restore_track(track, self.state.clone(), sr, ch);
+            track.seek(pos);
+            if playing {
+                track.set_state(crate::engine::track::TrackState::Playing);
+            }
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str { "Delete Track" }
+    fn details(&self) -> String { format!("'{}'", self.state.name) }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
+}
+
+pub struct ReorderTracks {
+    pub old_order: Vec<TrackId>,
+    pub new_order: Vec<TrackId>,
+}
+
+impl Command for ReorderTracks {
+    fn execute(&self, engine: &mut Engine) -> Result<()> {
+        engine.reorder_tracks(&self.new_order)
+    }
+    fn undo(&self, engine: &mut Engine) -> Result<()> {
+        engine.reorder_tracks(&self.old_order)
+    }
+    fn name(&self) -> &str { "Reorder Tracks" }
 }
 
 pub struct MoveClip {
@@ -177,29 +621,144 @@ pub struct MoveClip {
 
 impl Command for MoveClip {
     fn execute(&self, engine: &mut Engine) -> Result<()> {
+        let position = engine.transport.time();
         if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == self.track_id) {
-            track.move_clip(self.clip_index, self.new_start);
+            track.move_clip(self.clip_index, self.new_start, position);
         }
         Ok(())
     }
     fn undo(&self, engine: &mut Engine) -> Result<()> {
+        let position = engine.transport.time();
         if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == self.track_id) {
-            track.move_clip(self.clip_index, self.old_start);
+            track.move_clip(self.clip_index, self.old_start, position);
         }
         Ok(())
     }
     fn name(&self) -> &str { "Move Clip" }
+    fn details(&self) -> String {
+        format!("clip {}: {:.3}s -> {:.3}s", self.clip_index, self.old_start.as_secs_f64(), self.new_start.as_secs_f64())
+    }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
+}
+
+/// Trim either edge of a clip. The clip is found by its bounds, since a trim can re-sort the track.
+pub struct TrimClip {
+    pub track_id: TrackId,
+    pub path: String,
+    pub old: ClipBounds,
+    pub new: ClipBounds,
+    pub rate: u32, // the clip's frame rate, which the bounds count at
+    pub label: &'static str,
+}
+
+impl TrimClip {
+    fn apply(engine: &mut Engine, track_id: TrackId, path: &str, from: &ClipBounds, to: &ClipBounds) -> Result<()> {
+        let position = engine.transport.time();
+        if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == track_id) {
+            let index = track
+                .clips
+                .iter()
+                .position(|c| c.path == path && c.start_time == from.start_time && c.offset == from.offset)
+                .ok_or_else(|| anyhow::anyhow!("Trimmed clip not found"))?;
+            track.set_clip_bounds(index, to.clone(), position)?;
+        }
+        Ok(())
+    }
+}
+
+impl Command for TrimClip {
+    fn execute(&self, engine: &mut Engine) -> Result<()> {
+        Self::apply(engine, self.track_id, &self.path, &self.old, &self.new)
+    }
+    fn undo(&self, engine: &mut Engine) -> Result<()> {
+        Self::apply(engine, self.track_id, &self.path, &self.new, &self.old)
+    }
+    fn name(&self) -> &str { self.label }
+    fn details(&self) -> String {
+        format!(
+            "{:.3}s-{:.3}s -> {:.3}s-{:.3}s",
+            self.old.start_time.to_seconds(self.rate).0,
+            (self.old.start_time + self.old.duration).to_seconds(self.rate).0,
+            self.new.start_time.to_seconds(self.rate).0,
+            (self.new.start_time + self.new.duration).to_seconds(self.rate).0
+        )
+    }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 // Data needed to restore a clip
 pub struct DeletedClipData {
     pub path: String,
-    pub start_time: Duration,
-    pub offset: Duration,
-    pub duration: Duration,
-    pub source_duration: Duration,
+    pub start_time: Frames, // at `rate`
+    pub offset: Frames,     // at `source_sr`
+    pub duration: Frames,   // at `rate`
+    pub source_duration: Frames,
+    pub rate: u32, // the clip's frame rate; the restored clip keeps it
     pub source_sr: u32,
     pub source_ch: usize,
+    pub notes: String,
+    pub mute_regions: Vec<MuteRegion>,
+    pub stretch: f64,
+    pub varispeed: bool,
+    pub crossfade: Option<Duration>,
+}
+
+impl DeletedClipData {
+    /// Everything needed to bring `clip` back.
+    pub fn of(clip: &Clip) -> Self {
+        Self {
+            path: clip.path.clone(),
+            start_time: clip.start_time,
+            offset: clip.offset,
+            duration: clip.duration,
+            source_duration: clip.source_duration,
+            rate: clip.frame_rate(),
+            source_sr: clip.source_sr,
+            source_ch: clip.source_ch,
+            notes: clip.notes.clone(),
+            mute_regions: clip.mute_regions.clone(),
+            stretch: clip.stretch(),
+            varispeed: clip.varispeed(),
+            crossfade: clip.crossfade,
+        }
+    }
+
+    fn restore(&self, track: &mut crate::engine::track::Track, index: usize, channels: usize) -> Result<()> {
+        track.restore_deleted_clip(
+            index,
+            self.path.clone(),
+            self.start_time,
+            self.offset,
+            self.duration,
+            self.source_duration,
+            self.source_sr,
+            self.source_ch,
+            self.rate,
+            channels,
+        )
+    }
+
+    fn is_clip(&self, clip: &Clip) -> bool {
+        clip.path == self.path && clip.frame_rate() == self.rate && clip.start_time == self.start_time
+    }
+
+    /// Put the saved notes, mute regions, time stretch and crossfade back on the restored clip
+    /// (restoring re-sorts, so find it by position).
+    fn restore_annotations(&self, track: &mut crate::engine::track::Track) {
+        if self.notes.is_empty() && self.mute_regions.is_empty() && self.stretch == 1.0 && !self.varispeed && self.crossfade.is_none() {
+            return;
+        }
+        if let Some(clip) = track.clips.iter_mut().find(|c| self.is_clip(c)) {
+            clip.notes = self.notes.clone();
+            clip.mute_regions = self.mute_regions.clone();
+            clip.crossfade = self.crossfade;
+            if self.stretch != 1.0 || self.varispeed {
+                clip.set_stretch(self.stretch);
+                clip.set_varispeed(self.varispeed);
+                clip.seek(self.start_time.to_duration(self.rate));
+            }
+        }
+    }
 }
 
 pub struct DeleteClip {
@@ -216,26 +775,16 @@ impl Command for DeleteClip {
         Ok(())
     }
     fn undo(&self, engine: &mut Engine) -> Result<()> {
-        let sr = engine.sample_rate;
         let ch = engine.channels;
 
         if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == self.track_id) {
-            track.restore_deleted_clip( // <--- CHANGED HERE
-                self.clip_index,
-                self.clip_data.path.clone(),
-                self.clip_data.start_time,
-                self.clip_data.offset,
-                self.clip_data.duration,
-                self.clip_data.source_duration, // <--- ADDED
-                self.clip_data.source_sr,       // <--- ADDED
-                self.clip_data.source_ch,       // <--- ADDED
-                sr,
-                ch
-            )?;
+            self.clip_data.restore(track, self.clip_index, ch)?;
+            self.clip_data.restore_annotations(track);
         }
         Ok(())
     }
     fn name(&self) -> &str { "Delete Clip" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 pub struct SplitClip {
@@ -246,11 +795,10 @@ pub struct SplitClip {
 impl Command for SplitClip {
     fn execute(&self, engine: &mut Engine) -> Result<()> {
          // FIX: Capture variables before borrowing tracks_mut()
-         let sr = engine.sample_rate;
          let ch = engine.channels;
          
          if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == self.track_id) {
-             track.split_at_time(self.split_time, sr, ch)?;
+             track.split_at_time(self.split_time, ch)?;
          }
          Ok(())
     }
@@ -260,25 +808,22 @@ impl Command for SplitClip {
             // merge_next takes the index of the LEFT clip.
             // We need to find index `i` where `clips[i].end == split_time`
             
-            let eps = 0.001;
-            let split_secs = self.split_time.as_secs_f64();
+            let eps = Duration::from_millis(1);
             
-            if let Some(idx) = track.clips.iter().position(|c| {
-                let end = c.start_time.as_secs_f64() + c.duration.as_secs_f64();
-                (end - split_secs).abs() < eps
-            }) {
+            if let Some(idx) = track.clips.iter().position(|c| c.end().abs_diff(self.split_time) < eps) {
                 track.merge_next(idx)?;
             }
         }
         Ok(())
     }
     fn name(&self) -> &str { "Split Clip" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 pub struct MergeClip {
     pub track_id: TrackId,
     pub clip_index: usize,
-    pub original_duration: Duration,
+    pub original_duration: Frames, // at the left clip's frame rate
     pub right_clip_data: DeletedClipData,
 }
 
@@ -291,33 +836,100 @@ impl Command for MergeClip {
     }
     
     fn undo(&self, engine: &mut Engine) -> Result<()> {
-        let sr = engine.sample_rate;
         let ch = engine.channels;
 
         if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == self.track_id) {
             // 1. Restore left clip's original duration
             if let Some(left) = track.clips.get_mut(self.clip_index) {
                 left.duration = self.original_duration;
+                let secs = self.original_duration.to_seconds(left.frame_rate()).0;
+                left.mute_regions = mute_regions::slice(&left.mute_regions, 0.0, secs);
             }
             
             // 2. Restore the deleted right clip
-            track.restore_deleted_clip( // <--- CHANGED HERE
-                self.clip_index + 1,
-                self.right_clip_data.path.clone(),
-                self.right_clip_data.start_time,
-                self.right_clip_data.offset,
-                self.right_clip_data.duration,
-                self.right_clip_data.source_duration, // <--- ADDED
-                self.right_clip_data.source_sr,       // <--- ADDED
-                self.right_clip_data.source_ch,       // <--- ADDED
-                sr,
-                ch
-            )?;
+            self.right_clip_data.restore(track, self.clip_index + 1, ch)?;
+            self.right_clip_data.restore_annotations(track);
         }
         Ok(())
     }
     
     fn name(&self) -> &str { "Merge Clips" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
+}
+
+/// A finished recording placed on a track.
+/// Undo removes the clip and parks the WAV in `.trash/` next to it; redo brings both back.
+/// If another clip still references the file (duplicate/copy), undo only removes the clip.
+pub struct RecordTake {
+    pub track_id: TrackId,
+    pub clip_data: DeletedClipData,
+}
+
+impl RecordTake {
+    fn trash_path(&self) -> Option<std::path::PathBuf> {
+        let path = std::path::Path::new(&self.clip_data.path);
+        let name = path.file_name()?;
+        Some(path.parent()?.join(".trash").join(name))
+    }
+
+    fn is_take_clip(&self, clip: &Clip) -> bool {
+        self.clip_data.is_clip(clip)
+    }
+}
+
+impl Command for RecordTake {
+    fn execute(&self, engine: &mut Engine) -> Result<()> {
+        // Redo: pull the file back out of the trash first
+        let live = std::path::Path::new(&self.clip_data.path);
+        if !live.exists() {
+            if let Some(trash) = self.trash_path().filter(|p| p.exists()) {
+                std::fs::rename(&trash, live)?;
+            }
+        }
+
+        let ch = engine.channels;
+        let playing = engine.transport.playing;
+        let pos = engine.transport.time();
+
+        if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == self.track_id) {
+            let index = track.clips.len();
+            self.clip_data.restore(track, index, ch)?;
+            if let Some(clip) = track.clips.iter_mut().find(|c| self.is_take_clip(c)) {
+                clip.seek(pos);
+                clip.set_playing(playing);
+            }
+        }
+        Ok(())
+    }
+
+    fn undo(&self, engine: &mut Engine) -> Result<()> {
+        if let Some(track) = engine.tracks_mut().iter_mut().find(|t| t.id == self.track_id) {
+            if let Some(idx) = track.clips.iter().position(|c| self.is_take_clip(c)) {
+                track.delete_clip(idx)?;
+            }
+        }
+
+        // Only trash the file if nothing else still plays it
+        let still_referenced = engine.tracks().iter()
+            .flat_map(|t| t.clips.iter())
+            .any(|c| c.path == self.clip_data.path);
+
+        if !still_referenced {
+            if let Some(trash) = self.trash_path() {
+                if let Some(dir) = trash.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                if let Err(e) = std::fs::rename(&self.clip_data.path, &trash) {
+                    // File may still be held open by a decoder on some platforms; the clip is gone either way
+                    rt_warn!("⚠️ Could not move take to trash: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str { "Record Take" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 pub struct UpdateEq {
@@ -341,6 +953,7 @@ impl Command for UpdateEq {
         Ok(())
     }
     fn name(&self) -> &str { "EQ Change" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 pub struct UpdateCompressor {
@@ -363,6 +976,7 @@ impl Command for UpdateCompressor {
         Ok(())
     }
     fn name(&self) -> &str { "Compressor Change" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 pub struct UpdateReverb {
@@ -388,6 +1002,7 @@ impl Command for UpdateReverb {
     }
     
     fn name(&self) -> &str { "Reverb Change" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 /// ==========================================
@@ -409,6 +1024,7 @@ impl Command for ClearVolumeAutomationCmd {
         Ok(())
     }
     fn name(&self) -> &str { "Clear Volume Automation" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 pub struct AddVolumeAutomationCmd {
@@ -433,6 +1049,7 @@ impl Command for AddVolumeAutomationCmd {
         Ok(())
     }
     fn name(&self) -> &str { "Add Automation Node" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 pub struct DuckVolumeCmd {
@@ -463,6 +1080,7 @@ impl Command for DuckVolumeCmd {
     }
     fn undo(&self, _engine: &mut Engine) -> Result<()> { Ok(()) }
     fn name(&self) -> &str { "Duck Peak Volume" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
 
 pub struct RideVocalLevelCmd {
@@ -485,47 +1103,36 @@ impl Command for RideVocalLevelCmd {
             let clips_info: Vec<_> = track.clips.iter().map(|c| {
                 (
                     c.path.clone(), 
-                    c.start_time.as_secs_f64(), 
-                    c.offset.as_secs_f64(), 
-                    c.duration.as_secs_f64()
+                    c.start().as_secs_f64(), 
+                    c.offset_time().as_secs_f64(), 
+                    c.length().as_secs_f64()
                 )
             }).collect();
 
             for (path, start_sec, offset_sec, dur_sec) in clips_info {
-                // 2. Decode the raw audio file (Offline Background Processing)
-                if let Ok((samples, source_sr, source_ch)) = crate::bpm::adapter::decode_to_vec(&path) {
-                    
-                    // 3. Slice the buffer to strictly match the clip's trim (offset) and length
-                    let start_sample_idx = (offset_sec * source_sr as f64) as usize * source_ch;
-                    let end_sample_idx = ((offset_sec + dur_sec) * source_sr as f64) as usize * source_ch;
-
-                    let active_samples = if end_sample_idx <= samples.len() {
-                        &samples[start_sample_idx..end_sample_idx]
-                    } else if start_sample_idx < samples.len() {
-                        &samples[start_sample_idx..]
-                    } else {
-                        &[]
-                    };
-
-                    // 4. Generate the automation nodes for this specific trimmed slice
-                    let nodes = crate::engine::automation::generate_rider_automation(
-                        active_samples,
-                        source_ch,
-                        source_sr,
-                        start_sec, // Use the Timeline position so the nodes draw in the right place!
-                        self.target_lufs,
-                        self.max_boost_db,
-                        self.max_cut_db,
-                        self.smoothness,
-                        self.analysis_window_ms,
-                        self.noise_floor_db,
-                    );
-
-                    // 5. Insert nodes into the track, translating Source Hz to Engine Hz to prevent drift
-                    for node in nodes {
-                        let time_in_engine_samples = (node.time as f64 / source_sr as f64 * engine_sr) as u64;
-                        track.volume_automation.insert_node(time_in_engine_samples, node.value);
-                    }
+                // 2-3. Decode just the clip's trim (offset) and length (Offline Background Processing);
+                // the shared analysis cache only holds the start of long files
+                let (active_samples, source_sr, source_ch) = crate::bpm::adapter::decode_range(&path, offset_sec, dur_sec)
+                    .map_err(|e| anyhow::anyhow!("Vocal rider could not decode {}: {}", path, e))?;
+
+                // 4. Generate the automation nodes for this specific trimmed slice
+                let nodes = crate::engine::automation::generate_rider_automation(
+                    &active_samples,
+                    source_ch,
+                    source_sr,
+                    start_sec, // Use the Timeline position so the nodes draw in the right place!
+                    self.target_lufs,
+                    self.max_boost_db,
+                    self.max_cut_db,
+                    self.smoothness,
+                    self.analysis_window_ms,
+                    self.noise_floor_db,
+                );
+
+                // 5. Insert nodes into the track, translating Source Hz to Engine Hz to prevent drift
+                for node in nodes {
+                    let time_in_engine_samples = (node.time as f64 / source_sr as f64 * engine_sr) as u64;
+                    track.volume_automation.insert_node(time_in_engine_samples, node.value);
                 }
             }
         }
@@ -534,4 +1141,5 @@ impl Command for RideVocalLevelCmd {
     
     fn undo(&self, _engine: &mut Engine) -> Result<()> { Ok(()) }
     fn name(&self) -> &str { "Vocal Rider" }
+    fn track_ids(&self) -> Vec<TrackId> { vec![self.track_id] }
 }
\ No newline at end of file
@@ -0,0 +1,164 @@
+// src/session/dither.rs
+
+// Dither for bit-depth reduction on export. TPDF noise of ±1 LSB decorrelates the
+// quantization error from the signal (no distortion on quiet fades); the shaped variant
+// adds first-order error feedback, moving that noise up towards less audible frequencies.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Dither {
+    None,
+    #[default]
+    Tpdf,
+    TpdfShaped,
+}
+
+pub struct Ditherer {
+    kind: Dither,
+    scale: f32, // full scale in LSBs
+    min: i32,
+    max: i32,
+    rng: StdRng,
+    error: Vec<f32>, // last quantization error per channel (noise shaping)
+}
+
+impl Ditherer {
+    /// `bits` is the integer target depth (16 or 24).
+    pub fn new(kind: Dither, bits: u16, channels: usize) -> Self {
+        let max = (1i32 << (bits.clamp(2, 31) - 1)) - 1;
+        Self {
+            kind,
+            scale: max as f32,
+            min: -max - 1,
+            max,
+            // Fixed seed: the same mix exports to the same file
+            rng: StdRng::seed_from_u64(0x48_4156_454e),
+            error: vec![0.0; channels.max(1)],
+        }
+    }
+
+    /// Quantize one sample (nominally -1.0..=1.0) of channel `ch`. Never overflows.
+    pub fn quantize(&mut self, sample: f32, ch: usize) -> i32 {
+        let x = sample * self.scale;
+        let q = match self.kind {
+            Dither::None => x.round(),
+            Dither::Tpdf => (x + self.tpdf()).round(),
+            Dither::TpdfShaped => {
+                let d = self.tpdf();
+                let channels = self.error.len();
+                let e = &mut self.error[ch % channels];
+                let v = x - *e;
+                let q = (v + d).round().clamp(self.min as f32, self.max as f32);
+                // Keep the fed-back error bounded even when clamping kicks in
+                *e = (q - v).clamp(-2.0, 2.0);
+                q
+            }
+        };
+        (q as i32).clamp(self.min, self.max)
+    }
+
+    // Triangular noise in (-1, 1) LSB
+    fn tpdf(&mut self) -> f32 {
+        self.rng.random::<f32>() - self.rng.random::<f32>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustfft::{num_complex::Complex, FftPlanner};
+
+    const N: usize = 1 << 15;
+    const BIN: usize = 683; // the sine sits exactly on this FFT bin
+
+    // Power spectrum (Hann window) of the 16-bit quantization error, in LSB², for a slow
+    // fade-out of a -60 dBFS sine down to about one LSB, where truncation distorts most
+    fn error_spectrum(kind: Dither) -> Vec<f32> {
+        let mut ditherer = Ditherer::new(kind, 16, 1);
+        let scale = 32767.0;
+        let mut buf: Vec<Complex<f32>> = (0..N)
+            .map(|i| {
+                let t = i as f32 / N as f32;
+                let x = 0.001 * (1.0 - 0.98 * t) * (2.0 * std::f32::consts::PI * (BIN * i) as f32 / N as f32).sin();
+                let error = ditherer.quantize(x, 0) as f32 - x * scale;
+                let window = 0.5 - 0.5 * (2.0 * std::f32::consts::PI * t).cos();
+                Complex::new(error * window, 0.0)
+            })
+            .collect();
+        FftPlanner::new().plan_fft_forward(N).process(&mut buf);
+        buf[..N / 2].iter().map(|c| c.norm_sqr()).collect()
+    }
+
+    fn median(values: &[f32]) -> f32 {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f32::total_cmp);
+        sorted[sorted.len() / 2]
+    }
+
+    // Mean error power around the sine's harmonics, over the error's median bin
+    fn harmonic_spike(spectrum: &[f32]) -> f32 {
+        let bands: Vec<f32> = (2..=15)
+            .map(|h| h * BIN)
+            .filter(|&bin| bin + 4 < spectrum.len())
+            .flat_map(|bin| spectrum[bin - 4..=bin + 4].iter().copied())
+            .collect();
+        bands.iter().sum::<f32>() / bands.len() as f32 / median(&spectrum[1..])
+    }
+
+    #[test]
+    fn truncation_error_has_harmonics_and_tpdf_error_is_flat() {
+        let plain = error_spectrum(Dither::None);
+        let dithered = error_spectrum(Dither::Tpdf);
+        assert!(harmonic_spike(&plain) > 8.0, "rounding alone: {}", harmonic_spike(&plain));
+        assert!(harmonic_spike(&dithered) < 2.0, "with dither: {}", harmonic_spike(&dithered));
+
+        // White: the lower and upper halves of the band carry the same noise
+        let half = dithered.len() / 2;
+        let low = dithered[1..half].iter().sum::<f32>() / (half - 1) as f32;
+        let high = dithered[half..].iter().sum::<f32>() / half as f32;
+        assert!((low / high - 1.0).abs() < 0.1, "low {low} vs high {high}");
+    }
+
+    #[test]
+    fn shaped_dither_moves_the_noise_up_without_harmonics() {
+        let shaped = error_spectrum(Dither::TpdfShaped);
+        assert!(harmonic_spike(&shaped) < 2.0, "shaped: {}", harmonic_spike(&shaped));
+        let quarter = shaped.len() / 4;
+        let low = shaped[1..quarter].iter().sum::<f32>();
+        let high = shaped[3 * quarter..].iter().sum::<f32>();
+        assert!(high > 4.0 * low, "low {low} vs high {high}");
+    }
+
+    #[test]
+    fn full_scale_never_overflows_or_wraps() {
+        for kind in [Dither::None, Dither::Tpdf, Dither::TpdfShaped] {
+            for bits in [16u16, 24] {
+                let mut ditherer = Ditherer::new(kind, bits, 2);
+                let max = (1i32 << (bits - 1)) - 1;
+                for i in 0..10_000 {
+                    let ch = i % 2;
+                    let top = ditherer.quantize(1.0, ch);
+                    let bottom = ditherer.quantize(-1.0, ch);
+                    let over = ditherer.quantize(1.5, ch);
+                    assert!(top > max - 4 && top <= max, "{kind:?} {bits}: {top}");
+                    assert!(bottom < -max + 4 && bottom >= -max - 1, "{kind:?} {bits}: {bottom}");
+                    assert_eq!(over, max);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_mix_dithers_the_same_way() {
+        let mut a = Ditherer::new(Dither::Tpdf, 16, 2);
+        let mut b = Ditherer::new(Dither::Tpdf, 16, 2);
+        for i in 0..1000 {
+            let x = (i as f32 * 0.01).sin() * 0.001;
+            assert_eq!(a.quantize(x, i % 2), b.quantize(x, i % 2));
+        }
+    }
+}
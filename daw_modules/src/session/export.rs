@@ -1,347 +1,317 @@
 // daw_modules/src/session/export.rs
 
 use crate::session::serialization::ProjectManifest;
-use crate::decoder::{pipe, resample};
+use crate::decoder::dsp::{self, ResampleQuality};
+use crate::decoder::stretch::{self, MAX_STRETCH, MIN_STRETCH};
+use crate::bpm::adapter::decode_chunks_from;
 use anyhow::Result;
+use std::ops::ControlFlow;
 use hound::{WavSpec, WavWriter, SampleFormat};
-use symphonia::core::audio::SampleBuffer;
-use symphonia::core::formats::FormatReader;
-use symphonia::core::codecs::Decoder;
-use rubato::Resampler; 
 
 use crate::effects::equalizer::{TrackEq, EqParams};
 use crate::effects::compressor::{CompressorNode, CompressorParams};
 use crate::effects::reverb::{ReverbNode, ReverbParams};
+use crate::effects::chain::EffectChain;
+use crate::effects::limiter::LimiterNode;
+use crate::effects::pitch_shift::PitchShiftNode;
+use crate::effects::soft_clip::SoftClipNode;
 use crate::engine::automation::AutomationCurve;
+use crate::engine::mixer::{self, AuxBus, AuxSend, GroupBus};
+use crate::engine::pdc::PdcDelay;
+use crate::effects::Effect;
+use crate::engine::time::{Frames, Seconds};
+use crate::engine::mute_regions::{self, MuteRegion};
+use crate::engine::crossfades::{self, Span};
+use crate::session::bounce::BounceFormat;
+use crate::session::dither::{Dither, Ditherer};
+use serde::{Deserialize, Serialize};
+
+/// Output format of an offline export. Dither only applies to the integer formats.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+    #[serde(default)]
+    pub format: BounceFormat,
+    #[serde(default)]
+    pub dither: Dither,
+}
+
+/// A clip's audio as it plays: trimmed to offset/duration, time stretched (or varispeeded),
+/// stereo, at `sample_rate`.
+pub fn load_clip_audio(path: &str, sample_rate: u32, offset: f64, duration: f64, stretch: f64, varispeed: bool) -> Result<Vec<f32>> {
+    // Decode only the trimmed region (seeked to the offset, stopped once it is covered),
+    // then resample it in one offline pass
+    let stretch = stretch.clamp(MIN_STRETCH, MAX_STRETCH);
+    let mut stereo = Vec::new();
+    let mut wanted = None; // samples of the region, once the source rate is known
+    let (source_rate, _, _) = decode_chunks_from(path, offset.max(0.0), None, |chunk, source_rate, channels| {
+        let wanted = *wanted.get_or_insert_with(|| {
+            let samples = Seconds(duration / stretch).to_frames(source_rate).as_usize() * 2;
+            stereo.reserve(samples);
+            samples
+        });
+        stereo.extend(dsp::updown_mix_interleaved(chunk, channels.max(1), 2));
+        if stereo.len() >= wanted {
+            stereo.truncate(wanted);
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })?;
+    let region = &stereo[..];
+
+    // Varispeed folds the stretch into the resampling: `stretch` times as many frames,
+    // played back at `sample_rate`, so the pitch moves with the speed
+    let mut samples = if varispeed {
+        let render_rate = (sample_rate as f64 * stretch).round() as u32;
+        dsp::offline_resample(region, 2, source_rate, render_rate, ResampleQuality::Best)
+    } else {
+        let samples = dsp::offline_resample(region, 2, source_rate, sample_rate, ResampleQuality::Best);
+        stretch::stretch_interleaved(&samples, 2, sample_rate, stretch)
+    };
+
+    // Never play past the clip boundary, even if rounding added a frame
+    let max_frames_to_play = Seconds(duration).to_frames(sample_rate).as_usize();
+    samples.truncate(max_frames_to_play * 2);
+    Ok(samples)
+}
 
 pub struct ExportVoice {
-    format: Box<dyn FormatReader>,
-    decoder: Box<dyn Decoder>,
-    track_id: u32,
-    resampler: Option<rubato::SincFixedIn<f32>>,
-    
-    input_sample_buf: Option<SampleBuffer<f32>>,
-    resampler_input_buffer: Vec<f32>, 
-    output_buffer: Vec<f32>,          
+    // Clip audio, already trimmed to offset/duration, stereo, at the export rate
+    samples: Vec<f32>,
+    read_pos: usize, // in frames
     
-    finished: bool,
-    source_channels: usize,
-    
-    start_frame: usize,
-    frames_processed: usize,
-
-    // --- NEW FIELDS FOR DRIFT FIX ---
-    frames_played: usize,
-    max_frames_to_play: usize,
-    // --------------------------------
+    start_frame: Frames,
+    frames_processed: Frames,
     
     gain: f32,
     pan: f32,
     muted: bool,
+    fx_bypass: bool,
+    bypass_delay: PdcDelay, // holds the dry signal back by the chain's latency while bypassed
 
     track_eq: TrackEq,
     track_compressor: CompressorNode,
     track_reverb: ReverbNode,
+    inserts: EffectChain,
     volume_automation: AutomationCurve<f32>,
+    pub sends: Vec<AuxSend>,
+    pub group: Option<u32>,
 }
 
 impl ExportVoice {
     // FIX: Added start_time to arguments
     // FIX: Added DSP parameters to signature
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         path: &str, 
         target_sample_rate: u32, 
         start_time: f64, 
         offset: f64, 
         duration: f64,
+        stretch: f64,
+        varispeed: bool,
         eq_state: Option<Vec<EqParams>>,
         comp_state: Option<CompressorParams>,
         rev_state: Option<ReverbParams>,
         automation: AutomationCurve<f32>
     ) -> Result<Self> {
-        // FIX: Reverted to standard open_and_probe (returns 2 items, not 3)
-        let (format, track_id) = pipe::open_and_probe(path)?;
-        
-        let track = format.tracks().iter().find(|t| t.id == track_id).unwrap();
-        let source_rate = track.codec_params.sample_rate.unwrap_or(44100);
-        let source_channels = track.codec_params.channels.map(|c| c.count()).unwrap_or(2);
+        let samples = load_clip_audio(path, target_sample_rate, offset, duration, stretch, varispeed)?;
 
-        let mut format = format; 
-        let decoder = pipe::make_decoder(&mut *format)?;
-        
-        let resampler = if source_rate != target_sample_rate {
-            resample::build_resampler(source_rate, target_sample_rate, 2)?
-        } else {
-            None
-        };
-
-        let start_frame = (start_time * target_sample_rate as f64).round() as usize;
-        let frames_to_skip = (offset * target_sample_rate as f64).round() as usize;
-        let max_frames_to_play = (duration * target_sample_rate as f64).round() as usize;
+        let start_frame = Seconds(start_time).to_frames(target_sample_rate);
 
         // --- ADDED: Setup DSP Nodes ---
         let mut track_eq = TrackEq::new(target_sample_rate, 2);
         if let Some(eq) = eq_state { track_eq.set_state(eq); }
 
-        let track_compressor = CompressorNode::new(target_sample_rate as f32);
+        let track_compressor = CompressorNode::new(target_sample_rate as f32, 2);
         if let Some(comp) = comp_state { track_compressor.set_params(comp); }
 
         let track_reverb = ReverbNode::new(target_sample_rate as f32);
         if let Some(rev) = rev_state { track_reverb.set_params(rev); }
         // ------------------------------
 
-        let mut voice = Self {
-            format,
-            decoder,
-            track_id,
-            resampler,
-            input_sample_buf: None,
-            resampler_input_buffer: Vec::with_capacity(4096),
-            output_buffer: Vec::new(),
-            finished: false,
-            source_channels,
+        Ok(Self {
+            samples,
+            read_pos: 0,
             gain: 1.0,
             pan: 0.0,
             muted: false,
+            fx_bypass: false,
+            bypass_delay: PdcDelay::new(2),
             start_frame,
-            frames_processed: 0, 
-            frames_played: 0,
-            max_frames_to_play,
+            frames_processed: Frames::ZERO, 
             // --- ADDED FIELDS ---
             track_eq,
             track_compressor,
             track_reverb,
+            inserts: EffectChain::new(2, target_sample_rate),
             volume_automation: automation,
-        };
-
-        // Pre-roll: Decode and discard the trimmed 'offset' audio silently
-        let mut skipped = 0;
-        while skipped < frames_to_skip {
-            let chunk = (frames_to_skip - skipped).min(4096);
-            let _ = voice.prepare_samples(chunk);
-            let available = voice.output_buffer.len() / 2;
-            let actual = chunk.min(available);
-            if actual == 0 { break; } // Hit EOF early
-            voice.output_buffer.drain(0..(actual * 2));
-            skipped += actual;
-        }
-        Ok(voice)
+            sends: Vec::new(),
+            group: None,
+        })
     }
 
-    fn prepare_samples(&mut self, frames_needed: usize) -> Result<bool> {
-        while self.output_buffer.len() < frames_needed * 2 {
-            if self.finished && self.resampler_input_buffer.is_empty() {
-                break;
-            }
+    /// Insert latency of this voice's DSP chain, in frames (bypassed too, like `Track`).
+    pub fn latency_frames(&self) -> usize {
+        self.track_eq.latency_frames()
+            + self.track_compressor.latency_frames()
+            + self.track_reverb.latency_frames()
+            + self.inserts.latency_frames()
+    }
 
-            let chunk_size = if let Some(r) = &self.resampler { r.input_frames_next() } else { 0 };
-            let needed_for_resample = if chunk_size > 0 { chunk_size * 2 } else { 0 };
-
-            if !self.finished && (self.resampler.is_none() || self.resampler_input_buffer.len() < needed_for_resample) {
-                let packet_opt = loop {
-                    match self.format.next_packet() {
-                        Ok(p) => {
-                            if p.track_id() == self.track_id { break Some(p); }
-                        },
-                        Err(_) => {
-                            self.finished = true;
-                            break None; 
-                        }
-                    }
-                };
-
-                if let Some(packet) = packet_opt {
-                    let decoded = match self.decoder.decode(&packet) {
-                        Ok(d) => d,
-                        Err(_) => continue,
-                    };
-
-                    if self.input_sample_buf.is_none() {
-                        let spec = *decoded.spec();
-                        self.input_sample_buf = Some(SampleBuffer::new(decoded.capacity() as u64 + 4096, spec));
-                    }
-                    let buf = self.input_sample_buf.as_mut().unwrap();
-                    buf.copy_interleaved_ref(decoded);
-                    let samples = buf.samples();
-
-                    if self.source_channels == 1 {
-                        for s in samples {
-                            self.resampler_input_buffer.push(*s);
-                            self.resampler_input_buffer.push(*s);
-                        }
-                    } else {
-                        self.resampler_input_buffer.extend_from_slice(samples);
-                    }
-                }
-            }
+    /// Silence the clip's mute regions (the samples start at the clip start).
+    pub fn apply_mute_regions(&mut self, regions: &[MuteRegion], sample_rate: u32) {
+        mute_regions::apply(&mut self.samples, 2, 0, regions, sample_rate);
+    }
 
-            if let Some(r) = &mut self.resampler {
-                let chunk_frames = r.input_frames_next();
-                let chunk_samples = chunk_frames * 2;
-
-                while self.resampler_input_buffer.len() >= chunk_samples {
-                    let chunk_slice = &self.resampler_input_buffer[0..chunk_samples];
-                    let mut planar = vec![Vec::with_capacity(chunk_frames); 2];
-                    for i in 0..chunk_frames {
-                        planar[0].push(chunk_slice[i*2]);
-                        planar[1].push(chunk_slice[i*2+1]);
-                    }
-
-                    if let Ok(resampled) = r.process(&planar, None) {
-                         let out_frames = resampled[0].len();
-                         for i in 0..out_frames {
-                             self.output_buffer.push(resampled[0][i]);
-                             self.output_buffer.push(resampled[1][i]);
-                         }
-                    }
-                    self.resampler_input_buffer.drain(0..chunk_samples);
-                }
+    /// Fade where this clip (`index` among `spans`, its track's clips) overlaps another.
+    /// Before `delay_start`, while `start_frame` is still the clip's timeline position.
+    pub fn apply_crossfades(&mut self, index: usize, spans: &[Span], sample_rate: u32) {
+        crossfades::apply(&mut self.samples, 2, self.start_frame.0, index, spans, sample_rate);
+    }
 
-                if self.finished && !self.resampler_input_buffer.is_empty() {
-                    let remaining_samples = self.resampler_input_buffer.len();
-                    let remaining_frames = remaining_samples / 2;
-                    
-                    if remaining_frames > 0 {
-                        let mut planar = vec![Vec::with_capacity(remaining_frames); 2];
-                        for i in 0..remaining_frames {
-                            planar[0].push(self.resampler_input_buffer[i*2]);
-                            planar[1].push(self.resampler_input_buffer[i*2+1]);
-                        }
-                        
-                        if let Ok(resampled) = r.process_partial::<Vec<f32>>(Some(&planar), None) {
-                             if !resampled.is_empty() {
-                                 let out_frames = resampled[0].len();
-                                 for i in 0..out_frames {
-                                     self.output_buffer.push(resampled[0][i]);
-                                     self.output_buffer.push(resampled[1][i]);
-                                 }
-                             }
-                        }
-                    }
-                    self.resampler_input_buffer.clear();
-                }
-            } else {
-                self.output_buffer.append(&mut self.resampler_input_buffer);
-                if self.finished && self.output_buffer.is_empty() { break; }
-            }
+    /// PDC: schedule the voice later so it lines up with more latent tracks.
+    pub fn delay_start(&mut self, frames: usize) {
+        self.start_frame += Frames(frames as u64);
+    }
 
-            if self.output_buffer.len() >= frames_needed * 2 { break; }
-            if self.finished && self.resampler_input_buffer.is_empty() { break; }
-        }
-        Ok(!self.output_buffer.is_empty())
+    fn frames_remaining(&self) -> usize {
+        (self.samples.len() / 2).saturating_sub(self.read_pos)
     }
 
-    pub fn add_to_mix(&mut self, out_buf: &mut [f32], frames: usize) -> Result<()> {
+    /// Mix this block into `out_buf` and, through the voice's sends, into the aux buses.
+    pub fn add_to_mix(&mut self, out_buf: &mut [f32], buses: &mut [AuxBus], frames: usize) -> Result<()> {
+        let block = Frames(frames as u64);
         if self.muted { 
-            self.frames_processed += frames;
+            self.frames_processed += block;
             return Ok(()); 
         }
 
         let mut buf_offset = 0;
         if self.frames_processed < self.start_frame {
             let silence_needed = self.start_frame - self.frames_processed;
-            if silence_needed >= frames {
-                self.frames_processed += frames; // Advance block
+            if silence_needed >= block {
+                self.frames_processed += block; // Advance block
                 return Ok(());
             } else {
-                buf_offset = silence_needed;
+                buf_offset = silence_needed.as_usize();
             }
         }
 
         // --- ENFORCE CLIP DURATION BOUNDARY ---
-        let frames_remaining = self.max_frames_to_play.saturating_sub(self.frames_played);
-        let audio_frames_requested = (frames - buf_offset).min(frames_remaining);
-
-        if audio_frames_requested > 0 {
-            self.prepare_samples(audio_frames_requested)?;
-            
-            let samples_available = self.output_buffer.len() / 2; 
-            let frames_to_mix = audio_frames_requested.min(samples_available);
-
-            if frames_to_mix > 0 {
-                // 1. Extract audio chunk
-                let mut chunk = vec![0.0f32; frames_to_mix * 2];
-                chunk.copy_from_slice(&self.output_buffer[0..(frames_to_mix * 2)]);
-
-                // 2. Process DSP (Pre-Fader exactly like track.rs)
+        let frames_to_mix = (frames - buf_offset).min(self.frames_remaining());
+
+        if frames_to_mix > 0 {
+            // 1. Extract audio chunk
+            let from = self.read_pos * 2;
+            let mut chunk = self.samples[from..from + frames_to_mix * 2].to_vec();
+            let start_sample = self.frames_processed.0 + buf_offset as u64; // accurate global timeline sample
+
+            // 2. Process DSP (Pre-Fader exactly like track.rs)
+            if self.fx_bypass {
+                // Late by the chain's latency all the same, so PDC lines it up as in the engine
+                self.bypass_delay.set_delay_frames(self.latency_frames());
+                self.bypass_delay.process(&mut chunk);
+            } else {
                 self.track_eq.process_buffer(&mut chunk, 2);
                 self.track_compressor.process(&mut chunk);
-                for i in (0..chunk.len()).step_by(2) {
-                    let (l, r) = self.track_reverb.process(chunk[i], chunk[i+1]);
-                    chunk[i] = l;
-                    chunk[i+1] = r;
-                }
+                self.track_reverb.process_block(&mut chunk, 2);
+                self.inserts.apply_automation(start_sample);
+                self.inserts.process_block(&mut chunk, 2);
+            }
 
-                // 3. Automation & Gain 
-                let start_sample = (self.frames_processed + buf_offset) as u64; // accurate global timeline sample
-                let end_sample = start_sample + frames_to_mix as u64;
-                let start_gain_db = self.volume_automation.get_value_at_time(start_sample, 0.0);
-                let end_gain_db = self.volume_automation.get_value_at_time(end_sample, 0.0);
-
-                let start_gain_linear = self.gain * 10.0_f32.powf(start_gain_db / 20.0);
-                let end_gain_linear = self.gain * 10.0_f32.powf(end_gain_db / 20.0);
-                let gain_step = if frames_to_mix > 1 {
-                    (end_gain_linear - start_gain_linear) / (frames_to_mix as f32 - 1.0)
-                } else { 0.0 };
-
-                let mut current_gain = start_gain_linear;
-                let pan = self.pan.clamp(-1.0, 1.0);
-                let (pan_l, pan_r) = if self.pan != 0.0 {
-                    let angle = (pan + 1.0) * 0.25 * std::f32::consts::PI;
-                    (angle.cos(), angle.sin())
-                } else {
-                    (1.0, 1.0)
-                };
-
-                // 4. Apply Gain/Pan and Mix into Master
-                for i in 0..frames_to_mix {
-                    let out_idx = (buf_offset + i) * 2;
-                    let in_idx = i * 2;
-                    
-                    let l = chunk[in_idx] * current_gain * pan_l;
-                    let r = chunk[in_idx+1] * current_gain * pan_r;
-                    
-                    out_buf[out_idx] += l;
-                    out_buf[out_idx+1] += r;
-                    
-                    current_gain += gain_step;
-                }
+            // 3. Automation & Gain 
+            let end_sample = start_sample + frames_to_mix as u64;
+            let start_gain_db = self.volume_automation.get_value_at_time(start_sample, 0.0);
+            let end_gain_db = self.volume_automation.get_value_at_time(end_sample, 0.0);
+
+            let start_gain_linear = self.gain * 10.0_f32.powf(start_gain_db / 20.0);
+            let end_gain_linear = self.gain * 10.0_f32.powf(end_gain_db / 20.0);
+            let gain_step = if frames_to_mix > 1 {
+                (end_gain_linear - start_gain_linear) / (frames_to_mix as f32 - 1.0)
+            } else { 0.0 };
+
+            let mut current_gain = start_gain_linear;
+            let pan = self.pan.clamp(-1.0, 1.0);
+            let (pan_l, pan_r) = if self.pan != 0.0 {
+                let angle = (pan + 1.0) * 0.25 * std::f32::consts::PI;
+                (angle.cos(), angle.sin())
+            } else {
+                (1.0, 1.0)
+            };
+
+            // 4. Apply Gain/Pan and Mix into Master
+            for i in 0..frames_to_mix {
+                let out_idx = (buf_offset + i) * 2;
+                let in_idx = i * 2;
+                
+                chunk[in_idx] *= current_gain * pan_l;
+                chunk[in_idx+1] *= current_gain * pan_r;
+                
+                out_buf[out_idx] += chunk[in_idx];
+                out_buf[out_idx+1] += chunk[in_idx+1];
+                
+                current_gain += gain_step;
+            }
 
-                // 5. Drain processed samples
-                self.output_buffer.drain(0..(frames_to_mix * 2));
-                self.frames_played += frames_to_mix;
+            // 5. Post-fader sends
+            for send in &mut self.sends {
+                if let Some(bus) = buses.iter_mut().find(|b| b.id == send.bus_id) {
+                    bus.add_send(buf_offset * 2, &chunk, send);
+                }
             }
+
+            // 6. Advance read cursor
+            self.read_pos += frames_to_mix;
         }
 
         // Always advance the timeline cursor by the exact block size to stay in perfect sync
-        self.frames_processed += frames;
+        self.frames_processed += block;
         Ok(())
     }
     
     pub fn is_finished(&self) -> bool {
-        self.frames_played >= self.max_frames_to_play || 
-        (self.finished && self.output_buffer.is_empty() && self.resampler_input_buffer.is_empty())
+        self.frames_remaining() == 0
     }
 }
 
 pub fn export_project_to_wav(manifest: &ProjectManifest, output_path: &str) -> Result<()> {
-    println!("🚀 Starting Export: {}", output_path);
+    export_project_to_wav_with(manifest, output_path, &ExportOptions::default())
+}
+
+pub fn export_project_to_wav_with(manifest: &ProjectManifest, output_path: &str, options: &ExportOptions) -> Result<()> {
+    rt_info!("🚀 Starting Export: {} ({:?}, dither {:?})", output_path, options.format, options.dither);
     let sample_rate = 44100;
+    let (bits_per_sample, sample_format) = match options.format {
+        BounceFormat::Wav16 => (16, SampleFormat::Int),
+        BounceFormat::Wav24 => (24, SampleFormat::Int),
+        BounceFormat::Wav32Float => (32, SampleFormat::Float),
+    };
     let spec = WavSpec {
         channels: 2,
         sample_rate,
-        bits_per_sample: 16,
-        sample_format: SampleFormat::Int,
+        bits_per_sample,
+        sample_format,
     };
     let mut writer = WavWriter::create(output_path, spec)?;
-    let mut voices_with_solo: Vec<(ExportVoice, bool)> = Vec::new();
+    let mut ditherer = Ditherer::new(options.dither, bits_per_sample, 2);
+    let mut voices: Vec<ExportVoice> = Vec::new();
+    // A group's mute and solo count as set on every member, like in the engine
+    let group_mute_solo = |group: Option<u32>| {
+        group
+            .and_then(|id| manifest.groups.iter().find(|g| g.id == id))
+            .map_or((false, false), |g| (g.muted, g.solo))
+    };
+    let any_solo = manifest.tracks.iter().any(|t| t.solo || group_mute_solo(t.group).1);
     
     // --- THE 10 MINUTE BUG FIX ---
     // Dynamically calculate the actual end time of the project
     let mut max_end_time = 0.0;
     
     for t_state in &manifest.tracks {
-        for clip in &t_state.clips {
+        let spans: Vec<Span> = t_state.clips.iter().map(|c| c.span()).collect();
+        for (clip_index, clip) in t_state.clips.iter().enumerate() {
             // Find the furthest point any clip reaches on the timeline
             let clip_end = clip.start_time + clip.duration;
             if clip_end > max_end_time {
@@ -355,6 +325,8 @@ pub fn export_project_to_wav(manifest: &ProjectManifest, output_path: &str) -> R
                 clip.start_time, 
                 clip.offset, 
                 clip.duration,
+                clip.stretch,
+                clip.varispeed,
                 t_state.eq.clone(),
                 t_state.compressor.clone(),
                 t_state.reverb.clone(),
@@ -362,57 +334,246 @@ pub fn export_project_to_wav(manifest: &ProjectManifest, output_path: &str) -> R
             ) {
                 v.gain = t_state.gain;
                 v.pan = t_state.pan;
-                v.muted = t_state.muted; 
-                voices_with_solo.push((v, t_state.solo));
+                v.fx_bypass = t_state.fx_bypass;
+                v.sends = t_state.sends.iter().map(|s| AuxSend::new(s.bus_id, s.level.clamp(0.0, 2.0))).collect();
+                v.apply_mute_regions(&clip.mute_regions, sample_rate);
+                v.apply_crossfades(clip_index, &spans, sample_rate);
+                v.group = t_state.group;
+                v.inserts.set_states(&t_state.inserts);
+                // Same audibility rule as the realtime engine; the manifest itself is never rewritten
+                let (group_muted, group_solo) = group_mute_solo(t_state.group);
+                v.muted = !manifest.solo_policy.is_audible(t_state.muted || group_muted, t_state.solo || group_solo, any_solo && !t_state.solo_safe);
+                voices.push(v);
             } else {
-                 eprintln!("⚠️ Failed to load clip {}", clip.path);
+                 rt_warn!("⚠️ Failed to load clip {}", clip.path);
             }
         }
     }
 
-    // Add a 1.0 second tail so the audio doesn't abruptly cut off (good for reverbs)
-    let max_frames = ((max_end_time + 1.0) * sample_rate as f64).round() as usize;
-
-    let any_solo = manifest.tracks.iter().any(|t| t.solo);
-    if any_solo {
-        for (v, is_track_solo) in &mut voices_with_solo {
-            if !*is_track_solo {
-                v.muted = true;
-            } else {
-                v.muted = false; 
-            }
-        }
+    // --- PDC: same plus-delay compensation as the live engine ---
+    // Dry voices are pushed back to the most latent one, then that shared delay is trimmed from the file.
+    let project_latency = voices.iter().map(|v| v.latency_frames()).max().unwrap_or(0);
+    for v in &mut voices {
+        let own = v.latency_frames();
+        v.delay_start(project_latency - own);
     }
+    // Bus returns wait for the most latent bus chain, and the dry mix with them
+    let mut buses: Vec<AuxBus> = manifest.aux_buses.iter().map(|b| b.to_bus(2, sample_rate)).collect();
+    let bus_latency = mixer::compensate_buses(&mut buses);
+    let mut dry_delay = PdcDelay::new(2);
+    dry_delay.set_delay_frames(bus_latency);
+    let mut master_pitch = PitchShiftNode::new(manifest.master_pitch_shift, 2, sample_rate);
+    let mut master_clip = SoftClipNode::new(manifest.master_soft_clip, 2);
+    let mut master_limiter = LimiterNode::new(manifest.master_limiter, 2, sample_rate);
+    let output_latency = project_latency
+        + bus_latency
+        + master_pitch.latency_frames()
+        + master_clip.latency_frames()
+        + master_limiter.latency_frames();
+    let mut frames_to_skip = output_latency;
+
+    // Add a 1.0 second tail so the audio doesn't abruptly cut off (good for reverbs)
+    let max_frames = Seconds(max_end_time + 1.0).to_frames(sample_rate) + Frames(output_latency as u64);
 
     let block_size = 1024;
     let mut mix_buffer = vec![0.0; block_size * 2]; 
-    let mut total_frames = 0;
+    let mut groups: Vec<GroupBus> = manifest.groups.iter().map(|g| g.to_group(2, sample_rate)).collect();
+    let mut total_frames = Frames::ZERO;
 
     loop {
         // Break when all voices end OR when we hit the exact calculated project length
-        if voices_with_solo.iter().all(|(v, _)| v.is_finished()) || total_frames >= max_frames { 
+        if voices.iter().all(|v| v.is_finished()) || total_frames >= max_frames { 
             break; 
         }
         
         mix_buffer.fill(0.0);
-        for (v, _) in &mut voices_with_solo { 
-            v.add_to_mix(&mut mix_buffer, block_size)?; 
+        for bus in &mut buses {
+            bus.begin_block(mix_buffer.len());
+        }
+        for group in &mut groups {
+            group.begin_block(mix_buffer.len());
+        }
+        for v in &mut voices { 
+            // Group members are summed into their group's submix
+            let dest = match v.group.and_then(|id| groups.iter_mut().find(|g| g.id == id)) {
+                Some(group) => group.buffer_mut(),
+                None => &mut mix_buffer[..],
+            };
+            v.add_to_mix(dest, &mut buses, block_size)?; 
+        }
+        for group in &mut groups {
+            group.mix_into(&mut mix_buffer);
+        }
+        dry_delay.process(&mut mix_buffer);
+        for bus in &mut buses {
+            bus.mix_into(&mut mix_buffer);
         }
+        master_pitch.process_block(&mut mix_buffer, 2);
 
         if (manifest.master_gain - 1.0).abs() > 0.001 {
             for s in &mut mix_buffer { *s *= manifest.master_gain; }
         }
-
-        for sample in &mix_buffer {
-             let val = *sample;
-             let soft_clipped = val.tanh(); 
-             let s = (soft_clipped * i16::MAX as f32) as i16;
-             writer.write_sample(s)?;
+        master_clip.process_block(&mut mix_buffer, 2);
+        master_limiter.process_block(&mut mix_buffer, 2);
+
+        // Skip the compensated latency so the export starts at musical time zero
+        let skip = frames_to_skip.min(block_size);
+        frames_to_skip -= skip;
+
+        for (i, &sample) in mix_buffer[skip * 2..].iter().enumerate() {
+             match options.format {
+                 BounceFormat::Wav16 => writer.write_sample(ditherer.quantize(sample, i % 2) as i16)?,
+                 BounceFormat::Wav24 => writer.write_sample(ditherer.quantize(sample, i % 2))?,
+                 BounceFormat::Wav32Float => writer.write_sample(sample)?,
+             }
         }
-        total_frames += block_size;
+        total_frames += Frames(block_size as u64);
     }
     
     writer.finalize()?;
-    println!("✅ Export Complete! Total Length: {:.2}s", total_frames as f64 / sample_rate as f64);
+    let written = total_frames.saturating_sub(Frames(output_latency as u64));
+    rt_info!("✅ Export Complete! Total Length: {:.2}s", written.to_seconds(sample_rate).0);
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A mono WAV whose sample `i` is `i / frames`, so positions can be read back.
+    fn ramp_wav(name: &str, rate: u32, frames: usize) -> String {
+        let samples: Vec<f32> = (0..frames).map(|i| i as f32 / frames as f32).collect();
+        crate::test_util::wav(&format!("export_{}", name), rate, 1, &samples)
+    }
+
+    /// A mono 44.1 kHz WAV, silent but for a full-scale-ish click at `frame`.
+    fn impulse_wav(name: &str, frame: usize, frames: usize) -> String {
+        let mut samples = vec![0.0; frames];
+        samples[frame] = 0.5;
+        crate::test_util::wav(&format!("export_{}", name), 44_100, 1, &samples)
+    }
+
+    fn track(path: &str, compressor: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "name": "t", "color": "#fff", "gain": 1.0, "pan": 0.0, "muted": false, "solo": false,
+            "clips": [{ "path": path, "start_time": 0.0, "offset": 0.0, "duration": 0.5 }],
+            "compressor": compressor, "reverb": null,
+        })
+    }
+
+    /// Frames of the exported file holding anything but silence.
+    fn export_hits(tracks: Vec<serde_json::Value>, name: &str) -> Vec<usize> {
+        export_manifest_hits(serde_json::json!({ "version": 1, "master_gain": 1.0, "bpm": 120.0, "tracks": tracks }), name)
+    }
+
+    fn export_manifest_hits(manifest: serde_json::Value, name: &str) -> Vec<usize> {
+        let manifest: ProjectManifest = serde_json::from_value(manifest).unwrap();
+        let out = crate::test_util::temp_path(&format!("export_{}_out", name)).with_extension("wav");
+        let out = out.to_string_lossy().into_owned();
+        let options = ExportOptions { format: BounceFormat::Wav32Float, ..Default::default() };
+        export_project_to_wav_with(&manifest, &out, &options).unwrap();
+        let samples: Vec<f32> = hound::WavReader::open(&out).unwrap().samples::<f32>().map(|s| s.unwrap()).collect();
+        let _ = std::fs::remove_file(&out);
+        samples.chunks(2).enumerate().filter(|(_, f)| f[0].abs() > 1e-3).map(|(i, _)| i).collect()
+    }
+
+    #[test]
+    fn latent_and_dry_tracks_line_up_in_the_export() {
+        let path = impulse_wav("pdc", 1_000, 22_050);
+        // Ratio 1 leaves the click alone; the lookahead makes it 256 frames late
+        let lookahead = serde_json::json!({
+            "is_active": true, "threshold_db": 0.0, "ratio": 1.0, "attack_ms": 5.0, "release_ms": 50.0,
+            "makeup_gain_db": 0.0, "knee_db": 0.0, "lookahead_ms": 256.0 / 44.1,
+        });
+        let params: CompressorParams = serde_json::from_value(lookahead.clone()).unwrap();
+        let voice = ExportVoice::new(&path, 44_100, 0.0, 0.0, 0.5, 1.0, false, None, Some(params), None, AutomationCurve::new()).unwrap();
+        assert_eq!(voice.latency_frames(), 256);
+
+        let latent_alone = export_hits(vec![track(&path, lookahead.clone())], "pdc_latent");
+        let dry_alone = export_hits(vec![track(&path, serde_json::Value::Null)], "pdc_dry");
+        let both = export_hits(vec![track(&path, lookahead), track(&path, serde_json::Value::Null)], "pdc_both");
+        let _ = std::fs::remove_file(&path);
+
+        // Compensation is trimmed from the file: every version clicks at musical frame 1000
+        assert_eq!(latent_alone, vec![1_000]);
+        assert_eq!(dry_alone, vec![1_000]);
+        assert_eq!(both, vec![1_000]);
+    }
+
+    #[test]
+    fn export_and_playback_agree_on_every_mute_solo_combination() {
+        use crate::engine::{Engine, SoloPolicy};
+
+        // Track i clicks at frame 1000 * (i + 1), so the export shows who was heard
+        let paths = [impulse_wav("solo_a", 1_000, 22_050), impulse_wav("solo_b", 2_000, 22_050)];
+        for policy in [SoloPolicy::SoloOverridesMute, SoloPolicy::MuteAlwaysWins] {
+            for flags in 0..16u32 {
+                let flag = |bit: u32| flags & (1 << bit) != 0;
+                let (muted, solo) = ([flag(0), flag(1)], [flag(2), flag(3)]);
+
+                let tracks: Vec<_> = (0..2).map(|i| {
+                    let mut t = track(&paths[i], serde_json::Value::Null);
+                    t["muted"] = muted[i].into();
+                    t["solo"] = solo[i].into();
+                    t
+                }).collect();
+                let mut manifest = serde_json::json!({ "version": 1, "master_gain": 1.0, "bpm": 120.0, "tracks": tracks });
+                manifest["solo_policy"] = serde_json::to_value(policy).unwrap();
+                let hits = export_manifest_hits(manifest, "solo");
+                let exported = [hits.contains(&1_000), hits.contains(&2_000)];
+
+                let mut eng = Engine::new(44_100, 2);
+                eng.solo_policy = policy;
+                for i in 0..2 {
+                    eng.add_empty_track();
+                    let t = &mut eng.tracks_mut()[i];
+                    t.muted = muted[i];
+                    t.solo = solo[i];
+                }
+                let any_solo = eng.any_solo();
+                let played = [0, 1].map(|i| eng.is_track_audible(&eng.tracks()[i], any_solo));
+
+                assert_eq!(exported, played, "{policy:?}, muted {muted:?}, solo {solo:?}");
+            }
+        }
+        for path in &paths {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn clip_audio_starts_at_the_offset_and_stops_at_the_duration() {
+        let path = ramp_wav("trim", 48_000, 96_000);
+        let samples = load_clip_audio(&path, 48_000, 0.5, 0.25, 1.0, false).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(samples.len(), 12_000 * 2);
+        for (f, frame) in samples.chunks(2).enumerate() {
+            let expected = (24_000 + f) as f32 / 96_000.0;
+            assert_eq!(frame[0], frame[1]);
+            assert!((frame[0] - expected).abs() < 1e-6, "frame {f}: {} != {expected}", frame[0]);
+        }
+    }
+
+    #[test]
+    fn clip_audio_is_resampled_to_the_export_rate() {
+        let path = ramp_wav("rate", 44_100, 44_100);
+        let samples = load_clip_audio(&path, 48_000, 0.25, 0.5, 1.0, false).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(samples.len(), 24_000 * 2);
+        // Mid-clip the ramp reads 0.25 s (offset) + 0.25 s into the source
+        let mid = samples[12_000 * 2];
+        assert!((mid - 0.5).abs() < 1e-3, "mid-clip sample {mid}");
+    }
+
+    #[test]
+    fn clip_audio_past_the_source_end_is_short() {
+        let path = ramp_wav("tail", 48_000, 48_000);
+        let samples = load_clip_audio(&path, 48_000, 0.75, 1.0, 1.0, false).unwrap();
+        let past = load_clip_audio(&path, 48_000, 2.0, 1.0, 1.0, false).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(samples.len(), 12_000 * 2);
+        assert!(past.is_empty());
+    }
+}
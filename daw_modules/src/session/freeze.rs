@@ -0,0 +1,130 @@
+// src/session/freeze.rs
+
+// Track freeze: print a track's clips through its inserts (EQ, compressor, reverb) to a
+// 32-bit float file that starts at timeline zero. While frozen the track plays that file
+// instead of decoding its clips and running its inserts; fader, pan, volume automation and
+// sends stay live, insert automation is printed. The inserts' latency is trimmed, so the
+// file lines up with the timeline.
+
+use anyhow::Result;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::effects::compressor::CompressorNode;
+use crate::effects::equalizer::TrackEq;
+use crate::effects::reverb::ReverbNode;
+use crate::effects::chain::EffectChain;
+use crate::effects::Effect;
+use crate::engine::mute_regions;
+use crate::engine::crossfades::{self, Span};
+use crate::engine::time::{Frames, Seconds};
+use crate::engine::track::CLIP_EDGE_FADE;
+use super::export::load_clip_audio;
+use super::serialization::TrackState;
+
+/// Rendered past the last clip while the reverb is on, so its tail isn't cut.
+pub const FREEZE_REVERB_TAIL: Duration = Duration::from_secs(3);
+
+struct FreezeClip {
+    start: usize, // frames
+    samples: Vec<f32>,
+}
+
+/// Render `track` (stereo, at `sample_rate`) to `path`. Fader, pan and automation are not applied.
+pub fn render_frozen_track(track: &TrackState, sample_rate: u32, path: &Path) -> Result<()> {
+    let fade = Frames::from_duration(CLIP_EDGE_FADE, sample_rate).as_usize();
+    let spans: Vec<Span> = track.clips.iter().map(|c| c.span()).collect();
+    let mut clips = Vec::with_capacity(track.clips.len());
+    for (index, clip) in track.clips.iter().enumerate() {
+        let mut samples = match load_clip_audio(&clip.path, sample_rate, clip.offset, clip.duration, clip.stretch, clip.varispeed) {
+            Ok(samples) => samples,
+            Err(e) => {
+                rt_warn!("⚠️ Freeze: clip {} skipped: {}", clip.path, e);
+                continue;
+            }
+        };
+        let start = Seconds(clip.start_time).to_frames(sample_rate);
+        mute_regions::apply(&mut samples, 2, 0, &clip.mute_regions, sample_rate);
+        crossfades::apply(&mut samples, 2, start.0, index, &spans, sample_rate);
+        apply_edge_fades(&mut samples, fade);
+        clips.push(FreezeClip { start: start.as_usize(), samples });
+    }
+
+    let mut eq = TrackEq::new(sample_rate, 2);
+    let mut compressor = CompressorNode::new(sample_rate as f32, 2);
+    let mut reverb = ReverbNode::new(sample_rate as f32);
+    if let Some(state) = track.eq.clone() { eq.set_state(state); }
+    if let Some(params) = track.compressor { compressor.set_params(params); }
+    if let Some(params) = track.reverb { reverb.set_params(params); }
+    let mut inserts = EffectChain::new(2, sample_rate);
+    inserts.set_states(&track.inserts);
+
+    let fx = !track.fx_bypass;
+    let latency = if fx { eq.latency_frames() + compressor.latency_frames() + reverb.latency_frames() + inserts.latency_frames() } else { 0 };
+    let tail = if fx && (reverb.get_params().is_active || inserts.has_tail()) { Frames::from_duration(FREEZE_REVERB_TAIL, sample_rate).as_usize() } else { 0 };
+    let end = clips.iter().map(|c| c.start + c.samples.len() / 2).max().unwrap_or(0);
+    let total = end + tail + latency;
+
+    let spec = WavSpec { channels: 2, sample_rate, bits_per_sample: 32, sample_format: SampleFormat::Float };
+    let mut writer = WavWriter::create(path, spec)?;
+
+    let block_size = 1024;
+    let mut block = vec![0.0f32; block_size * 2];
+    let mut to_skip = latency;
+    let mut frame = 0;
+    while frame < total {
+        let frames = block_size.min(total - frame);
+        let buf = &mut block[..frames * 2];
+        buf.fill(0.0);
+
+        // Clips overlapping this block
+        for clip in &clips {
+            let clip_end = clip.start + clip.samples.len() / 2;
+            if clip_end <= frame || clip.start >= frame + frames {
+                continue;
+            }
+            let from = frame.max(clip.start);
+            let to = (frame + frames).min(clip_end);
+            let src = &clip.samples[(from - clip.start) * 2..(to - clip.start) * 2];
+            for (d, s) in buf[(from - frame) * 2..(to - frame) * 2].iter_mut().zip(src) {
+                *d += s;
+            }
+        }
+
+        // Same order as the track: EQ, compressor, reverb, inserts
+        if fx {
+            eq.process_buffer(buf, 2);
+            compressor.process(buf);
+            reverb.process_block(buf, 2);
+            inserts.apply_automation(frame as u64);
+            inserts.process_block(buf, 2);
+        }
+
+        let skip = to_skip.min(frames);
+        to_skip -= skip;
+        for &sample in &buf[skip * 2..] {
+            writer.write_sample(sample)?;
+        }
+        frame += frames;
+    }
+
+    writer.finalize()?;
+    rt_info!("🧊 Frozen {}: {:.2}s -> {}", track.name, Frames((total - latency) as u64).to_seconds(sample_rate).0, path.display());
+    Ok(())
+}
+
+// Same anti-click ramp the track applies at clip edges
+fn apply_edge_fades(samples: &mut [f32], fade: usize) {
+    let frames = samples.len() / 2;
+    if fade == 0 || frames == 0 {
+        return;
+    }
+    for (f, frame) in samples.chunks_exact_mut(2).enumerate() {
+        let rem = frames - 1 - f;
+        let g = (f.min(rem) as f32 / fade as f32).min(1.0);
+        if g < 1.0 {
+            frame.iter_mut().for_each(|s| *s *= g);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+// src/session/history.rs
+
+// Reviewable edit history. Unlike the undo stack this is append-only:
+// undo/redo are logged as entries of their own instead of removing anything.
+// When the project has a location the log is mirrored to `project.log.jsonl`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const LOG_FILE_NAME: &str = "project.log.jsonl";
+/// The log file is rotated to `project.log.1.jsonl` once it grows past this.
+pub const MAX_LOG_FILE_BYTES: u64 = 4 * 1024 * 1024;
+pub const DEFAULT_MAX_ENTRIES: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub action: String,
+    pub details: String,
+    pub track_ids: Vec<u32>,
+}
+
+pub type HistoryListener = Arc<dyn Fn(&HistoryEntry) + Send + Sync>;
+
+pub struct EditHistory {
+    entries: VecDeque<HistoryEntry>,
+    max_entries: usize,
+    log_path: Option<PathBuf>,
+    listener: Option<HistoryListener>,
+    unsent: Vec<HistoryEntry>, // recorded but not yet handed to the listener
+}
+
+impl EditHistory {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            max_entries: max_entries.max(1),
+            log_path: None,
+            listener: None,
+            unsent: Vec::new(),
+        }
+    }
+
+    /// Gets every new entry (e.g. to push it to the UI). `record` doesn't call it: the
+    /// owner collects the entries with `take_unsent` and calls it once its lock is released.
+    pub fn set_listener(&mut self, listener: HistoryListener) {
+        self.listener = Some(listener);
+    }
+
+    /// The listener and the entries it hasn't seen yet, oldest first.
+    pub fn take_unsent(&mut self) -> Option<(HistoryListener, Vec<HistoryEntry>)> {
+        if self.unsent.is_empty() {
+            return None;
+        }
+        let entries = std::mem::take(&mut self.unsent);
+        self.listener.clone().map(|listener| (listener, entries))
+    }
+
+    pub fn record(&mut self, action: &str, details: String, track_ids: Vec<u32>) {
+        let entry = HistoryEntry {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+            action: action.to_string(),
+            details,
+            track_ids,
+        };
+
+        if let Some(path) = &self.log_path {
+            if let Err(e) = append_line(path, &entry) {
+                rt_warn!("⚠️ Could not write edit log {}: {}", path.display(), e);
+            }
+        }
+        if self.listener.is_some() {
+            self.unsent.push(entry.clone());
+        }
+
+        self.entries.push_back(entry);
+        while self.entries.len() > self.max_entries {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Newest first. `offset` skips that many of the most recent entries.
+    pub fn page(&self, limit: usize, offset: usize) -> Vec<HistoryEntry> {
+        self.entries.iter().rev().skip(offset).take(limit).cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Start mirroring to `<project dir>/project.log.jsonl`.
+    /// An existing log is read back; otherwise what has been recorded so far is written out.
+    pub fn attach_to_project(&mut self, manifest_path: &str) {
+        let dir = Path::new(manifest_path).parent().map(Path::to_path_buf).unwrap_or_default();
+        let log_path = dir.join(LOG_FILE_NAME);
+        if self.log_path.as_ref() == Some(&log_path) {
+            return;
+        }
+
+        if log_path.exists() {
+            self.entries = read_tail(&log_path, self.max_entries);
+        } else {
+            for entry in &self.entries {
+                if let Err(e) = append_line(&log_path, entry) {
+                    rt_warn!("⚠️ Could not write edit log {}: {}", log_path.display(), e);
+                    break;
+                }
+            }
+        }
+        self.log_path = Some(log_path);
+    }
+
+    /// Forget everything (new project). The listener stays installed.
+    pub fn reset(&mut self) {
+        self.entries.clear();
+        self.log_path = None;
+    }
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ENTRIES)
+    }
+}
+
+fn append_line(path: &Path, entry: &HistoryEntry) -> std::io::Result<()> {
+    if fs::metadata(path).map(|m| m.len() >= MAX_LOG_FILE_BYTES).unwrap_or(false) {
+        fs::rename(path, path.with_file_name("project.log.1.jsonl"))?;
+    }
+    let line = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+fn read_tail(path: &Path, max: usize) -> VecDeque<HistoryEntry> {
+    let mut entries = VecDeque::new();
+    let Ok(file) = fs::File::open(path) else { return entries };
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        // A torn last line (crash mid-write) is simply skipped
+        if let Ok(entry) = serde_json::from_str::<HistoryEntry>(&line) {
+            entries.push_back(entry);
+            if entries.len() > max {
+                entries.pop_front();
+            }
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn entries_wait_for_the_owner_to_hand_them_to_the_listener() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut history = EditHistory::new(10);
+        let sink = seen.clone();
+        history.set_listener(Arc::new(move |entry: &HistoryEntry| sink.lock().unwrap().push(entry.action.clone())));
+
+        history.record("Move Clip", String::new(), vec![1]);
+        history.record("Split Clip", String::new(), vec![1]);
+        // Recording never calls out: the caller may still hold a lock around the history
+        assert!(seen.lock().unwrap().is_empty());
+
+        let (listener, entries) = history.take_unsent().unwrap();
+        for entry in &entries {
+            listener(entry);
+        }
+        assert_eq!(*seen.lock().unwrap(), vec!["Move Clip", "Split Clip"]);
+        assert!(history.take_unsent().is_none());
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn nothing_is_queued_without_a_listener() {
+        let mut history = EditHistory::new(10);
+        history.record("Move Clip", String::new(), Vec::new());
+        assert!(history.take_unsent().is_none());
+        history.set_listener(Arc::new(|_: &HistoryEntry| {}));
+        assert!(history.take_unsent().is_none());
+    }
+}
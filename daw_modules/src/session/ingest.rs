@@ -0,0 +1,259 @@
+// src/session/ingest.rs
+
+// Folder ingest for field recorders: scan a card dump for audio, work out when each file
+// started, copy everything into the project's audio folder under a naming template, and
+// group the files into tracks (one per recorder channel: "ZOOM0001_Tr1", "ZOOM0002_Tr1"
+// share the "Tr1" track). Clip positions are the start times relative to the earliest
+// file, so devices that recorded at the same moment line up.
+//
+// Start times come from the BWF `bext` stamp. If any file lacks one, every file falls back
+// to its modification time (minus its length where known, since recorders write the mtime
+// when a file is closed): mixing the recorder's local clock with filesystem UTC would
+// misplace clips.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
+
+use super::bwf;
+
+pub const DEFAULT_NAME_TEMPLATE: &str = "{group}_{name}";
+
+const AUDIO_EXTENSIONS: &[&str] = &["wav", "bwf", "flac", "aif", "aiff", "mp3", "ogg", "m4a"];
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct IngestOptions {
+    /// The project's audio folder; created if needed.
+    pub dest_dir: String,
+    /// File name for each copy, without the extension. Placeholders: `{group}` (track),
+    /// `{name}` (original file name), `{index}` (001, 002... in recording order).
+    pub name_template: String,
+}
+
+impl Default for IngestOptions {
+    fn default() -> Self {
+        Self { dest_dir: String::new(), name_template: DEFAULT_NAME_TEMPLATE.to_string() }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TimestampSource {
+    Bext,
+    Mtime,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestFile {
+    pub source: String,
+    pub dest: String,
+    /// Track this file lands on.
+    pub group: String,
+    /// Timeline position: start relative to the earliest file.
+    pub offset_secs: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct IngestPlan {
+    /// In recording order.
+    pub files: Vec<IngestFile>,
+    pub timestamps: TimestampSource,
+    /// Audio files that could not be read, with the reason.
+    pub skipped: Vec<String>,
+}
+
+impl IngestPlan {
+    /// Files per track, tracks sorted by name, clips in recording order.
+    pub fn groups(&self) -> BTreeMap<&str, Vec<&IngestFile>> {
+        let mut groups: BTreeMap<&str, Vec<&IngestFile>> = BTreeMap::new();
+        for file in &self.files {
+            groups.entry(file.group.as_str()).or_default().push(file);
+        }
+        groups
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestedTrack {
+    pub track_id: u32,
+    pub name: String,
+    pub clips: usize,
+}
+
+/// Summary of a finished (or cancelled) ingest.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestResult {
+    pub files: usize,
+    pub tracks: Vec<IngestedTrack>,
+    pub timestamps: TimestampSource,
+    pub skipped: Vec<String>,
+    /// Nothing was copied or placed.
+    pub cancelled: bool,
+}
+
+struct Scanned {
+    path: PathBuf,
+    bext_start: Option<f64>,
+    mtime_start: f64,
+}
+
+/// Scan `folder` (recursively) and decide where everything goes. Touches nothing.
+pub fn plan_ingest(folder: &Path, options: &IngestOptions) -> Result<IngestPlan> {
+    if !folder.is_dir() {
+        bail!("{} is not a folder", folder.display());
+    }
+    let mut skipped = Vec::new();
+    let mut scanned = Vec::new();
+    for path in find_audio_files(folder) {
+        match scan_file(&path) {
+            Ok(s) => scanned.push(s),
+            Err(e) => skipped.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    if scanned.is_empty() {
+        bail!("No audio files found in {}", folder.display());
+    }
+
+    let timestamps = if scanned.iter().all(|s| s.bext_start.is_some()) { TimestampSource::Bext } else { TimestampSource::Mtime };
+    let start_of = |s: &Scanned| match timestamps {
+        TimestampSource::Bext => s.bext_start.unwrap_or(s.mtime_start),
+        TimestampSource::Mtime => s.mtime_start,
+    };
+    scanned.sort_by(|a, b| start_of(a).total_cmp(&start_of(b)).then_with(|| a.path.cmp(&b.path)));
+    let earliest = scanned.first().map(start_of).unwrap_or(0.0);
+
+    let mut taken = HashSet::new();
+    let files = scanned
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let stem = s.path.file_stem().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let ext = s.path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_else(|| "wav".into());
+            let group = channel_group(&stem);
+            let name = options
+                .name_template
+                .replace("{group}", &group)
+                .replace("{name}", &stem)
+                .replace("{index}", &format!("{:03}", i + 1));
+            IngestFile {
+                source: s.path.to_string_lossy().into_owned(),
+                dest: unique_dest(Path::new(&options.dest_dir), &sanitize(&name), &ext, &mut taken),
+                group,
+                offset_secs: (start_of(s) - earliest).max(0.0),
+            }
+        })
+        .collect();
+
+    Ok(IngestPlan { files, timestamps, skipped })
+}
+
+/// Copy every file of the plan, calling `on_progress(done, total, source)` after each.
+/// On error or cancel the copies made so far are removed again; returns false when cancelled.
+pub fn copy_files(
+    plan: &IngestPlan,
+    dest_dir: &Path,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(usize, usize, &str),
+) -> Result<bool> {
+    std::fs::create_dir_all(dest_dir)?;
+    let mut copied: Vec<&str> = Vec::with_capacity(plan.files.len());
+    let rollback = |copied: &[&str]| {
+        for dest in copied {
+            let _ = std::fs::remove_file(dest);
+        }
+    };
+
+    for (i, file) in plan.files.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            rollback(&copied);
+            return Ok(false);
+        }
+        if let Err(e) = std::fs::copy(&file.source, &file.dest) {
+            let _ = std::fs::remove_file(&file.dest);
+            rollback(&copied);
+            bail!("Copying {} failed: {}", file.source, e);
+        }
+        copied.push(&file.dest);
+        on_progress(i + 1, plan.files.len(), &file.source);
+    }
+    Ok(true)
+}
+
+fn scan_file(path: &Path) -> Result<Scanned> {
+    let meta = std::fs::metadata(path)?;
+    let mtime = meta.modified()?.duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or(0.0);
+    let is_wav = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("wav") || e.eq_ignore_ascii_case("bwf"));
+    let info = if is_wav { Some(bwf::read_wav_info(path)?) } else { None };
+
+    let length = info.as_ref().and_then(bwf::WavInfo::duration_secs).unwrap_or(0.0);
+    Ok(Scanned {
+        path: path.to_path_buf(),
+        bext_start: info.as_ref().and_then(bwf::WavInfo::recorded_at),
+        mtime_start: mtime - length,
+    })
+}
+
+// Audio files below `dir`, skipping hidden files (and the "._" forks macOS leaves on cards)
+fn find_audio_files(dir: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(folder) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&folder) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let Ok(kind) = entry.file_type() else { continue };
+            if kind.is_dir() {
+                stack.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|e| AUDIO_EXTENSIONS.iter().any(|a| e.eq_ignore_ascii_case(a)))
+            {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+// "ZOOM0001_Tr1" -> "Tr1", "ZOOM0001_LR" -> "LR"; without a channel suffix the take
+// number is dropped ("Take_003" -> "Take", "DR0042" -> "DR")
+fn channel_group(stem: &str) -> String {
+    let suffix = stem.rsplit_once(['_', '-']).map(|(_, s)| s).filter(|s| s.chars().any(char::is_alphabetic));
+    if let Some(suffix) = suffix {
+        return suffix.to_string();
+    }
+    let base = stem.trim_end_matches(|c: char| c.is_ascii_digit() || matches!(c, '_' | '-' | ' '));
+    if base.is_empty() { stem.to_string() } else { base.to_string() }
+}
+
+fn sanitize(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.' | '(' | ')') { c } else { '_' })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.');
+    if cleaned.is_empty() { "Take".to_string() } else { cleaned.to_string() }
+}
+
+// `{stem}.{ext}`, or `{stem} (2).{ext}`... avoiding both earlier files of this ingest and
+// anything already in the folder
+fn unique_dest(dir: &Path, stem: &str, ext: &str, taken: &mut HashSet<String>) -> String {
+    let mut candidate = format!("{}.{}", stem, ext);
+    let mut n = 2;
+    while dir.join(&candidate).exists() || !taken.insert(candidate.to_lowercase()) {
+        candidate = format!("{} ({}).{}", stem, n, ext);
+        n += 1;
+    }
+    dir.join(candidate).to_string_lossy().into_owned()
+}
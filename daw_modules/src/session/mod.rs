@@ -3,138 +3,306 @@
 pub mod commands;
 pub mod serialization; // <--- ADD THIS
 pub mod export;
+pub mod history;
+pub mod bounce;
+pub mod dither;
+pub mod multitrack;
+pub mod stats;
+pub mod bwf;
+pub mod ingest;
+pub mod recovery;
+pub mod freeze;
 
+use crate::decoder::stretch::{MAX_STRETCH, MIN_STRETCH};
 use crate::engine::Engine;
+use crate::engine::handle::EngineHandle;
+use crate::engine::time::Frames;
+use crate::engine::track::Track;
 use commands::{Command, CommandManager};
-use serialization::{ProjectManifest, TrackState, ClipState}; // <--- USE THIS
-use std::sync::{Arc, Mutex};
+use history::EditHistory;
+use serialization::{AudioPrefs, AuxBusState, GroupState, MarkerState, ProjectManifest, SendState, TrackState, ClipState}; // <--- USE THIS
 use anyhow::Result;
 
+/// Coalesced edits further apart than this start a new undo step.
+pub const EDIT_SESSION_WINDOW: std::time::Duration = std::time::Duration::from_secs(5);
+
 pub struct Session {
     pub command_manager: CommandManager,
+    pub history: EditHistory,
+    /// Rotating `project.json.N` backups kept on save.
+    pub backup_count: usize,
 }
 
 impl Session {
     pub fn new() -> Self {
         Self {
             command_manager: CommandManager::new(100),
+            history: EditHistory::default(),
+            backup_count: serialization::DEFAULT_BACKUP_COUNT,
         }
     }
 
-    pub fn apply(&mut self, engine: &Arc<Mutex<Engine>>, cmd: Box<dyn Command>) -> Result<()> {
-        let mut guard = engine.lock().unwrap();
-        self.command_manager.push(cmd, &mut guard)
+    pub fn apply(&mut self, engine: &EngineHandle, cmd: Box<dyn Command>) -> Result<()> {
+        let (action, details, ids) = describe(cmd.as_ref());
+        self.command_manager.push(cmd, engine)?;
+        self.history.record(&action, details, ids);
+        Ok(())
     }
 
-    pub fn undo(&mut self, engine: &Arc<Mutex<Engine>>) -> Result<bool> {
-        let mut guard = engine.lock().unwrap();
-        self.command_manager.undo(&mut guard)
+    /// `apply` for commands that merge into one undo step while the user keeps editing
+    /// (see `Command::coalesce_key`). Only the first edit of a step is logged.
+    pub fn apply_coalesced(&mut self, engine: &EngineHandle, cmd: Box<dyn Command>) -> Result<()> {
+        let (action, details, ids) = describe(cmd.as_ref());
+        let joined = self.command_manager.push_coalesced(cmd, engine, EDIT_SESSION_WINDOW)?;
+        if !joined {
+            self.history.record(&action, details, ids);
+        }
+        Ok(())
     }
 
-    pub fn redo(&mut self, engine: &Arc<Mutex<Engine>>) -> Result<bool> {
-        let mut guard = engine.lock().unwrap();
-        self.command_manager.redo(&mut guard)
+    pub fn undo(&mut self, engine: &EngineHandle) -> Result<bool> {
+        let entry = self.command_manager.peek_undo().map(describe);
+        let undone = self.command_manager.undo(engine)?;
+        if let (true, Some((action, details, ids))) = (undone, entry) {
+            self.history.record(&format!("Undo {}", action), details, ids);
+        }
+        Ok(undone)
+    }
+
+    pub fn redo(&mut self, engine: &EngineHandle) -> Result<bool> {
+        let entry = self.command_manager.peek_redo().map(describe);
+        let redone = self.command_manager.redo(engine)?;
+        if let (true, Some((action, details, ids))) = (redone, entry) {
+            self.history.record(&format!("Redo {}", action), details, ids);
+        }
+        Ok(redone)
     }
 
     // --- SAVE / LOAD IMPLEMENTATION ---
 
-    pub fn save_project(&self, engine: &Arc<Mutex<Engine>>, path: &str, master_gain: f32) -> Result<()> {
-        let eng = engine.lock().unwrap();
-
-        // 1. Gather state from Engine tracks
-        let tracks: Vec<TrackState> = eng.tracks().iter().map(|t| {
-            // FIX: Use a code block { } to define variables before returning the struct
-            let clips = t.clips.iter().map(|c| ClipState {
-                path: c.path.clone(), 
-                start_time: c.start_time.as_secs_f64(),
-                offset: c.offset.as_secs_f64(),
-                duration: c.duration.as_secs_f64(),
-            }).collect();
-
-            // Return the struct at the end of the block
-            TrackState {
-                name: t.name.clone(),
-                color: t.color.clone(), 
-                gain: t.gain,
-                pan: t.pan,
-                muted: t.muted,
-                solo: t.solo,
-                clips,
-                volume_automation: t.volume_automation.clone(),
-                compressor: Some(t.track_compressor.get_params()),
-                eq: Some(t.track_eq.get_state()),
-                reverb: Some(t.track_reverb.get_params()),
-            }    
-        }).collect();
-
-        // 2. Create Manifest
-        let manifest = ProjectManifest {
-            version: 1,
-            master_gain,
-            bpm: eng.transport.tempo.bpm as f32,
-            tracks,
-        };
-
-        // 3. Write to disk
-        manifest.save_to_disk(path)?;
+    pub fn save_project(&mut self, engine: &EngineHandle, path: &str, master_gain: f32, audio_prefs: Option<AudioPrefs>) -> Result<()> {
+        let manifest = engine.with(move |eng| capture_manifest(eng, master_gain, audio_prefs))?;
+        manifest.save_to_disk_with_backups(path, self.backup_count)?;
+        self.history.attach_to_project(path);
         Ok(())
     }
 
-    pub fn load_project(&mut self, engine: &Arc<Mutex<Engine>>, path: &str) -> Result<f32> {
+    pub fn load_project(&mut self, engine: &EngineHandle, path: &str) -> Result<f32> {
         let manifest = ProjectManifest::load_from_disk(path)?;
-        let mut eng = engine.lock().unwrap();
+        let master_gain = self.load_manifest(engine, manifest)?;
+        self.history.attach_to_project(path);
+        Ok(master_gain)
+    }
 
-        eng.clear_tracks();
-        eng.transport.tempo.bpm = manifest.bpm as f64;
+    /// Replace the engine contents with an already-parsed manifest. Returns the master gain.
+    /// The engine leaves the audio callback while the tracks are rebuilt.
+    pub fn load_manifest(&mut self, engine: &EngineHandle, manifest: ProjectManifest) -> Result<f32> {
+        let master_gain = engine.with_detached(|eng| replay_manifest(eng, manifest))?;
         self.command_manager = CommandManager::new(100);
+        self.history.reset();
+        Ok(master_gain)
+    }
+}
 
-        // FIX: Capture these values BEFORE the loop starts
-        let sample_rate = eng.sample_rate;
-        let channels = eng.channels;
-
-        for t_state in manifest.tracks {
-            let id = eng.add_empty_track();
-            
-            if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id == id) {
-                track.name = t_state.name;
-                track.color = t_state.color;
-                track.gain = t_state.gain;
-                track.pan = t_state.pan;
-                track.muted = t_state.muted;
-                track.solo = t_state.solo;
-
-                if let Some(comp_params) = t_state.compressor {
-                    track.track_compressor.set_params(comp_params);
-                }
+/// The project as saved: every track with its clips and settings.
+pub fn capture_manifest(eng: &Engine, master_gain: f32, audio_prefs: Option<AudioPrefs>) -> ProjectManifest {
+    // 1. Gather state from Engine tracks
+    let tracks: Vec<TrackState> = eng.tracks().iter().map(capture_track).collect();
 
-                if let Some(eq_state) = t_state.eq {
-                    track.track_eq.set_state(eq_state);
-                }
+    // 2. Create Manifest
+    ProjectManifest {
+        version: 1,
+        master_gain,
+        bpm: eng.transport.tempo.bpm as f32,
+        tracks,
+        audio_prefs,
+        solo_policy: eng.solo_policy,
+        solo_mode: eng.solo_mode,
+        arm_exclusive: eng.arm_exclusive,
+        return_to_start_on_stop: eng.return_to_start_on_stop,
+        preroll: eng.preroll,
+        end_action: eng.end_action,
+        master_pitch_shift: eng.master_pitch_shift(),
+        master_soft_clip: eng.master_soft_clip(),
+        master_limiter: eng.master_limiter(),
+        time_signature: eng.transport.tempo.signature,
+        meter_changes: eng.transport.tempo.meter_changes().to_vec(),
+        markers: eng
+            .markers
+            .iter()
+            .map(|m| MarkerState { name: m.name.clone(), color: m.color.clone(), time: m.time.as_secs_f64() })
+            .collect(),
+        aux_buses: eng.aux_buses().iter().map(AuxBusState::from_bus).collect(),
+        groups: eng.groups().iter().map(GroupState::from_group).collect(),
+    }
+}
 
-                if let Some(rev_params) = t_state.reverb {
-                    track.track_reverb.set_params(rev_params);
-                }
-                
-                for clip_state in t_state.clips {
-                    let start = std::time::Duration::from_secs_f64(clip_state.start_time);
-                    let offset = std::time::Duration::from_secs_f64(clip_state.offset);
-                    let duration = std::time::Duration::from_secs_f64(clip_state.duration);
-                    
-                    // FIX: Use restore_clip instead of add_clip.
-                    // This ensures we respect the saved Offset and Duration (Split/Trim data).
-                    let _ = track.restore_clip(
-                        track.clips.len(), // Append to the end
-                        clip_state.path, 
-                        start, 
-                        offset,
-                        duration,
-                        sample_rate, 
-                        channels
-                    );
+/// Rebuild the engine's tracks from a manifest (undo history untouched). Returns the master gain.
+pub fn replay_manifest(eng: &mut Engine, manifest: ProjectManifest) -> f32 {
+    eng.clear_tracks();
+    eng.markers.clear();
+    eng.transport.tempo.bpm = manifest.bpm as f64;
+    if manifest.time_signature.is_valid() {
+        eng.transport.tempo.signature = manifest.time_signature;
+    }
+    eng.transport.tempo.set_meter_changes(&manifest.meter_changes);
+    for m in manifest.markers {
+        let time = crate::engine::time::Seconds(m.time).to_duration();
+        eng.markers.add(time, m.name, m.color);
+    }
+    eng.solo_policy = manifest.solo_policy;
+    eng.solo_mode = manifest.solo_mode;
+    eng.arm_exclusive = manifest.arm_exclusive;
+    eng.return_to_start_on_stop = manifest.return_to_start_on_stop;
+    eng.preroll = manifest.preroll;
+    eng.end_action = manifest.end_action;
+    eng.set_master_pitch_shift(manifest.master_pitch_shift);
+    eng.set_master_soft_clip(manifest.master_soft_clip);
+    eng.set_master_limiter(manifest.master_limiter);
+    eng.clear_aux_buses();
+    for bus in &manifest.aux_buses {
+        if let Err(e) = eng.insert_aux_bus(bus.to_bus(eng.channels, eng.sample_rate)) {
+            rt_warn!("⚠️ Aux bus {} not restored: {}", bus.name, e);
+        }
+    }
+    eng.clear_groups();
+    for group in &manifest.groups {
+        if let Err(e) = eng.insert_group(group.to_group(eng.channels, eng.sample_rate)) {
+            rt_warn!("⚠️ Group {} not restored: {}", group.name, e);
+        }
+    }
+
+    // FIX: Capture these values BEFORE the loop starts
+    let sample_rate = eng.sample_rate;
+    let channels = eng.channels;
+
+    for t_state in manifest.tracks {
+        let id = eng.add_empty_track();
+        if let Some(track) = eng.tracks_mut().iter_mut().find(|t| t.id == id) {
+            restore_track(track, t_state, sample_rate, channels);
+        }
+    }
+
+    manifest.master_gain
+}
+
+/// One track's saved state: clips, mix settings and inserts.
+pub fn capture_track(t: &Track) -> TrackState {
+    let clips = t.clips.iter().map(|c| ClipState {
+        path: c.path.clone(), 
+        start_time: c.start().as_secs_f64(),
+        offset: c.offset_time().as_secs_f64(),
+        duration: c.length().as_secs_f64(),
+        notes: c.notes.clone(),
+        mute_regions: c.mute_regions.clone(),
+        stretch: c.stretch(),
+        varispeed: c.varispeed(),
+        crossfade: c.crossfade.map(|d| d.as_secs_f64()),
+    }).collect();
+
+    // Return the struct at the end of the block
+    TrackState {
+        name: t.name.clone(),
+        color: t.color.clone(), 
+        gain: t.gain,
+        pan: t.pan,
+        muted: t.muted,
+        solo: t.solo,
+        clips,
+        volume_automation: t.volume_automation.clone(),
+        compressor: Some(t.track_compressor.get_params()),
+        eq: Some(t.track_eq.get_state()),
+        reverb: Some(t.track_reverb.get_params()),
+        record_safe: t.record_safe,
+        record_inputs: t.record_inputs.clone(),
+        record_device: t.record_device.clone(),
+        solo_safe: t.solo_safe,
+        notes: t.notes.clone(),
+        fx_bypass: t.fx_bypass,
+        inserts: t.inserts.states(),
+        automation_mode: t.automation_mode,
+        kind: t.kind,
+        kind_manual: t.kind_manual,
+        sends: t.sends.iter().map(|s| SendState { bus_id: s.bus_id, level: s.level }).collect(),
+        group: t.group,
+    }
+}
+
+/// Apply a saved state to a freshly created track (project load, undoing a track delete).
+pub fn restore_track(track: &mut Track, t_state: TrackState, sample_rate: u32, channels: usize) {
+    track.name = t_state.name;
+    track.color = t_state.color;
+    track.gain = t_state.gain;
+    track.pan = t_state.pan;
+    track.muted = t_state.muted;
+    track.solo = t_state.solo;
+    track.record_safe = t_state.record_safe;
+    track.record_inputs = t_state.record_inputs;
+    track.record_device = t_state.record_device;
+    track.solo_safe = t_state.solo_safe;
+    track.notes = t_state.notes;
+    track.fx_bypass = t_state.fx_bypass;
+    track.kind = t_state.kind;
+    track.kind_manual = t_state.kind_manual;
+    track.sends = t_state
+        .sends
+        .iter()
+        .map(|s| crate::engine::mixer::AuxSend::new(s.bus_id, s.level.clamp(0.0, 2.0)))
+        .collect();
+    track.group = t_state.group;
+
+    if let Some(comp_params) = t_state.compressor {
+        track.track_compressor.set_params(comp_params);
+    }
+
+    if let Some(eq_state) = t_state.eq {
+        track.track_eq.set_state(eq_state);
+    }
+
+    if let Some(rev_params) = t_state.reverb {
+        track.track_reverb.set_params(rev_params);
+    }
+    track.inserts.set_states(&t_state.inserts);
+    track.automation_mode = t_state.automation_mode;
+    
+    for clip_state in t_state.clips {
+        let start = std::time::Duration::from_secs_f64(clip_state.start_time);
+        let offset = std::time::Duration::from_secs_f64(clip_state.offset);
+        let duration = std::time::Duration::from_secs_f64(clip_state.duration);
+        
+        // FIX: Use restore_clip instead of add_clip.
+        // This ensures we respect the saved Offset and Duration (Split/Trim data).
+        let restored = track.restore_clip(
+            track.clips.len(), // Append to the end
+            clip_state.path.clone(), 
+            start, 
+            offset,
+            duration,
+            sample_rate, 
+            channels
+        );
+        let annotated = !clip_state.notes.is_empty() || !clip_state.mute_regions.is_empty();
+        if restored.is_ok() && (annotated || clip_state.stretch != 1.0 || clip_state.varispeed || clip_state.crossfade.is_some()) {
+            // restore_clip re-sorts, so find the clip again by position
+            if let Some(clip) = track.clips.iter_mut().find(|c| c.path == clip_state.path && c.start_time == Frames::from_duration(start, c.frame_rate())) {
+                clip.notes = clip_state.notes;
+                // Hand-edited files may hold overlapping or out-of-order regions
+                let mut regions = clip_state.mute_regions;
+                crate::engine::mute_regions::normalize(&mut regions);
+                clip.mute_regions = regions;
+                clip.crossfade = clip_state.crossfade.map(|secs| std::time::Duration::from_secs_f64(secs.max(0.0)));
+                if clip_state.stretch != 1.0 || clip_state.varispeed {
+                    let stretch = clip_state.stretch.clamp(MIN_STRETCH, MAX_STRETCH);
+                    clip.set_stretch(stretch);
+                    clip.set_varispeed(clip_state.varispeed);
+                    clip.seek(start);
                 }
             }
         }
-
-        Ok(manifest.master_gain)
     }
-}
\ No newline at end of file
+}
+
+/// (action, details, track ids) of a command, for the edit history.
+fn describe(cmd: &dyn Command) -> (String, String, Vec<u32>) {
+    (cmd.name().to_string(), cmd.details(), cmd.track_ids().iter().map(|id| id.0).collect())
+}
@@ -0,0 +1,187 @@
+// src/session/multitrack.rs
+
+// Multitrack print: every track's post-fader output written to its own file during one
+// realtime pass, alongside the master (a regular realtime bounce). Each track has its own
+// tap and writer thread; a writer that falls behind only loses samples from its own file
+// (counted per track), the audio thread never waits on disk.
+
+use anyhow::{anyhow, Result};
+use ringbuf::HeapCons;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use super::bounce::{self, BounceOptions, BounceResult, RealtimeBounce};
+
+/// The master's file in the capture folder (tracks get their own names).
+pub const MASTER_FILE_STEM: &str = "Master";
+
+/// Reading end of one track's installed tap.
+pub struct TrackFeed {
+    pub track_id: u32,
+    pub name: String,
+    pub consumer: HeapCons<f32>,
+    pub dropped: Arc<AtomicU64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackPrintResult {
+    pub track_id: u32,
+    pub name: String,
+    pub path: String,
+    pub peak_db: f32,
+    /// Samples lost because this track's writer fell behind (should be 0).
+    pub dropped_samples: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MultitrackResult {
+    pub dir: String,
+    pub master: BounceResult,
+    pub tracks: Vec<TrackPrintResult>,
+}
+
+struct TrackWriter {
+    track_id: u32,
+    name: String,
+    path: String,
+    dropped: Arc<AtomicU64>,
+    handle: thread::JoinHandle<Result<f32>>,
+}
+
+pub struct MultitrackCapture {
+    dir: String,
+    stop: Arc<AtomicBool>,
+    master: RealtimeBounce,
+    writers: Vec<TrackWriter>,
+}
+
+impl MultitrackCapture {
+    /// Spawn one writer per track plus the master bounce, all into `dir`.
+    /// `master_consumer`/`master_dropped` come from an installed `MasterTap`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start(
+        dir: String,
+        sample_rate: u32,
+        channels: usize,
+        options: BounceOptions,
+        max_frames: Option<u64>,
+        master_consumer: HeapCons<f32>,
+        master_dropped: Arc<AtomicU64>,
+        feeds: Vec<TrackFeed>,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let mut taken = HashSet::new();
+        let master_path = unique_path(&dir, MASTER_FILE_STEM, &mut taken);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut writers = Vec::with_capacity(feeds.len());
+        let started = feeds.into_iter().try_for_each(|feed| -> Result<()> {
+            let path = unique_path(&dir, &file_stem(&feed.name, feed.track_id), &mut taken);
+            let writer = bounce::create_writer(&path, sample_rate, channels, options.format)?;
+            let stop_w = stop.clone();
+            let format = options.format;
+            let consumer = feed.consumer;
+            let handle = thread::Builder::new()
+                .name(format!("multitrack-{}", feed.track_id))
+                .spawn(move || bounce::write_loop(writer, consumer, &stop_w, max_frames, channels, format, None))?;
+            writers.push(TrackWriter { track_id: feed.track_id, name: feed.name, path, dropped: feed.dropped, handle });
+            Ok(())
+        });
+        let master = started.and_then(|_| {
+            RealtimeBounce::start(master_path, sample_rate, channels, options, max_frames, master_consumer, master_dropped)
+        });
+        let master = match master {
+            Ok(master) => master,
+            Err(e) => {
+                // Nothing is feeding the writers yet; let them finalize what they opened
+                stop.store(true, Ordering::Release);
+                for w in writers {
+                    let _ = w.handle.join();
+                }
+                return Err(e);
+            }
+        };
+
+        rt_info!("🎚️ Multitrack capture started: {} tracks into {}", writers.len(), dir);
+        Ok(Self { dir, stop, master, writers })
+    }
+
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    pub fn track_count(&self) -> usize {
+        self.writers.len()
+    }
+
+    /// True once the master stopped by itself (project end); the tracks stop on the same frame.
+    pub fn is_finished(&self) -> bool {
+        self.master.is_finished()
+    }
+
+    /// Drain every writer, finalize all files and report on them.
+    pub fn stop(self) -> Result<MultitrackResult> {
+        self.stop.store(true, Ordering::Release);
+        let master = self.master.stop();
+
+        let mut tracks = Vec::with_capacity(self.writers.len());
+        let mut failed = None;
+        for w in self.writers {
+            let peak = match w.handle.join() {
+                Ok(Ok(peak)) => peak,
+                Ok(Err(e)) => {
+                    failed.get_or_insert_with(|| anyhow!("Writing {} failed: {}", w.path, e));
+                    continue;
+                }
+                Err(_) => {
+                    failed.get_or_insert_with(|| anyhow!("Writer for {} panicked", w.path));
+                    continue;
+                }
+            };
+            let dropped_samples = w.dropped.load(Ordering::Relaxed);
+            if dropped_samples > 0 {
+                rt_warn!("⚠️ Multitrack print of '{}' dropped {} samples", w.name, dropped_samples);
+            }
+            tracks.push(TrackPrintResult {
+                track_id: w.track_id,
+                name: w.name,
+                path: w.path,
+                peak_db: if peak > 0.0 { 20.0 * peak.log10() } else { f32::NEG_INFINITY },
+                dropped_samples,
+            });
+        }
+
+        let master = master?;
+        if let Some(e) = failed {
+            return Err(e);
+        }
+        Ok(MultitrackResult { dir: self.dir, master, tracks })
+    }
+}
+
+// Track name made safe for a file name; unnamed tracks fall back to their id
+fn file_stem(name: &str, track_id: u32) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.') { c } else { '_' })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.');
+    if cleaned.is_empty() { format!("Track {}", track_id + 1) } else { cleaned.to_string() }
+}
+
+// `{stem}.wav`, or `{stem} (2).wav`... when two tracks share a name
+fn unique_path(dir: &str, stem: &str, taken: &mut HashSet<String>) -> String {
+    let mut candidate = stem.to_string();
+    let mut n = 2;
+    while !taken.insert(candidate.to_lowercase()) {
+        candidate = format!("{} ({})", stem, n);
+        n += 1;
+    }
+    Path::new(dir).join(format!("{}.wav", candidate)).to_string_lossy().into_owned()
+}
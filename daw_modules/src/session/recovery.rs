@@ -0,0 +1,81 @@
+// src/session/recovery.rs
+
+// What it takes to rebuild the engine from scratch when it gets into a bad state (a lock
+// poisoned by a panicking command, a callback that stopped running). The project manifest
+// covers tracks, clips and markers; on top of it the snapshot keeps what a save doesn't:
+// track and marker ids (the frontend and the undo stack refer to both by id), output
+// routing, the metronome and the playhead.
+
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::engine::markers::Markers;
+use crate::engine::metronome::MetronomeConfig;
+use crate::engine::output_routing::BusRoute;
+use crate::engine::Engine;
+use super::serialization::ProjectManifest;
+
+/// Where the state of a rebuilt engine came from.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SnapshotSource {
+    /// Read from the old engine, which was still reachable.
+    Live,
+    /// Read through a poisoned lock; an edit interrupted by the panic may be half applied.
+    Poisoned,
+    /// The old engine was stuck; the last periodic checkpoint was used.
+    Checkpoint,
+    /// Nothing to restore from; the engine starts empty.
+    Empty,
+}
+
+#[derive(Clone)]
+pub struct RecoverySnapshot {
+    manifest: ProjectManifest,
+    track_ids: Vec<u32>,
+    markers: Markers,
+    routes: Vec<BusRoute>,
+    metronome: MetronomeConfig,
+    pub position: Duration,
+}
+
+impl RecoverySnapshot {
+    pub fn capture(eng: &Engine, master_gain: f32) -> Self {
+        Self {
+            manifest: super::capture_manifest(eng, master_gain, None),
+            track_ids: eng.tracks().iter().map(|t| t.id.0).collect(),
+            markers: eng.markers.clone(),
+            routes: eng.output_routing.routes().to_vec(),
+            metronome: eng.metronome_config(),
+            position: eng.transport.time(),
+        }
+    }
+
+    pub fn track_count(&self) -> usize {
+        self.track_ids.len()
+    }
+
+    /// Replay into a fresh engine (already at the device's rate), paused at the saved
+    /// position. Returns the master gain.
+    pub fn restore(self, eng: &mut Engine) -> f32 {
+        let master_gain = super::replay_manifest(eng, self.manifest);
+        eng.reassign_track_ids(&self.track_ids);
+        eng.markers = self.markers;
+        for e in eng.restore_output_routing(&self.routes) {
+            rt_warn!("⚠️ Output route not restored after rebuild: {}", e);
+        }
+        eng.set_metronome_config(self.metronome);
+        eng.seek(self.position);
+        master_gain
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryReport {
+    pub source: SnapshotSource,
+    pub tracks: usize,
+    pub position_secs: f64,
+    /// False when the output device could not be reopened (retry with a device reload).
+    pub stream_running: bool,
+}
@@ -1,12 +1,24 @@
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
-use anyhow::Result;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use anyhow::{anyhow, Result};
 
 use crate::engine::automation::AutomationCurve;
+use crate::engine::crossfades::Span;
+use crate::engine::mixer::{AuxBus, BusEffectParams, GroupBus};
+use crate::engine::mute_regions::MuteRegion;
+use crate::engine::{EndAction, PreRoll, SoloMode, SoloPolicy};
+use crate::engine::time::{MeterChange, TimeSignature};
 use crate::effects::compressor::CompressorParams;
 use crate::effects::equalizer::EqParams;
 use crate::effects::reverb::ReverbParams;
+use crate::effects::limiter::LimiterParams;
+use crate::effects::pitch_shift::PitchShiftParams;
+use crate::effects::soft_clip::SoftClipParams;
 
 // Represents a single audio clip within a track
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -15,9 +27,101 @@ pub struct ClipState {
     pub start_time: f64,    // Position on timeline (seconds)
     pub offset: f64,        // Start offset in the file (trimming)
     pub duration: f64,      // Playback duration (seconds)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mute_regions: Vec<MuteRegion>, // seconds from the clip start
+    // Timeline length / source length (time stretch); offset is in source seconds
+    #[serde(default = "default_stretch", skip_serializing_if = "is_unit_stretch")]
+    pub stretch: f64,
+    // The stretch plays as varispeed (pitch follows speed) rather than a time stretch
+    #[serde(default)]
+    pub varispeed: bool,
+    // Crossfade into the clip this one overlaps, seconds; absent = the whole overlap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crossfade: Option<f64>,
+}
+
+impl ClipState {
+    pub fn span(&self) -> Span {
+        let start = Duration::from_secs_f64(self.start_time.max(0.0));
+        Span {
+            start,
+            end: start + Duration::from_secs_f64(self.duration.max(0.0)),
+            crossfade: self.crossfade.map(|secs| Duration::from_secs_f64(secs.max(0.0))),
+        }
+    }
+}
+
+fn default_stretch() -> f64 {
+    1.0
+}
+
+fn is_unit_stretch(stretch: &f64) -> bool {
+    *stretch == 1.0
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MarkerState {
+    pub name: String,
+    pub color: String,
+    pub time: f64, // seconds
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SendState {
+    pub bus_id: u32,
+    pub level: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuxBusState {
+    pub id: u32, // what the tracks' sends refer to
+    pub name: String,
+    pub gain: f32,
+    pub muted: bool,
+    #[serde(default)]
+    pub effects: Vec<BusEffectParams>,
+}
+
+impl AuxBusState {
+    pub fn from_bus(bus: &AuxBus) -> Self {
+        Self { id: bus.id, name: bus.name.clone(), gain: bus.gain, muted: bus.muted, effects: bus.effects() }
+    }
+
+    pub fn to_bus(&self, channels: usize, sample_rate: u32) -> AuxBus {
+        let mut bus = AuxBus::new(self.id, self.name.clone(), channels, sample_rate);
+        bus.gain = self.gain;
+        bus.muted = self.muted;
+        bus.set_effects(&self.effects);
+        bus
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GroupState {
+    pub id: u32, // what the member tracks' `group` refers to
+    pub name: String,
+    pub gain: f32,
+    pub muted: bool,
+    pub solo: bool,
+}
+
+impl GroupState {
+    pub fn from_group(group: &GroupBus) -> Self {
+        Self { id: group.id, name: group.name.clone(), gain: group.gain, muted: group.muted, solo: group.solo }
+    }
+
+    pub fn to_group(&self, channels: usize, sample_rate: u32) -> GroupBus {
+        let mut group = GroupBus::new(self.id, self.name.clone(), channels, sample_rate);
+        group.gain = self.gain;
+        group.muted = self.muted;
+        group.solo = self.solo;
+        group
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct TrackState {
     pub name: String,
     pub color: String,
@@ -34,26 +138,98 @@ pub struct TrackState {
     #[serde(default)]
     pub eq: Option<Vec<EqParams>>,
     pub reverb: Option<ReverbParams>,
+    #[serde(default)]
+    pub record_safe: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub record_inputs: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_device: Option<String>,
+    #[serde(default)]
+    pub solo_safe: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    #[serde(default)]
+    pub fx_bypass: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inserts: Vec<crate::effects::chain::InsertState>,
+    #[serde(default)]
+    pub automation_mode: crate::engine::automation::AutomationMode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<crate::engine::track::TrackKind>,
+    #[serde(default)]
+    pub kind_manual: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sends: Vec<SendState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<u32>,
 }
 
 fn default_automation() -> AutomationCurve<f32> {
     AutomationCurve::new()
 }
 
-#[derive(Serialize, Deserialize)]
+/// Audio setup the project was made with. Every field is a preference:
+/// on load it is applied only where the current hardware supports it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct AudioPrefs {
+    pub sample_rate: Option<u32>,
+    pub buffer_size: Option<u32>, // frames; None = device default
+    pub output_device: Option<String>,
+    #[serde(default)]
+    pub block_size: Option<u32>, // internal processing quantum in frames; None = follow the device
+}
+
+/// Internal block sizes the engine can process in (see `AudioPrefs::block_size`).
+pub const PROCESSING_BLOCK_SIZES: [u32; 4] = [128, 256, 512, 1024];
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ProjectManifest {
     pub version: u32,
     pub master_gain: f32,
     pub bpm: f32, // <--- NEW: Save the Global Tempo
     pub tracks: Vec<TrackState>,
+    #[serde(default)]
+    pub audio_prefs: Option<AudioPrefs>,
+    #[serde(default)]
+    pub solo_policy: SoloPolicy,
+    #[serde(default)]
+    pub solo_mode: SoloMode,
+    #[serde(default)]
+    pub arm_exclusive: bool,
+    #[serde(default)]
+    pub return_to_start_on_stop: bool,
+    #[serde(default)]
+    pub preroll: PreRoll,
+    #[serde(default)]
+    pub end_action: EndAction,
+    #[serde(default)]
+    pub master_pitch_shift: PitchShiftParams,
+    #[serde(default)]
+    pub master_soft_clip: SoftClipParams,
+    #[serde(default)]
+    pub master_limiter: LimiterParams,
+    #[serde(default)]
+    pub time_signature: TimeSignature,
+    #[serde(default)]
+    pub meter_changes: Vec<MeterChange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<MarkerState>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aux_buses: Vec<AuxBusState>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupState>,
 }
 
 impl ProjectManifest {
     pub fn save_to_disk(&self, path: &str) -> Result<()> {
-        let file = File::create(path)?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, self)?;
-        Ok(())
+        self.save_to_disk_with_backups(path, DEFAULT_BACKUP_COUNT)
+    }
+
+    /// Atomic save (temp file + rename) keeping up to `keep` rotating backups
+    /// (`project.json.1` is the newest). If anything fails the existing file is untouched.
+    pub fn save_to_disk_with_backups(&self, path: &str, keep: usize) -> Result<()> {
+        let bytes = serde_json::to_vec_pretty(self)?;
+        write_with_backups(Path::new(path), &bytes, keep)
     }
 
     pub fn load_from_disk(path: &str) -> Result<Self> {
@@ -62,4 +238,169 @@ impl ProjectManifest {
         let manifest = serde_json::from_reader(reader)?;
         Ok(manifest)
     }
-}
\ No newline at end of file
+}
+
+// --- BACKUPS ---
+
+pub const DEFAULT_BACKUP_COUNT: usize = 5;
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub index: usize,
+    pub path: String,
+    pub size: u64,
+    /// Seconds since the Unix epoch.
+    pub modified: u64,
+}
+
+/// `project.json` -> `project.json.3`
+pub fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut p = path.as_os_str().to_os_string();
+    p.push(format!(".{}", index));
+    PathBuf::from(p)
+}
+
+/// Backups that exist for `path`, newest first.
+pub fn list_backups(path: &str) -> Vec<BackupInfo> {
+    let path = Path::new(path);
+    (1..)
+        .map(|i| (i, backup_path(path, i)))
+        .take_while(|(_, p)| p.exists())
+        .filter_map(|(index, p)| {
+            let meta = std::fs::metadata(&p).ok()?;
+            let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+            Some(BackupInfo { index, path: p.to_string_lossy().to_string(), size: meta.len(), modified })
+        })
+        .collect()
+}
+
+/// Make backup `index` the current project. The file being replaced is itself backed up,
+/// so a restore can be undone by restoring `.1`.
+pub fn restore_backup(path: &str, index: usize, keep: usize) -> Result<()> {
+    let backup = backup_path(Path::new(path), index);
+    let bytes = std::fs::read(&backup).map_err(|e| anyhow!("Backup {} unavailable: {}", backup.display(), e))?;
+    // Never restore something we couldn't load
+    serde_json::from_slice::<ProjectManifest>(&bytes)
+        .map_err(|e| anyhow!("Backup {} is not a valid project: {}", backup.display(), e))?;
+    write_with_backups(Path::new(path), &bytes, keep.max(1))
+}
+
+fn content_hash(bytes: &[u8]) -> u64 {
+    let mut h = DefaultHasher::new();
+    bytes.hash(&mut h);
+    h.finish()
+}
+
+fn write_with_backups(path: &Path, bytes: &[u8], keep: usize) -> Result<()> {
+    // A save that changes nothing leaves the file and its backups alone
+    if std::fs::read(path).is_ok_and(|current| current == bytes) {
+        return Ok(());
+    }
+
+    // 1. New content goes to a temp file first; a failure here leaves everything as it was
+    let mut tmp = path.as_os_str().to_os_string();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    {
+        let mut file = File::create(&tmp)?;
+        if let Err(e) = file.write_all(bytes).and_then(|_| file.sync_all()) {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+    }
+
+    // 2. Back up the file we are about to replace
+    if keep > 0 {
+        if let Err(e) = rotate_backups(path, keep) {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e);
+        }
+    }
+
+    // 3. Atomic swap
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// Skipped when the newest backup already holds the current content (no-op saves)
+fn rotate_backups(path: &Path, keep: usize) -> Result<()> {
+    let Ok(current) = std::fs::read(path) else { return Ok(()) };
+    let newest = std::fs::read(backup_path(path, 1)).ok();
+    if newest.map(|b| content_hash(&b)) == Some(content_hash(&current)) {
+        return Ok(());
+    }
+
+    let _ = std::fs::remove_file(backup_path(path, keep));
+    for i in (1..keep).rev() {
+        let from = backup_path(path, i);
+        if from.exists() {
+            std::fs::rename(&from, backup_path(path, i + 1))?;
+        }
+    }
+    std::fs::write(backup_path(path, 1), &current)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project_path(name: &str) -> PathBuf {
+        let dir = crate::test_util::temp_path(&format!("backups_{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("project.json")
+    }
+
+    #[test]
+    fn unchanged_saves_leave_the_backups_alone() {
+        let path = project_path("unchanged");
+        write_with_backups(&path, b"first", 3).unwrap();
+        write_with_backups(&path, b"second", 3).unwrap();
+        let backed_up = std::fs::read(backup_path(&path, 1)).unwrap();
+
+        for _ in 0..5 {
+            write_with_backups(&path, b"second", 3).unwrap();
+        }
+        let backups = list_backups(&path.to_string_lossy()).len();
+        let current = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+        assert_eq!(backed_up, b"first");
+        assert_eq!(backups, 1);
+        assert_eq!(current, b"second");
+    }
+
+    #[test]
+    fn changed_saves_rotate_up_to_the_limit() {
+        let path = project_path("rotate");
+        for content in ["a", "b", "c", "d", "e"] {
+            write_with_backups(&path, content.as_bytes(), 3).unwrap();
+        }
+        let backups: Vec<Vec<u8>> = (1..=4).map(|i| std::fs::read(backup_path(&path, i)).unwrap_or_default()).collect();
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+        assert_eq!(backups, vec![b"d".to_vec(), b"c".to_vec(), b"b".to_vec(), Vec::new()]);
+    }
+
+    #[test]
+    fn a_failed_write_leaves_the_project_and_backups_as_they_were() {
+        let path = project_path("failed");
+        write_with_backups(&path, b"first", 3).unwrap();
+        write_with_backups(&path, b"second", 3).unwrap();
+        // The temp file can't be created where a directory already is
+        let mut tmp = path.as_os_str().to_os_string();
+        tmp.push(".tmp");
+        std::fs::create_dir_all(PathBuf::from(tmp)).unwrap();
+
+        let result = write_with_backups(&path, b"third", 3);
+        let current = std::fs::read(&path).unwrap();
+        let backup = std::fs::read(backup_path(&path, 1)).unwrap();
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+        assert!(result.is_err());
+        assert_eq!(current, b"second");
+        assert_eq!(backup, b"first");
+    }
+}
@@ -0,0 +1,143 @@
+// src/session/stats.rs
+
+// Project statistics for the dashboard and the save flow. The engine lock is only held to
+// copy out clip references (`StatsInput::gather`); stat-ing the media happens after, since
+// a project on a slow or network drive can take a while to walk.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::engine::Engine;
+
+/// Referenced media outside the project folder above this size gets a warning on save.
+pub const UNCOLLECTED_MEDIA_WARN_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaFileInfo {
+    pub path: String,
+    /// None when the file is missing or unreadable.
+    pub bytes: Option<u64>,
+    /// Clips playing from this file.
+    pub clips: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LongestTrack {
+    pub track_id: u32,
+    pub name: String,
+    pub length_secs: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    pub track_count: usize,
+    pub clip_count: usize,
+    /// Unique media files and the sum of their sizes on disk (missing ones count as 0).
+    pub media_files: usize,
+    pub media_bytes: u64,
+    pub length_secs: f64,
+    pub longest_track: Option<LongestTrack>,
+    /// Clips per file extension, lowercased ("wav", "mp3", ...).
+    pub formats: BTreeMap<String, usize>,
+    /// Clips whose source rate differs from the engine and are resampled on playback.
+    pub resampled_clips: usize,
+    /// Clips whose file is missing or unreadable.
+    pub missing_clips: usize,
+    pub media: Vec<MediaFileInfo>,
+}
+
+impl ProjectStats {
+    /// Media not inside `project_dir` (or below it), as (files, bytes).
+    pub fn uncollected_media(&self, project_dir: &Path) -> (usize, u64) {
+        self.media
+            .iter()
+            .filter(|m| !Path::new(&m.path).starts_with(project_dir))
+            .fold((0, 0), |(files, bytes), m| (files + 1, bytes + m.bytes.unwrap_or(0)))
+    }
+}
+
+struct ClipRef {
+    path: String,
+    source_sr: u32,
+}
+
+/// What the stats need from the engine, copied out under the lock.
+pub struct StatsInput {
+    sample_rate: u32,
+    track_count: usize,
+    length_secs: f64,
+    longest_track: Option<LongestTrack>,
+    clips: Vec<ClipRef>,
+}
+
+impl StatsInput {
+    pub fn gather(engine: &Engine) -> Self {
+        let longest_track = engine
+            .tracks()
+            .iter()
+            .filter_map(|t| {
+                let end = t.clips.iter().map(|c| c.end()).max()?;
+                Some(LongestTrack { track_id: t.id.0, name: t.name.clone(), length_secs: end.as_secs_f64() })
+            })
+            .max_by(|a, b| a.length_secs.total_cmp(&b.length_secs));
+        let clips = engine
+            .tracks()
+            .iter()
+            .flat_map(|t| t.clips.iter())
+            .map(|c| ClipRef { path: c.path.clone(), source_sr: c.source_sr })
+            .collect();
+
+        Self {
+            sample_rate: engine.sample_rate,
+            track_count: engine.tracks().len(),
+            length_secs: engine.project_end().as_secs_f64(),
+            longest_track,
+            clips,
+        }
+    }
+
+    /// Stat the referenced files. Call without the engine lock.
+    pub fn compute(self) -> ProjectStats {
+        let mut clips_per_file: HashMap<&str, usize> = HashMap::new();
+        let mut formats = BTreeMap::new();
+        let mut resampled_clips = 0;
+        for clip in &self.clips {
+            *clips_per_file.entry(clip.path.as_str()).or_default() += 1;
+            let ext = Path::new(&clip.path)
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_else(|| "unknown".into());
+            *formats.entry(ext).or_default() += 1;
+            if clip.source_sr != self.sample_rate {
+                resampled_clips += 1;
+            }
+        }
+
+        let mut media: Vec<MediaFileInfo> = clips_per_file
+            .into_iter()
+            .map(|(path, clips)| MediaFileInfo {
+                path: path.to_string(),
+                bytes: std::fs::metadata(path).ok().filter(|m| m.is_file()).map(|m| m.len()),
+                clips,
+            })
+            .collect();
+        media.sort_by(|a, b| a.path.cmp(&b.path));
+
+        ProjectStats {
+            track_count: self.track_count,
+            clip_count: self.clips.len(),
+            media_files: media.len(),
+            media_bytes: media.iter().filter_map(|m| m.bytes).sum(),
+            length_secs: self.length_secs,
+            longest_track: self.longest_track,
+            formats,
+            resampled_clips,
+            missing_clips: media.iter().filter(|m| m.bytes.is_none()).map(|m| m.clips).sum(),
+            media,
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// src/test_util.rs
+//
+// Fixtures shared by the test modules.
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::path::{Path, PathBuf};
+
+/// A path in the temp directory unique to this test run: `haven_{name}_{pid}`.
+/// Add an extension with `with_extension` for files; use as is for scratch folders.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("haven_{}_{}", name, std::process::id()))
+}
+
+/// Writes interleaved `samples` as a 32-bit float WAV at `path`.
+pub fn write_wav(path: &Path, rate: u32, channels: u16, samples: &[f32]) {
+    let spec = WavSpec { channels, sample_rate: rate, bits_per_sample: 32, sample_format: SampleFormat::Float };
+    let mut writer = WavWriter::create(path, spec).unwrap();
+    for &s in samples {
+        writer.write_sample(s).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+/// Writes interleaved `samples` to a temp WAV named after `name` and returns its path.
+/// The caller removes the file when done.
+pub fn wav(name: &str, rate: u32, channels: u16, samples: &[f32]) -> String {
+    let path = temp_path(name).with_extension("wav");
+    write_wav(&path, rate, channels, samples);
+    path.to_string_lossy().into_owned()
+}
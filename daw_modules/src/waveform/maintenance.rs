@@ -0,0 +1,256 @@
+// src/waveform/maintenance.rs
+
+// Upkeep of the `.peaks` sidecars. Caches for media the project uses are validated and, if
+// stale or damaged, queued for a rebuild on the WaveformService. A full pass also walks the
+// project folder for caches whose source file is gone (and leftover `.peaks.tmp` files from
+// interrupted writes) and deletes them. Only the project folder is swept: media referenced
+// from elsewhere may belong to other projects.
+
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use super::peaks::{self, CacheStatus};
+use super::service::{WaveformJobOptions, WaveformService};
+
+/// `.peaks.tmp` files younger than this are left alone (a write may be in progress).
+const TMP_GRACE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaintenanceMode {
+    /// Headers only, no sweep: cheap enough to run on every project load.
+    Quick,
+    /// Header plus size checks, and orphan cleanup in the project folder.
+    Full,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheReport {
+    pub checked: usize,
+    /// Media whose stale or corrupt cache was queued for a rebuild.
+    pub rebuilt: usize,
+    pub deleted_orphans: usize,
+    pub bytes_freed: u64,
+    pub cancelled: bool,
+}
+
+/// Validate the caches of `media` and (in `Full` mode) sweep `project_dir` for orphans.
+/// `on_progress(done, total)` runs after every file; setting `cancel` stops between files.
+pub fn maintain_peaks_cache(
+    project_dir: &Path,
+    media: &[String],
+    mode: MaintenanceMode,
+    waveforms: &WaveformService,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(usize, usize),
+) -> CacheReport {
+    let mut report = CacheReport::default();
+    let orphans = match mode {
+        MaintenanceMode::Full => find_orphans(project_dir, cancel),
+        MaintenanceMode::Quick => Vec::new(),
+    };
+    let total = media.len() + orphans.len();
+
+    for (i, path) in media.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            report.cancelled = true;
+            return report;
+        }
+        // No source, nothing to rebuild from (the stats report it as missing)
+        if Path::new(path).is_file() {
+            report.checked += 1;
+            match peaks::check_peaks(path, mode == MaintenanceMode::Full) {
+                // A missing cache is built the first time the waveform is needed
+                CacheStatus::Fresh | CacheStatus::Missing => {}
+                status => {
+                    rt_info!("🧹 Peaks cache for {} is {:?}, rebuilding", path, status);
+                    let _ = std::fs::remove_file(peaks::peaks_path_for(path));
+                    waveforms.enqueue(path.clone(), WaveformJobOptions { force: true, ..Default::default() });
+                    report.rebuilt += 1;
+                }
+            }
+        }
+        on_progress(i + 1, total);
+    }
+
+    for (i, orphan) in orphans.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            report.cancelled = true;
+            return report;
+        }
+        let bytes = std::fs::metadata(orphan).map(|m| m.len()).unwrap_or(0);
+        match std::fs::remove_file(orphan) {
+            Ok(()) => {
+                report.deleted_orphans += 1;
+                report.bytes_freed += bytes;
+            }
+            Err(e) => rt_warn!("⚠️ Could not delete orphaned cache {}: {}", orphan.display(), e),
+        }
+        on_progress(media.len() + i + 1, total);
+    }
+
+    if report.rebuilt > 0 || report.deleted_orphans > 0 {
+        rt_info!(
+            "🧹 Peaks cache: {} rebuilt, {} orphans deleted ({} bytes freed)",
+            report.rebuilt, report.deleted_orphans, report.bytes_freed
+        );
+    }
+    report
+}
+
+// `.peaks` files without their source, and `.peaks.tmp` leftovers, below `dir`
+fn find_orphans(dir: &Path, cancel: &AtomicBool) -> Vec<std::path::PathBuf> {
+    let mut orphans = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(folder) = stack.pop() {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let Ok(entries) = std::fs::read_dir(&folder) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(kind) = entry.file_type() else { continue };
+            // file_type doesn't follow links, so linked folders (loops, other projects) are skipped
+            if kind.is_dir() {
+                stack.push(path);
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let orphaned = if name.ends_with(".peaks.tmp") {
+                // A fresh one may still be being written by the waveform service
+                entry.metadata().and_then(|m| m.modified()).is_ok_and(|t| t.elapsed().is_ok_and(|age| age >= TMP_GRACE))
+            } else {
+                name.strip_suffix(".peaks").is_some_and(|source| !folder.join(source).is_file())
+            };
+            if orphaned {
+                orphans.push(path);
+            }
+        }
+    }
+    orphans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::waveform::service::WaveformEvent;
+    use crate::waveform::Waveform;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc;
+
+    // A project folder with a short WAV and a fresh cache for every name in `media`
+    fn project(name: &str, media: &[&str]) -> (PathBuf, Vec<String>) {
+        let dir = crate::test_util::temp_path(&format!("peaks_maint_{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("audio")).unwrap();
+        let samples: Vec<f32> = (0..4800).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        let paths = media
+            .iter()
+            .map(|m| {
+                let path = dir.join("audio").join(m).to_string_lossy().into_owned();
+                crate::test_util::write_wav(Path::new(&path), 48_000, 1, &samples);
+                peaks::save_peaks(&Waveform::build_from_samples(&samples, 48_000, 1, 512), &path).unwrap();
+                path
+            })
+            .collect();
+        (dir, paths)
+    }
+
+    fn service() -> (WaveformService, mpsc::Receiver<String>) {
+        let (tx, rx) = mpsc::channel();
+        let service = WaveformService::new(move |event| {
+            if let WaveformEvent::Ready { path, .. } = event {
+                let _ = tx.send(path);
+            }
+        });
+        (service, rx)
+    }
+
+    fn age(path: &Path, by: Duration) {
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(std::time::SystemTime::now() - by).unwrap();
+    }
+
+    #[test]
+    fn full_pass_rebuilds_damaged_caches_and_deletes_orphans() {
+        let (dir, media) = project("full", &["fresh.wav", "corrupt.wav", "truncated.wav"]);
+        std::fs::write(peaks::peaks_path_for(&media[1]), b"not a peaks file").unwrap();
+        let truncated = peaks::peaks_path_for(&media[2]);
+        let len = std::fs::metadata(&truncated).unwrap().len();
+        std::fs::File::options().write(true).open(&truncated).unwrap().set_len(len - 8).unwrap();
+
+        // A cache for deleted media, an abandoned temp file, and one still being written
+        let orphan = dir.join("audio").join("deleted.wav.peaks");
+        std::fs::write(&orphan, vec![0u8; 1000]).unwrap();
+        let stale_tmp = dir.join("audio").join("old.wav.peaks.tmp");
+        std::fs::write(&stale_tmp, vec![0u8; 24]).unwrap();
+        age(&stale_tmp, TMP_GRACE * 2);
+        let live_tmp = dir.join("audio").join("writing.wav.peaks.tmp");
+        std::fs::write(&live_tmp, vec![0u8; 24]).unwrap();
+
+        let (waveforms, ready) = service();
+        let mut progress = Vec::new();
+        let report = maintain_peaks_cache(&dir, &media, MaintenanceMode::Full, &waveforms, &AtomicBool::new(false), |done, total| {
+            progress.push((done, total))
+        });
+
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.rebuilt, 2);
+        assert_eq!(report.deleted_orphans, 2);
+        assert_eq!(report.bytes_freed, 1024);
+        assert!(!report.cancelled);
+        assert_eq!(progress.last(), Some(&(5, 5)));
+        assert!(!orphan.exists() && !stale_tmp.exists());
+        assert!(live_tmp.exists(), "a temp file being written is left alone");
+
+        // The rebuilds land as fresh caches
+        let mut rebuilt: Vec<String> = (0..2).map(|_| ready.recv_timeout(Duration::from_secs(10)).unwrap()).collect();
+        rebuilt.sort();
+        assert_eq!(rebuilt, vec![media[1].clone(), media[2].clone()]);
+        for path in &media {
+            assert_eq!(peaks::check_peaks(path, true), CacheStatus::Fresh, "{path}");
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn quick_pass_checks_headers_and_sweeps_nothing() {
+        let (dir, media) = project("quick", &["stale.wav", "truncated.wav"]);
+        // Rewriting the source moves its size and mtime on from the cached stamp
+        std::fs::write(&media[0], vec![0u8; 64]).unwrap();
+        let truncated = peaks::peaks_path_for(&media[1]);
+        let len = std::fs::metadata(&truncated).unwrap().len();
+        std::fs::File::options().write(true).open(&truncated).unwrap().set_len(len - 8).unwrap();
+        let orphan = dir.join("deleted.wav.peaks");
+        std::fs::write(&orphan, b"x").unwrap();
+
+        let (waveforms, _ready) = service();
+        let report = maintain_peaks_cache(&dir, &media, MaintenanceMode::Quick, &waveforms, &AtomicBool::new(false), |_, _| {});
+
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.rebuilt, 1, "only the stale header is caught without the size check");
+        assert_eq!((report.deleted_orphans, report.bytes_freed), (0, 0));
+        assert!(orphan.exists());
+        assert_eq!(peaks::check_peaks(&media[1], true), CacheStatus::Corrupt);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_cancelled_pass_stops_without_touching_anything() {
+        let (dir, media) = project("cancel", &["a.wav"]);
+        std::fs::write(peaks::peaks_path_for(&media[0]), b"garbage").unwrap();
+        let orphan = dir.join("deleted.wav.peaks");
+        std::fs::write(&orphan, b"x").unwrap();
+
+        let (waveforms, _ready) = service();
+        let report = maintain_peaks_cache(&dir, &media, MaintenanceMode::Full, &waveforms, &AtomicBool::new(true), |_, _| {});
+
+        assert!(report.cancelled);
+        assert_eq!((report.checked, report.rebuilt, report.deleted_orphans), (0, 0, 0));
+        assert!(orphan.exists());
+        assert!(waveforms.pending_jobs().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,16 +1,64 @@
 // src/waveform/mod.rs
-use anyhow::{anyhow, Result};
-use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::DecoderOptions;
-use symphonia::core::formats::FormatOptions;
-use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
-use symphonia::default::{get_codecs, get_probe};
-use std::fs::File;
+pub mod peaks;
+pub mod service;
+pub mod maintenance;
+
+use anyhow::Result;
+use std::ops::ControlFlow;
+use crate::engine::time::Frames;
+
+/// Which channel(s) `bins_for` returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WaveformChannelMode {
+    /// A single source channel (empty if out of range).
+    Channel(usize),
+    /// One envelope covering every channel, so one-sided material is still visible.
+    #[default]
+    MixMono,
+    /// Every channel, for split stereo display.
+    PerChannel,
+}
 
 pub struct WaveformLevel {
     pub min: Vec<Vec<f32>>,
     pub max: Vec<Vec<f32>>,
+    /// Channel-combined envelope: per bin, the lowest min and highest max of any channel.
+    pub mix_min: Vec<f32>,
+    pub mix_max: Vec<f32>,
+}
+
+impl WaveformLevel {
+    fn new(min: Vec<Vec<f32>>, max: Vec<Vec<f32>>) -> Self {
+        let bins = min.first().map(|c| c.len()).unwrap_or(0);
+        let mut mix_min = vec![0.0f32; bins];
+        let mut mix_max = vec![0.0f32; bins];
+        for (ch_min, ch_max) in min.iter().zip(&max) {
+            for i in 0..bins {
+                // Signed envelope: whichever channel swings furthest each way wins
+                mix_min[i] = mix_min[i].min(ch_min[i]);
+                mix_max[i] = mix_max[i].max(ch_max[i]);
+            }
+        }
+        Self { min, max, mix_min, mix_max }
+    }
+}
+
+/// Result of `Waveform::bins_for`: one (min, max) pair of slices per returned channel.
+pub struct WaveformBins<'a> {
+    pub min: Vec<&'a [f32]>,
+    pub max: Vec<&'a [f32]>,
+    /// Mipmap level the bins come from.
+    pub level: usize,
+}
+
+impl<'a> WaveformBins<'a> {
+    /// First (or only) channel, for single-lane consumers.
+    pub fn first(&self) -> (&'a [f32], &'a [f32]) {
+        match (self.min.first(), self.max.first()) {
+            (Some(min), Some(max)) => (min, max),
+            _ => (&[], &[]),
+        }
+    }
 }
 
 pub struct Waveform {
@@ -21,184 +69,109 @@ pub struct Waveform {
     pub levels: Vec<WaveformLevel>,
 }
 
-impl Waveform {
-    /// 1. Single-Pass Builder (In-Memory)
-    /// Updated to TRIM LEADING SILENCE for tight visual alignment.
-    pub fn build_from_samples(
-        samples: &[f32],
-        sample_rate: u32,
-        channels: usize,
-        base_bin: usize,
-    ) -> Self {
-        // --- STEP 1: Detect Start of Audio (Skip Silence) ---
-        // Threshold: -46dB (0.005). Anything quieter is treated as "dead air".
-
-        // Slice the samples to remove the start silence
-        let effective_samples = samples;
+/// Incremental base-level builder: feed interleaved blocks in order, then `finish`.
+/// Holds one (min, max) pair per bin and channel, so a file can be summarised while
+/// it's being decoded.
+pub struct WaveformBuilder {
+    sample_rate: u32,
+    channels: usize,
+    base_bin: usize,
+    lvl0_min: Vec<Vec<f32>>,
+    lvl0_max: Vec<Vec<f32>>,
+    cur_min: Vec<f32>,
+    cur_max: Vec<f32>,
+    in_bin: usize,
+    global_peak: f32,
+    total_frames: u64,
+}
 
-        // --- STEP 2: Standard Build Logic ---
-        let mut lvl0_min = vec![Vec::<f32>::new(); channels];
-        let mut lvl0_max = vec![Vec::<f32>::new(); channels];
-        let mut cur_min = vec![f32::INFINITY; channels];
-        let mut cur_max = vec![f32::NEG_INFINITY; channels];
-        let mut in_bin = 0usize;
-        let mut global_peak = 0.0f32;
+impl WaveformBuilder {
+    pub fn new(sample_rate: u32, channels: usize, base_bin: usize) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            base_bin: base_bin.max(1),
+            lvl0_min: vec![Vec::new(); channels],
+            lvl0_max: vec![Vec::new(); channels],
+            cur_min: vec![f32::INFINITY; channels],
+            cur_max: vec![f32::NEG_INFINITY; channels],
+            in_bin: 0,
+            global_peak: 0.0,
+            total_frames: 0,
+        }
+    }
 
-        for frame in effective_samples.chunks(channels) {
+    /// Add the next block of interleaved samples (whole frames).
+    pub fn push(&mut self, samples: &[f32]) {
+        let channels = self.channels;
+        if channels == 0 { return; }
+        for frame in samples.chunks_exact(channels) {
             for (c, &sample) in frame.iter().enumerate() {
-                if c >= channels { break; }
-                if sample < cur_min[c] { cur_min[c] = sample; }
-                if sample > cur_max[c] { cur_max[c] = sample; }
-                if sample.abs() > global_peak { global_peak = sample.abs(); }
+                if sample < self.cur_min[c] { self.cur_min[c] = sample; }
+                if sample > self.cur_max[c] { self.cur_max[c] = sample; }
+                if sample.abs() > self.global_peak { self.global_peak = sample.abs(); }
             }
-            
-            in_bin += 1;
-            if in_bin == base_bin {
+
+            self.in_bin += 1;
+            if self.in_bin == self.base_bin {
                 for c in 0..channels {
-                    lvl0_min[c].push(cur_min[c]);
-                    lvl0_max[c].push(cur_max[c]);
-                    cur_min[c] = f32::INFINITY;
-                    cur_max[c] = f32::NEG_INFINITY;
+                    self.lvl0_min[c].push(self.cur_min[c]);
+                    self.lvl0_max[c].push(self.cur_max[c]);
+                    self.cur_min[c] = f32::INFINITY;
+                    self.cur_max[c] = f32::NEG_INFINITY;
                 }
-                in_bin = 0;
+                self.in_bin = 0;
             }
         }
+        self.total_frames += (samples.len() / channels) as u64;
+    }
+
+    pub fn finish(mut self) -> Waveform {
+        let channels = self.channels;
 
         // Flush remainder
-        if in_bin > 0 {
+        if self.in_bin > 0 {
             for c in 0..channels {
-                lvl0_min[c].push(if cur_min[c].is_finite() { cur_min[c] } else { 0.0 });
-                lvl0_max[c].push(if cur_max[c].is_finite() { cur_max[c] } else { 0.0 });
+                self.lvl0_min[c].push(if self.cur_min[c].is_finite() { self.cur_min[c] } else { 0.0 });
+                self.lvl0_max[c].push(if self.cur_max[c].is_finite() { self.cur_max[c] } else { 0.0 });
             }
         }
 
         // Normalize
-        if global_peak > 0.0 {
-            let scale = 1.0 / global_peak;
+        if self.global_peak > 0.0 {
+            let scale = 1.0 / self.global_peak;
             for c in 0..channels {
-                for v in &mut lvl0_min[c] { *v *= scale; }
-                for v in &mut lvl0_max[c] { *v *= scale; }
+                for v in &mut self.lvl0_min[c] { *v *= scale; }
+                for v in &mut self.lvl0_max[c] { *v *= scale; }
             }
         }
 
-        // Recalculate duration based on TRIMMED length
-        let total_frames = effective_samples.len() / channels;
-        let duration_secs = total_frames as f64 / sample_rate as f64;
+        let duration_secs = Frames(self.total_frames).to_seconds(self.sample_rate).0;
+        Waveform::build_mipmaps(self.sample_rate, channels, duration_secs, self.base_bin, self.lvl0_min, self.lvl0_max)
+    }
+}
 
-        Self::build_mipmaps(sample_rate, channels, duration_secs, base_bin, lvl0_min, lvl0_max)
+impl Waveform {
+    /// Build from interleaved samples already in memory.
+    pub fn build_from_samples(
+        samples: &[f32],
+        sample_rate: u32,
+        channels: usize,
+        base_bin: usize,
+    ) -> Self {
+        let mut builder = WaveformBuilder::new(sample_rate, channels, base_bin);
+        builder.push(samples);
+        builder.finish()
     }
 
-    /// 2. Legacy Builder (From File)
-    /// (Keep this as-is or replace with the mixed-channel logic if you use it directly)
+    /// Build straight from the decoder: only the bins are kept, never the audio.
     pub fn build_from_path(path: &str, base_bin: usize) -> Result<Self> {
-        // You can keep the robust mixed-channel logic here from the previous step
-        // or just rely on the fact that your app uses build_from_samples now.
-        // For safety, I will include the ROBUST MIXED logic here too.
-        
-        let file = File::open(path)?;
-        let mss = MediaSourceStream::new(Box::new(file), Default::default());
-        let probed = get_probe().format(&Default::default(), mss, &FormatOptions::default(), &MetadataOptions::default())?;
-        let mut format = probed.format;
-        let track = format.default_track().ok_or_else(|| anyhow!("no audio track"))?;
-        let track_id = track.id;
-        let codec_params = track.codec_params.clone();
-
-        let mut sr = codec_params.sample_rate.unwrap_or(44100);
-        let mut channels = codec_params.channels.map(|c| c.count()).unwrap_or(2);
-        let mut decoder = get_codecs().make(&codec_params, &DecoderOptions::default())?;
-
-        let mut lvl0_min = vec![Vec::<f32>::new(); channels];
-        let mut lvl0_max = vec![Vec::<f32>::new(); channels];
-        let mut cur_min = vec![f32::INFINITY; channels];
-        let mut cur_max = vec![f32::NEG_INFINITY; channels];
-        let mut in_bin = 0usize;
-        let mut sample_buf: Option<SampleBuffer<f32>> = None;
-        let mut total_frames_decoded = 0u64;
-        let mut global_peak = 0.0f32;
-        let mut first_packet = true;
-
-        loop {
-            let packet = match format.next_packet() {
-                Ok(p) => p, Err(_) => break,
-            };
-            if packet.track_id() != track_id { continue; }
-            let decoded = match decoder.decode(&packet) {
-                Ok(d) => d, Err(_) => continue,
-            };
-
-            let spec = decoded.spec();
-            let current_channels = spec.channels.count();
-            let current_rate = spec.rate;
-
-            if first_packet {
-                if decoded.frames() > 0 {
-                    sr = current_rate;
-                    channels = current_channels;
-                    lvl0_min = vec![Vec::new(); channels];
-                    lvl0_max = vec![Vec::new(); channels];
-                    cur_min = vec![f32::INFINITY; channels];
-                    cur_max = vec![f32::NEG_INFINITY; channels];
-                    first_packet = false;
-                } else { continue; }
-            }
-
-            if sample_buf.is_none() || sample_buf.as_ref().unwrap().capacity() < decoded.capacity() {
-                sample_buf = Some(SampleBuffer::<f32>::new(decoded.capacity() as u64, *spec));
-            }
-            let buf = sample_buf.as_mut().unwrap();
-            buf.copy_interleaved_ref(decoded);
-            let samples = buf.samples();
-
-            // Robust Mix
-            let mut processed_samples = Vec::with_capacity(samples.len());
-            if current_channels == channels {
-                processed_samples.extend_from_slice(samples);
-            } else if current_channels == 1 && channels == 2 {
-                for &s in samples { processed_samples.push(s); processed_samples.push(s); }
-            } else if current_channels == 2 && channels == 1 {
-                for pair in samples.chunks(2) { processed_samples.push((pair[0] + pair[1]) * 0.5); }
-            } else { continue; }
-
-            let frames = processed_samples.len() / channels;
-            total_frames_decoded += frames as u64;
-
-            for f in 0..frames {
-                for c in 0..channels {
-                    let s = processed_samples[f * channels + c];
-                    if s < cur_min[c] { cur_min[c] = s; }
-                    if s > cur_max[c] { cur_max[c] = s; }
-                    if s.abs() > global_peak { global_peak = s.abs(); }
-                }
-                in_bin += 1;
-                if in_bin == base_bin {
-                    for c in 0..channels {
-                        lvl0_min[c].push(cur_min[c]);
-                        lvl0_max[c].push(cur_max[c]);
-                        cur_min[c] = f32::INFINITY;
-                        cur_max[c] = f32::NEG_INFINITY;
-                    }
-                    in_bin = 0;
-                }
-            }
-        }
-        
-        if in_bin > 0 {
-            for c in 0..channels {
-                lvl0_min[c].push(if cur_min[c].is_finite() { cur_min[c] } else { 0.0 });
-                lvl0_max[c].push(if cur_max[c].is_finite() { cur_max[c] } else { 0.0 });
-            }
-        }
-
-        if global_peak > 0.0 {
-            let scale = 1.0 / global_peak;
-            for c in 0..channels {
-                for v in &mut lvl0_min[c] { *v *= scale; }
-                for v in &mut lvl0_max[c] { *v *= scale; }
-            }
-        }
-
-        let duration_secs = total_frames_decoded as f64 / sr as f64;
-        Ok(Self::build_mipmaps(sr, channels, duration_secs, base_bin, lvl0_min, lvl0_max))
+        let mut builder: Option<WaveformBuilder> = None;
+        let (sr, channels, _) = crate::bpm::adapter::decode_chunks(path, None, |samples, sr, channels| {
+            builder.get_or_insert_with(|| WaveformBuilder::new(sr, channels, base_bin)).push(samples);
+            ControlFlow::Continue(())
+        })?;
+        Ok(builder.unwrap_or_else(|| WaveformBuilder::new(sr, channels, base_bin)).finish())
     }
 
     fn build_mipmaps(
@@ -210,16 +183,17 @@ impl Waveform {
         lvl0_max: Vec<Vec<f32>>,
     ) -> Self {
         let mut levels = Vec::new();
-        levels.push(WaveformLevel { min: lvl0_min, max: lvl0_max });
+        levels.push(WaveformLevel::new(lvl0_min, lvl0_max));
 
-        loop {
-            let prev = levels.last().unwrap();
-            let bins = prev.min[0].len();
+        // A zero-channel or zero-length source keeps just its (empty) base level
+        while let Some(prev) = levels.last() {
+            let bins = prev.min.first().map(|c| c.len()).unwrap_or(0);
             if bins <= 1 { break; }
             let next_bins = bins / 2;
-            let mut next_min = vec![Vec::with_capacity(next_bins); channels];
-            let mut next_max = vec![Vec::with_capacity(next_bins); channels];
-            for c in 0..channels {
+            let lanes = prev.min.len().min(prev.max.len());
+            let mut next_min = vec![Vec::with_capacity(next_bins); lanes];
+            let mut next_max = vec![Vec::with_capacity(next_bins); lanes];
+            for c in 0..lanes {
                 let pm = &prev.min[c];
                 let px = &prev.max[c];
                 let mut i = 0usize;
@@ -235,7 +209,7 @@ impl Waveform {
                     next_max[c].push(px[i]);
                 }
             }
-            levels.push(WaveformLevel { min: next_min, max: next_max });
+            levels.push(WaveformLevel::new(next_min, next_max));
             if next_bins <= 1 { break; }
         }
 
@@ -248,26 +222,129 @@ impl Waveform {
         }
     }
 
+    /// Bins for a window of `columns` starting at `start_bin`, from the coarsest level
+    /// that still has at least one bin per pixel.
     pub fn bins_for(
         &self,
         samples_per_pixel: f64,
-        channel: usize,
+        mode: WaveformChannelMode,
         start_bin: usize,
         columns: usize,
-    ) -> (&[f32], &[f32], usize) {
+    ) -> WaveformBins<'_> {
         let mut level_idx = 0usize;
         let mut bin_size = self.base_bin as f64;
         while level_idx + 1 < self.levels.len() && bin_size * 2.0 <= samples_per_pixel {
             level_idx += 1;
             bin_size *= 2.0;
         }
-        let lvl = &self.levels[level_idx];
-        let total_bins = lvl.min[0].len();
-        let end = (start_bin + columns).min(total_bins);
-        if channel < lvl.min.len() {
-            (&lvl.min[channel][start_bin..end], &lvl.max[channel][start_bin..end], level_idx)
-        } else {
-            (&[], &[], level_idx)
+        let Some(lvl) = self.levels.get(level_idx) else {
+            return WaveformBins { min: Vec::new(), max: Vec::new(), level: 0 };
+        };
+        let total_bins = lvl.mix_min.len();
+        let start = start_bin.min(total_bins);
+        let end = start_bin.saturating_add(columns).min(total_bins);
+
+        let (min, max) = match mode {
+            WaveformChannelMode::Channel(c) if c < lvl.min.len() => {
+                (vec![&lvl.min[c][start..end]], vec![&lvl.max[c][start..end]])
+            }
+            WaveformChannelMode::Channel(_) => (Vec::new(), Vec::new()),
+            WaveformChannelMode::MixMono => (vec![&lvl.mix_min[start..end]], vec![&lvl.mix_max[start..end]]),
+            WaveformChannelMode::PerChannel => (
+                lvl.min.iter().map(|c| &c[start..end]).collect(),
+                lvl.max.iter().map(|c| &c[start..end]).collect(),
+            ),
+        };
+        WaveformBins { min, max, level: level_idx }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SR: u32 = 8_000;
+
+    // Stereo, left silent, right a full-scale square: a guitar panned hard right
+    fn hard_right(frames: usize) -> Vec<f32> {
+        (0..frames).flat_map(|n| [0.0, if (n / 20) % 2 == 0 { 0.8 } else { -0.8 }]).collect()
+    }
+
+    fn peak(bins: &[f32]) -> f32 {
+        bins.iter().fold(0.0f32, |m, v| m.max(v.abs()))
+    }
+
+    #[test]
+    fn each_mode_sees_the_right_channels_of_hard_panned_material() {
+        let wf = Waveform::build_from_samples(&hard_right(SR as usize), SR, 2, 64);
+
+        let left = wf.bins_for(64.0, WaveformChannelMode::Channel(0), 0, usize::MAX);
+        let (min, max) = left.first();
+        assert!(!min.is_empty());
+        assert_eq!(peak(min).max(peak(max)), 0.0);
+
+        let right = wf.bins_for(64.0, WaveformChannelMode::Channel(1), 0, usize::MAX);
+        let (right_min, right_max) = right.first();
+        assert!(right_min.iter().all(|&v| v < -0.9) && right_max.iter().all(|&v| v > 0.9));
+
+        // The mixed view keeps the signed envelope of the loud side
+        let mix = wf.bins_for(64.0, WaveformChannelMode::MixMono, 0, usize::MAX);
+        assert_eq!(mix.first(), (right_min, right_max));
+
+        let split = wf.bins_for(64.0, WaveformChannelMode::PerChannel, 0, usize::MAX);
+        assert_eq!(split.min.len(), 2);
+        assert_eq!(split.min[0], min);
+        assert_eq!(split.max[1], right_max);
+
+        assert!(wf.bins_for(64.0, WaveformChannelMode::Channel(2), 0, usize::MAX).min.is_empty());
+    }
+
+    #[test]
+    fn coarser_levels_and_windows_apply_to_every_mode() {
+        let wf = Waveform::build_from_samples(&hard_right(SR as usize * 4), SR, 2, 64);
+        let full = wf.bins_for(64.0, WaveformChannelMode::MixMono, 0, usize::MAX).first().0.len();
+
+        for mode in [WaveformChannelMode::Channel(1), WaveformChannelMode::MixMono, WaveformChannelMode::PerChannel] {
+            let coarse = wf.bins_for(256.0, mode, 0, usize::MAX);
+            assert_eq!(coarse.level, 2);
+            assert_eq!(coarse.first().0.len(), full.div_ceil(4));
+
+            let window = wf.bins_for(64.0, mode, 10, 5);
+            assert!(window.min.iter().all(|lane| lane.len() == 5));
+        }
+    }
+
+    #[test]
+    fn a_peaks_cache_serves_every_mode() {
+        let path = crate::test_util::wav("waveform_modes", SR, 2, &hard_right(SR as usize));
+
+        let built = Waveform::build_from_path(&path, 64).unwrap();
+        peaks::save_peaks(&built, &path).unwrap();
+        let loaded = peaks::load_peaks(&path).unwrap().unwrap();
+        let _ = std::fs::remove_file(peaks::peaks_path_for(&path));
+        let _ = std::fs::remove_file(&path);
+
+        for mode in [WaveformChannelMode::Channel(0), WaveformChannelMode::Channel(1), WaveformChannelMode::MixMono, WaveformChannelMode::PerChannel] {
+            let (a, b) = (built.bins_for(128.0, mode, 0, usize::MAX), loaded.bins_for(128.0, mode, 0, usize::MAX));
+            assert_eq!(a.min, b.min, "{:?}", mode);
+            assert_eq!(a.max, b.max, "{:?}", mode);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn empty_and_single_frame_sources_give_empty_or_tiny_bins() {
+        for channels in [0, 1, 2] {
+            let wf = Waveform::build_from_samples(&[], 48_000, channels, 256);
+            for mode in [WaveformChannelMode::MixMono, WaveformChannelMode::PerChannel, WaveformChannelMode::Channel(1)] {
+                let bins = wf.bins_for(1024.0, mode, 0, 100);
+                assert!(bins.min.iter().chain(&bins.max).all(|lane| lane.is_empty()), "{channels} channels, {mode:?}");
+            }
+        }
+
+        let wf = Waveform::build_from_samples(&[0.5, -0.25], 48_000, 2, 256);
+        let bins = wf.bins_for(1.0, WaveformChannelMode::MixMono, 0, 100);
+        assert_eq!(bins.min.iter().map(|lane| lane.len()).collect::<Vec<_>>(), vec![1]);
+        let past_the_end = wf.bins_for(1.0, WaveformChannelMode::PerChannel, 5, 100);
+        assert!(past_the_end.min.iter().all(|lane| lane.is_empty()));
+    }
+}
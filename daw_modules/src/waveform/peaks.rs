@@ -0,0 +1,159 @@
+// src/waveform/peaks.rs
+
+// On-disk `.peaks` sidecar: level-0 min/max bins for every channel plus the source file's
+// size and mtime, so a stale cache is detected and rebuilt. Mipmaps and the mixed-mono
+// envelope are recomputed on load, so one cache serves every `WaveformChannelMode`.
+
+use super::Waveform;
+use anyhow::{anyhow, Result};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const MAGIC: &[u8; 4] = b"HVPK";
+const VERSION: u32 = 1;
+
+/// `song.wav` -> `song.wav.peaks`
+pub fn peaks_path_for(audio_path: &str) -> PathBuf {
+    let mut p = std::ffi::OsString::from(audio_path);
+    p.push(".peaks");
+    PathBuf::from(p)
+}
+
+// (size, mtime secs) identifies the version of the source we summarised
+fn source_stamp(audio_path: &str) -> Result<(u64, u64)> {
+    let meta = std::fs::metadata(audio_path)?;
+    let mtime = meta.modified()?.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    Ok((meta.len(), mtime))
+}
+
+pub fn save_peaks(wf: &Waveform, audio_path: &str) -> Result<PathBuf> {
+    let (size, mtime) = source_stamp(audio_path)?;
+    let out_path = peaks_path_for(audio_path);
+    let tmp_path = out_path.with_extension("peaks.tmp");
+
+    {
+        let mut w = BufWriter::new(File::create(&tmp_path)?);
+        let lvl = wf.levels.first().ok_or_else(|| anyhow!("waveform has no levels"))?;
+        let bins = lvl.min.first().map(|c| c.len()).unwrap_or(0);
+
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        w.write_all(&size.to_le_bytes())?;
+        w.write_all(&mtime.to_le_bytes())?;
+        w.write_all(&wf.sample_rate.to_le_bytes())?;
+        w.write_all(&(wf.channels as u32).to_le_bytes())?;
+        w.write_all(&(wf.base_bin as u32).to_le_bytes())?;
+        w.write_all(&wf.duration_secs.to_le_bytes())?;
+        w.write_all(&(bins as u64).to_le_bytes())?;
+        for c in 0..wf.channels {
+            for v in &lvl.min[c] { w.write_all(&v.to_le_bytes())?; }
+            for v in &lvl.max[c] { w.write_all(&v.to_le_bytes())?; }
+        }
+        w.flush()?;
+    }
+
+    // Atomic replace so readers never see a half-written cache
+    std::fs::rename(&tmp_path, &out_path)?;
+    Ok(out_path)
+}
+
+/// Load a cached waveform. `Ok(None)` when there is no cache or it is stale.
+pub fn load_peaks(audio_path: &str) -> Result<Option<Waveform>> {
+    let path = peaks_path_for(audio_path);
+    if !Path::new(&path).exists() {
+        return Ok(None);
+    }
+    let (size, mtime) = source_stamp(audio_path)?;
+    let mut r = BufReader::new(File::open(&path)?);
+
+    let mut magic = [0u8; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(anyhow!("Not a peaks file: {}", path.display()));
+    }
+    if read_u32(&mut r)? != VERSION {
+        return Ok(None);
+    }
+    if read_u64(&mut r)? != size || read_u64(&mut r)? != mtime {
+        return Ok(None);
+    }
+
+    let sample_rate = read_u32(&mut r)?;
+    let channels = read_u32(&mut r)? as usize;
+    let base_bin = read_u32(&mut r)? as usize;
+    let duration_secs = f64::from_le_bytes(read_array(&mut r)?);
+    let bins = read_u64(&mut r)? as usize;
+
+    let mut lvl0_min = vec![Vec::with_capacity(bins); channels];
+    let mut lvl0_max = vec![Vec::with_capacity(bins); channels];
+    for c in 0..channels {
+        for _ in 0..bins { lvl0_min[c].push(f32::from_le_bytes(read_array(&mut r)?)); }
+        for _ in 0..bins { lvl0_max[c].push(f32::from_le_bytes(read_array(&mut r)?)); }
+    }
+
+    Ok(Some(Waveform::build_mipmaps(sample_rate, channels, duration_secs, base_bin, lvl0_min, lvl0_max)))
+}
+
+/// Health of a `.peaks` sidecar, as judged by `check_peaks`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    Missing,
+    Fresh,
+    /// Older format version, or the source changed since it was written.
+    Stale,
+    /// Unreadable, wrong magic or truncated.
+    Corrupt,
+}
+
+// magic, version, size, mtime, sample rate, channels, base bin, duration, bins
+const HEADER_BYTES: u64 = 4 + 4 + 8 + 8 + 4 + 4 + 4 + 8 + 8;
+
+/// Validate the cache of `audio_path` without decoding it: header, version and source
+/// stamp, plus (if `thorough`) that the file holds as many bins as the header promises.
+pub fn check_peaks(audio_path: &str, thorough: bool) -> CacheStatus {
+    let path = peaks_path_for(audio_path);
+    let Ok(file) = File::open(&path) else { return CacheStatus::Missing };
+    let Ok(file_len) = file.metadata().map(|m| m.len()) else { return CacheStatus::Corrupt };
+    let Ok(stamp) = source_stamp(audio_path) else { return CacheStatus::Stale };
+    check_header(&mut BufReader::new(file), stamp, file_len, thorough).unwrap_or(CacheStatus::Corrupt)
+}
+
+// A read error anywhere means the file is truncated
+fn check_header(r: &mut impl Read, stamp: (u64, u64), file_len: u64, thorough: bool) -> Result<CacheStatus> {
+    let magic: [u8; 4] = read_array(r)?;
+    if &magic != MAGIC {
+        return Ok(CacheStatus::Corrupt);
+    }
+    if read_u32(r)? != VERSION {
+        return Ok(CacheStatus::Stale);
+    }
+    if (read_u64(r)?, read_u64(r)?) != stamp {
+        return Ok(CacheStatus::Stale);
+    }
+    if !thorough {
+        return Ok(CacheStatus::Fresh);
+    }
+    let _sample_rate = read_u32(r)?;
+    let channels = read_u32(r)? as u64;
+    let _base_bin = read_u32(r)?;
+    let _duration: [u8; 8] = read_array(r)?;
+    let bins = read_u64(r)?;
+    let expected = channels.checked_mul(bins).and_then(|n| n.checked_mul(8)).map(|n| n + HEADER_BYTES);
+    Ok(if channels > 0 && expected == Some(file_len) { CacheStatus::Fresh } else { CacheStatus::Corrupt })
+}
+
+fn read_array<const N: usize>(r: &mut impl Read) -> Result<[u8; N]> {
+    let mut b = [0u8; N];
+    r.read_exact(&mut b)?;
+    Ok(b)
+}
+
+fn read_u32(r: &mut impl Read) -> Result<u32> {
+    Ok(u32::from_le_bytes(read_array(r)?))
+}
+
+fn read_u64(r: &mut impl Read) -> Result<u64> {
+    Ok(u64::from_le_bytes(read_array(r)?))
+}
@@ -0,0 +1,144 @@
+// src/waveform/service.rs
+
+// Background waveform rebuilds.
+// Anything that writes a new audio file (recording, destructive edits, conversions)
+// enqueues a job here instead of blocking its command on the rebuild.
+
+use super::{peaks, Waveform};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Debug, Clone, Copy)]
+pub struct WaveformJobOptions {
+    pub base_bin: usize,
+    /// Rebuild even if a fresh `.peaks` cache exists.
+    pub force: bool,
+}
+
+impl Default for WaveformJobOptions {
+    fn default() -> Self {
+        Self { base_bin: 512, force: false }
+    }
+}
+
+/// Result handed to the `on_event` callback.
+pub enum WaveformEvent {
+    Ready { path: String, waveform: Waveform },
+    Failed { path: String, error: String },
+}
+
+struct Job {
+    path: String,
+    options: WaveformJobOptions,
+    generation: u64,
+}
+
+pub struct WaveformService {
+    tx: Mutex<Sender<Job>>,
+    pending: Arc<Mutex<HashSet<String>>>,
+    generation: Arc<AtomicU64>,
+}
+
+impl WaveformService {
+    /// Spawns the worker. `on_event` runs on the worker thread.
+    pub fn new<F>(on_event: F) -> Self
+    where
+        F: Fn(WaveformEvent) + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let pending = Arc::new(Mutex::new(HashSet::new()));
+        let generation = Arc::new(AtomicU64::new(0));
+
+        let pending_w = pending.clone();
+        let generation_w = generation.clone();
+        thread::Builder::new()
+            .name("waveform-service".into())
+            .spawn(move || worker(rx, pending_w, generation_w, on_event))
+            .expect("failed to spawn waveform service");
+
+        Self { tx: Mutex::new(tx), pending, generation }
+    }
+
+    /// Queue a rebuild. Returns false if the same path is already queued.
+    pub fn enqueue(&self, path: String, options: WaveformJobOptions) -> bool {
+        if let Ok(mut pending) = self.pending.lock() {
+            if !pending.insert(path.clone()) {
+                return false;
+            }
+        }
+        let job = Job { path, options, generation: self.generation.load(Ordering::Acquire) };
+        self.tx.lock().map(|tx| tx.send(job).is_ok()).unwrap_or(false)
+    }
+
+    /// Drop a queued job (a job already being built still finishes).
+    pub fn cancel(&self, path: &str) -> bool {
+        self.pending.lock().map(|mut p| p.remove(path)).unwrap_or(false)
+    }
+
+    /// Call when the project is closed/replaced: every job queued before now is discarded.
+    pub fn close_project(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.clear();
+        }
+    }
+
+    /// Paths waiting for (or currently in) a rebuild.
+    pub fn pending_jobs(&self) -> Vec<String> {
+        self.pending.lock().map(|p| p.iter().cloned().collect()).unwrap_or_default()
+    }
+}
+
+fn worker<F>(rx: Receiver<Job>, pending: Arc<Mutex<HashSet<String>>>, generation: Arc<AtomicU64>, on_event: F)
+where
+    F: Fn(WaveformEvent),
+{
+    while let Ok(job) = rx.recv() {
+        // Stale project or cancelled while queued
+        let still_wanted = pending.lock().map(|p| p.contains(&job.path)).unwrap_or(false);
+        if job.generation != generation.load(Ordering::Acquire) || !still_wanted {
+            continue;
+        }
+
+        let result = build(&job);
+
+        // The project may have closed while we were decoding
+        let current = job.generation == generation.load(Ordering::Acquire);
+        if let Ok(mut p) = pending.lock() {
+            if current {
+                p.remove(&job.path);
+            }
+        }
+        if !current {
+            continue;
+        }
+
+        match result {
+            Ok(waveform) => on_event(WaveformEvent::Ready { path: job.path, waveform }),
+            Err(e) => {
+                rt_warn!("⚠️ Waveform rebuild failed for {}: {}", job.path, e);
+                on_event(WaveformEvent::Failed { path: job.path, error: e.to_string() });
+            }
+        }
+    }
+}
+
+fn build(job: &Job) -> anyhow::Result<Waveform> {
+    if !job.options.force {
+        if let Ok(Some(wf)) = peaks::load_peaks(&job.path) {
+            if wf.base_bin == job.options.base_bin {
+                return Ok(wf);
+            }
+        }
+    }
+
+    let wf = Waveform::build_from_path(&job.path, job.options.base_bin)?;
+    if let Err(e) = peaks::save_peaks(&wf, &job.path) {
+        // Cache is an optimisation; the waveform itself is still good
+        rt_warn!("⚠️ Could not write peaks cache for {}: {}", job.path, e);
+    }
+    Ok(wf)
+}
@@ -0,0 +1,183 @@
+// src-tauri/src/global_shortcuts.rs
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::AppState;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum ShortcutAction {
+    PlayPause,
+    Record,
+    ReturnToZero,
+}
+
+/// Action -> accelerator string (e.g. "MediaPlayPause", "CommandOrControl+Shift+R").
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutSettings {
+    pub enabled: bool,
+    pub map: BTreeMap<ShortcutAction, String>,
+}
+
+impl Default for ShortcutSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            map: BTreeMap::from([(ShortcutAction::PlayPause, "MediaPlayPause".to_string())]),
+        }
+    }
+}
+
+/// A binding that could not be registered, usually because another app owns the key.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutFailure {
+    pub action: ShortcutAction,
+    pub accelerator: String,
+    pub reason: String,
+}
+
+/// Sent to the UI on every press, after the action ran; `error` says why it didn't.
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ShortcutPayload {
+    action: ShortcutAction,
+    error: Option<String>,
+}
+
+/// Global transport shortcuts, persisted as `global_shortcuts.json` in the app config dir.
+#[derive(Default)]
+pub struct ShortcutStore {
+    path: Option<PathBuf>,
+    pub settings: ShortcutSettings,
+    active: Vec<(Shortcut, ShortcutAction)>,
+}
+
+impl ShortcutStore {
+    pub fn load(dir: PathBuf) -> Self {
+        let path = dir.join("global_shortcuts.json");
+        let settings = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path: Some(path), settings, active: Vec::new() }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(&self.settings).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    fn action_for(&self, shortcut: &Shortcut) -> Option<ShortcutAction> {
+        self.active.iter().find(|(s, _)| s == shortcut).map(|(_, a)| *a)
+    }
+}
+
+/// Drop every binding Haven holds and register the current settings. Returns the ones that failed.
+pub fn register_all(app: &AppHandle, store: &mut ShortcutStore) -> Vec<ShortcutFailure> {
+    let manager = app.global_shortcut();
+    if let Err(e) = manager.unregister_all() {
+        log::warn!("Could not release global shortcuts: {}", e);
+    }
+    store.active.clear();
+    if !store.settings.enabled {
+        return Vec::new();
+    }
+
+    let mut failures = Vec::new();
+    for (&action, accelerator) in &store.settings.map {
+        let registered = accelerator
+            .parse::<Shortcut>()
+            .map_err(|e| e.to_string())
+            .and_then(|shortcut| manager.register(shortcut).map(|_| shortcut).map_err(|e| e.to_string()));
+        match registered {
+            Ok(shortcut) => store.active.push((shortcut, action)),
+            Err(reason) => {
+                log::warn!("Global shortcut {} for {:?} not registered: {}", accelerator, action, reason);
+                failures.push(ShortcutFailure { action, accelerator: accelerator.clone(), reason });
+            }
+        }
+    }
+    failures
+}
+
+/// Plugin handler: run the action the same way the transport commands do, then tell the UI.
+/// Record starts a take on the armed tracks and stops it again; a transport key pressed
+/// mid-take ends the take first, so the clips land before the playhead moves.
+pub fn on_shortcut(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    let state = app.state::<AppState>();
+    let Some(action) = state.shortcuts.lock().ok().and_then(|s| s.action_for(shortcut)) else { return };
+
+    let recording = state.recorder.lock().map(|r| r.is_some()).unwrap_or(false);
+    let result = match action {
+        ShortcutAction::Record if recording => {
+            stop_take(app).and_then(|_| with_audio(&state, |audio| audio.pause()))
+        }
+        ShortcutAction::Record => crate::start_armed_recording(None, app.clone(), app.state()).map(|_| ()),
+        ShortcutAction::PlayPause => {
+            let stopped = if recording { stop_take(app) } else { Ok(()) };
+            stopped.and_then(|_| with_audio(&state, |audio| audio.toggle_play()))
+        }
+        ShortcutAction::ReturnToZero => {
+            let stopped = if recording { stop_take(app) } else { Ok(()) };
+            stopped.and_then(|_| with_audio(&state, |audio| audio.seek(Duration::ZERO)))
+        }
+    };
+    if let Err(e) = &result {
+        log::warn!("Global shortcut {:?} failed: {}", action, e);
+    }
+    let _ = app.emit("global-shortcut", ShortcutPayload { action, error: result.err() });
+}
+
+// Stop the running take and place its clips, exactly like `stop_recording` from the UI,
+// which picks the result up from `recording-stopped`.
+fn stop_take(app: &AppHandle) -> Result<(), String> {
+    let stopped = crate::stop_recording(app.state(), app.state())?;
+    for error in &stopped.errors {
+        log::warn!("Take not placed: {}", error);
+    }
+    let _ = app.emit("recording-stopped", &stopped);
+    Ok(())
+}
+
+fn with_audio(state: &AppState, f: impl FnOnce(&daw_modules::audio_runtime::AudioRuntime)) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    f(&audio);
+    Ok(())
+}
+
+/// Enable/disable global shortcuts and optionally replace the bindings.
+/// Saved either way; the result lists bindings that another app already owns.
+#[tauri::command]
+pub fn set_global_shortcuts(
+    enabled: bool,
+    map: Option<BTreeMap<ShortcutAction, String>>,
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<Vec<ShortcutFailure>, String> {
+    let mut store = state.shortcuts.lock().map_err(|_| "Failed to lock shortcuts")?;
+    store.settings.enabled = enabled;
+    if let Some(map) = map {
+        store.settings.map = map;
+    }
+    store.save()?;
+    Ok(register_all(&app, &mut store))
+}
+
+#[tauri::command]
+pub fn get_global_shortcuts(state: State<AppState>) -> Result<ShortcutSettings, String> {
+    let store = state.shortcuts.lock().map_err(|_| "Failed to lock shortcuts")?;
+    Ok(store.settings.clone())
+}
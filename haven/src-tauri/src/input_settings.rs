@@ -0,0 +1,400 @@
+// src-tauri/src/input_settings.rs
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::State;
+use cpal::traits::{DeviceTrait, HostTrait};
+
+use daw_modules::audio_runtime::AudioRuntime;
+use daw_modules::recorder::calibration::{measure_round_trip, RoundTripLatency};
+use daw_modules::recorder::input::{CaptureMode, InputSelection};
+use daw_modules::recorder::input_meter::InputMeter;
+use tauri::Manager;
+
+use crate::AppState;
+
+/// Per-input-device software gain, persisted as `input_gains.json` in the app config dir.
+#[derive(Default)]
+pub struct InputGainStore {
+    path: Option<PathBuf>,
+    gains: HashMap<String, f32>,
+}
+
+impl InputGainStore {
+    pub fn load(dir: PathBuf) -> Self {
+        let path = dir.join("input_gains.json");
+        let gains = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path: Some(path), gains }
+    }
+
+    pub fn get(&self, device: &str) -> f32 {
+        self.gains.get(device).copied().unwrap_or(0.0)
+    }
+
+    pub fn set(&mut self, device: String, db: f32) -> Result<(), String> {
+        self.gains.insert(device, db);
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(&self.gains).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+/// Per-input-device channel selection for takes, persisted as `input_selection.json`.
+#[derive(Default)]
+pub struct InputSelectionStore {
+    path: Option<PathBuf>,
+    selections: HashMap<String, InputSelection>,
+}
+
+impl InputSelectionStore {
+    pub fn load(dir: PathBuf) -> Self {
+        let path = dir.join("input_selection.json");
+        let selections = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path: Some(path), selections }
+    }
+
+    pub fn get(&self, device: &str) -> InputSelection {
+        self.selections.get(device).copied().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, device: String, selection: InputSelection) -> Result<(), String> {
+        self.selections.insert(device, selection);
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(&self.selections).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+/// Measured round-trip latency per output/input device pair (ms), persisted as
+/// `record_latency.json`. Takes are shifted back by it so they line up with the playback.
+#[derive(Default)]
+pub struct RecordLatencyStore {
+    path: Option<PathBuf>,
+    latencies: HashMap<String, f64>,
+}
+
+impl RecordLatencyStore {
+    pub fn load(dir: PathBuf) -> Self {
+        let path = dir.join("record_latency.json");
+        let latencies = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path: Some(path), latencies }
+    }
+
+    pub fn get(&self, devices: &str) -> f64 {
+        self.latencies.get(devices).copied().unwrap_or(0.0)
+    }
+
+    pub fn set(&mut self, devices: String, ms: f64) -> Result<(), String> {
+        self.latencies.insert(devices, ms);
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(&self.latencies).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+/// Cue mix balance (live input vs playback), persisted as `monitor_blend.json`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorBlend {
+    pub input_level: f32,
+    pub playback_level: f32,
+    /// Without cue outputs, the speakers play the cue mix rather than the master.
+    #[serde(default)]
+    pub cue_on_speakers: bool,
+}
+
+impl Default for MonitorBlend {
+    fn default() -> Self {
+        Self { input_level: 1.0, playback_level: 1.0, cue_on_speakers: false }
+    }
+}
+
+impl MonitorBlend {
+    pub fn apply(&self, audio: &AudioRuntime) {
+        audio.set_monitor_blend(self.input_level, self.playback_level);
+        audio.set_cue_on_speakers(self.cue_on_speakers);
+    }
+}
+
+#[derive(Default)]
+pub struct MonitorBlendStore {
+    path: Option<PathBuf>,
+    pub blend: MonitorBlend,
+}
+
+impl MonitorBlendStore {
+    pub fn load(dir: PathBuf) -> Self {
+        let path = dir.join("monitor_blend.json");
+        let blend = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path: Some(path), blend }
+    }
+
+    pub fn set(&mut self, blend: MonitorBlend) -> Result<(), String> {
+        self.blend = blend;
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(&self.blend).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+pub fn default_input_device_name() -> String {
+    cpal::default_host()
+        .default_input_device()
+        .and_then(|d| d.name().ok())
+        .unwrap_or_default()
+}
+
+/// Key of the record latency store: the running output and the default input.
+pub fn latency_devices(audio: &daw_modules::audio_runtime::AudioRuntime) -> String {
+    let output = audio.audio_prefs().output_device.unwrap_or_else(|| {
+        cpal::default_host().default_output_device().and_then(|d| d.name().ok()).unwrap_or_default()
+    });
+    format!("{} -> {}", output, default_input_device_name())
+}
+
+/// Stored round-trip latency for the current devices, 0 until calibrated.
+pub fn record_latency_ms(state: &AppState) -> f64 {
+    let devices = match state.audio.lock() {
+        Ok(audio) => latency_devices(&audio),
+        Err(_) => return 0.0,
+    };
+    state.record_latency.lock().map(|store| store.get(&devices)).unwrap_or(0.0)
+}
+
+/// Hardware inputs a take on `device` (`None`: the default input) records, from its saved
+/// selection; `None` when every input is recorded.
+pub fn selected_input_channels(state: &AppState, device: Option<&str>) -> Result<Option<Vec<usize>>, String> {
+    let name = device.map(str::to_string).unwrap_or_else(default_input_device_name);
+    let selection = state.input_selection.lock()
+        .map(|store| store.get(&name))
+        .unwrap_or_default();
+    if selection.mode == CaptureMode::All {
+        return Ok(None);
+    }
+    let (_, device_channels, _) = daw_modules::recorder::input::probe_input(device).map_err(|e| e.to_string())?;
+    selection.channels(device_channels).map(Some).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_input_gain_db(gain_db: f32, state: State<AppState>) -> Result<f32, String> {
+    let gain_db = gain_db.clamp(
+        daw_modules::recorder::input::MIN_INPUT_GAIN_DB,
+        daw_modules::recorder::input::MAX_INPUT_GAIN_DB,
+    );
+
+    // Apply live if we are recording or checking levels, and remember it for whichever
+    // device is in use
+    let device = {
+        let rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
+        let meter_guard = state.input_meter.lock().map_err(|_| "Failed to lock input meter")?;
+        match (rec_guard.as_ref(), meter_guard.as_ref()) {
+            (Some(rec), _) => {
+                rec.set_input_gain_db(gain_db);
+                rec.input_device_name().to_string()
+            }
+            (None, Some(meter)) => {
+                meter.set_input_gain_db(gain_db);
+                meter.device_name().to_string()
+            }
+            (None, None) => default_input_device_name(),
+        }
+    };
+    if let Some(retro) = state.retro.lock().map_err(|_| "Failed to lock retro buffer")?.as_ref() {
+        retro.set_input_gain_db(gain_db);
+    }
+
+    let mut store = state.input_gains.lock().map_err(|_| "Failed to lock input settings")?;
+    store.set(device, gain_db)?;
+    Ok(gain_db)
+}
+
+#[tauri::command]
+pub fn get_input_gain_db(state: State<AppState>) -> Result<f32, String> {
+    let rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
+    if let Some(rec) = rec_guard.as_ref() {
+        return Ok(rec.input_gain_db());
+    }
+    let store = state.input_gains.lock().map_err(|_| "Failed to lock input settings")?;
+    Ok(store.get(&default_input_device_name()))
+}
+
+#[tauri::command]
+pub fn clear_input_clip(state: State<AppState>) -> Result<(), String> {
+    let rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
+    if let Some(rec) = rec_guard.as_ref() {
+        rec.clear_input_clip();
+    }
+    if let Some(meter) = state.input_meter.lock().map_err(|_| "Failed to lock input meter")?.as_ref() {
+        meter.clear_clip();
+    }
+    Ok(())
+}
+
+/// Choose which inputs of the default device feed the recorder: `first_channel` (0-based)
+/// alone as mono, `first_channel` and the next as a stereo pair, or every input. Applies
+/// from the next take.
+#[tauri::command]
+pub fn set_input_selection(mode: CaptureMode, first_channel: usize, state: State<AppState>) -> Result<InputSelection, String> {
+    let selection = InputSelection {
+        mode,
+        first_channel: if mode == CaptureMode::All { 0 } else { first_channel },
+    };
+    let (device, device_channels, _) = daw_modules::recorder::input::probe_default_input().map_err(|e| e.to_string())?;
+    selection.channels(device_channels).map_err(|e| e.to_string())?;
+
+    let mut store = state.input_selection.lock().map_err(|_| "Failed to lock input settings")?;
+    store.set(device, selection)?;
+    Ok(selection)
+}
+
+#[tauri::command]
+pub fn get_input_selection(state: State<AppState>) -> Result<InputSelection, String> {
+    let store = state.input_selection.lock().map_err(|_| "Failed to lock input settings")?;
+    Ok(store.get(&default_input_device_name()))
+}
+
+/// Post-gain input levels, readable before a take so levels can be set without recording.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputLevelsReading {
+    pub peak: f32, // linear, held since the last read
+    pub rms: f32,  // linear, last input block
+    pub clipped: bool,
+    pub gain_db: f32,
+}
+
+/// Open the default input for metering only (with its saved trim). A no-op while
+/// recording, whose own meter `get_input_levels` then reads.
+#[tauri::command]
+pub fn start_input_meter(state: State<AppState>) -> Result<(), String> {
+    if state.recorder.lock().map_err(|_| "Failed to lock recorder")?.is_some() {
+        return Ok(());
+    }
+    let mut meter = state.input_meter.lock().map_err(|_| "Failed to lock input meter")?;
+    if meter.is_none() {
+        let gain_db = state.input_gains.lock()
+            .map(|store| store.get(&default_input_device_name()))
+            .unwrap_or(0.0);
+        *meter = Some(InputMeter::start(gain_db).map_err(|e| e.to_string())?);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_input_meter(state: State<AppState>) -> Result<(), String> {
+    *state.input_meter.lock().map_err(|_| "Failed to lock input meter")? = None;
+    Ok(())
+}
+
+/// Levels of the running take, else of the level check. Errors when neither is open.
+#[tauri::command]
+pub fn get_input_levels(state: State<AppState>) -> Result<InputLevelsReading, String> {
+    let rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
+    if let Some(rec) = rec_guard.as_ref() {
+        let (peak, rms) = rec.take_input_meter();
+        return Ok(InputLevelsReading { peak, rms, clipped: rec.is_input_clipped(), gain_db: rec.input_gain_db() });
+    }
+    let meter_guard = state.input_meter.lock().map_err(|_| "Failed to lock input meter")?;
+    let meter = meter_guard.as_ref().ok_or("Input meter is not running")?;
+    let (peak, rms) = meter.take_meter();
+    Ok(InputLevelsReading { peak, rms, clipped: meter.is_clipped(), gain_db: meter.input_gain_db() })
+}
+
+/// Set the performer's cue balance. Only affects what is heard while monitoring: the
+/// speakers hear it only with `cue_on_speakers` (kept as it was when not given).
+#[tauri::command]
+pub fn set_monitor_blend(
+    input_level: f32,
+    playback_level: f32,
+    cue_on_speakers: Option<bool>,
+    state: State<AppState>,
+) -> Result<MonitorBlend, String> {
+    let max = daw_modules::engine::cue::MAX_CUE_LEVEL;
+    let cue_on_speakers = match cue_on_speakers {
+        Some(on) => on,
+        None => state.monitor_blend.lock().map_err(|_| "Failed to lock monitor settings")?.blend.cue_on_speakers,
+    };
+    let blend = MonitorBlend {
+        input_level: if input_level.is_finite() { input_level.clamp(0.0, max) } else { 1.0 },
+        playback_level: if playback_level.is_finite() { playback_level.clamp(0.0, max) } else { 1.0 },
+        cue_on_speakers,
+    };
+
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    blend.apply(&audio);
+    drop(audio);
+
+    let mut store = state.monitor_blend.lock().map_err(|_| "Failed to lock monitor settings")?;
+    store.set(blend)?;
+    Ok(blend)
+}
+
+#[tauri::command]
+pub fn get_monitor_blend(state: State<AppState>) -> Result<MonitorBlend, String> {
+    let store = state.monitor_blend.lock().map_err(|_| "Failed to lock monitor settings")?;
+    Ok(store.blend)
+}
+
+/// Play clicks through the output, record them back and store the measured latency for
+/// the current devices. Not while recording; blocks for a few seconds.
+#[tauri::command]
+pub async fn calibrate_record_latency(app: tauri::AppHandle) -> Result<RoundTripLatency, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        if state.recorder.lock().map_err(|_| "Failed to lock recorder")?.is_some() {
+            return Err("Stop recording before calibrating".to_string());
+        }
+        // The measurement opens the input itself
+        *state.input_meter.lock().map_err(|_| "Failed to lock input meter")? = None;
+
+        let devices = latency_devices(&*state.audio.lock().map_err(|_| "Failed to lock audio")?);
+        // The audio lock is only held per click, so other commands keep working meanwhile
+        let result = measure_round_trip(|| {
+            state.audio.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).send_latency_ping()
+        })
+        .map_err(|e| e.to_string())?;
+        state.record_latency.lock().map_err(|_| "Failed to lock latency settings")?.set(devices, result.latency_ms)?;
+        Ok(result)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Round-trip latency used to line takes up on the current devices (ms).
+#[tauri::command]
+pub fn get_record_latency_ms(state: State<AppState>) -> f64 {
+    record_latency_ms(&state)
+}
+
+/// Override the latency for the current devices by hand (ms, 0 to 1000).
+#[tauri::command]
+pub fn set_record_latency_ms(latency_ms: f64, state: State<AppState>) -> Result<f64, String> {
+    let latency_ms = if latency_ms.is_finite() { latency_ms.clamp(0.0, 1000.0) } else { 0.0 };
+    let devices = latency_devices(&*state.audio.lock().map_err(|_| "Failed to lock audio")?);
+    state.record_latency.lock().map_err(|_| "Failed to lock latency settings")?.set(devices, latency_ms)?;
+    Ok(latency_ms)
+}
@@ -7,22 +7,47 @@
 mod stem_separation;
 mod ai_transaction;
 mod automation;
+mod input_settings;
+mod output_settings;
+mod take_recovery;
+mod global_shortcuts;
+mod settings;
 pub mod effects;
 
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration};
 use std::collections::HashMap;
+use std::ops::ControlFlow;
 use tauri::{State, Emitter, Manager};
 use cpal::traits::{HostTrait, DeviceTrait};
 use dotenv::dotenv;
 
 // Import modules
-use daw_modules::audio_runtime::AudioRuntime;
+use daw_modules::audio_runtime::{AudioRuntime, EngineHealth};
+use daw_modules::session::bounce::{BounceOptions, BounceResult};
+use daw_modules::session::multitrack::MultitrackResult;
+use daw_modules::session::recovery::RecoveryReport;
+use daw_modules::session::ingest::{self, IngestOptions, IngestResult, IngestedTrack};
+use daw_modules::session::stats::{ProjectStats, UNCOLLECTED_MEDIA_WARN_BYTES};
 use daw_modules::recorder::Recorder;
-use daw_modules::waveform::Waveform;
+use daw_modules::recorder::retro::RetroBuffer;
+use daw_modules::waveform::{Waveform, WaveformBuilder, WaveformChannelMode};
+use daw_modules::waveform::service::{WaveformEvent, WaveformJobOptions, WaveformService};
+use daw_modules::waveform::maintenance::{CacheReport, MaintenanceMode};
 use daw_modules::bpm; // Import the new BPM module
+use daw_modules::classifier;
+use daw_modules::effects::limiter::{GainReductionMeter, LimiterParams};
+use daw_modules::effects::pitch_shift::PitchShiftParams;
+use daw_modules::effects::soft_clip::SoftClipParams;
+use daw_modules::engine::mixer::{AuxBusInfo, BusEffectParams, GroupInfo};
+use daw_modules::effects::chain::{InsertParams, InsertState};
+use daw_modules::engine::automation::AutomationMode;
+use daw_modules::engine::markers::MarkerInfo;
+use daw_modules::engine::metronome::MetronomeConfig;
+use daw_modules::engine::track::TrackKind;
 use daw_modules::engine::time::GridLine; // Import GridLine
+use daw_modules::engine::time::SnapResolution;
 
 
 
@@ -36,10 +61,25 @@ pub struct PendingStemGroup {
 pub struct AppState {
     pub audio: Mutex<AudioRuntime>,
     pub recorder: Mutex<Option<Recorder>>,
+    pub input_meter: Mutex<Option<daw_modules::recorder::input_meter::InputMeter>>, // level check while not recording
+    pub retro: Mutex<Option<RetroBuffer>>, // rolling input buffer for `capture_last`
     pub cache: Mutex<HashMap<String, ImportResult>>,
     pub pending_stems: Mutex<HashMap<String, PendingStemGroup>>,
     pub master_meter: Arc<daw_modules::engine::metering::TrackMeters>,
+    pub limiter_meter: Arc<GainReductionMeter>,
+    pub clip_detect: Arc<daw_modules::engine::clip_detect::ClipReadout>,
+    pub transport_clock: Arc<daw_modules::engine::clock::TransportClock>,
     pub meter_registry: Arc<Mutex<HashMap<u32, Arc<daw_modules::engine::metering::TrackMeters>>>>,
+    pub input_gains: Mutex<input_settings::InputGainStore>,
+    pub record_latency: Mutex<input_settings::RecordLatencyStore>,
+    pub input_selection: Mutex<input_settings::InputSelectionStore>,
+    pub monitor_blend: Mutex<input_settings::MonitorBlendStore>,
+    pub recording_dirs: Mutex<take_recovery::RecordingDirsStore>,
+    pub output_routing: Mutex<output_settings::OutputRoutingStore>,
+    pub shortcuts: Mutex<global_shortcuts::ShortcutStore>,
+    pub settings: Mutex<settings::SettingsStore>,
+    pub peaks_maintenance: Mutex<Option<Arc<std::sync::atomic::AtomicBool>>>, // cancel flag of the running pass
+    pub ingest: Mutex<Option<Arc<std::sync::atomic::AtomicBool>>>, // cancel flag of the running folder ingest
 }
 
 // --- 2. Define Return Struct ---
@@ -52,6 +92,8 @@ pub struct ImportResult {
     pub bins_per_second: f64,
     pub bpm: Option<f32>, // New field for BPM
     pub color: String,
+    /// The file is longer than the analysis ceiling: BPM came from its start only.
+    pub truncated: bool,
 }
 
 // Helper function to build the UI state from the raw track list
@@ -102,6 +144,7 @@ fn build_ui_state(
                     bins_per_second: 100.0,
                     bpm: None,
                     color: "".to_string(),
+                    truncated: false,
                 }
             };
 
@@ -120,6 +163,11 @@ fn build_ui_state(
                 color: color.clone(),
                 waveform: import_result, // <--- Use the cached result
                 clip_number: clip_info.clip_number, // <--- NEW
+                notes: clip_info.notes.clone(),
+                mute_regions: clip_info.mute_regions.clone(),
+                stretch: clip_info.stretch,
+                varispeed: clip_info.varispeed,
+                crossfade: clip_info.crossfade,
             });
         }
 
@@ -141,6 +189,14 @@ fn build_ui_state(
             pan: info.pan,
             muted: info.muted,
             solo: info.solo,
+            armed: info.armed,
+            record_safe: info.record_safe,
+            solo_safe: info.solo_safe,
+            notes: info.notes.clone(),
+            listen: info.listen,
+            fx_bypass: info.fx_bypass,
+            kind: info.kind,
+            kind_manual: info.kind_manual,
             source: source_type,
             volume_automation: info.volume_automation.clone(),
             eq,           // <--- Attach EQ to UI Payload
@@ -153,6 +209,7 @@ fn build_ui_state(
         tracks: results,
         bpm,
         master_gain,
+        audio_prefs: None,
     })
 }
 
@@ -219,9 +276,15 @@ fn get_input_devices() -> Result<Vec<AudioDeviceInfo>, String> {
 }
 
 #[tauri::command]
-fn set_output_device(device_name: String, state: State<AppState>) -> Result<(), String> {
+fn set_output_device(device_name: String, app: tauri::AppHandle, state: State<AppState>) -> Result<(), String> {
+    let input = output_settings::monitored_input(&state);
     let mut audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
     audio.set_output_device(device_name).map_err(|e| e.to_string())?;
+    // Each interface keeps its own routing
+    if let Ok(store) = state.output_routing.lock() {
+        output_settings::apply_saved_routing(&audio, &store);
+    }
+    output_settings::warn_on_feedback(&app, &audio, input, audio.is_monitor_muted());
     Ok(())
 }
 
@@ -243,6 +306,14 @@ fn play(state: State<AppState>) -> Result<(), String> {
     Ok(())
 }
 
+/// Play after the metronome count-in (plain play when the count-in is off).
+#[tauri::command]
+fn play_with_count_in(state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.play_with_count_in();
+    Ok(())
+}
+
 #[tauri::command]
 fn pause(state: State<AppState>) -> Result<(), String> {
     let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
@@ -250,10 +321,225 @@ fn pause(state: State<AppState>) -> Result<(), String> {
     Ok(())
 }
 
+/// Pause and return the playhead to where play began (or to zero, see `set_return_to_start_on_stop`).
+#[tauri::command]
+fn stop(state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.stop();
+    Ok(())
+}
+
+/// `{ "kind": "off" }`, `{ "kind": "bars", "value": 2 }` or `{ "kind": "seconds", "value": 3.5 }`.
+#[tauri::command]
+fn set_preroll(preroll: daw_modules::engine::PreRoll, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_preroll(preroll);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_preroll(state: State<AppState>) -> Result<daw_modules::engine::PreRoll, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.preroll())
+}
+
+#[tauri::command]
+fn set_return_to_start_on_stop(to_start: bool, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_return_to_start_on_stop(to_start);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_return_to_start_on_stop(state: State<AppState>) -> Result<bool, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.return_to_start_on_stop())
+}
+
+/// `"continue"`, `"stop"` or `"loop"`: what happens when playback reaches the end of the last clip.
+#[tauri::command]
+fn set_end_action(action: daw_modules::engine::EndAction, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_end_action(action);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_end_action(state: State<AppState>) -> Result<daw_modules::engine::EndAction, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.end_action())
+}
+
+/// Fade everything out, stop, and mute the monitor (unmute with `set_monitor_muted(false)`).
+#[tauri::command]
+fn panic(state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.panic();
+    Ok(())
+}
+
+#[tauri::command]
+fn set_monitor_muted(muted: bool, app: tauri::AppHandle, state: State<AppState>) -> Result<(), String> {
+    let input = output_settings::monitored_input(&state);
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_monitor_muted(muted);
+    output_settings::warn_on_feedback(&app, &audio, input, muted);
+    Ok(())
+}
+
+/// Playhead as heard (output latency compensated), from the callback's published clock.
+/// Lock-free: never waits on the audio mutex; a seek shows up from the next block on.
 #[tauri::command]
 fn get_position(state: State<AppState>) -> Result<f64, String> {
+    Ok(state.transport_clock.snapshot().audible_position())
+}
+
+/// Timing pair for extrapolating the playhead from `performance.now()`.
+/// Lock-free, so it answers even while the audio mutex is busy.
+#[tauri::command]
+fn get_time_sync(state: State<AppState>) -> daw_modules::engine::clock::TimeSync {
+    state.transport_clock.snapshot()
+}
+
+/// How often `transport-sync` corrections go out while playing.
+const TRANSPORT_SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Push `transport-sync` a few times a second while playing, and once after any seek/play/pause.
+/// `playback-finished` goes out (with the end position in seconds) whenever playback ran past
+/// the last clip since the previous check.
+fn spawn_transport_sync(app: tauri::AppHandle, clock: Arc<daw_modules::engine::clock::TransportClock>) {
+    std::thread::spawn(move || {
+        let mut last_generation = None;
+        let mut last_finished = clock.finished().0;
+        loop {
+            std::thread::sleep(TRANSPORT_SYNC_INTERVAL);
+            let sync = clock.snapshot();
+            let (finished, end_frames) = clock.finished();
+            if finished != last_finished {
+                last_finished = finished;
+                let end_secs = end_frames as f64 / sync.sample_rate.max(1) as f64;
+                let _ = app.emit("playback-finished", serde_json::json!({ "endSecs": end_secs, "playing": sync.playing }));
+            }
+            if sync.playing || last_generation != Some(sync.generation) {
+                last_generation = Some(sync.generation);
+                let _ = app.emit("transport-sync", sync);
+            }
+        }
+    });
+}
+
+/// How often the watchdog looks at the engine.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+/// No audio callback for this long, with a stream open, counts as a stall.
+const ENGINE_STALL: Duration = Duration::from_secs(5);
+/// How often a known-good state is kept for a rebuild.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+/// At most one automatic rebuild per this long, so a device that keeps failing doesn't loop.
+const AUTO_REBUILD_COOLDOWN: Duration = Duration::from_secs(30);
+
+// The audio mutex even when a panicking command poisoned it; `rebuild` repairs what's inside
+fn lock_audio_for_recovery(state: &AppState) -> std::sync::MutexGuard<'_, AudioRuntime> {
+    state.audio.lock().unwrap_or_else(|poisoned| {
+        state.audio.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+// Rebuild, put back the app settings the engine held, and tell the frontend
+fn recover_audio(app: &tauri::AppHandle, state: &AppState, audio: &mut AudioRuntime) -> Result<RecoveryReport, String> {
+    let report = audio.rebuild()?;
+    if let Ok(store) = state.monitor_blend.lock() {
+        store.blend.apply(audio);
+    }
+    if let Ok(store) = state.settings.lock() {
+        store.settings.apply(audio);
+    }
+    let _ = app.emit("engine-recovered", &report);
+    Ok(report)
+}
+
+/// Tear down the engine and stream and rebuild them from the last good state.
+/// Works when a command panicked and left the audio state locked up.
+#[tauri::command]
+fn recover_engine(app: tauri::AppHandle, state: State<AppState>) -> Result<RecoveryReport, String> {
+    let mut audio = lock_audio_for_recovery(&state);
+    recover_audio(&app, &state, &mut audio)
+}
+
+/// Audio settings of the running stream: rate, device buffer, processing block, device.
+#[tauri::command]
+fn get_audio_settings(state: State<AppState>) -> Result<daw_modules::session::serialization::AudioPrefs, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.audio_prefs())
+}
+
+/// Reopen the stream with these settings (on the current output device). `block_size`
+/// fixes the engine's processing quantum (128/256/512/1024 frames), `None` follows the device.
+#[tauri::command]
+fn set_audio_settings(
+    sample_rate: Option<u32>,
+    buffer_size: Option<u32>,
+    block_size: Option<u32>,
+    state: State<AppState>,
+) -> Result<daw_modules::audio_runtime::AppliedAudioPrefs, String> {
+    let mut audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let prefs = daw_modules::session::serialization::AudioPrefs {
+        sample_rate,
+        buffer_size,
+        output_device: audio.audio_prefs().output_device,
+        block_size,
+    };
+    Ok(audio.apply_audio_prefs(&prefs))
+}
+
+/// A new project starts on the device's default rate, buffer and block size.
+#[tauri::command]
+fn reset_audio_settings(state: State<AppState>) -> Result<daw_modules::session::serialization::AudioPrefs, String> {
+    let mut audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.clear_audio_prefs().map_err(|e| e.to_string())?;
+    if let Ok(store) = state.output_routing.lock() {
+        output_settings::apply_saved_routing(&audio, &store);
+    }
+    Ok(audio.audio_prefs())
+}
+
+/// Audio blocks played as silence because the engine was busy (never waited on).
+#[tauri::command]
+fn get_audio_dropouts(state: State<AppState>) -> Result<u64, String> {
     let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
-    Ok(audio.position().as_secs_f64())
+    Ok(audio.dropouts())
+}
+
+/// Checkpoint the engine while it's healthy; rebuild it when its lock is poisoned or the
+/// audio callback stops running.
+fn spawn_engine_watchdog(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_checkpoint = std::time::Instant::now();
+        let mut last_rebuild: Option<std::time::Instant> = None;
+        loop {
+            std::thread::sleep(WATCHDOG_INTERVAL);
+            let state = app.state::<AppState>();
+            let mut audio = lock_audio_for_recovery(&state);
+            match audio.engine_health(ENGINE_STALL) {
+                EngineHealth::Ok => {
+                    if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                        audio.checkpoint();
+                        last_checkpoint = std::time::Instant::now();
+                    }
+                }
+                health => {
+                    if last_rebuild.is_some_and(|t| t.elapsed() < AUTO_REBUILD_COOLDOWN) {
+                        continue;
+                    }
+                    log::warn!("Audio engine {:?}, rebuilding", health);
+                    last_rebuild = Some(std::time::Instant::now());
+                    if let Err(e) = recover_audio(&app, &state, &mut audio) {
+                        log::warn!("Automatic engine rebuild failed: {}", e);
+                    }
+                }
+            }
+        }
+    });
 }
 
 #[derive(Clone, serde::Serialize)]
@@ -263,145 +549,234 @@ struct ProgressPayload {
     pub visible: bool,
 }
 
+/// Seconds from the start of an import kept in memory for content classification.
+const CLASSIFY_EXCERPT_SECS: usize = 120;
+/// Least time between two `import-progress` events while a file decodes.
+const IMPORT_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Where an import stands, emitted as `import-progress`.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportProgress {
+    path: String,
+    file: usize, // 1-based
+    files: usize,
+    stage: &'static str, // "preparing", "analyzing", "finalizing" or "done"
+    /// Of the whole import, 0-100.
+    percent: f64,
+}
+
+// `import-progress` plus the loader's `progress-update`; `within` is how far into this file
+fn report_import(app: &tauri::AppHandle, path: &str, file: usize, files: usize, stage: &'static str, within: f64) {
+    let step = 100.0 / files.max(1) as f64;
+    let percent = (file - 1) as f64 * step + step * within.clamp(0.0, 1.0);
+    let message = match stage {
+        "preparing" => format!("Preparing file {} of {}...", file, files),
+        "analyzing" => format!("Analyzing Audio {}...", file),
+        _ => format!("Finalizing {}...", file),
+    };
+    let _ = app.emit("import-progress", ImportProgress { path: path.to_string(), file, files, stage, percent });
+    let _ = app.emit("progress-update", ProgressPayload { message, progress: percent, visible: true });
+}
+
+fn report_import_done(app: &tauri::AppHandle, path: &str, files: usize) {
+    let _ = app.emit("import-progress", ImportProgress { path: path.to_string(), file: files, files, stage: "done", percent: 100.0 });
+    let _ = app.emit("progress-update", ProgressPayload {
+        message: "Import Complete!".into(),
+        progress: 100.0,
+        visible: false
+    });
+}
+
+/// Import every file in turn; `import-progress` reports each one as it decodes.
 #[tauri::command]
 async fn import_tracks( // <--- CHANGED to 'async fn' for better UI behavior
     app: tauri::AppHandle,
     paths: Vec<String>, 
     state: State<'_, AppState>
 ) -> Result<Vec<ImportResult>, String> { 
-    
-    let total_files = paths.len() as f64;
     let mut results = Vec::new();
-
     for (i, path) in paths.iter().enumerate() {
-        let file_num = i + 1;
-        let step_size = 100.0 / total_files;
-        let base_progress = (i as f64) * step_size;
-
-        // --- STEP 1: PREPARING (Fast) ---
-        let _ = app.emit("progress-update", ProgressPayload { 
-            message: format!("Preparing file {} of {}...", file_num, total_files),
-            progress: base_progress + (step_size * 0.05), 
-            visible: true 
-        });
+        results.push(import_file(&app, &state, path, i + 1, paths.len()).await?);
+    }
+    report_import_done(&app, paths.last().map(String::as_str).unwrap_or(""), paths.len());
+    Ok(results)
+}
 
-        // LOCK SCOPE: Only lock audio for the split second we need to add the track
-        // LOCK SCOPE: Add track AND Set Name
-        // Capture the assigned color directly from the backend
-        let assigned_color = {
-            let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
-            audio.add_track(path.clone()).map_err(|e| e.to_string())?;
-            
-            // Set Name and Get Color
-            let track_list = audio.get_tracks_list();
-            let id = track_list.len() - 1; 
-            
-            let filename = std::path::Path::new(&path)
-                .file_name().unwrap_or_default().to_string_lossy().to_string();
-            
-            audio.set_track_name(id, filename);
-            
-            // Return the color the backend generated
-            track_list[id].color.clone() 
-        };
+/// Import one file. Decoding, the waveform and tempo analysis run off the UI thread, with
+/// `import-progress` events along the way (as `load_project` reports `load-progress`);
+/// resolves once the track is in and analysed.
+#[tauri::command]
+async fn import_track(app: tauri::AppHandle, path: String, state: State<'_, AppState>) -> Result<ImportResult, String> {
+    let result = import_file(&app, &state, &path, 1, 1).await?;
+    report_import_done(&app, &path, 1);
+    Ok(result)
+}
 
-        // --- STEP 2: DECODING (Heavy) ---
-        let _ = app.emit("progress-update", ProgressPayload { 
-            message: format!("Decoding Audio Data {}...", file_num),
-            progress: base_progress + (step_size * 0.15), 
-            visible: true 
+// Add `path` as a new track and analyse it: file `file` of `files` in one import
+async fn import_file(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    path: &str,
+    file: usize,
+    files: usize,
+) -> Result<ImportResult, String> {
+    // --- STEP 1: PREPARING (Fast) ---
+    report_import(app, path, file, files, "preparing", 0.05);
+
+    // Tags name the track when the file has them; the filename otherwise. The probe also
+    // gives the length the analysis progress is measured against.
+    let path_meta = path.to_string();
+    let meta = tauri::async_runtime::spawn_blocking(move || {
+        bpm::metadata::probe_file_metadata(&path_meta).ok()
+    }).await.unwrap_or(None);
+    let expected_frames = meta.as_ref().map(|m| m.frames).unwrap_or(0);
+    let tagged_name = meta.and_then(|m| m.display_name());
+
+    // LOCK SCOPE: Only lock audio for the split second we need to add the track
+    // LOCK SCOPE: Add track AND Set Name
+    // Capture the assigned color directly from the backend
+    let (assigned_color, track_id) = {
+        let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+        audio.add_track(path.to_string()).map_err(|e| e.to_string())?;
+        
+        // Set Name and Get Color
+        let track_list = audio.get_tracks_list();
+        let id = track_list.len() - 1; 
+        
+        let filename = std::path::Path::new(path)
+            .file_name().unwrap_or_default().to_string_lossy().to_string();
+        
+        audio.set_track_name(id, tagged_name.unwrap_or(filename));
+        
+        // Return the color the backend generated
+        (track_list[id].color.clone(), track_list[id].id)
+    };
+
+    // --- STEP 2: ANALYSIS (Heavy) ---
+    report_import(app, path, file, files, "analyzing", 0.15);
+
+    // We clone path so we can move it into the thread
+    let path_clone = path.to_string();
+    let app_progress = app.clone();
+
+    // RUN HEAVY TASK ON SEPARATE THREAD so UI doesn't freeze
+    // One streaming pass: waveform and tempo are fed block by block from the decoder,
+    // so memory stays flat however long the file is. Only an excerpt is kept, for the
+    // content guess.
+    let (wf, detected_bpm, excerpt, sr, channels) = tauri::async_runtime::spawn_blocking(move || {
+        let mut builders: Option<(WaveformBuilder, bpm::BpmStream)> = None;
+        let mut excerpt = Vec::<f32>::new();
+        let mut decoded = 0u64;
+        let mut last_report = std::time::Instant::now();
+        let (sr, channels, frames) = bpm::adapter::decode_chunks(&path_clone, None, |samples, sr, channels| {
+            let (wf, tempo) = builders.get_or_insert_with(|| {
+                let opts = bpm::BpmOptions { compute_beats: true, ..Default::default() };
+                (WaveformBuilder::new(sr, channels, 512), bpm::BpmStream::new(channels, sr, opts))
+            });
+            wf.push(samples);
+            tempo.push(samples);
+            let room = (CLASSIFY_EXCERPT_SECS * sr as usize * channels).saturating_sub(excerpt.len());
+            excerpt.extend_from_slice(&samples[..room.min(samples.len())]);
+
+            // Analysis runs from 15% to 95% of this file's share
+            decoded += (samples.len() / channels.max(1)) as u64;
+            if expected_frames > 0 && last_report.elapsed() >= IMPORT_PROGRESS_INTERVAL {
+                last_report = std::time::Instant::now();
+                let done = (decoded as f64 / expected_frames as f64).min(1.0);
+                report_import(&app_progress, &path_clone, file, files, "analyzing", 0.15 + 0.8 * done);
+            }
+            ControlFlow::Continue(())
+        })?;
+        let (wf, tempo) = builders.unwrap_or_else(|| {
+            (WaveformBuilder::new(sr, channels, 512), bpm::BpmStream::new(channels, sr, Default::default()))
         });
+        log::info!("📊 [Import] Streamed {} frames", frames);
+        bpm::cache::shared().note_format(&path_clone, sr, channels, frames);
+        Ok::<_, bpm::adapter::DecodeError>((wf.finish(), tempo.finish().map(|res| res.bpm), excerpt, sr, channels))
+    }).await.map_err(|e| e.to_string())?.map_err(|e| {
+        log::error!("Failed to decode {}: {}", path, e);
+        format!("Failed to decode {}: {}", path, e)
+    })?;
+
+    // Content guess for the track icon, off the import path (nobody waits on it)
+    spawn_content_classification(app.clone(), track_id, excerpt, sr, channels);
+
+    // --- ADD THIS DEBUG BLOCK ---
+    println!("--------------------------------------------------");
+    println!("📊 BACKEND TRUTH:");
+    println!("   - Duration:     {:.6} seconds", wf.duration_secs);
+    println!("   - Channels:     {}", channels);
+    println!("   - Rate:         {}", sr);
+    
+    let target_width = wf.duration_secs * 50.0;
+    println!("   - Target Width: {:.4} px (at 1x Zoom)", target_width);
+    println!("--------------------------------------------------");
+    // ----------------------------
 
-        // We clone path so we can move it into the thread
-        let path_clone = path.clone();
+    // --- STEP 5: FINALIZE ---
+    report_import(app, path, file, files, "finalizing", 0.95);
 
-        // RUN HEAVY TASK ON SEPARATE THREAD so UI doesn't freeze
-        let (samples, sr, channels) = tauri::async_runtime::spawn_blocking(move || {
-            bpm::adapter::decode_to_vec(&path_clone)
-        }).await.map_err(|e| e.to_string())?.map_err(|e| format!("Failed to decode: {}", e))?;
+    let pixels_per_second = 100.0;
+    let spp = (sr as f64) / pixels_per_second;
+    let (mins, maxs) = wf.bins_for(spp, WaveformChannelMode::MixMono, 0, usize::MAX).first();
 
-        // --- STEP 3: WAVEFORM (Heavy) ---
-        let _ = app.emit("progress-update", ProgressPayload { 
-            message: format!("Generating Waveform {}...", file_num),
-            progress: base_progress + (step_size * 0.50), 
-            visible: true 
-        });
+    // 1. Create the result object first
+    let result = ImportResult {
+        mins: mins.to_vec(),
+        maxs: maxs.to_vec(),
+        duration: wf.duration_secs,
+        bins_per_second: if wf.duration_secs > 0.0 { (mins.len() as f64) / wf.duration_secs } else { 0.0 },
+        bpm: detected_bpm,
+        color: assigned_color,
+        truncated: false, // streamed end to end
+    };
 
-        let samples_clone = samples.clone();
-        
-        // RUN HEAVY TASK ON SEPARATE THREAD
-        let wf = tauri::async_runtime::spawn_blocking(move || {
-            Waveform::build_from_samples(&samples_clone, sr, channels, 512)
-        }).await.map_err(|e| e.to_string())?;
-
-        // --- STEP 4: BPM (Heavy) ---
-        let _ = app.emit("progress-update", ProgressPayload { 
-            message: format!("Detecting Tempo {}...", file_num),
-            progress: base_progress + (step_size * 0.80), 
-            visible: true 
-        });
+    // 2. WRITE TO CACHE (This is the critical fix)
+    // We lock the cache briefly to store the data for future lookups
+    if let Ok(mut cache) = state.cache.lock() {
+        cache.insert(path.to_string(), result.clone());
+    }
 
+    Ok(result)
+}
 
-        // --- ADD THIS DEBUG BLOCK ---
-        println!("--------------------------------------------------");
-        println!("📊 BACKEND TRUTH:");
-        println!("   - Duration:     {:.6} seconds", wf.duration_secs);
-        println!("   - Samples:      {}", samples.len());
-        println!("   - Channels:     {}", channels);
-        println!("   - Rate:         {}", sr);
-        
-        let target_width = wf.duration_secs * 50.0;
-        println!("   - Target Width: {:.4} px (at 1x Zoom)", target_width);
-        println!("--------------------------------------------------");
-        // ----------------------------
-
-
-        let samples_bpm = samples.clone();
-        let detected_bpm = tauri::async_runtime::spawn_blocking(move || {
-            let mut det = bpm::BpmDetector::new(2048);
-            let opts = bpm::BpmOptions { compute_beats: true, ..Default::default() };
-            det.detect(&samples_bpm, channels, sr, opts).map(|res| res.bpm)
-        }).await.map_err(|e| e.to_string())?;
-
-        // --- STEP 5: FINALIZE ---
-        let _ = app.emit("progress-update", ProgressPayload { 
-            message: format!("Finalizing {}...", file_num),
-            progress: base_progress + (step_size * 0.95), 
-            visible: true 
-        });
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TrackClassifiedPayload {
+    track_id: u32,
+    kind: TrackKind,
+    confidence: f32,
+}
 
-        let pixels_per_second = 100.0;
-        let spp = (sr as f64) / pixels_per_second;
-        let (mins, maxs, _) = wf.bins_for(spp, 0, 0, usize::MAX);
+/// Guess drums/bass/vocals from the decoded audio and tag the track, unless the user already
+/// picked a kind or the guess is too weak. Emits `track-classified` when a tag was set.
+fn spawn_content_classification(app: tauri::AppHandle, track_id: u32, samples: Vec<f32>, sr: u32, channels: usize) {
+    tauri::async_runtime::spawn_blocking(move || {
+        let profile = daw_modules::analyzer::analyze_audio_buffer(&samples, channels, sr);
+        let onsets = classifier::onset_rate(&samples, channels, sr);
+        let (kind, confidence) = classifier::classify_content(&profile, onsets);
+        if kind == TrackKind::Other || confidence < classifier::MIN_CLASSIFY_CONFIDENCE {
+            return;
+        }
 
-        // 1. Create the result object first
-        let result = ImportResult {
-            mins: mins.to_vec(),
-            maxs: maxs.to_vec(),
-            duration: wf.duration_secs,
-            bins_per_second: if wf.duration_secs > 0.0 { (mins.len() as f64) / wf.duration_secs } else { 0.0 },
-            bpm: detected_bpm,
-            color: assigned_color,
+        let state = app.state::<AppState>();
+        let applied = match state.audio.lock() {
+            Ok(audio) => audio.apply_detected_kind(track_id, kind),
+            Err(_) => false,
         };
-
-        // 2. WRITE TO CACHE (This is the critical fix)
-        // We lock the cache briefly to store the data for future lookups
-        if let Ok(mut cache) = state.cache.lock() {
-            cache.insert(path.clone(), result.clone());
+        if applied {
+            log::info!("🏷️ Track {} looks like {:?} ({:.0}%)", track_id, kind, confidence * 100.0);
+            let _ = app.emit("track-classified", TrackClassifiedPayload { track_id, kind, confidence });
         }
-
-        // 3. Push to results for the UI
-        results.push(result);
-    }
-
-    // --- DONE ---
-    let _ = app.emit("progress-update", ProgressPayload { 
-        message: "Import Complete!".into(), 
-        progress: 100.0, 
-        visible: false 
     });
+}
 
-    Ok(results)
+/// Manual kind (`null` clears it). Auto-classification never overrides a manual choice.
+#[tauri::command]
+fn set_track_kind(track_id: u32, kind: Option<TrackKind>, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_track_kind(track_id, kind)
 }
 
 #[tauri::command]
@@ -409,18 +784,27 @@ async fn analyze_file(path: String, state: State<'_, AppState>) -> Result<Import
     // Offload the heavy DSP work to a background thread
     let path_clone = path.clone();
     let result = tauri::async_runtime::spawn_blocking(move || {
-        let (samples, sr, channels) = bpm::adapter::decode_to_vec(&path_clone)
-            .map_err(|e| format!("Failed to decode: {}", e))?;
+        // Shared decode: a file already probed or analysed elsewhere isn't decoded again
+        let pcm = bpm::cache::shared().get(&path_clone).map_err(|e| {
+            log::error!("Failed to decode {}: {}", path_clone, e);
+            format!("Failed to decode {}: {}", path_clone, e)
+        })?;
+        let (sr, channels) = (pcm.sample_rate, pcm.channels);
         
-        let wf = Waveform::build_from_samples(&samples, sr, channels, 512);
+        // A capped decode would give a short waveform, so stream the whole file instead
+        let wf = if pcm.truncated {
+            Waveform::build_from_path(&path_clone, 512).map_err(|e| format!("Failed to build waveform: {}", e))?
+        } else {
+            Waveform::build_from_samples(&pcm.samples, sr, channels, 512)
+        };
         
         let mut det = bpm::BpmDetector::new(2048);
         let opts = bpm::BpmOptions { compute_beats: true, ..Default::default() };
-        let detected_bpm = det.detect(&samples, channels, sr, opts).map(|res| res.bpm);
+        let detected_bpm = det.detect(&pcm.samples, channels, sr, opts).map(|res| res.bpm);
 
         let pixels_per_second = 100.0;
         let spp = (sr as f64) / pixels_per_second;
-        let (mins, maxs, _) = wf.bins_for(spp, 0, 0, usize::MAX);
+        let (mins, maxs) = wf.bins_for(spp, WaveformChannelMode::MixMono, 0, usize::MAX).first();
 
         Ok::<ImportResult, String>(ImportResult {
             mins: mins.to_vec(),
@@ -429,6 +813,7 @@ async fn analyze_file(path: String, state: State<'_, AppState>) -> Result<Import
             bins_per_second: if wf.duration_secs > 0.0 { (mins.len() as f64) / wf.duration_secs } else { 0.0 },
             bpm: detected_bpm,
             color: "".to_string(), 
+            truncated: pcm.truncated,
         })
     }).await.map_err(|e| e.to_string())??; // Double unwrap for thread panic & our error
 
@@ -440,13 +825,14 @@ async fn analyze_file(path: String, state: State<'_, AppState>) -> Result<Import
     Ok(result)
 }
 
+/// Returns the start the clip landed on (snapped to the grid when snapping is on).
 #[tauri::command]
 fn move_clip(
     track_id: u32, 
     clip_index: usize, 
     new_time: f64, 
     state: State<AppState>
-) -> Result<(), String> {
+) -> Result<f64, String> {
     let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
 
     let list = audio.get_tracks_list();
@@ -454,9 +840,20 @@ fn move_clip(
     
     // Call the runtime logic we just added
     audio.move_clip(index, clip_index, new_time)
-        .map_err(|e| e.to_string())?;
-        
-    Ok(())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_snap_resolution(state: State<AppState>) -> Result<SnapResolution, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.snap_resolution())
+}
+
+/// "off", "bar", "beat", "eighth" or "sixteenth".
+#[tauri::command]
+fn set_snap_resolution(resolution: SnapResolution, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_snap_resolution(resolution)
 }
 
 #[derive(serde::Serialize)]
@@ -467,108 +864,879 @@ struct RecordingState {
     // For now, let's send the current RMS (volume) for the meter
     current_rms: f32,
     is_monitoring: bool, 
+    input_peak: f32,     // post-gain, linear
+    input_clipped: bool, // latched until clear_input_clip
+    input_gain_db: f32,
+    is_paused: bool,
+}
+
+/// Frontend description of one record target (an armed track and the inputs feeding it).
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordTargetArgs {
+    track_id: Option<u32>,
+    path: String,
+    input_channels: Vec<usize>, // 0-based hardware inputs
+    #[serde(default)]
+    device: Option<String>, // input device name; the default input when absent
 }
 
 #[tauri::command]
-fn start_recording(path: String, state: State<AppState>) -> Result<(), String> {
+fn start_recording(
+    path: String,
+    targets: Option<Vec<RecordTargetArgs>>,
+    latency_ms: Option<f64>,
+    app: tauri::AppHandle,
+    state: State<AppState>
+) -> Result<(), String> {
     let mut rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
-    let mut new_recorder = Recorder::start(PathBuf::from(path)).map_err(|e| e.to_string())?;
+
+    // The recorder opens the input itself; a level check in progress hands over to it
+    if let Ok(mut meter) = state.input_meter.lock() {
+        *meter = None;
+    }
+
+    // Restore the saved preamp for the device we are about to open
+    let input_gain_db = state.input_gains.lock()
+        .map(|store| store.get(&input_settings::default_input_device_name()))
+        .unwrap_or(0.0);
+
+    // Record-safe tracks never receive a take, whatever the frontend sent
+    let targets = match targets {
+        Some(targets) => {
+            let targets = targets.into_iter()
+                .map(|t| daw_modules::recorder::RecordTarget {
+                    track_id: t.track_id,
+                    path: PathBuf::from(t.path),
+                    input_channels: t.input_channels,
+                    device: t.device,
+                })
+                .collect();
+            let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+            Some(audio.recordable_targets(targets)?)
+        }
+        None => None,
+    };
+
+    // Remember where takes go so a crash mid-take can be recovered on next launch
+    if let Ok(mut store) = state.recording_dirs.lock() {
+        let take_paths: Vec<PathBuf> = match &targets {
+            Some(targets) if !targets.is_empty() => targets.iter().map(|t| t.path.clone()).collect(),
+            _ => vec![PathBuf::from(&path)],
+        };
+        for p in &take_paths {
+            if let Err(e) = store.remember(p) {
+                log::warn!("Could not remember recording folder: {}", e);
+            }
+        }
+    }
+
+    // Every take is placed at the playhead it started from
+    let start_time = {
+        let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+        audio.position()
+    };
+    // Without an explicit value the calibrated latency of the current devices applies
+    let latency_ms = latency_ms.unwrap_or_else(|| input_settings::record_latency_ms(&state));
+    let latency = Duration::from_secs_f64(latency_ms.max(0.0) / 1000.0);
+    let mut new_recorder = match targets {
+        // Multi-input take: one file per target
+        Some(targets) if !targets.is_empty() => {
+            Recorder::start_targets(targets, input_gain_db, start_time, latency).map_err(|e| e.to_string())?
+        }
+        // Single-file take from the selected inputs: lands on a new track when recording stops
+        _ => {
+            let input_channels = input_settings::selected_input_channels(&state, None)?;
+            Recorder::start_with_gain(PathBuf::from(path), input_gain_db, input_channels, start_time, latency)
+                .map_err(|e| e.to_string())?
+        }
+    };
     
     // Detach the monitor and send it to the Audio Thread natively!
     if let Some(monitor) = new_recorder.monitor.take() {
         if let Ok(audio) = state.audio.lock() {
             audio.set_monitor(monitor);
+            let input = Some(new_recorder.input_device_name().to_string());
+            output_settings::warn_on_feedback(&app, &audio, input, audio.is_monitor_muted());
         }
     }
     
+    // A hover preview must not bleed into the take
+    if let Ok(audio) = state.audio.lock() {
+        audio.stop_preview();
+        audio.set_recording(true);
+    }
+
     *rec_guard = Some(new_recorder);
     Ok(())
 }
 
+/// One take across every armed track: each gets its own file from its record inputs, and
+/// the transport starts with it so the clips land at the playhead. Returns the recorded
+/// track ids; `stop_recording` places the clips.
+#[tauri::command]
+fn start_armed_recording(
+    latency_ms: Option<f64>,
+    app: tauri::AppHandle,
+    state: State<AppState>
+) -> Result<Vec<u32>, String> {
+    let armed = {
+        let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+        audio.armed_record_inputs()
+    };
+    if armed.is_empty() {
+        return Err("No track armed".to_string());
+    }
+
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let mut targets = Vec::with_capacity(armed.len());
+    for (track_id, device, inputs) in &armed {
+        // Each track records from its own device (e.g. a USB mic next to the interface)
+        let (name, device_channels, _) = daw_modules::recorder::input::probe_input(device.as_deref())
+            .map_err(|e| format!("Track {}: {}", track_id, e))?;
+        if let Some(bad) = inputs.iter().find(|&&c| c >= device_channels) {
+            return Err(format!("Track {} records input {} but {} has {}", track_id, bad + 1, name, device_channels));
+        }
+        // Tracks without their own inputs record the device's input selection
+        let input_channels = if inputs.is_empty() {
+            input_settings::selected_input_channels(&state, device.as_deref())?
+                .unwrap_or_else(|| (0..device_channels).collect())
+        } else {
+            inputs.clone()
+        };
+        targets.push(RecordTargetArgs {
+            track_id: Some(*track_id),
+            path: get_temp_path(format!("Recording_{}_{}.wav", track_id, stamp), state.clone()),
+            input_channels,
+            device: device.clone(),
+        });
+    }
+
+    let was_playing = {
+        let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+        let playing = audio.is_playing();
+        if !playing {
+            audio.play();
+        }
+        playing
+    };
+    if let Err(e) = start_recording(String::new(), Some(targets), latency_ms, app, state.clone()) {
+        if !was_playing {
+            if let Ok(audio) = state.audio.lock() {
+                audio.pause();
+            }
+        }
+        return Err(e);
+    }
+    Ok(armed.into_iter().map(|(id, _, _)| id).collect())
+}
+
+/// Hardware inputs (0-based) a take on the track captures; empty records the input selection.
+#[tauri::command]
+fn set_track_record_inputs(track_id: u32, inputs: Vec<usize>, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.set_track_record_inputs(index, inputs)
+}
+
+#[tauri::command]
+fn get_track_record_inputs(track_id: u32, state: State<AppState>) -> Result<Vec<usize>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.track_record_inputs(index)
+}
+
+/// Input device (by name, as listed by `get_input_devices`) the track records from; `null`
+/// is the default input. Armed tracks on different devices record in one take, each device
+/// drift corrected onto the first one's clock.
+#[tauri::command]
+fn set_track_record_device(track_id: u32, device: Option<String>, state: State<AppState>) -> Result<(), String> {
+    if let Some(name) = device.as_deref() {
+        daw_modules::recorder::input::probe_input(Some(name)).map_err(|e| e.to_string())?;
+    }
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.set_track_record_device(index, device)
+}
+
+#[tauri::command]
+fn get_track_record_device(track_id: u32, state: State<AppState>) -> Result<Option<String>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.track_record_device(index)
+}
+
+#[tauri::command]
+fn get_recording_status(state: State<AppState>) -> Result<RecordingState, String> {
+    let rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
+    
+    if let Some(rec) = rec_guard.as_ref() {
+        let duration = rec.get_record_time().as_secs_f64();
+        let (input_peak, current_rms) = rec.take_input_meter();
+        
+        Ok(RecordingState {
+            is_recording: true,
+            duration,
+            current_rms,
+            is_monitoring: rec.is_monitor_enabled(), // <--- Fetch real state
+            input_peak,
+            input_clipped: rec.is_input_clipped(),
+            input_gain_db: rec.input_gain_db(),
+            is_paused: rec.is_paused(),
+        })
+    } else {
+        Ok(RecordingState {
+            is_recording: false,
+            duration: 0.0,
+            current_rms: 0.0,
+            is_monitoring: false, // Default off
+            input_peak: 0.0,
+            input_clipped: false,
+            input_gain_db: 0.0,
+            is_paused: false,
+        })
+    }
+}
+
+/// Hold the running take without closing its files; `resume_recording` carries on in the
+/// same clip, which is split at the pause when recording stops.
+#[tauri::command]
+fn pause_recording(state: State<AppState>) -> Result<(), String> {
+    let rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
+    rec_guard.as_ref().ok_or("Not recording")?.pause();
+    Ok(())
+}
+
+#[tauri::command]
+fn resume_recording(state: State<AppState>) -> Result<(), String> {
+    let rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
+    rec_guard.as_ref().ok_or("Not recording")?.resume();
+    Ok(())
+}
+
+#[tauri::command]
+fn toggle_monitor_cmd(state: State<AppState>) -> Result<bool, String> {
+    let mut rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
+    if let Some(rec) = rec_guard.as_mut() {
+        rec.toggle_monitor().map_err(|e| e.to_string())?;
+        Ok(rec.is_monitor_enabled())
+    } else {
+        // If not recording, we can't toggle the hardware monitor yet.
+        Ok(false)
+    }
+}
+
+/// What `stop_recording` hands back: every take, the tracks created for untargeted ones,
+/// and why any take couldn't be placed (its file is still on disk).
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StoppedRecording {
+    takes: Vec<daw_modules::recorder::RecordingResult>,
+    new_tracks: Vec<LoadedTrack>,
+    errors: Vec<String>,
+}
+
+#[tauri::command]
+fn stop_recording(
+    state: State<AppState>,
+    waveforms: State<WaveformService>,
+) -> Result<StoppedRecording, String> {
+    let mut rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
+    let mut results = match rec_guard.take() {
+        Some(rec) => rec.stop(),
+        None => Vec::new(),
+    };
+    // Peaks are built in the background; the UI swaps in the real waveform on `waveform-ready`
+    for res in results.iter().filter(|r| r.duration > 0.0) {
+        waveforms.enqueue(res.path.clone(), WaveformJobOptions { force: true, ..Default::default() });
+    }
+    // Tell the audio thread to drop the monitor connection
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio engine")?;
+    audio.clear_monitor();
+    audio.set_recording(false);
+    let (new_tracks, errors) = place_takes(audio, &mut results, &state.cache)?;
+    Ok(StoppedRecording { takes: results, new_tracks, errors })
+}
+
+// Takes land as undoable "Record Take" steps: targeted ones on their tracks, the others on
+// a new track each, at the timeline position they were recorded at. A take that can't be
+// placed doesn't hold up the others. Returns the new tracks and one error per failed take.
+fn place_takes(
+    audio: std::sync::MutexGuard<'_, AudioRuntime>,
+    results: &mut [daw_modules::recorder::RecordingResult],
+    cache: &Mutex<HashMap<String, ImportResult>>,
+) -> Result<(Vec<LoadedTrack>, Vec<String>), String> {
+    let list = audio.get_tracks_list();
+    let mut created = Vec::new();
+    let mut errors = Vec::new();
+    for res in results.iter_mut().filter(|r| r.duration > 0.0) {
+        if let Err(e) = place_take(&audio, &list, res, &mut created) {
+            errors.push(format!("{}: {}", res.path, e));
+        }
+    }
+    if created.is_empty() {
+        return Ok((Vec::new(), errors));
+    }
+
+    let list = audio.get_tracks_list();
+    let mut fx_data = Vec::new();
+    for &id in &created {
+        let index = resolve_track_index(&list, id)?;
+        fx_data.push((audio.get_eq_state(index), audio.get_compressor_state(index), audio.get_reverb_state(index)));
+    }
+    let tracks_info: Vec<_> = list.into_iter().filter(|info| created.contains(&info.id)).collect();
+    let (bpm, master_gain) = (audio.bpm(), audio.master_gain());
+    drop(audio);
+    Ok((build_ui_state(tracks_info, bpm, master_gain, true, cache, fx_data)?.tracks, errors))
+}
+
+// One take of `place_takes`; a track created for it goes into `created` even if splitting
+// it at the pauses fails afterwards.
+fn place_take(
+    audio: &AudioRuntime,
+    list: &[daw_modules::audio_runtime::FrontendTrackInfo],
+    res: &mut daw_modules::recorder::RecordingResult,
+    created: &mut Vec<u32>,
+) -> Result<(), String> {
+    let index = match res.track_id {
+        Some(track_id) => {
+            let index = resolve_track_index(list, track_id)?;
+            audio.commit_record_take(index, res.path.clone(), res.start_time, res.offset)
+                .map_err(|e| e.to_string())?;
+            index
+        }
+        None => {
+            let id = audio.add_record_take_track(res.path.clone(), res.start_time, res.offset)
+                .map_err(|e| e.to_string())?;
+            res.track_id = Some(id);
+            created.push(id);
+            resolve_track_index(&audio.get_tracks_list(), id)?
+        }
+    };
+    // Cut the clip where the take was paused, so each stretch can be moved on its own
+    for gap in res.gaps.iter().filter(|&&g| g > res.offset) {
+        audio.split_clip(index, res.start_time + gap - res.offset).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Keep a rolling buffer of the last `seconds` (5 to 300, default 60) of input so
+/// `capture_last` can save a performance that wasn't recorded. Returns the buffer length,
+/// 0 when turned off.
+#[tauri::command]
+fn set_retro_record(enabled: bool, seconds: Option<f64>, state: State<AppState>) -> Result<f64, String> {
+    let mut retro = state.retro.lock().map_err(|_| "Failed to lock retro buffer")?;
+    *retro = None; // the old buffer (and its input stream) closes first
+    if !enabled {
+        return Ok(0.0);
+    }
+    let input_gain_db = state.input_gains.lock()
+        .map(|store| store.get(&input_settings::default_input_device_name()))
+        .unwrap_or(0.0);
+    let buffer = RetroBuffer::start(seconds.unwrap_or(60.0), input_gain_db, state.transport_clock.clone())
+        .map_err(|e| e.to_string())?;
+    let seconds = buffer.seconds();
+    *retro = Some(buffer);
+    Ok(seconds)
+}
+
+/// Save up to the last `duration` seconds of buffered input that the transport was rolling
+/// through, placed where it was played: on `track_id`, else on a new track.
+#[tauri::command]
+fn capture_last(
+    duration: f64,
+    track_id: Option<u32>,
+    state: State<AppState>,
+    waveforms: State<WaveformService>,
+) -> Result<StoppedRecording, String> {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let path = get_temp_path(format!("Retro_{}.wav", stamp), state.clone());
+    let mut take = {
+        let retro = state.retro.lock().map_err(|_| "Failed to lock retro buffer")?;
+        let retro = retro.as_ref().ok_or("Retro record is off")?;
+        retro.capture_last(duration, std::path::Path::new(&path)).map_err(|e| e.to_string())?
+    };
+    if let Ok(mut store) = state.recording_dirs.lock() {
+        if let Err(e) = store.remember(std::path::Path::new(&path)) {
+            log::warn!("Could not remember recording folder: {}", e);
+        }
+    }
+    take.track_id = track_id;
+    take.offset = input_settings::record_latency_ms(&state) / 1000.0;
+    waveforms.enqueue(take.path.clone(), WaveformJobOptions { force: true, ..Default::default() });
+
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let mut takes = vec![take];
+    let new_tracks = place_takes(audio, &mut takes, &state.cache)?;
+    Ok(StoppedRecording { takes, new_tracks })
+}
+
+/// Loopback mode: record what the master plays (effects, master gain and monitored input
+/// included) while the transport rolls. Returns the file it writes to.
+#[tauri::command]
+fn start_loopback_recording(state: State<AppState>) -> Result<String, String> {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let path = get_temp_path(format!("Loopback_{}.wav", stamp), state.clone());
+    if let Ok(mut store) = state.recording_dirs.lock() {
+        if let Err(e) = store.remember(std::path::Path::new(&path)) {
+            log::warn!("Could not remember recording folder: {}", e);
+        }
+    }
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.start_loopback_recording(path.clone())?;
+    Ok(path)
+}
+
+/// Finish the loopback take and place it on a new track where it was started.
+#[tauri::command]
+fn stop_loopback_recording(state: State<AppState>, waveforms: State<WaveformService>) -> Result<StoppedRecording, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let take = audio.stop_loopback_recording()?;
+    waveforms.enqueue(take.path.clone(), WaveformJobOptions { force: true, ..Default::default() });
+    let mut takes = vec![take];
+    let new_tracks = place_takes(audio, &mut takes, &state.cache)?;
+    Ok(StoppedRecording { takes, new_tracks })
+}
+
+/// Place a finished single-file recording on a track (undo removes it and trashes the file).
+#[tauri::command]
+fn commit_recorded_take(track_id: u32, path: String, start_time: f64, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.commit_record_take(index, path, start_time, 0.0).map_err(|e| e.to_string())
+}
+
+
+#[tauri::command]
+fn seek(pos: f64, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    // Convert float seconds to Duration
+    let position = Duration::from_secs_f64(pos.max(0.0));
+    audio.seek(position);
+    Ok(())
+}
+
+// --- Record arm ---
+
+/// Returns the ids of all armed tracks. Errors are structured: `{ kind: "recordSafe", trackId }`.
+#[tauri::command]
+fn arm_track(track_id: u32, armed: bool, state: State<AppState>) -> Result<Vec<u32>, serde_json::Value> {
+    let audio = state.audio.lock().map_err(|_| serde_json::json!({ "kind": "internal", "message": "Failed to lock audio" }))?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)
+        .map_err(|e| serde_json::json!({ "kind": "trackNotFound", "message": e }))?;
+    audio.set_track_armed(index, armed)
+        .map_err(|e| serde_json::to_value(&e).unwrap_or_else(|_| serde_json::json!({ "message": e.to_string() })))
+}
+
+/// Hover preview of a clip from `offset` seconds into it (default length from settings). Off while recording.
+#[tauri::command]
+fn preview_clip_at(track_id: u32, clip_index: usize, offset: f64, duration: Option<f64>, state: State<AppState>) -> Result<(), String> {
+    if state.recorder.lock().map_err(|_| "Failed to lock recorder")?.is_some() {
+        return Err("Preview is disabled while recording".to_string());
+    }
+    let default_secs = state.settings.lock().map_err(|_| "Failed to lock settings")?.settings.preview_secs;
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.preview_clip_at(index, clip_index, offset, duration.unwrap_or(default_secs)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn stop_preview(state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.stop_preview();
+    Ok(())
+}
+
+/// PFL/AFL a track on the cue output; engaging one track releases the previous.
+/// Returns false when nothing plays the listen signal (no cue outputs, cue not on the speakers).
+#[tauri::command]
+fn set_track_listen(track_id: u32, mode: daw_modules::engine::track::ListenMode, state: State<AppState>) -> Result<bool, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.set_track_listen(index, mode).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_record_safe(track_id: u32, safe: bool, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.set_record_safe(index, safe).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_arm_exclusive(exclusive: bool, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_arm_exclusive(exclusive);
+    Ok(())
+}
+
+// --- Markers (undoable) ---
+
+#[tauri::command]
+fn get_markers(state: State<AppState>) -> Result<Vec<MarkerInfo>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.markers())
+}
+
+/// Returns the new marker's id.
+#[tauri::command]
+fn add_marker(time: f64, name: Option<String>, color: Option<String>, state: State<AppState>) -> Result<u32, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.add_marker(time, name.unwrap_or_default(), color).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn move_marker(id: u32, time: f64, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.move_marker(id, time).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn rename_marker(id: u32, name: String, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.rename_marker(id, name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_marker_color(id: u32, color: String, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_marker_color(id, color).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_marker(id: u32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.delete_marker(id).map_err(|e| e.to_string())
+}
+
+// --- Navigation: each returns the new playhead (seconds), or null if there was nothing to jump to ---
+
+#[tauri::command]
+fn seek_to_marker(id: u32, state: State<AppState>) -> Result<Option<f64>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.seek_to_marker(id).map(|t| t.as_secs_f64()))
+}
+
+#[tauri::command]
+fn seek_next_marker(state: State<AppState>) -> Result<Option<f64>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.seek_to_next_marker().map(|t| t.as_secs_f64()))
+}
+
+#[tauri::command]
+fn seek_previous_marker(state: State<AppState>) -> Result<Option<f64>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.seek_to_previous_marker().map(|t| t.as_secs_f64()))
+}
+
+#[tauri::command]
+fn seek_next_edit_point(state: State<AppState>) -> Result<Option<f64>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.seek_to_next_edit_point().map(|t| t.as_secs_f64()))
+}
+
+#[tauri::command]
+fn seek_previous_edit_point(state: State<AppState>) -> Result<Option<f64>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.seek_to_previous_edit_point().map(|t| t.as_secs_f64()))
+}
+
+#[tauri::command]
+fn set_track_gain(track_id: u32, gain: f32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+
+    audio.set_track_gain(index, gain);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_master_gain(state: tauri::State<AppState>) -> Result<f32, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.master_gain())
+}
+
+#[tauri::command]
+fn set_master_gain(gain: f32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_master_gain(gain);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_master_pitch_shift(state: State<AppState>) -> Result<PitchShiftParams, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.master_pitch_shift()
+}
+
+/// Semitones are clamped to ±12. While active the shifter delays the master by its latency.
+#[tauri::command]
+fn set_master_pitch_shift(params: PitchShiftParams, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_master_pitch_shift(params)
+}
+
+#[tauri::command]
+fn get_master_soft_clip(state: State<AppState>) -> Result<SoftClipParams, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.master_soft_clip()
+}
+
+/// Out-of-range drive/trim are clamped. `bypass: true` leaves the master bit-identical.
+#[tauri::command]
+fn set_master_soft_clip(params: SoftClipParams, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_master_soft_clip(params)
+}
+
+#[tauri::command]
+fn get_master_limiter(state: State<AppState>) -> Result<LimiterParams, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.master_limiter()
+}
+
+/// Out-of-range values are clamped. A new lookahead changes the master latency.
+#[tauri::command]
+fn set_master_limiter(params: LimiterParams, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_master_limiter(params)
+}
+
+// --- GROUPS ---
+
+#[tauri::command]
+fn get_groups(state: State<AppState>) -> Result<Vec<GroupInfo>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.groups()
+}
+
+/// Returns the new group id.
+#[tauri::command]
+fn create_group(name: String, state: State<AppState>) -> Result<u32, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.add_group(name)
+}
+
+/// Members go straight to the master again.
+#[tauri::command]
+fn delete_group(group_id: u32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.remove_group(group_id)
+}
+
+#[tauri::command]
+fn rename_group(group_id: u32, name: String, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.rename_group(group_id, name)
+}
+
+#[tauri::command]
+fn set_group_gain(group_id: u32, gain: f32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_group_gain(group_id, gain)
+}
+
+#[tauri::command]
+fn set_group_mute(group_id: u32, muted: bool, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_group_muted(group_id, muted)
+}
+
+#[tauri::command]
+fn set_group_solo(group_id: u32, solo: bool, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_group_solo(group_id, solo)
+}
+
+/// `group_id: null` takes the track out of its group.
+#[tauri::command]
+fn assign_track_group(track_id: u32, group_id: Option<u32>, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.set_track_group(index, group_id)
+}
+
+// --- FREEZE ---
+
+/// Renders the track through its inserts and plays the result; blocks until the render is done.
+#[tauri::command]
+fn freeze_track(track_id: u32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.freeze_track(index)
+}
+
+#[tauri::command]
+fn unfreeze_track(track_id: u32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.unfreeze_track(index)
+}
+
+// --- AUX BUSES ---
+
+#[tauri::command]
+fn get_aux_buses(state: State<AppState>) -> Result<Vec<AuxBusInfo>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.aux_buses()
+}
+
+/// Returns the new bus id. Fails once the bus limit is reached.
+#[tauri::command]
+fn add_aux_bus(name: String, state: State<AppState>) -> Result<u32, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.add_aux_bus(name)
+}
+
+/// Also removes every send into the bus.
+#[tauri::command]
+fn remove_aux_bus(bus_id: u32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.remove_aux_bus(bus_id)
+}
+
+#[tauri::command]
+fn rename_aux_bus(bus_id: u32, name: String, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.rename_aux_bus(bus_id, name)
+}
+
+#[tauri::command]
+fn set_aux_bus_gain(bus_id: u32, gain: f32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_aux_bus_gain(bus_id, gain)
+}
+
+#[tauri::command]
+fn set_aux_bus_muted(bus_id: u32, muted: bool, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_aux_bus_muted(bus_id, muted)
+}
+
+/// The whole chain, in order: `[{ kind: "reverb" | "delay", params: {...} }]`.
+#[tauri::command]
+fn get_track_inserts(track_id: u32, state: State<AppState>) -> Result<Vec<InsertState>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.track_inserts(index)
+}
+
+/// Add an insert to a track's chain, at `position` or the end. Returns the insert id.
+#[tauri::command]
+fn add_track_insert(track_id: u32, effect: InsertParams, position: Option<usize>, state: State<AppState>) -> Result<u32, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.add_track_insert(index, effect, position)
+}
+
 #[tauri::command]
-fn get_recording_status(state: State<AppState>) -> Result<RecordingState, String> {
-    let rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
-    
-    if let Some(rec) = rec_guard.as_ref() {
-        let duration = rec.get_record_time().as_secs_f64();
-        let current_rms = 0.5; // Placeholder RMS
-        
-        Ok(RecordingState {
-            is_recording: true,
-            duration,
-            current_rms,
-            is_monitoring: rec.is_monitor_enabled(), // <--- Fetch real state
-        })
-    } else {
-        Ok(RecordingState {
-            is_recording: false,
-            duration: 0.0,
-            current_rms: 0.0,
-            is_monitoring: false, // Default off
-        })
-    }
+fn remove_track_insert(track_id: u32, insert_id: u32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.remove_track_insert(index, insert_id)
 }
 
 #[tauri::command]
-fn toggle_monitor_cmd(state: State<AppState>) -> Result<bool, String> {
-    let mut rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
-    if let Some(rec) = rec_guard.as_mut() {
-        rec.toggle_monitor().map_err(|e| e.to_string())?;
-        Ok(rec.is_monitor_enabled())
-    } else {
-        // If not recording, we can't toggle the hardware monitor yet.
-        Ok(false)
-    }
+fn move_track_insert(track_id: u32, insert_id: u32, position: usize, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.move_track_insert(index, insert_id, position)
 }
 
 #[tauri::command]
-fn stop_recording(state: State<AppState>) -> Result<(), String> {
-    let mut rec_guard = state.recorder.lock().map_err(|_| "Failed to lock recorder")?;
-    if let Some(rec) = rec_guard.take() {
-        rec.stop();
-    }
-    // Tell the audio thread to drop the monitor connection
-    if let Ok(audio) = state.audio.lock() {
-        audio.clear_monitor();
-    }
-    Ok(())
+fn set_track_insert_bypass(track_id: u32, insert_id: u32, bypass: bool, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.set_track_insert_bypass(index, insert_id, bypass)
 }
 
+#[tauri::command]
+fn get_track_automation_mode(track_id: u32, state: State<AppState>) -> Result<AutomationMode, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.track_automation_mode(index)
+}
 
+/// "read" plays the insert lanes back; "write" and "latch" record knob moves while playing.
 #[tauri::command]
-fn seek(pos: f64, state: State<AppState>) -> Result<(), String> {
+fn set_track_automation_mode(track_id: u32, mode: AutomationMode, state: State<AppState>) -> Result<(), String> {
     let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
-    // Convert float seconds to Duration
-    let position = Duration::from_secs_f64(pos.max(0.0));
-    audio.seek(position);
-    Ok(())
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.set_track_automation_mode(index, mode)
 }
 
+/// Without `param`, every lane of the insert is cleared.
 #[tauri::command]
-fn set_track_gain(track_id: u32, gain: f32, state: State<AppState>) -> Result<(), String> {
+fn clear_track_insert_automation(track_id: u32, insert_id: u32, param: Option<String>, state: State<AppState>) -> Result<(), String> {
     let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.clear_track_insert_automation(index, insert_id, param)
+}
 
+/// `mix` is the wet share, 0.0 to 1.0. EQ inserts are always fully wet.
+#[tauri::command]
+fn set_track_insert_mix(track_id: u32, insert_id: u32, mix: f32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
     let list = audio.get_tracks_list();
     let index = resolve_track_index(&list, track_id)?;
+    audio.set_track_insert_mix(index, insert_id, mix)
+}
 
-    audio.set_track_gain(index, gain);
-    Ok(())
+#[tauri::command]
+fn set_track_insert_params(track_id: u32, insert_id: u32, effect: InsertParams, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.set_track_insert_params(index, insert_id, effect)
 }
 
 #[tauri::command]
-fn get_master_gain(state: tauri::State<AppState>) -> Result<f32, String> {
+fn set_aux_bus_effects(bus_id: u32, effects: Vec<BusEffectParams>, state: State<AppState>) -> Result<(), String> {
     let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
-    Ok(audio.master_gain())
+    audio.set_aux_bus_effects(bus_id, effects)
 }
 
+/// Post-fader send level, linear (0 = off, 1 = unity, up to 2).
 #[tauri::command]
-fn set_master_gain(gain: f32, state: State<AppState>) -> Result<(), String> {
+fn set_track_send(track_id: u32, bus_id: u32, level: f32, state: State<AppState>) -> Result<(), String> {
     let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
-    audio.set_master_gain(gain);
-    Ok(())
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.set_track_send(index, bus_id, level)
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, Clone)]
 struct MasterMeterState {
     peak_l: f32,
     peak_r: f32,
@@ -576,28 +1744,98 @@ struct MasterMeterState {
     hold_r : f32,
     rms_l: f32,
     rms_r: f32,
+    gain_reduction_db: f32,
 }
 
 #[tauri::command]
 fn get_master_meter(state: tauri::State<AppState>) -> Result<MasterMeterState, String> {
-    // 100% Lock Free - Reads directly from atomics!
-    let peak_l = f32::from_bits(state.master_meter.peak_l.load(std::sync::atomic::Ordering::Relaxed));
-    let peak_r = f32::from_bits(state.master_meter.peak_r.load(std::sync::atomic::Ordering::Relaxed));
+    Ok(read_master_meter(&state.master_meter, &state.limiter_meter))
+}
 
-    let hold_l = f32::from_bits(state.master_meter.hold_l.load(std::sync::atomic::Ordering::Relaxed));
-    let hold_r = f32::from_bits(state.master_meter.hold_r.load(std::sync::atomic::Ordering::Relaxed));
+// 100% Lock Free - Reads directly from atomics!
+fn read_master_meter(meter: &daw_modules::engine::metering::TrackMeters, limiter: &GainReductionMeter) -> MasterMeterState {
+    let peak_l = f32::from_bits(meter.peak_l.load(std::sync::atomic::Ordering::Relaxed));
+    let peak_r = f32::from_bits(meter.peak_r.load(std::sync::atomic::Ordering::Relaxed));
 
-    let rms_l = f32::from_bits(state.master_meter.rms_l.load(std::sync::atomic::Ordering::Relaxed));
-    let rms_r = f32::from_bits(state.master_meter.rms_r.load(std::sync::atomic::Ordering::Relaxed));
-    
-    Ok(MasterMeterState {
+    let hold_l = f32::from_bits(meter.hold_l.load(std::sync::atomic::Ordering::Relaxed));
+    let hold_r = f32::from_bits(meter.hold_r.load(std::sync::atomic::Ordering::Relaxed));
+
+    let rms_l = f32::from_bits(meter.rms_l.load(std::sync::atomic::Ordering::Relaxed));
+    let rms_r = f32::from_bits(meter.rms_r.load(std::sync::atomic::Ordering::Relaxed));
+
+    MasterMeterState {
         peak_l,
         peak_r,
         hold_l,
         hold_r,
         rms_l,
         rms_r,
-    })
+        gain_reduction_db: limiter.get(),
+    }
+}
+
+/// Master loudness: momentary, short-term and integrated LUFS plus true peak (dBTP).
+#[tauri::command]
+fn get_loudness(state: State<AppState>) -> Result<daw_modules::engine::loudness::LoudnessReading, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.loudness())
+}
+
+/// Start integrated loudness and true peak over.
+#[tauri::command]
+fn reset_loudness(state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.reset_loudness();
+    Ok(())
+}
+
+/// The last over on the master (pre-limiter), or `None` while the indicator is clear.
+#[tauri::command]
+fn get_clip_status(state: State<AppState>) -> Result<Option<daw_modules::engine::clip_detect::ClipEvent>, String> {
+    Ok(state.clip_detect.latest())
+}
+
+#[tauri::command]
+fn clear_clip_indicator(state: State<AppState>) -> Result<(), String> {
+    state.clip_detect.clear();
+    Ok(())
+}
+
+/// Master phase correlation and goniometer points (L/R pairs, oldest first).
+#[tauri::command]
+fn get_correlation(state: State<AppState>) -> Result<daw_modules::engine::correlation::CorrelationReading, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.correlation())
+}
+
+// Spectrum commands: `track_id: None` means the master
+fn spectrum_index(audio: &AudioRuntime, track_id: Option<u32>) -> Result<Option<usize>, String> {
+    track_id.map(|id| resolve_track_index(&audio.get_tracks_list(), id)).transpose()
+}
+
+#[tauri::command]
+fn attach_spectrum(track_id: Option<u32>, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let index = spectrum_index(&audio, track_id)?;
+    audio.attach_spectrum(index)
+}
+
+#[tauri::command]
+fn detach_spectrum(track_id: Option<u32>, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let index = spectrum_index(&audio, track_id)?;
+    audio.detach_spectrum(index)
+}
+
+/// Latest FFT magnitudes (dBFS per bin); `None` if no analyzer is attached there.
+#[tauri::command]
+fn get_spectrum(
+    track_id: Option<u32>,
+    state: State<AppState>,
+) -> Result<Option<daw_modules::engine::spectrum::SpectrumFrame>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let index = spectrum_index(&audio, track_id)?;
+    audio.spectrum(index)
 }
 
 #[tauri::command]
@@ -610,6 +1848,10 @@ fn get_all_meters(
         .lock()
         .map_err(|_| "meter registry poisoned")?;
 
+    Ok(read_track_meters(&reg))
+}
+
+fn read_track_meters(reg: &HashMap<u32, Arc<daw_modules::engine::metering::TrackMeters>>) -> Vec<daw_modules::audio_runtime::MeterSnapshot> {
     let mut results = Vec::with_capacity(reg.len());
 
     for (&track_id, meters) in reg.iter() {
@@ -627,7 +1869,75 @@ fn get_all_meters(
     // ❌ Remove sorting (registry should not change order during playback)
     // results.sort_by_key(|m| m.track_id);
 
-    Ok(results)
+    results
+}
+
+/// How often a `meters` event goes out (~30 per second).
+const METER_STREAM_INTERVAL: Duration = Duration::from_millis(33);
+
+#[derive(serde::Serialize, Clone)]
+struct MetersPayload {
+    tracks: Vec<daw_modules::audio_runtime::MeterSnapshot>,
+    master: MasterMeterState,
+}
+
+/// Push every track's and the master's meters as a `meters` event, so the mixer doesn't poll.
+/// While everything reads silent only the first silent frame is sent. Clip and recording
+/// overrun events ride along on the same poll.
+fn spawn_meter_stream(app: tauri::AppHandle) {
+    let (master_meter, limiter_meter, registry, clip_detect) = {
+        let state = app.state::<AppState>();
+        (state.master_meter.clone(), state.limiter_meter.clone(), state.meter_registry.clone(), state.clip_detect.clone())
+    };
+    std::thread::spawn(move || {
+        let mut was_silent = false;
+        let mut clip_events = clip_detect.event_count();
+        let mut overrun_events = 0;
+        loop {
+            std::thread::sleep(METER_STREAM_INTERVAL);
+
+            // A take that lost input says so right away ("recording-overrun"), not as a short file
+            let overruns = app.state::<AppState>().recorder.lock().ok()
+                .and_then(|rec| rec.as_ref().map(|r| r.overruns()));
+            match overruns {
+                Some(report) if report.events > overrun_events => {
+                    overrun_events = report.events;
+                    log::warn!(
+                        "Recording overrun at {:.2}s: {} frames dropped so far",
+                        report.last_at, report.dropped_frames
+                    );
+                    let _ = app.emit("recording-overrun", report);
+                }
+                Some(_) => {}
+                None => overrun_events = 0,
+            }
+
+            // One "clip-detected" per poll, however many blocks clipped in between
+            let events = clip_detect.event_count();
+            if events != clip_events {
+                clip_events = events;
+                if let Some(clip) = clip_detect.latest() {
+                    let _ = app.emit("clip-detected", clip);
+                }
+            }
+
+            let tracks = match registry.lock() {
+                Ok(reg) => read_track_meters(&reg),
+                Err(_) => continue,
+            };
+            let master = read_master_meter(&master_meter, &limiter_meter);
+            let silent = master.hold_l == 0.0
+                && master.hold_r == 0.0
+                && master.rms_l == 0.0
+                && master.rms_r == 0.0
+                && tracks.iter().all(|m| m.hold_l == 0.0 && m.hold_r == 0.0 && m.rms_l == 0.0 && m.rms_r == 0.0);
+            if silent && was_silent {
+                continue;
+            }
+            was_silent = silent;
+            let _ = app.emit("meters", MetersPayload { tracks, master });
+        }
+    });
 }
 
 #[tauri::command]
@@ -666,6 +1976,42 @@ fn toggle_solo(track_id: u32, state: State<AppState>) -> Result<(), String> {
     Ok(())
 }
 
+/// "SoloOverridesMute" or "MuteAlwaysWins". Saved with the project.
+#[tauri::command]
+fn set_solo_policy(policy: daw_modules::engine::SoloPolicy, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_solo_policy(policy);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_solo_policy(state: State<AppState>) -> Result<daw_modules::engine::SoloPolicy, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.solo_policy())
+}
+
+/// "Additive" or "Exclusive" (soloing clears the other solos). Saved with the project.
+#[tauri::command]
+fn set_solo_mode(mode: daw_modules::engine::SoloMode, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_solo_mode(mode);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_solo_mode(state: State<AppState>) -> Result<daw_modules::engine::SoloMode, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.solo_mode())
+}
+
+#[tauri::command]
+fn set_solo_safe(track_id: u32, safe: bool, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.set_solo_safe(index, safe)
+}
+
 #[tauri::command]
 fn set_bpm(bpm: f32, state: State<AppState>) -> Result<(), String> {
     let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
@@ -674,6 +2020,19 @@ fn set_bpm(bpm: f32, state: State<AppState>) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+fn get_playback_rate(state: State<AppState>) -> Result<f64, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.playback_rate())
+}
+
+/// Varispeed; returns the rate applied (clamped to 0.25x..2x).
+#[tauri::command]
+fn set_playback_rate(rate: f64, state: State<AppState>) -> Result<f64, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_playback_rate(rate)
+}
+
 #[tauri::command]
 fn set_time_signature(numerator: u32, state: State<AppState>) -> Result<(), String> {
     let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
@@ -682,6 +2041,25 @@ fn set_time_signature(numerator: u32, state: State<AppState>) -> Result<(), Stri
     Ok(())
 }
 
+/// Time signature from the start of `bar` (1-indexed) onwards; bar 1 changes the initial meter.
+#[tauri::command]
+fn set_meter_change(bar: u32, numerator: u32, denominator: u32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_meter_change(bar, numerator, denominator)
+}
+
+#[tauri::command]
+fn remove_meter_change(bar: u32, state: State<AppState>) -> Result<bool, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.remove_meter_change(bar)
+}
+
+#[tauri::command]
+fn get_meter_changes(state: State<AppState>) -> Result<Vec<daw_modules::engine::time::MeterChange>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.meter_changes()
+}
+
 #[tauri::command]
 fn get_grid_lines(
     start: f64, 
@@ -738,6 +2116,9 @@ fn create_track(state: State<AppState>) -> Result<LoadedTrack, String> {
         pan: 0.0,
         muted: false,
         solo: false,
+        armed: false,
+        record_safe: false,
+        solo_safe: false,
         source: "mic".to_string(),
         volume_automation: vec![],
         eq: vec![],
@@ -748,6 +2129,8 @@ fn create_track(state: State<AppState>) -> Result<LoadedTrack, String> {
             attack_ms: 5.0,
             release_ms: 50.0,
             makeup_gain_db: 0.0,
+            knee_db: 6.0,
+            lookahead_ms: 0.0,
         },
         reverb: daw_modules::effects::reverb::ReverbParams { 
             is_active: false, 
@@ -763,52 +2146,226 @@ fn create_track(state: State<AppState>) -> Result<LoadedTrack, String> {
 }
 
 
-// --- NEW: Tauri command for AI analysis ---
+// --- NEW: Tauri command for AI analysis ---
+#[tauri::command]
+async fn get_track_analysis(state: State<'_, AppState>) -> Result<Vec<daw_modules::audio_runtime::TrackAnalysisPayload>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.get_all_track_analysis())
+}
+
+/// Errors are structured: `{ kind: "tooShort", durationSecs, minSecs }` when a half would be too short.
+#[tauri::command]
+fn split_clip(
+    track_id: u32, 
+    time: f64, 
+    state: State<AppState>
+) -> Result<(), serde_json::Value> {
+    let audio = state.audio.lock().map_err(|_| serde_json::json!({ "kind": "internal", "message": "Failed to lock engine" }))?;
+    
+    // Frontend uses 1-based track IDs usually? 
+    // If your frontend passes the array index (0-based), keep as is.
+    // If frontend passes ID (1, 2...), subtract 1.
+    // Based on 'move_clip' in your file, it seems direct mapping or handled there.
+    // Let's assume track_index matches the Vec index.
+
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)
+        .map_err(|e| serde_json::json!({ "kind": "trackNotFound", "message": e }))?;
+    
+    audio.split_clip(index, time).map_err(|e| clip_edit_error(e.downcast_ref(), e.to_string()))?;
+    
+    Ok(())
+}
+
+// `ClipEditError`s go to the UI structured; anything else as `{ kind: "internal", message }`
+fn clip_edit_error(edit: Option<&daw_modules::engine::track::ClipEditError>, message: String) -> serde_json::Value {
+    match edit {
+        Some(edit) => serde_json::to_value(edit).unwrap_or_else(|_| serde_json::json!({ "message": message })),
+        None => serde_json::json!({ "kind": "internal", "message": message }),
+    }
+}
+
+/// Drag a clip's left edge to `new_start` (seconds on the timeline); the audio stays in place.
+/// Clamped to the start of the file. Errors like `split_clip`.
+#[tauri::command]
+fn trim_clip_start(track_id: u32, clip_index: usize, new_start: f64, state: State<AppState>) -> Result<(), serde_json::Value> {
+    let audio = state.audio.lock().map_err(|_| serde_json::json!({ "kind": "internal", "message": "Failed to lock engine" }))?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)
+        .map_err(|e| serde_json::json!({ "kind": "trackNotFound", "message": e }))?;
+    audio.trim_clip_start(index, clip_index, new_start).map_err(|e| clip_edit_error(e.downcast_ref(), e.to_string()))
+}
+
+/// Drag a clip's right edge to `new_end` (seconds on the timeline), clamped to the end of the file.
+#[tauri::command]
+fn trim_clip_end(track_id: u32, clip_index: usize, new_end: f64, state: State<AppState>) -> Result<(), serde_json::Value> {
+    let audio = state.audio.lock().map_err(|_| serde_json::json!({ "kind": "internal", "message": "Failed to lock engine" }))?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)
+        .map_err(|e| serde_json::json!({ "kind": "trackNotFound", "message": e }))?;
+    audio.trim_clip_end(index, clip_index, new_end).map_err(|e| clip_edit_error(e.downcast_ref(), e.to_string()))
+}
+
+/// Stretch a clip (no pitch change) so `clip_bpm` lands on the project tempo. Without
+/// `clip_bpm`, the tempo detected at import is used. Returns the applied ratio (timeline
+/// length / source length). Errors like `split_clip`, plus `{ kind: "noBpm" }`.
+#[tauri::command]
+fn conform_clip_to_tempo(track_id: u32, clip_index: usize, clip_bpm: Option<f32>, state: State<AppState>) -> Result<f64, serde_json::Value> {
+    let audio = state.audio.lock().map_err(|_| serde_json::json!({ "kind": "internal", "message": "Failed to lock engine" }))?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)
+        .map_err(|e| serde_json::json!({ "kind": "trackNotFound", "message": e }))?;
+    let clip_bpm = match clip_bpm {
+        Some(bpm) => Some(bpm),
+        None => {
+            let path = list[index].clips.get(clip_index).map(|c| c.path.clone()).unwrap_or_default();
+            state.cache.lock().ok().and_then(|cache| cache.get(&path).and_then(|r| r.bpm))
+        }
+    };
+    let clip_bpm = clip_bpm.ok_or_else(|| serde_json::json!({ "kind": "noBpm", "message": "No tempo detected for this clip" }))?;
+    audio.conform_clip_to_tempo(index, clip_index, clip_bpm).map_err(|e| clip_edit_error(e.downcast_ref(), e.to_string()))
+}
+
+/// `ratio: 1.0` undoes a conform.
+#[tauri::command]
+fn set_clip_stretch(track_id: u32, clip_index: usize, ratio: f64, state: State<AppState>) -> Result<(), serde_json::Value> {
+    let audio = state.audio.lock().map_err(|_| serde_json::json!({ "kind": "internal", "message": "Failed to lock engine" }))?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)
+        .map_err(|e| serde_json::json!({ "kind": "trackNotFound", "message": e }))?;
+    audio.set_clip_stretch(index, clip_index, ratio).map_err(|e| clip_edit_error(e.downcast_ref(), e.to_string()))
+}
+
+/// Tape-style stretch: the clip's speed change moves its pitch too.
+#[tauri::command]
+fn set_clip_varispeed(track_id: u32, clip_index: usize, enabled: bool, state: State<AppState>) -> Result<(), serde_json::Value> {
+    let audio = state.audio.lock().map_err(|_| serde_json::json!({ "kind": "internal", "message": "Failed to lock engine" }))?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)
+        .map_err(|e| serde_json::json!({ "kind": "trackNotFound", "message": e }))?;
+    audio.set_clip_varispeed(index, clip_index, enabled).map_err(|e| clip_edit_error(e.downcast_ref(), e.to_string()))
+}
+
+#[tauri::command]
+fn merge_clip_with_next(track_id: u32, clip_index: usize, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock engine")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+
+    audio.merge_clip_with_next(index, clip_index).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_track(track_id: u32, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock engine")?;
+
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+
+    audio.delete_track(index).map_err(|e| e.to_string())
+}
+
+/// Undoable. `order` lists every track id once, top to bottom.
+#[tauri::command]
+fn reorder_tracks(order: Vec<u32>, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock engine")?;
+    audio.reorder_tracks(&order).map_err(|e| e.to_string())
+}
+
+/// Undoable; keystrokes within one editing session collapse into a single undo step.
+#[tauri::command]
+fn set_track_notes(track_id: u32, notes: String, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.set_track_notes(index, notes).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
-async fn get_track_analysis(state: State<'_, AppState>) -> Result<Vec<daw_modules::audio_runtime::TrackAnalysisPayload>, String> {
+fn set_clip_notes(track_id: u32, clip_index: usize, notes: String, state: State<AppState>) -> Result<(), String> {
     let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
-    Ok(audio.get_all_track_analysis())
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.set_clip_notes(index, clip_index, notes).map_err(|e| e.to_string())
 }
 
+/// Neutral mix across every track in one undo step; `options` defaults to gains, pans, mutes/solos.
 #[tauri::command]
-fn split_clip(
-    track_id: u32, 
-    time: f64, 
-    state: State<AppState>
-) -> Result<(), String> {
-    let audio = state.audio.lock().map_err(|_| "Failed to lock engine")?;
-    
-    // Frontend uses 1-based track IDs usually? 
-    // If your frontend passes the array index (0-based), keep as is.
-    // If frontend passes ID (1, 2...), subtract 1.
-    // Based on 'move_clip' in your file, it seems direct mapping or handled there.
-    // Let's assume track_index matches the Vec index.
+fn reset_mixer(options: Option<daw_modules::session::commands::MixerResetOptions>, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.reset_mixer(options.unwrap_or_default()).map_err(|e| e.to_string())
+}
 
+#[tauri::command]
+fn set_track_fx_bypass(track_id: u32, bypass: bool, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
     let list = audio.get_tracks_list();
     let index = resolve_track_index(&list, track_id)?;
-    
-    audio.split_clip(index, time).map_err(|e| e.to_string())?;
-    
-    Ok(())
+    audio.set_track_fx_bypass(index, bypass).map_err(|e| e.to_string())
 }
 
+/// Silence `start..end` seconds into the clip without splitting it. Undoable.
 #[tauri::command]
-fn merge_clip_with_next(track_id: u32, clip_index: usize, state: State<AppState>) -> Result<(), String> {
-    let audio = state.audio.lock().map_err(|_| "Failed to lock engine")?;
+fn add_clip_mute_region(track_id: u32, clip_index: usize, start: f64, end: f64, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
     let list = audio.get_tracks_list();
     let index = resolve_track_index(&list, track_id)?;
+    audio.add_clip_mute_region(index, clip_index, start, end).map_err(|e| e.to_string())
+}
 
-    audio.merge_clip_with_next(index, clip_index).map_err(|e| e.to_string())
+#[tauri::command]
+fn remove_clip_mute_region(track_id: u32, clip_index: usize, region_index: usize, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.remove_clip_mute_region(index, clip_index, region_index).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn delete_track(track_id: u32, state: State<AppState>) -> Result<(), String> {
-    let audio = state.audio.lock().map_err(|_| "Failed to lock engine")?;
+fn list_clip_mute_regions(
+    track_id: u32,
+    clip_index: usize,
+    state: State<AppState>,
+) -> Result<Vec<daw_modules::engine::mute_regions::MuteRegion>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    audio.clip_mute_regions(index, clip_index).map_err(|e| e.to_string())
+}
 
+/// `length` in seconds; `None` fades over the whole overlap.
+#[tauri::command]
+fn set_clip_crossfade(track_id: u32, clip_index: usize, length: Option<f64>, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
     let list = audio.get_tracks_list();
     let index = resolve_track_index(&list, track_id)?;
+    audio.set_clip_crossfade(index, clip_index, length).map_err(|e| e.to_string())
+}
 
-    audio.delete_track(index).map_err(|e| e.to_string())
+#[derive(serde::Serialize)]
+struct GapRange {
+    start: f64,
+    end: f64,
+}
+
+/// Holes where nothing plays, to overlay on the timeline before an export.
+/// `include_fades` (default true) also counts stretches covered only by clip edge fades.
+#[tauri::command]
+fn find_gaps(min_gap_ms: Option<f64>, include_fades: Option<bool>, state: State<AppState>) -> Result<Vec<GapRange>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let min_gap = Duration::from_secs_f64(min_gap_ms.unwrap_or(100.0).max(0.0) / 1000.0);
+    Ok(audio
+        .find_gaps(min_gap, include_fades.unwrap_or(true))
+        .into_iter()
+        .map(|(start, end)| GapRange { start, end })
+        .collect())
+}
+
+/// Everything with a note, for the to-do sidebar.
+#[tauri::command]
+fn list_annotated(state: State<AppState>) -> Result<Vec<daw_modules::audio_runtime::Annotation>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.list_annotated())
 }
 
 #[tauri::command]
@@ -838,6 +2395,13 @@ fn redo(state: State<AppState>) -> Result<(), String> {
     Ok(())
 }
 
+/// Edit history, newest first.
+#[tauri::command]
+fn get_edit_history(limit: usize, offset: usize, state: State<AppState>) -> Result<Vec<daw_modules::session::history::HistoryEntry>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.edit_history(limit, offset))
+}
+
 // 1. Argument Struct
 #[derive(serde::Deserialize)]
 struct EqUpdateArgs {
@@ -892,6 +2456,22 @@ fn get_eq_state(track_id: u32, state: State<AppState>) -> Result<Vec<daw_modules
     Ok(audio.get_eq_state(index))
 }
 
+/// The EQ curve to draw: the track's own EQ, or the EQ insert `insert_id`. `points`
+/// defaults to 256.
+#[tauri::command]
+fn get_eq_response(
+    track_id: u32,
+    insert_id: Option<u32>,
+    points: Option<usize>,
+    state: State<AppState>,
+) -> Result<daw_modules::effects::equalizer::EqResponse, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock engine")?;
+    let list = audio.get_tracks_list();
+    let index = resolve_track_index(&list, track_id)?;
+    let points = points.unwrap_or(daw_modules::effects::equalizer::EQ_RESPONSE_POINTS).clamp(2, 4096);
+    audio.eq_response(index, insert_id, points)
+}
+
 #[tauri::command]
 fn update_compressor(
     track_id: u32, 
@@ -962,6 +2542,11 @@ pub struct LoadedClip {
     pub waveform: ImportResult,
     pub color: String,
     pub clip_number: usize, // <--- NEW
+    pub notes: String,
+    pub mute_regions: Vec<daw_modules::engine::mute_regions::MuteRegion>, // drawn as hatched areas
+    pub stretch: f64, // waveform is drawn `stretch` times wider; offset is in source seconds
+    pub varispeed: bool, // the stretch shifts pitch too
+    pub crossfade: Option<f64>, // seconds of crossfade into the previous clip, when they overlap
 }
 
 #[derive(serde::Serialize)]
@@ -975,6 +2560,14 @@ pub struct LoadedTrack {
     pub pan: f32,
     pub muted: bool,
     pub solo: bool,
+    pub armed: bool,
+    pub record_safe: bool,
+    pub solo_safe: bool,
+    pub notes: String,
+    pub listen: daw_modules::engine::track::ListenMode,
+    pub fx_bypass: bool,
+    pub kind: Option<TrackKind>,
+    pub kind_manual: bool,
     pub source: String,
     pub volume_automation: Vec<daw_modules::engine::automation::AutomationNode<f32>>,
     pub eq: Vec<daw_modules::effects::equalizer::EqParams>,
@@ -988,17 +2581,107 @@ pub struct ProjectState {
     pub tracks: Vec<LoadedTrack>,
     pub bpm: f32,
     pub master_gain: f32,
+    // Only set by load_project when the project carried audio settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_prefs: Option<daw_modules::audio_runtime::AppliedAudioPrefs>,
+}
+
+
+#[tauri::command]
+fn save_project(path: String, app: tauri::AppHandle, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.save_project(path.clone())?;
+
+    // The save went through; large media left outside the project folder only gets a warning
+    let project_dir = std::path::Path::new(&path).parent().unwrap_or(std::path::Path::new(""));
+    if let Ok(stats) = audio.project_stats() {
+        let (files, bytes) = stats.uncollected_media(project_dir);
+        if bytes > UNCOLLECTED_MEDIA_WARN_BYTES {
+            log::warn!("⚠️ {} media files ({} bytes) are referenced from outside {}", files, bytes, project_dir.display());
+            let _ = app.emit("uncollected-media", serde_json::json!({ "files": files, "bytes": bytes }));
+        }
+    }
+    Ok(())
 }
 
+/// Counts, media size, formats and missing files for the project dashboard.
+#[tauri::command]
+fn get_project_stats(state: State<AppState>) -> Result<ProjectStats, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.project_stats()
+}
+
+/// Rotating backups of a project file, newest (index 1) first.
+#[tauri::command]
+fn list_backups(path: String, state: State<AppState>) -> Result<Vec<daw_modules::session::serialization::BackupInfo>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.list_backups(&path))
+}
+
+/// Roll the project file back to a backup. The frontend reloads the project afterwards.
+#[tauri::command]
+fn restore_backup(path: String, index: usize, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.restore_backup(&path, index)
+}
+
+/// Shorthand for `update_settings({ backupCount })`, so the count survives a restart.
+#[tauri::command]
+fn set_backup_count(count: usize, app: tauri::AppHandle, state: State<AppState>) -> Result<(), String> {
+    settings::update_settings(serde_json::json!({ "backupCount": count }), app, state).map(|_| ())
+}
+
+/// Shorthand for `update_settings({ metronome })`. Returns the config as applied (clamped).
+#[tauri::command]
+fn set_metronome_config(
+    config: MetronomeConfig,
+    app: tauri::AppHandle,
+    state: State<AppState>,
+) -> Result<MetronomeConfig, String> {
+    let config = config.sanitized();
+    settings::update_settings(serde_json::json!({ "metronome": config }), app, state).map(|s| s.metronome)
+}
 
 #[tauri::command]
-fn save_project(path: String, state: State<AppState>) -> Result<(), String> {
+fn get_metronome_config(state: State<AppState>) -> Result<MetronomeConfig, String> {
     let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
-    audio.save_project(path)
+    audio.metronome_config()
+}
+
+// Change one part of the saved metronome config
+fn update_metronome(
+    app: tauri::AppHandle,
+    state: State<AppState>,
+    change: impl FnOnce(&mut MetronomeConfig),
+) -> Result<MetronomeConfig, String> {
+    let mut config = state.settings.lock().map_err(|_| "Failed to lock settings")?.settings.metronome;
+    change(&mut config);
+    set_metronome_config(config, app, state)
+}
+
+#[tauri::command]
+fn set_metronome_enabled(enabled: bool, app: tauri::AppHandle, state: State<AppState>) -> Result<MetronomeConfig, String> {
+    update_metronome(app, state, |c| c.enabled = enabled)
+}
+
+/// Overall click level in dB (-60 to 6).
+#[tauri::command]
+fn set_metronome_volume(volume_db: f32, app: tauri::AppHandle, state: State<AppState>) -> Result<MetronomeConfig, String> {
+    update_metronome(app, state, |c| c.volume_db = volume_db)
+}
+
+/// Bars of pre-count before `play_with_count_in` starts the transport (0 to 4).
+#[tauri::command]
+fn set_count_in_bars(bars: u32, app: tauri::AppHandle, state: State<AppState>) -> Result<MetronomeConfig, String> {
+    update_metronome(app, state, |c| c.count_in_bars = bars)
 }
 
 #[tauri::command]
-async fn export_project(app: tauri::AppHandle, path: String) -> Result<(), String> {
+async fn export_project(
+    app: tauri::AppHandle,
+    path: String,
+    options: Option<daw_modules::session::export::ExportOptions>,
+) -> Result<(), String> {
     let _ = app.emit("progress-update", ProgressPayload { 
         message: "Rendering Project...".into(), progress: 0.0, visible: true 
     });
@@ -1010,9 +2693,15 @@ async fn export_project(app: tauri::AppHandle, path: String) -> Result<(), Strin
     tauri::async_runtime::spawn_blocking(move || {
         // Extract the state and lock the mutex INSIDE the thread
         let state = app_clone.state::<AppState>();
+        let options = options.unwrap_or_else(|| {
+            state.settings.lock().map(|s| daw_modules::session::export::ExportOptions {
+                format: s.settings.export_format,
+                dither: s.settings.export_dither,
+            }).unwrap_or_default()
+        });
         let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
         
-        audio.export_project(path)
+        audio.export_project_with(path, options)
     }).await.map_err(|e| e.to_string())??; // Double unwrap: one for thread panic, one for our Result
 
     let _ = app.emit("progress-update", ProgressPayload { 
@@ -1022,6 +2711,62 @@ async fn export_project(app: tauri::AppHandle, path: String) -> Result<(), Strin
     Ok(())
 }
 
+/// Record the live master to `path` while playback runs; live tweaks are heard in the file.
+#[tauri::command]
+fn start_realtime_bounce(path: String, options: Option<BounceOptions>, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.start_realtime_bounce(path, options.unwrap_or_default())
+}
+
+/// The file's peaks are built in the background, so importing it later is instant.
+#[tauri::command]
+fn stop_realtime_bounce(state: State<AppState>, waveforms: State<WaveformService>) -> Result<BounceResult, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let result = audio.stop_realtime_bounce()?;
+    waveforms.enqueue(result.path.clone(), WaveformJobOptions { force: true, ..Default::default() });
+    Ok(result)
+}
+
+/// `null` when idle, otherwise whether the bounce already reached the project end.
+#[tauri::command]
+fn get_realtime_bounce_status(state: State<AppState>) -> Result<Option<bool>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.realtime_bounce_status())
+}
+
+/// Realtime pass that prints every track to `{dir}/{track}.wav` next to the master.
+#[tauri::command]
+fn start_multitrack_capture(dir: String, options: Option<BounceOptions>, state: State<AppState>) -> Result<(), String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.start_multitrack_capture(dir, options.unwrap_or_default())
+}
+
+#[tauri::command]
+fn stop_multitrack_capture(state: State<AppState>, waveforms: State<WaveformService>) -> Result<MultitrackResult, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let result = audio.stop_multitrack_capture()?;
+    for path in std::iter::once(&result.master.path).chain(result.tracks.iter().map(|t| &t.path)) {
+        waveforms.enqueue(path.clone(), WaveformJobOptions { force: true, ..Default::default() });
+    }
+    Ok(result)
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultitrackStatus {
+    dir: String,
+    tracks: usize,
+    /// Reached the project end; stop to collect the files.
+    finished: bool,
+}
+
+/// `null` when idle.
+#[tauri::command]
+fn get_multitrack_capture_status(state: State<AppState>) -> Result<Option<MultitrackStatus>, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.multitrack_capture_status().map(|(dir, tracks, finished)| MultitrackStatus { dir, tracks, finished }))
+}
+
 #[tauri::command]
 async fn load_project(
     app: tauri::AppHandle, 
@@ -1029,9 +2774,16 @@ async fn load_project(
     state: State<'_, AppState>,
 ) -> Result<ProjectState, String> {
     
+    // Jobs queued for the previous project are no longer wanted
+    app.state::<WaveformService>().close_project();
+
     // 1. Perform the Load (Disk I/O)
-    let audio_runtime = state.audio.lock().map_err(|_| "Failed to lock audio")?;
-    audio_runtime.load_project(path.clone())?;
+    let mut audio_runtime = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    let applied_prefs = audio_runtime.load_project(path.clone())?;
+    // The project may have switched the output device, or dropped the last one's settings
+    if let Ok(store) = state.output_routing.lock() {
+        output_settings::apply_saved_routing(&audio_runtime, &store);
+    }
 
     // 2. Fetch Data from Memory
     let bpm = audio_runtime.bpm();
@@ -1057,11 +2809,19 @@ async fn load_project(
             };
             if needs_load {
                 let _ = app.emit("load-progress", format!("Loading {}", clip.path));
-                if let Ok((samples, sr, ch)) = daw_modules::bpm::adapter::decode_to_vec(&clip.path) {
-                    let wf = Waveform::build_from_samples(&samples, sr, ch, 512);
+                // Through the shared cache: the clip's own analysis reuses this decode
+                let wf = match bpm::cache::shared().get(&clip.path) {
+                    Ok(pcm) if !pcm.truncated => Some((Waveform::build_from_samples(&pcm.samples, pcm.sample_rate, pcm.channels, 512), pcm.sample_rate)),
+                    Ok(pcm) => Waveform::build_from_path(&clip.path, 512).ok().map(|wf| (wf, pcm.sample_rate)),
+                    Err(e) => {
+                        log::warn!("⚠️ Could not load waveform for {}: {}", clip.path, e);
+                        None
+                    }
+                };
+                if let Some((wf, sr)) = wf {
                     let pixels_per_second = 100.0;
                     let spp = (sr as f64) / pixels_per_second;
-                    let (mins, maxs, _) = wf.bins_for(spp, 0, 0, usize::MAX);
+                    let (mins, maxs) = wf.bins_for(spp, WaveformChannelMode::MixMono, 0, usize::MAX).first();
                     
                     let data = ImportResult {
                           mins: mins.to_vec(),
@@ -1070,6 +2830,7 @@ async fn load_project(
                           bins_per_second: pixels_per_second,
                           bpm: None,
                           color: String::new(),
+                          truncated: false,
                     };
                     
                     state.cache.lock().unwrap().insert(path_key, data);
@@ -1080,17 +2841,215 @@ async fn load_project(
 
     // 3. Build UI State (Reuse Helper)
     // Pass cache AND color store
-    let state_ui = build_ui_state(tracks_info, bpm, master_gain, false, &state.cache, fx_data)?;
+    let mut state_ui = build_ui_state(tracks_info, bpm, master_gain, false, &state.cache, fx_data)?;
+    state_ui.audio_prefs = applied_prefs;
     let _ = app.emit("load-percent", 100.0);
     let _ = app.emit("load-progress", "Ready");
 
+    // Quick header check of the caches the project uses; rebuilds go to the waveform service
+    let project_dir = std::path::Path::new(&path).parent().map(|p| p.to_path_buf()).unwrap_or_default();
+    let app_check = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(e) = run_peaks_maintenance(&app_check, &project_dir, MaintenanceMode::Quick) {
+            log::warn!("Peaks cache check skipped: {}", e);
+        }
+    });
+
     Ok(state_ui)
 }
 
+#[derive(Clone, serde::Serialize)]
+struct PeaksMaintenanceProgress {
+    done: usize,
+    total: usize,
+}
+
+// One pass at a time; its cancel flag lives in AppState while it runs
+fn run_peaks_maintenance(app: &tauri::AppHandle, project_dir: &std::path::Path, mode: MaintenanceMode) -> Result<CacheReport, String> {
+    let state = app.state::<AppState>();
+    let cancel = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    {
+        let mut running = state.peaks_maintenance.lock().map_err(|_| "Failed to lock maintenance state")?;
+        if running.is_some() {
+            return Err("Peaks cache maintenance is already running".into());
+        }
+        *running = Some(cancel.clone());
+    }
+
+    let media: Result<Vec<String>, String> = state
+        .audio
+        .lock()
+        .map_err(|_| "Failed to lock audio".to_string())
+        .and_then(|audio| audio.project_stats())
+        .map(|stats| stats.media.into_iter().map(|m| m.path).collect());
+    let report = media.map(|media| {
+        daw_modules::waveform::maintenance::maintain_peaks_cache(
+            project_dir,
+            &media,
+            mode,
+            &app.state::<WaveformService>(),
+            &cancel,
+            |done, total| {
+                let _ = app.emit("peaks-maintenance-progress", PeaksMaintenanceProgress { done, total });
+            },
+        )
+    });
+
+    if let Ok(mut running) = state.peaks_maintenance.lock() {
+        *running = None;
+    }
+    report
+}
+
+/// Validate every cache the project uses (rebuilding bad ones) and delete orphaned caches in
+/// `project_dir`. Reports `peaks-maintenance-progress`; stop it with `cancel_peaks_maintenance`.
+#[tauri::command]
+async fn maintain_peaks_cache(project_dir: String, app: tauri::AppHandle) -> Result<CacheReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        run_peaks_maintenance(&app, std::path::Path::new(&project_dir), MaintenanceMode::Full)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Returns false when no pass is running.
+#[tauri::command]
+fn cancel_peaks_maintenance(state: State<AppState>) -> Result<bool, String> {
+    let running = state.peaks_maintenance.lock().map_err(|_| "Failed to lock maintenance state")?;
+    if let Some(cancel) = running.as_ref() {
+        cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+    Ok(running.is_some())
+}
+
+/// Tags, cover art, codec and exact length of an audio file, without importing it.
+#[tauri::command]
+async fn probe_file_metadata(path: String) -> Result<bpm::metadata::FileMetadata, String> {
+    tauri::async_runtime::spawn_blocking(move || bpm::metadata::probe_file_metadata(&path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Files held by the shared decode cache, and how often it saved a decode.
+#[tauri::command]
+fn get_decode_cache_stats() -> bpm::cache::DecodeCacheStats {
+    bpm::cache::shared().stats()
+}
+
+/// Drop all decoded audio; the next consumer of each file decodes it again.
+#[tauri::command]
+fn clear_decode_cache() {
+    bpm::cache::shared().clear();
+}
+
+/// Stretches of `path` found damaged so far (skipped, or filled with silence to keep timing).
+#[tauri::command]
+fn get_damaged_ranges(path: String) -> Vec<daw_modules::decoder::damage::DamagedRange> {
+    daw_modules::decoder::damage::damaged_ranges(&path)
+}
+
+/// Decoded audio in memory and spilled to disk, per file, against the `decodeMemoryMb` setting.
+#[tauri::command]
+fn get_memory_usage() -> bpm::cache::MemoryUsage {
+    bpm::cache::shared().memory_usage()
+}
+
+#[derive(Clone, serde::Serialize)]
+struct IngestProgress {
+    done: usize,
+    total: usize,
+    file: String,
+}
+
+/// Copy a recorder's card dump (`path`, scanned recursively) into `options.destDir` and
+/// place it: one track per recorder channel, clips positioned by their recording start.
+/// Reports `ingest-progress` per copied file; stop it with `cancel_ingest`.
+#[tauri::command]
+async fn ingest_folder(path: String, options: IngestOptions, app: tauri::AppHandle) -> Result<IngestResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let cancel = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        {
+            let mut running = state.ingest.lock().map_err(|_| "Failed to lock ingest state")?;
+            if running.is_some() {
+                return Err("An ingest is already running".to_string());
+            }
+            *running = Some(cancel.clone());
+        }
+        let result = run_ingest(&app, &path, &options, &cancel);
+        if let Ok(mut running) = state.ingest.lock() {
+            *running = None;
+        }
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn run_ingest(
+    app: &tauri::AppHandle,
+    path: &str,
+    options: &IngestOptions,
+    cancel: &std::sync::atomic::AtomicBool,
+) -> Result<IngestResult, String> {
+    if options.dest_dir.trim().is_empty() {
+        return Err("No destination folder given".into());
+    }
+    let plan = ingest::plan_ingest(std::path::Path::new(path), options).map_err(|e| e.to_string())?;
+    let completed = ingest::copy_files(&plan, std::path::Path::new(&options.dest_dir), cancel, |done, total, file| {
+        let _ = app.emit("ingest-progress", IngestProgress { done, total, file: file.to_string() });
+    })
+    .map_err(|e| e.to_string())?;
+
+    let mut result = IngestResult {
+        files: 0,
+        tracks: Vec::new(),
+        timestamps: plan.timestamps,
+        skipped: plan.skipped.clone(),
+        cancelled: !completed,
+    };
+    if !completed {
+        return Ok(result);
+    }
+
+    let state = app.state::<AppState>();
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    for (group, files) in plan.groups() {
+        let clips: Vec<(String, f64)> = files.iter().map(|f| (f.dest.clone(), f.offset_secs)).collect();
+        let track_id = audio.add_track_with_clips(group.to_string(), &clips).map_err(|e| e.to_string())?;
+        result.files += clips.len();
+        result.tracks.push(IngestedTrack { track_id, name: group.to_string(), clips: clips.len() });
+    }
+    drop(audio);
+
+    // Waveforms build in the background, like after a recording
+    let waveforms = app.state::<WaveformService>();
+    for file in &plan.files {
+        waveforms.enqueue(file.dest.clone(), WaveformJobOptions::default());
+    }
+    Ok(result)
+}
+
+/// Returns false when no ingest is running.
+#[tauri::command]
+fn cancel_ingest(state: State<AppState>) -> Result<bool, String> {
+    let running = state.ingest.lock().map_err(|_| "Failed to lock ingest state")?;
+    if let Some(cancel) = running.as_ref() {
+        cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+    Ok(running.is_some())
+}
+
 // Add these to the invoke_handler list!
+/// Where a new take goes: the configured recordings folder, else the system temp dir.
 #[tauri::command]
-fn get_temp_path(filename: String) -> String {
-    let mut path = std::env::temp_dir();
+fn get_temp_path(filename: String, state: State<AppState>) -> String {
+    let configured = state.settings.lock().ok().and_then(|s| s.settings.recordings_dir.clone());
+    let mut path = configured
+        .map(std::path::PathBuf::from)
+        .filter(|dir| std::fs::create_dir_all(dir).is_ok())
+        .unwrap_or_else(std::env::temp_dir);
     path.push(filename);
     path.to_string_lossy().to_string()
 }
@@ -1263,17 +3222,24 @@ async fn ask_ai(
 
 // --- SHARED HELPER: Decodes audio & generates waveform data ---
 fn analyze_audio_internal(path: &str, color: String) -> Result<ImportResult, String> {
-    // 1. Decode (Heavy CPU)
-    let (samples, sr, channels) = bpm::adapter::decode_to_vec(path)
-        .map_err(|e| format!("Failed to decode: {}", e))?;
-
-    // 2. Build Waveform
-    let wf = Waveform::build_from_samples(&samples, sr, channels, 512);
+    // 1. Decode (Heavy CPU), capped and shared with every other analysis of this file
+    let pcm = bpm::cache::shared().get(path).map_err(|e| {
+        log::error!("Failed to decode {}: {}", path, e);
+        format!("Failed to decode {}: {}", path, e)
+    })?;
+    let sr = pcm.sample_rate;
+
+    // 2. Build Waveform (streamed when the decode stopped at the ceiling, so it covers the whole file)
+    let wf = if pcm.truncated {
+        Waveform::build_from_path(path, 512).map_err(|e| format!("Failed to build waveform for {}: {}", path, e))?
+    } else {
+        Waveform::build_from_samples(&pcm.samples, sr, pcm.channels, 512)
+    };
 
     // 3. Calculate Bins
     let pixels_per_second = 100.0;
     let spp = (sr as f64) / pixels_per_second;
-    let (mins, maxs, _) = wf.bins_for(spp, 0, 0, usize::MAX);
+    let (mins, maxs) = wf.bins_for(spp, WaveformChannelMode::MixMono, 0, usize::MAX).first();
 
     let actual_bps = if wf.duration_secs > 0.0 { 
         (mins.len() as f64) / wf.duration_secs 
@@ -1288,9 +3254,65 @@ fn analyze_audio_internal(path: &str, color: String) -> Result<ImportResult, Str
         bins_per_second: actual_bps,
         bpm: None, // Stems inherit project BPM, so we skip detection to be faster
         color,
+        truncated: pcm.truncated,
     })
 }
 
+// --- NEW: Background waveform rebuilds ---
+#[derive(serde::Serialize, Clone)]
+struct WaveformReadyPayload {
+    path: String,
+}
+
+// Called on the waveform worker thread once a rebuild finishes
+fn on_waveform_event(app: &tauri::AppHandle, event: WaveformEvent) {
+    match event {
+        WaveformEvent::Ready { path, waveform } => {
+            let spp = (waveform.sample_rate as f64) / 100.0;
+            let (mins, maxs) = waveform.bins_for(spp, WaveformChannelMode::MixMono, 0, usize::MAX).first();
+            let state = app.state::<AppState>();
+            if let Ok(mut cache) = state.cache.lock() {
+                // Keep BPM/color from the previous analysis of this file
+                let (bpm, color, truncated) = cache
+                    .get(&path)
+                    .map(|r| (r.bpm, r.color.clone(), r.truncated))
+                    .unwrap_or((None, String::new(), false));
+                cache.insert(path.clone(), ImportResult {
+                    mins: mins.to_vec(),
+                    maxs: maxs.to_vec(),
+                    duration: waveform.duration_secs,
+                    bins_per_second: if waveform.duration_secs > 0.0 { (mins.len() as f64) / waveform.duration_secs } else { 0.0 },
+                    bpm,
+                    color,
+                    truncated,
+                });
+            }
+            let _ = app.emit("waveform-ready", WaveformReadyPayload { path });
+        }
+        WaveformEvent::Failed { path, error } => {
+            log::warn!("Waveform rebuild failed for {}: {}", path, error);
+        }
+    }
+}
+
+/// Queue a waveform rebuild (e.g. after a destructive edit). Listen for `waveform-ready`.
+#[tauri::command]
+fn rebuild_waveform(path: String, waveforms: State<WaveformService>) -> Result<bool, String> {
+    Ok(waveforms.enqueue(path, WaveformJobOptions { force: true, ..Default::default() }))
+}
+
+/// Drop a queued rebuild. False if it wasn't queued (or is already being built).
+#[tauri::command]
+fn cancel_waveform_rebuild(path: String, waveforms: State<WaveformService>) -> Result<bool, String> {
+    Ok(waveforms.cancel(&path))
+}
+
+/// Paths waiting for a rebuild, for a progress list.
+#[tauri::command]
+fn get_pending_waveforms(waveforms: State<WaveformService>) -> Result<Vec<String>, String> {
+    Ok(waveforms.pending_jobs())
+}
+
 // IMPORTANT: Make sure this import is at the top of your main.rs file
 // use tauri::Manager; 
 
@@ -1460,59 +3482,316 @@ fn sanitize_ai_batch(mut steps: Vec<AiStep>) -> Result<Vec<AiStep>, String> {
 fn main() {
 
     dotenv().ok();
+
+    // Forward daw_modules' realtime log queue into tauri_plugin_log
+    daw_modules::rt_log::set_sink(|level, msg| {
+        use daw_modules::rt_log::Level;
+        match level {
+            Level::Error => log::error!(target: "daw", "{}", msg),
+            Level::Warn => log::warn!(target: "daw", "{}", msg),
+            Level::Info => log::info!(target: "daw", "{}", msg),
+            Level::Debug => log::debug!(target: "daw", "{}", msg),
+        }
+    });
+
     let runtime = AudioRuntime::new(None).expect("Failed to init Audio Engine");
 
     let master_meter = runtime.master_meter.clone();
+    let limiter_meter = runtime.limiter_meter.clone();
+    let clip_detect = runtime.clip_detect.clone();
+    let transport_clock = runtime.transport_clock.clone();
     let meter_registry = runtime.meter_registry.clone();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_log::Builder::default().build())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(global_shortcuts::on_shortcut)
+                .build(),
+        )
         .manage(AppState {
             audio: Mutex::new(runtime),
             recorder: Mutex::new(None),
+            input_meter: Mutex::new(None),
+            retro: Mutex::new(None),
             cache: Mutex::new(HashMap::new()),
             pending_stems: Mutex::new(HashMap::new()),
             master_meter,
+            limiter_meter,
+            clip_detect,
+            transport_clock,
             meter_registry,
+            input_gains: Mutex::new(input_settings::InputGainStore::default()),
+            record_latency: Mutex::new(input_settings::RecordLatencyStore::default()),
+            input_selection: Mutex::new(input_settings::InputSelectionStore::default()),
+            monitor_blend: Mutex::new(input_settings::MonitorBlendStore::default()),
+            recording_dirs: Mutex::new(take_recovery::RecordingDirsStore::default()),
+            output_routing: Mutex::new(output_settings::OutputRoutingStore::default()),
+            shortcuts: Mutex::new(global_shortcuts::ShortcutStore::default()),
+            settings: Mutex::new(settings::SettingsStore::default()),
+            peaks_maintenance: Mutex::new(None),
+            ingest: Mutex::new(None),
+        })
+        .setup(|app| {
+            // Clips dropped in as https links are cached here
+            if let Ok(dir) = app.path().app_cache_dir() {
+                daw_modules::decoder::remote::set_cache_dir(dir.join("remote-media"));
+                bpm::cache::set_spill_dir(dir.join("decode-spill"));
+            }
+            // Damaged stretches found while decoding, for the clip views to mark
+            let damage_app = app.handle().clone();
+            daw_modules::decoder::damage::set_listener(move |path, range| {
+                let _ = damage_app.emit("audio-damaged", serde_json::json!({ "path": path, "range": range }));
+            });
+            // Load persisted per-device settings once the config dir is known
+            if let Ok(dir) = app.path().app_config_dir() {
+                let state = app.state::<AppState>();
+                if let Ok(mut store) = state.input_gains.lock() {
+                    *store = input_settings::InputGainStore::load(dir.clone());
+                }
+                if let Ok(mut store) = state.record_latency.lock() {
+                    *store = input_settings::RecordLatencyStore::load(dir.clone());
+                }
+                if let Ok(mut store) = state.input_selection.lock() {
+                    *store = input_settings::InputSelectionStore::load(dir.clone());
+                }
+                let blend_store = input_settings::MonitorBlendStore::load(dir.clone());
+                if let Ok(audio) = state.audio.lock() {
+                    blend_store.blend.apply(&audio);
+                }
+                if let Ok(mut store) = state.monitor_blend.lock() {
+                    *store = blend_store;
+                }
+                if let Ok(mut store) = state.recording_dirs.lock() {
+                    *store = take_recovery::RecordingDirsStore::load(dir.clone());
+                }
+                let routing_store = output_settings::OutputRoutingStore::load(dir.clone());
+                if let Ok(audio) = state.audio.lock() {
+                    output_settings::apply_saved_routing(&audio, &routing_store);
+                }
+                if let Ok(mut store) = state.output_routing.lock() {
+                    *store = routing_store;
+                }
+                let settings_store = settings::SettingsStore::load(dir.clone());
+                if let Ok(audio) = state.audio.lock() {
+                    settings_store.settings.apply(&audio);
+                }
+                if let Ok(mut store) = state.settings.lock() {
+                    *store = settings_store;
+                }
+                if let Ok(mut store) = state.shortcuts.lock() {
+                    *store = global_shortcuts::ShortcutStore::load(dir);
+                    let failures = global_shortcuts::register_all(app.handle(), &mut store);
+                    if !failures.is_empty() {
+                        let _ = app.emit("global-shortcut-conflicts", &failures);
+                    }
+                }
+            }
+            take_recovery::scan_on_startup(app.handle().clone());
+            let clock = app.state::<AppState>().transport_clock.clone();
+            spawn_transport_sync(app.handle().clone(), clock);
+            spawn_engine_watchdog(app.handle().clone());
+            spawn_meter_stream(app.handle().clone());
+
+            let handle = app.handle().clone();
+            app.manage(WaveformService::new(move |event| on_waveform_event(&handle, event)));
+
+            // Push every edit-history entry to the history panel as it happens
+            let handle = app.handle().clone();
+            if let Ok(audio) = app.state::<AppState>().audio.lock() {
+                audio.set_history_listener(move |entry| {
+                    let _ = handle.emit("edit-logged", entry.clone());
+                });
+            }
+            Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            recover_engine,
+            get_audio_dropouts,
+            get_audio_settings,
+            set_audio_settings,
+            reset_audio_settings,
             play,
+            play_with_count_in,
             pause,
+            stop,
+            set_return_to_start_on_stop,
+            get_return_to_start_on_stop,
+            set_end_action,
+            get_end_action,
+            set_preroll,
+            get_preroll,
+            panic,
+            set_monitor_muted,
             import_tracks,
+            import_track,
             analyze_file,
             create_track,
             get_position,
+            get_time_sync,
             start_recording,
+            start_armed_recording,
+            set_track_record_inputs,
+            get_track_record_inputs,
+            set_track_record_device,
+            get_track_record_device,
             toggle_monitor_cmd,
+            pause_recording,
+            resume_recording,
+            start_loopback_recording,
+            stop_loopback_recording,
             stop_recording,
+            set_retro_record,
+            capture_last,
+            commit_recorded_take,
+            rebuild_waveform,
+            cancel_waveform_rebuild,
+            get_pending_waveforms,
+            maintain_peaks_cache,
+            cancel_peaks_maintenance,
+            probe_file_metadata,
+            get_decode_cache_stats,
+            clear_decode_cache,
+            get_memory_usage,
+            get_damaged_ranges,
+            ingest_folder,
+            cancel_ingest,
             get_recording_status,
             set_bpm,
+            get_playback_rate,
+            set_playback_rate,
             set_time_signature,
+            set_meter_change,
+            remove_meter_change,
+            get_meter_changes,
             get_grid_lines,
+            get_snap_resolution,
+            set_snap_resolution,
             move_clip,
             seek,
+            arm_track,
+            set_record_safe,
+            set_track_listen,
+            preview_clip_at,
+            stop_preview,
+            set_arm_exclusive,
+            get_markers,
+            add_marker,
+            move_marker,
+            rename_marker,
+            set_marker_color,
+            delete_marker,
+            seek_to_marker,
+            seek_next_marker,
+            seek_previous_marker,
+            seek_next_edit_point,
+            seek_previous_edit_point,
             set_track_gain,
             set_track_pan,
             toggle_mute,
             toggle_solo,
+            set_solo_policy,
+            get_solo_policy,
+            set_solo_mode,
+            get_solo_mode,
+            set_solo_safe,
             set_master_gain,
+            get_master_pitch_shift,
+            set_master_pitch_shift,
+            get_master_soft_clip,
+            set_master_soft_clip,
+            get_master_limiter,
+            set_master_limiter,
+            get_groups,
+            create_group,
+            delete_group,
+            rename_group,
+            set_group_gain,
+            set_group_mute,
+            set_group_solo,
+            assign_track_group,
+            freeze_track,
+            unfreeze_track,
+            get_aux_buses,
+            add_aux_bus,
+            remove_aux_bus,
+            rename_aux_bus,
+            set_aux_bus_gain,
+            set_aux_bus_muted,
+            set_aux_bus_effects,
+            get_track_inserts,
+            add_track_insert,
+            remove_track_insert,
+            move_track_insert,
+            set_track_insert_bypass,
+            set_track_insert_mix,
+            get_track_automation_mode,
+            set_track_automation_mode,
+            clear_track_insert_automation,
+            set_track_insert_params,
+            set_track_send,
             get_master_gain,
             get_master_meter,
             save_project,
+            get_project_stats,
+            list_backups,
+            restore_backup,
+            set_backup_count,
+            set_metronome_config,
+            get_metronome_config,
+            set_metronome_enabled,
+            set_metronome_volume,
+            set_count_in_bars,
             load_project,
             export_project,
+            start_realtime_bounce,
+            stop_realtime_bounce,
+            get_realtime_bounce_status,
+            start_multitrack_capture,
+            stop_multitrack_capture,
+            get_multitrack_capture_status,
             get_temp_path,
             add_clip,
             get_all_meters,
+            get_loudness,
+            reset_loudness,
+            get_correlation,
+            get_clip_status,
+            clear_clip_indicator,
+            attach_spectrum,
+            detach_spectrum,
+            get_spectrum,
             get_track_analysis,
             split_clip,
+            trim_clip_start,
+            trim_clip_end,
+            conform_clip_to_tempo,
+            set_clip_stretch,
+            set_clip_varispeed,
             get_project_state,
             merge_clip_with_next,
             delete_track,
+            reorder_tracks,
             delete_clip,
+            set_track_notes,
+            set_clip_notes,
+            add_clip_mute_region,
+            reset_mixer,
+            set_track_fx_bypass,
+            set_track_kind,
+            remove_clip_mute_region,
+            list_clip_mute_regions,
+            set_clip_crossfade,
+            list_annotated,
+            find_gaps,
+            global_shortcuts::set_global_shortcuts,
+            global_shortcuts::get_global_shortcuts,
+            settings::get_settings,
+            settings::update_settings,
             update_eq,
             get_eq_state,
+            get_eq_response,
             update_compressor,
             get_compressor_state,
             effects::set_effect_param, 
@@ -1523,6 +3802,13 @@ fn main() {
             set_output_device,
             undo,
             redo,
+            get_edit_history,
+            take_recovery::get_recovered_takes,
+            take_recovery::repair_recording,
+            output_settings::set_bus_output_channels,
+            output_settings::clear_bus_output,
+            output_settings::get_output_routing,
+            output_settings::apply_routing_fix,
             ask_ai,
             ai_transaction::execute_ai_transaction,
             stem_separation::separate_stems,
@@ -1532,7 +3818,20 @@ fn main() {
             sanitize_ai_batch,
             automation::get_volume_automation,
             automation::add_volume_automation_node,
-            automation::remove_volume_automation_node
+            automation::remove_volume_automation_node,
+            input_settings::set_input_gain_db,
+            input_settings::get_input_gain_db,
+            input_settings::clear_input_clip,
+            input_settings::start_input_meter,
+            input_settings::stop_input_meter,
+            input_settings::get_input_levels,
+            input_settings::calibrate_record_latency,
+            input_settings::get_record_latency_ms,
+            input_settings::set_record_latency_ms,
+            input_settings::set_input_selection,
+            input_settings::get_input_selection,
+            input_settings::set_monitor_blend,
+            input_settings::get_monitor_blend
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
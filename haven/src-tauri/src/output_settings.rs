@@ -0,0 +1,128 @@
+// src-tauri/src/output_settings.rs
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, State};
+use daw_modules::audio_runtime::{AudioRuntime, OutputRoutingSnapshot};
+use daw_modules::engine::output_routing::{BusRoute, OutputBus};
+use daw_modules::routing_check::{self, RoutingFix, RoutingSetup};
+
+use crate::AppState;
+
+/// Per-output-device bus routing, persisted as `output_routing.json` in the app config dir.
+#[derive(Default)]
+pub struct OutputRoutingStore {
+    path: Option<PathBuf>,
+    routes: HashMap<String, Vec<BusRoute>>,
+}
+
+impl OutputRoutingStore {
+    pub fn load(dir: PathBuf) -> Self {
+        let path = dir.join("output_routing.json");
+        let routes = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path: Some(path), routes }
+    }
+
+    pub fn get(&self, device: &str) -> Option<&[BusRoute]> {
+        self.routes.get(device).map(Vec::as_slice)
+    }
+
+    pub fn set(&mut self, device: String, routes: Vec<BusRoute>) -> Result<(), String> {
+        self.routes.insert(device, routes);
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(&self.routes).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+/// Re-apply the saved routing for whatever device the runtime is now using.
+pub fn apply_saved_routing(audio: &AudioRuntime, store: &OutputRoutingStore) {
+    let snapshot = audio.output_routing();
+    let Some(device) = snapshot.device else { return };
+    if let Some(routes) = store.get(&device) {
+        for e in audio.restore_output_routing(routes) {
+            log::warn!("Saved output route for {} skipped: {}", device, e);
+        }
+    }
+}
+
+fn persist(audio: &AudioRuntime, state: &State<AppState>) -> Result<OutputRoutingSnapshot, String> {
+    let snapshot = audio.output_routing();
+    if let Some(device) = snapshot.device.clone() {
+        let mut store = state.output_routing.lock().map_err(|_| "Failed to lock output settings")?;
+        store.set(device, snapshot.routes.clone())?;
+    }
+    Ok(snapshot)
+}
+
+/// The input being monitored right now (the recorder's device), if any.
+/// Take this before locking the audio: `start_recording` locks recorder, then audio.
+pub fn monitored_input(state: &State<AppState>) -> Option<String> {
+    let rec_guard = state.recorder.lock().ok()?;
+    rec_guard.as_ref().map(|rec| rec.input_device_name().to_string())
+}
+
+/// Run the feedback check and emit a `routing-warning` per problem found.
+/// Call after anything that changes the routing or the monitor path.
+pub fn warn_on_feedback(app: &AppHandle, audio: &AudioRuntime, monitored_input: Option<String>, monitor_muted: bool) {
+    let snapshot = audio.output_routing();
+    let setup = RoutingSetup {
+        monitoring: monitored_input.is_some(),
+        input_device: monitored_input,
+        monitor_muted,
+        output_device: snapshot.device,
+        routes: snapshot.routes,
+    };
+    for warning in routing_check::check_routing(&setup) {
+        log::warn!("Routing: {}", warning.message);
+        let _ = app.emit("routing-warning", &warning);
+    }
+}
+
+/// Route a bus to two (0-based) device channels, e.g. the click to outputs 3/4 -> (2, 3).
+#[tauri::command]
+pub fn set_bus_output_channels(
+    bus: OutputBus,
+    l_ch: usize,
+    r_ch: usize,
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<OutputRoutingSnapshot, String> {
+    let input = monitored_input(&state);
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.set_bus_output_channels(bus, l_ch, r_ch).map_err(|e| e.to_string())?;
+    warn_on_feedback(&app, &audio, input, audio.is_monitor_muted());
+    persist(&audio, &state)
+}
+
+#[tauri::command]
+pub fn clear_bus_output(bus: OutputBus, app: AppHandle, state: State<AppState>) -> Result<OutputRoutingSnapshot, String> {
+    let input = monitored_input(&state);
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    audio.clear_bus_output(bus);
+    warn_on_feedback(&app, &audio, input, audio.is_monitor_muted());
+    persist(&audio, &state)
+}
+
+/// Break a path reported by a `routing-warning` (one of its `fixes`).
+#[tauri::command]
+pub fn apply_routing_fix(fix: RoutingFix, state: State<AppState>) -> Result<OutputRoutingSnapshot, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    match fix {
+        RoutingFix::MuteMonitor => audio.set_monitor_muted(true),
+        RoutingFix::UnrouteBus { bus } => audio.clear_bus_output(bus),
+    }
+    persist(&audio, &state)
+}
+
+#[tauri::command]
+pub fn get_output_routing(state: State<AppState>) -> Result<OutputRoutingSnapshot, String> {
+    let audio = state.audio.lock().map_err(|_| "Failed to lock audio")?;
+    Ok(audio.output_routing())
+}
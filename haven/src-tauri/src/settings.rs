@@ -0,0 +1,184 @@
+// src-tauri/src/settings.rs
+
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, State};
+use daw_modules::audio_runtime::AudioRuntime;
+use daw_modules::session::bounce::BounceFormat;
+use daw_modules::session::dither::Dither;
+use daw_modules::engine::metronome::MetronomeConfig;
+use daw_modules::bpm::cache;
+
+use crate::AppState;
+
+const MB: usize = 1024 * 1024;
+/// Ceiling for `decode_memory_mb` (64 GB).
+const MAX_DECODE_MEMORY_MB: usize = 64 * 1024;
+
+/// App-wide preferences (not per project). Missing fields fall back to their defaults,
+/// so older files keep loading as fields are added.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    /// Where new takes go; None = the system temp dir.
+    pub recordings_dir: Option<String>,
+    /// Rotating `project.json.N` backups kept on save.
+    pub backup_count: usize,
+    /// Defaults for exports that don't pass their own options.
+    pub export_format: BounceFormat,
+    pub export_dither: Dither,
+    /// Length of a clip hover preview.
+    pub preview_secs: f64,
+    pub metronome: MetronomeConfig,
+    /// Decoded audio kept in memory before the least recently used files spill to disk.
+    pub decode_memory_mb: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            recordings_dir: None,
+            backup_count: daw_modules::session::serialization::DEFAULT_BACKUP_COUNT,
+            export_format: BounceFormat::Wav16,
+            export_dither: Dither::Tpdf,
+            preview_secs: 2.0,
+            metronome: MetronomeConfig::default(),
+            decode_memory_mb: cache::DEFAULT_CACHE_BUDGET_BYTES / MB,
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(dir) = &self.recordings_dir {
+            if dir.trim().is_empty() || !PathBuf::from(dir).is_absolute() {
+                return Err(format!("recordingsDir must be an absolute path, got {:?}", dir));
+            }
+        }
+        if self.backup_count > 50 {
+            return Err(format!("backupCount must be at most 50, got {}", self.backup_count));
+        }
+        if !(0.1..=30.0).contains(&self.preview_secs) {
+            return Err(format!("previewSecs must be between 0.1 and 30, got {}", self.preview_secs));
+        }
+        if self.metronome.sanitized() != self.metronome {
+            return Err("metronome volume and gains must be between -60 and 6 dB, countInBars at most 4".into());
+        }
+        let min_mb = cache::MIN_CACHE_BUDGET_BYTES / MB;
+        if !(min_mb..=MAX_DECODE_MEMORY_MB).contains(&self.decode_memory_mb) {
+            return Err(format!(
+                "decodeMemoryMb must be between {} and {}, got {}",
+                min_mb, MAX_DECODE_MEMORY_MB, self.decode_memory_mb
+            ));
+        }
+        Ok(())
+    }
+
+    /// Push the settings long-lived subsystems hold on to.
+    pub fn apply(&self, audio: &AudioRuntime) {
+        audio.set_backup_count(self.backup_count);
+        cache::shared().set_budget_bytes(self.decode_memory_mb * MB);
+        if let Err(e) = audio.set_metronome_config(self.metronome) {
+            log::warn!("Could not apply the metronome settings: {}", e);
+        }
+    }
+}
+
+/// `settings.json` in the app config dir.
+#[derive(Default)]
+pub struct SettingsStore {
+    path: Option<PathBuf>,
+    pub settings: Settings,
+}
+
+impl SettingsStore {
+    /// Never fails: an unreadable or invalid file is moved aside and replaced with defaults.
+    pub fn load(dir: PathBuf) -> Self {
+        let path = dir.join("settings.json");
+        let mut store = Self { path: Some(path.clone()), settings: Settings::default() };
+        let Ok(text) = std::fs::read_to_string(&path) else { return store };
+
+        let parsed = serde_json::from_str::<Settings>(&text)
+            .map_err(|e| e.to_string())
+            .and_then(|s| s.validate().map(|_| s));
+        match parsed {
+            Ok(settings) => store.settings = settings,
+            Err(e) => {
+                let stamp = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                let backup = dir.join(format!("settings.json.corrupt-{}", stamp));
+                log::warn!("settings.json is invalid ({}); moved to {} and reset to defaults", e, backup.display());
+                let _ = std::fs::rename(&path, &backup);
+                if let Err(e) = store.save() {
+                    log::warn!("Could not write default settings: {}", e);
+                }
+            }
+        }
+        store
+    }
+
+    fn save(&self) -> Result<(), String> {
+        self.write(&self.settings)
+    }
+
+    fn write(&self, settings: &Settings) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    /// Apply a partial update (`{ "backupCount": 3 }`). Returns the changed fields with their new values.
+    pub fn update(&mut self, patch: Value) -> Result<Map<String, Value>, String> {
+        let Value::Object(patch) = patch else { return Err("Settings patch must be an object".into()) };
+        let Value::Object(old) = serde_json::to_value(&self.settings).map_err(|e| e.to_string())? else {
+            return Err("Settings are not an object".into());
+        };
+
+        let mut merged = old.clone();
+        for (key, value) in patch {
+            if !merged.contains_key(&key) {
+                return Err(format!("Unknown setting: {}", key));
+            }
+            merged.insert(key, value);
+        }
+        let updated: Settings = serde_json::from_value(Value::Object(merged.clone())).map_err(|e| e.to_string())?;
+        updated.validate()?;
+
+        let diff: Map<String, Value> = merged.into_iter().filter(|(k, v)| old.get(k) != Some(v)).collect();
+        // Only a saved change takes effect, so memory and disk never disagree
+        if !diff.is_empty() {
+            self.write(&updated)?;
+            self.settings = updated;
+        }
+        Ok(diff)
+    }
+}
+
+#[tauri::command]
+pub fn get_settings(state: State<AppState>) -> Result<Settings, String> {
+    let store = state.settings.lock().map_err(|_| "Failed to lock settings")?;
+    Ok(store.settings.clone())
+}
+
+/// Partial update; emits `settings-changed` with only the fields that changed.
+#[tauri::command]
+pub fn update_settings(patch: Value, app: AppHandle, state: State<AppState>) -> Result<Settings, String> {
+    let mut store = state.settings.lock().map_err(|_| "Failed to lock settings")?;
+    let diff = store.update(patch)?;
+    let settings = store.settings.clone();
+    drop(store);
+
+    if !diff.is_empty() {
+        if let Ok(audio) = state.audio.lock() {
+            settings.apply(&audio);
+        }
+        let _ = app.emit("settings-changed", &diff);
+    }
+    Ok(settings)
+}
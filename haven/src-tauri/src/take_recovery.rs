@@ -0,0 +1,93 @@
+// src-tauri/src/take_recovery.rs
+
+use std::path::{Path, PathBuf};
+use tauri::{Emitter, Manager, State};
+use daw_modules::recorder::recovery::{self, RecoveredTake, RepairedInfo};
+
+use crate::AppState;
+
+/// How many recording folders are remembered for the startup scan.
+const MAX_RECORDING_DIRS: usize = 16;
+
+/// Folders takes were recorded into, persisted as `recording_dirs.json`, plus whatever
+/// the last startup scan recovered (until the UI has dealt with it).
+#[derive(Default)]
+pub struct RecordingDirsStore {
+    path: Option<PathBuf>,
+    dirs: Vec<PathBuf>,
+    pub recovered: Vec<RecoveredTake>,
+}
+
+impl RecordingDirsStore {
+    pub fn load(dir: PathBuf) -> Self {
+        let path = dir.join("recording_dirs.json");
+        let dirs = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path: Some(path), dirs, recovered: Vec::new() }
+    }
+
+    pub fn dirs(&self) -> Vec<PathBuf> {
+        self.dirs.clone()
+    }
+
+    /// Remember the folder of a take that is about to be recorded (most recent first).
+    pub fn remember(&mut self, take_path: &Path) -> Result<(), String> {
+        let Some(dir) = take_path.parent().map(Path::to_path_buf) else { return Ok(()) };
+        if self.dirs.first() == Some(&dir) {
+            return Ok(());
+        }
+        self.dirs.retain(|d| d != &dir);
+        self.dirs.insert(0, dir);
+        self.dirs.truncate(MAX_RECORDING_DIRS);
+
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(&self.dirs).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+/// Repair takes left behind by a crash, off the main thread, then tell the UI.
+pub fn scan_on_startup(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let state = app.state::<AppState>();
+        let dirs = match state.recording_dirs.lock() {
+            Ok(store) => store.dirs(),
+            Err(_) => return,
+        };
+
+        let recovered: Vec<RecoveredTake> = dirs.iter().flat_map(|d| recovery::recover_unfinished(d)).collect();
+        if recovered.is_empty() {
+            return;
+        }
+        log::info!("Recovered {} unfinished take(s)", recovered.len());
+
+        if let Ok(mut store) = state.recording_dirs.lock() {
+            store.recovered.extend(recovered.iter().cloned());
+        }
+        let _ = app.emit("takes-recovered", &recovered);
+    });
+}
+
+/// Takes recovered at startup. `clear` drops them once the UI has offered them for import.
+#[tauri::command]
+pub fn get_recovered_takes(clear: Option<bool>, state: State<AppState>) -> Result<Vec<RecoveredTake>, String> {
+    let mut store = state.recording_dirs.lock().map_err(|_| "Failed to lock recording dirs")?;
+    let takes = store.recovered.clone();
+    if clear.unwrap_or(false) {
+        store.recovered.clear();
+    }
+    Ok(takes)
+}
+
+/// Manually repair a WAV whose header was never finalized.
+#[tauri::command]
+pub fn repair_recording(path: String) -> Result<RepairedInfo, String> {
+    let info = recovery::repair_wav(Path::new(&path)).map_err(|e| e.to_string())?;
+    recovery::remove_marker(Path::new(&path));
+    Ok(info)
+}
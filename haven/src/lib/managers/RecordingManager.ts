@@ -58,37 +58,28 @@ export class RecordingManager {
         this.stopPolling();
 
         try {
-            // 1. Stop the file writer (Flushes to disk)
-            await invoke('stop_recording');
+            // 1. Stop the file writer (Flushes to disk). The backend places the take on a
+            // new track at the playhead recording started from and returns that track.
+            const stopped = await invoke<{ takes: unknown[], newTracks: unknown[], errors: string[] }>('stop_recording');
+            // Takes that couldn't be placed are still on disk; the rest were placed
+            for (const error of stopped.errors) {
+                console.warn("⚠️ Take not placed:", error);
+            }
             
             // Wait for Windows to release the lock (Increased to 200ms for safety)
             await sleep(200);
 
-            // --- STEP 2: ANALYZE FIRST (Swapped Order) ---
-            // We run analysis first. If this works, we PROVE the file is valid and readable.
+            // --- STEP 2: ANALYZE ---
+            // If this works, we PROVE the file is valid and readable.
             console.log("Analyzing waveform...");
-            const result = await invoke<{
+            const analysis = await invoke<{
                 mins: number[], 
                 maxs: number[], 
                 duration: number 
             }>('analyze_file', { path: this.currentFilePath });
+            const result = { ...analysis, newTracks: stopped.newTracks };
             // ----------------------------------------------
 
-            // --- STEP 3: ADD TO ENGINE ---
-            // Now that we know the file is good, we add it to the audio engine.
-            if (this.trackId !== null) {
-                console.log("Registering clip with engine...");
-                try {
-                    await invoke('add_clip', { 
-                        trackId: this.trackId, 
-                        path: this.currentFilePath, 
-                        startTime: this.startTime 
-                    });
-                } catch (err) {
-                    console.error("⚠️ Failed to add clip to engine:", err);
-                }
-            }
-
             console.log("✅ Recording Finalized:", result);
             return result;
 